@@ -0,0 +1,98 @@
+use egui::accesskit::{Live, Role};
+use std::process::{Command, Stdio};
+
+/// Kind of event, which picks the sound played for it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cue {
+    Success,
+    Problem,
+}
+
+/// Latest announcement, kept in a polite live region so screen readers speak
+/// it when it changes without moving focus
+#[derive(Default)]
+pub struct Announcer {
+    message: String,
+    /// Flipped with every announcement so the same message twice in a row
+    /// still changes the region and is spoken again
+    repeat: bool,
+}
+
+impl Announcer {
+    pub fn announce(&mut self, message: impl Into<String>) {
+        self.message = message.into();
+        self.repeat = !self.repeat;
+    }
+
+    /// Add the live region to this frame's accessibility tree. Does nothing
+    /// unless a screen reader is connected.
+    pub fn show(&self, ctx: &egui::Context) {
+        if self.message.is_empty() {
+            return;
+        }
+        let mut name = self.message.clone();
+        if self.repeat {
+            name.push('\u{a0}');
+        }
+        ctx.accesskit_node_builder(egui::Id::new("announcements"), |node| {
+            node.set_role(Role::Status);
+            node.set_live(Live::Polite);
+            node.set_name(name);
+        });
+    }
+}
+
+/// Play the system sound for `cue` without waiting for it. Missing players
+/// are ignored.
+pub fn play(cue: Cue) {
+    let _ = std::thread::Builder::new()
+        .name("sound cue".to_string())
+        .spawn(move || {
+            for mut command in sound_commands(cue) {
+                let played = command
+                    .stdin(Stdio::null())
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .status()
+                    .is_ok_and(|status| status.success());
+                if played {
+                    break;
+                }
+            }
+        });
+}
+
+/// Commands that play the sound for `cue`, to try in order
+fn sound_commands(cue: Cue) -> Vec<Command> {
+    if cfg!(target_os = "windows") {
+        let sound = match cue {
+            Cue::Success => "Asterisk",
+            Cue::Problem => "Exclamation",
+        };
+        // SystemSounds play asynchronously, so PowerShell has to wait for them
+        let script =
+            format!("[System.Media.SystemSounds]::{sound}.Play(); Start-Sleep -Milliseconds 600");
+        let mut command = Command::new("powershell");
+        command.args(["-NoProfile", "-Command", &script]);
+        vec![command]
+    } else if cfg!(target_os = "macos") {
+        let sound = match cue {
+            Cue::Success => "Glass",
+            Cue::Problem => "Basso",
+        };
+        let mut command = Command::new("afplay");
+        command.arg(format!("/System/Library/Sounds/{sound}.aiff"));
+        vec![command]
+    } else {
+        // Freedesktop sound theme names, through libcanberra or PulseAudio
+        let sound = match cue {
+            Cue::Success => "complete",
+            Cue::Problem => "dialog-warning",
+        };
+        let mut canberra = Command::new("canberra-gtk-play");
+        canberra.args(["--id", sound]);
+        let mut paplay = Command::new("paplay");
+        paplay.arg(format!("/usr/share/sounds/freedesktop/stereo/{sound}.oga"));
+        vec![canberra, paplay]
+    }
+}
@@ -0,0 +1,174 @@
+use egui::Color32;
+
+/// The 16 basic terminal colors (normal, then bright), as VSCode's terminal draws them
+const BASIC_COLORS: [Color32; 16] = [
+    Color32::from_rgb(0x00, 0x00, 0x00),
+    Color32::from_rgb(0xcd, 0x31, 0x31),
+    Color32::from_rgb(0x0d, 0xbc, 0x79),
+    Color32::from_rgb(0xe5, 0xe5, 0x10),
+    Color32::from_rgb(0x24, 0x72, 0xc8),
+    Color32::from_rgb(0xbc, 0x3f, 0xbc),
+    Color32::from_rgb(0x11, 0xa8, 0xcd),
+    Color32::from_rgb(0xe5, 0xe5, 0xe5),
+    Color32::from_rgb(0x66, 0x66, 0x66),
+    Color32::from_rgb(0xf1, 0x4c, 0x4c),
+    Color32::from_rgb(0x23, 0xd1, 0x8b),
+    Color32::from_rgb(0xf5, 0xf5, 0x43),
+    Color32::from_rgb(0x3b, 0x8e, 0xea),
+    Color32::from_rgb(0xd6, 0x70, 0xd6),
+    Color32::from_rgb(0x29, 0xb8, 0xdb),
+    Color32::from_rgb(0xe5, 0xe5, 0xe5),
+];
+
+/// Text attributes set by SGR escape sequences
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AnsiStyle {
+    /// None for the default text color
+    pub foreground: Option<Color32>,
+    pub background: Option<Color32>,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    /// Target of an OSC 8 hyperlink around the text
+    pub link: Option<String>,
+}
+
+/// Piece of a line with one style
+#[derive(Debug, Clone, PartialEq)]
+pub struct StyledRun {
+    pub text: String,
+    pub style: AnsiStyle,
+}
+
+/// Split a line of terminal output into styled runs, dropping escape
+/// sequences that don't affect how text looks
+pub fn parse(line: &str) -> Vec<StyledRun> {
+    let mut runs: Vec<StyledRun> = Vec::new();
+    let mut style = AnsiStyle::default();
+    let mut text = String::new();
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            text.push(c);
+            continue;
+        }
+        let before = style.clone();
+        match chars.next() {
+            // CSI: parameters, then a final byte in @..~
+            Some('[') => {
+                let mut params = String::new();
+                let mut command = None;
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        command = Some(c);
+                        break;
+                    }
+                    params.push(c);
+                }
+                if command == Some('m') {
+                    apply_sgr(&mut style, &params);
+                }
+            }
+            // OSC: up to BEL or ESC \
+            Some(']') => {
+                let mut content = String::new();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\x07' => break,
+                        '\x1b' if chars.peek() == Some(&'\\') => {
+                            chars.next();
+                            break;
+                        }
+                        c => content.push(c),
+                    }
+                }
+                // OSC 8 ; params ; URI starts a hyperlink, an empty URI ends it
+                if let Some(rest) = content.strip_prefix("8;") {
+                    let uri = rest.split_once(';').map_or("", |(_, uri)| uri);
+                    style.link = (!uri.is_empty()).then(|| uri.to_string());
+                }
+            }
+            _ => {}
+        }
+        if style != before && !text.is_empty() {
+            runs.push(StyledRun {
+                text: std::mem::take(&mut text),
+                style: before,
+            });
+        }
+    }
+    if !text.is_empty() || runs.is_empty() {
+        runs.push(StyledRun { text, style });
+    }
+    runs
+}
+
+/// Apply the `;`-separated parameters of an SGR (`ESC [ ... m`) sequence
+fn apply_sgr(style: &mut AnsiStyle, params: &str) {
+    // Empty parameters (`ESC [ m`) count as a reset
+    let mut codes = params
+        .split([';', ':'])
+        .map(|code| code.parse::<u16>().unwrap_or(0));
+
+    while let Some(code) = codes.next() {
+        match code {
+            0 => {
+                *style = AnsiStyle {
+                    link: style.link.take(),
+                    ..AnsiStyle::default()
+                }
+            }
+            1 => style.bold = true,
+            3 => style.italic = true,
+            4 => style.underline = true,
+            22 => style.bold = false,
+            23 => style.italic = false,
+            24 => style.underline = false,
+            30..=37 => style.foreground = Some(BASIC_COLORS[(code - 30) as usize]),
+            90..=97 => style.foreground = Some(BASIC_COLORS[(code - 90 + 8) as usize]),
+            40..=47 => style.background = Some(BASIC_COLORS[(code - 40) as usize]),
+            100..=107 => style.background = Some(BASIC_COLORS[(code - 100 + 8) as usize]),
+            39 => style.foreground = None,
+            49 => style.background = None,
+            38 | 48 => {
+                let color = extended_color(&mut codes);
+                if code == 38 {
+                    style.foreground = color;
+                } else {
+                    style.background = color;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Color after `38`/`48`: `5;n` from the 256-color palette or `2;r;g;b`
+fn extended_color(codes: &mut impl Iterator<Item = u16>) -> Option<Color32> {
+    match codes.next()? {
+        5 => Some(palette_256(codes.next()?.min(255) as u8)),
+        2 => {
+            let mut channel = || codes.next().map(|c| c.min(255) as u8);
+            Some(Color32::from_rgb(channel()?, channel()?, channel()?))
+        }
+        _ => None,
+    }
+}
+
+fn palette_256(index: u8) -> Color32 {
+    match index {
+        0..=15 => BASIC_COLORS[index as usize],
+        // 6x6x6 color cube
+        16..=231 => {
+            let level = |n: u8| if n == 0 { 0 } else { 55 + n * 40 };
+            let n = index - 16;
+            Color32::from_rgb(level(n / 36), level(n / 6 % 6), level(n % 6))
+        }
+        // Grayscale ramp
+        _ => {
+            let gray = 8 + (index - 232) * 10;
+            Color32::from_rgb(gray, gray, gray)
+        }
+    }
+}
@@ -1,31 +1,123 @@
+use crate::announcer::{self, Announcer, Cue};
+use crate::cargo::{self, Action, CargoRun, DependencyGraph, Invocation, Metadata};
+use crate::collab::{self, Caret, Update};
+use crate::commands::{self, Command, Menu, MenuItem};
+use crate::contributions;
+use crate::counterpart;
+use crate::display_path::display_path;
+use crate::dotenv;
+use crate::encoding::TextEncoding;
+use crate::exclude::Exclusions;
 use crate::file_icons;
+use crate::find_in_files;
+use crate::folder_diff::{self, DiffLine, DiffNode, LineChange};
+use crate::forge::{self, Item, ItemKind, Repository};
+use crate::formatter;
 use crate::fs_tree::FileNode;
-use crate::state::{CursorPosition, EditorTabState};
-use crate::theme::{colors, create_vscode_style, fonts, layout};
-use crate::widgets::{
-    status_bar::detect_language, ActivityBar, ActivityItem, LineNumbersGutter, Minimap, StatusBar,
-    StatusBarInfo, Tab, TabBar,
+use crate::git::{self, SigningKey};
+use crate::highlight::{BackgroundHighlighter, HighlightRequest};
+use crate::keybindings::{self, KeyBinding, Keybindings};
+use crate::log_file::LogFile;
+use crate::lsp::{self, LspClient, LspEvent, PositionEncoding, Severity};
+use crate::pretty_print::{self, FragmentKind};
+use crate::primary_selection;
+use crate::project_template::{self, Template};
+use crate::rest_client::{self, HttpRequest, HttpResponse};
+use crate::server::{self, StaticServer};
+use crate::settings::{
+    Environments, Keymap, Layout, MultiCursorModifier, Session, Sessions, Settings, ThemeMode,
+    WorkspaceSettings,
 };
-use egui::{
-    Color32, FontId, Frame, Margin, Pos2, Rect, RichText, ScrollArea, TextEdit, TextStyle, Vec2,
+use crate::sql::{self, QueryResult};
+use crate::state::comment;
+use crate::state::completion::{self, CompletionItem};
+use crate::state::control_chars::ControlChar;
+use crate::state::crdt::Change;
+use crate::state::debounce::{Debounce, Debouncer};
+use crate::state::document::Document;
+use crate::state::emmet;
+use crate::state::folding::{self, FoldIndex, FoldKind, FoldRange};
+use crate::state::fuzzy;
+use crate::state::gestures::{DragGesture, Gesture};
+use crate::state::goto::GotoTarget;
+use crate::state::hover;
+use crate::state::merge;
+use crate::state::multi_cursor;
+use crate::state::navigation::{Location, NavigationHistory};
+use crate::state::occurrences::{self, Occurrence};
+use crate::state::outline::{self, OutlineSymbol};
+use crate::state::rope_diff;
+use crate::state::rust_doc;
+use crate::state::rust_mod;
+use crate::state::tags::{self, Markup};
+use crate::state::{CursorPosition, EditKind, EditorTabState};
+use crate::symbol_index::{self, WorkspaceSymbol};
+#[cfg(feature = "tree-sitter")]
+use crate::syntax_tree::SyntaxTree;
+use crate::theme::{create_vscode_style, fonts, layout, palette, Palette};
+use crate::watchdog::Watchdog;
+use crate::widgets::{
+    scroll_fraction, status_bar::detect_language, ActivityBar, ActivityItem, Bookmark,
+    BookmarksPanel, Breadcrumbs, CargoPanel, CodeEditor, CodeLens, CompletionPopup,
+    DependencyGraphView, DiffView, FolderDiffView, GraphViewport, GutterColumn, GutterDecoration,
+    GutterMark, HttpResponseView, LineNumbersGutter, LineStyle, LogView, MarkdownPreview, Minimap,
+    MinimapCache, OutlinePanel, OutputLink, OutputPanel, PathSegment, QueryResults, QuickPick,
+    QuickPickItem, Reference, ReferencesPanel, RegexFlags, RegexPlayground, RemoteCaret,
+    SourceControlPanel, StatusBar, StatusBarInfo, Tab, TabBar, TextHighlight, Underline,
 };
+use egui::containers::panel::PanelState;
+use egui::{Color32, FontId, Frame, Margin, Pos2, RichText, ScrollArea, Stroke, TextEdit, Vec2};
 use rfd::FileDialog;
 use ropey::Rope;
-use std::path::PathBuf;
-use syntect::easy::HighlightLines;
-use syntect::highlighting::ThemeSet;
-use syntect::parsing::SyntaxSet;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Id source of the code editor widget
+const CODE_EDITOR_ID: &str = "code_editor";
+
+/// Font sizes a tab can be zoomed between
+const MIN_FONT_SIZE: f32 = 6.0;
+const MAX_FONT_SIZE: f32 = 48.0;
 
-const SYNTAX_THEME: &str = "base16-ocean.dark";
+/// Files at least this large open in the log viewer instead of the editor
+const LOG_VIEWER_THRESHOLD: u64 = 64 << 20;
+
+/// How often followed files are checked for new content
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(500);
+/// How often open files are checked for having been deleted or moved
+const DISK_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How often the current session is written while only the active tab or
+/// layout changes; opening or closing tabs writes it right away
+const SESSION_AUTOSAVE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How long the line jumped to by Next/Previous Function stays highlighted
+const FLASH_DURATION: Duration = Duration::from_millis(600);
 
 /// Bracket pairs for matching
+/// Colors of other people's carets in a shared session, by peer
+const PARTICIPANT_COLORS: [Color32; 6] = [
+    Color32::from_rgb(0xE0, 0x6C, 0x75),
+    Color32::from_rgb(0x61, 0xAF, 0xEF),
+    Color32::from_rgb(0x98, 0xC3, 0x79),
+    Color32::from_rgb(0xC6, 0x78, 0xDD),
+    Color32::from_rgb(0xE5, 0xC0, 0x7B),
+    Color32::from_rgb(0x56, 0xB6, 0xC2),
+];
+
 const BRACKET_PAIRS: &[(char, char)] = &[('(', ')'), ('[', ']'), ('{', '}'), ('<', '>')];
 
 /// Find the matching bracket position for a given cursor position
-fn find_matching_bracket(text: &str, cursor_offset: usize) -> Option<(usize, usize)> {
-    let chars: Vec<char> = text.chars().collect();
+fn find_matching_bracket(rope: &Rope, cursor_offset: usize) -> Option<(usize, usize)> {
+    let len = rope.len_chars();
 
-    if cursor_offset >= chars.len() {
+    if cursor_offset >= len {
         return None;
     }
 
@@ -37,18 +129,19 @@ fn find_matching_bracket(text: &str, cursor_offset: usize) -> Option<(usize, usi
     };
 
     for pos in positions_to_check {
-        if pos >= chars.len() {
+        if pos >= len {
             continue;
         }
 
-        let ch = chars[pos];
+        let ch = rope.char(pos);
 
         // Check if it's an opening bracket
         for &(open, close) in BRACKET_PAIRS {
             if ch == open {
                 // Search forward for closing bracket
                 let mut depth = 1;
-                for (i, &c) in chars.iter().enumerate().skip(pos + 1) {
+                for (i, c) in rope.chars_at(pos + 1).enumerate() {
+                    let i = pos + 1 + i;
                     if c == open {
                         depth += 1;
                     } else if c == close {
@@ -62,9 +155,10 @@ fn find_matching_bracket(text: &str, cursor_offset: usize) -> Option<(usize, usi
                 // Search backward for opening bracket
                 let mut depth = 1;
                 for i in (0..pos).rev() {
-                    if chars[i] == close {
+                    let c = rope.char(i);
+                    if c == close {
                         depth += 1;
-                    } else if chars[i] == open {
+                    } else if c == open {
                         depth -= 1;
                         if depth == 0 {
                             return Some((i, pos));
@@ -78,24 +172,51 @@ fn find_matching_bracket(text: &str, cursor_offset: usize) -> Option<(usize, usi
     None
 }
 
-/// Convert character offset to (line, column) for rendering
-fn offset_to_line_col(text: &str, offset: usize) -> (usize, usize) {
-    let mut line = 0;
-    let mut col = 0;
+/// Reference entry for a char offset in a file's text
+fn reference_at(path: &std::path::Path, rope: &Rope, offset: usize) -> Reference {
+    let line = rope.char_to_line(offset);
+    Reference {
+        path: path.to_path_buf(),
+        line,
+        column: offset - rope.line_to_char(line),
+        preview: rope.line(line).to_string().trim().to_string(),
+    }
+}
 
-    for (i, ch) in text.chars().enumerate() {
-        if i == offset {
-            return (line, col);
-        }
-        if ch == '\n' {
-            line += 1;
-            col = 0;
-        } else {
-            col += 1;
-        }
+/// Number of errors and warnings among a file's diagnostics
+fn problem_counts(diagnostics: &[(Range<usize>, lsp::Diagnostic)]) -> (usize, usize) {
+    let count = |severity| {
+        diagnostics
+            .iter()
+            .filter(|(_, d)| d.severity == severity)
+            .count()
+    };
+    (count(Severity::Error), count(Severity::Warning))
+}
+
+/// Spoken summary of a file's errors and warnings
+fn problems_message(name: &str, (errors, warnings): (usize, usize)) -> String {
+    let plural = |n: usize, word: &str| match n {
+        1 => format!("1 {word}"),
+        n => format!("{n} {word}s"),
+    };
+    if errors == 0 && warnings == 0 {
+        return format!("{name}: no problems");
     }
+    format!(
+        "{name}: {}, {}",
+        plural(errors, "error"),
+        plural(warnings, "warning")
+    )
+}
 
-    (line, col)
+/// A tab's part in the shared session
+struct SharedDocument {
+    /// Index of the document in the session
+    document: usize,
+    /// Revision and text the session last saw, to send only what changed
+    revision: u64,
+    synced: Rope,
 }
 
 pub struct OpenFile {
@@ -103,15 +224,93 @@ pub struct OpenFile {
     pub buffer: Rope,
     pub original_content: String,
     pub state: EditorTabState,
+    /// Identifies the file to the background highlighter
+    id: u64,
+    /// Latest syntax colors from the background highlighter (may lag behind edits)
+    line_styles: Arc<Vec<LineStyle>>,
+    minimap: MinimapCache,
+    /// Revision and syntax theme of the last highlight request
+    highlight_requested: Option<(u64, String)>,
+    /// Latest diagnostics from the language server, with their char ranges at
+    /// the time they arrived
+    diagnostics: Vec<(Range<usize>, lsp::Diagnostic)>,
+    /// Revision last sent to the language server
+    lsp_revision: Option<u64>,
+    /// Declarations found by the highlighter, and by the language server when
+    /// it has any
+    symbols: Vec<OutlineSymbol>,
+    lsp_symbols: Option<Vec<OutlineSymbol>>,
+    /// Pending document symbols request
+    symbols_request: Option<u64>,
+    /// Parsed syntax tree, for languages with a tree-sitter grammar
+    #[cfg(feature = "tree-sitter")]
+    syntax_tree: Option<SyntaxTree>,
+    /// Selections visited by Expand Selection, the current one last
+    expanded_selections: Vec<Range<usize>>,
+    /// Request blocks of a `.http` file and the revision they were parsed at
+    http_requests: Vec<HttpRequest>,
+    http_requests_revision: Option<u64>,
+    /// Foldable comments and regions, and the revision and line count they
+    /// were found at
+    folds: FoldIndex,
+    fold_ranges_at: Option<(u64, usize)>,
+    /// Reload content appended on disk and keep the cursor at the end
+    follow: bool,
+    /// Size of the file on disk when it was last read or written
+    disk_len: u64,
+    /// How the file is stored on disk; saving writes it the same way
+    encoding: TextEncoding,
+    /// The file was deleted or moved on disk and the tab is waiting for the
+    /// user to keep or close it
+    missing: bool,
+    /// Kept in the editor after its file was deleted; saving asks where to
+    /// write it
+    untitled: bool,
+    shared: Option<SharedDocument>,
+    /// Line briefly highlighted after jumping to it, and when the jump was
+    flash: Option<(Range<usize>, Instant)>,
+    /// Caret offset the bracket pair was last matched at, and the pair
+    bracket_match: Option<(usize, Option<(usize, usize)>)>,
 }
 
 impl OpenFile {
     fn new(path: PathBuf, content: String) -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+        #[cfg(feature = "tree-sitter")]
+        let syntax_tree = path
+            .extension()
+            .and_then(|ext| SyntaxTree::new(&ext.to_string_lossy()));
+
         Self {
             path,
             buffer: Rope::from_str(&content),
             original_content: content,
             state: EditorTabState::default(),
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            line_styles: Arc::default(),
+            minimap: MinimapCache::default(),
+            highlight_requested: None,
+            diagnostics: Vec::new(),
+            lsp_revision: None,
+            symbols: Vec::new(),
+            lsp_symbols: None,
+            symbols_request: None,
+            #[cfg(feature = "tree-sitter")]
+            syntax_tree,
+            expanded_selections: Vec::new(),
+            http_requests: Vec::new(),
+            http_requests_revision: None,
+            folds: FoldIndex::default(),
+            fold_ranges_at: None,
+            follow: false,
+            disk_len: 0,
+            encoding: TextEncoding::default(),
+            missing: false,
+            untitled: false,
+            shared: None,
+            flash: None,
+            bracket_match: None,
         }
     }
 
@@ -130,943 +329,9187 @@ impl OpenFile {
             .unwrap_or("txt")
     }
 
-    fn is_modified(&self) -> bool {
-        self.state.is_modified
+    fn is_html(&self) -> bool {
+        matches!(self.extension(), "html" | "htm")
     }
-}
-
-/// Find/Replace panel state
-#[derive(Default)]
-struct FindReplaceState {
-    is_open: bool,
-    show_replace: bool,
-    search_text: String,
-    replace_text: String,
-    case_sensitive: bool,
-    current_match: usize,
-    matches: Vec<(usize, usize)>, // (start_offset, end_offset)
-}
-
-pub struct EditorApp {
-    workspace: Option<PathBuf>,
-    tree: Vec<FileNode>,
-    open_files: Vec<OpenFile>,
-    active_tab: usize,
-    syntax_set: SyntaxSet,
-    theme_set: ThemeSet,
-    active_activity: ActivityItem,
-    show_minimap: bool,
-    editor_scroll_offset: Vec2,
-    find_replace: FindReplaceState,
-}
 
-impl Default for EditorApp {
-    fn default() -> Self {
-        Self {
-            workspace: None,
-            tree: vec![],
-            open_files: vec![],
-            active_tab: 0,
-            syntax_set: SyntaxSet::load_defaults_newlines(),
-            theme_set: ThemeSet::load_defaults(),
-            active_activity: ActivityItem::Explorer,
-            show_minimap: true,
-            editor_scroll_offset: Vec2::ZERO,
-            find_replace: FindReplaceState::default(),
-        }
+    fn is_markdown(&self) -> bool {
+        matches!(self.extension(), "md" | "markdown")
     }
-}
-
-impl eframe::App for EditorApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        ctx.set_style(create_vscode_style());
 
-        // Handle keyboard shortcuts
-        self.handle_keyboard_shortcuts(ctx);
-
-        self.render_menu_bar(ctx);
-        self.render_activity_bar(ctx);
-        self.render_sidebar(ctx);
-        self.render_editor(ctx);
-        self.render_status_bar(ctx);
+    fn is_sql(&self) -> bool {
+        self.extension() == "sql"
+    }
 
-        // Render find/replace panel on top if open
-        if self.find_replace.is_open {
-            self.render_find_replace_panel(ctx);
-        }
+    fn is_http(&self) -> bool {
+        matches!(self.extension(), "http" | "rest")
     }
-}
 
-impl EditorApp {
-    // === Keyboard Shortcuts ===
+    /// Load what was appended to the file on disk (or all of it, if it got
+    /// shorter) and move the cursor to the end. Files with unsaved edits are
+    /// left alone. Returns whether anything was loaded.
+    fn follow_disk_changes(&mut self) -> std::io::Result<bool> {
+        use std::io::{Read, Seek, SeekFrom};
 
-    fn handle_keyboard_shortcuts(&mut self, ctx: &egui::Context) {
-        use egui::Key;
+        let len = std::fs::metadata(&self.path)?.len();
+        if len == self.disk_len || self.is_modified() {
+            return Ok(false);
+        }
+        if len < self.disk_len {
+            let content = self.read_from_disk()?;
+            self.buffer = Rope::from_str(&content);
+            self.original_content = content;
+            self.disk_len = len;
+        } else {
+            let mut file = std::fs::File::open(&self.path)?;
+            file.seek(SeekFrom::Start(self.disk_len))?;
+            let mut appended = Vec::new();
+            file.take(len - self.disk_len).read_to_end(&mut appended)?;
+            // A character split by the writer is picked up once it is complete
+            let text = match std::str::from_utf8(&appended) {
+                Ok(text) => text,
+                Err(e) if e.error_len().is_none() => {
+                    std::str::from_utf8(&appended[..e.valid_up_to()]).unwrap_or_default()
+                }
+                Err(_) => return Err(std::io::ErrorKind::InvalidData.into()),
+            };
+            self.buffer.insert(self.buffer.len_chars(), text);
+            self.original_content.push_str(text);
+            self.disk_len += text.len() as u64;
+        }
 
-        ctx.input(|i| {
-            // Ctrl+F - Open Find
-            if i.modifiers.ctrl && i.key_pressed(Key::F) {
-                self.find_replace.is_open = true;
-                self.find_replace.show_replace = false;
-            }
+        self.state.revision += 1;
+        self.state.clear_secondary_cursors();
+        self.state.set_cursor(CursorPosition::from_char_offset(
+            &self.buffer,
+            self.buffer.len_chars(),
+        ));
+        self.state.scroll_to_cursor = true;
+        Ok(true)
+    }
 
-            // Ctrl+H - Open Find and Replace
-            if i.modifiers.ctrl && i.key_pressed(Key::H) {
-                self.find_replace.is_open = true;
-                self.find_replace.show_replace = true;
-            }
+    /// Apply edits someone else made in a shared session, keeping the carets
+    /// on the same text
+    /// Make the tab document `document` of the shared session
+    fn share(&mut self, document: usize) {
+        self.shared = Some(SharedDocument {
+            document,
+            revision: self.state.revision,
+            synced: self.buffer.clone(),
+        });
+    }
 
-            // Escape - Close Find panel
-            if i.key_pressed(Key::Escape) && self.find_replace.is_open {
-                self.find_replace.is_open = false;
+    fn apply_remote_changes(&mut self, changes: &[Change]) {
+        let before = self.buffer.clone();
+        let mut carets: Vec<(usize, usize)> = self
+            .state
+            .cursors
+            .iter()
+            .map(|c| (c.offset, c.anchor))
+            .collect();
+        for &change in changes {
+            // A caret right where text is inserted stays in front of it
+            let shift = |position: &mut usize| match change {
+                Change::Inserted { offset, .. } if *position > offset => *position += 1,
+                Change::Deleted { offset } if *position > offset => *position -= 1,
+                _ => {}
+            };
+            for (offset, anchor) in carets.iter_mut() {
+                shift(offset);
+                shift(anchor);
             }
+            self.buffer.apply_change(change);
+        }
+        for (cursor, (offset, anchor)) in self.state.cursors.iter_mut().zip(carets) {
+            *cursor = CursorPosition::from_char_offset(&self.buffer, offset).with_anchor(anchor);
+        }
+        multi_cursor::dedup(&mut self.state.cursors);
+        self.state.history.rebase(&before, &self.buffer, changes);
+        self.state.revision += 1;
+        self.state.is_modified = self.buffer != self.original_content.as_str();
+        if let Some(shared) = &mut self.shared {
+            shared.revision = self.state.revision;
+            shared.synced = self.buffer.clone();
+        }
+    }
 
-            // Ctrl+S - Save
-            if i.modifiers.ctrl && i.key_pressed(Key::S) {
-                self.save_current_file();
+    /// Bring changes made to the file on disk into the buffer without losing
+    /// its unsaved edits, in one undo step. Returns the number of conflicts
+    /// left marked in the buffer, or None when the file hadn't changed.
+    fn merge_disk_changes(&mut self) -> std::io::Result<Option<usize>> {
+        let disk = self.read_from_disk()?;
+        if disk == self.original_content {
+            return Ok(None);
+        }
+        let merged = merge::merge(&self.original_content, &self.buffer.to_string(), &disk);
+        self.disk_len = std::fs::metadata(&self.path)?.len();
+        self.original_content = disk;
+        self.edit_lines(|buffer, cursors| {
+            *buffer = Rope::from_str(&merged.text);
+            for cursor in cursors.iter_mut() {
+                *cursor = CursorPosition::from_char_offset(buffer, cursor.offset);
             }
+            multi_cursor::dedup(cursors);
         });
+        Ok(Some(merged.conflicts))
     }
 
-    // === Find/Replace Panel ===
-
-    fn render_find_replace_panel(&mut self, ctx: &egui::Context) {
-        egui::Area::new(egui::Id::new("find_replace_panel"))
-            .anchor(egui::Align2::RIGHT_TOP, Vec2::new(-20.0, 50.0))
-            .show(ctx, |ui| {
-                Frame::none()
-                    .fill(colors::FIND_PANEL_BG)
-                    .inner_margin(Margin::same(8.0))
-                    .rounding(4.0)
-                    .shadow(egui::epaint::Shadow {
-                        extrusion: 8.0,
-                        color: Color32::from_black_alpha(100),
-                    })
-                    .show(ui, |ui| {
-                        ui.set_min_width(320.0);
-
-                        // Find row
-                        ui.horizontal(|ui| {
-                            ui.label("Find:");
-                            let find_response = ui.add(
-                                TextEdit::singleline(&mut self.find_replace.search_text)
-                                    .desired_width(200.0)
-                                    .hint_text("Search..."),
-                            );
-
-                            // Auto-search when text changes
-                            if find_response.changed() {
-                                self.perform_search();
-                            }
-
-                            // Request focus on first open
-                            if !self.find_replace.search_text.is_empty()
-                                || find_response.gained_focus()
-                            {
-                                find_response.request_focus();
-                            }
-
-                            // Navigation buttons
-                            if ui
-                                .button("▲")
-                                .on_hover_text("Previous (Shift+Enter)")
-                                .clicked()
-                            {
-                                self.find_previous();
-                            }
-                            if ui.button("▼").on_hover_text("Next (Enter)").clicked() {
-                                self.find_next();
-                            }
-
-                            // Match count
-                            let match_count = self.find_replace.matches.len();
-                            if match_count > 0 {
-                                let current = self.find_replace.current_match + 1;
-                                ui.label(format!("{}/{}", current, match_count));
-                            } else if !self.find_replace.search_text.is_empty() {
-                                ui.label("No results");
-                            }
-
-                            // Close button
-                            if ui.button("✕").clicked() {
-                                self.find_replace.is_open = false;
-                            }
-                        });
-
-                        // Replace row (if enabled)
-                        if self.find_replace.show_replace {
-                            ui.add_space(4.0);
-                            ui.horizontal(|ui| {
-                                ui.label("Replace:");
-                                ui.add(
-                                    TextEdit::singleline(&mut self.find_replace.replace_text)
-                                        .desired_width(200.0)
-                                        .hint_text("Replace with..."),
-                                );
-
-                                if ui
-                                    .button("Replace")
-                                    .on_hover_text("Replace current")
-                                    .clicked()
-                                {
-                                    self.replace_current();
-                                }
-                                if ui.button("All").on_hover_text("Replace all").clicked() {
-                                    self.replace_all();
-                                }
-                            });
-                        }
+    /// The file's text on disk, in its encoding
+    fn read_from_disk(&self) -> std::io::Result<String> {
+        let bytes = std::fs::read(&self.path)?;
+        self.encoding
+            .decode(&bytes)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
 
-                        // Options row
-                        ui.add_space(4.0);
-                        ui.horizontal(|ui| {
-                            ui.checkbox(&mut self.find_replace.case_sensitive, "Match case");
-                            ui.checkbox(&mut self.find_replace.show_replace, "Replace");
-                        });
-                    });
-            });
+    /// Read the file again as `encoding`, replacing the buffer and its undo
+    /// history
+    fn reopen_with_encoding(&mut self, encoding: TextEncoding) -> Result<(), String> {
+        let bytes = std::fs::read(&self.path).map_err(|e| e.to_string())?;
+        let content = encoding.decode(&bytes)?;
+        self.encoding = encoding;
+        self.disk_len = bytes.len() as u64;
+        self.buffer = Rope::from_str(&content);
+        self.original_content = content;
+        self.state.history = Default::default();
+        self.state.is_modified = false;
+        self.state.revision += 1;
+        self.state.clear_secondary_cursors();
+        let offset = self.state.cursor().offset.min(self.buffer.len_chars());
+        self.state
+            .set_cursor(CursorPosition::from_char_offset(&self.buffer, offset));
+        Ok(())
     }
 
-    fn perform_search(&mut self) {
-        self.find_replace.matches.clear();
-        self.find_replace.current_match = 0;
+    fn update_http_requests(&mut self) {
+        if self.http_requests_revision != Some(self.state.revision) {
+            self.http_requests = rest_client::parse_requests(&self.buffer.to_string());
+            self.http_requests_revision = Some(self.state.revision);
+        }
+    }
 
-        if self.find_replace.search_text.is_empty() {
+    /// Find the foldable ranges again after an edit. Collapsed folds stay
+    /// collapsed when a range of the same kind still starts on their line,
+    /// or as many lines further as the edit added.
+    fn update_fold_ranges(&mut self) {
+        let line_count = self.buffer.len_lines();
+        if self
+            .fold_ranges_at
+            .is_some_and(|(revision, _)| revision == self.state.revision)
+        {
             return;
         }
-
-        if let Some(file) = self.open_files.get(self.active_tab) {
-            let text = file.buffer.to_string();
-            let search = &self.find_replace.search_text;
-
-            let (text_to_search, search_pattern) = if self.find_replace.case_sensitive {
-                (text.clone(), search.clone())
-            } else {
-                (text.to_lowercase(), search.to_lowercase())
-            };
-
-            let search_len = search.chars().count();
-            let mut start = 0;
-
-            while let Some(pos) = text_to_search[start..].find(&search_pattern) {
-                let abs_pos = start + pos;
-                // Convert byte position to char position
-                let char_start = text[..abs_pos].chars().count();
-                let char_end = char_start + search_len;
-                self.find_replace.matches.push((char_start, char_end));
-                start = abs_pos + search.len();
+        let previous_count = self.fold_ranges_at.map_or(line_count, |(_, count)| count);
+        self.folds.update(&self.buffer);
+        self.fold_ranges_at = Some((self.state.revision, line_count));
+        let shift = line_count as isize - previous_count as isize;
+        let ranges = self.folds.ranges();
+        for fold in std::mem::take(&mut self.state.folded) {
+            let start = fold.lines.start;
+            let moved = start.checked_add_signed(shift).unwrap_or(start);
+            let found = [start, moved].into_iter().find_map(|line_idx| {
+                folding::fold_at(ranges, line_idx).filter(|range| range.kind == fold.kind)
+            });
+            if let Some(range) = found {
+                if !self.state.folded.contains(range) {
+                    self.state.folded.push(range.clone());
+                }
             }
         }
     }
 
-    fn find_next(&mut self) {
-        if !self.find_replace.matches.is_empty() {
-            self.find_replace.current_match =
-                (self.find_replace.current_match + 1) % self.find_replace.matches.len();
+    /// Collapse a fold, moving carets inside it to its first line
+    fn fold(&mut self, fold: FoldRange) {
+        if self.state.folded.contains(&fold) {
+            return;
+        }
+        let hidden = fold.hidden();
+        let buffer = &self.buffer;
+        let inside = |cursor: &CursorPosition| hidden.contains(&buffer.char_to_line(cursor.offset));
+        if inside(self.state.cursor()) {
+            let start = buffer.line_to_char(fold.lines.start);
+            self.state
+                .set_cursor(CursorPosition::from_char_offset(buffer, start));
         }
+        self.state.cursors.retain(|cursor| !inside(cursor));
+        self.state.folded.push(fold);
     }
 
-    fn find_previous(&mut self) {
-        if !self.find_replace.matches.is_empty() {
-            if self.find_replace.current_match == 0 {
-                self.find_replace.current_match = self.find_replace.matches.len() - 1;
-            } else {
-                self.find_replace.current_match -= 1;
+    /// Collapse or open the fold starting on a line
+    fn toggle_fold(&mut self, line_idx: usize) {
+        let folded = self.state.folded.len();
+        self.state
+            .folded
+            .retain(|fold| fold.lines.start != line_idx);
+        if self.state.folded.len() == folded {
+            if let Some(range) = folding::fold_at(self.folds.ranges(), line_idx).cloned() {
+                self.fold(range);
             }
         }
     }
 
-    fn replace_current(&mut self) {
-        if self.find_replace.matches.is_empty() {
-            return;
+    /// Collapse the innermost open fold around the caret's line
+    fn fold_at_caret(&mut self) {
+        let line_idx = self.state.cursor().line.saturating_sub(1);
+        let range = self
+            .folds
+            .ranges()
+            .iter()
+            .filter(|range| range.lines.contains(&line_idx) && !self.state.folded.contains(range))
+            .min_by_key(|range| range.lines.len())
+            .cloned();
+        if let Some(range) = range {
+            self.fold(range);
         }
+    }
 
-        if let Some(file) = self.open_files.get_mut(self.active_tab) {
-            let (start, end) = self.find_replace.matches[self.find_replace.current_match];
-            let mut text = file.buffer.to_string();
-            let chars: Vec<char> = text.chars().collect();
+    /// Open the folds starting on the caret's line
+    fn unfold_at_caret(&mut self) {
+        let line_idx = self.state.cursor().line.saturating_sub(1);
+        self.state
+            .folded
+            .retain(|fold| fold.lines.start != line_idx);
+    }
 
-            // Convert char positions to byte positions
-            let byte_start: usize = chars[..start].iter().collect::<String>().len();
-            let byte_end: usize = chars[..end].iter().collect::<String>().len();
+    /// Collapse every fold of the given kinds
+    fn fold_all(&mut self, kinds: &[FoldKind]) {
+        let ranges: Vec<FoldRange> = self
+            .folds
+            .ranges()
+            .iter()
+            .filter(|range| kinds.contains(&range.kind))
+            .cloned()
+            .collect();
+        for range in ranges {
+            self.fold(range);
+        }
+    }
 
-            text.replace_range(byte_start..byte_end, &self.find_replace.replace_text);
-            file.buffer = Rope::from_str(&text);
-            file.state.is_modified = true;
+    fn is_modified(&self) -> bool {
+        self.state.is_modified || self.untitled
+    }
 
-            // Re-search to update matches
-            self.perform_search();
+    #[cfg(feature = "tree-sitter")]
+    fn update_syntax_tree(&mut self) {
+        if let Some(tree) = &mut self.syntax_tree {
+            tree.update(&self.buffer, self.state.revision);
         }
     }
 
-    fn replace_all(&mut self) {
-        if self.find_replace.matches.is_empty() {
+    /// Select the enclosing syntax node, or without a syntax tree the
+    /// enclosing word, string, brackets, line or block
+    fn expand_selection(&mut self) {
+        let current = self.state.cursor().selection();
+        #[cfg(feature = "tree-sitter")]
+        let expanded = {
+            self.update_syntax_tree();
+            match &self.syntax_tree {
+                Some(tree) => tree.expand_selection(current.clone()),
+                None => multi_cursor::expand_range(&self.buffer, current.clone()),
+            }
+        };
+        #[cfg(not(feature = "tree-sitter"))]
+        let expanded = multi_cursor::expand_range(&self.buffer, current.clone());
+        let Some(expanded) = expanded else {
             return;
-        }
-
-        if let Some(file) = self.open_files.get_mut(self.active_tab) {
-            let text = file.buffer.to_string();
-            let search = &self.find_replace.search_text;
-            let replace = &self.find_replace.replace_text;
+        };
 
-            let new_text = if self.find_replace.case_sensitive {
-                text.replace(search, replace)
-            } else {
-                // Case-insensitive replace
-                let mut result = text.clone();
-                let lower_text = text.to_lowercase();
-                let lower_search = search.to_lowercase();
-                let mut offset: i64 = 0;
-
-                for (pos, _) in lower_text.match_indices(&lower_search) {
-                    let adjusted_pos = (pos as i64 + offset) as usize;
-                    let end_pos = adjusted_pos + search.len();
-                    result.replace_range(adjusted_pos..end_pos, replace);
-                    offset += replace.len() as i64 - search.len() as i64;
-                }
-                result
-            };
+        if self.expanded_selections.last() != Some(&current) {
+            self.expanded_selections = vec![current];
+        }
+        self.expanded_selections.push(expanded.clone());
+        self.select(expanded);
+    }
 
-            file.buffer = Rope::from_str(&new_text);
-            file.state.is_modified = true;
+    /// Go back to the selection before the last Expand Selection
+    fn shrink_selection(&mut self) {
+        if self.expanded_selections.len() < 2
+            || self.expanded_selections.last() != Some(&self.state.cursor().selection())
+        {
+            return;
+        }
+        self.expanded_selections.pop();
+        if let Some(previous) = self.expanded_selections.last().cloned() {
+            self.select(previous);
+        }
+    }
 
-            // Re-search to update matches
-            self.perform_search();
+    /// Select inside the quotes around the selection, then the quotes too
+    fn select_string_contents(&mut self) {
+        let current = self.state.cursor().selection();
+        if let Some(range) = multi_cursor::string_range_around(&self.buffer, current) {
+            self.select(range);
         }
     }
 
-    // === Menu Bar ===
+    /// Select inside the brackets around the selection, then the brackets too
+    fn select_bracket_contents(&mut self) {
+        let current = self.state.cursor().selection();
+        if let Some(range) = multi_cursor::bracket_range_around(&self.buffer, current) {
+            self.select(range);
+        }
+    }
+
+    /// Offsets of the bracket at or before `offset` and its match
+    fn matching_bracket(&self, offset: usize) -> Option<(usize, usize)> {
+        #[cfg(feature = "tree-sitter")]
+        return match &self.syntax_tree {
+            Some(tree) => tree.matching_bracket(offset),
+            None => find_matching_bracket(&self.buffer, offset),
+        };
+        #[cfg(not(feature = "tree-sitter"))]
+        find_matching_bracket(&self.buffer, offset)
+    }
+
+    /// Offsets of the bracket at the caret and its match, as highlighted, or
+    /// else of the nearest pair around the caret
+    fn bracket_pair(&self) -> Option<(usize, usize)> {
+        let offset = self.state.cursor().offset;
+        self.matching_bracket(offset).or_else(|| {
+            let contents = multi_cursor::bracket_range_around(&self.buffer, offset..offset)?;
+            Some((contents.start.checked_sub(1)?, contents.end))
+        })
+    }
+
+    /// Move the caret to the bracket matching the one next to it, or to the
+    /// closing bracket of the pair around it
+    fn go_to_bracket(&mut self) {
+        let Some((open, close)) = self.bracket_pair() else {
+            return;
+        };
+        let offset = self.state.cursor().offset;
+        let at_close = offset == close || offset == close + 1;
+        let target = if at_close { open } else { close };
+        self.state.clear_secondary_cursors();
+        self.state
+            .set_cursor(CursorPosition::from_char_offset(&self.buffer, target));
+        self.state.scroll_to_cursor = true;
+    }
+
+    /// Select the bracket pair at or around the caret, brackets included
+    fn select_to_bracket(&mut self) {
+        if let Some((open, close)) = self.bracket_pair() {
+            self.select(open..close + 1);
+        }
+    }
+
+    /// Put a cursor on every range, each selecting it with the caret at its
+    /// end. The range the primary cursor is in stays primary.
+    fn select_ranges(&mut self, ranges: &[Range<usize>]) {
+        let caret = self.state.cursor().offset;
+        let primary = ranges
+            .iter()
+            .position(|r| r.start <= caret && caret <= r.end)
+            .unwrap_or(0);
+        let cursor = |range: &Range<usize>| {
+            CursorPosition::from_char_offset(&self.buffer, range.end).with_anchor(range.start)
+        };
+        let mut cursors = vec![cursor(&ranges[primary])];
+        cursors.extend(
+            ranges
+                .iter()
+                .enumerate()
+                .filter(|&(i, _)| i != primary)
+                .map(|(_, range)| cursor(range)),
+        );
+        self.state.cursors = cursors;
+        self.state.scroll_to_cursor = true;
+    }
+
+    /// Select every occurrence of the selection, or of the word at the caret
+    /// as a whole word. Returns how many there are.
+    fn select_all_occurrences(&mut self) -> usize {
+        let cursor = self.state.cursor();
+        let ranges: Vec<Range<usize>> = if cursor.has_selection() {
+            let selected = self.buffer.slice(cursor.selection()).to_string();
+            find_in_files::find_matches(&self.buffer.to_string(), &selected, true)
+                .into_iter()
+                .map(|(start, end)| start..end)
+                .collect()
+        } else {
+            let offset = cursor.offset;
+            let Some(word) = hover::word_at(&self.buffer, offset)
+                .or_else(|| hover::word_at(&self.buffer, offset.checked_sub(1)?))
+            else {
+                return 0;
+            };
+            let len = word.len();
+            let word = self.buffer.slice(word).to_string();
+            hover::word_positions(&self.buffer, &word)
+                .into_iter()
+                .map(|start| start..start + len)
+                .collect()
+        };
+        if !ranges.is_empty() {
+            self.select_ranges(&ranges);
+        }
+        ranges.len()
+    }
+
+    fn select(&mut self, range: Range<usize>) {
+        self.state.clear_secondary_cursors();
+        self.state.set_cursor(
+            CursorPosition::from_char_offset(&self.buffer, range.end).with_anchor(range.start),
+        );
+        self.state.scroll_to_cursor = true;
+    }
+
+    /// Rewrite the buffer through its cursors in one undo step, like sorting
+    /// the selected lines
+    fn edit_lines(&mut self, edit: impl FnOnce(&mut Rope, &mut Vec<CursorPosition>)) {
+        self.state
+            .history
+            .record(&self.buffer, &self.state.cursors, EditKind::Other);
+        edit(&mut self.buffer, &mut self.state.cursors);
+        self.state.revision += 1;
+        self.state.is_modified = self.buffer != self.original_content.as_str();
+        self.state.scroll_to_cursor = true;
+    }
+
+    /// Replace an Emmet abbreviation right before the caret with its
+    /// expansion, in HTML, CSS and JSX files
+    fn expand_emmet(&mut self) -> bool {
+        let Some(syntax) = emmet::Syntax::for_extension(self.extension()) else {
+            return false;
+        };
+        if self.state.has_multiple_cursors() || self.state.cursor().has_selection() {
+            return false;
+        }
+        let indent = pretty_print::indent_unit(&self.buffer);
+        let offset = self.state.cursor().offset;
+        let Some(expansion) = emmet::expand(&self.buffer, offset, syntax, &indent) else {
+            return false;
+        };
+        self.replace_ranges(std::slice::from_ref(&expansion.range), &expansion.text);
+        let caret = expansion.range.start + expansion.caret;
+        self.state
+            .set_cursor(CursorPosition::from_char_offset(&self.buffer, caret));
+        self.state.history.break_group();
+        true
+    }
+
+    /// Replace character ranges (sorted, non-overlapping) with `text` in one undo step.
+    /// Ranges are applied back to front so earlier offsets stay valid.
+    fn replace_ranges(&mut self, ranges: &[Range<usize>], text: &str) {
+        self.state
+            .history
+            .record(&self.buffer, &self.state.cursors, EditKind::Other);
+
+        for range in ranges.iter().rev() {
+            self.buffer.remove(range.clone());
+            if !text.is_empty() {
+                self.buffer.insert(range.start, text);
+            }
+        }
+
+        for cursor in self.state.cursors.iter_mut() {
+            *cursor = CursorPosition::from_char_offset(&self.buffer, cursor.offset);
+        }
+        multi_cursor::dedup(&mut self.state.cursors);
+        self.state.revision += 1;
+        self.state.is_modified = self.buffer != self.original_content.as_str();
+    }
+}
+
+/// Find/Replace panel state
+#[derive(Default)]
+struct FindReplaceState {
+    is_open: bool,
+    show_replace: bool,
+    search_text: String,
+    replace_text: String,
+    case_sensitive: bool,
+    /// Where Replace All replaces; Find always searches the active file
+    scope: FindScope,
+    current_match: usize,
+    matches: Vec<(usize, usize)>, // (start_offset, end_offset)
+    /// Matches in the other open files, and how many of them have any
+    other_matches: (usize, usize),
+}
+
+/// Files the Find/Replace panel's Replace All works on
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+enum FindScope {
+    #[default]
+    File,
+    /// Every open buffer, changed in memory
+    OpenFiles,
+    /// Open buffers in memory, and the workspace's other files on disk
+    Workspace,
+}
+
+impl FindScope {
+    const ALL: [FindScope; 3] = [FindScope::File, FindScope::OpenFiles, FindScope::Workspace];
+
+    fn label(self) -> &'static str {
+        match self {
+            FindScope::File => "This File",
+            FindScope::OpenFiles => "Open Files",
+            FindScope::Workspace => "Workspace",
+        }
+    }
+}
+
+/// Offer to pretty-print a single-line JSON/XML fragment that was just pasted
+struct PasteFormatOffer {
+    tab: usize,
+    /// Revision right after the paste; any later edit withdraws the offer
+    revision: u64,
+    range: Range<usize>,
+    kind: FragmentKind,
+    formatted: String,
+}
+
+/// Replace All waiting for the user to confirm it
+struct ReplaceAllConfirm {
+    replacements: usize,
+    /// Names of the files with matches
+    files: Vec<String>,
+}
+
+/// What the last Replace All changed, to put back in one go
+struct ReplaceAllUndo {
+    summary: String,
+    /// Open files by id: the text before, and the revision right after the
+    /// replacement; any later edit to them withdraws the undo
+    buffers: Vec<(u64, String, u64)>,
+    /// Files rewritten on disk: the text before and the text written
+    disk: Vec<(PathBuf, String, String)>,
+}
+
+/// Offer to give the other tag of a pair the name one of them was edited to
+#[derive(Clone)]
+struct TagRenameOffer {
+    tab: usize,
+    /// Revision right after the edit; any later edit outside the name
+    /// withdraws the offer
+    revision: u64,
+    /// Length of the text then, to tell how much an edit added
+    length: usize,
+    /// Name of the edited tag
+    name: Range<usize>,
+    /// Name of the other tag, still `old_name`
+    partner: Range<usize>,
+    old_name: String,
+}
+
+/// A step of a keyboard macro
+#[derive(Clone)]
+enum MacroStep {
+    /// Input the code editor got
+    Input(egui::Event),
+    /// Command run from a shortcut, a menu or the command palette
+    Command(Command),
+}
+
+/// Edit > Record Macro and Play Macro
+#[derive(Default)]
+struct MacroState {
+    recording: bool,
+    /// Steps of the last recording
+    steps: Vec<MacroStep>,
+    /// Steps still to play. Input goes to the editor up to the next command,
+    /// which runs once the editor has had the input before it.
+    playing: VecDeque<MacroStep>,
+    /// Number of times to play, while Play Macro asks for it
+    repeat: Option<String>,
+}
+
+/// A second editor group beside the first. Keyboard input goes to the active
+/// tab's group; the other one shows `tab` until it is clicked.
+#[derive(Debug, Clone, Copy)]
+struct EditorSplit {
+    tab: usize,
+    /// The active tab's group is the one on the right
+    active_right: bool,
+}
+
+/// Go to Line panel state
+#[derive(Default)]
+struct GotoLineState {
+    is_open: bool,
+    input: String,
+    /// The last submitted input couldn't be parsed
+    invalid: bool,
+}
+
+impl GotoLineState {
+    fn open(&mut self) {
+        self.is_open = true;
+        self.input.clear();
+        self.invalid = false;
+    }
+}
+
+/// Autocomplete popup state
+#[derive(Default)]
+struct CompletionState {
+    is_open: bool,
+    /// Tab the popup belongs to
+    tab: usize,
+    /// Start of the word being completed
+    word_start: usize,
+    /// Typed part of the word the items were filtered with
+    prefix: String,
+    /// Everything that can complete the word; `items` is the filtered part
+    candidates: Vec<CompletionItem>,
+    items: Vec<CompletionItem>,
+    selected: usize,
+    /// Language server request whose answer would replace the candidates
+    pending_request: Option<u64>,
+    /// Completing a file name in a path string rather than an identifier
+    in_path: bool,
+    /// Screen position of the popup, below the caret
+    anchor: Pos2,
+}
+
+impl CompletionState {
+    fn close(&mut self) {
+        *self = Self {
+            anchor: self.anchor,
+            ..Default::default()
+        };
+    }
+
+    fn refilter(&mut self) {
+        self.items = completion::filter(&self.candidates, &self.prefix);
+        self.selected = 0;
+    }
+}
+
+/// Hover tooltip state
+#[derive(Default)]
+struct HoverState {
+    /// Tab, revision, and identifier range the contents are for
+    tab: usize,
+    revision: u64,
+    word: Option<Range<usize>>,
+    /// Language server request whose answer would fill `contents`
+    pending_request: Option<u64>,
+    /// Markdown shown in the tooltip
+    contents: Option<String>,
+}
+
+/// Occurrences of the identifier at the caret
+#[derive(Default)]
+struct OccurrenceState {
+    /// Tab, revision, and identifier range the occurrences are for
+    tab: usize,
+    revision: u64,
+    word: Option<Range<usize>>,
+    /// Language server request whose answer replaces the guessed occurrences
+    pending_request: Option<u64>,
+    occurrences: Vec<Occurrence>,
+}
+
+/// Bottom panels that can move to a window of their own
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum FloatingPanel {
+    Output,
+    References,
+}
+
+impl FloatingPanel {
+    const ALL: [Self; 2] = [Self::Output, Self::References];
+
+    fn title(self) -> &'static str {
+        match self {
+            Self::Output => "Output",
+            Self::References => "References",
+        }
+    }
+}
+
+/// Find References panel state
+#[derive(Default)]
+struct ReferencesState {
+    is_open: bool,
+    symbol: String,
+    /// Language server request whose answer fills `references`
+    pending_request: Option<u64>,
+    /// Sorted by path and position
+    references: Vec<Reference>,
+}
+
+/// Response pane of the REST client
+#[derive(Default)]
+struct RestState {
+    is_open: bool,
+    /// Method and URL of the last request sent
+    title: String,
+    pending: Option<Receiver<Result<HttpResponse, String>>>,
+    result: Option<Result<HttpResponse, String>>,
+}
+
+/// SQL scratchpad state
+#[derive(Default)]
+struct SqlState {
+    /// SQLite database each `.sql` file is connected to
+    databases: HashMap<PathBuf, PathBuf>,
+    /// Query Results panel
+    is_open: bool,
+    /// Database of the last query
+    database: String,
+    pending: Option<Receiver<Result<QueryResult, String>>>,
+    result: Option<Result<QueryResult, String>>,
+}
+
+/// Log viewer state, shown in place of the editor while open
+struct LogViewerState {
+    log: LogFile,
+    filter_text: String,
+    filter_error: Option<String>,
+    /// Scroll to new lines as the file grows
+    follow: bool,
+}
+
+/// Regex Playground state, shown in place of the editor while open
+#[derive(Default)]
+struct RegexPlaygroundState {
+    pattern: String,
+    flags: RegexFlags,
+    sample: String,
+    /// Compiled pattern, None while it is empty or invalid
+    regex: Option<regex::Regex>,
+    error: Option<String>,
+}
+
+/// Pull requests and issues of the open folder's repository, shown in the
+/// Source Control view
+#[derive(Default)]
+struct ForgeState {
+    /// Where the items were loaded from
+    repository: Option<Repository>,
+    pending: Option<Receiver<Result<Vec<Item>, String>>>,
+    /// None until they have been loaded
+    items: Option<Result<Vec<Item>, String>>,
+    /// Index of the item whose details are shown
+    open: Option<usize>,
+    /// Running checkout of a pull request's branch
+    checkout: Option<Receiver<Result<String, String>>>,
+}
+
+/// Cargo panel state for the open folder
+#[derive(Default)]
+struct CargoState {
+    pending: Option<Receiver<Result<Metadata, String>>>,
+    /// None until `cargo metadata` has run
+    metadata: Option<Result<Metadata, String>>,
+    /// Features ticked per package
+    features: HashMap<String, BTreeSet<String>>,
+    run: Option<CargoRun>,
+    graph_pending: Option<Receiver<Result<DependencyGraph, String>>>,
+    /// Resolved dependency graph, kept while the graph view is closed
+    graph: Option<Result<DependencyGraph, String>>,
+}
+
+/// Compare Folders state, shown in place of the editor while open
+struct FolderCompareState {
+    left: PathBuf,
+    right: PathBuf,
+    pending: Option<Receiver<Vec<DiffNode>>>,
+    /// None until the comparison finishes
+    nodes: Option<Vec<DiffNode>>,
+    /// Hide entries that are the same in both folders
+    changed_only: bool,
+    /// Relative path of the file being diffed, and its diff
+    selected: Option<(PathBuf, Result<Vec<DiffLine>, String>)>,
+}
+
+/// File > New Project dialog state
+struct NewProjectState {
+    templates: Vec<Template>,
+    /// Index into `templates`
+    template: usize,
+    name: String,
+    /// Folder the project is created in
+    location: String,
+    init_git: bool,
+    /// Why the last attempt failed
+    error: Option<String>,
+}
+
+/// File > Join Shared Session dialog state
+#[derive(Default)]
+struct JoinSessionState {
+    /// Host's address, with the port if it isn't the default one
+    address: String,
+    code: String,
+    /// Why the last attempt failed
+    error: Option<String>,
+}
+
+/// File > Share Session dialog state
+struct ShareSessionState {
+    /// Paths of the tabs to share; tabs opened later are never shared
+    chosen: HashSet<PathBuf>,
+}
+
+/// Explorer > Rename dialog state
+struct RenameFileState {
+    path: PathBuf,
+    name: String,
+    /// Why the last attempt failed
+    error: Option<String>,
+}
+
+/// Window close held back to ask about unsaved files and a running task
+struct ExitPrompt {
+    /// Names of the modified files
+    unsaved: Vec<String>,
+    /// Label of the cargo command still running
+    task: Option<String>,
+}
+
+/// Source file whose references follow a renamed Rust module
+struct ModuleReferenceEdit {
+    /// Where the file is after the rename
+    path: PathBuf,
+    /// Path relative to the crate's `src` folder
+    label: String,
+    new_text: String,
+    /// Changed lines with a little context
+    diff: Vec<DiffLine>,
+    apply: bool,
+}
+
+/// Offer to update `mod` declarations and paths after a module file was renamed
+struct ModuleRenameState {
+    /// Old module path and new name, e.g. `crate::widgets::tab_bar → tabs`
+    title: String,
+    edits: Vec<ModuleReferenceEdit>,
+    /// Index into `edits` of the file whose diff is shown
+    selected: usize,
+}
+
+/// Variable in the environment editor
+struct EnvRow {
+    key: String,
+    value: String,
+    /// Show the value even though the key looks like it names a secret
+    revealed: bool,
+}
+
+/// Commit dialog state, for the changes staged in the workspace's repository
+struct CommitState {
+    subject: String,
+    body: String,
+    /// Run the repository's commit hooks
+    verify: bool,
+    staged: Vec<String>,
+    /// Key the repository signs commits with
+    signing_key: Option<SigningKey>,
+    pending: Option<Receiver<Result<String, String>>>,
+    /// Output of the last commit attempt that failed, e.g. a hook's complaint
+    error: Option<String>,
+}
+
+/// File > Keybindings: changes to the shortcuts, saved to `keybindings.toml`
+struct KeybindingsEditorState {
+    draft: Keybindings,
+    /// Shows only the commands whose name or shortcut contains it
+    filter: String,
+    /// Command whose shortcut is being recorded, and the first key of a chord
+    recording: Option<(Command, Option<egui::KeyboardShortcut>)>,
+    /// Why the last save failed
+    error: Option<String>,
+}
+
+/// Environment Variables dialog state; rows are saved to `environments.toml`
+struct EnvEditorState {
+    rows: Vec<EnvRow>,
+    /// Why the last save failed
+    error: Option<String>,
+}
+
+/// Whether a variable probably holds a secret, so its value is masked
+fn is_secret(key: &str) -> bool {
+    let key = key.to_ascii_uppercase();
+    [
+        "TOKEN",
+        "SECRET",
+        "PASSWORD",
+        "PASSWD",
+        "API_KEY",
+        "PRIVATE",
+        "CREDENTIAL",
+        "AUTH",
+    ]
+    .iter()
+    .any(|word| key.contains(word))
+}
+
+/// Workspace symbol search (Ctrl+T) state
+#[derive(Default)]
+struct SymbolSearchState {
+    is_open: bool,
+    query: String,
+    selected: usize,
+    /// Declarations found by the background indexer
+    index: Vec<WorkspaceSymbol>,
+    indexing: Option<Receiver<Vec<WorkspaceSymbol>>>,
+    /// Language server requests for the current query, and their answers so far
+    pending_requests: HashSet<u64>,
+    lsp_symbols: Vec<WorkspaceSymbol>,
+    /// Best matches for the query, best first
+    matches: Vec<WorkspaceSymbol>,
+    /// `matches` has to be recomputed
+    stale: bool,
+}
+
+impl SymbolSearchState {
+    /// Rank the symbols against the query. The language servers' answers are
+    /// used when there are any, the index otherwise.
+    fn refilter(&mut self) {
+        const MAX_MATCHES: usize = 100;
+
+        let symbols = if self.lsp_symbols.is_empty() {
+            &self.index
+        } else {
+            &self.lsp_symbols
+        };
+        let mut scored: Vec<(i32, &WorkspaceSymbol)> = symbols
+            .iter()
+            .filter_map(|symbol| Some((fuzzy::score(&self.query, &symbol.name)?, symbol)))
+            .collect();
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.name.cmp(&b.1.name)));
+        self.matches = scored
+            .into_iter()
+            .take(MAX_MATCHES)
+            .map(|(_, symbol)| symbol.clone())
+            .collect();
+        self.stale = false;
+    }
+}
+
+/// Go to File (Ctrl+P): the workspace's files, ranked against the query
+#[derive(Default)]
+struct QuickOpenState {
+    picker: PickerState,
+    /// Files of the Explorer tree that git doesn't ignore, relative to the
+    /// workspace folder with `/` between folders
+    files: Vec<String>,
+    /// Indices into `files` of the best matches, best first
+    matches: Vec<usize>,
+}
+
+impl QuickOpenState {
+    /// Rank the files against the query. Files whose name matches come
+    /// before ones that only match across their folders.
+    fn refilter(&mut self) {
+        const MAX_MATCHES: usize = 100;
+        const NAME_BONUS: i32 = 1000;
+
+        let query = &self.picker.query;
+        let mut scored: Vec<(i32, usize)> = self
+            .files
+            .iter()
+            .enumerate()
+            .filter_map(|(i, path)| {
+                let name = path.rsplit('/').next().unwrap_or(path);
+                let score = fuzzy::score(query, name)
+                    .map(|score| score + NAME_BONUS)
+                    .or_else(|| fuzzy::score(query, path))?;
+                Some((score, i))
+            })
+            .collect();
+        scored.sort_by(|a, b| {
+            b.0.cmp(&a.0)
+                .then_with(|| self.files[a.1].cmp(&self.files[b.1]))
+        });
+        self.matches = scored
+            .into_iter()
+            .take(MAX_MATCHES)
+            .map(|(_, i)| i)
+            .collect();
+    }
+}
+
+/// What the encoding picker does with the encoding picked
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum EncodingAction {
+    Reopen,
+    Save,
+}
+
+/// Closed tabs kept for Reopen Closed Tab
+const MAX_CLOSED_TABS: usize = 20;
+
+/// A tab that was closed, for Reopen Closed Tab
+struct ClosedTab {
+    path: PathBuf,
+    /// Char offset of the primary cursor
+    offset: usize,
+    /// Scroll position of the editor, when the tab was the one shown
+    scroll: Option<Vec2>,
+}
+
+/// Reopen / Save with Encoding: the action, once chosen, and the encodings
+#[derive(Default)]
+struct EncodingPickerState {
+    picker: PickerState,
+    /// None while the status bar item asks which of the two to do
+    action: Option<EncodingAction>,
+}
+
+/// Query and selection of a picker over a fixed list, like the command palette
+#[derive(Default)]
+struct PickerState {
+    is_open: bool,
+    query: String,
+    selected: usize,
+}
+
+impl PickerState {
+    fn open(&mut self) {
+        *self = Self {
+            is_open: true,
+            ..Self::default()
+        };
+    }
+}
+
+/// Markdown preview pane state
+struct PreviewState {
+    is_open: bool,
+    /// Keep the editor and the preview at the same relative scroll position
+    sync_scroll: bool,
+    /// Relative scroll positions (0.0..=1.0) of both panes in the last frame
+    editor_fraction: f32,
+    preview_fraction: f32,
+    /// The editor scrolled since the previous frame
+    editor_scrolled: bool,
+    /// Scrollable height of the editor in the last frame
+    editor_max_scroll: f32,
+    /// Scroll the editor to this relative position on the next frame
+    scroll_editor_to: Option<f32>,
+}
+
+impl Default for PreviewState {
+    fn default() -> Self {
+        Self {
+            is_open: false,
+            sync_scroll: true,
+            editor_fraction: 0.0,
+            preview_fraction: 0.0,
+            editor_scrolled: false,
+            editor_max_scroll: 0.0,
+            scroll_editor_to: None,
+        }
+    }
+}
+
+pub struct EditorApp {
+    workspace: Option<PathBuf>,
+    tree: Vec<FileNode>,
+    open_files: Vec<OpenFile>,
+    active_tab: usize,
+    highlighter: BackgroundHighlighter,
+    active_activity: ActivityItem,
+    editor_scroll_offset: Vec2,
+    /// Scroll position to give the editor on the next frame
+    restore_scroll: Option<Vec2>,
+    /// Most recently closed last
+    closed_tabs: Vec<ClosedTab>,
+    /// Id of the file shown in the active tab last frame, and of the one
+    /// shown before it, for Toggle Last Editor
+    active_file: Option<u64>,
+    previous_file: Option<u64>,
+    find_replace: FindReplaceState,
+    /// Searching the active file again after edits, once typing pauses
+    search_debounce: Debouncer,
+    /// Matching the bracket at the caret after edits, once typing pauses
+    bracket_debounce: Debouncer,
+    goto_line: GotoLineState,
+    completion: CompletionState,
+    hover: HoverState,
+    occurrences: OccurrenceState,
+    /// Go to Definition request awaiting an answer
+    pending_definition: Option<u64>,
+    references: ReferencesState,
+    symbol_search: SymbolSearchState,
+    /// Where the cursor jumped from, for Go Back and Go Forward
+    navigation: NavigationHistory,
+    /// Bookmarks panel, toggled in the View menu
+    bookmarks_panel: bool,
+    /// Command palette (Ctrl+Shift+P)
+    command_palette: PickerState,
+    /// Cargo command picker of Run > Run Task
+    run_task: PickerState,
+    sessions: Sessions,
+    /// Session last saved or switched to
+    current_session: Option<String>,
+    /// When the current session was last written
+    session_saved_at: Instant,
+    /// Name being entered in the Save Session dialog
+    session_name: Option<String>,
+    session_picker: PickerState,
+    encoding_picker: EncodingPickerState,
+    quick_open: QuickOpenState,
+    /// Cut and copied texts, latest first, for Edit > Paste from History
+    clipboard_history: VecDeque<String>,
+    clipboard_picker: PickerState,
+    /// Input replayed to the code editor next frame, for editor commands run
+    /// from a menu or the command palette
+    editor_events: Vec<egui::Event>,
+    macros: MacroState,
+    /// Shortcuts of the commands, with the user's overrides
+    keybindings: Keybindings,
+    /// First half of a chord such as Ctrl+K Ctrl+S, waiting for the second
+    pending_chord: Option<egui::KeyboardShortcut>,
+    /// The Emacs mark is set: motions extend the selection
+    emacs_mark: bool,
+    /// Width of the window this frame, which decides whether the sidebar and
+    /// minimap are docked
+    window_width: f32,
+    /// Sidebar and minimap shown over the editor while the window is too
+    /// narrow to dock them
+    sidebar_overlay: bool,
+    minimap_overlay: bool,
+    split: Option<EditorSplit>,
+    /// Screen rect of the other editor group in the last frame
+    split_rect: egui::Rect,
+    /// Pointer movement of the tab being dragged
+    tab_drag: DragGesture,
+    show_shortcuts: bool,
+    show_about: bool,
+    preview: PreviewState,
+    rest: RestState,
+    sql: SqlState,
+    log_viewer: Option<LogViewerState>,
+    folder_compare: Option<FolderCompareState>,
+    regex_playground: Option<RegexPlaygroundState>,
+    /// Dependency graph view, shown in place of the editor while open
+    dependency_graph: Option<GraphViewport>,
+    new_project: Option<NewProjectState>,
+    rename_file: Option<RenameFileState>,
+    /// Shared editing session hosted or joined
+    collab: Option<collab::Session>,
+    join_session: Option<JoinSessionState>,
+    share_session: Option<ShareSessionState>,
+    commit: Option<CommitState>,
+    /// git's `commit.gpgsign` in the workspace's repository, what Sign
+    /// Commits shows until it is changed
+    git_signs_commits: bool,
+    module_rename: Option<ModuleRenameState>,
+    exit_prompt: Option<ExitPrompt>,
+    replace_confirm: Option<ReplaceAllConfirm>,
+    replace_undo: Option<ReplaceAllUndo>,
+    /// Shut down and waiting for the window to close
+    exiting: bool,
+    env_editor: Option<EnvEditorState>,
+    keybindings_editor: Option<KeybindingsEditorState>,
+    cargo: CargoState,
+    forge: ForgeState,
+    paste_format: Option<PasteFormatOffer>,
+    tag_rename: Option<TagRenameOffer>,
+    /// Serves the workspace (Serve Workspace Folder) and HTML previews
+    server: Option<StaticServer>,
+    /// Running language servers by file extension
+    language_servers: HashMap<String, LspClient>,
+    /// Extensions whose language server failed to start
+    failed_language_servers: HashSet<String>,
+    /// Messages shown in the Output panel
+    output: Vec<String>,
+    /// Screen reader announcements of background events
+    announcer: Announcer,
+    /// Bottom panels moved to windows of their own, with where to open the
+    /// window until it has opened
+    floating_panels: HashMap<FloatingPanel, Option<Pos2>>,
+    settings: Settings,
+    /// Why settings.toml failed to load; it isn't overwritten while set
+    settings_error: Option<String>,
+    /// Panel sizes and visible views, kept in `layout.toml`
+    layout: Layout,
+    /// Layout as last written, so only changes are saved
+    saved_layout: Layout,
+    /// Overrides from the open folder's `.rust_code_editor.toml`
+    workspace_settings: WorkspaceSettings,
+    /// Environment variables for the processes started for the workspace
+    workspace_env: BTreeMap<String, String>,
+    /// Scale factor of the previous frame
+    pixels_per_point: f32,
+    watchdog: Option<Watchdog>,
+    /// When open files were last checked for having been deleted
+    last_disk_check: Instant,
+    /// File id and range of the selection last made the primary selection
+    primary_selection: Option<(u64, Range<usize>)>,
+}
+
+impl Default for EditorApp {
+    fn default() -> Self {
+        Self {
+            workspace: None,
+            tree: vec![],
+            open_files: vec![],
+            active_tab: 0,
+            highlighter: BackgroundHighlighter::default(),
+            active_activity: ActivityItem::Explorer,
+            editor_scroll_offset: Vec2::ZERO,
+            restore_scroll: None,
+            closed_tabs: Vec::new(),
+            active_file: None,
+            previous_file: None,
+            find_replace: FindReplaceState::default(),
+            search_debounce: Debouncer::default(),
+            bracket_debounce: Debouncer::default(),
+            goto_line: GotoLineState::default(),
+            completion: CompletionState::default(),
+            hover: HoverState::default(),
+            occurrences: OccurrenceState::default(),
+            pending_definition: None,
+            references: ReferencesState::default(),
+            symbol_search: SymbolSearchState::default(),
+            navigation: NavigationHistory::default(),
+            bookmarks_panel: false,
+            command_palette: PickerState::default(),
+            run_task: PickerState::default(),
+            sessions: Sessions::default(),
+            current_session: None,
+            session_saved_at: Instant::now(),
+            session_name: None,
+            session_picker: PickerState::default(),
+            encoding_picker: EncodingPickerState::default(),
+            quick_open: QuickOpenState::default(),
+            clipboard_history: VecDeque::new(),
+            clipboard_picker: PickerState::default(),
+            editor_events: Vec::new(),
+            macros: MacroState::default(),
+            keybindings: Keybindings::default(),
+            pending_chord: None,
+            emacs_mark: false,
+            window_width: f32::INFINITY,
+            sidebar_overlay: false,
+            minimap_overlay: false,
+            split: None,
+            split_rect: egui::Rect::NOTHING,
+            tab_drag: DragGesture::default(),
+            show_shortcuts: false,
+            show_about: false,
+            preview: PreviewState::default(),
+            rest: RestState::default(),
+            sql: SqlState::default(),
+            log_viewer: None,
+            folder_compare: None,
+            regex_playground: None,
+            dependency_graph: None,
+            new_project: None,
+            rename_file: None,
+            collab: None,
+            join_session: None,
+            share_session: None,
+            commit: None,
+            git_signs_commits: false,
+            module_rename: None,
+            exit_prompt: None,
+            replace_confirm: None,
+            replace_undo: None,
+            exiting: false,
+            env_editor: None,
+            keybindings_editor: None,
+            cargo: CargoState::default(),
+            forge: ForgeState::default(),
+            paste_format: None,
+            tag_rename: None,
+            server: None,
+            language_servers: HashMap::new(),
+            failed_language_servers: HashSet::new(),
+            output: Vec::new(),
+            announcer: Announcer::default(),
+            floating_panels: HashMap::new(),
+            settings: Settings::default(),
+            settings_error: None,
+            layout: Layout::default(),
+            saved_layout: Layout::default(),
+            workspace_settings: WorkspaceSettings::default(),
+            workspace_env: BTreeMap::new(),
+            pixels_per_point: 1.0,
+            watchdog: None,
+            last_disk_check: Instant::now(),
+            primary_selection: None,
+        }
+    }
+}
+
+impl eframe::App for EditorApp {
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        self.feed_watchdog();
+
+        // In auto mode the style follows the OS preference, which eframe keeps up to date
+        let dark_mode = match self.settings.theme {
+            ThemeMode::Dark => true,
+            ThemeMode::Light => false,
+            ThemeMode::Auto => frame.info().system_theme != Some(eframe::Theme::Light),
+        };
+        ctx.set_style(create_vscode_style(dark_mode));
+        self.apply_text_rendering(ctx);
+
+        // Handle keyboard shortcuts
+        self.handle_keyboard_shortcuts(ctx);
+        self.handle_close_request(ctx);
+        self.sync_language_servers(ctx);
+        self.follow_files(ctx);
+        self.check_missing_files(ctx);
+        self.poll_cargo_run();
+        self.poll_forge();
+        self.sync_collaboration();
+
+        if self.settings.custom_title_bar {
+            self.render_title_bar(ctx);
+        } else {
+            self.render_menu_bar(ctx);
+        }
+        self.window_width = ctx.screen_rect().width();
+        // Panels along the edges have to be added before the central editor panel
+        self.render_status_bar(ctx);
+        self.render_activity_bar(ctx);
+        self.render_sidebar(ctx);
+        if self.layout.show_output {
+            self.render_output_panel(ctx);
+        }
+        if self.sql.is_open {
+            self.render_query_results(ctx);
+        }
+        if self.references.is_open {
+            self.render_references_panel(ctx);
+        }
+        if self.bookmarks_panel {
+            self.render_bookmarks_panel(ctx);
+        }
+        self.render_editor(ctx);
+        if self.completion.is_open && !self.completion.items.is_empty() {
+            self.render_completion_popup(ctx);
+        }
+
+        // Render find/replace panel on top if open
+        if self.find_replace.is_open {
+            self.render_find_replace_panel(ctx);
+        }
+        if self.goto_line.is_open {
+            self.render_goto_line_panel(ctx);
+        }
+        if self.macros.repeat.is_some() {
+            self.render_macro_repeat(ctx);
+        }
+        if self.new_project.is_some() {
+            self.render_new_project_dialog(ctx);
+        }
+        if self.rename_file.is_some() {
+            self.render_rename_dialog(ctx);
+        }
+        if self.join_session.is_some() {
+            self.render_join_session(ctx);
+        }
+        if self.share_session.is_some() {
+            self.render_share_session(ctx);
+        }
+        if self
+            .collab
+            .as_ref()
+            .is_some_and(|s| !s.join_requests().is_empty())
+        {
+            self.render_join_request(ctx);
+        }
+        if self.module_rename.is_some() {
+            self.render_module_rename(ctx);
+        }
+        if self.env_editor.is_some() {
+            self.render_env_editor(ctx);
+        }
+        if self.keybindings_editor.is_some() {
+            self.render_keybindings_editor(ctx);
+        }
+        if self.symbol_search.is_open {
+            self.render_symbol_search(ctx);
+        }
+        if self.quick_open.picker.is_open {
+            self.render_quick_open(ctx);
+        }
+        if self.command_palette.is_open {
+            self.render_command_palette(ctx);
+        }
+        if self.run_task.is_open {
+            self.render_run_task(ctx);
+        }
+        if self.commit.is_some() {
+            self.render_commit(ctx);
+        }
+        if self.forge.open.is_some() {
+            self.render_forge_item(ctx);
+        }
+        if self.session_name.is_some() {
+            self.render_save_session(ctx);
+        }
+        if self.session_picker.is_open {
+            self.render_session_picker(ctx);
+        }
+        if self.encoding_picker.picker.is_open {
+            self.render_encoding_picker(ctx);
+        }
+        if self.clipboard_picker.is_open {
+            self.render_clipboard_picker(ctx);
+        }
+        if self.show_shortcuts {
+            self.render_keyboard_shortcuts(ctx);
+        }
+        if self.show_about {
+            self.render_about(ctx);
+        }
+        if self.exit_prompt.is_some() {
+            self.render_exit_prompt(ctx);
+        }
+        if self.replace_confirm.is_some() {
+            self.render_replace_all_confirm(ctx);
+        }
+        if self.replace_undo.is_some() {
+            self.render_replace_all_undo(ctx);
+        }
+        if self.paste_format.is_some() {
+            self.render_paste_format_offer(ctx);
+        }
+        if self.tag_rename.is_some() {
+            self.render_tag_rename_offer(ctx);
+        }
+        self.announcer.show(ctx);
+        self.record_clipboard(ctx);
+        self.save_layout(ctx);
+        self.autosave_session(ctx);
+        self.track_active_file();
+        if let Some(watchdog) = &self.watchdog {
+            watchdog.frame_finished();
+        }
+    }
+}
+
+impl EditorApp {
+    pub fn new(settings: Settings, settings_error: Option<String>) -> Self {
+        let watchdog = settings.watchdog.enabled.then(|| {
+            Watchdog::spawn(
+                Duration::from_secs(settings.watchdog.timeout_secs.max(1)),
+                settings.watchdog.save_recovery,
+            )
+        });
+        let layout = Layout::load();
+        let mut app = Self {
+            settings,
+            settings_error,
+            watchdog,
+            floating_panels: floating_panels(&layout),
+            saved_layout: layout.clone(),
+            layout,
+            sessions: Sessions::load(),
+            ..Default::default()
+        };
+        if let Some(error) = app.settings_error.clone() {
+            app.log_output(format!(
+                "{error}\nUsing the default settings; changes won't be saved until the file \
+                 is fixed and the editor restarted"
+            ));
+        }
+        for error in contributions::load() {
+            app.log_output(error);
+        }
+        let (keybindings, errors) = Keybindings::load();
+        app.keybindings = keybindings;
+        for error in errors {
+            app.log_output(error);
+        }
+        app
+    }
+
+    /// Report progress to the watchdog and hand it the unsaved buffers it
+    /// should rescue if the UI thread hangs
+    fn feed_watchdog(&self) {
+        let Some(watchdog) = &self.watchdog else {
+            return;
+        };
+        watchdog.frame_started();
+        watchdog.set_recovery_data(
+            self.open_files
+                .iter()
+                .filter(|f| f.is_modified())
+                .map(|f| (f.path.clone(), f.buffer.clone()))
+                .collect(),
+        );
+    }
+
+    /// Tell screen readers about an event, with a sound when sound cues are on
+    fn announce(&mut self, message: impl Into<String>, cue: Option<Cue>) {
+        self.announcer.announce(message);
+        if let Some(cue) = cue.filter(|_| self.settings.sound_cues) {
+            announcer::play(cue);
+        }
+    }
+
+    /// Note what the UI thread is about to do, for the watchdog's hang report
+    fn set_activity(&self, activity: impl Into<Cow<'static, str>>) {
+        if let Some(watchdog) = &self.watchdog {
+            watchdog.set_activity(activity);
+        }
+    }
+
+    /// Show a native dialog, which blocks the UI thread without that being a hang
+    fn blocking_dialog<R>(&self, dialog: impl FnOnce() -> R) -> R {
+        match &self.watchdog {
+            Some(watchdog) => watchdog.suspended(dialog),
+            None => dialog(),
+        }
+    }
+
+    /// Note the panel sizes of this frame and write the layout when it
+    /// changed, once no drag is in progress
+    fn save_layout(&mut self, ctx: &egui::Context) {
+        let size = |id: &str| PanelState::load(ctx, egui::Id::new(id)).map(|s| s.rect.size());
+        if let Some(size) = size("explorer") {
+            self.layout.sidebar_width = size.x;
+        }
+        if let Some(size) = size("outline") {
+            self.layout.outline_height = size.y;
+        }
+        if let Some(size) = size("output_panel") {
+            self.layout.output_height = size.y;
+        }
+        if let Some(size) = size("references_panel") {
+            self.layout.references_height = size.y;
+        }
+        self.layout.floating_panels = FloatingPanel::ALL
+            .into_iter()
+            .filter(|panel| self.floating_panels.contains_key(panel))
+            .map(|panel| panel.title().to_string())
+            .collect();
+
+        if !self.settings.save_layout
+            || self.layout == self.saved_layout
+            || ctx.input(|i| i.pointer.any_down())
+        {
+            return;
+        }
+        if let Err(e) = self.layout.save() {
+            self.log_output(format!("Could not save the layout: {e}"));
+        }
+        self.saved_layout = self.layout.clone();
+    }
+
+    /// Put panel sizes and visible views back to their defaults
+    fn reset_layout(&mut self, ctx: &egui::Context) {
+        self.apply_layout(ctx, Layout::default());
+    }
+
+    /// Resize the panels and show the views of `layout`
+    fn apply_layout(&mut self, ctx: &egui::Context, layout: Layout) {
+        // Panels only take their default size when they have no saved state
+        for id in ["explorer", "outline", "output_panel", "references_panel"] {
+            ctx.data_mut(|d| d.remove::<PanelState>(egui::Id::new(id)));
+        }
+        self.floating_panels = floating_panels(&layout);
+        self.layout = layout;
+    }
+
+    /// Apply rendering settings and react to scale factor changes (e.g. the
+    /// window moving to a monitor with a different DPI)
+    fn apply_text_rendering(&mut self, ctx: &egui::Context) {
+        let text_rendering = &self.settings.text_rendering;
+        ctx.tessellation_options_mut(|options| {
+            options.feathering = text_rendering.anti_aliasing;
+            options.round_text_to_pixels = text_rendering.round_text_to_pixels;
+        });
+
+        // The first frame at a new scale is laid out with the old font atlas and
+        // pixel grid, so paint another one right away
+        let pixels_per_point = ctx.pixels_per_point();
+        if pixels_per_point != self.pixels_per_point {
+            self.pixels_per_point = pixels_per_point;
+            ctx.request_repaint();
+        }
+    }
+
+    // === Keyboard Shortcuts ===
+
+    fn handle_keyboard_shortcuts(&mut self, ctx: &egui::Context) {
+        use egui::{Key, Modifiers};
+
+        // A shortcut being recorded in the keybindings editor takes every key
+        if self
+            .keybindings_editor
+            .as_ref()
+            .is_some_and(|editor| editor.recording.is_some())
+        {
+            self.record_keybinding(ctx);
+            return;
+        }
+
+        // Up/Down/Enter/Tab/Escape - Navigate the autocomplete popup
+        // (consumed so the text editor doesn't also handle them)
+        if self.completion.is_open && !self.completion.items.is_empty() {
+            let (up, down, accept, dismiss) = ctx.input_mut(|i| {
+                (
+                    i.consume_key(Modifiers::NONE, Key::ArrowUp),
+                    i.consume_key(Modifiers::NONE, Key::ArrowDown),
+                    i.consume_key(Modifiers::NONE, Key::Enter)
+                        || i.consume_key(Modifiers::NONE, Key::Tab),
+                    i.consume_key(Modifiers::NONE, Key::Escape),
+                )
+            });
+            let count = self.completion.items.len();
+            if up {
+                self.completion.selected = (self.completion.selected + count - 1) % count;
+            } else if down {
+                self.completion.selected = (self.completion.selected + 1) % count;
+            } else if accept {
+                self.accept_completion(self.completion.selected);
+            } else if dismiss {
+                self.completion.close();
+            }
+        }
+
+        // Tab - Expand an Emmet abbreviation before the caret
+        let editor_focused = ctx.memory(|m| m.has_focus(egui::Id::new(CODE_EDITOR_ID)));
+        if editor_focused && self.settings.keymap == Keymap::Emacs {
+            self.translate_emacs_keys(ctx);
+        }
+        if editor_focused && ctx.input(|i| i.key_pressed(Key::Tab) && i.modifiers.is_none()) {
+            let expanded = self
+                .open_files
+                .get_mut(self.active_tab)
+                .is_some_and(OpenFile::expand_emmet);
+            if expanded {
+                ctx.input_mut(|i| i.consume_key(Modifiers::NONE, Key::Tab));
+            }
+        }
+
+        // Shortcuts of the commands in the menus. Alt shortcuts can also arrive
+        // as typed text, which the editor must not insert. The first key of a
+        // chord waits for the second one in the next frames.
+        let pending_chord = self.pending_chord;
+        let mut chord_handled = false;
+        for (command, binding) in self.keybindings.bindings() {
+            if !self.command_enabled(command) {
+                continue;
+            }
+            let shortcut = match (pending_chord, binding.second) {
+                (Some(first), Some(second)) if first == binding.first => second,
+                (Some(_), _) => continue,
+                (None, _) => binding.first,
+            };
+            let pressed = ctx.input_mut(|i| {
+                let pressed = keybindings::consume_shortcut(i, &shortcut);
+                if pressed && shortcut.modifiers.alt {
+                    i.events.retain(|e| !matches!(e, egui::Event::Text(_)));
+                }
+                pressed
+            });
+            if !pressed {
+                continue;
+            }
+            chord_handled = true;
+            if pending_chord.is_none() && binding.second.is_some() {
+                self.pending_chord = Some(binding.first);
+            } else {
+                self.pending_chord = None;
+                self.run_command(ctx, command);
+            }
+        }
+        // Any other key ends a chord, and is swallowed
+        if pending_chord.is_some() && !chord_handled {
+            let key_pressed = ctx.input(|i| {
+                i.events
+                    .iter()
+                    .any(|e| matches!(e, egui::Event::Key { pressed: true, .. }))
+            });
+            if key_pressed {
+                self.pending_chord = None;
+                ctx.input_mut(|i| {
+                    i.events.retain(|e| {
+                        !matches!(
+                            e,
+                            egui::Event::Key { pressed: true, .. } | egui::Event::Text(_)
+                        )
+                    })
+                });
+            }
+        }
+
+        // The editor's own shortcuts of editor commands bound to other keys
+        for command in self.keybindings.rebound_editor_commands() {
+            ctx.input_mut(|i| match command {
+                Command::Cut => i.events.retain(|e| !matches!(e, egui::Event::Cut)),
+                Command::Copy => i.events.retain(|e| !matches!(e, egui::Event::Copy)),
+                Command::Paste => i.events.retain(|e| !matches!(e, egui::Event::Paste(_))),
+                _ => {
+                    if let Some(shortcut) = command.shortcut() {
+                        i.consume_shortcut(&shortcut);
+                    }
+                }
+            });
+        }
+
+        // Escape - Collapse multiple cursors, otherwise close Find panel
+        if ctx.input(|i| i.key_pressed(Key::Escape)) {
+            match self.open_files.get_mut(self.active_tab) {
+                Some(file) if file.state.has_multiple_cursors() => {
+                    file.state.clear_secondary_cursors();
+                }
+                _ => {
+                    self.find_replace.is_open = false;
+                    self.goto_line.is_open = false;
+                    self.macros.repeat = None;
+                    self.sidebar_overlay = false;
+                    self.minimap_overlay = false;
+                    self.symbol_search.is_open = false;
+                    self.paste_format = None;
+                    self.tag_rename = None;
+                }
+            }
+        }
+
+        // Input the editor gets this frame goes into a macro being recorded
+        if self.macros.recording && ctx.memory(|m| m.has_focus(egui::Id::new(CODE_EDITOR_ID))) {
+            let input: Vec<MacroStep> = ctx.input(|i| {
+                i.events
+                    .iter()
+                    .filter(|e| {
+                        matches!(
+                            e,
+                            egui::Event::Text(_)
+                                | egui::Event::Paste(_)
+                                | egui::Event::Copy
+                                | egui::Event::Cut
+                                | egui::Event::Key { pressed: true, .. }
+                        )
+                    })
+                    .cloned()
+                    .map(MacroStep::Input)
+                    .collect()
+            });
+            self.macros.steps.extend(input);
+        }
+        self.play_macro_steps(ctx);
+
+        // Editor commands picked from a menu or the palette since the last frame
+        if !self.editor_events.is_empty() {
+            let events = std::mem::take(&mut self.editor_events);
+            ctx.input_mut(|i| i.events.extend(events));
+            ctx.memory_mut(|m| m.request_focus(egui::Id::new(CODE_EDITOR_ID)));
+        }
+    }
+
+    // === Find/Replace Panel ===
+
+    fn render_find_replace_panel(&mut self, ctx: &egui::Context) {
+        let colors = palette(&ctx.style().visuals);
+        egui::Area::new(egui::Id::new("find_replace_panel"))
+            .anchor(egui::Align2::RIGHT_TOP, Vec2::new(-20.0, 50.0))
+            .show(ctx, |ui| {
+                Frame::none()
+                    .fill(colors.find_panel_bg)
+                    .inner_margin(Margin::same(8.0))
+                    .rounding(4.0)
+                    .shadow(egui::epaint::Shadow {
+                        extrusion: 8.0,
+                        color: Color32::from_black_alpha(100),
+                    })
+                    .show(ui, |ui| {
+                        ui.set_min_width(320.0);
+
+                        // Find row
+                        ui.horizontal(|ui| {
+                            ui.label("Find:");
+                            let find_response = ui.add(
+                                TextEdit::singleline(&mut self.find_replace.search_text)
+                                    .desired_width(200.0)
+                                    .hint_text("Search..."),
+                            );
+
+                            // Auto-search when text changes
+                            if find_response.changed() {
+                                self.perform_search();
+                                self.announce_matches();
+                            }
+
+                            // Request focus on first open
+                            if !self.find_replace.search_text.is_empty()
+                                || find_response.gained_focus()
+                            {
+                                find_response.request_focus();
+                            }
+
+                            // Navigation buttons
+                            if ui
+                                .button("▲")
+                                .on_hover_text("Previous (Shift+Enter)")
+                                .clicked()
+                            {
+                                self.find_previous();
+                            }
+                            if ui.button("▼").on_hover_text("Next (Enter)").clicked() {
+                                self.find_next();
+                            }
+
+                            // Match count
+                            let match_count = self.find_replace.matches.len();
+                            if match_count > 0 {
+                                let current = self.find_replace.current_match + 1;
+                                ui.label(format!("{}/{}", current, match_count));
+                                if let Some(in_view) = self.matches_in_view() {
+                                    ui.weak(in_view)
+                                        .on_hover_text("Matches in the visible part of the file");
+                                }
+                            } else if !self.find_replace.search_text.is_empty() {
+                                ui.label("No results");
+                            }
+                            let (others, files) = self.find_replace.other_matches;
+                            if others > 0 {
+                                ui.weak(format!("+{others} in {files} other"))
+                                    .on_hover_text("Matches in the other open files");
+                            }
+
+                            // Close button
+                            if ui.button("✕").clicked() {
+                                self.find_replace.is_open = false;
+                            }
+                        });
+
+                        // Replace row (if enabled)
+                        if self.find_replace.show_replace {
+                            ui.add_space(4.0);
+                            ui.horizontal(|ui| {
+                                ui.label("Replace:");
+                                ui.add(
+                                    TextEdit::singleline(&mut self.find_replace.replace_text)
+                                        .desired_width(200.0)
+                                        .hint_text("Replace with..."),
+                                );
+
+                                if ui
+                                    .button("Replace")
+                                    .on_hover_text("Replace current")
+                                    .clicked()
+                                {
+                                    self.replace_current();
+                                }
+                                if ui.button("All").on_hover_text("Replace all").clicked() {
+                                    self.replace_all();
+                                }
+                            });
+
+                            ui.add_space(4.0);
+                            ui.horizontal(|ui| {
+                                ui.label("In:");
+                                let mut changed = false;
+                                for scope in FindScope::ALL {
+                                    let enabled =
+                                        scope != FindScope::Workspace || self.workspace.is_some();
+                                    changed |= ui
+                                        .add_enabled_ui(enabled, |ui| {
+                                            ui.selectable_value(
+                                                &mut self.find_replace.scope,
+                                                scope,
+                                                scope.label(),
+                                            )
+                                        })
+                                        .inner
+                                        .changed();
+                                }
+                                if changed {
+                                    self.perform_search();
+                                }
+                            });
+                        }
+
+                        // Options row
+                        ui.add_space(4.0);
+                        ui.horizontal(|ui| {
+                            if ui
+                                .checkbox(&mut self.find_replace.case_sensitive, "Match case")
+                                .changed()
+                            {
+                                self.perform_search();
+                            }
+                            ui.checkbox(&mut self.find_replace.show_replace, "Replace");
+                        });
+                    });
+            });
+    }
+
+    // === Go to Line Panel ===
+
+    fn render_goto_line_panel(&mut self, ctx: &egui::Context) {
+        let colors = palette(&ctx.style().visuals);
+        let position = self.open_files.get(self.active_tab).map(|file| {
+            let cursor = file.state.cursor();
+            format!(
+                "Current line {}, column {}. Type a line between 1 and {}",
+                cursor.line,
+                cursor.column,
+                file.buffer.len_lines()
+            )
+        });
+        egui::Area::new(egui::Id::new("goto_line_panel"))
+            .anchor(egui::Align2::CENTER_TOP, Vec2::new(0.0, 50.0))
+            .show(ctx, |ui| {
+                Frame::none()
+                    .fill(colors.find_panel_bg)
+                    .inner_margin(Margin::same(8.0))
+                    .rounding(4.0)
+                    .shadow(egui::epaint::Shadow {
+                        extrusion: 8.0,
+                        color: Color32::from_black_alpha(100),
+                    })
+                    .show(ui, |ui| {
+                        ui.set_min_width(320.0);
+
+                        let response = ui.add(
+                            TextEdit::singleline(&mut self.goto_line.input)
+                                .desired_width(300.0)
+                                .hint_text("Line[:column], :o char offset, :b byte offset"),
+                        );
+                        response.request_focus();
+
+                        if self.goto_line.invalid {
+                            ui.label("Enter a line number or an offset");
+                        } else if let Some(position) = &position {
+                            ui.label(
+                                RichText::new(position)
+                                    .size(fonts::SMALL)
+                                    .color(colors.text_muted),
+                            );
+                        }
+
+                        if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                            match GotoTarget::parse(&self.goto_line.input) {
+                                Some(target) => {
+                                    self.goto(target);
+                                    self.goto_line.is_open = false;
+                                    ui.memory_mut(|m| {
+                                        m.request_focus(egui::Id::new(CODE_EDITOR_ID))
+                                    });
+                                }
+                                None => self.goto_line.invalid = true,
+                            }
+                        }
+                    });
+            });
+    }
+
+    // === Macros ===
+
+    fn toggle_macro_recording(&mut self) {
+        if self.macros.recording {
+            self.macros.recording = false;
+            let message = match self.macros.steps.len() {
+                0 => "Macro recording stopped; nothing was recorded".to_string(),
+                1 => "Macro recorded: 1 step".to_string(),
+                n => format!("Macro recorded: {n} steps"),
+            };
+            self.announce(message, Some(Cue::Success));
+        } else {
+            self.macros.recording = true;
+            self.macros.steps.clear();
+            self.announce("Recording macro", None);
+        }
+    }
+
+    /// Ask how many times to play the macro, then queue it that many times
+    fn render_macro_repeat(&mut self, ctx: &egui::Context) {
+        let colors = palette(&ctx.style().visuals);
+        let Some(input) = &mut self.macros.repeat else {
+            return;
+        };
+        let mut play = None;
+        egui::Area::new(egui::Id::new("macro_repeat_panel"))
+            .anchor(egui::Align2::CENTER_TOP, Vec2::new(0.0, 50.0))
+            .show(ctx, |ui| {
+                Frame::none()
+                    .fill(colors.find_panel_bg)
+                    .inner_margin(Margin::same(8.0))
+                    .rounding(4.0)
+                    .shadow(egui::epaint::Shadow {
+                        extrusion: 8.0,
+                        color: Color32::from_black_alpha(100),
+                    })
+                    .show(ui, |ui| {
+                        ui.set_min_width(320.0);
+                        let response = ui.add(
+                            TextEdit::singleline(input)
+                                .desired_width(300.0)
+                                .hint_text("Times to play the macro"),
+                        );
+                        response.request_focus();
+                        let times = input.trim().parse::<usize>().ok().filter(|&n| n > 0);
+                        if times.is_none() {
+                            ui.label("Enter a number of times, 1 or more");
+                        }
+                        if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                            play = times;
+                        }
+                    });
+            });
+
+        if let Some(times) = play {
+            self.macros.repeat = None;
+            for _ in 0..times {
+                self.macros
+                    .playing
+                    .extend(self.macros.steps.iter().cloned());
+            }
+            ctx.memory_mut(|m| m.request_focus(egui::Id::new(CODE_EDITOR_ID)));
+            ctx.request_repaint();
+        }
+    }
+
+    /// Hand the next steps of a playing macro to the editor, running commands
+    /// once the input before them has been handled
+    fn play_macro_steps(&mut self, ctx: &egui::Context) {
+        while let Some(step) = self.macros.playing.front() {
+            match step {
+                MacroStep::Command(_) if !self.editor_events.is_empty() => break,
+                MacroStep::Command(command) => {
+                    let command = *command;
+                    self.macros.playing.pop_front();
+                    if self.command_enabled(command) {
+                        self.run_command(ctx, command);
+                    }
+                }
+                MacroStep::Input(event) => {
+                    self.editor_events.push(event.clone());
+                    self.macros.playing.pop_front();
+                }
+            }
+        }
+        if !self.macros.playing.is_empty() {
+            ctx.request_repaint();
+        }
+    }
+
+    /// Move the caret of the active tab to a Go to Line target
+    fn goto(&mut self, target: GotoTarget) {
+        self.record_jump();
+        self.place_caret(target);
+    }
+
+    /// Move the caret of the active tab without remembering where it was
+    fn place_caret(&mut self, target: GotoTarget) {
+        if let Some(file) = self.open_files.get_mut(self.active_tab) {
+            let offset = target.resolve(&file.buffer);
+            file.state.clear_secondary_cursors();
+            file.state
+                .set_cursor(CursorPosition::from_char_offset(&file.buffer, offset));
+            file.state.scroll_to_cursor = true;
+            // A line out of view is shown in the middle rather than at an edge
+            let line = file.buffer.char_to_line(offset) + 1;
+            let (first, last) = file.state.visible_lines;
+            file.state.center_on_cursor = !(first..last).contains(&line);
+            file.state.history.break_group();
+        }
+    }
+
+    /// Move to the next or previous function or top-level item of the
+    /// outline, and flash its line
+    fn goto_adjacent_item(&mut self, forward: bool) {
+        let Some(file) = self.open_files.get(self.active_tab) else {
+            return;
+        };
+        let symbols = file.lsp_symbols.as_deref().unwrap_or(&file.symbols);
+        let line = file.state.cursor().line.saturating_sub(1);
+        let Some(index) = outline::adjacent_item(symbols, line, forward) else {
+            return;
+        };
+        let (line, column) = (symbols[index].line, symbols[index].column);
+        self.goto(GotoTarget::Line {
+            line: line + 1,
+            column: Some(column + 1),
+        });
+        let file = &mut self.open_files[self.active_tab];
+        let text = file.buffer.line(line).to_string();
+        let start = file.buffer.line_to_char(line);
+        let end = start + text.trim_end_matches(['\n', '\r']).chars().count();
+        file.flash = Some((start..end, Instant::now()));
+    }
+
+    // === Bookmarks ===
+
+    /// Bookmark the line of the primary cursor, or remove its bookmark
+    fn toggle_bookmark(&mut self) {
+        let Some(file) = self.open_files.get_mut(self.active_tab) else {
+            return;
+        };
+        let line_idx = file.state.cursor().line.saturating_sub(1);
+        let revision = file.state.revision;
+        file.state
+            .bookmarks
+            .toggle(&file.buffer, revision, line_idx);
+    }
+
+    /// Tab and zero-based line of each bookmark, in tab order
+    fn bookmarks(&mut self) -> Vec<(usize, usize)> {
+        let mut bookmarks = Vec::new();
+        for (tab, file) in self.open_files.iter_mut().enumerate() {
+            let revision = file.state.revision;
+            file.state.bookmarks.update(&file.buffer, revision);
+            let lines = file.state.bookmarks.lines();
+            bookmarks.extend(lines.into_iter().map(|line_idx| (tab, line_idx)));
+        }
+        bookmarks
+    }
+
+    fn has_bookmarks(&self) -> bool {
+        self.open_files
+            .iter()
+            .any(|file| !file.state.bookmarks.is_empty())
+    }
+
+    /// Move to the bookmark after or before the cursor, going on into the
+    /// other open files and around
+    fn goto_bookmark(&mut self, forward: bool) {
+        let bookmarks = self.bookmarks();
+        let Some(file) = self.open_files.get(self.active_tab) else {
+            return;
+        };
+        let here = (self.active_tab, file.state.cursor().line.saturating_sub(1));
+        let target = if forward {
+            bookmarks.iter().find(|&&b| b > here).or(bookmarks.first())
+        } else {
+            bookmarks
+                .iter()
+                .rev()
+                .find(|&&b| b < here)
+                .or(bookmarks.last())
+        };
+        if let Some(&(tab, line_idx)) = target {
+            self.goto_bookmark_at(tab, line_idx);
+        }
+    }
+
+    fn goto_bookmark_at(&mut self, tab: usize, line_idx: usize) {
+        self.record_jump();
+        self.activate_tab(tab);
+        self.place_caret(GotoTarget::Line {
+            line: line_idx + 1,
+            column: None,
+        });
+    }
+
+    fn render_bookmarks_panel(&mut self, ctx: &egui::Context) {
+        let colors = palette(&ctx.style().visuals);
+        let bookmarks = self.bookmarks();
+        let items: Vec<Bookmark> = bookmarks
+            .iter()
+            .map(|&(tab, line_idx)| {
+                let file = &self.open_files[tab];
+                Bookmark {
+                    path: file.path.clone(),
+                    line: line_idx,
+                    preview: file.buffer.line(line_idx).to_string().trim().to_string(),
+                }
+            })
+            .collect();
+        let response = egui::TopBottomPanel::bottom("bookmarks_panel")
+            .resizable(true)
+            .default_height(self.layout.references_height)
+            .frame(
+                Frame::none()
+                    .fill(colors.panel_bg)
+                    .inner_margin(Margin::symmetric(8.0, 4.0)),
+            )
+            .show(ctx, |ui| BookmarksPanel::new(&items).show(ui))
+            .inner;
+
+        if let Some(index) = response.clicked {
+            let (tab, line_idx) = bookmarks[index];
+            self.goto_bookmark_at(tab, line_idx);
+            ctx.memory_mut(|m| m.request_focus(egui::Id::new(CODE_EDITOR_ID)));
+        }
+        if let Some(index) = response.removed {
+            let (tab, line_idx) = bookmarks[index];
+            let file = &mut self.open_files[tab];
+            let revision = file.state.revision;
+            file.state
+                .bookmarks
+                .toggle(&file.buffer, revision, line_idx);
+        }
+        if response.close_clicked {
+            self.bookmarks_panel = false;
+        }
+    }
+
+    /// Open the most recently closed tab again, with its cursor and scroll
+    /// position
+    fn reopen_closed_tab(&mut self) {
+        let Some(closed) = self.closed_tabs.pop() else {
+            return;
+        };
+        self.open_file(closed.path.clone());
+        let Some(file) = self.open_files.get_mut(self.active_tab) else {
+            return;
+        };
+        if file.path != closed.path {
+            return;
+        }
+        let offset = closed.offset.min(file.buffer.len_chars());
+        file.state
+            .set_cursor(CursorPosition::from_char_offset(&file.buffer, offset));
+        match closed.scroll {
+            Some(scroll) => self.restore_scroll = Some(scroll),
+            None => file.state.scroll_to_cursor = true,
+        }
+    }
+
+    /// Note when another file becomes the active tab, keeping the one it
+    /// replaced to go back to, unless that one was closed
+    fn track_active_file(&mut self) {
+        let active = self.open_files.get(self.active_tab).map(|f| f.id);
+        if active == self.active_file {
+            return;
+        }
+        if self
+            .open_files
+            .iter()
+            .any(|f| Some(f.id) == self.active_file)
+        {
+            self.previous_file = self.active_file;
+        }
+        self.active_file = active;
+    }
+
+    /// Tab of the file that was active before the current one
+    fn previous_tab(&self) -> Option<usize> {
+        let previous = self.previous_file?;
+        self.open_files
+            .iter()
+            .position(|f| f.id == previous)
+            .filter(|&idx| idx != self.active_tab)
+    }
+
+    /// Where the cursor of the active tab is
+    fn current_location(&self) -> Option<Location> {
+        let file = self.open_files.get(self.active_tab)?;
+        Some(Location {
+            path: file.path.clone(),
+            offset: file.state.cursor().offset,
+        })
+    }
+
+    /// Remember the cursor before a jump so Go Back returns to it
+    fn record_jump(&mut self) {
+        if let Some(location) = self.current_location() {
+            self.navigation.push(location);
+        }
+    }
+
+    /// Go Back or Go Forward to where the cursor jumped from, opening the
+    /// file again if its tab was closed
+    fn navigate(&mut self, back: bool) {
+        let Some(current) = self.current_location() else {
+            return;
+        };
+        let target = if back {
+            self.navigation.back(current)
+        } else {
+            self.navigation.forward(current)
+        };
+        let Some(target) = target else {
+            return;
+        };
+        self.open_file(target.path.clone());
+        let Some(file) = self.open_files.get_mut(self.active_tab) else {
+            return;
+        };
+        if file.path != target.path {
+            return;
+        }
+        let offset = target.offset.min(file.buffer.len_chars());
+        file.state.clear_secondary_cursors();
+        file.state
+            .set_cursor(CursorPosition::from_char_offset(&file.buffer, offset));
+        file.state.scroll_to_cursor = true;
+        let line = file.buffer.char_to_line(offset) + 1;
+        let (first, last) = file.state.visible_lines;
+        file.state.center_on_cursor = !(first..last).contains(&line);
+        file.state.history.break_group();
+    }
+
+    fn perform_search(&mut self) {
+        let find = &mut self.find_replace;
+        find.current_match = 0;
+        find.matches = match self.open_files.get(self.active_tab) {
+            Some(file) => find_in_files::find_matches(
+                &file.buffer.to_string(),
+                &find.search_text,
+                find.case_sensitive,
+            ),
+            None => Vec::new(),
+        };
+
+        find.other_matches = (0, 0);
+        if find.scope != FindScope::File && !find.search_text.is_empty() {
+            for (idx, file) in self.open_files.iter().enumerate() {
+                if idx == self.active_tab {
+                    continue;
+                }
+                let text = file.buffer.to_string();
+                let count =
+                    find_in_files::find_matches(&text, &find.search_text, find.case_sensitive)
+                        .len();
+                if count > 0 {
+                    find.other_matches.0 += count;
+                    find.other_matches.1 += 1;
+                }
+            }
+        }
+        if let Some(file) = self.open_files.get(self.active_tab) {
+            self.search_debounce.mark_done(file.id, file.state.revision);
+        }
+    }
+
+    /// Search the active file again once typing pauses
+    fn update_search(&mut self, ctx: &egui::Context) {
+        let Some(file) = self.open_files.get(self.active_tab) else {
+            return;
+        };
+        if self.find_replace.search_text.is_empty() {
+            return;
+        }
+        match self.search_debounce.poll(file.id, file.state.revision) {
+            Debounce::Ready => self.perform_search(),
+            Debounce::Wait(left) => ctx.request_repaint_after(left),
+            Debounce::Done => {}
+        }
+    }
+
+    /// Search now if the matches are from before the last edit, so they can
+    /// be stepped through and replaced
+    fn flush_search(&mut self) {
+        let Some(file) = self.open_files.get(self.active_tab) else {
+            return;
+        };
+        let stale = !self.search_debounce.is_done(file.id, file.state.revision);
+        if stale && !self.find_replace.search_text.is_empty() {
+            let current = self.find_replace.current_match;
+            self.perform_search();
+            let count = self.find_replace.matches.len();
+            self.find_replace.current_match = current.min(count.saturating_sub(1));
+        }
+    }
+
+    /// Numbers of the find matches on the lines in view, e.g. "12–15 in
+    /// view", following the editor as it scrolls
+    fn matches_in_view(&self) -> Option<String> {
+        let file = self.open_files.get(self.active_tab)?;
+        let (first, last) = file.state.visible_lines;
+        let in_view: Vec<usize> = self
+            .find_replace
+            .matches
+            .iter()
+            .enumerate()
+            .filter(|(_, &(start, _))| {
+                let line = file.buffer.char_to_line(start.min(file.buffer.len_chars())) + 1;
+                (first..=last).contains(&line)
+            })
+            .map(|(i, _)| i + 1)
+            .collect();
+        Some(match (in_view.first(), in_view.last()) {
+            (Some(a), Some(b)) if a == b => format!("{a} in view"),
+            (Some(a), Some(b)) => format!("{a}–{b} in view"),
+            _ => "none in view".to_string(),
+        })
+    }
+
+    fn announce_matches(&mut self) {
+        let message = match self.find_replace.matches.len() {
+            _ if self.find_replace.search_text.is_empty() => return,
+            0 => "No results".to_string(),
+            1 => "1 match".to_string(),
+            n => format!("{n} matches"),
+        };
+        self.announce(message, None);
+    }
+
+    fn find_next(&mut self) {
+        self.flush_search();
+        if !self.find_replace.matches.is_empty() {
+            self.find_replace.current_match =
+                (self.find_replace.current_match + 1) % self.find_replace.matches.len();
+        }
+    }
+
+    fn find_previous(&mut self) {
+        self.flush_search();
+        if !self.find_replace.matches.is_empty() {
+            if self.find_replace.current_match == 0 {
+                self.find_replace.current_match = self.find_replace.matches.len() - 1;
+            } else {
+                self.find_replace.current_match -= 1;
+            }
+        }
+    }
+
+    fn replace_current(&mut self) {
+        self.flush_search();
+        if self.find_replace.matches.is_empty() {
+            return;
+        }
+
+        if let Some(file) = self.open_files.get_mut(self.active_tab) {
+            let (start, end) = self.find_replace.matches[self.find_replace.current_match];
+            file.replace_ranges(
+                std::slice::from_ref(&(start..end)),
+                &self.find_replace.replace_text,
+            );
+
+            // Re-search to update matches
+            self.perform_search();
+        }
+    }
+
+    /// Replace All, asking first when it would make more replacements than
+    /// the configured threshold
+    fn replace_all(&mut self) {
+        let threshold = self.settings.confirm_replace_all_above;
+        if threshold > 0 {
+            let (replacements, files) = self.replace_all_targets();
+            if replacements > threshold {
+                self.replace_confirm = Some(ReplaceAllConfirm {
+                    replacements,
+                    files,
+                });
+                return;
+            }
+        }
+        self.run_replace_all();
+    }
+
+    /// Number of matches Replace All would replace in its scope, and the
+    /// names of the files they are in
+    fn replace_all_targets(&self) -> (usize, Vec<String>) {
+        let find = &self.find_replace;
+        if find.scope == FindScope::File {
+            let name = self.open_files.get(self.active_tab).map(|f| f.name());
+            return (find.matches.len(), name.into_iter().collect());
+        }
+        if find.search_text.is_empty() {
+            return (0, Vec::new());
+        }
+
+        let mut replacements = 0;
+        let mut files = Vec::new();
+        for file in &self.open_files {
+            let text = file.buffer.to_string();
+            let count =
+                find_in_files::find_matches(&text, &find.search_text, find.case_sensitive).len();
+            if count > 0 {
+                replacements += count;
+                files.push(file.name());
+            }
+        }
+        let workspace = self
+            .workspace
+            .as_ref()
+            .filter(|_| find.scope == FindScope::Workspace);
+        if let Some(workspace) = workspace {
+            let exclusions = self.exclusions(workspace);
+            for path in find_in_files::workspace_files(workspace, &exclusions) {
+                if self.open_files.iter().any(|f| f.path == path) {
+                    continue;
+                }
+                let Ok(text) = std::fs::read_to_string(&path) else {
+                    continue;
+                };
+                let count =
+                    find_in_files::find_matches(&text, &find.search_text, find.case_sensitive)
+                        .len();
+                if count > 0 {
+                    replacements += count;
+                    files.push(display_path(&path, std::slice::from_ref(workspace)));
+                }
+            }
+        }
+        (replacements, files)
+    }
+
+    fn run_replace_all(&mut self) {
+        if self.find_replace.scope != FindScope::File {
+            self.replace_in_files();
+            return;
+        }
+        self.flush_search();
+        if self.find_replace.matches.is_empty() {
+            return;
+        }
+
+        if let Some(file) = self.open_files.get_mut(self.active_tab) {
+            let ranges: Vec<Range<usize>> = self
+                .find_replace
+                .matches
+                .iter()
+                .map(|&(start, end)| start..end)
+                .collect();
+            let before = file.buffer.to_string();
+            file.replace_ranges(&ranges, &self.find_replace.replace_text);
+            self.replace_undo = Some(ReplaceAllUndo {
+                summary: match ranges.len() {
+                    1 => "Replaced 1 match".to_string(),
+                    n => format!("Replaced {n} matches"),
+                },
+                buffers: vec![(file.id, before, file.state.revision)],
+                disk: Vec::new(),
+            });
+
+            // Re-search to update matches
+            self.perform_search();
+        }
+    }
+
+    /// Replace All across the open files, and with the Workspace scope the
+    /// workspace files that aren't open. Open files are only changed in
+    /// memory, each in one undo step.
+    fn replace_in_files(&mut self) {
+        let find = &self.find_replace;
+        let (search, replacement) = (find.search_text.clone(), find.replace_text.clone());
+        let case_sensitive = find.case_sensitive;
+        if search.is_empty() {
+            return;
+        }
+
+        let mut replaced = 0;
+        let mut files = 0;
+        let mut buffers = Vec::new();
+        let mut disk = Vec::new();
+        for file in &mut self.open_files {
+            let text = file.buffer.to_string();
+            let ranges: Vec<Range<usize>> =
+                find_in_files::find_matches(&text, &search, case_sensitive)
+                    .into_iter()
+                    .map(|(start, end)| start..end)
+                    .collect();
+            if !ranges.is_empty() {
+                file.replace_ranges(&ranges, &replacement);
+                replaced += ranges.len();
+                files += 1;
+                buffers.push((file.id, text, file.state.revision));
+            }
+        }
+
+        let workspace = self
+            .workspace
+            .clone()
+            .filter(|_| self.find_replace.scope == FindScope::Workspace);
+        if let Some(workspace) = workspace {
+            self.set_activity(format!("replacing in {}", workspace.display()));
+            let exclusions = self.exclusions(&workspace);
+            for path in find_in_files::workspace_files(&workspace, &exclusions) {
+                if self.open_files.iter().any(|f| f.path == path) {
+                    continue;
+                }
+                // Binary and non-UTF-8 files are left alone
+                let Ok(text) = std::fs::read_to_string(&path) else {
+                    continue;
+                };
+                let Some((new_text, count)) =
+                    find_in_files::replace_all(&text, &search, case_sensitive, &replacement)
+                else {
+                    continue;
+                };
+                match std::fs::write(&path, &new_text) {
+                    Ok(()) => {
+                        replaced += count;
+                        files += 1;
+                        disk.push((path, text, new_text));
+                    }
+                    Err(e) => self.log_output(format!("Could not write {}: {e}", path.display())),
+                }
+            }
+        }
+
+        let message = match (replaced, files) {
+            (0, _) => "No matches to replace".to_string(),
+            (1, _) => "Replaced 1 match".to_string(),
+            (n, 1) => format!("Replaced {n} matches in 1 file"),
+            (n, files) => format!("Replaced {n} matches in {files} files"),
+        };
+        self.log_output(message.clone());
+        if replaced > 0 {
+            self.replace_undo = Some(ReplaceAllUndo {
+                summary: message.clone(),
+                buffers,
+                disk,
+            });
+        }
+        self.announce(message, None);
+        self.perform_search();
+    }
+
+    fn render_replace_all_confirm(&mut self, ctx: &egui::Context) {
+        let colors = palette(&ctx.style().visuals);
+        let mut replace = false;
+        let mut cancel = ctx.input(|i| i.key_pressed(egui::Key::Escape));
+        let Some(confirm) = &self.replace_confirm else {
+            return;
+        };
+        let find = &self.find_replace;
+
+        egui::Area::new(egui::Id::new("replace_all_confirm"))
+            .anchor(egui::Align2::CENTER_TOP, Vec2::new(0.0, 50.0))
+            .show(ctx, |ui| {
+                Frame::none()
+                    .fill(colors.find_panel_bg)
+                    .inner_margin(Margin::same(12.0))
+                    .rounding(4.0)
+                    .shadow(egui::epaint::Shadow {
+                        extrusion: 8.0,
+                        color: Color32::from_black_alpha(100),
+                    })
+                    .show(ui, |ui| {
+                        ui.set_max_width(420.0);
+                        ui.label(RichText::new("Replace All").size(fonts::HEADING));
+                        ui.add_space(8.0);
+                        let files = match confirm.files.len() {
+                            1 => "1 file".to_string(),
+                            n => format!("{n} files"),
+                        };
+                        ui.label(format!(
+                            "Replace {} matches of \"{}\" with \"{}\" in {files}?",
+                            confirm.replacements, find.search_text, find.replace_text
+                        ));
+                        // A few names are enough to spot a scope that is too wide
+                        const LISTED: usize = 8;
+                        for name in confirm.files.iter().take(LISTED) {
+                            ui.label(format!("  • {name}"));
+                        }
+                        if confirm.files.len() > LISTED {
+                            ui.label(format!("  and {} more", confirm.files.len() - LISTED));
+                        }
+                        ui.add_space(4.0);
+                        ui.horizontal(|ui| {
+                            replace = ui.button("Replace All").clicked();
+                            cancel |= ui.button("Cancel").clicked();
+                        });
+                    });
+            });
+
+        if replace {
+            self.replace_confirm = None;
+            self.run_replace_all();
+        } else if cancel {
+            self.replace_confirm = None;
+        }
+    }
+
+    /// Offer to undo the last Replace All until a file it changed is edited
+    fn render_replace_all_undo(&mut self, ctx: &egui::Context) {
+        let Some(undo) = &self.replace_undo else {
+            return;
+        };
+        let current = undo.buffers.iter().all(|(id, _, revision)| {
+            self.open_files
+                .iter()
+                .find(|f| f.id == *id)
+                .map_or(true, |f| f.state.revision == *revision)
+        });
+        if !current {
+            self.replace_undo = None;
+            return;
+        }
+
+        let colors = palette(&ctx.style().visuals);
+        let mut revert = false;
+        let mut dismiss = false;
+        egui::Area::new(egui::Id::new("replace_all_undo"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, Vec2::new(-20.0, -40.0))
+            .show(ctx, |ui| {
+                Frame::none()
+                    .fill(colors.find_panel_bg)
+                    .inner_margin(Margin::same(8.0))
+                    .rounding(4.0)
+                    .shadow(egui::epaint::Shadow {
+                        extrusion: 8.0,
+                        color: Color32::from_black_alpha(100),
+                    })
+                    .show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label(&undo.summary);
+                            revert = ui.button("Undo").clicked();
+                            dismiss = ui.button("✕").on_hover_text("Dismiss").clicked();
+                        });
+                    });
+            });
+
+        if revert {
+            self.undo_replace_all();
+        } else if dismiss {
+            self.replace_undo = None;
+        }
+    }
+
+    /// Put back every file the last Replace All changed: open files in one
+    /// undo step each, and files on disk unless they changed since
+    fn undo_replace_all(&mut self) {
+        let Some(undo) = self.replace_undo.take() else {
+            return;
+        };
+        let mut restored = 0;
+        for (id, before, _) in undo.buffers {
+            if let Some(file) = self.open_files.iter_mut().find(|f| f.id == id) {
+                let whole = 0..file.buffer.len_chars();
+                file.replace_ranges(std::slice::from_ref(&whole), &before);
+                file.state.history.break_group();
+                restored += 1;
+            }
+        }
+        for (path, before, written) in undo.disk {
+            match std::fs::read_to_string(&path) {
+                Ok(current) if current == written => match std::fs::write(&path, before) {
+                    Ok(()) => restored += 1,
+                    Err(e) => self.log_output(format!("Could not write {}: {e}", path.display())),
+                },
+                _ => self.log_output(format!(
+                    "Left {} alone: it changed after Replace All",
+                    path.display()
+                )),
+            }
+        }
+
+        let message = match restored {
+            1 => "Undid Replace All in 1 file".to_string(),
+            n => format!("Undid Replace All in {n} files"),
+        };
+        self.log_output(message.clone());
+        self.announce(message, None);
+        self.perform_search();
+    }
+
+    // === Menu Bar ===
 
     fn render_menu_bar(&mut self, ctx: &egui::Context) {
+        let colors = palette(&ctx.style().visuals);
         egui::TopBottomPanel::top("menu_bar")
             .frame(
                 Frame::none()
-                    .fill(colors::MENU_BAR_BG)
-                    .inner_margin(Margin::symmetric(8.0, 6.0)),
-            )
+                    .fill(colors.menu_bar_bg)
+                    .inner_margin(Margin::symmetric(8.0, 6.0)),
+            )
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.spacing_mut().item_spacing.x = 4.0;
+                    ui.spacing_mut().button_padding = Vec2::new(8.0, 4.0);
+
+                    self.menus(ui);
+                });
+            });
+    }
+
+    // === Exit ===
+
+    /// Hold back a window close until unsaved files and the running task are
+    /// dealt with, then shut down before letting it through
+    fn handle_close_request(&mut self, ctx: &egui::Context) {
+        if self.exiting || !ctx.input(|i| i.viewport().close_requested()) {
+            return;
+        }
+        ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+        let unsaved: Vec<String> = self
+            .open_files
+            .iter()
+            .filter(|f| f.is_modified())
+            .map(|f| f.name())
+            .collect();
+        let task = self.cargo.run.as_ref().map(|run| run.label.clone());
+        if unsaved.is_empty() && task.is_none() {
+            self.exit(ctx);
+        } else {
+            self.exit_prompt = Some(ExitPrompt { unsaved, task });
+        }
+    }
+
+    fn render_exit_prompt(&mut self, ctx: &egui::Context) {
+        let colors = palette(&ctx.style().visuals);
+        let mut save_all = false;
+        let mut discard = false;
+        let mut cancel = ctx.input(|i| i.key_pressed(egui::Key::Escape));
+        let Some(prompt) = &self.exit_prompt else {
+            return;
+        };
+
+        egui::Area::new(egui::Id::new("exit_prompt"))
+            .anchor(egui::Align2::CENTER_TOP, Vec2::new(0.0, 50.0))
+            .show(ctx, |ui| {
+                Frame::none()
+                    .fill(colors.find_panel_bg)
+                    .inner_margin(Margin::same(12.0))
+                    .rounding(4.0)
+                    .shadow(egui::epaint::Shadow {
+                        extrusion: 8.0,
+                        color: Color32::from_black_alpha(100),
+                    })
+                    .show(ui, |ui| {
+                        ui.set_max_width(420.0);
+                        ui.label(RichText::new("Exit").size(fonts::HEADING));
+                        ui.add_space(8.0);
+                        if !prompt.unsaved.is_empty() {
+                            ui.label("These files have unsaved changes:");
+                            for name in &prompt.unsaved {
+                                ui.label(format!("  • {name}"));
+                            }
+                            ui.add_space(4.0);
+                        }
+                        if let Some(task) = &prompt.task {
+                            ui.label(format!("{task} is still running and will be stopped."));
+                            ui.add_space(4.0);
+                        }
+                        ui.horizontal(|ui| {
+                            if !prompt.unsaved.is_empty() {
+                                save_all = ui.button("Save All and Exit").clicked();
+                                discard = ui.button("Exit Without Saving").clicked();
+                            } else {
+                                discard = ui.button("Exit").clicked();
+                            }
+                            cancel |= ui.button("Cancel").clicked();
+                        });
+                    });
+            });
+
+        if cancel {
+            self.exit_prompt = None;
+        } else if save_all {
+            self.exit_prompt = None;
+            if self.save_all() {
+                self.exit(ctx);
+            }
+        } else if discard {
+            self.exit_prompt = None;
+            self.exit(ctx);
+        }
+    }
+
+    /// Save every modified file. False if one of them wasn't saved, e.g. when
+    /// the dialog for a new file was cancelled.
+    fn save_all(&mut self) -> bool {
+        let active = self.active_tab;
+        for idx in 0..self.open_files.len() {
+            if self.open_files[idx].is_modified() {
+                self.active_tab = idx;
+                self.save_current_file();
+            }
+        }
+        self.active_tab = active.min(self.open_files.len().saturating_sub(1));
+        !self.open_files.iter().any(|f| f.is_modified())
+    }
+
+    /// Save the layout, stop background work and close the window
+    fn exit(&mut self, ctx: &egui::Context) {
+        self.set_activity("shutting down");
+        self.save_layout(ctx);
+        if let Some(current) = self.current_session.clone() {
+            self.save_session(current);
+        }
+        // Dropping them kills the cargo command, stops the server and asks
+        // the language servers to exit
+        if let Some(run) = self.cargo.run.take() {
+            self.log_output(format!("{} stopped", run.label));
+        }
+        self.server = None;
+        self.language_servers.clear();
+        if let Some(watchdog) = self.watchdog.take() {
+            watchdog.stop();
+        }
+        self.exiting = true;
+        ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+    }
+
+    // === Title Bar ===
+
+    /// VSCode-style title bar drawn by egui when native decorations are disabled
+    fn render_title_bar(&mut self, ctx: &egui::Context) {
+        let colors = palette(&ctx.style().visuals);
+        egui::TopBottomPanel::top("title_bar")
+            .exact_height(layout::TITLE_BAR_HEIGHT)
+            .frame(
+                Frame::none()
+                    .fill(colors.menu_bar_bg)
+                    .inner_margin(Margin::symmetric(8.0, 0.0)),
+            )
+            .show(ctx, |ui| {
+                let title_bar_rect = ui.max_rect();
+
+                // Background interaction is registered first so the menus and
+                // window buttons added on top of it take precedence
+                let drag_response = ui.interact(
+                    title_bar_rect,
+                    egui::Id::new("title_bar_drag"),
+                    egui::Sense::click_and_drag(),
+                );
+                if drag_response.double_clicked() {
+                    let maximized = ctx.input(|i| i.viewport().maximized.unwrap_or(false));
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Maximized(!maximized));
+                } else if drag_response.drag_started_by(egui::PointerButton::Primary) {
+                    ctx.send_viewport_cmd(egui::ViewportCommand::StartDrag);
+                }
+
+                ui.painter().text(
+                    title_bar_rect.center(),
+                    egui::Align2::CENTER_CENTER,
+                    self.window_title(),
+                    FontId::proportional(fonts::BODY),
+                    colors.text_secondary,
+                );
+
+                ui.horizontal_centered(|ui| {
+                    ui.spacing_mut().item_spacing.x = 4.0;
+                    ui.spacing_mut().button_padding = Vec2::new(8.0, 4.0);
+
+                    self.menus(ui);
+
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        ui.spacing_mut().item_spacing.x = 0.0;
+                        self.window_controls(ui);
+                    });
+                });
+            });
+    }
+
+    fn window_controls(&self, ui: &mut egui::Ui) {
+        let colors = palette(ui.visuals());
+        let ctx = ui.ctx().clone();
+        let maximized = ctx.input(|i| i.viewport().maximized.unwrap_or(false));
+
+        // Laid out right to left: close, maximize/restore, minimize
+        if Self::window_control_button(ui, "🗙", colors.title_bar_close_hover).clicked() {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+        }
+        let maximize_icon = if maximized { "🗗" } else { "🗖" };
+        if Self::window_control_button(ui, maximize_icon, colors.widget_hovered).clicked() {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Maximized(!maximized));
+        }
+        if Self::window_control_button(ui, "🗕", colors.widget_hovered).clicked() {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(true));
+        }
+    }
+
+    fn window_control_button(
+        ui: &mut egui::Ui,
+        icon: &str,
+        hover_color: Color32,
+    ) -> egui::Response {
+        let colors = palette(ui.visuals());
+        let (rect, response) = ui.allocate_exact_size(
+            Vec2::new(layout::TITLE_BAR_BUTTON_WIDTH, layout::TITLE_BAR_HEIGHT),
+            egui::Sense::click(),
+        );
+
+        if response.hovered() {
+            ui.painter().rect_filled(rect, 0.0, hover_color);
+        }
+        ui.painter().text(
+            rect.center(),
+            egui::Align2::CENTER_CENTER,
+            icon,
+            FontId::proportional(fonts::BODY),
+            colors.text_primary,
+        );
+
+        response
+    }
+
+    /// Folders open in the workspace, for showing paths relative to them
+    fn workspace_roots(&self) -> &[PathBuf] {
+        match &self.workspace {
+            Some(workspace) => std::slice::from_ref(workspace),
+            None => &[],
+        }
+    }
+
+    fn window_title(&self) -> String {
+        match self.open_files.get(self.active_tab) {
+            Some(file) => format!(
+                "{} - Rust Code Editor",
+                display_path(&file.path, self.workspace_roots())
+            ),
+            None => "Rust Code Editor".to_string(),
+        }
+    }
+
+    // === Commands ===
+
+    /// Menu bar menus, built from the command registry
+    fn menus(&mut self, ui: &mut egui::Ui) {
+        for menu in Menu::ALL {
+            ui.menu_button(menu.label(), |ui| {
+                ui.style_mut().spacing.item_spacing.y = 4.0;
+                self.menu_items(ui, menu.items());
+            });
+        }
+    }
+
+    fn menu_items(&mut self, ui: &mut egui::Ui, items: &[MenuItem]) {
+        for item in items {
+            let command = match item {
+                MenuItem::Command(command) => *command,
+                MenuItem::Separator => {
+                    ui.separator();
+                    continue;
+                }
+                MenuItem::Submenu(label, items) => {
+                    ui.menu_button(*label, |ui| self.menu_items(ui, items));
+                    continue;
+                }
+            };
+            let label = match command.icon() {
+                "" => command.label().to_string(),
+                icon => format!("{icon} {}", command.label()),
+            };
+            let shortcut = self
+                .keybindings
+                .get(command)
+                .map(|binding| binding.format(ui.ctx()));
+            let enabled = self.command_enabled(command);
+            let mut response = match self.command_checked(command) {
+                Some(checked) if command.is_choice() => {
+                    ui.add_enabled(enabled, egui::RadioButton::new(checked, label))
+                }
+                Some(mut checked) => {
+                    // Checkboxes have no room for a shortcut of their own
+                    let label = match &shortcut {
+                        Some(shortcut) => format!("{label}   {shortcut}"),
+                        None => label,
+                    };
+                    ui.add_enabled(enabled, egui::Checkbox::new(&mut checked, label))
+                }
+                None => ui.add_enabled(
+                    enabled,
+                    egui::Button::new(label).shortcut_text(shortcut.unwrap_or_default()),
+                ),
+            };
+            if let Some(tooltip) = command.tooltip() {
+                response = response.on_hover_text(tooltip);
+            }
+            if response.clicked() {
+                self.run_command(ui.ctx(), command);
+                ui.close_menu();
+            }
+        }
+    }
+
+    /// Whether a command can run in the current state. Shortcuts of disabled
+    /// commands are left to the editor.
+    fn command_enabled(&self, command: Command) -> bool {
+        let file = self.open_files.get(self.active_tab);
+        let has_file = file.is_some();
+        match command {
+            Command::EnvironmentVariables
+            | Command::SignCommits
+            | Command::RunTask
+            | Command::GitCommit
+            | Command::QuickOpen => self.workspace.is_some(),
+            Command::SwitchSession => !self.sessions.sessions.is_empty(),
+            Command::NextBookmark | Command::PreviousBookmark => self.has_bookmarks(),
+            Command::ReopenClosedTab => !self.closed_tabs.is_empty(),
+            Command::GoBack => self.navigation.can_go_back(),
+            Command::GoForward => self.navigation.can_go_forward(),
+            Command::ToggleLastEditor => self.previous_tab().is_some(),
+            Command::ServeWorkspace => self.server.is_some() || self.workspace.is_some(),
+            Command::ShareSession => !self.collab.as_ref().is_some_and(|s| !s.is_host()),
+            Command::JoinSession => !self.collab.as_ref().is_some_and(|s| s.is_host()),
+            Command::ConnectDatabase | Command::RunQuery => file.is_some_and(|f| f.is_sql()),
+            Command::HtmlPreview => file.is_some_and(|f| f.is_html()),
+            Command::RecordMacro => {
+                self.macros.playing.is_empty() && (self.macros.recording || has_file)
+            }
+            Command::PlayMacro => {
+                has_file && !self.macros.recording && !self.macros.steps.is_empty()
+            }
+            Command::Unfold | Command::UnfoldAll => {
+                file.is_some_and(|f| !f.state.folded.is_empty())
+            }
+            Command::UseGlobalSettings => {
+                file.is_some_and(|f| f.state.font_size.is_some() || f.state.word_wrap.is_some())
+            }
+            Command::SendRequest => file.is_some_and(|f| f.is_http()),
+            Command::StopTask => self.cargo.run.is_some(),
+            Command::MergeFromDisk | Command::SwitchToCounterpart | Command::ReopenWithEncoding => {
+                file.is_some_and(|f| !f.untitled)
+            }
+            Command::Save
+            | Command::SaveAs
+            | Command::SaveWithEncoding
+            | Command::CloseEditor
+            | Command::PasteFromHistory
+            | Command::Find
+            | Command::Replace
+            | Command::SortLinesAscending
+            | Command::SortLinesDescending
+            | Command::RemoveDuplicateLines
+            | Command::TriggerSuggest
+            | Command::ExpandSelection
+            | Command::ShrinkSelection
+            | Command::AddCursorAbove
+            | Command::AddCursorBelow
+            | Command::NextFunction
+            | Command::PreviousFunction
+            | Command::ToggleBookmark
+            | Command::SelectAllOccurrences
+            | Command::CursorUndo
+            | Command::SelectStringContents
+            | Command::SelectBracketContents
+            | Command::GoToBracket
+            | Command::SelectToBracket
+            | Command::TabZoomIn
+            | Command::TabZoomOut
+            | Command::TabWordWrap
+            | Command::Fold
+            | Command::FoldAllComments
+            | Command::FoldAllRegions
+            | Command::FollowFile
+            | Command::GoToLine
+            | Command::GoToDefinition
+            | Command::FindReferences => has_file,
+            _ if command.is_editor_command() => has_file,
+            _ => true,
+        }
+    }
+
+    /// State of a command that toggles something, None for other commands
+    fn command_checked(&self, command: Command) -> Option<bool> {
+        let settings = &self.settings;
+        let checked = match command {
+            Command::FormatOnSave => settings.format_on_save,
+            Command::RecordMacro => self.macros.recording,
+            Command::SignCommits => self.sign_commits().unwrap_or(self.git_signs_commits),
+            Command::CloseDeletedTabs => settings.close_deleted_tabs,
+            Command::ServeWorkspace => self.server.is_some(),
+            Command::ShareSession => self.collab.as_ref().is_some_and(|s| s.is_host()),
+            Command::JoinSession => self.collab.as_ref().is_some_and(|s| !s.is_host()),
+            Command::ShowSidebar if self.sidebar_overlaid() => self.sidebar_overlay,
+            Command::ShowSidebar => self.layout.show_sidebar,
+            Command::ShowMinimap if self.minimap_overlaid() => self.minimap_overlay,
+            Command::ShowMinimap => self.layout.show_minimap,
+            Command::WordWrap => settings.word_wrap,
+            Command::TabWordWrap => self.word_wrap(self.active_tab),
+            Command::SplitEditor => self.split.is_some(),
+            Command::RenderWhitespace => settings.render_whitespace,
+            Command::ShowHiddenFiles => settings.show_hidden_files,
+            Command::MouseGestures => settings.mouse_gestures,
+            Command::SoundCues => settings.sound_cues,
+            Command::CustomTitleBar => settings.custom_title_bar,
+            Command::SaveLayout => settings.save_layout,
+            Command::ToggleOutput => self.layout.show_output,
+            Command::ShowBookmarks => self.bookmarks_panel,
+            Command::FollowFile => self
+                .open_files
+                .get(self.active_tab)
+                .is_some_and(|f| f.follow),
+            Command::MarkdownPreview => self.preview.is_open,
+            Command::SyncPreviewScrolling => self.preview.sync_scroll,
+            Command::AntiAliasing => settings.text_rendering.anti_aliasing,
+            Command::RoundTextToPixels => settings.text_rendering.round_text_to_pixels,
+            Command::SnapHighlightsToPixels => settings.text_rendering.pixel_snapping,
+            Command::KeymapDefault => settings.keymap == Keymap::Default,
+            Command::KeymapEmacs => settings.keymap == Keymap::Emacs,
+            Command::ThemeDark => settings.theme == ThemeMode::Dark,
+            Command::ThemeLight => settings.theme == ThemeMode::Light,
+            Command::ThemeAuto => settings.theme == ThemeMode::Auto,
+            _ => return None,
+        };
+        Some(checked)
+    }
+
+    fn run_command(&mut self, ctx: &egui::Context, command: Command) {
+        if self.macros.recording && !matches!(command, Command::RecordMacro | Command::PlayMacro) {
+            self.macros.steps.push(MacroStep::Command(command));
+        }
+        if let Some(event) = command.editor_event() {
+            self.editor_events.push(event);
+            ctx.request_repaint();
+            return;
+        }
+        match command {
+            Command::NewFile => self.new_file(),
+            Command::NewProject => self.open_new_project_dialog(),
+            Command::OpenFile => self.open_file_dialog(),
+            Command::OpenFolder => self.open_folder(),
+            Command::OpenLogFile => {
+                if let Some(path) = self.blocking_dialog(|| FileDialog::new().pick_file()) {
+                    self.open_log_file(path);
+                }
+            }
+            Command::CompareFolders => self.compare_folders(ctx),
+            Command::EnvironmentVariables => self.open_env_editor(),
+            Command::EditKeybindings => {
+                self.keybindings_editor = Some(KeybindingsEditorState {
+                    draft: self.keybindings.clone(),
+                    filter: String::new(),
+                    recording: None,
+                    error: None,
+                });
+            }
+            Command::Save => self.save_current_file(),
+            Command::SaveAs => {
+                if self.choose_save_path() {
+                    self.save_current_file();
+                }
+            }
+            Command::SaveWithEncoding => self.open_encoding_picker(Some(EncodingAction::Save)),
+            Command::ReopenWithEncoding => {
+                self.open_encoding_picker(Some(EncodingAction::Reopen));
+            }
+            Command::FormatOnSave => self.toggle_setting(|s| &mut s.format_on_save),
+            Command::SignCommits => {
+                if let Some(workspace) = self.workspace.clone() {
+                    let sign = self.sign_commits().unwrap_or(self.git_signs_commits);
+                    self.settings.sign_commits_in.insert(workspace, !sign);
+                    self.save_settings();
+                }
+            }
+            Command::CloseDeletedTabs => self.toggle_setting(|s| &mut s.close_deleted_tabs),
+            Command::MergeFromDisk => self.merge_disk_changes(),
+            Command::ServeWorkspace if self.server.is_some() => self.stop_server(),
+            Command::ServeWorkspace => self.serve_workspace(),
+            Command::ShareSession | Command::JoinSession if self.collab.is_some() => {
+                self.end_collaboration("Left the shared session".to_string());
+            }
+            Command::ShareSession => {
+                let chosen = self.open_files.get(self.active_tab).map(|f| f.path.clone());
+                self.share_session = Some(ShareSessionState {
+                    chosen: chosen.into_iter().collect(),
+                });
+            }
+            Command::JoinSession => self.join_session = Some(JoinSessionState::default()),
+            Command::ConnectDatabase => {
+                self.connect_database();
+            }
+            Command::CloseEditor => self.close_tab(self.active_tab),
+            Command::ReopenClosedTab => self.reopen_closed_tab(),
+            Command::Exit => ctx.send_viewport_cmd(egui::ViewportCommand::Close),
+            Command::Paste => self.paste_from_clipboard(ctx),
+            Command::PasteFromHistory => self.clipboard_picker.open(),
+            Command::RecordMacro => self.toggle_macro_recording(),
+            Command::PlayMacro => self.macros.repeat = Some("1".to_string()),
+            Command::Find | Command::Replace => {
+                self.find_replace.is_open = true;
+                self.find_replace.show_replace = command == Command::Replace;
+            }
+            Command::SortLinesAscending
+            | Command::SortLinesDescending
+            | Command::RemoveDuplicateLines => {
+                let Some(file) = self.open_files.get_mut(self.active_tab) else {
+                    return;
+                };
+                file.edit_lines(|buffer, cursors| match command {
+                    Command::RemoveDuplicateLines => {
+                        multi_cursor::remove_duplicate_lines(buffer, cursors)
+                    }
+                    _ => multi_cursor::sort_lines(
+                        buffer,
+                        cursors,
+                        command == Command::SortLinesDescending,
+                    ),
+                });
+                if !self.find_replace.search_text.is_empty() {
+                    self.perform_search();
+                }
+            }
+            Command::TriggerSuggest => self.trigger_completion(true),
+            Command::ExpandSelection | Command::ShrinkSelection => {
+                if let Some(file) = self.open_files.get_mut(self.active_tab) {
+                    if command == Command::ExpandSelection {
+                        file.expand_selection();
+                    } else {
+                        file.shrink_selection();
+                    }
+                }
+            }
+            Command::AddCursorAbove | Command::AddCursorBelow => {
+                if let Some(file) = self.open_files.get_mut(self.active_tab) {
+                    let above = command == Command::AddCursorAbove;
+                    multi_cursor::add_cursor_vertical(&file.buffer, &mut file.state.cursors, above);
+                }
+            }
+            Command::SelectAllOccurrences => self.select_all_occurrences(ctx),
+            Command::CursorUndo => {
+                if let Some(file) = self.open_files.get_mut(self.active_tab) {
+                    if file.state.cursor_history.undo(&mut file.state.cursors) {
+                        file.state.scroll_to_cursor = true;
+                    }
+                }
+            }
+            Command::SelectStringContents => {
+                if let Some(file) = self.open_files.get_mut(self.active_tab) {
+                    file.select_string_contents();
+                }
+            }
+            Command::SelectBracketContents => {
+                if let Some(file) = self.open_files.get_mut(self.active_tab) {
+                    file.select_bracket_contents();
+                }
+            }
+            Command::GoToBracket => {
+                if let Some(file) = self.open_files.get_mut(self.active_tab) {
+                    file.go_to_bracket();
+                }
+            }
+            Command::SelectToBracket => {
+                if let Some(file) = self.open_files.get_mut(self.active_tab) {
+                    file.select_to_bracket();
+                }
+            }
+            Command::ShowAllCommands => self.command_palette.open(),
+            Command::ShowSidebar => self.toggle_sidebar(),
+            Command::ShowMinimap if self.minimap_overlaid() => {
+                self.minimap_overlay = !self.minimap_overlay;
+            }
+            Command::ShowMinimap => self.layout.show_minimap = !self.layout.show_minimap,
+            Command::WordWrap => self.toggle_setting(|s| &mut s.word_wrap),
+            Command::SplitEditor => self.toggle_split(),
+            Command::RenderWhitespace => self.toggle_setting(|s| &mut s.render_whitespace),
+            Command::ShowHiddenFiles => self.toggle_hidden_files(),
+            Command::MouseGestures => self.toggle_setting(|s| &mut s.mouse_gestures),
+            Command::SoundCues => self.toggle_setting(|s| &mut s.sound_cues),
+            Command::CustomTitleBar => {
+                self.toggle_setting(|s| &mut s.custom_title_bar);
+                ctx.send_viewport_cmd(egui::ViewportCommand::Decorations(
+                    !self.settings.custom_title_bar,
+                ));
+            }
+            Command::SaveLayout => self.toggle_setting(|s| &mut s.save_layout),
+            Command::ResetLayout => self.reset_layout(ctx),
+            Command::ToggleOutput => self.layout.show_output = !self.layout.show_output,
+            Command::FollowFile => self.toggle_follow(),
+            Command::MarkdownPreview => self.open_preview(),
+            Command::SyncPreviewScrolling => {
+                self.preview.sync_scroll = !self.preview.sync_scroll;
+            }
+            Command::HtmlPreview => self.open_html_preview(),
+            Command::RegexPlayground => self.open_regex_playground(),
+            Command::TabZoomIn | Command::TabZoomOut => {
+                let step = if command == Command::TabZoomIn {
+                    1.0
+                } else {
+                    -1.0
+                };
+                if let Some(file) = self.open_files.get_mut(self.active_tab) {
+                    let size = file.state.font_size.unwrap_or(fonts::BODY) + step;
+                    let size = size.clamp(MIN_FONT_SIZE, MAX_FONT_SIZE);
+                    file.state.font_size = (size != fonts::BODY).then_some(size);
+                }
+            }
+            Command::TabWordWrap => {
+                let word_wrap = !self.word_wrap(self.active_tab);
+                if let Some(file) = self.open_files.get_mut(self.active_tab) {
+                    file.state.word_wrap = Some(word_wrap);
+                }
+            }
+            Command::UseGlobalSettings => {
+                if let Some(file) = self.open_files.get_mut(self.active_tab) {
+                    file.state.font_size = None;
+                    file.state.word_wrap = None;
+                }
+            }
+            Command::Fold
+            | Command::Unfold
+            | Command::FoldAllComments
+            | Command::FoldAllRegions
+            | Command::UnfoldAll => {
+                let Some(file) = self.open_files.get_mut(self.active_tab) else {
+                    return;
+                };
+                file.update_fold_ranges();
+                match command {
+                    Command::Fold => file.fold_at_caret(),
+                    Command::Unfold => file.unfold_at_caret(),
+                    Command::FoldAllComments => {
+                        file.fold_all(&[FoldKind::BlockComment, FoldKind::DocComment]);
+                    }
+                    Command::FoldAllRegions => file.fold_all(&[FoldKind::Region]),
+                    _ => file.state.folded.clear(),
+                }
+            }
+            Command::AntiAliasing => self.toggle_setting(|s| &mut s.text_rendering.anti_aliasing),
+            Command::RoundTextToPixels => {
+                self.toggle_setting(|s| &mut s.text_rendering.round_text_to_pixels);
+            }
+            Command::SnapHighlightsToPixels => {
+                self.toggle_setting(|s| &mut s.text_rendering.pixel_snapping);
+            }
+            Command::ThemeDark | Command::ThemeLight | Command::ThemeAuto => {
+                self.settings.theme = match command {
+                    Command::ThemeDark => ThemeMode::Dark,
+                    Command::ThemeLight => ThemeMode::Light,
+                    _ => ThemeMode::Auto,
+                };
+                self.save_settings();
+            }
+            Command::KeymapDefault | Command::KeymapEmacs => {
+                self.settings.keymap = match command {
+                    Command::KeymapEmacs => Keymap::Emacs,
+                    _ => Keymap::Default,
+                };
+                self.emacs_mark = false;
+                self.save_settings();
+            }
+            Command::GoToLine => self.goto_line.open(),
+            Command::SwitchToCounterpart => self.switch_to_counterpart(),
+            Command::QuickOpen => self.open_quick_open(),
+            Command::NextFunction => self.goto_adjacent_item(true),
+            Command::PreviousFunction => self.goto_adjacent_item(false),
+            Command::ToggleBookmark => self.toggle_bookmark(),
+            Command::NextBookmark => self.goto_bookmark(true),
+            Command::PreviousBookmark => self.goto_bookmark(false),
+            Command::ShowBookmarks => self.bookmarks_panel = !self.bookmarks_panel,
+            Command::GoBack => self.navigate(true),
+            Command::GoForward => self.navigate(false),
+            Command::ToggleLastEditor => {
+                if let Some(idx) = self.previous_tab() {
+                    self.activate_tab(idx);
+                }
+            }
+            Command::GoToSymbol => self.open_symbol_search(ctx),
+            Command::GoToDefinition => {
+                if let Some(file) = self.open_files.get(self.active_tab) {
+                    self.go_to_definition(file.state.cursor().offset);
+                }
+            }
+            Command::FindReferences => self.find_references(),
+            Command::SaveSession => {
+                self.session_name = Some(self.current_session.clone().unwrap_or_default());
+            }
+            Command::SwitchSession => self.session_picker.open(),
+            Command::GitCommit => self.open_commit(),
+            Command::RunTask => {
+                self.run_task.open();
+                if let Some(workspace) = self.workspace.clone() {
+                    if self.cargo.metadata.is_none() && self.cargo.pending.is_none() {
+                        self.reload_cargo_metadata(ctx, workspace);
+                    }
+                }
+            }
+            Command::StopTask => {
+                if let Some(run) = &mut self.cargo.run {
+                    run.stop();
+                }
+            }
+            Command::RunQuery => self.run_query(ctx),
+            Command::SendRequest => self.send_request_at_cursor(ctx),
+            Command::KeyboardShortcuts => self.show_shortcuts = true,
+            Command::ReportIssue => {
+                let url = concat!(env!("CARGO_PKG_REPOSITORY"), "/issues");
+                if let Err(e) = server::open_in_browser(url) {
+                    self.log_output(format!("Failed to open {url}: {e}"));
+                }
+            }
+            Command::About => self.show_about = true,
+            // Replayed to the editor above
+            Command::Undo
+            | Command::Redo
+            | Command::Cut
+            | Command::Copy
+            | Command::SelectAll
+            | Command::CopyLineUp
+            | Command::CopyLineDown
+            | Command::DuplicateSelection
+            | Command::DeleteLine => {}
+        }
+    }
+
+    /// Selection > Select All Occurrences: a cursor on every match of the find
+    /// panel while it has any, otherwise on every occurrence of the selection
+    /// or the word at the caret
+    fn select_all_occurrences(&mut self, ctx: &egui::Context) {
+        let Some(file) = self.open_files.get_mut(self.active_tab) else {
+            return;
+        };
+        let find = &mut self.find_replace;
+        let count = if find.is_open && !find.matches.is_empty() {
+            let ranges: Vec<Range<usize>> = find
+                .matches
+                .iter()
+                .map(|&(start, end)| start..end)
+                .collect();
+            file.select_ranges(&ranges);
+            // Typing goes to the cursors rather than the find field
+            find.is_open = false;
+            ranges.len()
+        } else {
+            file.select_all_occurrences()
+        };
+        if count > 0 {
+            ctx.memory_mut(|m| m.request_focus(egui::Id::new(CODE_EDITOR_ID)));
+            self.announce(
+                match count {
+                    1 => "1 occurrence selected".to_string(),
+                    n => format!("{n} occurrences selected"),
+                },
+                None,
+            );
+        }
+    }
+
+    /// Flip a boolean setting and save the settings
+    /// Whether lines wrap in a tab, by its own choice or the setting
+    fn word_wrap(&self, tab: usize) -> bool {
+        self.open_files
+            .get(tab)
+            .and_then(|f| f.state.word_wrap)
+            .unwrap_or(self.settings.word_wrap)
+    }
+
+    /// Write the settings, unless settings.toml failed to load: saving the
+    /// defaults in its place would lose what the user wrote there
+    fn save_settings(&mut self) {
+        if self.settings_error.is_some() {
+            self.log_output("Not saving settings: settings.toml has an error (see above)");
+            return;
+        }
+        if let Err(e) = self.settings.save() {
+            self.log_output(format!("Could not save settings: {e}"));
+        }
+    }
+
+    fn toggle_setting(&mut self, setting: impl FnOnce(&mut Settings) -> &mut bool) {
+        let value = setting(&mut self.settings);
+        *value = !*value;
+        self.save_settings();
+    }
+
+    /// Edit > Paste: the editor only receives the clipboard from Ctrl+V, so the
+    /// menu reads it itself
+    fn paste_from_clipboard(&mut self, ctx: &egui::Context) {
+        match arboard::Clipboard::new().and_then(|mut clipboard| clipboard.get_text()) {
+            Ok(text) => {
+                self.editor_events.push(egui::Event::Paste(text));
+                ctx.request_repaint();
+            }
+            Err(e) => self.log_output(format!("Could not read the clipboard: {e}")),
+        }
+    }
+
+    /// Keep what was cut or copied this frame in the clipboard history
+    fn record_clipboard(&mut self, ctx: &egui::Context) {
+        let copied = ctx.output(|o| o.copied_text.clone());
+        if copied.is_empty() {
+            return;
+        }
+        self.clipboard_history.retain(|entry| *entry != copied);
+        self.clipboard_history.push_front(copied);
+        let size = self.settings.clipboard_history_size.max(1);
+        self.clipboard_history.truncate(size);
+    }
+
+    /// Rewrite the Emacs keys of this frame into input the editor and the
+    /// commands understand: Ctrl+A/E to Home/End, Alt+F/B to Ctrl+Right/Left,
+    /// Ctrl+K to selecting the rest of the line and cutting it, and Ctrl+Y to
+    /// pasting the clipboard. Ctrl+Space sets the mark, after which motions
+    /// extend the selection until the text changes or Escape.
+    fn translate_emacs_keys(&mut self, ctx: &egui::Context) {
+        use egui::{Event, Key, Modifiers};
+        let Some(file) = self.open_files.get_mut(self.active_tab) else {
+            return;
+        };
+        let caret = file.state.cursor().offset;
+        let line_idx = file.buffer.char_to_line(caret);
+        let line_end =
+            file.buffer.line_to_char(line_idx) + multi_cursor::line_len(&file.buffer, line_idx);
+        let at_end = caret == file.buffer.len_chars();
+
+        let key_event = |key: Key, modifiers: Modifiers| Event::Key {
+            key,
+            physical_key: None,
+            pressed: true,
+            repeat: false,
+            modifiers,
+        };
+        let mut mark = self.emacs_mark;
+        let mut toggle_mark = false;
+        let mut yank = false;
+        ctx.input_mut(|i| {
+            let mut alt_motion = false;
+            for event in std::mem::take(&mut i.events) {
+                let Event::Key {
+                    key,
+                    pressed: true,
+                    modifiers,
+                    ..
+                } = event
+                else {
+                    if matches!(event, Event::Text(_) | Event::Paste(_) | Event::Cut) {
+                        mark = false;
+                    }
+                    i.events.push(event);
+                    continue;
+                };
+                let ctrl = modifiers.ctrl && !modifiers.alt;
+                let alt = modifiers.alt && !modifiers.ctrl;
+                let extend = if mark {
+                    Modifiers::SHIFT
+                } else {
+                    Modifiers::NONE
+                };
+                match key {
+                    Key::A if ctrl => i.events.push(key_event(Key::Home, extend)),
+                    Key::E if ctrl => i.events.push(key_event(Key::End, extend)),
+                    Key::F | Key::B if alt => {
+                        let arrow = if key == Key::F {
+                            Key::ArrowRight
+                        } else {
+                            Key::ArrowLeft
+                        };
+                        i.events.push(key_event(arrow, Modifiers::CTRL | extend));
+                        alt_motion = true;
+                    }
+                    Key::K if ctrl => {
+                        // The line break when the caret is at the end of the line
+                        if !at_end {
+                            let to = if caret == line_end {
+                                Key::ArrowRight
+                            } else {
+                                Key::End
+                            };
+                            i.events.push(key_event(to, Modifiers::SHIFT));
+                            i.events.push(Event::Cut);
+                        }
+                        mark = false;
+                    }
+                    Key::Y if ctrl => {
+                        yank = true;
+                        mark = false;
+                    }
+                    Key::Space if ctrl => toggle_mark = true,
+                    Key::ArrowLeft
+                    | Key::ArrowRight
+                    | Key::ArrowUp
+                    | Key::ArrowDown
+                    | Key::Home
+                    | Key::End
+                    | Key::PageUp
+                    | Key::PageDown
+                        if mark =>
+                    {
+                        i.events.push(key_event(key, modifiers | Modifiers::SHIFT));
+                    }
+                    _ => {
+                        if matches!(
+                            key,
+                            Key::Escape | Key::Enter | Key::Backspace | Key::Delete | Key::Tab
+                        ) {
+                            mark = false;
+                        }
+                        i.events.push(event);
+                    }
+                }
+            }
+            // Alt+F and Alt+B can also arrive as typed letters
+            if alt_motion {
+                i.events.retain(|e| !matches!(e, Event::Text(_)));
+            }
+        });
+
+        // Setting or clearing the mark starts over from the caret
+        if toggle_mark {
+            mark = !mark;
+            file.state
+                .set_cursor(CursorPosition::from_char_offset(&file.buffer, caret));
+        }
+        self.emacs_mark = mark;
+        if yank {
+            self.paste_from_clipboard(ctx);
+        }
+    }
+
+    /// Edit > Paste from History: earlier cuts and copies in a picker
+    fn render_clipboard_picker(&mut self, ctx: &egui::Context) {
+        let picker = &mut self.clipboard_picker;
+        let entries: Vec<String> = self
+            .clipboard_history
+            .iter()
+            .filter(|entry| fuzzy::score(&picker.query, entry).is_some())
+            .cloned()
+            .collect();
+        let items: Vec<QuickPickItem> = entries
+            .iter()
+            .map(|entry| {
+                let first = entry.lines().map(str::trim).find(|l| !l.is_empty());
+                let mut label: String = first.unwrap_or_default().chars().take(80).collect();
+                if first.is_some_and(|l| l.chars().count() > 80) {
+                    label.push('…');
+                }
+                QuickPickItem {
+                    icon: "📋",
+                    label,
+                    detail: match entry.lines().count() {
+                        0 | 1 => String::new(),
+                        n => format!("{n} lines"),
+                    },
+                }
+            })
+            .collect();
+        let empty_text = if self.clipboard_history.is_empty() {
+            "Nothing was cut or copied yet"
+        } else {
+            "No matching entries"
+        };
+
+        let response = QuickPick::new(
+            "clipboard_picker",
+            &mut picker.query,
+            &items,
+            &mut picker.selected,
+        )
+        .hint_text("Select the text to paste")
+        .empty_text(empty_text)
+        .show(ctx);
+
+        if response.query_changed {
+            picker.selected = 0;
+        }
+        if let Some(index) = response.accepted {
+            picker.is_open = false;
+            let text = entries[index].clone();
+            self.clipboard_history.retain(|entry| *entry != text);
+            self.clipboard_history.push_front(text.clone());
+            self.editor_events.push(egui::Event::Paste(text));
+            ctx.request_repaint();
+        } else if response.dismissed {
+            picker.is_open = false;
+        }
+    }
+
+    fn render_command_palette(&mut self, ctx: &egui::Context) {
+        let palette = &mut self.command_palette;
+        let mut commands: Vec<(Menu, Command)> = commands::all()
+            .into_iter()
+            .filter(|(_, command)| *command != Command::ShowAllCommands)
+            .collect();
+        if !palette.query.is_empty() {
+            let mut scored: Vec<(i32, (Menu, Command))> = commands
+                .into_iter()
+                .filter_map(|(menu, command)| {
+                    let label = format!("{}: {}", menu.label(), command.label());
+                    Some((fuzzy::score(&palette.query, &label)?, (menu, command)))
+                })
+                .collect();
+            // Stable, so equal scores keep the menu order
+            scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+            commands = scored.into_iter().map(|(_, entry)| entry).collect();
+        }
+        let commands: Vec<(Menu, Command)> = commands
+            .into_iter()
+            .filter(|(_, command)| self.command_enabled(*command))
+            .collect();
+        let items: Vec<QuickPickItem> = commands
+            .iter()
+            .map(|(menu, command)| QuickPickItem {
+                icon: match self.command_checked(*command) {
+                    Some(true) => "✔",
+                    _ => command.icon(),
+                },
+                label: format!("{}: {}", menu.label(), command.label()),
+                detail: self
+                    .keybindings
+                    .get(*command)
+                    .map(|binding| binding.format(ctx))
+                    .unwrap_or_default(),
+            })
+            .collect();
+
+        let palette = &mut self.command_palette;
+        let response = QuickPick::new(
+            "command_palette",
+            &mut palette.query,
+            &items,
+            &mut palette.selected,
+        )
+        .hint_text("Type the name of a command to run")
+        .empty_text("No matching commands")
+        .show(ctx);
+
+        if response.query_changed {
+            palette.selected = 0;
+        }
+        if let Some(index) = response.accepted {
+            palette.is_open = false;
+            ctx.memory_mut(|m| m.request_focus(egui::Id::new(CODE_EDITOR_ID)));
+            self.run_command(ctx, commands[index].1);
+        } else if response.dismissed {
+            palette.is_open = false;
+        }
+    }
+
+    /// Run > Run Task: cargo commands of the workspace's packages in a picker
+    fn render_run_task(&mut self, ctx: &egui::Context) {
+        self.poll_cargo_metadata();
+        let tasks: Vec<Invocation> = match &self.cargo.metadata {
+            Some(Ok(metadata)) => metadata
+                .packages
+                .iter()
+                .flat_map(|package| {
+                    let invocation = |action, target: Option<&cargo::Target>| Invocation {
+                        action,
+                        package: package.name.clone(),
+                        target: target.cloned(),
+                        features: Vec::new(),
+                        default_features: true,
+                    };
+                    let runnable = package.targets.iter().filter(|t| t.kind.is_runnable());
+                    [Action::Build, Action::Test]
+                        .map(|action| invocation(action, None))
+                        .into_iter()
+                        .chain(runnable.map(|target| invocation(Action::Run, Some(target))))
+                        .chain([invocation(Action::Clean, None)])
+                        .collect::<Vec<_>>()
+                })
+                .filter(|invocation| {
+                    let label = format!("cargo {}", invocation.args().join(" "));
+                    fuzzy::score(&self.run_task.query, &label).is_some()
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        let items: Vec<QuickPickItem> = tasks
+            .iter()
+            .map(|invocation| QuickPickItem {
+                icon: match invocation.action {
+                    Action::Build => "🔨",
+                    Action::Run => "▶",
+                    Action::Test => "✔",
+                    Action::Clean => "🗑",
+                },
+                label: format!("cargo {}", invocation.args().join(" ")),
+                detail: String::new(),
+            })
+            .collect();
+        let empty_text = match &self.cargo.metadata {
+            _ if self.cargo.pending.is_some() => "Loading cargo metadata...",
+            Some(Err(e)) => e.as_str(),
+            _ => "No matching tasks",
+        };
+
+        let run_task = &mut self.run_task;
+        let response = QuickPick::new(
+            "run_task",
+            &mut run_task.query,
+            &items,
+            &mut run_task.selected,
+        )
+        .hint_text("Select the task to run")
+        .empty_text(empty_text)
+        .show(ctx);
+
+        if response.query_changed {
+            run_task.selected = 0;
+        }
+        if let Some(index) = response.accepted {
+            run_task.is_open = false;
+            self.run_cargo(ctx, &tasks[index]);
+        } else if response.dismissed {
+            run_task.is_open = false;
+        }
+    }
+
+    // === Sessions ===
+
+    /// The open files and layout, as a session
+    fn session(&self) -> Session {
+        let files: Vec<PathBuf> = self
+            .open_files
+            .iter()
+            .filter(|f| !f.untitled)
+            .map(|f| f.path.clone())
+            .collect();
+        Session {
+            workspace: self.workspace.clone(),
+            active: self
+                .open_files
+                .get(self.active_tab)
+                .map(|f| f.path.clone())
+                .filter(|path| files.contains(path)),
+            files,
+            layout: self.layout.clone(),
+        }
+    }
+
+    /// Store the open files and layout under `name`, replacing any session
+    /// with that name
+    fn save_session(&mut self, name: String) {
+        self.sessions.sessions.insert(name.clone(), self.session());
+        if let Err(e) = self.sessions.save() {
+            self.log_output(format!("Could not save the session: {e}"));
+        }
+        self.current_session = Some(name);
+        self.session_saved_at = Instant::now();
+    }
+
+    /// Keep the current session up to date with the open tabs, so a crash
+    /// loses at most a few seconds of it: a change to which files are open
+    /// is written at once, other changes once the autosave interval passed
+    fn autosave_session(&mut self, ctx: &egui::Context) {
+        let Some(name) = self.current_session.clone() else {
+            return;
+        };
+        let Some(saved) = self.sessions.sessions.get(&name) else {
+            return;
+        };
+        let session = self.session();
+        if *saved == session {
+            return;
+        }
+        let wait = SESSION_AUTOSAVE_INTERVAL.saturating_sub(self.session_saved_at.elapsed());
+        if saved.files != session.files || wait.is_zero() {
+            self.save_session(name);
+        } else {
+            ctx.request_repaint_after(wait);
+        }
+    }
+
+    /// Close the tabs that aren't part of the session `name` and open its
+    /// files, workspace and layout. Tabs with unsaved changes stay open.
+    fn switch_session(&mut self, ctx: &egui::Context, name: &str) {
+        let Some(session) = self.sessions.sessions.get(name).cloned() else {
+            return;
+        };
+        // Keep the session being left as it is now, to come back to
+        if let Some(current) = self.current_session.clone().filter(|c| c != name) {
+            if self.sessions.sessions.contains_key(&current) {
+                self.save_session(current);
+            }
+        }
+        self.set_activity(format!("switching to session {name}"));
+
+        if let Some(workspace) = &session.workspace {
+            if self.workspace.as_ref() != Some(workspace) && workspace.is_dir() {
+                self.set_workspace(workspace.clone());
+            }
+        }
+        let mut kept = 0;
+        for index in (0..self.open_files.len()).rev() {
+            let file = &self.open_files[index];
+            if session.files.contains(&file.path) {
+                continue;
+            }
+            if file.is_modified() {
+                kept += 1;
+            } else {
+                self.close_tab(index);
+            }
+        }
+        let mut missing = 0;
+        for path in &session.files {
+            if path.is_file() {
+                self.open_file(path.clone());
+            } else {
+                missing += 1;
+            }
+        }
+        if let Some(index) = session
+            .active
+            .and_then(|active| self.open_files.iter().position(|f| f.path == active))
+        {
+            self.active_tab = index;
+        }
+        self.apply_layout(ctx, session.layout);
+        self.current_session = Some(name.to_string());
+
+        if kept > 0 {
+            self.log_output(format!(
+                "Kept {kept} tab(s) with unsaved changes open when switching to session {name}"
+            ));
+        }
+        if missing > 0 {
+            self.log_output(format!(
+                "{missing} file(s) of session {name} no longer exist and were skipped"
+            ));
+        }
+        self.announce(format!("Switched to session {name}"), None);
+    }
+
+    /// File > Save Session As: asks for the session's name
+    fn render_save_session(&mut self, ctx: &egui::Context) {
+        let colors = palette(&ctx.style().visuals);
+        let mut save = false;
+        let mut cancel = ctx.input(|i| i.key_pressed(egui::Key::Escape));
+        let Some(name) = &mut self.session_name else {
+            return;
+        };
+
+        egui::Area::new(egui::Id::new("save_session_dialog"))
+            .anchor(egui::Align2::CENTER_TOP, Vec2::new(0.0, 50.0))
+            .show(ctx, |ui| {
+                Frame::none()
+                    .fill(colors.find_panel_bg)
+                    .inner_margin(Margin::same(12.0))
+                    .rounding(4.0)
+                    .shadow(egui::epaint::Shadow {
+                        extrusion: 8.0,
+                        color: Color32::from_black_alpha(100),
+                    })
+                    .show(ui, |ui| {
+                        ui.label(RichText::new("Save Session").size(fonts::HEADING));
+                        ui.add_space(8.0);
+                        let input = ui.add(
+                            TextEdit::singleline(name)
+                                .desired_width(320.0)
+                                .hint_text("Session name"),
+                        );
+                        input.request_focus();
+                        if input.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                            save = true;
+                        }
+                        if self.sessions.sessions.contains_key(name.trim()) {
+                            ui.label(RichText::new("Replaces the session with this name").weak());
+                        }
+                        ui.add_space(4.0);
+                        ui.horizontal(|ui| {
+                            save |= ui
+                                .add_enabled(!name.trim().is_empty(), egui::Button::new("Save"))
+                                .clicked();
+                            cancel |= ui.button("Cancel").clicked();
+                        });
+                    });
+            });
+
+        if cancel {
+            self.session_name = None;
+        } else if save {
+            let name = name.trim().to_string();
+            if !name.is_empty() {
+                self.session_name = None;
+                self.save_session(name.clone());
+                self.announce(format!("Saved session {name}"), None);
+            }
+        }
+    }
+
+    /// File > Switch Session: the saved sessions in a picker
+    fn render_session_picker(&mut self, ctx: &egui::Context) {
+        let picker = &mut self.session_picker;
+        let names: Vec<&String> = self
+            .sessions
+            .sessions
+            .keys()
+            .filter(|name| fuzzy::score(&picker.query, name).is_some())
+            .collect();
+        let items: Vec<QuickPickItem> = names
+            .iter()
+            .map(|&name| {
+                let session = &self.sessions.sessions[name];
+                let files = match session.files.len() {
+                    1 => "1 file".to_string(),
+                    n => format!("{n} files"),
+                };
+                let folder = session
+                    .workspace
+                    .as_ref()
+                    .and_then(|w| w.file_name())
+                    .map(|w| format!("{} · ", w.to_string_lossy()))
+                    .unwrap_or_default();
+                QuickPickItem {
+                    icon: if self.current_session.as_ref() == Some(name) {
+                        "✔"
+                    } else {
+                        ""
+                    },
+                    label: name.clone(),
+                    detail: format!("{folder}{files}"),
+                }
+            })
+            .collect();
+        let names: Vec<String> = names.into_iter().cloned().collect();
+
+        let response = QuickPick::new(
+            "session_picker",
+            &mut picker.query,
+            &items,
+            &mut picker.selected,
+        )
+        .hint_text("Select the session to switch to")
+        .empty_text("No matching sessions")
+        .show(ctx);
+
+        if response.query_changed {
+            picker.selected = 0;
+        }
+        if let Some(index) = response.accepted {
+            picker.is_open = false;
+            self.switch_session(ctx, &names[index]);
+        } else if response.dismissed {
+            picker.is_open = false;
+        }
+    }
+
+    // === Encodings ===
+
+    fn open_encoding_picker(&mut self, action: Option<EncodingAction>) {
+        self.encoding_picker.picker.open();
+        self.encoding_picker.action = action;
+    }
+
+    /// Reopen / Save with Encoding: the encodings, the active file's marked.
+    /// From the status bar it first asks which of the two to do.
+    fn render_encoding_picker(&mut self, ctx: &egui::Context) {
+        let Some(current) = self.open_files.get(self.active_tab).map(|f| f.encoding) else {
+            self.encoding_picker.picker.is_open = false;
+            return;
+        };
+        let state = &mut self.encoding_picker;
+        let picker = &mut state.picker;
+        let (items, hint_text): (Vec<QuickPickItem>, _) = match state.action {
+            None => (
+                [Command::ReopenWithEncoding, Command::SaveWithEncoding]
+                    .iter()
+                    .filter(|command| fuzzy::score(&picker.query, command.label()).is_some())
+                    .map(|command| QuickPickItem {
+                        icon: "",
+                        label: command.label().trim_end_matches("...").to_string(),
+                        detail: String::new(),
+                    })
+                    .collect(),
+                "Select an action",
+            ),
+            Some(action) => (
+                TextEncoding::ALL
+                    .iter()
+                    .filter(|encoding| fuzzy::score(&picker.query, encoding.label()).is_some())
+                    .map(|&encoding| QuickPickItem {
+                        icon: if encoding == current { "✔" } else { "" },
+                        label: encoding.label().to_string(),
+                        detail: String::new(),
+                    })
+                    .collect(),
+                match action {
+                    EncodingAction::Reopen => "Select the encoding to reopen the file with",
+                    EncodingAction::Save => "Select the encoding to save the file with",
+                },
+            ),
+        };
+
+        let response = QuickPick::new(
+            "encoding_picker",
+            &mut picker.query,
+            &items,
+            &mut picker.selected,
+        )
+        .hint_text(hint_text)
+        .empty_text("No matching encodings")
+        .show(ctx);
+
+        if response.query_changed {
+            picker.selected = 0;
+        }
+        if response.dismissed {
+            picker.is_open = false;
+            return;
+        }
+        let Some(index) = response.accepted else {
+            return;
+        };
+        let label = &items[index].label;
+        match state.action {
+            None => {
+                let action = if label.starts_with("Reopen") {
+                    EncodingAction::Reopen
+                } else {
+                    EncodingAction::Save
+                };
+                let untitled = self.open_files[self.active_tab].untitled;
+                if action == EncodingAction::Reopen && untitled {
+                    picker.is_open = false;
+                    self.announce("The file isn't on disk yet", Some(Cue::Problem));
+                } else {
+                    self.open_encoding_picker(Some(action));
+                }
+            }
+            Some(action) => {
+                picker.is_open = false;
+                let encoding = TextEncoding::ALL
+                    .into_iter()
+                    .find(|encoding| encoding.label() == label)
+                    .unwrap_or_default();
+                match action {
+                    EncodingAction::Reopen => self.reopen_active_with_encoding(encoding),
+                    EncodingAction::Save => self.save_with_encoding(encoding),
+                }
+            }
+        }
+    }
+
+    /// Read the active file again as `encoding`. Unsaved changes would be
+    /// lost, so they have to be saved first.
+    fn reopen_active_with_encoding(&mut self, encoding: TextEncoding) {
+        let Some(file) = self.open_files.get_mut(self.active_tab) else {
+            return;
+        };
+        if file.is_modified() {
+            let message = format!("Save {} before reopening it", file.name());
+            self.announce(message, Some(Cue::Problem));
+            return;
+        }
+        let name = file.name();
+        match file.reopen_with_encoding(encoding) {
+            Ok(()) => self.announce(
+                format!("Reopened {name} as {}", encoding.label()),
+                Some(Cue::Success),
+            ),
+            Err(e) => self.announce(
+                format!("Could not reopen {name} as {}: {e}", encoding.label()),
+                Some(Cue::Problem),
+            ),
+        }
+    }
+
+    /// Write the active file as `encoding`, which later saves keep. Text the
+    /// encoding can't store leaves the file as it is.
+    fn save_with_encoding(&mut self, encoding: TextEncoding) {
+        let Some(file) = self.open_files.get_mut(self.active_tab) else {
+            return;
+        };
+        if let Err(e) = encoding.encode(&file.buffer.to_string()) {
+            let message = format!(
+                "Could not save {} as {}: {e}",
+                file.name(),
+                encoding.label()
+            );
+            self.announce(message, Some(Cue::Problem));
+            return;
+        }
+        file.encoding = encoding;
+        self.save_current_file();
+    }
+
+    /// Help > Keyboard Shortcuts: every command with a shortcut, by menu
+    fn render_keyboard_shortcuts(&mut self, ctx: &egui::Context) {
+        egui::Window::new("Keyboard Shortcuts")
+            .open(&mut self.show_shortcuts)
+            .default_size([420.0, 480.0])
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ScrollArea::vertical().show(ui, |ui| {
+                    egui::Grid::new("keyboard_shortcuts")
+                        .num_columns(2)
+                        .spacing([24.0, 4.0])
+                        .striped(true)
+                        .show(ui, |ui| {
+                            for (menu, command) in commands::all() {
+                                let Some(binding) = self.keybindings.get(command) else {
+                                    continue;
+                                };
+                                ui.label(format!("{}: {}", menu.label(), command.label()));
+                                ui.label(RichText::new(binding.format(ctx)).monospace());
+                                ui.end_row();
+                            }
+                        });
+                });
+            });
+    }
+
+    fn render_about(&mut self, ctx: &egui::Context) {
+        egui::Window::new("About")
+            .open(&mut self.show_about)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.heading(format!("Rust Code Editor {}", env!("CARGO_PKG_VERSION")));
+                ui.label(env!("CARGO_PKG_DESCRIPTION"));
+                ui.hyperlink(env!("CARGO_PKG_REPOSITORY"));
+            });
+    }
+
+    // === Activity Bar ===
+
+    fn render_activity_bar(&mut self, ctx: &egui::Context) {
+        let colors = palette(&ctx.style().visuals);
+        egui::SidePanel::left("activity_bar")
+            .resizable(false)
+            .exact_width(layout::ACTIVITY_BAR_WIDTH)
+            .frame(Frame::none().fill(colors.activity_bar_bg))
+            .show(ctx, |ui| {
+                let response = ActivityBar::new(self.active_activity)
+                    .git_changes(0)
+                    .show(ui);
+
+                // Clicking the active view hides the sidebar, clicking
+                // another one shows it
+                if let Some(item) = response.clicked_item {
+                    let shown = if self.sidebar_overlaid() {
+                        self.sidebar_overlay
+                    } else {
+                        self.layout.show_sidebar
+                    };
+                    if item == self.active_activity || !shown {
+                        self.toggle_sidebar();
+                    }
+                    self.active_activity = item;
+                }
+            });
+    }
+
+    // === Sidebar ===
+
+    fn render_sidebar(&mut self, ctx: &egui::Context) {
+        if self.sidebar_overlaid() {
+            if self.sidebar_overlay {
+                self.render_sidebar_overlay(ctx);
+            }
+            return;
+        }
+        if !self.layout.show_sidebar {
+            return;
+        }
+        let colors = palette(&ctx.style().visuals);
+        egui::SidePanel::left("explorer")
+            .resizable(true)
+            .default_width(self.layout.sidebar_width)
+            .min_width(layout::SIDEBAR_MIN_WIDTH)
+            .frame(
+                Frame::none()
+                    .fill(colors.panel_bg)
+                    .inner_margin(Margin::same(0.0)),
+            )
+            .show(ctx, |ui| self.render_sidebar_contents(ui));
+    }
+
+    /// The sidebar over the editor, for windows too narrow to dock it.
+    /// Clicking outside of it closes it, except on the activity bar.
+    fn render_sidebar_overlay(&mut self, ctx: &egui::Context) {
+        let colors = palette(&ctx.style().visuals);
+        let rect = ctx.available_rect();
+        let width = self
+            .layout
+            .sidebar_width
+            .min(rect.width() - layout::MINIMAP_WIDTH)
+            .max(layout::SIDEBAR_MIN_WIDTH);
+        let area = egui::Area::new(egui::Id::new("sidebar_overlay"))
+            .order(egui::Order::Foreground)
+            .fixed_pos(rect.left_top())
+            .show(ctx, |ui| {
+                Frame::none()
+                    .fill(colors.panel_bg)
+                    .shadow(egui::epaint::Shadow {
+                        extrusion: 8.0,
+                        color: Color32::from_black_alpha(100),
+                    })
+                    .show(ui, |ui| {
+                        ui.set_width(width);
+                        ui.set_height(rect.height());
+                        self.render_sidebar_contents(ui);
+                    });
+            });
+        let on_activity_bar = ctx.input(|i| {
+            i.pointer
+                .interact_pos()
+                .is_some_and(|pos| pos.x < rect.left())
+        });
+        if area.response.clicked_elsewhere() && !on_activity_bar {
+            self.sidebar_overlay = false;
+        }
+    }
+
+    fn render_sidebar_contents(&mut self, ui: &mut egui::Ui) {
+        if self.active_activity == ActivityItem::Cargo {
+            self.render_cargo_panel(ui);
+            return;
+        }
+        if self.active_activity == ActivityItem::Git {
+            self.render_source_control(ui);
+            return;
+        }
+        egui::TopBottomPanel::bottom("outline")
+            .resizable(true)
+            .default_height(self.layout.outline_height)
+            .frame(Frame::none())
+            .show_inside(ui, |ui| self.render_outline(ui));
+        self.render_explorer_header(ui);
+        ui.separator();
+        self.render_file_tree(ui);
+    }
+
+    /// Whether the window is too narrow to dock the sidebar
+    fn sidebar_overlaid(&self) -> bool {
+        let below = self.settings.sidebar_overlay_below;
+        below > 0.0 && self.window_width < below
+    }
+
+    /// Whether the window is too narrow to dock the minimap
+    fn minimap_overlaid(&self) -> bool {
+        let below = self.settings.minimap_overlay_below;
+        below > 0.0 && self.window_width < below
+    }
+
+    /// Show or hide the sidebar, over the editor in a narrow window
+    fn toggle_sidebar(&mut self) {
+        if self.sidebar_overlaid() {
+            self.sidebar_overlay = !self.sidebar_overlay;
+        } else {
+            self.layout.show_sidebar = !self.layout.show_sidebar;
+        }
+    }
+
+    fn render_explorer_header(&mut self, ui: &mut egui::Ui) {
+        let colors = palette(ui.visuals());
+        let mut toggle_hidden = false;
+        Frame::none()
+            .inner_margin(Margin::symmetric(12.0, 8.0))
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(
+                        RichText::new("EXPLORER")
+                            .size(fonts::EXPLORER_HEADER)
+                            .color(colors.text_muted)
+                            .strong(),
+                    );
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        let shown = self.settings.show_hidden_files;
+                        toggle_hidden = ui
+                            .selectable_label(shown, RichText::new(".*").size(fonts::SMALL))
+                            .on_hover_text(Command::ShowHiddenFiles.label())
+                            .clicked();
+                    });
+                });
+            });
+        if toggle_hidden {
+            self.toggle_hidden_files();
+        }
+    }
+
+    fn render_file_tree(&mut self, ui: &mut egui::Ui) {
+        let mut file_to_open: Option<PathBuf> = None;
+        let mut file_to_rename: Option<PathBuf> = None;
+        let active_path = self.open_files.get(self.active_tab).map(|f| f.path.clone());
+
+        ScrollArea::vertical()
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                ui.add_space(8.0);
+                ui.spacing_mut().item_spacing.y = 0.0;
+
+                for node in &self.tree {
+                    Self::render_file_node(
+                        ui,
+                        node,
+                        &mut file_to_open,
+                        &mut file_to_rename,
+                        active_path.as_ref(),
+                        0,
+                    );
+                }
+                ui.add_space(8.0);
+            });
+
+        if let Some(path) = file_to_open {
+            self.open_file(path);
+        }
+        if let Some(path) = file_to_rename {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            self.rename_file = Some(RenameFileState {
+                name: name.to_string(),
+                path,
+                error: None,
+            });
+        }
+    }
+
+    fn render_file_node(
+        ui: &mut egui::Ui,
+        node: &FileNode,
+        file_to_open: &mut Option<PathBuf>,
+        file_to_rename: &mut Option<PathBuf>,
+        active_path: Option<&PathBuf>,
+        depth: usize,
+    ) {
+        let colors = palette(ui.visuals());
+        let indent = depth as f32 * layout::INDENT_SIZE;
+        let name = node.name();
+        let item_height = layout::LINE_HEIGHT + 2.0;
+
+        if node.is_dir {
+            ui.horizontal(|ui| {
+                ui.add_space(indent);
+                let header = format!("{} {}", file_icons::FOLDER_ICON, name);
+                egui::CollapsingHeader::new(RichText::new(header).size(fonts::BODY))
+                    .default_open(depth == 0)
+                    .show(ui, |ui| {
+                        for child in &node.children {
+                            Self::render_file_node(
+                                ui,
+                                child,
+                                file_to_open,
+                                file_to_rename,
+                                active_path,
+                                depth + 1,
+                            );
+                        }
+                    });
+            });
+        } else {
+            let is_selected = active_path == Some(&node.path);
+            let available_width = ui.available_width();
+
+            let (rect, response) = ui.allocate_exact_size(
+                Vec2::new(available_width, item_height),
+                egui::Sense::click(),
+            );
+
+            // Draw background for hover/selection
+            let bg_color = if is_selected {
+                colors.file_tree_selected
+            } else if response.hovered() {
+                colors.file_tree_hover
+            } else {
+                Color32::TRANSPARENT
+            };
+
+            if bg_color != Color32::TRANSPARENT {
+                ui.painter().rect_filled(rect, 0.0, bg_color);
+            }
+
+            // Draw icon and text
+            let icon = file_icons::get_icon(&name);
+            let text_pos = Pos2::new(
+                rect.left() + indent + layout::INDENT_SIZE + 4.0,
+                rect.center().y,
+            );
+            ui.painter().text(
+                text_pos,
+                egui::Align2::LEFT_CENTER,
+                format!("{} {}", icon, name),
+                FontId::proportional(fonts::BODY),
+                colors.text_primary,
+            );
+
+            if response.clicked() {
+                *file_to_open = Some(node.path.clone());
+            }
+            response.context_menu(|ui| {
+                if ui.button("Rename...").clicked() {
+                    *file_to_rename = Some(node.path.clone());
+                    ui.close_menu();
+                }
+            });
+        }
+    }
+
+    /// Declarations of the active file; clicking one moves the cursor there
+    fn render_outline(&mut self, ui: &mut egui::Ui) {
+        let file = self.open_files.get(self.active_tab);
+        let symbols = file.map_or(&[][..], |f| f.lsp_symbols.as_deref().unwrap_or(&f.symbols));
+        let current = file.and_then(|f| outline::symbol_at_line(symbols, f.state.cursor().line));
+        let response = OutlinePanel::new(symbols).current(current).show(ui);
+
+        if let Some(index) = response.clicked {
+            let symbol = &symbols[index];
+            let target = GotoTarget::Line {
+                line: symbol.line + 1,
+                column: Some(symbol.column + 1),
+            };
+            self.goto(target);
+        }
+    }
+
+    // === Editor ===
+
+    fn render_editor(&mut self, ctx: &egui::Context) {
+        let colors = palette(&ctx.style().visuals);
+        egui::CentralPanel::default()
+            .frame(
+                Frame::none()
+                    .fill(colors.editor_bg)
+                    .inner_margin(Margin::same(0.0)),
+            )
+            .show(ctx, |ui| {
+                self.render_tab_bar(ui);
+                ui.separator();
+                if self.log_viewer.is_some() {
+                    self.render_log_viewer(ui);
+                } else if self.folder_compare.is_some() {
+                    self.render_folder_compare(ui);
+                } else if self.regex_playground.is_some() {
+                    self.render_regex_playground(ui);
+                } else if self.dependency_graph.is_some() {
+                    self.render_dependency_graph(ui);
+                } else {
+                    self.render_breadcrumbs(ui);
+                    self.render_editor_content(ui);
+                }
+            });
+    }
+
+    fn render_breadcrumbs(&mut self, ui: &mut egui::Ui) {
+        let Some(file) = self.open_files.get(self.active_tab) else {
+            return;
+        };
+
+        // Folders and file relative to the workspace, each with the entries of
+        // its folder as siblings
+        let relative =
+            self.workspace
+                .as_ref()
+                .zip(self.tree.first())
+                .and_then(|(workspace, root)| {
+                    Some((workspace, root, file.path.strip_prefix(workspace).ok()?))
+                });
+        let path = match relative {
+            Some((workspace, root, relative)) => {
+                let mut folder = Some(root);
+                let mut path = workspace.clone();
+                relative
+                    .components()
+                    .map(|component| {
+                        path.push(component);
+                        let siblings = folder.map(|f| &f.children[..]);
+                        folder = folder.and_then(|f| f.children.iter().find(|c| c.path == path));
+                        PathSegment {
+                            name: component.as_os_str().to_string_lossy().into_owned(),
+                            path: path.clone(),
+                            siblings,
+                        }
+                    })
+                    .collect()
+            }
+            None => vec![PathSegment {
+                name: file.name(),
+                path: file.path.clone(),
+                siblings: None,
+            }],
+        };
+
+        let symbols = file.lsp_symbols.as_deref().unwrap_or(&file.symbols);
+        let chain = outline::symbol_chain(symbols, file.state.cursor().line.saturating_sub(1));
+        let response = Breadcrumbs::new(path).symbols(symbols, chain).show(ui);
+
+        if let Some(index) = response.goto_symbol {
+            let symbol = &symbols[index];
+            let target = GotoTarget::Line {
+                line: symbol.line + 1,
+                column: Some(symbol.column + 1),
+            };
+            self.goto(target);
+        }
+        if let Some(path) = response.open_file {
+            self.open_file(path);
+        }
+    }
+
+    fn render_tab_bar(&mut self, ui: &mut egui::Ui) {
+        let colors = palette(ui.visuals());
+        let tabs: Vec<Tab> = self
+            .open_files
+            .iter()
+            .enumerate()
+            .map(|(i, f)| {
+                Tab::new(f.name(), file_icons::get_icon(&f.name()))
+                    .modified(f.is_modified())
+                    .following(f.follow)
+                    .missing(f.missing)
+                    // New files are already called Untitled-N
+                    .untitled(f.untitled && f.path.is_absolute())
+                    .split(self.split.is_some_and(|split| split.tab == i))
+            })
+            .collect();
+
+        if tabs.is_empty() {
+            return;
+        }
+
+        Frame::none()
+            .fill(colors.panel_bg)
+            .inner_margin(Margin::symmetric(0.0, 4.0))
+            .show(ui, |ui| {
+                let response = TabBar::new(tabs, self.active_tab).show(ui);
+
+                if let Some(idx) = response.activated {
+                    self.activate_tab(idx);
+                }
+                if let Some(idx) = response.dragged {
+                    let (time, pos) = ui.input(|i| (i.time, i.pointer.interact_pos()));
+                    let gesture = pos.and_then(|pos| self.tab_drag.push(time, (pos.x, pos.y)));
+                    if gesture == Some(Gesture::Shake) && self.settings.mouse_gestures {
+                        self.activate_tab(idx);
+                        self.split = None;
+                    }
+                }
+                if let Some(idx) = response.released {
+                    let (time, pos) = ui.input(|i| (i.time, i.pointer.interact_pos()));
+                    let gesture = self
+                        .tab_drag
+                        .release(time)
+                        .filter(|_| self.settings.mouse_gestures);
+                    match gesture {
+                        Some(Gesture::FlickUp) if !self.open_files[idx].is_modified() => {
+                            self.close_tab(idx);
+                        }
+                        Some(Gesture::FlickDown) => {
+                            let right = self.split.map_or(true, |split| !split.active_right);
+                            self.move_tab_to_group(idx, right);
+                        }
+                        Some(_) => {}
+                        None => {
+                            // Dropped onto the editor rather than along the bar
+                            let below = response.rect.bottom() + layout::TAB_DROP_DISTANCE;
+                            if let Some(pos) = pos.filter(|pos| pos.y > below) {
+                                self.drop_tab(idx, pos);
+                            }
+                        }
+                    }
+                }
+                if let Some(idx) = response.closed {
+                    self.close_tab(idx);
+                }
+            });
+    }
+
+    /// Show a tab in the active editor group
+    fn activate_tab(&mut self, idx: usize) {
+        self.active_tab = idx;
+        self.log_viewer = None;
+        self.folder_compare = None;
+        self.regex_playground = None;
+        self.dependency_graph = None;
+    }
+
+    // === Editor Groups ===
+
+    /// View > Split Editor: show the active tab in a second group on the
+    /// right, or close the second group
+    fn toggle_split(&mut self) {
+        self.split = match self.split {
+            Some(_) => None,
+            None => (!self.open_files.is_empty()).then_some(EditorSplit {
+                tab: self.active_tab,
+                active_right: false,
+            }),
+        };
+    }
+
+    /// Show `tab` in the group on the right or on the left, splitting the
+    /// editor first if needed
+    fn move_tab_to_group(&mut self, tab: usize, right: bool) {
+        match &mut self.split {
+            Some(split) if split.active_right == right => self.activate_tab(tab),
+            Some(split) => split.tab = tab,
+            None => {
+                self.split = Some(EditorSplit {
+                    tab,
+                    active_right: !right,
+                })
+            }
+        }
+    }
+
+    /// A tab dragged down onto the editor goes to the group it was dropped
+    /// on, or to a new group on the right
+    fn drop_tab(&mut self, tab: usize, pos: Pos2) {
+        let right = match self.split {
+            Some(split) => split.active_right != self.split_rect.contains(pos),
+            None => true,
+        };
+        self.move_tab_to_group(tab, right);
+    }
+
+    /// Make the other group the one keyboard input goes to
+    fn focus_split(&mut self) {
+        if let Some(split) = &mut self.split {
+            (split.tab, self.active_tab) = (self.active_tab, split.tab);
+            split.active_right = !split.active_right;
+        }
+    }
+
+    fn render_editor_content(&mut self, ui: &mut egui::Ui) {
+        if self
+            .open_files
+            .get(self.active_tab)
+            .is_some_and(|f| f.missing)
+        {
+            self.render_missing_file_banner(ui);
+        }
+        if self.open_files.is_empty() {
+            self.render_welcome_screen(ui);
+            return;
+        }
+
+        let active_idx = self.active_tab;
+        let font_size = self.open_files[active_idx].state.font_size;
+        let zoom = font_size.map_or(1.0, |size| size / fonts::BODY);
+        let line_height = layout::LINE_HEIGHT * zoom;
+        let available_height = ui.available_height();
+        let (show_minimap, minimap_overlay) = if self.minimap_overlaid() {
+            (false, self.minimap_overlay)
+        } else {
+            (self.layout.show_minimap, false)
+        };
+        let pixel_snapping = self.settings.text_rendering.pixel_snapping;
+        let scroll_offset_y = self.editor_scroll_offset.y;
+        let show_preview = self.preview.is_open && self.open_files[active_idx].is_markdown();
+        let show_response = self.rest.is_open && self.open_files[active_idx].is_http();
+
+        // Get file info for line numbers and minimap
+        let (total_lines, visible_lines, current_line) = {
+            let file = &self.open_files[active_idx];
+            (
+                file.buffer.len_lines(),
+                file.state.visible_lines,
+                file.state.cursor().line,
+            )
+        };
+
+        self.open_files[active_idx].update_fold_ranges();
+        let mut gutter_decorations = self.diagnostic_decorations(active_idx, palette(ui.visuals()));
+        gutter_decorations.extend(self.control_char_decorations(active_idx, palette(ui.visuals())));
+        gutter_decorations.extend(self.bookmark_decorations(active_idx, palette(ui.visuals())));
+        gutter_decorations.extend(self.fold_decorations(active_idx));
+        let mut minimap_clicked_line: Option<usize> = None;
+        // Rows wrapped in the last frame; the editor rebuilds them after the gutter is drawn
+        let wrap = self.open_files[active_idx].state.wrap.clone();
+        let mut editor_rect = egui::Rect::NOTHING;
+        let split = self.split.filter(|split| split.tab < self.open_files.len());
+
+        ui.horizontal(|ui| {
+            ui.spacing_mut().item_spacing = Vec2::ZERO;
+            let minimap_width = if show_minimap {
+                layout::MINIMAP_WIDTH
+            } else {
+                0.0
+            };
+            let group_width = (ui.available_width() - minimap_width) / 2.0;
+
+            // === Other Editor Group, on the left ===
+            if let Some(split) = split.filter(|split| split.active_right) {
+                self.render_split_group(ui, split.tab, group_width);
+                ui.separator();
+            }
+
+            // === Line Numbers Gutter ===
+            let gutter = LineNumbersGutter::new(total_lines)
+                .wrap(wrap.as_deref())
+                .reserve(GutterColumn::Diagnostic)
+                .reserve(GutterColumn::Fold)
+                .decorations(gutter_decorations)
+                .current_line(current_line)
+                .scroll_offset(scroll_offset_y)
+                .line_height(line_height)
+                .font_size(fonts::LINE_NUMBER * zoom)
+                .visible_height(available_height)
+                .pixel_snapping(pixel_snapping)
+                .show(ui);
+            match gutter.clicked {
+                Some((line, GutterColumn::Diagnostic)) => {
+                    self.goto(GotoTarget::Line { line, column: None });
+                }
+                Some((line, GutterColumn::Fold)) => {
+                    self.open_files[active_idx].toggle_fold(line - 1);
+                }
+                Some((line, GutterColumn::Bookmark)) => {
+                    let file = &mut self.open_files[active_idx];
+                    let revision = file.state.revision;
+                    file.state
+                        .bookmarks
+                        .toggle(&file.buffer, revision, line - 1);
+                }
+                _ => {}
+            }
+
+            // === Main Editor Area ===
+            // Editor fills remaining space between gutter and minimap
+            let mut editor_width = ui.available_width() - minimap_width;
+            if split.is_some_and(|split| !split.active_right) {
+                editor_width -= group_width;
+            }
+            if show_preview || show_response {
+                editor_width /= 2.0;
+            }
+
+            editor_rect = ui
+                .vertical(|ui| {
+                    ui.set_width(editor_width);
+                    ui.set_height(available_height);
+                    self.render_text_editor(ui, line_height);
+                })
+                .response
+                .rect;
+
+            // === Markdown Preview ===
+            if show_preview {
+                ui.separator();
+                ui.vertical(|ui| {
+                    ui.set_width(editor_width - ui.spacing().item_spacing.x);
+                    ui.set_height(available_height);
+                    self.render_markdown_preview(ui, active_idx);
+                });
+            }
+
+            // === HTTP Response ===
+            if show_response {
+                ui.separator();
+                ui.vertical(|ui| {
+                    ui.set_width(editor_width - ui.spacing().item_spacing.x);
+                    ui.set_height(available_height);
+                    self.render_http_response(ui);
+                });
+            }
+
+            // === Other Editor Group, on the right ===
+            if let Some(split) = split.filter(|split| !split.active_right) {
+                ui.separator();
+                let width = ui.available_width() - minimap_width;
+                self.render_split_group(ui, split.tab, width);
+            }
+
+            // === Minimap ===
+            if show_minimap {
+                minimap_clicked_line = self.render_minimap(ui, visible_lines, current_line);
+            }
+        });
+
+        // In a narrow window the minimap opens over the right edge of the editor
+        if minimap_overlay {
+            egui::Area::new(egui::Id::new("minimap_overlay"))
+                .order(egui::Order::Foreground)
+                .fixed_pos(editor_rect.right_top() - Vec2::new(layout::MINIMAP_WIDTH, 0.0))
+                .show(ui.ctx(), |ui| {
+                    ui.set_max_height(editor_rect.height());
+                    minimap_clicked_line = self.render_minimap(ui, visible_lines, current_line);
+                });
+        }
+
+        // Handle minimap click
+        if let Some(clicked_line) = minimap_clicked_line {
+            self.record_jump();
+            let line_idx = clicked_line.saturating_sub(1);
+            let row = wrap.map_or(line_idx, |wrap| wrap.line_rows(line_idx).start);
+            let target_y = row as f32 * line_height;
+            self.editor_scroll_offset.y = target_y;
+        }
+    }
+
+    /// The file of the other editor group, with highlighting but without the
+    /// language features of the active one. Clicking into it makes it the
+    /// active group.
+    fn render_split_group(&mut self, ui: &mut egui::Ui, tab: usize, width: f32) {
+        self.request_highlighting(ui.ctx(), tab, ui.visuals().dark_mode);
+        let colors = palette(ui.visuals());
+        let word_wrap = self.word_wrap(tab);
+        let add_caret_with_ctrl = self.settings.multi_cursor_modifier == MultiCursorModifier::Ctrl;
+        let pixel_snapping = self.settings.text_rendering.pixel_snapping;
+        let render_whitespace = self.settings.render_whitespace;
+        let file = &mut self.open_files[tab];
+        let font_size = file.state.font_size.unwrap_or(fonts::BODY);
+        let zoom = font_size / fonts::BODY;
+        let mut close = false;
+
+        let rect = ui
+            .vertical(|ui| {
+                ui.set_width(width);
+                ui.set_height(ui.available_height());
+                Frame::none()
+                    .fill(colors.panel_bg)
+                    .inner_margin(Margin::symmetric(8.0, 2.0))
+                    .show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            let name = file.name();
+                            ui.label(
+                                RichText::new(format!("{} {name}", file_icons::get_icon(&name)))
+                                    .size(fonts::BODY)
+                                    .color(colors.text_secondary),
+                            );
+                            ui.with_layout(
+                                egui::Layout::right_to_left(egui::Align::Center),
+                                |ui| {
+                                    close =
+                                        ui.small_button("×").on_hover_text("Close Split").clicked();
+                                },
+                            );
+                        });
+                    });
+
+                let mut scroll_area = ScrollArea::new([!word_wrap, true])
+                    .id_source("split_editor_scroll")
+                    .auto_shrink([false, false]);
+                if word_wrap {
+                    scroll_area = scroll_area.horizontal_scroll_offset(0.0);
+                }
+                let output = scroll_area.show(ui, |ui| {
+                    CodeEditor::new(
+                        egui::Id::new("split_editor"),
+                        &mut file.buffer,
+                        &mut file.state,
+                    )
+                    .line_styles(&file.line_styles)
+                    .line_height(layout::LINE_HEIGHT * zoom)
+                    .font_size(font_size)
+                    .pixel_snapping(pixel_snapping)
+                    .render_whitespace(render_whitespace)
+                    .word_wrap(word_wrap)
+                    .add_caret_with_ctrl(add_caret_with_ctrl)
+                    .show(ui)
+                });
+                if output.inner.changed {
+                    file.state.is_modified = file.buffer != file.original_content.as_str();
+                }
+            })
+            .response
+            .rect;
+        self.split_rect = rect;
+
+        if close {
+            self.split = None;
+        } else if ui.input(|i| i.pointer.primary_pressed()) && ui.rect_contains_pointer(rect) {
+            self.focus_split();
+            ui.ctx()
+                .memory_mut(|m| m.request_focus(egui::Id::new(CODE_EDITOR_ID)));
+        }
+    }
+
+    /// Minimap of the active file with the find matches marked; returns the
+    /// clicked line
+    fn render_minimap(
+        &mut self,
+        ui: &mut egui::Ui,
+        visible_lines: (usize, usize),
+        current_line: usize,
+    ) -> Option<usize> {
+        let find = &self.find_replace;
+        let file = &mut self.open_files[self.active_tab];
+        let match_lines: Vec<usize> = if find.is_open {
+            find.matches
+                .iter()
+                .map(|&(start, _)| file.buffer.char_to_line(start.min(file.buffer.len_chars())))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let current_match_line = match_lines.get(find.current_match).copied();
+        Minimap::new(&file.buffer, file.state.revision, &mut file.minimap)
+            .line_styles(&file.line_styles)
+            .visible_lines(visible_lines)
+            .current_line(current_line)
+            .find_matches(&match_lines, current_match_line)
+            .show(ui)
+            .clicked_line
+    }
+
+    fn render_markdown_preview(&mut self, ui: &mut egui::Ui, idx: usize) {
+        let preview = &mut self.preview;
+        let scroll_to =
+            (preview.sync_scroll && preview.editor_scrolled).then_some(preview.editor_fraction);
+        let source = self.open_files[idx].buffer.to_string();
+        let response = MarkdownPreview::new(egui::Id::new("markdown_preview"), &source)
+            .scroll_to(scroll_to)
+            .show(ui);
+
+        // Scrolling the preview by hand moves the editor along on the next frame
+        let preview_scrolled = (response.scroll_fraction - preview.preview_fraction).abs() > 1e-3;
+        if preview.sync_scroll && scroll_to.is_none() && preview_scrolled {
+            preview.scroll_editor_to = Some(response.scroll_fraction);
+        }
+        preview.preview_fraction = response.scroll_fraction;
+    }
+
+    /// Markdown files toggle the preview pane, HTML files open in the browser
+    fn open_preview(&mut self) {
+        match self.open_files.get(self.active_tab) {
+            Some(file) if file.is_html() => self.open_html_preview(),
+            _ => self.preview.is_open = !self.preview.is_open,
+        }
+    }
+
+    /// Open the active HTML file in the default browser through the local server,
+    /// which reloads the page on save
+    fn open_html_preview(&mut self) {
+        let Some(path) = self.open_files.get(self.active_tab).map(|f| f.path.clone()) else {
+            return;
+        };
+
+        let url = self.server.as_ref().and_then(|s| s.url_for(&path));
+        let url = match url {
+            Some(url) => url,
+            None => {
+                // Serve the workspace, or just the file's folder if it's outside of it
+                let root = match &self.workspace {
+                    Some(workspace) if path.starts_with(workspace) => workspace.clone(),
+                    _ => path.parent().map(PathBuf::from).unwrap_or_default(),
+                };
+                match StaticServer::start(root, 0) {
+                    Ok(started) => {
+                        let url = started.url_for(&path).unwrap_or_else(|| started.url());
+                        self.server = Some(started);
+                        url
+                    }
+                    Err(e) => {
+                        self.log_output(format!("Failed to start the preview server: {e}"));
+                        return;
+                    }
+                }
+            }
+        };
+
+        if let Err(e) = server::open_in_browser(&url) {
+            self.log_output(format!("Failed to open {url}: {e}"));
+        }
+    }
+
+    // === REST Client ===
+
+    /// Send a request block of the active `.http` file and open the response pane
+    fn send_http_request(&mut self, ctx: &egui::Context, index: usize) {
+        let Some(request) = self
+            .open_files
+            .get(self.active_tab)
+            .and_then(|f| f.http_requests.get(index))
+        else {
+            return;
+        };
+        self.rest = RestState {
+            is_open: true,
+            title: request.title(),
+            pending: Some(rest_client::send_in_background(
+                request.clone(),
+                ctx.clone(),
+            )),
+            result: None,
+        };
+    }
+
+    /// Send the request block the cursor is in
+    fn send_request_at_cursor(&mut self, ctx: &egui::Context) {
+        let Some(file) = self.open_files.get_mut(self.active_tab) else {
+            return;
+        };
+        if !file.is_http() {
+            return;
+        }
+        file.update_http_requests();
+        let line = file.state.cursor().line.saturating_sub(1);
+        let index = file.http_requests.iter().rposition(|r| r.line <= line);
+        if let Some(index) = index.or((!file.http_requests.is_empty()).then_some(0)) {
+            self.send_http_request(ctx, index);
+        }
+    }
+
+    fn render_http_response(&mut self, ui: &mut egui::Ui) {
+        if let Some(result) = self.rest.pending.as_ref().and_then(|rx| rx.try_recv().ok()) {
+            self.rest.result = Some(result);
+            self.rest.pending = None;
+        }
+        let response = HttpResponseView::new(&self.rest.title, self.rest.result.as_ref()).show(ui);
+        if response.close_clicked {
+            self.rest.is_open = false;
+        }
+    }
+
+    // === SQL Scratchpad ===
+
+    /// Pick the SQLite database the active `.sql` file runs against
+    fn connect_database(&mut self) -> Option<PathBuf> {
+        let path = self.open_files.get(self.active_tab)?.path.clone();
+        let mut dialog = FileDialog::new()
+            .set_title("Connect SQLite Database")
+            .add_filter("SQLite Database", &["db", "sqlite", "sqlite3", "db3"])
+            .add_filter("All Files", &["*"]);
+        if let Some(workspace) = &self.workspace {
+            dialog = dialog.set_directory(workspace);
+        }
+        let database = self.blocking_dialog(|| dialog.pick_file())?;
+        self.sql.databases.insert(path, database.clone());
+        Some(database)
+    }
+
+    /// Run the selection, or the statement under the cursor, against the
+    /// file's database, connecting first if needed
+    fn run_query(&mut self, ctx: &egui::Context) {
+        let Some(file) = self.open_files.get(self.active_tab) else {
+            return;
+        };
+        let database = match self.sql.databases.get(&file.path) {
+            Some(database) => database.clone(),
+            None => match self.connect_database() {
+                Some(database) => database,
+                None => return,
+            },
+        };
+
+        let file = &self.open_files[self.active_tab];
+        let cursor = file.state.cursor();
+        let query = if cursor.has_selection() {
+            file.buffer.slice(cursor.selection()).to_string()
+        } else {
+            let text = file.buffer.to_string();
+            let byte = file.buffer.char_to_byte(cursor.offset);
+            let ranges = sql::statement_ranges(&text);
+            match ranges.iter().find(|r| byte <= r.end).or(ranges.last()) {
+                Some(range) => text[range.clone()].to_string(),
+                None => return,
+            }
+        };
+
+        self.sql = SqlState {
+            is_open: true,
+            database: database
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string(),
+            pending: Some(sql::run_in_background(database, query, ctx.clone())),
+            result: None,
+            databases: std::mem::take(&mut self.sql.databases),
+        };
+    }
+
+    fn render_query_results(&mut self, ctx: &egui::Context) {
+        if let Some(result) = self.sql.pending.as_ref().and_then(|rx| rx.try_recv().ok()) {
+            self.sql.result = Some(result);
+            self.sql.pending = None;
+        }
+        let colors = palette(&ctx.style().visuals);
+        egui::TopBottomPanel::bottom("query_results")
+            .resizable(true)
+            .default_height(220.0)
+            .frame(
+                Frame::none()
+                    .fill(colors.panel_bg)
+                    .inner_margin(Margin::symmetric(8.0, 4.0)),
+            )
+            .show(ctx, |ui| {
+                let response =
+                    QueryResults::new(&self.sql.database, self.sql.result.as_ref()).show(ui);
+                if response.close_clicked {
+                    self.sql.is_open = false;
+                }
+            });
+    }
+
+    // === Log Viewer ===
+
+    fn open_log_file(&mut self, path: PathBuf) {
+        match LogFile::open(&path) {
+            Ok(log) => {
+                self.folder_compare = None;
+                self.regex_playground = None;
+                self.dependency_graph = None;
+                self.log_viewer = Some(LogViewerState {
+                    log,
+                    filter_text: String::new(),
+                    filter_error: None,
+                    follow: false,
+                });
+            }
+            Err(e) => self.log_output(format!("Failed to open {}: {e}", path.display())),
+        }
+    }
+
+    fn render_log_viewer(&mut self, ui: &mut egui::Ui) {
+        let Some(viewer) = &mut self.log_viewer else {
+            return;
+        };
+        if let Err(e) = viewer.log.refresh() {
+            let message = format!("Failed to read {}: {e}", viewer.log.path().display());
+            self.log_viewer = None;
+            self.log_output(message);
+            return;
+        }
+        if viewer.log.scan() {
+            ui.ctx().request_repaint();
+        } else if viewer.follow {
+            ui.ctx().request_repaint_after(FOLLOW_INTERVAL);
+        }
+
+        let response = LogView::new(&viewer.log, &mut viewer.filter_text, &mut viewer.follow)
+            .filter_error(viewer.filter_error.as_deref())
+            .show(ui);
+        if response.filter_changed {
+            let regex = if viewer.filter_text.is_empty() {
+                Ok(None)
+            } else {
+                regex::bytes::Regex::new(&viewer.filter_text).map(Some)
+            };
+            match regex {
+                Ok(regex) => {
+                    viewer.log.set_filter(regex);
+                    viewer.filter_error = None;
+                }
+                Err(e) => viewer.filter_error = Some(e.to_string()),
+            }
+        }
+        if response.close_clicked {
+            self.log_viewer = None;
+        }
+    }
+
+    // === New Project ===
+
+    fn open_new_project_dialog(&mut self) {
+        // Next to the open folder, or in the home folder
+        let location = self
+            .workspace
+            .as_ref()
+            .and_then(|w| w.parent())
+            .map(PathBuf::from)
+            .or_else(dirs::home_dir)
+            .unwrap_or_default();
+        self.new_project = Some(NewProjectState {
+            templates: project_template::templates(),
+            template: 0,
+            name: String::new(),
+            location: location.display().to_string(),
+            init_git: true,
+            error: None,
+        });
+    }
+
+    fn render_new_project_dialog(&mut self, ctx: &egui::Context) {
+        let colors = palette(&ctx.style().visuals);
+        let mut create = false;
+        let mut cancel = ctx.input(|i| i.key_pressed(egui::Key::Escape));
+        let mut browse = false;
+        let Some(dialog) = &mut self.new_project else {
+            return;
+        };
+
+        egui::Area::new(egui::Id::new("new_project_dialog"))
+            .anchor(egui::Align2::CENTER_TOP, Vec2::new(0.0, 50.0))
+            .show(ctx, |ui| {
+                Frame::none()
+                    .fill(colors.find_panel_bg)
+                    .inner_margin(Margin::same(12.0))
+                    .rounding(4.0)
+                    .shadow(egui::epaint::Shadow {
+                        extrusion: 8.0,
+                        color: Color32::from_black_alpha(100),
+                    })
+                    .show(ui, |ui| {
+                        ui.label(RichText::new("New Project").size(fonts::HEADING));
+                        ui.add_space(8.0);
+                        egui::Grid::new("new_project_form")
+                            .num_columns(2)
+                            .spacing([8.0, 8.0])
+                            .show(ui, |ui| {
+                                ui.label("Template");
+                                egui::ComboBox::from_id_source("new_project_template")
+                                    .width(320.0)
+                                    .selected_text(dialog.templates[dialog.template].name())
+                                    .show_ui(ui, |ui| {
+                                        for (i, template) in dialog.templates.iter().enumerate() {
+                                            ui.selectable_value(
+                                                &mut dialog.template,
+                                                i,
+                                                template.name(),
+                                            );
+                                        }
+                                    });
+                                ui.end_row();
+
+                                ui.label("Name");
+                                let name = ui.add(
+                                    TextEdit::singleline(&mut dialog.name)
+                                        .desired_width(320.0)
+                                        .hint_text("my-project"),
+                                );
+                                if name.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter))
+                                {
+                                    create = true;
+                                }
+                                ui.end_row();
+
+                                ui.label("Location");
+                                ui.horizontal(|ui| {
+                                    ui.add(
+                                        TextEdit::singleline(&mut dialog.location)
+                                            .desired_width(250.0),
+                                    );
+                                    browse = ui.button("Browse...").clicked();
+                                });
+                                ui.end_row();
+
+                                ui.label("");
+                                ui.checkbox(&mut dialog.init_git, "Initialize a git repository");
+                                ui.end_row();
+                            });
+
+                        if let Some(dir) = project_template::templates_dir() {
+                            ui.label(
+                                RichText::new(format!(
+                                    "Folders in {} are listed as templates; {{{{project_name}}}} in their files is replaced",
+                                    dir.display()
+                                ))
+                                .size(fonts::SMALL)
+                                .color(colors.text_muted),
+                            );
+                        }
+                        if let Some(error) = &dialog.error {
+                            ui.label(RichText::new(error).color(colors.diagnostic_error));
+                        }
+                        ui.add_space(4.0);
+                        ui.horizontal(|ui| {
+                            create |= ui.button("Create").clicked();
+                            cancel |= ui.button("Cancel").clicked();
+                        });
+                    });
+            });
+
+        if browse {
+            if let Some(folder) = self.blocking_dialog(|| FileDialog::new().pick_folder()) {
+                if let Some(dialog) = &mut self.new_project {
+                    dialog.location = folder.display().to_string();
+                }
+            }
+        }
+        if cancel {
+            self.new_project = None;
+        } else if create {
+            self.create_project();
+        }
+    }
+
+    // === Rename ===
+
+    fn render_rename_dialog(&mut self, ctx: &egui::Context) {
+        let colors = palette(&ctx.style().visuals);
+        let mut rename = false;
+        let mut cancel = ctx.input(|i| i.key_pressed(egui::Key::Escape));
+        let Some(dialog) = &mut self.rename_file else {
+            return;
+        };
+
+        egui::Area::new(egui::Id::new("rename_file_dialog"))
+            .anchor(egui::Align2::CENTER_TOP, Vec2::new(0.0, 50.0))
+            .show(ctx, |ui| {
+                Frame::none()
+                    .fill(colors.find_panel_bg)
+                    .inner_margin(Margin::same(12.0))
+                    .rounding(4.0)
+                    .shadow(egui::epaint::Shadow {
+                        extrusion: 8.0,
+                        color: Color32::from_black_alpha(100),
+                    })
+                    .show(ui, |ui| {
+                        ui.label(RichText::new("Rename").size(fonts::HEADING));
+                        ui.add_space(8.0);
+                        let name =
+                            ui.add(TextEdit::singleline(&mut dialog.name).desired_width(320.0));
+                        name.request_focus();
+                        if name.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                            rename = true;
+                        }
+                        if let Some(error) = &dialog.error {
+                            ui.label(RichText::new(error).color(colors.diagnostic_error));
+                        }
+                        ui.add_space(4.0);
+                        ui.horizontal(|ui| {
+                            rename |= ui.button("Rename").clicked();
+                            cancel |= ui.button("Cancel").clicked();
+                        });
+                    });
+            });
+
+        if cancel {
+            self.rename_file = None;
+        } else if rename {
+            self.rename_from_dialog();
+        }
+    }
+
+    /// Rename the file from the Rename dialog. Renaming a Rust module file
+    /// moves its folder of submodules along and offers to update the `mod`
+    /// declarations and paths that name it.
+    fn rename_from_dialog(&mut self) {
+        let Some(dialog) = &mut self.rename_file else {
+            return;
+        };
+        let name = dialog.name.trim();
+        let old_path = dialog.path.clone();
+        let new_path = old_path.with_file_name(name);
+        if new_path == old_path {
+            self.rename_file = None;
+            return;
+        }
+        if name.is_empty() || name.contains(['/', '\\']) {
+            dialog.error = Some("Enter a file name".to_string());
+            return;
+        }
+        if new_path.exists() {
+            dialog.error = Some(format!("{name} already exists"));
+            return;
+        }
+
+        // References are found before the move, while the old paths still resolve
+        let module_rename = self.module_rename_edits(&old_path, &new_path);
+        if let Err(e) = std::fs::rename(&old_path, &new_path) {
+            if let Some(dialog) = &mut self.rename_file {
+                dialog.error = Some(format!("Could not rename: {e}"));
+            }
+            return;
+        }
+        let mut moved = vec![(old_path.clone(), new_path.clone())];
+        let (old_dir, new_dir) = (old_path.with_extension(""), new_path.with_extension(""));
+        if module_rename.is_some() && old_dir.is_dir() && !new_dir.exists() {
+            match std::fs::rename(&old_dir, &new_dir) {
+                Ok(()) => moved.push((old_dir, new_dir)),
+                Err(e) => self.log_output(format!("Could not rename {}: {e}", old_dir.display())),
+            }
+        }
+        for idx in 0..self.open_files.len() {
+            let path = &self.open_files[idx].path;
+            if let Some(path) = moved_path(path, &moved) {
+                self.rename_open_file(idx, path);
+            }
+        }
+
+        self.rename_file = None;
+        self.log_output(format!(
+            "Renamed {} to {}",
+            old_path.display(),
+            new_path.display()
+        ));
+        self.refresh_tree();
+        self.module_rename = module_rename.map(|mut rename| {
+            for edit in &mut rename.edits {
+                if let Some(path) = moved_path(&edit.path, &moved) {
+                    edit.path = path;
+                }
+            }
+            rename
+        });
+    }
+
+    /// Edits to the crate's source files that follow renaming the module file
+    /// `old_path` to `new_path`, taken from open buffers where there are any.
+    /// `None` unless it is a module named by its file and something refers to it.
+    fn module_rename_edits(&self, old_path: &Path, new_path: &Path) -> Option<ModuleRenameState> {
+        let is_rust = |path: &Path| path.extension().is_some_and(|ext| ext == "rs");
+        if !is_rust(old_path) || !is_rust(new_path) {
+            return None;
+        }
+        let new_name = new_path.file_stem()?.to_str()?;
+        if !rust_mod::is_identifier(new_name) {
+            return None;
+        }
+        let src = rust_mod::crate_src(old_path)?;
+        let module = rust_mod::module_path(&src, old_path)?;
+        // `mod.rs`, `main.rs` and `lib.rs` don't name their module
+        if module.last().map(String::as_str) != old_path.file_stem()?.to_str() {
+            return None;
+        }
+
+        let mut edits = Vec::new();
+        for path in rust_mod::source_files(&src) {
+            let Some(file_module) = rust_mod::module_path(&src, &path) else {
+                continue;
+            };
+            let text = match self.open_files.iter().find(|f| f.path == path) {
+                Some(file) => file.buffer.to_string(),
+                None => match std::fs::read_to_string(&path) {
+                    Ok(text) => text,
+                    Err(_) => continue,
+                },
+            };
+            let Some(new_text) =
+                rust_mod::rename_references(&text, &file_module, &module, new_name)
+            else {
+                continue;
+            };
+            edits.push(ModuleReferenceEdit {
+                label: path
+                    .strip_prefix(&src)
+                    .unwrap_or(&path)
+                    .display()
+                    .to_string(),
+                diff: changed_lines(&text, &new_text),
+                path,
+                new_text,
+                apply: true,
+            });
+        }
+        if edits.is_empty() {
+            return None;
+        }
+        Some(ModuleRenameState {
+            title: format!("crate::{} → {new_name}", module.join("::")),
+            edits,
+            selected: 0,
+        })
+    }
+
+    /// Preview of the reference updates for a renamed module, one file at a time
+    fn render_module_rename(&mut self, ctx: &egui::Context) {
+        let mut apply = false;
+        let mut skip = false;
+        let mut open = true;
+        let Some(rename) = &mut self.module_rename else {
+            return;
+        };
+
+        egui::Window::new("Update Module References")
+            .open(&mut open)
+            .default_size([720.0, 420.0])
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.label(&rename.title);
+                ui.add_space(4.0);
+                egui::TopBottomPanel::bottom("module_rename_buttons")
+                    .frame(Frame::none())
+                    .show_inside(ui, |ui| {
+                        ui.add_space(4.0);
+                        ui.horizontal(|ui| {
+                            let any = rename.edits.iter().any(|e| e.apply);
+                            apply = ui.add_enabled(any, egui::Button::new("Apply")).clicked();
+                            skip = ui.button("Skip").clicked();
+                        });
+                    });
+                egui::SidePanel::left("module_rename_files")
+                    .resizable(true)
+                    .default_width(200.0)
+                    .frame(Frame::none())
+                    .show_inside(ui, |ui| {
+                        ScrollArea::vertical().show(ui, |ui| {
+                            for (i, edit) in rename.edits.iter_mut().enumerate() {
+                                ui.horizontal(|ui| {
+                                    ui.checkbox(&mut edit.apply, "");
+                                    if ui
+                                        .selectable_label(rename.selected == i, &edit.label)
+                                        .clicked()
+                                    {
+                                        rename.selected = i;
+                                    }
+                                });
+                            }
+                        });
+                    });
+                if let Some(edit) = rename.edits.get(rename.selected) {
+                    DiffView::new("module_rename_diff", &edit.diff).show(ui);
+                }
+            });
+
+        if apply {
+            self.apply_module_rename();
+        } else if skip || !open {
+            self.module_rename = None;
+        }
+    }
+
+    /// Write the checked reference updates. Open files change in their tab as
+    /// one undo step and are left for the user to save.
+    fn apply_module_rename(&mut self) {
+        let Some(rename) = self.module_rename.take() else {
+            return;
+        };
+        let mut updated = 0;
+        for edit in rename.edits.into_iter().filter(|e| e.apply) {
+            if let Some(file) = self.open_files.iter_mut().find(|f| f.path == edit.path) {
+                let whole = 0..file.buffer.len_chars();
+                file.replace_ranges(std::slice::from_ref(&whole), &edit.new_text);
+                file.state.history.break_group();
+                updated += 1;
+                continue;
+            }
+            match std::fs::write(&edit.path, &edit.new_text) {
+                Ok(()) => updated += 1,
+                Err(e) => self.log_output(format!("Could not update {}: {e}", edit.path.display())),
+            }
+        }
+        self.log_output(format!("Updated module references in {updated} file(s)"));
+    }
+
+    /// Scaffold the project from the dialog and open it as the workspace
+    fn create_project(&mut self) {
+        let Some(dialog) = &self.new_project else {
+            return;
+        };
+        self.set_activity(format!("creating project {}", dialog.name));
+        let created = project_template::create_project(
+            &dialog.templates[dialog.template],
+            Path::new(&dialog.location),
+            &dialog.name,
+            dialog.init_git,
+        );
+        match created {
+            Ok(root) => {
+                self.new_project = None;
+                self.log_output(format!("Created {}", root.display()));
+                self.set_workspace(root);
+            }
+            Err(e) => {
+                if let Some(dialog) = &mut self.new_project {
+                    dialog.error = Some(e);
+                }
+            }
+        }
+    }
+
+    // === Commit ===
+
+    /// File > Commit Staged Changes: a message editor for the staged changes
+    fn open_commit(&mut self) {
+        if self.commit.is_some() {
+            return;
+        }
+        let Some(workspace) = &self.workspace else {
+            return;
+        };
+        let Some(staged) = git::staged_files(workspace) else {
+            self.log_output(format!(
+                "{} is not in a git repository",
+                workspace.display()
+            ));
+            return;
+        };
+        self.git_signs_commits = git::signs_commits(workspace);
+        self.commit = Some(CommitState {
+            subject: String::new(),
+            body: String::new(),
+            verify: true,
+            staged,
+            signing_key: git::signing_key(workspace),
+            pending: None,
+            error: None,
+        });
+    }
+
+    /// Sign Commits for the open folder; None leaves it to git
+    fn sign_commits(&self) -> Option<bool> {
+        let workspace = self.workspace.as_ref()?;
+        self.settings.sign_commits_in.get(workspace).copied()
+    }
+
+    fn render_commit(&mut self, ctx: &egui::Context) {
+        let colors = palette(&ctx.style().visuals);
+        let mut commit = false;
+        let mut cancel = ctx.input(|i| i.key_pressed(egui::Key::Escape));
+        let sign = self.sign_commits();
+        let Some(state) = &mut self.commit else {
+            return;
+        };
+
+        // Result of the commit started earlier
+        let finished = match &state.pending {
+            Some(pending) => match pending.try_recv() {
+                Ok(result) => Some(result),
+                Err(std::sync::mpsc::TryRecvError::Empty) => None,
+                Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+                    Some(Err("Failed to run git commit".to_string()))
+                }
+            },
+            None => None,
+        };
+        if let Some(result) = finished {
+            state.pending = None;
+            match result {
+                Ok(output) => {
+                    self.commit = None;
+                    self.log_output(output);
+                    self.announce("Committed", Some(Cue::Success));
+                }
+                Err(output) => {
+                    state.error = Some(output.clone());
+                    self.log_output(output);
+                    self.announce("Commit failed", Some(Cue::Problem));
+                }
+            }
+            return;
+        }
+        let committing = state.pending.is_some();
+        let git_signs = self.git_signs_commits;
+
+        let font = FontId::monospace(fonts::BODY);
+        let char_width = ctx.fonts(|f| f.glyph_width(&font, ' '));
+        let margin = Vec2::new(4.0, 2.0);
+        let width = (git::LINE_LIMIT + 2) as f32 * char_width + 2.0 * margin.x;
+        // Rulers at the line length limits of a text field
+        let rulers = |ui: &egui::Ui, rect: egui::Rect, limits: &[usize]| {
+            for &limit in limits {
+                let x = rect.left() + margin.x + limit as f32 * char_width;
+                ui.painter().vline(
+                    x,
+                    rect.y_range(),
+                    Stroke::new(1.0, colors.text_muted.gamma_multiply(0.4)),
+                );
+            }
+        };
+
+        egui::Area::new(egui::Id::new("commit_dialog"))
+            .anchor(egui::Align2::CENTER_TOP, Vec2::new(0.0, 50.0))
+            .show(ctx, |ui| {
+                Frame::none()
+                    .fill(colors.find_panel_bg)
+                    .inner_margin(Margin::same(12.0))
+                    .rounding(4.0)
+                    .shadow(egui::epaint::Shadow {
+                        extrusion: 8.0,
+                        color: Color32::from_black_alpha(100),
+                    })
+                    .show(ui, |ui| {
+                        ui.label(RichText::new("Commit Staged Changes").size(fonts::HEADING));
+                        let staged = match state.staged.len() {
+                            0 => "Nothing is staged".to_string(),
+                            1 => "1 staged file".to_string(),
+                            n => format!("{n} staged files"),
+                        };
+                        ui.label(
+                            RichText::new(staged)
+                                .size(fonts::SMALL)
+                                .color(colors.text_muted),
+                        )
+                        .on_hover_text(state.staged.join("\n"));
+                        ui.add_space(8.0);
+
+                        // Subject, with Tab completing a Conventional Commits type
+                        let subject_id = egui::Id::new("commit_subject");
+                        let completions = git::conventional_completions(&state.subject);
+                        let mut completed = None;
+                        if !completions.is_empty()
+                            && !state.subject.is_empty()
+                            && ui.memory(|m| m.has_focus(subject_id))
+                            && ui
+                                .input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::Tab))
+                        {
+                            completed = Some(completions[0].0);
+                        }
+                        let subject = ui.add_enabled(
+                            !committing,
+                            TextEdit::singleline(&mut state.subject)
+                                .id(subject_id)
+                                .font(font.clone())
+                                .margin(margin)
+                                .desired_width(width)
+                                .hint_text("Summary of the change"),
+                        );
+                        rulers(ui, subject.rect, &[git::SUBJECT_LIMIT, git::LINE_LIMIT]);
+                        if state.subject.is_empty() && state.body.is_empty() {
+                            subject.request_focus();
+                        }
+                        if !completions.is_empty() && !state.subject.is_empty() {
+                            ui.horizontal_wrapped(|ui| {
+                                for &(kind, description) in &completions {
+                                    if ui.small_button(kind).on_hover_text(description).clicked() {
+                                        completed = Some(kind);
+                                    }
+                                }
+                                ui.label(RichText::new("Tab").size(fonts::SMALL).weak());
+                            });
+                        }
+                        if let Some(kind) = completed {
+                            state.subject = format!("{kind}: ");
+                            let end = state.subject.chars().count();
+                            let mut text = egui::text_edit::TextEditState::load(ctx, subject_id)
+                                .unwrap_or_default();
+                            text.cursor
+                                .set_char_range(Some(egui::text::CCursorRange::one(
+                                    egui::text::CCursor::new(end),
+                                )));
+                            text.store(ctx, subject_id);
+                            ui.memory_mut(|m| m.request_focus(subject_id));
+                        }
+                        let length = state.subject.trim().chars().count();
+                        let (color, note) = if length > git::LINE_LIMIT {
+                            (colors.diagnostic_error, " (too long for one line)")
+                        } else if length > git::SUBJECT_LIMIT {
+                            (colors.diagnostic_warning, " (keep the subject short)")
+                        } else {
+                            (colors.text_muted, "")
+                        };
+                        ui.label(
+                            RichText::new(format!("{length}/{}{note}", git::SUBJECT_LIMIT))
+                                .size(fonts::SMALL)
+                                .color(color),
+                        );
+                        ui.add_space(4.0);
+
+                        let body = ui.add_enabled(
+                            !committing,
+                            TextEdit::multiline(&mut state.body)
+                                .font(font.clone())
+                                .margin(margin)
+                                .desired_width(width)
+                                .desired_rows(8)
+                                .hint_text("Why the change was made (optional)"),
+                        );
+                        rulers(ui, body.rect, &[git::LINE_LIMIT]);
+                        let long_lines: Vec<String> = state
+                            .body
+                            .lines()
+                            .enumerate()
+                            .filter(|(_, line)| line.chars().count() > git::LINE_LIMIT)
+                            .map(|(i, _)| (i + 1).to_string())
+                            .collect();
+                        if !long_lines.is_empty() {
+                            ui.label(
+                                RichText::new(format!(
+                                    "Longer than {} characters: line {}",
+                                    git::LINE_LIMIT,
+                                    long_lines.join(", ")
+                                ))
+                                .size(fonts::SMALL)
+                                .color(colors.diagnostic_warning),
+                            );
+                        }
+                        ui.add_space(4.0);
+                        ui.add_enabled(
+                            !committing,
+                            egui::Checkbox::new(&mut state.verify, "Run commit hooks"),
+                        );
+                        let signing = match (sign, &state.signing_key) {
+                            (Some(false), _) => "Not signed: Sign Commits is off".to_string(),
+                            (None, _) if !git_signs => {
+                                "Not signed: git's commit.gpgsign is off".to_string()
+                            }
+                            (_, Some(key)) => format!("Signed with {}", key.describe()),
+                            (_, None) => "Signed with the committer's GPG key".to_string(),
+                        };
+                        ui.label(
+                            RichText::new(signing)
+                                .size(fonts::SMALL)
+                                .color(colors.text_muted),
+                        );
+
+                        if let Some(error) = &state.error {
+                            ui.add_space(4.0);
+                            ScrollArea::vertical().max_height(160.0).show(ui, |ui| {
+                                ui.label(
+                                    RichText::new(error)
+                                        .monospace()
+                                        .color(colors.diagnostic_error),
+                                );
+                            });
+                        }
+
+                        ui.add_space(8.0);
+                        ui.horizontal(|ui| {
+                            let ready = !committing
+                                && !state.staged.is_empty()
+                                && !state.subject.trim().is_empty();
+                            commit |= ui
+                                .add_enabled(ready, egui::Button::new("Commit"))
+                                .on_hover_text("Ctrl+Enter")
+                                .clicked();
+                            commit |= ready
+                                && ui
+                                    .input(|i| i.modifiers.ctrl && i.key_pressed(egui::Key::Enter));
+                            cancel |= ui.button("Cancel").clicked();
+                            if committing {
+                                ui.spinner();
+                                ui.label("Committing...");
+                            }
+                        });
+                    });
+            });
+
+        if cancel && !committing {
+            self.commit = None;
+        } else if commit {
+            let Some(workspace) = self.workspace.clone() else {
+                return;
+            };
+            if let Some(state) = &mut self.commit {
+                state.error = None;
+                state.pending = Some(git::commit_in_background(
+                    workspace,
+                    git::commit_message(&state.subject, &state.body),
+                    state.verify,
+                    sign,
+                    ctx.clone(),
+                ));
+            }
+        }
+    }
+
+    // === Environment Variables ===
+
+    fn open_env_editor(&mut self) {
+        let rows = self
+            .workspace_env
+            .iter()
+            .map(|(key, value)| EnvRow {
+                key: key.clone(),
+                value: value.clone(),
+                revealed: false,
+            })
+            .collect();
+        self.env_editor = Some(EnvEditorState { rows, error: None });
+    }
+
+    fn render_env_editor(&mut self, ctx: &egui::Context) {
+        let colors = palette(&ctx.style().visuals);
+        let mut save = false;
+        let mut cancel = ctx.input(|i| i.key_pressed(egui::Key::Escape));
+        let mut import = false;
+        let Some(editor) = &mut self.env_editor else {
+            return;
+        };
+
+        egui::Area::new(egui::Id::new("env_editor"))
+            .anchor(egui::Align2::CENTER_TOP, Vec2::new(0.0, 50.0))
+            .show(ctx, |ui| {
+                Frame::none()
+                    .fill(colors.find_panel_bg)
+                    .inner_margin(Margin::same(12.0))
+                    .rounding(4.0)
+                    .shadow(egui::epaint::Shadow {
+                        extrusion: 8.0,
+                        color: Color32::from_black_alpha(100),
+                    })
+                    .show(ui, |ui| {
+                        ui.label(RichText::new("Environment Variables").size(fonts::HEADING));
+                        ui.label(
+                            RichText::new(
+                                "Passed to language servers and formatters started for this workspace",
+                            )
+                            .size(fonts::SMALL)
+                            .color(colors.text_muted),
+                        );
+                        ui.add_space(8.0);
+
+                        let mut removed = None;
+                        ScrollArea::vertical().max_height(360.0).show(ui, |ui| {
+                            egui::Grid::new("env_editor_rows")
+                                .num_columns(3)
+                                .spacing([8.0, 6.0])
+                                .show(ui, |ui| {
+                                    for (i, row) in editor.rows.iter_mut().enumerate() {
+                                        ui.add(
+                                            TextEdit::singleline(&mut row.key)
+                                                .desired_width(180.0)
+                                                .hint_text("NAME"),
+                                        );
+                                        let masked = is_secret(&row.key) && !row.revealed;
+                                        ui.add(
+                                            TextEdit::singleline(&mut row.value)
+                                                .desired_width(260.0)
+                                                .password(masked),
+                                        );
+                                        ui.horizontal(|ui| {
+                                            if is_secret(&row.key) {
+                                                let hint =
+                                                    if row.revealed { "Hide" } else { "Show" };
+                                                if ui.small_button("👁").on_hover_text(hint).clicked()
+                                                {
+                                                    row.revealed = !row.revealed;
+                                                }
+                                            }
+                                            if ui.small_button("✕").on_hover_text("Remove").clicked()
+                                            {
+                                                removed = Some(i);
+                                            }
+                                        });
+                                        ui.end_row();
+                                    }
+                                });
+                        });
+                        if let Some(i) = removed {
+                            editor.rows.remove(i);
+                        }
+
+                        ui.horizontal(|ui| {
+                            if ui.button("➕ Add").clicked() {
+                                editor.rows.push(EnvRow {
+                                    key: String::new(),
+                                    value: String::new(),
+                                    revealed: true,
+                                });
+                            }
+                            import = ui.button("Import .env...").clicked();
+                        });
+                        if let Some(error) = &editor.error {
+                            ui.label(RichText::new(error).color(colors.diagnostic_error));
+                        }
+                        ui.add_space(4.0);
+                        ui.horizontal(|ui| {
+                            save |= ui.button("Save").clicked();
+                            cancel |= ui.button("Cancel").clicked();
+                        });
+                    });
+            });
+
+        if import {
+            self.import_env_file();
+        }
+        if cancel {
+            self.env_editor = None;
+        } else if save {
+            self.save_env();
+        }
+    }
+
+    // === Keybindings Editor ===
+
+    /// Keys pressed while recording a shortcut: the first one, then Enter to
+    /// keep it or a second one for a chord. Escape stops recording.
+    fn record_keybinding(&mut self, ctx: &egui::Context) {
+        use egui::{Event, Key};
+        let strokes: Vec<egui::KeyboardShortcut> = ctx.input_mut(|i| {
+            // Ctrl+X, C and V arrive as clipboard events; with Shift or Alt
+            // held they can be bound like other keys
+            let held = i.modifiers;
+            let clipboard_key = |e: &Event| match e {
+                _ if !(held.shift || held.alt) => None,
+                Event::Cut => Some(Key::X),
+                Event::Copy => Some(Key::C),
+                Event::Paste(_) => Some(Key::V),
+                _ => None,
+            };
+            let strokes = i
+                .events
+                .iter()
+                .filter_map(|e| match e {
+                    Event::Key {
+                        key,
+                        pressed: true,
+                        modifiers,
+                        ..
+                    } => Some(keybindings::recorded_shortcut(*modifiers, *key)),
+                    _ => clipboard_key(e).map(|key| keybindings::recorded_shortcut(held, key)),
+                })
+                .collect();
+            i.events.retain(|e| {
+                !matches!(e, Event::Key { .. } | Event::Text(_)) && clipboard_key(e).is_none()
+            });
+            strokes
+        });
+        let Some(editor) = &mut self.keybindings_editor else {
+            return;
+        };
+        for stroke in strokes {
+            let Some((command, first)) = editor.recording else {
+                break;
+            };
+            let plain = stroke.modifiers.is_none();
+            match first {
+                _ if plain && stroke.logical_key == Key::Escape => editor.recording = None,
+                Some(first) if plain && stroke.logical_key == Key::Enter => {
+                    editor.draft.set(command, Some(KeyBinding::new(first)));
+                    editor.recording = None;
+                }
+                Some(first) => {
+                    let binding = KeyBinding {
+                        first,
+                        second: Some(stroke),
+                    };
+                    editor.draft.set(command, Some(binding));
+                    editor.recording = None;
+                }
+                None => editor.recording = Some((command, Some(stroke))),
+            }
+        }
+    }
+
+    fn render_keybindings_editor(&mut self, ctx: &egui::Context) {
+        let colors = palette(&ctx.style().visuals);
+        let mut open = true;
+        let mut save = false;
+        let mut cancel = false;
+        let saved = &self.keybindings;
+        let Some(editor) = &mut self.keybindings_editor else {
+            return;
+        };
+
+        // Changed bindings that another command's binding would shadow
+        let commands: Vec<(Menu, Command)> = commands::all();
+        let conflicts: HashMap<Command, Vec<Command>> = commands
+            .iter()
+            .filter(|(_, command)| editor.draft.get(*command) != saved.get(*command))
+            .filter_map(|&(_, command)| {
+                let binding = editor.draft.get(command)?;
+                let others: Vec<Command> = commands
+                    .iter()
+                    .map(|&(_, other)| other)
+                    .filter(|&other| other != command)
+                    .filter(|&other| {
+                        editor
+                            .draft
+                            .get(other)
+                            .is_some_and(|b| b.conflicts_with(&binding))
+                    })
+                    .collect();
+                (!others.is_empty()).then_some((command, others))
+            })
+            .collect();
+
+        egui::Window::new("Keybindings")
+            .open(&mut open)
+            .default_size([560.0, 520.0])
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.add(
+                    TextEdit::singleline(&mut editor.filter)
+                        .desired_width(f32::INFINITY)
+                        .hint_text("Search commands or shortcuts"),
+                );
+                ui.label(
+                    RichText::new(
+                        "⏺ records a shortcut: press it, then Enter, or a second key for a chord",
+                    )
+                    .size(fonts::SMALL)
+                    .color(colors.text_muted),
+                );
+                ui.add_space(4.0);
+
+                let filter = editor.filter.to_lowercase();
+                ScrollArea::vertical()
+                    .max_height(400.0)
+                    .auto_shrink([false, true])
+                    .show(ui, |ui| {
+                        egui::Grid::new("keybindings_editor_rows")
+                            .num_columns(3)
+                            .spacing([16.0, 4.0])
+                            .striped(true)
+                            .show(ui, |ui| {
+                                for &(menu, command) in &commands {
+                                    let name = format!("{}: {}", menu.label(), command.label());
+                                    let binding = editor.draft.get(command);
+                                    let text = binding.map(|b| b.format(ctx)).unwrap_or_default();
+                                    if !name.to_lowercase().contains(&filter)
+                                        && !text.to_lowercase().contains(&filter)
+                                    {
+                                        continue;
+                                    }
+
+                                    ui.label(&name).on_hover_text(command.id());
+                                    let text = match editor.recording {
+                                        Some((recording, first)) if recording == command => {
+                                            match first {
+                                                Some(first) => format!(
+                                                    "{} … (Enter or a second key)",
+                                                    ctx.format_shortcut(&first)
+                                                ),
+                                                None => "Press a shortcut…".to_string(),
+                                            }
+                                        }
+                                        _ if binding.is_none() => "—".to_string(),
+                                        _ => text,
+                                    };
+                                    let color = if conflicts.contains_key(&command) {
+                                        colors.diagnostic_error
+                                    } else if binding != saved.get(command) {
+                                        colors.accent
+                                    } else {
+                                        colors.text_primary
+                                    };
+                                    let label = ui.label(RichText::new(text).monospace().color(color));
+                                    if let Some(others) = conflicts.get(&command) {
+                                        let names: Vec<&str> =
+                                            others.iter().map(|c| c.label()).collect();
+                                        label.on_hover_text(format!(
+                                            "Also bound to {}",
+                                            names.join(", ")
+                                        ));
+                                    }
+
+                                    ui.horizontal(|ui| {
+                                        if ui.small_button("⏺").on_hover_text("Record Shortcut").clicked() {
+                                            editor.recording = Some((command, None));
+                                        }
+                                        let overridden = editor.draft.is_overridden(command);
+                                        if ui
+                                            .add_enabled(overridden, egui::Button::new("↺").small())
+                                            .on_hover_text("Reset to Default")
+                                            .clicked()
+                                        {
+                                            editor.draft.reset(command);
+                                        }
+                                        if ui
+                                            .add_enabled(binding.is_some(), egui::Button::new("✕").small())
+                                            .on_hover_text("Remove Shortcut")
+                                            .clicked()
+                                        {
+                                            editor.draft.set(command, None);
+                                        }
+                                    });
+                                    ui.end_row();
+                                }
+                            });
+                    });
+
+                ui.add_space(4.0);
+                if !conflicts.is_empty() {
+                    ui.label(
+                        RichText::new(format!(
+                            "{} changed shortcut(s) conflict with other commands; hover them for details",
+                            conflicts.len()
+                        ))
+                        .color(colors.diagnostic_error),
+                    );
+                }
+                if let Some(error) = &editor.error {
+                    ui.label(RichText::new(error).color(colors.diagnostic_error));
+                }
+                ui.horizontal(|ui| {
+                    save = ui
+                        .add_enabled(conflicts.is_empty(), egui::Button::new("Save"))
+                        .clicked();
+                    cancel = ui.button("Cancel").clicked();
+                });
+            });
+
+        if cancel || !open {
+            self.keybindings_editor = None;
+        } else if save {
+            self.save_keybindings();
+        }
+    }
+
+    fn save_keybindings(&mut self) {
+        let Some(editor) = &mut self.keybindings_editor else {
+            return;
+        };
+        match editor.draft.save() {
+            Ok(()) => {
+                self.keybindings = editor.draft.clone();
+                self.keybindings_editor = None;
+                self.announce("Saved keybindings", Some(Cue::Success));
+            }
+            Err(e) => editor.error = Some(format!("Could not save keybindings.toml: {e}")),
+        }
+    }
+
+    /// Add the variables of a `.env` file to the editor, replacing rows with the same name
+    fn import_env_file(&mut self) {
+        let directory = self.workspace.clone().unwrap_or_default();
+        let Some(path) = self.blocking_dialog(|| {
+            FileDialog::new()
+                .set_title("Import .env File")
+                .set_directory(&directory)
+                .pick_file()
+        }) else {
+            return;
+        };
+        let Some(editor) = &mut self.env_editor else {
+            return;
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(content) => {
+                for (key, value) in dotenv::parse(&content) {
+                    match editor.rows.iter_mut().find(|row| row.key == key) {
+                        Some(row) => row.value = value,
+                        None => editor.rows.push(EnvRow {
+                            key,
+                            value,
+                            revealed: false,
+                        }),
+                    }
+                }
+                editor.error = None;
+            }
+            Err(e) => editor.error = Some(format!("Failed to read {}: {e}", path.display())),
+        }
+    }
+
+    fn save_env(&mut self) {
+        let (Some(editor), Some(workspace)) = (&mut self.env_editor, &self.workspace) else {
+            return;
+        };
+        let env = editor
+            .rows
+            .iter()
+            .filter(|row| !row.key.trim().is_empty())
+            .map(|row| (row.key.trim().to_string(), row.value.clone()))
+            .collect();
+        let mut environments = Environments::load();
+        environments.workspaces.insert(workspace.clone(), env);
+        match environments.save() {
+            Ok(()) => {
+                self.workspace_env = environments
+                    .workspaces
+                    .remove(workspace)
+                    .unwrap_or_default();
+                self.env_editor = None;
+            }
+            Err(e) => editor.error = Some(format!("Failed to save: {e}")),
+        }
+    }
+
+    // === Compare Folders ===
+
+    /// Ask for two folders and compare them in the background
+    fn compare_folders(&mut self, ctx: &egui::Context) {
+        let Some(left) = self.blocking_dialog(|| {
+            FileDialog::new()
+                .set_title("Compare Folders: first folder")
+                .pick_folder()
+        }) else {
+            return;
+        };
+        let Some(right) = self.blocking_dialog(|| {
+            FileDialog::new()
+                .set_title(&format!("Compare {} with...", left.display()))
+                .pick_folder()
+        }) else {
+            return;
+        };
+
+        self.log_viewer = None;
+        self.regex_playground = None;
+        self.dependency_graph = None;
+        self.folder_compare = Some(FolderCompareState {
+            pending: Some(folder_diff::compare_in_background(
+                left.clone(),
+                right.clone(),
+                ctx.clone(),
+            )),
+            left,
+            right,
+            nodes: None,
+            changed_only: false,
+            selected: None,
+        });
+    }
+
+    fn render_folder_compare(&mut self, ui: &mut egui::Ui) {
+        let Some(compare) = &mut self.folder_compare else {
+            return;
+        };
+        if let Some(pending) = &compare.pending {
+            match pending.try_recv() {
+                Ok(nodes) => {
+                    compare.nodes = Some(nodes);
+                    compare.pending = None;
+                }
+                Err(std::sync::mpsc::TryRecvError::Empty) => {}
+                Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+                    compare.nodes = Some(Vec::new());
+                    compare.pending = None;
+                }
+            }
+        }
+
+        let mut view = FolderDiffView::new(
+            &compare.left,
+            &compare.right,
+            compare.nodes.as_deref(),
+            &mut compare.changed_only,
+        );
+        if let Some((path, diff)) = &compare.selected {
+            view = view.selected(path, diff);
+        }
+        let response = view.show(ui);
+
+        if let Some(path) = response.clicked {
+            let diff =
+                folder_diff::diff_files(&compare.left.join(&path), &compare.right.join(&path));
+            compare.selected = Some((path, diff));
+        }
+        if response.close_clicked {
+            self.folder_compare = None;
+        }
+    }
+
+    // === Source Control ===
+
+    fn render_source_control(&mut self, ui: &mut egui::Ui) {
+        if self.workspace.is_none() {
+            ui.add_space(8.0);
+            ui.horizontal(|ui| {
+                ui.add_space(12.0);
+                ui.label(
+                    RichText::new("Open a folder in a git repository")
+                        .color(palette(ui.visuals()).text_muted),
+                );
+            });
+            return;
+        }
+        let enabled = self.settings.forge.enabled;
+        if enabled && self.forge.items.is_none() && self.forge.pending.is_none() {
+            self.reload_forge_items(ui.ctx());
+        }
+
+        // A reload shows the spinner rather than the stale list
+        let items = match self.forge.pending {
+            Some(_) => None,
+            None => self.forge.items.as_ref(),
+        };
+        let response = SourceControlPanel::new(enabled, items)
+            .repository(self.forge.repository.as_ref())
+            .show(ui);
+
+        if response.commit_clicked {
+            self.open_commit();
+        }
+        if response.enable_clicked {
+            self.settings.forge.enabled = true;
+            self.save_settings();
+            self.reload_forge_items(ui.ctx());
+        }
+        if response.refresh_clicked {
+            self.reload_forge_items(ui.ctx());
+        }
+        if response.opened.is_some() {
+            self.forge.open = response.opened;
+        }
+    }
+
+    /// Ask GitHub or GitLab for the open pull requests and issues of the
+    /// repository at the configured remote
+    fn reload_forge_items(&mut self, ctx: &egui::Context) {
+        let Some(workspace) = &self.workspace else {
+            return;
+        };
+        let remote = &self.settings.forge.remote;
+        // A remote's name, or its URL
+        let url = if remote.contains("://") || remote.contains('@') {
+            Some(remote.clone())
+        } else {
+            git::remote_url(workspace, remote)
+        };
+        let Some(url) = url else {
+            self.forge.items = Some(Err(format!("The repository has no remote \"{remote}\"")));
+            return;
+        };
+        let Some(repository) = Repository::from_remote(&url, &self.settings.forge) else {
+            self.forge.items = Some(Err(format!(
+                "{url} is not on github.com or gitlab.com; list self-hosted servers in \
+                 github_hosts or gitlab_hosts under [forge] in settings.toml"
+            )));
+            return;
+        };
+        let token = repository.token(&self.settings.forge);
+        self.forge.open = None;
+        self.forge.pending = Some(forge::load_in_background(
+            repository.clone(),
+            token,
+            ctx.clone(),
+        ));
+        self.forge.repository = Some(repository);
+    }
+
+    /// Take the results of a reload or checkout once they are done
+    fn poll_forge(&mut self) {
+        if let Some(pending) = &self.forge.pending {
+            match pending.try_recv() {
+                Ok(items) => {
+                    self.forge.items = Some(items);
+                    self.forge.pending = None;
+                }
+                Err(std::sync::mpsc::TryRecvError::Empty) => {}
+                Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+                    self.forge.items = Some(Err("Failed to load pull requests".to_string()));
+                    self.forge.pending = None;
+                }
+            }
+        }
+        if let Some(checkout) = &self.forge.checkout {
+            let result = match checkout.try_recv() {
+                Ok(result) => result,
+                Err(std::sync::mpsc::TryRecvError::Empty) => return,
+                Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+                    Err("Failed to run git".to_string())
+                }
+            };
+            self.forge.checkout = None;
+            match result {
+                Ok(output) => {
+                    if !output.is_empty() {
+                        self.log_output(output);
+                    }
+                    self.announce("Checked out the branch", Some(Cue::Success));
+                }
+                Err(e) => {
+                    self.log_output(format!("Checkout failed: {e}"));
+                    self.announce("Checkout failed", Some(Cue::Problem));
+                }
+            }
+        }
+    }
+
+    /// Details of a pull request or issue, with its description as Markdown
+    fn render_forge_item(&mut self, ctx: &egui::Context) {
+        let (Some(index), Some(repository), Some(Ok(items))) =
+            (self.forge.open, &self.forge.repository, &self.forge.items)
+        else {
+            self.forge.open = None;
+            return;
+        };
+        let Some(item) = items.get(index).cloned() else {
+            self.forge.open = None;
+            return;
+        };
+        let repository = repository.clone();
+        let colors = palette(&ctx.style().visuals);
+        let checking_out = self.forge.checkout.is_some();
+        let mut open = true;
+        let mut check_out = false;
+        let mut browse = false;
+        egui::Window::new(format!(
+            "{} {}",
+            item.reference(repository.forge),
+            item.title
+        ))
+        .id(egui::Id::new("forge_item"))
+        .open(&mut open)
+        .default_size([560.0, 480.0])
+        .collapsible(false)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let detail = match &item.branch {
+                    Some(branch) => format!("{} wants to merge {branch}", item.author),
+                    None => format!("Opened by {}", item.author),
+                };
+                ui.label(RichText::new(detail).color(colors.text_secondary));
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    browse = ui.button("Open in Browser").clicked();
+                    if item.kind == ItemKind::Request {
+                        check_out = ui
+                            .add_enabled(!checking_out, egui::Button::new("Check Out"))
+                            .on_hover_text("Fetch the branch and switch to it")
+                            .clicked();
+                    }
+                });
+            });
+            ui.separator();
+            if item.body.trim().is_empty() {
+                ui.label(RichText::new("No description provided.").color(colors.text_muted));
+            } else {
+                MarkdownPreview::new(egui::Id::new("forge_item_body").with(index), &item.body)
+                    .show(ui);
+            }
+        });
+
+        if browse {
+            if let Err(e) = server::open_in_browser(&item.url) {
+                self.log_output(format!("Failed to open {}: {e}", item.url));
+            }
+        }
+        if check_out {
+            let (source, branch) = repository.checkout_refs(item.number);
+            let remote = self.settings.forge.remote.clone();
+            if let Some(workspace) = self.workspace.clone() {
+                self.log_output(format!("> git fetch {remote} +{source}:{branch}"));
+                self.forge.checkout = Some(git::checkout_in_background(
+                    workspace,
+                    remote,
+                    source,
+                    branch,
+                    ctx.clone(),
+                ));
+            }
+        }
+        if !open {
+            self.forge.open = None;
+        }
+    }
+
+    // === Cargo ===
+
+    fn render_cargo_panel(&mut self, ui: &mut egui::Ui) {
+        let Some(workspace) = self.workspace.clone() else {
+            ui.add_space(8.0);
+            ui.horizontal(|ui| {
+                ui.add_space(12.0);
+                ui.label(
+                    RichText::new("Open a folder with a Cargo.toml")
+                        .color(palette(ui.visuals()).text_muted),
+                );
+            });
+            return;
+        };
+        if self.cargo.metadata.is_none() && self.cargo.pending.is_none() {
+            self.reload_cargo_metadata(ui.ctx(), workspace);
+        }
+        self.poll_cargo_metadata();
+
+        // A reload shows the spinner rather than the stale metadata
+        let metadata = match self.cargo.pending {
+            Some(_) => None,
+            None => self.cargo.metadata.as_ref(),
+        };
+        let response = CargoPanel::new(metadata, &mut self.cargo.features)
+            .running(self.cargo.run.as_ref().map(|run| run.label.as_str()))
+            .show(ui);
+
+        if response.refresh_clicked {
+            if let Some(workspace) = self.workspace.clone() {
+                self.reload_cargo_metadata(ui.ctx(), workspace);
+            }
+        }
+        if response.stop_clicked {
+            if let Some(run) = &mut self.cargo.run {
+                run.stop();
+            }
+        }
+        if let Some(invocation) = response.run {
+            self.run_cargo(ui.ctx(), &invocation);
+        }
+        if let Some(path) = response.open_file {
+            self.open_file(path);
+        }
+        if response.graph_clicked {
+            self.open_dependency_graph(ui.ctx());
+        }
+    }
+
+    /// Take the result of a metadata reload once it is done
+    fn poll_cargo_metadata(&mut self) {
+        let Some(pending) = &self.cargo.pending else {
+            return;
+        };
+        match pending.try_recv() {
+            Ok(metadata) => {
+                self.cargo.metadata = Some(metadata);
+                self.cargo.pending = None;
+            }
+            Err(std::sync::mpsc::TryRecvError::Empty) => {}
+            Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+                self.cargo.metadata = Some(Err("Failed to run cargo metadata".to_string()));
+                self.cargo.pending = None;
+            }
+        }
+    }
+
+    fn reload_cargo_metadata(&mut self, ctx: &egui::Context, workspace: PathBuf) {
+        self.cargo.pending = Some(cargo::load_in_background(
+            workspace,
+            self.workspace_env.clone(),
+            ctx.clone(),
+        ));
+    }
+
+    fn run_cargo(&mut self, ctx: &egui::Context, invocation: &Invocation) {
+        let root = match &self.cargo.metadata {
+            Some(Ok(metadata)) => metadata.workspace_root.clone(),
+            _ => return,
+        };
+        match CargoRun::spawn(invocation, &root, &self.workspace_env, ctx.clone()) {
+            Ok(run) => {
+                self.log_output(format!("> {}", run.label));
+                self.cargo.run = Some(run);
+            }
+            Err(e) => self.log_output(e),
+        }
+    }
+
+    /// Move output of the running cargo command to the Output panel
+    fn poll_cargo_run(&mut self) {
+        let Some(run) = &mut self.cargo.run else {
+            return;
+        };
+        let (lines, status) = run.poll();
+        let label = run.label.clone();
+        self.output.extend(lines);
+        match status {
+            Some(Ok(status)) if status.success() => {
+                self.log_output(format!("{label} finished"));
+            }
+            Some(Ok(status)) => self.log_output(format!("{label} exited with {status}")),
+            Some(Err(e)) => self.log_output(format!("Failed to wait for {label}: {e}")),
+            None => return,
+        }
+        self.cargo.run = None;
+    }
+
+    fn open_dependency_graph(&mut self, ctx: &egui::Context) {
+        if self.cargo.graph.is_none() && self.cargo.graph_pending.is_none() {
+            self.reload_dependency_graph(ctx);
+        }
+        self.log_viewer = None;
+        self.folder_compare = None;
+        self.regex_playground = None;
+        self.dependency_graph
+            .get_or_insert_with(GraphViewport::default);
+    }
+
+    fn reload_dependency_graph(&mut self, ctx: &egui::Context) {
+        if let Some(workspace) = self.workspace.clone() {
+            self.cargo.graph_pending = Some(cargo::load_graph_in_background(
+                workspace,
+                self.workspace_env.clone(),
+                ctx.clone(),
+            ));
+        }
+    }
+
+    fn render_dependency_graph(&mut self, ui: &mut egui::Ui) {
+        let Some(viewport) = &mut self.dependency_graph else {
+            return;
+        };
+        if let Some(pending) = &self.cargo.graph_pending {
+            match pending.try_recv() {
+                Ok(graph) => {
+                    self.cargo.graph = Some(graph);
+                    self.cargo.graph_pending = None;
+                }
+                Err(std::sync::mpsc::TryRecvError::Empty) => {}
+                Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+                    self.cargo.graph = Some(Err("Failed to run cargo metadata".to_string()));
+                    self.cargo.graph_pending = None;
+                }
+            }
+        }
+
+        let graph = match self.cargo.graph_pending {
+            Some(_) => None,
+            None => self.cargo.graph.as_ref(),
+        };
+        let response = DependencyGraphView::new(graph, viewport).show(ui);
+
+        if response.refresh_clicked {
+            self.reload_dependency_graph(ui.ctx());
+        }
+        if response.close_clicked {
+            self.dependency_graph = None;
+        }
+        if let Some(index) = response.clicked {
+            self.open_dependency_entry(index);
+        }
+    }
+
+    /// Open the manifest of a workspace member at the line that declares the
+    /// crate, or the crate's own manifest when no member depends on it directly
+    fn open_dependency_entry(&mut self, index: usize) {
+        let Some(Ok(graph)) = &self.cargo.graph else {
+            return;
+        };
+        let node = &graph.nodes[index];
+        let entry = graph.direct_dependents(index).find_map(|member| {
+            let manifest = graph.nodes[member].manifest_path.clone();
+            let content = std::fs::read_to_string(&manifest).ok()?;
+            Some((manifest, cargo::dependency_line(&content, &node.name)?))
+        });
+        let (path, line) = match entry {
+            Some(entry) if !node.is_member => entry,
+            _ => (node.manifest_path.clone(), 0),
+        };
+        let position = lsp::Position { line, character: 0 };
+        self.open_location(path, position, PositionEncoding::Utf32);
+    }
+
+    // === Regex Playground ===
+
+    /// Open the Regex Playground, seeded with the selection of the active tab
+    fn open_regex_playground(&mut self) {
+        let sample = self
+            .open_files
+            .get(self.active_tab)
+            .map(|f| multi_cursor::selected_text(&f.buffer, &f.state.cursors))
+            .unwrap_or_default();
+        let playground = self.regex_playground.get_or_insert_with(Default::default);
+        if !sample.is_empty() {
+            playground.sample = sample;
+        }
+        self.log_viewer = None;
+        self.folder_compare = None;
+        self.dependency_graph = None;
+    }
+
+    fn render_regex_playground(&mut self, ui: &mut egui::Ui) {
+        let Some(playground) = &mut self.regex_playground else {
+            return;
+        };
+        let response = RegexPlayground::new(
+            &mut playground.pattern,
+            &mut playground.flags,
+            &mut playground.sample,
+        )
+        .regex(playground.regex.as_ref())
+        .error(playground.error.as_deref())
+        .show(ui);
+
+        if response.pattern_changed {
+            let compiled = if playground.pattern.is_empty() {
+                Ok(None)
+            } else {
+                playground.flags.compile(&playground.pattern).map(Some)
+            };
+            match compiled {
+                Ok(regex) => {
+                    playground.regex = regex;
+                    playground.error = None;
+                }
+                Err(e) => {
+                    playground.regex = None;
+                    playground.error = Some(e.to_string());
+                }
+            }
+        }
+        if response.close_clicked {
+            self.regex_playground = None;
+        }
+    }
+
+    // === Follow Mode ===
+
+    /// Toggle following the end of the active file
+    fn toggle_follow(&mut self) {
+        let Some(file) = self.open_files.get_mut(self.active_tab) else {
+            return;
+        };
+        file.follow = !file.follow;
+        if file.follow {
+            file.state.clear_secondary_cursors();
+            file.state.set_cursor(CursorPosition::from_char_offset(
+                &file.buffer,
+                file.buffer.len_chars(),
+            ));
+            file.state.scroll_to_cursor = true;
+        }
+    }
+
+    /// File > Merge Changes from Disk for the active tab
+    fn merge_disk_changes(&mut self) {
+        let Some(file) = self.open_files.get_mut(self.active_tab) else {
+            return;
+        };
+        let name = file.name();
+        match file.merge_disk_changes() {
+            Ok(Some(0)) => self.announce(format!("Merged the changes to {name} on disk"), None),
+            Ok(Some(conflicts)) => {
+                let places = if conflicts == 1 { "place" } else { "places" };
+                self.log_output(format!(
+                    "Merged the changes to {name} on disk; {conflicts} {places} changed on both \
+                     sides are marked with <<<<<<< buffer and >>>>>>> disk"
+                ));
+            }
+            Ok(None) => self.log_output(format!("{name} hasn't changed on disk")),
+            Err(e) => self.log_output(format!("Failed to read {name}: {e}")),
+        }
+    }
+
+    /// Load new content of followed files
+    fn follow_files(&mut self, ctx: &egui::Context) {
+        let mut errors = Vec::new();
+        for file in self.open_files.iter_mut().filter(|f| f.follow) {
+            if let Err(e) = file.follow_disk_changes() {
+                file.follow = false;
+                errors.push(format!("Stopped following {}: {e}", file.path.display()));
+            }
+        }
+        for error in errors {
+            self.log_output(error);
+        }
+        if self.open_files.iter().any(|f| f.follow) {
+            ctx.request_repaint_after(FOLLOW_INTERVAL);
+        }
+    }
+
+    /// Notice open files that were deleted or moved on disk. A move git knows
+    /// about is followed; otherwise the tab is marked until the file comes
+    /// back or the user keeps or closes it. With Close Tabs of Deleted Files
+    /// on, tabs without unsaved changes close instead.
+    fn check_missing_files(&mut self, ctx: &egui::Context) {
+        if self.open_files.is_empty() {
+            return;
+        }
+        ctx.request_repaint_after(DISK_CHECK_INTERVAL);
+        if self.last_disk_check.elapsed() < DISK_CHECK_INTERVAL {
+            return;
+        }
+        self.last_disk_check = Instant::now();
+
+        let mut closed = Vec::new();
+        for idx in 0..self.open_files.len() {
+            let file = &mut self.open_files[idx];
+            if file.untitled {
+                continue;
+            }
+            let exists = file.path.is_file();
+            if exists || file.missing {
+                // Restored files lose the mark; marked ones are only looked up once
+                file.missing = !exists;
+                continue;
+            }
+            if let Some(path) = git::renamed_to(&file.path) {
+                let message = format!("{} moved to {}", file.path.display(), path.display());
+                self.rename_open_file(idx, path);
+                self.log_output(message);
+            } else if self.settings.close_deleted_tabs && !file.is_modified() {
+                closed.push(idx);
+            } else {
+                file.missing = true;
+                let message = format!("{} was deleted or moved", file.name());
+                self.announce(message, Some(Cue::Problem));
+            }
+        }
+        for idx in closed.into_iter().rev() {
+            self.close_tab(idx);
+        }
+    }
+
+    /// Point a tab at the path its file was moved or saved to, switching it
+    /// to the new file type if the extension changed
+    fn rename_open_file(&mut self, idx: usize, path: PathBuf) {
+        let file = &mut self.open_files[idx];
+        if let Some(client) = self.language_servers.get_mut(file.extension()) {
+            client.did_close(&file.path);
+        }
+        let old_extension = file.extension().to_string();
+        file.path = path;
+        file.missing = false;
+        // Opened again under the new path by the next sync, with the server
+        // for the new extension
+        file.lsp_revision = None;
+        file.diagnostics.clear();
+        // Highlighted again, with the syntax for the new extension
+        file.highlight_requested = None;
+        if file.extension() == old_extension {
+            return;
+        }
+
+        file.lsp_symbols = None;
+        file.symbols_request = None;
+        file.http_requests.clear();
+        file.http_requests_revision = None;
+        #[cfg(feature = "tree-sitter")]
+        {
+            file.syntax_tree = SyntaxTree::new(file.extension());
+            file.expanded_selections.clear();
+        }
+        let (old, new) = (
+            detect_language(&old_extension),
+            detect_language(file.extension()),
+        );
+        if old != new {
+            let message = format!("{} is now {new}", file.name());
+            self.log_output(format!("{message} (was {old})"));
+            self.announce(message, None);
+        }
+    }
+
+    /// Banner over a tab whose file is gone, offering to keep it or close it
+    fn render_missing_file_banner(&mut self, ui: &mut egui::Ui) {
+        let idx = self.active_tab;
+        let colors = palette(ui.visuals());
+        let mut keep = false;
+        let mut close = false;
+        Frame::none()
+            .fill(colors.panel_bg)
+            .inner_margin(Margin::symmetric(8.0, 4.0))
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    let message = format!(
+                        "⚠ {} was deleted or moved on disk.",
+                        self.open_files[idx].path.display()
+                    );
+                    ui.label(RichText::new(message).color(colors.diagnostic_warning));
+                    keep = ui.button("Keep in editor (as untitled)").clicked();
+                    close = ui.button("Close").clicked();
+                });
+            });
+        if keep {
+            let file = &mut self.open_files[idx];
+            file.missing = false;
+            file.untitled = true;
+        } else if close {
+            self.close_tab(idx);
+        }
+    }
+
+    // === Static File Server ===
+
+    fn serve_workspace(&mut self) {
+        let Some(workspace) = self.workspace.clone() else {
+            return;
+        };
+        // Replaces a preview server, which may be on a different port
+        self.server = None;
+        match StaticServer::start(workspace.clone(), self.settings.server_port) {
+            Ok(server) => {
+                self.log_output(format!(
+                    "Serving {} at {}",
+                    workspace.display(),
+                    server.url()
+                ));
+                self.server = Some(server);
+            }
+            Err(e) => self.log_output(format!(
+                "Failed to serve {} on port {}: {e}",
+                workspace.display(),
+                self.settings.server_port
+            )),
+        }
+    }
+
+    fn stop_server(&mut self) {
+        if let Some(server) = self.server.take() {
+            self.log_output(format!("Stopped serving at {}", server.url()));
+        }
+    }
+
+    // === Shared Sessions ===
+
+    fn render_share_session(&mut self, ctx: &egui::Context) {
+        let colors = palette(&ctx.style().visuals);
+        let mut share = false;
+        let mut cancel = ctx.input(|i| i.key_pressed(egui::Key::Escape));
+        let Some(dialog) = &mut self.share_session else {
+            return;
+        };
+
+        egui::Area::new(egui::Id::new("share_session_dialog"))
+            .anchor(egui::Align2::CENTER_TOP, Vec2::new(0.0, 50.0))
+            .show(ctx, |ui| {
+                Frame::none()
+                    .fill(colors.find_panel_bg)
+                    .inner_margin(Margin::same(12.0))
+                    .rounding(4.0)
+                    .shadow(egui::epaint::Shadow {
+                        extrusion: 8.0,
+                        color: Color32::from_black_alpha(100),
+                    })
+                    .show(ui, |ui| {
+                        ui.label(RichText::new("Share Session").size(fonts::HEADING));
+                        ui.add_space(8.0);
+                        ui.label("Tabs to share:");
+                        egui::ScrollArea::vertical()
+                            .max_height(300.0)
+                            .show(ui, |ui| {
+                                for file in &self.open_files {
+                                    let mut checked = dialog.chosen.contains(&file.path);
+                                    let checkbox = ui
+                                        .checkbox(&mut checked, file.name())
+                                        .on_hover_text(file.path.display().to_string());
+                                    if checkbox.changed() {
+                                        if checked {
+                                            dialog.chosen.insert(file.path.clone());
+                                        } else {
+                                            dialog.chosen.remove(&file.path);
+                                        }
+                                    }
+                                }
+                            });
+                        ui.label(
+                            RichText::new("Tabs you open later stay private")
+                                .size(fonts::SMALL)
+                                .color(colors.text_muted),
+                        );
+                        ui.add_space(4.0);
+                        ui.horizontal(|ui| {
+                            let any = self
+                                .open_files
+                                .iter()
+                                .any(|f| dialog.chosen.contains(&f.path));
+                            share |= ui.add_enabled(any, egui::Button::new("Share")).clicked();
+                            cancel |= ui.button("Cancel").clicked();
+                        });
+                    });
+            });
+
+        if cancel {
+            self.share_session = None;
+        } else if share {
+            let chosen = std::mem::take(&mut dialog.chosen);
+            self.share_session = None;
+            self.share_session(ctx, &chosen);
+        }
+    }
+
+    fn share_session(&mut self, ctx: &egui::Context, chosen: &HashSet<PathBuf>) {
+        let documents = self
+            .open_files
+            .iter()
+            .filter(|file| chosen.contains(&file.path))
+            .map(|file| (file.name(), file.buffer.to_string()))
+            .collect();
+        match collab::Session::host(collab::user_name(), documents, ctx.clone()) {
+            Ok(session) => {
+                let shared = self
+                    .open_files
+                    .iter_mut()
+                    .filter(|file| chosen.contains(&file.path));
+                for (document, file) in shared.enumerate() {
+                    file.share(document);
+                }
+                if let (Some(address), Some(code)) = (session.address(), session.code()) {
+                    self.log_output(format!(
+                        "Sharing the chosen tabs at {address} with code {code}; others join \
+                         with File > Join Shared Session, and you let each of them in"
+                    ));
+                }
+                self.collab = Some(session);
+            }
+            Err(e) => self.log_output(format!("Failed to share the session: {e}")),
+        }
+    }
+
+    fn render_join_session(&mut self, ctx: &egui::Context) {
+        let colors = palette(&ctx.style().visuals);
+        let mut join = false;
+        let mut cancel = ctx.input(|i| i.key_pressed(egui::Key::Escape));
+        let Some(dialog) = &mut self.join_session else {
+            return;
+        };
+
+        egui::Area::new(egui::Id::new("join_session_dialog"))
+            .anchor(egui::Align2::CENTER_TOP, Vec2::new(0.0, 50.0))
+            .show(ctx, |ui| {
+                Frame::none()
+                    .fill(colors.find_panel_bg)
+                    .inner_margin(Margin::same(12.0))
+                    .rounding(4.0)
+                    .shadow(egui::epaint::Shadow {
+                        extrusion: 8.0,
+                        color: Color32::from_black_alpha(100),
+                    })
+                    .show(ui, |ui| {
+                        ui.label(RichText::new("Join Shared Session").size(fonts::HEADING));
+                        ui.add_space(8.0);
+                        egui::Grid::new("join_session_fields")
+                            .num_columns(2)
+                            .spacing([8.0, 6.0])
+                            .show(ui, |ui| {
+                                ui.label("Address");
+                                let address = ui.add(
+                                    TextEdit::singleline(&mut dialog.address)
+                                        .hint_text(format!("192.168.1.20:{}", collab::DEFAULT_PORT))
+                                        .desired_width(240.0),
+                                );
+                                if dialog.address.is_empty() && !address.has_focus() {
+                                    address.request_focus();
+                                }
+                                ui.end_row();
+                                ui.label("Code");
+                                let code = ui.add(
+                                    TextEdit::singleline(&mut dialog.code).desired_width(240.0),
+                                );
+                                if code.lost_focus()
+                                    && ui.input(|i| i.key_pressed(egui::Key::Enter))
+                                {
+                                    join = true;
+                                }
+                                ui.end_row();
+                            });
+                        if let Some(error) = &dialog.error {
+                            ui.label(RichText::new(error).color(colors.diagnostic_error));
+                        }
+                        ui.add_space(4.0);
+                        ui.horizontal(|ui| {
+                            join |= ui.button("Join").clicked();
+                            cancel |= ui.button("Cancel").clicked();
+                        });
+                    });
+            });
+
+        if cancel {
+            self.join_session = None;
+        } else if join {
+            let address = dialog.address.trim().to_string();
+            match collab::Session::join(&address, &dialog.code, collab::user_name(), ctx.clone()) {
+                Ok(session) => {
+                    self.join_session = None;
+                    self.collab = Some(session);
+                    self.log_output(format!(
+                        "Connected to {address}; waiting for the host to let you in"
+                    ));
+                }
+                Err(e) => dialog.error = Some(format!("Could not connect: {e}")),
+            }
+        }
+    }
+
+    /// Ask the host whether to let in the guest that has waited longest
+    fn render_join_request(&mut self, ctx: &egui::Context) {
+        let colors = palette(&ctx.style().visuals);
+        let mut admit = false;
+        let mut refuse = false;
+        let Some(request) = self
+            .collab
+            .as_ref()
+            .and_then(|s| s.join_requests().first().cloned())
+        else {
+            return;
+        };
+
+        egui::Area::new(egui::Id::new("join_request"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, Vec2::new(-20.0, -40.0))
             .show(ctx, |ui| {
-                ui.horizontal(|ui| {
-                    ui.spacing_mut().item_spacing.x = 4.0;
-                    ui.spacing_mut().button_padding = Vec2::new(8.0, 4.0);
+                Frame::none()
+                    .fill(colors.find_panel_bg)
+                    .inner_margin(Margin::same(8.0))
+                    .rounding(4.0)
+                    .shadow(egui::epaint::Shadow {
+                        extrusion: 8.0,
+                        color: Color32::from_black_alpha(100),
+                    })
+                    .show(ui, |ui| {
+                        ui.label(format!(
+                            "{} ({}) wants to join the shared session",
+                            request.name, request.address
+                        ));
+                        ui.horizontal(|ui| {
+                            admit = ui.button("Let In").clicked();
+                            refuse = ui.button("Refuse").clicked();
+                        });
+                    });
+            });
+
+        if let Some(session) = &mut self.collab {
+            if admit {
+                session.admit(request.peer);
+            } else if refuse {
+                session.refuse(request.peer);
+                self.log_output(format!("Refused {} ({})", request.name, request.address));
+            }
+        }
+    }
 
-                    self.file_menu(ui);
-                    self.edit_menu(ui);
-                    self.view_menu(ui);
+    /// Exchange edits and carets with the shared session
+    fn sync_collaboration(&mut self) {
+        let Some(session) = &mut self.collab else {
+            return;
+        };
+        // Our edits go out before others' are applied, so that the buffers
+        // and the session's replicas stay in step
+        for file in &mut self.open_files {
+            let Some(shared) = &mut file.shared else {
+                continue;
+            };
+            if shared.revision == file.state.revision {
+                continue;
+            }
+            if let Some(edit) = rope_diff::changed_range(&shared.synced, &file.buffer) {
+                let text = Cow::from(file.buffer.slice(edit.new));
+                session.edited(shared.document, edit.old, &text);
+            }
+            shared.revision = file.state.revision;
+            shared.synced = file.buffer.clone();
+        }
+        if let Some(file) = self.open_files.get(self.active_tab) {
+            if let Some(SharedDocument { document, .. }) = file.shared {
+                let cursor = file.state.cursor();
+                session.move_caret(Caret {
+                    document,
+                    offset: cursor.offset,
+                    anchor: cursor.anchor,
                 });
+            }
+        }
+
+        for update in session.poll() {
+            match update {
+                Update::Opened {
+                    document,
+                    name,
+                    text,
+                } => {
+                    let mut file = OpenFile::new(PathBuf::from(name), text);
+                    file.untitled = true;
+                    file.share(document);
+                    self.open_files.push(file);
+                    self.active_tab = self.open_files.len() - 1;
+                }
+                Update::Changed { document, changes } => {
+                    let file = self
+                        .open_files
+                        .iter_mut()
+                        .find(|f| f.shared.as_ref().is_some_and(|s| s.document == document));
+                    if let Some(file) = file {
+                        file.apply_remote_changes(&changes);
+                    }
+                }
+                Update::JoinRequested(name) => {
+                    let message = format!("{name} asks to join the shared session");
+                    self.log_output(message.clone());
+                    self.announce(message, None);
+                }
+                Update::Joined(name) => {
+                    let message = format!("{name} joined the shared session");
+                    self.log_output(message.clone());
+                    self.announce(message, None);
+                }
+                Update::Left(name) => self.log_output(format!("{name} left the shared session")),
+                Update::Ended(reason) => {
+                    self.end_collaboration(reason);
+                    return;
+                }
+            }
+        }
+    }
+
+    fn end_collaboration(&mut self, reason: String) {
+        self.collab = None;
+        for file in &mut self.open_files {
+            file.shared = None;
+        }
+        self.log_output(reason.clone());
+        self.announce(reason, None);
+    }
+
+    /// Carets and selections of the others in a shared session on a tab
+    fn remote_carets(&self, idx: usize) -> (Vec<RemoteCaret>, Vec<TextHighlight>) {
+        let mut carets = Vec::new();
+        let mut selections = Vec::new();
+        let (Some(session), Some(&SharedDocument { document, .. })) =
+            (&self.collab, self.open_files[idx].shared.as_ref())
+        else {
+            return (carets, selections);
+        };
+        for participant in session.participants() {
+            let caret = participant.caret;
+            if caret.document != document {
+                continue;
+            }
+            let color = PARTICIPANT_COLORS[participant.peer as usize % PARTICIPANT_COLORS.len()];
+            carets.push(RemoteCaret {
+                offset: caret.offset,
+                color,
+                label: participant.name.clone(),
             });
+            if caret.anchor != caret.offset {
+                selections.push(TextHighlight {
+                    range: caret.offset.min(caret.anchor)..caret.offset.max(caret.anchor),
+                    fill: color.gamma_multiply(0.3),
+                    stroke: Stroke::NONE,
+                });
+            }
+        }
+        (carets, selections)
     }
 
-    fn file_menu(&mut self, ui: &mut egui::Ui) {
-        ui.menu_button("File", |ui| {
-            ui.style_mut().spacing.item_spacing.y = 4.0;
+    // === Language Servers ===
 
-            if ui.button("📁 Open Folder...").clicked() {
-                self.open_folder();
-                ui.close_menu();
+    /// Start servers for newly opened file types, send buffer changes, and apply
+    /// what the servers sent back
+    fn sync_language_servers(&mut self, ctx: &egui::Context) {
+        for idx in 0..self.open_files.len() {
+            let extension = self.open_files[idx].extension().to_string();
+            if !self.language_servers.contains_key(&extension)
+                && !self.failed_language_servers.contains(&extension)
+            {
+                self.start_language_server(ctx, idx);
             }
-            if ui.button("📄 Open File...").clicked() {
-                self.open_file_dialog();
-                ui.close_menu();
+
+            let file = &mut self.open_files[idx];
+            let Some(client) = self.language_servers.get_mut(&extension) else {
+                continue;
+            };
+            if file.lsp_revision == Some(file.state.revision) {
+                continue;
             }
-            ui.separator();
-            if ui.button("💾 Save").clicked() {
-                self.save_current_file();
-                ui.close_menu();
+            if client.is_open(&file.path) {
+                client.did_change(&file.path, file.buffer.to_string());
+            } else {
+                let language_id = lsp::language_id(&extension);
+                client.did_open(&file.path, language_id, file.buffer.to_string());
             }
-        });
+            file.lsp_revision = Some(file.state.revision);
+            file.symbols_request = Some(client.document_symbols(&file.path));
+        }
+
+        let mut messages = Vec::new();
+        let mut announcement = None;
+        let mut definition = None;
+        let mut references = None;
+        for client in self.language_servers.values_mut() {
+            let encoding = client.encoding();
+            for event in client.poll() {
+                match event {
+                    LspEvent::Diagnostics { path, diagnostics } => {
+                        let Some(index) = self.open_files.iter().position(|f| f.path == path)
+                        else {
+                            continue;
+                        };
+                        let file = &mut self.open_files[index];
+                        let before = problem_counts(&file.diagnostics);
+                        file.diagnostics = diagnostics
+                            .into_iter()
+                            .map(|d| {
+                                let start = encoding.to_char(&file.buffer, d.start);
+                                let end = encoding.to_char(&file.buffer, d.end);
+                                (start..end, d)
+                            })
+                            .collect();
+                        // Only changes in the file being edited are worth interrupting for
+                        let after = problem_counts(&file.diagnostics);
+                        if index == self.active_tab && after != before {
+                            let cue = if after.0 > before.0 {
+                                Some(Cue::Problem)
+                            } else if after == (0, 0) {
+                                Some(Cue::Success)
+                            } else {
+                                None
+                            };
+                            announcement = Some((problems_message(&file.name(), after), cue));
+                        }
+                    }
+                    LspEvent::Completion { id, items } => {
+                        if self.completion.pending_request == Some(id) {
+                            self.completion.pending_request = None;
+                            // Keep the buffer words when the server has nothing
+                            if !items.is_empty() {
+                                self.completion.candidates = items;
+                                self.completion.refilter();
+                            }
+                        }
+                    }
+                    LspEvent::Hover { id, contents } => {
+                        if self.hover.pending_request == Some(id) {
+                            self.hover.pending_request = None;
+                            if contents.is_some() {
+                                self.hover.contents = contents;
+                            }
+                        }
+                    }
+                    LspEvent::DocumentHighlights { id, highlights } => {
+                        if self.occurrences.pending_request != Some(id) {
+                            continue;
+                        }
+                        self.occurrences.pending_request = None;
+                        let Some(file) = self.open_files.get(self.occurrences.tab) else {
+                            continue;
+                        };
+                        // Keep the guesses when the server knows nothing about the word
+                        if !highlights.is_empty() {
+                            self.occurrences.occurrences = highlights
+                                .into_iter()
+                                .map(|h| Occurrence {
+                                    range: encoding.to_char(&file.buffer, h.start)
+                                        ..encoding.to_char(&file.buffer, h.end),
+                                    write: h.write,
+                                })
+                                .collect();
+                        }
+                    }
+                    LspEvent::Definition { id, locations } => {
+                        if self.pending_definition == Some(id) {
+                            self.pending_definition = None;
+                            definition = locations.into_iter().next().map(|l| (l, encoding));
+                        }
+                    }
+                    LspEvent::References { id, locations } => {
+                        if self.references.pending_request == Some(id) {
+                            self.references.pending_request = None;
+                            references = Some((locations, encoding));
+                        }
+                    }
+                    LspEvent::WorkspaceSymbols { id, symbols } => {
+                        if self.symbol_search.pending_requests.remove(&id) {
+                            self.symbol_search.lsp_symbols.extend(symbols);
+                            self.symbol_search.stale = true;
+                        }
+                    }
+                    LspEvent::DocumentSymbols { id, mut symbols } => {
+                        let Some(file) = self
+                            .open_files
+                            .iter_mut()
+                            .find(|f| f.symbols_request == Some(id))
+                        else {
+                            continue;
+                        };
+                        file.symbols_request = None;
+                        for symbol in &mut symbols {
+                            let position = lsp::Position {
+                                line: symbol.line,
+                                character: symbol.column,
+                            };
+                            let offset = encoding.to_char(&file.buffer, position);
+                            symbol.column = offset - file.buffer.line_to_char(symbol.line);
+                        }
+                        // Servers without document symbols leave the outline to the highlighter
+                        file.lsp_symbols = (!symbols.is_empty()).then_some(symbols);
+                    }
+                    LspEvent::Message(message) => messages.push(message),
+                }
+            }
+        }
+        for message in messages {
+            self.log_output(message);
+        }
+        if let Some((message, cue)) = announcement {
+            self.announce(message, cue);
+        }
+        if let Some(((path, position), encoding)) = definition {
+            self.open_location(path, position, encoding);
+        }
+        if let Some((locations, encoding)) = references {
+            if locations.is_empty() {
+                self.references.references = self.buffer_references(&self.references.symbol);
+            } else {
+                self.references.references = self.resolve_references(locations, encoding);
+            }
+        }
     }
 
-    fn edit_menu(&mut self, ui: &mut egui::Ui) {
-        ui.menu_button("Edit", |ui| {
-            ui.style_mut().spacing.item_spacing.y = 4.0;
+    fn start_language_server(&mut self, ctx: &egui::Context, idx: usize) {
+        let file = &self.open_files[idx];
+        let extension = file.extension().to_string();
+        let Some(command) = self.settings.language_servers.get(&extension) else {
+            return;
+        };
+        let root = match &self.workspace {
+            Some(workspace) if file.path.starts_with(workspace) => workspace.clone(),
+            _ => file.path.parent().map(PathBuf::from).unwrap_or_default(),
+        };
 
-            if ui.button("🔍 Find                    Ctrl+F").clicked() {
-                self.find_replace.is_open = true;
-                self.find_replace.show_replace = false;
-                ui.close_menu();
+        match LspClient::spawn(command, &root, &self.workspace_env, ctx.clone()) {
+            Ok(client) => {
+                self.log_output(format!(
+                    "Started {} for .{extension} files",
+                    command.join(" ")
+                ));
+                self.language_servers.insert(extension, client);
             }
-            if ui.button("🔄 Find and Replace   Ctrl+H").clicked() {
-                self.find_replace.is_open = true;
-                self.find_replace.show_replace = true;
-                ui.close_menu();
+            Err(e) => {
+                self.log_output(format!("Failed to start {}: {e}", command.join(" ")));
+                self.failed_language_servers.insert(extension);
             }
-        });
+        }
     }
 
-    fn view_menu(&mut self, ui: &mut egui::Ui) {
-        ui.menu_button("View", |ui| {
-            if ui
-                .checkbox(&mut self.show_minimap, "Show Minimap")
-                .clicked()
-            {
-                ui.close_menu();
+    fn diagnostic_color(severity: Severity, colors: &Palette) -> Option<Color32> {
+        match severity {
+            Severity::Error => Some(colors.diagnostic_error),
+            Severity::Warning => Some(colors.diagnostic_warning),
+            Severity::Information => Some(colors.diagnostic_info),
+            Severity::Hint => None,
+        }
+    }
+
+    /// Squiggles for the diagnostics of a tab
+    fn diagnostic_underlines(&self, idx: usize, colors: &Palette) -> Vec<Underline> {
+        self.open_files[idx]
+            .diagnostics
+            .iter()
+            .filter_map(|(range, diagnostic)| {
+                Some(Underline {
+                    range: range.clone(),
+                    color: Self::diagnostic_color(diagnostic.severity, colors)?,
+                    tooltip: match &diagnostic.source {
+                        Some(source) => format!("{}\n({source})", diagnostic.message),
+                        None => diagnostic.message.clone(),
+                    },
+                })
+            })
+            .collect()
+    }
+
+    /// Control and invisible characters on the lines in view: their offset,
+    /// the character and its kind
+    fn control_chars(&self, idx: usize) -> Vec<(usize, char, ControlChar)> {
+        let file = &self.open_files[idx];
+        let (first, last) = file.state.visible_lines;
+        let lines = first.saturating_sub(1)..last.min(file.buffer.len_lines());
+        let mut found = Vec::new();
+        for line_idx in lines {
+            let start = file.buffer.line_to_char(line_idx);
+            for (i, c) in file.buffer.line(line_idx).chars().enumerate() {
+                if let Some(control) = ControlChar::of(c) {
+                    found.push((start + i, c, control));
+                }
+            }
+        }
+        found
+    }
+
+    /// Squiggles under the control characters in view, naming each
+    fn control_char_underlines(&self, idx: usize, colors: &Palette) -> Vec<Underline> {
+        self.control_chars(idx)
+            .into_iter()
+            .map(|(offset, c, control)| Underline {
+                range: offset..offset + 1,
+                color: colors.diagnostic_warning,
+                tooltip: control.warning(c),
+            })
+            .collect()
+    }
+
+    /// Warning icons on the lines in view with control characters
+    fn control_char_decorations(&self, idx: usize, colors: &Palette) -> Vec<GutterDecoration> {
+        let buffer = &self.open_files[idx].buffer;
+        let mut by_line: HashMap<usize, Vec<String>> = HashMap::new();
+        for (offset, c, control) in self.control_chars(idx) {
+            let warnings = by_line.entry(buffer.char_to_line(offset) + 1).or_default();
+            let warning = control.warning(c);
+            if !warnings.contains(&warning) {
+                warnings.push(warning);
+            }
+        }
+        let mark = GutterMark::Icon {
+            glyph: "⚠",
+            color: colors.diagnostic_warning,
+        };
+        by_line
+            .into_iter()
+            .map(|(line, warnings)| {
+                GutterDecoration::new(line, GutterColumn::Diagnostic, mark.clone())
+                    .tooltip(warnings.join("\n"))
+            })
+            .collect()
+    }
+
+    /// Gutter icons for the most severe diagnostic on each line, with the
+    /// line's messages as the tooltip
+    fn diagnostic_decorations(&self, idx: usize, colors: &Palette) -> Vec<GutterDecoration> {
+        let mut by_line: HashMap<usize, (Severity, Vec<&str>)> = HashMap::new();
+        for (_, diagnostic) in &self.open_files[idx].diagnostics {
+            let (severity, messages) = by_line
+                .entry(diagnostic.start.line + 1)
+                .or_insert((diagnostic.severity, Vec::new()));
+            *severity = (*severity).min(diagnostic.severity);
+            messages.push(&diagnostic.message);
+        }
+        by_line
+            .into_iter()
+            .filter_map(|(line, (severity, messages))| {
+                let color = Self::diagnostic_color(severity, colors)?;
+                let glyph = match severity {
+                    Severity::Error => "✖",
+                    Severity::Warning => "⚠",
+                    _ => "ℹ",
+                };
+                let mark = GutterMark::Icon { glyph, color };
+                Some(
+                    GutterDecoration::new(line, GutterColumn::Diagnostic, mark)
+                        .tooltip(messages.join("\n")),
+                )
+            })
+            .collect()
+    }
+
+    /// Bookmark icons, which remove the bookmark when clicked
+    fn bookmark_decorations(&mut self, idx: usize, colors: &Palette) -> Vec<GutterDecoration> {
+        let file = &mut self.open_files[idx];
+        file.state
+            .bookmarks
+            .update(&file.buffer, file.state.revision);
+        let mark = GutterMark::Icon {
+            glyph: "🔖",
+            color: colors.accent,
+        };
+        file.state
+            .bookmarks
+            .lines()
+            .into_iter()
+            .map(|line_idx| {
+                GutterDecoration::new(line_idx + 1, GutterColumn::Bookmark, mark.clone())
+                    .tooltip("Remove Bookmark")
+            })
+            .collect()
+    }
+
+    /// Chevrons on the first line of each fold that isn't inside a
+    /// collapsed one
+    fn fold_decorations(&self, idx: usize) -> Vec<GutterDecoration> {
+        let file = &self.open_files[idx];
+        let hidden = |line_idx: usize| {
+            file.state
+                .folded
+                .iter()
+                .any(|fold| fold.hidden().contains(&line_idx))
+        };
+        file.folds
+            .ranges()
+            .iter()
+            .filter(|range| !hidden(range.lines.start))
+            .map(|range| {
+                let folded = file.state.folded.contains(range);
+                GutterDecoration::new(
+                    range.lines.start + 1,
+                    GutterColumn::Fold,
+                    GutterMark::Fold { folded },
+                )
+            })
+            .collect()
+    }
+
+    // === Autocomplete ===
+
+    /// Open the popup for the word before the caret. Typing only opens it after
+    /// an identifier character, `.` or `::`, or in a relative path string;
+    /// `explicit` (Ctrl+Space) always does.
+    fn trigger_completion(&mut self, explicit: bool) {
+        let Some(file) = self.open_files.get_mut(self.active_tab) else {
+            return;
+        };
+        let cursor = file.state.cursor();
+        if file.state.has_multiple_cursors() || cursor.has_selection() {
+            self.completion.close();
+            return;
+        }
+        let offset = cursor.offset;
+        if let Some(path) = completion::path_prefix(&file.buffer, offset) {
+            self.trigger_path_completion(path, offset);
+            return;
+        }
+        if !explicit && !completion::is_trigger(&file.buffer, offset) {
+            self.completion.close();
+            return;
+        }
+
+        let word_start = completion::word_start(&file.buffer, offset);
+        if self.completion.is_open
+            && self.completion.tab == self.active_tab
+            && self.completion.word_start == word_start
+        {
+            // Still typing the same word; update_completion refilters
+            return;
+        }
+
+        let client = self.language_servers.get_mut(file.extension());
+        if client.is_none() && word_start == offset && !explicit {
+            // Buffer words can't complete a member access
+            self.completion.close();
+            return;
+        }
+        let pending_request = client.map(|client| {
+            // The server has to see the text the position refers to
+            if file.lsp_revision != Some(file.state.revision) && client.is_open(&file.path) {
+                client.did_change(&file.path, file.buffer.to_string());
+                file.lsp_revision = Some(file.state.revision);
             }
+            let position = client.encoding().to_position(&file.buffer, offset);
+            client.completion(&file.path, position)
         });
+
+        self.completion = CompletionState {
+            is_open: true,
+            tab: self.active_tab,
+            word_start,
+            prefix: file.buffer.slice(word_start..offset).to_string(),
+            candidates: completion::buffer_words(self.open_files.iter().map(|f| &f.buffer)),
+            pending_request,
+            anchor: self.completion.anchor,
+            ..Default::default()
+        };
+        self.completion.refilter();
     }
 
-    // === Activity Bar ===
+    /// Offer the entries of the folder a path string points into, relative to
+    /// the file or else to the workspace
+    fn trigger_path_completion(&mut self, path: completion::PathPrefix, offset: usize) {
+        if self.completion.is_open
+            && self.completion.tab == self.active_tab
+            && self.completion.word_start == path.segment_start
+        {
+            return;
+        }
+        let file = &self.open_files[self.active_tab];
+        let candidates = file
+            .path
+            .parent()
+            .into_iter()
+            .chain(self.workspace.as_deref())
+            .map(|base| completion::path_items(base, &path.dir))
+            .find(|items| !items.is_empty())
+            .unwrap_or_default();
+        if candidates.is_empty() {
+            self.completion.close();
+            return;
+        }
 
-    fn render_activity_bar(&mut self, ctx: &egui::Context) {
-        egui::SidePanel::left("activity_bar")
-            .resizable(false)
-            .exact_width(layout::ACTIVITY_BAR_WIDTH)
-            .frame(Frame::none().fill(colors::ACTIVITY_BAR_BG))
-            .show(ctx, |ui| {
-                let response = ActivityBar::new(self.active_activity)
-                    .git_changes(0)
-                    .show(ui);
+        self.completion = CompletionState {
+            is_open: true,
+            tab: self.active_tab,
+            word_start: path.segment_start,
+            prefix: file.buffer.slice(path.segment_start..offset).to_string(),
+            candidates,
+            in_path: true,
+            anchor: self.completion.anchor,
+            ..Default::default()
+        };
+        self.completion.refilter();
+    }
 
-                if let Some(item) = response.clicked_item {
-                    self.active_activity = item;
-                }
-            });
+    /// Close the popup once the caret leaves the word, otherwise refilter it
+    /// for what has been typed
+    fn update_completion(&mut self) {
+        if !self.completion.is_open {
+            return;
+        }
+        let Some(file) = self
+            .open_files
+            .get(self.active_tab)
+            .filter(|_| self.completion.tab == self.active_tab)
+        else {
+            self.completion.close();
+            return;
+        };
+        let cursor = file.state.cursor();
+        let offset = cursor.offset;
+        let word_start = if self.completion.in_path {
+            completion::path_prefix(&file.buffer, offset).map(|path| path.segment_start)
+        } else {
+            Some(completion::word_start(&file.buffer, offset))
+        };
+        if file.state.has_multiple_cursors()
+            || cursor.has_selection()
+            || offset < self.completion.word_start
+            || word_start != Some(self.completion.word_start)
+        {
+            self.completion.close();
+            return;
+        }
+        let prefix = file
+            .buffer
+            .slice(self.completion.word_start..offset)
+            .to_string();
+        if prefix != self.completion.prefix {
+            self.completion.prefix = prefix;
+            self.completion.refilter();
+        }
     }
 
-    // === Sidebar ===
+    /// Replace the word before the caret with an item
+    fn accept_completion(&mut self, index: usize) {
+        let Some(item) = self.completion.items.get(index).cloned() else {
+            return;
+        };
+        let word_start = self.completion.word_start;
+        self.completion.close();
+        let Some(file) = self.open_files.get_mut(self.active_tab) else {
+            return;
+        };
+        let offset = file.state.cursor().offset;
+        file.replace_ranges(
+            std::slice::from_ref(&(word_start..offset)),
+            &item.insert_text,
+        );
+        let end = word_start + item.insert_text.chars().count();
+        file.state
+            .set_cursor(CursorPosition::from_char_offset(&file.buffer, end));
+        file.state.scroll_to_cursor = true;
+        // Keep going into a completed folder
+        if item.insert_text.ends_with('/') {
+            self.trigger_completion(false);
+        }
+    }
 
-    fn render_sidebar(&mut self, ctx: &egui::Context) {
-        egui::SidePanel::left("explorer")
-            .resizable(true)
-            .default_width(layout::SIDEBAR_DEFAULT_WIDTH)
-            .min_width(layout::SIDEBAR_MIN_WIDTH)
-            .frame(
-                Frame::none()
-                    .fill(colors::PANEL_BG)
-                    .inner_margin(Margin::same(0.0)),
-            )
-            .show(ctx, |ui| {
-                self.render_explorer_header(ui);
-                ui.separator();
-                self.render_file_tree(ui);
-            });
+    fn render_completion_popup(&mut self, ctx: &egui::Context) {
+        let response = CompletionPopup::new(&self.completion.items, self.completion.selected)
+            .position(self.completion.anchor)
+            .show(ctx);
+        if let Some(index) = response.clicked {
+            self.accept_completion(index);
+            ctx.memory_mut(|m| m.request_focus(egui::Id::new(CODE_EDITOR_ID)));
+        }
     }
 
-    fn render_explorer_header(&self, ui: &mut egui::Ui) {
-        Frame::none()
-            .inner_margin(Margin::symmetric(12.0, 8.0))
-            .show(ui, |ui| {
-                ui.label(
-                    RichText::new("EXPLORER")
-                        .size(fonts::EXPLORER_HEADER)
-                        .color(colors::TEXT_MUTED)
-                        .strong(),
-                );
+    // === Hover ===
+
+    /// Look up the identifier the pointer rests on: ask the language server,
+    /// and until it answers (or without one) show how often the word occurs
+    fn update_hover(&mut self, offset: Option<usize>) {
+        let file = &mut self.open_files[self.active_tab];
+        let word = offset.and_then(|offset| hover::word_at(&file.buffer, offset));
+        let Some(word) = word else {
+            self.hover = HoverState::default();
+            return;
+        };
+        let unchanged = self.hover.tab == self.active_tab
+            && self.hover.revision == file.state.revision
+            && self.hover.word.as_ref() == Some(&word);
+        if unchanged {
+            return;
+        }
+
+        let text = file.buffer.slice(word.clone()).to_string();
+        let count = hover::word_positions(&file.buffer, &text).len();
+        let pending_request = self
+            .language_servers
+            .get_mut(file.extension())
+            .filter(|client| client.is_open(&file.path))
+            .map(|client| {
+                if file.lsp_revision != Some(file.state.revision) {
+                    client.did_change(&file.path, file.buffer.to_string());
+                    file.lsp_revision = Some(file.state.revision);
+                }
+                let position = client.encoding().to_position(&file.buffer, word.start);
+                client.hover(&file.path, position)
             });
+        self.hover = HoverState {
+            tab: self.active_tab,
+            revision: file.state.revision,
+            word: Some(word),
+            pending_request,
+            contents: Some(format!(
+                "{text}: {count} occurrence{} in this file",
+                if count == 1 { "" } else { "s" }
+            )),
+        };
     }
 
-    fn render_file_tree(&mut self, ui: &mut egui::Ui) {
-        let mut file_to_open: Option<PathBuf> = None;
-        let active_path = self.open_files.get(self.active_tab).map(|f| f.path.clone());
+    // === Occurrences ===
 
-        ScrollArea::vertical()
-            .auto_shrink([false, false])
-            .show(ui, |ui| {
-                ui.add_space(8.0);
-                ui.spacing_mut().item_spacing.y = 0.0;
+    /// Find the uses of the identifier at the caret, as reads and writes,
+    /// when the caret moves to another word
+    fn update_occurrences(&mut self) {
+        let file = &mut self.open_files[self.active_tab];
+        let cursor = file.state.cursor();
+        let word = if cursor.has_selection() || file.state.has_multiple_cursors() {
+            None
+        } else {
+            // The caret right after an identifier is still on it
+            hover::word_at(&file.buffer, cursor.offset).or_else(|| {
+                let before = cursor.offset.checked_sub(1)?;
+                hover::word_at(&file.buffer, before)
+            })
+        };
+        let Some(word) = word else {
+            self.occurrences = OccurrenceState::default();
+            return;
+        };
+        let unchanged = self.occurrences.tab == self.active_tab
+            && self.occurrences.revision == file.state.revision
+            && self.occurrences.word.as_ref() == Some(&word);
+        if unchanged {
+            return;
+        }
 
-                for node in &self.tree {
-                    Self::render_file_node(ui, node, &mut file_to_open, active_path.as_ref(), 0);
+        let pending_request = self
+            .language_servers
+            .get_mut(file.extension())
+            .filter(|client| client.is_open(&file.path))
+            .map(|client| {
+                if file.lsp_revision != Some(file.state.revision) {
+                    client.did_change(&file.path, file.buffer.to_string());
+                    file.lsp_revision = Some(file.state.revision);
                 }
-                ui.add_space(8.0);
+                let position = client.encoding().to_position(&file.buffer, word.start);
+                client.document_highlights(&file.path, position)
             });
+        self.occurrences = OccurrenceState {
+            tab: self.active_tab,
+            revision: file.state.revision,
+            occurrences: occurrences::occurrences(&file.buffer, word.clone()),
+            word: Some(word),
+            pending_request,
+        };
+    }
 
-        if let Some(path) = file_to_open {
-            self.open_file(path);
+    // === Go to Definition ===
+
+    /// Ask the language server where the symbol at `offset` is defined; the
+    /// answer is handled in `sync_language_servers`
+    fn go_to_definition(&mut self, offset: usize) {
+        let Some(file) = self.open_files.get_mut(self.active_tab) else {
+            return;
+        };
+        let Some(client) = self
+            .language_servers
+            .get_mut(file.extension())
+            .filter(|client| client.is_open(&file.path))
+        else {
+            return;
+        };
+        if file.lsp_revision != Some(file.state.revision) {
+            client.did_change(&file.path, file.buffer.to_string());
+            file.lsp_revision = Some(file.state.revision);
         }
+        let position = client.encoding().to_position(&file.buffer, offset);
+        self.pending_definition = Some(client.definition(&file.path, position));
     }
 
-    fn render_file_node(
-        ui: &mut egui::Ui,
-        node: &FileNode,
-        file_to_open: &mut Option<PathBuf>,
-        active_path: Option<&PathBuf>,
-        depth: usize,
+    /// Open a file (or switch to its tab) and move the cursor to a position
+    fn open_location(
+        &mut self,
+        path: PathBuf,
+        position: lsp::Position,
+        encoding: PositionEncoding,
     ) {
-        let indent = depth as f32 * layout::INDENT_SIZE;
-        let name = node.name();
-        let item_height = layout::LINE_HEIGHT + 2.0;
-
-        if node.is_dir {
-            ui.horizontal(|ui| {
-                ui.add_space(indent);
-                let header = format!("{} {}", file_icons::FOLDER_ICON, name);
-                egui::CollapsingHeader::new(RichText::new(header).size(fonts::BODY))
-                    .default_open(depth == 0)
-                    .show(ui, |ui| {
-                        for child in &node.children {
-                            Self::render_file_node(ui, child, file_to_open, active_path, depth + 1);
-                        }
-                    });
-            });
-        } else {
-            let is_selected = active_path == Some(&node.path);
-            let available_width = ui.available_width();
+        self.record_jump();
+        self.open_file(path.clone());
+        let Some(file) = self.open_files.get_mut(self.active_tab) else {
+            return;
+        };
+        if file.path != path {
+            // Opened in the log viewer
+            return;
+        }
+        let offset = encoding.to_char(&file.buffer, position);
+        file.state.clear_secondary_cursors();
+        file.state
+            .set_cursor(CursorPosition::from_char_offset(&file.buffer, offset));
+        file.state.scroll_to_cursor = true;
+        file.state.history.break_group();
+    }
 
-            let (rect, response) = ui.allocate_exact_size(
-                Vec2::new(available_width, item_height),
-                egui::Sense::click(),
-            );
+    // === Go to File ===
 
-            // Draw background for hover/selection
-            let bg_color = if is_selected {
-                colors::FILE_TREE_SELECTED
-            } else if response.hovered() {
-                colors::FILE_TREE_HOVER
-            } else {
-                Color32::TRANSPARENT
-            };
+    /// Open the Ctrl+P picker over the files of the Explorer tree, read again
+    /// so new files show up, leaving out what git ignores
+    fn open_quick_open(&mut self) {
+        let Some(workspace) = self.workspace.clone() else {
+            return;
+        };
+        self.refresh_tree();
+        let ignored = git::ignored_paths(&workspace).unwrap_or_default();
+        let is_ignored = |path: &str| {
+            ignored
+                .iter()
+                .any(|i| path == i || (i.ends_with('/') && path.starts_with(i.as_str())))
+        };
+        let quick_open = &mut self.quick_open;
+        quick_open.picker.open();
+        quick_open.files = self
+            .tree
+            .iter()
+            .flat_map(FileNode::files)
+            .filter_map(|path| {
+                let path = path.strip_prefix(&workspace).ok()?;
+                Some(path.to_string_lossy().replace('\\', "/"))
+            })
+            .filter(|path| !is_ignored(path))
+            .collect();
+        quick_open.refilter();
+    }
 
-            if bg_color != Color32::TRANSPARENT {
-                ui.painter().rect_filled(rect, 0.0, bg_color);
+    fn render_quick_open(&mut self, ctx: &egui::Context) {
+        let quick_open = &mut self.quick_open;
+        let items: Vec<QuickPickItem> = quick_open
+            .matches
+            .iter()
+            .map(|&i| {
+                let path = &quick_open.files[i];
+                let (folder, name) = path.rsplit_once('/').unwrap_or(("", path));
+                QuickPickItem {
+                    icon: file_icons::get_icon(name),
+                    label: name.to_string(),
+                    detail: folder.to_string(),
+                }
+            })
+            .collect();
+        let response = QuickPick::new(
+            "quick_open",
+            &mut quick_open.picker.query,
+            &items,
+            &mut quick_open.picker.selected,
+        )
+        .hint_text("Search files by name")
+        .empty_text("No matching files")
+        .show(ctx);
+
+        if response.query_changed {
+            quick_open.picker.selected = 0;
+            quick_open.refilter();
+        }
+        if let Some(index) = response.accepted {
+            quick_open.picker.is_open = false;
+            let path = quick_open.files[quick_open.matches[index]].clone();
+            if let Some(workspace) = self.workspace.clone() {
+                self.record_jump();
+                self.open_file(workspace.join(path));
+                ctx.memory_mut(|m| m.request_focus(egui::Id::new(CODE_EDITOR_ID)));
             }
+        } else if response.dismissed {
+            quick_open.picker.is_open = false;
+        }
+    }
 
-            // Draw icon and text
-            let icon = file_icons::get_icon(&name);
-            let text_pos = Pos2::new(
-                rect.left() + indent + layout::INDENT_SIZE + 4.0,
-                rect.center().y,
-            );
-            ui.painter().text(
-                text_pos,
-                egui::Align2::LEFT_CENTER,
-                format!("{} {}", icon, name),
-                FontId::proportional(fonts::BODY),
-                colors::TEXT_PRIMARY,
-            );
+    // === Workspace Symbols ===
+
+    /// Open the Ctrl+T picker and index the workspace again in the background
+    fn open_symbol_search(&mut self, ctx: &egui::Context) {
+        let exclusions = self.workspace.as_ref().map(|w| self.exclusions(w));
+        let search = &mut self.symbol_search;
+        search.is_open = true;
+        search.query.clear();
+        search.selected = 0;
+        search.lsp_symbols.clear();
+        search.pending_requests.clear();
+        search.stale = true;
+        if let (Some(workspace), Some(exclusions)) = (&self.workspace, exclusions) {
+            search.indexing = Some(symbol_index::index_in_background(
+                workspace.clone(),
+                exclusions,
+                ctx.clone(),
+            ));
+        }
+    }
 
-            if response.clicked() {
-                *file_to_open = Some(node.path.clone());
-            }
+    fn render_symbol_search(&mut self, ctx: &egui::Context) {
+        let roots = self.workspace_roots().to_vec();
+        let search = &mut self.symbol_search;
+        if let Some(index) = search.indexing.as_ref().and_then(|rx| rx.try_recv().ok()) {
+            search.index = index;
+            search.indexing = None;
+            search.stale = true;
+        }
+        if search.stale {
+            search.refilter();
+        }
+
+        let workspace = self.workspace.as_deref();
+        let items: Vec<QuickPickItem> = search
+            .matches
+            .iter()
+            .map(|symbol| QuickPickItem {
+                icon: symbol.kind.icon(),
+                label: symbol.name.clone(),
+                detail: format!(
+                    "{}:{}",
+                    display_path(&symbol.path, &roots),
+                    symbol.position.line + 1
+                ),
+            })
+            .collect();
+        let empty_text = if search.indexing.is_some() || !search.pending_requests.is_empty() {
+            "Searching..."
+        } else if workspace.is_none() && self.language_servers.is_empty() {
+            "Open a folder to search its symbols"
+        } else {
+            "No matching symbols"
+        };
+        let response = QuickPick::new(
+            "symbol_search",
+            &mut search.query,
+            &items,
+            &mut search.selected,
+        )
+        .hint_text("Go to symbol in workspace")
+        .empty_text(empty_text)
+        .show(ctx);
+
+        if response.query_changed {
+            search.lsp_symbols.clear();
+            search.pending_requests = self
+                .language_servers
+                .values_mut()
+                .map(|client| client.workspace_symbols(&search.query))
+                .collect();
+            search.stale = true;
+        }
+        if let Some(index) = response.accepted {
+            let symbol = search.matches[index].clone();
+            search.is_open = false;
+            self.open_location(symbol.path, symbol.position, symbol.encoding);
+            ctx.memory_mut(|m| m.request_focus(egui::Id::new(CODE_EDITOR_ID)));
+        } else if response.dismissed {
+            search.is_open = false;
         }
     }
 
-    // === Editor ===
+    // === Find References ===
 
-    fn render_editor(&mut self, ctx: &egui::Context) {
-        egui::CentralPanel::default()
-            .frame(
-                Frame::none()
-                    .fill(colors::EDITOR_BG)
-                    .inner_margin(Margin::same(0.0)),
-            )
-            .show(ctx, |ui| {
-                self.render_tab_bar(ui);
-                ui.separator();
-                self.render_editor_content(ui);
+    /// List the uses of the identifier at the cursor: from the language server
+    /// when there is one, otherwise its whole-word matches in the open files
+    fn find_references(&mut self) {
+        let Some(file) = self.open_files.get_mut(self.active_tab) else {
+            return;
+        };
+        let offset = file.state.cursor().offset;
+        // The cursor may sit right after the word
+        let Some(word) = hover::word_at(&file.buffer, offset)
+            .or_else(|| hover::word_at(&file.buffer, offset.checked_sub(1)?))
+        else {
+            return;
+        };
+        let symbol = file.buffer.slice(word.clone()).to_string();
+
+        let pending_request = self
+            .language_servers
+            .get_mut(file.extension())
+            .filter(|client| client.is_open(&file.path))
+            .map(|client| {
+                if file.lsp_revision != Some(file.state.revision) {
+                    client.did_change(&file.path, file.buffer.to_string());
+                    file.lsp_revision = Some(file.state.revision);
+                }
+                let position = client.encoding().to_position(&file.buffer, word.start);
+                client.references(&file.path, position)
             });
+        let references = if pending_request.is_some() {
+            Vec::new()
+        } else {
+            self.buffer_references(&symbol)
+        };
+        self.references = ReferencesState {
+            is_open: true,
+            symbol,
+            pending_request,
+            references,
+        };
     }
 
-    fn render_tab_bar(&mut self, ui: &mut egui::Ui) {
-        let tabs: Vec<Tab> = self
+    /// Whole-word matches of `symbol` in the open files
+    fn buffer_references(&self, symbol: &str) -> Vec<Reference> {
+        let mut references: Vec<Reference> = self
             .open_files
             .iter()
-            .map(|f| Tab::new(f.name(), file_icons::get_icon(&f.name())).modified(f.is_modified()))
+            .flat_map(|file| {
+                hover::word_positions(&file.buffer, symbol)
+                    .into_iter()
+                    .map(|offset| reference_at(&file.path, &file.buffer, offset))
+            })
             .collect();
+        references.sort_by(|a, b| (&a.path, a.line, a.column).cmp(&(&b.path, b.line, b.column)));
+        references
+    }
 
-        if tabs.is_empty() {
+    /// References for locations from a language server, with the line text
+    /// taken from the open buffer or read from disk
+    fn resolve_references(
+        &self,
+        mut locations: Vec<(PathBuf, lsp::Position)>,
+        encoding: PositionEncoding,
+    ) -> Vec<Reference> {
+        locations.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let mut references = Vec::new();
+        let mut text: Option<(&PathBuf, Rope)> = None;
+        for (path, position) in &locations {
+            if text.as_ref().map(|(p, _)| *p) != Some(path) {
+                let rope = match self.open_files.iter().find(|f| &f.path == path) {
+                    Some(file) => file.buffer.clone(),
+                    None => Rope::from_str(&std::fs::read_to_string(path).unwrap_or_default()),
+                };
+                text = Some((path, rope));
+            }
+            let Some((_, rope)) = &text else {
+                continue;
+            };
+            references.push(reference_at(path, rope, encoding.to_char(rope, *position)));
+        }
+        references.sort_by(|a, b| (&a.path, a.line, a.column).cmp(&(&b.path, b.line, b.column)));
+        references
+    }
+
+    fn render_references_panel(&mut self, ctx: &egui::Context) {
+        if self
+            .floating_panels
+            .contains_key(&FloatingPanel::References)
+        {
+            self.show_floating_panel(ctx, FloatingPanel::References, Self::references_contents);
             return;
         }
+        let colors = palette(&ctx.style().visuals);
+        egui::TopBottomPanel::bottom("references_panel")
+            .resizable(true)
+            .default_height(self.layout.references_height)
+            .frame(
+                Frame::none()
+                    .fill(colors.panel_bg)
+                    .inner_margin(Margin::symmetric(8.0, 4.0)),
+            )
+            .show(ctx, |ui| self.references_contents(ui));
+    }
 
-        Frame::none()
-            .fill(colors::PANEL_BG)
-            .inner_margin(Margin::symmetric(0.0, 4.0))
-            .show(ui, |ui| {
-                let response = TabBar::new(tabs, self.active_tab).show(ui);
+    fn references_contents(&mut self, ui: &mut egui::Ui) {
+        let response = ReferencesPanel::new(&self.references.symbol, &self.references.references)
+            .searching(self.references.pending_request.is_some())
+            .floating(
+                self.floating_panels
+                    .contains_key(&FloatingPanel::References),
+            )
+            .show(ui);
+        if let Some(index) = response.clicked {
+            let reference = self.references.references[index].clone();
+            let position = lsp::Position {
+                line: reference.line,
+                character: reference.column,
+            };
+            self.open_location(reference.path, position, PositionEncoding::Utf32);
+        }
+        if response.close_clicked {
+            self.references.is_open = false;
+        }
+        if response.float_toggled {
+            self.toggle_floating_panel(ui.ctx(), FloatingPanel::References);
+        }
+    }
 
-                if let Some(idx) = response.activated {
-                    self.active_tab = idx;
-                }
-                if let Some(idx) = response.closed {
-                    self.close_tab(idx);
-                }
+    // === Floating Panels ===
+
+    /// Move a bottom panel to a window at the pointer, or dock it back
+    fn toggle_floating_panel(&mut self, ctx: &egui::Context, panel: FloatingPanel) {
+        if self.floating_panels.remove(&panel).is_none() {
+            let position = ctx.input(|i| {
+                Some(i.viewport().inner_rect?.min + i.pointer.interact_pos()?.to_vec2())
             });
+            self.floating_panels.insert(panel, position);
+        }
     }
 
-    fn render_editor_content(&mut self, ui: &mut egui::Ui) {
-        if self.open_files.is_empty() {
-            self.render_welcome_screen(ui);
-            return;
+    /// Show a bottom panel in a native window of its own, or in a window inside
+    /// the main one when the platform has no multiple windows. Closing the
+    /// window docks the panel back.
+    fn show_floating_panel(
+        &mut self,
+        ctx: &egui::Context,
+        panel: FloatingPanel,
+        contents: fn(&mut Self, &mut egui::Ui),
+    ) {
+        let colors = palette(&ctx.style().visuals);
+        let size = egui::vec2(720.0, 260.0);
+        let mut builder = egui::ViewportBuilder::default()
+            .with_title(panel.title())
+            .with_inner_size(size);
+        // Only the first frame places the window, afterwards it stays where the user moves it
+        if let Some(position) = self.floating_panels.get_mut(&panel).and_then(Option::take) {
+            builder = builder.with_position(position);
+        }
+        let closed = ctx.show_viewport_immediate(
+            egui::ViewportId::from_hash_of(panel),
+            builder,
+            |ctx, class| {
+                if class == egui::ViewportClass::Embedded {
+                    let mut open = true;
+                    egui::Window::new(panel.title())
+                        .open(&mut open)
+                        .default_size(size)
+                        .show(ctx, |ui| contents(self, ui));
+                    return !open;
+                }
+                egui::CentralPanel::default()
+                    .frame(
+                        Frame::none()
+                            .fill(colors.panel_bg)
+                            .inner_margin(Margin::symmetric(8.0, 4.0)),
+                    )
+                    .show(ctx, |ui| contents(self, ui));
+                ctx.input(|i| i.viewport().close_requested())
+            },
+        );
+        if closed {
+            self.floating_panels.remove(&panel);
         }
+    }
 
-        let active_idx = self.active_tab;
-        let line_height = layout::LINE_HEIGHT;
-        let available_height = ui.available_height();
-        let show_minimap = self.show_minimap;
-        let scroll_offset_y = self.editor_scroll_offset.y;
+    // === Output Panel ===
 
-        // Get file info for line numbers and minimap
-        let (total_lines, text_content, visible_lines, current_line) = {
-            let file = &self.open_files[active_idx];
-            (
-                file.buffer.len_lines(),
-                file.buffer.to_string(),
-                file.state.visible_lines,
-                file.state.cursor.line,
+    /// Append a message to the Output panel and reveal it
+    fn log_output(&mut self, message: impl Into<String>) {
+        self.output.push(message.into());
+        self.layout.show_output = true;
+    }
+
+    fn render_output_panel(&mut self, ctx: &egui::Context) {
+        if self.floating_panels.contains_key(&FloatingPanel::Output) {
+            self.show_floating_panel(ctx, FloatingPanel::Output, Self::output_contents);
+            return;
+        }
+        let colors = palette(&ctx.style().visuals);
+        egui::TopBottomPanel::bottom("output_panel")
+            .resizable(true)
+            .default_height(self.layout.output_height)
+            .frame(
+                Frame::none()
+                    .fill(colors.panel_bg)
+                    .inner_margin(Margin::symmetric(8.0, 4.0)),
             )
+            .show(ctx, |ui| self.output_contents(ui));
+    }
+
+    fn output_contents(&mut self, ui: &mut egui::Ui) {
+        let response = OutputPanel::new(&self.output)
+            .floating(self.floating_panels.contains_key(&FloatingPanel::Output))
+            .show(ui);
+        if response.clear_clicked {
+            self.output.clear();
+        }
+        if response.close_clicked {
+            self.layout.show_output = false;
+        }
+        if response.float_toggled {
+            self.toggle_floating_panel(ui.ctx(), FloatingPanel::Output);
+        }
+        if let Some(link) = response.link_clicked {
+            self.open_output_link(link);
+        }
+    }
+
+    /// Open a URL from the Output panel in the browser, or a file location in the editor
+    fn open_output_link(&mut self, link: OutputLink) {
+        let (path, line, column) = match link {
+            OutputLink::Url(url) => match url.strip_prefix("file://") {
+                Some(path) => (path.to_string(), 1, 1),
+                None => {
+                    if let Err(e) = server::open_in_browser(&url) {
+                        self.log_output(format!("Failed to open {url}: {e}"));
+                    }
+                    return;
+                }
+            },
+            OutputLink::Location { path, line, column } => (path, line, column),
         };
 
-        let mut minimap_clicked_line: Option<usize> = None;
+        // Relative paths are usually relative to the workspace
+        let path = PathBuf::from(path);
+        let path = match &self.workspace {
+            Some(workspace) if path.is_relative() => workspace.join(path),
+            _ => path,
+        };
+        if !path.is_file() {
+            self.log_output(format!("{} not found", path.display()));
+            return;
+        }
+        let position = lsp::Position {
+            line: line.saturating_sub(1),
+            character: column.saturating_sub(1),
+        };
+        self.open_location(path, position, PositionEncoding::Utf32);
+    }
 
-        ui.horizontal(|ui| {
-            ui.spacing_mut().item_spacing = Vec2::ZERO;
+    /// Ask the background highlighter for fresh colors if a tab changed since the
+    /// last request
+    fn request_highlighting(&mut self, ctx: &egui::Context, idx: usize, dark_mode: bool) {
+        let theme = if dark_mode {
+            &self.settings.dark_syntax_theme
+        } else {
+            &self.settings.light_syntax_theme
+        };
+        let file = &mut self.open_files[idx];
+        let wanted = (file.state.revision, theme.clone());
+        if file.highlight_requested.as_ref() == Some(&wanted) {
+            return;
+        }
 
-            // === Line Numbers Gutter ===
-            LineNumbersGutter::new(total_lines)
-                .current_line(current_line)
-                .scroll_offset(scroll_offset_y)
-                .line_height(line_height)
-                .visible_height(available_height)
-                .show(ui);
+        self.highlighter.request(HighlightRequest {
+            document: file.id,
+            revision: file.state.revision,
+            buffer: file.buffer.clone(),
+            extension: file.extension().to_string(),
+            theme: theme.clone(),
+            ctx: ctx.clone(),
+        });
+        file.highlight_requested = Some(wanted);
+    }
 
-            // === Main Editor Area ===
-            // Editor fills remaining space between gutter and minimap
-            let minimap_width = if show_minimap {
-                layout::MINIMAP_WIDTH
-            } else {
-                0.0
-            };
-            let editor_width = ui.available_width() - minimap_width;
+    fn receive_highlighting(&mut self) {
+        for result in self.highlighter.poll() {
+            if let Some(file) = self.open_files.iter_mut().find(|f| f.id == result.document) {
+                file.line_styles = result.styles;
+                file.symbols = result.symbols;
+            }
+        }
+    }
 
-            ui.vertical(|ui| {
-                ui.set_width(editor_width);
-                ui.set_height(available_height);
-                self.render_text_editor(ui, line_height);
-            });
+    /// Match the bracket at the caret when it moves, and after edits once
+    /// typing pauses, leaving the pair unmarked until then
+    fn update_bracket_match(&mut self, ctx: &egui::Context, idx: usize) {
+        let file = &mut self.open_files[idx];
+        let offset = file.state.cursor().offset;
+        match self.bracket_debounce.poll(file.id, file.state.revision) {
+            Debounce::Wait(left) => {
+                file.bracket_match = None;
+                ctx.request_repaint_after(left);
+                return;
+            }
+            Debounce::Done if file.bracket_match.is_some_and(|(at, _)| at == offset) => return,
+            Debounce::Ready | Debounce::Done => {}
+        }
+        file.bracket_match = Some((offset, file.matching_bracket(offset)));
+    }
 
-            // === Minimap ===
-            if show_minimap {
-                let minimap_response = Minimap::new(&text_content, total_lines)
-                    .visible_lines(visible_lines)
-                    .current_line(current_line)
-                    .show(ui);
+    /// Find matches and the bracket pair around the primary cursor
+    fn editor_highlights(&self, idx: usize, colors: &Palette) -> Vec<TextHighlight> {
+        let file = &self.open_files[idx];
 
-                minimap_clicked_line = minimap_response.clicked_line;
+        // Occurrences first, so find matches are drawn over them
+        let occurrences: &[Occurrence] = if self.occurrences.tab == idx {
+            &self.occurrences.occurrences
+        } else {
+            &[]
+        };
+        let mut highlights: Vec<TextHighlight> = occurrences
+            .iter()
+            .map(|occurrence| TextHighlight {
+                range: occurrence.range.clone(),
+                fill: if occurrence.write {
+                    colors.occurrence_write_bg
+                } else {
+                    colors.occurrence_read_bg
+                },
+                stroke: Stroke::NONE,
+            })
+            .collect();
+        // Matches wait for typing to pause before they are found again, and
+        // may reach past the end of the text until then
+        let len = file.buffer.len_chars();
+        let matches = self.find_replace.matches.iter().enumerate();
+        highlights.extend(
+            matches
+                .filter(|(_, &(_, end))| end <= len)
+                .map(|(i, &(start, end))| {
+                    if i == self.find_replace.current_match {
+                        TextHighlight {
+                            range: start..end,
+                            fill: colors.find_match_current_bg,
+                            stroke: Stroke::new(2.0, colors.find_match_border),
+                        }
+                    } else {
+                        TextHighlight {
+                            range: start..end,
+                            fill: colors.find_match_bg,
+                            stroke: Stroke::NONE,
+                        }
+                    }
+                }),
+        );
+
+        if let Some((_, Some((open_pos, close_pos)))) = file.bracket_match {
+            for pos in [open_pos, close_pos] {
+                highlights.push(TextHighlight {
+                    range: pos..pos + 1,
+                    fill: colors.bracket_match_bg,
+                    stroke: Stroke::new(1.0, colors.bracket_match_border),
+                });
             }
-        });
+        }
 
-        // Handle minimap click
-        if let Some(clicked_line) = minimap_clicked_line {
-            let target_y = (clicked_line.saturating_sub(1)) as f32 * line_height;
-            self.editor_scroll_offset.y = target_y;
+        // Fading out over the flash duration
+        if let Some((range, at)) = &file.flash {
+            let left = 1.0 - at.elapsed().as_secs_f32() / FLASH_DURATION.as_secs_f32();
+            if left > 0.0 {
+                let len = file.buffer.len_chars();
+                highlights.push(TextHighlight {
+                    range: range.start.min(len)..range.end.min(len),
+                    fill: colors.find_match_current_bg.gamma_multiply(left),
+                    stroke: Stroke::NONE,
+                });
+            }
         }
+
+        highlights
     }
 
     fn render_text_editor(&mut self, ui: &mut egui::Ui, line_height: f32) {
         let active_idx = self.active_tab;
-        let file = &mut self.open_files[active_idx];
-        let mut text = file.buffer.to_string();
-        let original = file.original_content.clone();
-        let current_line = file.state.cursor.line;
-        let prev_char_count = text.chars().count();
-
-        // Get syntax highlighting info
-        let ext = file.extension().to_string();
-        let syntax = self
-            .syntax_set
-            .find_syntax_by_extension(&ext)
-            .unwrap_or_else(|| self.syntax_set.find_syntax_plain_text())
-            .clone();
-        let theme = self.theme_set.themes[SYNTAX_THEME].clone();
-        let syntax_set = self.syntax_set.clone();
-
-        let mut layouter = |ui: &egui::Ui, text: &str, wrap_width: f32| {
-            let mut job = egui::text::LayoutJob::default();
-            job.wrap.max_width = wrap_width;
-
-            let mut highlighter = HighlightLines::new(&syntax, &theme);
-
-            for line in text.lines() {
-                if let Ok(ranges) = highlighter.highlight_line(line, &syntax_set) {
-                    for (style, segment) in ranges {
-                        job.append(
-                            segment,
-                            0.0,
-                            egui::TextFormat {
-                                font_id: FontId::monospace(fonts::BODY),
-                                color: Color32::from_rgb(
-                                    style.foreground.r,
-                                    style.foreground.g,
-                                    style.foreground.b,
-                                ),
-                                line_height: Some(line_height),
-                                ..Default::default()
-                            },
-                        );
-                    }
-                } else {
-                    job.append(
-                        line,
-                        0.0,
-                        egui::TextFormat {
-                            font_id: FontId::monospace(fonts::BODY),
-                            color: colors::TEXT_FALLBACK,
-                            line_height: Some(line_height),
-                            ..Default::default()
-                        },
-                    );
-                }
-                job.append(
-                    "\n",
-                    0.0,
-                    egui::TextFormat {
-                        line_height: Some(line_height),
-                        ..Default::default()
-                    },
-                );
-            }
-
-            ui.fonts(|f| f.layout_job(job))
+        self.receive_highlighting();
+        self.request_highlighting(ui.ctx(), active_idx, ui.visuals().dark_mode);
+        #[cfg(feature = "tree-sitter")]
+        self.open_files[active_idx].update_syntax_tree();
+        self.update_occurrences();
+        self.update_search(ui.ctx());
+        self.update_bracket_match(ui.ctx(), active_idx);
+        let mut highlights = self.editor_highlights(active_idx, palette(ui.visuals()));
+        let flash = &mut self.open_files[active_idx].flash;
+        if flash
+            .as_ref()
+            .is_some_and(|(_, at)| at.elapsed() < FLASH_DURATION)
+        {
+            ui.ctx().request_repaint();
+        } else {
+            *flash = None;
+        }
+        let (remote_carets, remote_selections) = self.remote_carets(active_idx);
+        highlights.extend(remote_selections);
+        let mut underlines = self.diagnostic_underlines(active_idx, palette(ui.visuals()));
+        underlines.extend(self.control_char_underlines(active_idx, palette(ui.visuals())));
+        if ui.input(|i| i.modifiers.ctrl) {
+            underlines.extend(self.doc_link_underline(active_idx, palette(ui.visuals())));
+        }
+        let hover_markdown = (self.hover.tab == active_idx)
+            .then(|| self.hover.contents.clone())
+            .flatten();
+        let code_lenses = if self.open_files[active_idx].is_http() {
+            self.open_files[active_idx].update_http_requests();
+            self.open_files[active_idx]
+                .http_requests
+                .iter()
+                .map(|r| CodeLens {
+                    line: r.line,
+                    label: "Send Request".to_string(),
+                })
+                .collect()
+        } else {
+            Vec::new()
         };
 
         // ScrollArea fills available space directly - no Frame wrapper
-        let scroll_area = ScrollArea::both()
+        let word_wrap = self.word_wrap(active_idx);
+        let add_caret_with_ctrl = self.settings.multi_cursor_modifier == MultiCursorModifier::Ctrl;
+        let font_size = self.open_files[active_idx]
+            .state
+            .font_size
+            .unwrap_or(fonts::BODY);
+        let mut scroll_area = ScrollArea::new([!word_wrap, true])
             .auto_shrink([false, false])
             .scroll_bar_visibility(egui::scroll_area::ScrollBarVisibility::AlwaysVisible);
+        if word_wrap {
+            scroll_area = scroll_area.horizontal_scroll_offset(0.0);
+        }
+        if let Some(fraction) = self.preview.scroll_editor_to.take() {
+            scroll_area =
+                scroll_area.vertical_scroll_offset(fraction * self.preview.editor_max_scroll);
+        }
+        if let Some(offset) = self.restore_scroll.take() {
+            scroll_area = scroll_area.scroll_offset(offset);
+        }
 
-        // Calculate character width for indent guides
-        let char_width = ui.fonts(|f| f.glyph_width(&FontId::monospace(fonts::BODY), ' '));
+        // The tag pair at the caret before this frame's edits
+        let tag_pair = self.tag_pair_at_caret(active_idx);
 
-        // Pre-calculate indent levels for each line
-        let indent_levels: Vec<usize> = text
-            .lines()
-            .map(|line| {
-                let spaces = line.chars().take_while(|c| *c == ' ').count();
-                let tabs = line.chars().take_while(|c| *c == '\t').count();
-                (spaces + tabs * layout::TAB_SIZE) / layout::TAB_SIZE
-            })
-            .collect();
+        let pixel_snapping = self.settings.text_rendering.pixel_snapping;
+        let render_whitespace = self.settings.render_whitespace;
+        let file = &mut self.open_files[active_idx];
+        let scroll_output = scroll_area.show(ui, |ui| {
+            CodeEditor::new(
+                egui::Id::new(CODE_EDITOR_ID),
+                &mut file.buffer,
+                &mut file.state,
+            )
+            .line_styles(&file.line_styles)
+            .highlights(highlights)
+            .underlines(underlines)
+            .remote_carets(remote_carets)
+            .code_lenses(code_lenses)
+            .hover_markdown(hover_markdown)
+            .line_height(line_height)
+            .font_size(font_size)
+            .pixel_snapping(pixel_snapping)
+            .render_whitespace(render_whitespace)
+            .word_wrap(word_wrap)
+            .add_caret_with_ctrl(add_caret_with_ctrl)
+            .show(ui)
+        });
 
-        // Find the active indent level (indent level of the current line)
-        let active_indent = if current_line > 0 && current_line <= indent_levels.len() {
-            indent_levels[current_line - 1]
-        } else {
-            0
+        // Store scroll offset for gutter sync
+        self.editor_scroll_offset = scroll_output.state.offset;
+        self.completion.anchor = scroll_output.inner.caret.left_bottom();
+
+        // Relative position for the Markdown preview
+        let max_scroll =
+            (scroll_output.content_size.y - scroll_output.inner_rect.height()).max(0.0);
+        let fraction = scroll_fraction(self.editor_scroll_offset.y, max_scroll);
+        self.preview.editor_scrolled = (fraction - self.preview.editor_fraction).abs() > 1e-3;
+        self.preview.editor_fraction = fraction;
+        self.preview.editor_max_scroll = max_scroll;
+
+        // Update visible lines, from the visual rows in view when wrapped
+        let first_row = (self.editor_scroll_offset.y / line_height).floor() as usize;
+        let row_count = (ui.available_height() / line_height).ceil() as usize;
+        let row_line = |row: usize| match &file.state.wrap {
+            Some(wrap) => wrap.row_line(row) + 1,
+            None => row + 1,
         };
+        file.state.visible_lines = (row_line(first_row), row_line(first_row + row_count));
 
-        let scroll_output = scroll_area.show(ui, |ui| {
-            let rect = ui.min_rect();
-            let painter = ui.painter();
-
-            // Draw current line highlight
-            if current_line > 0 {
-                let highlight_y = (current_line - 1) as f32 * line_height;
-                let highlight_rect = Rect::from_min_size(
-                    Pos2::new(rect.left(), rect.top() + highlight_y),
-                    Vec2::new(ui.available_width() + 1000.0, line_height),
-                );
-                painter.rect_filled(highlight_rect, 0.0, colors::CURRENT_LINE_BG);
+        if scroll_output.inner.changed {
+            file.state.is_modified = file.buffer != file.original_content.as_str();
+            self.trigger_completion(false);
+            self.offer_tag_rename(active_idx, tag_pair);
+        }
+        self.update_completion();
+        self.update_hover(scroll_output.inner.hover_offset);
+        match scroll_output.inner.typed {
+            Some('/') => {
+                self.close_block_comment(active_idx);
+                self.scaffold_doc_comment(active_idx);
             }
+            Some('\n') => self.continue_comment(active_idx),
+            Some('>') => self.close_tag(active_idx),
+            _ => {}
+        }
+        if let Some(offset) = scroll_output.inner.definition_clicked {
+            let file = &self.open_files[active_idx];
+            let link = (file.extension() == "rs")
+                .then(|| rust_doc::std_doc_link(&file.buffer, offset))
+                .flatten();
+            match link {
+                Some(link) => {
+                    if let Err(e) = server::open_in_browser(&link.url) {
+                        self.log_output(format!("Failed to open {}: {e}", link.url));
+                    }
+                }
+                None => self.go_to_definition(offset),
+            }
+        }
+        if let Some(index) = scroll_output.inner.code_lens_clicked {
+            self.send_http_request(ui.ctx(), index);
+        }
+        if let Some(range) = scroll_output.inner.pasted {
+            self.offer_paste_format(active_idx, range);
+        }
+        if let Some(offset) = scroll_output.inner.middle_clicked {
+            self.paste_primary_selection(active_idx, offset);
+        }
+        self.publish_primary_selection(ui.ctx(), active_idx);
+        // A drag is one cursor move, recorded once the mouse lets go
+        if !ui.input(|i| i.pointer.primary_down()) {
+            let state = &mut self.open_files[active_idx].state;
+            state.cursor_history.observe(&state.cursors, state.revision);
+        }
+    }
 
-            // Draw indent guides
-            let indent_width = char_width * layout::TAB_SIZE as f32;
-            let total_lines = indent_levels.len();
-
-            // Find max indent level to draw
-            let max_indent = indent_levels.iter().copied().max().unwrap_or(0);
-
-            for indent in 1..=max_indent {
-                let x = rect.left() + (indent as f32 * indent_width)
-                    - (indent_width - char_width * 0.5);
+    // === Primary Selection ===
 
-                // Draw vertical line segments where this indent level is active
-                let mut segment_start: Option<usize> = None;
+    /// Make the selection the primary selection once the mouse lets go of it,
+    /// so other X11/Wayland applications can middle-click paste it
+    fn publish_primary_selection(&mut self, ctx: &egui::Context, tab: usize) {
+        if !primary_selection::SUPPORTED || ctx.input(|i| i.pointer.primary_down()) {
+            return;
+        }
+        let file = &self.open_files[tab];
+        let selection = file.state.cursor().selection();
+        if selection.is_empty() {
+            return;
+        }
+        let current = Some((file.id, selection));
+        if self.primary_selection == current {
+            return;
+        }
+        self.primary_selection = current;
+        primary_selection::set(multi_cursor::selected_text(
+            &file.buffer,
+            &file.state.cursors,
+        ));
+    }
 
-                for (line_idx, &line_indent) in indent_levels.iter().enumerate() {
-                    let is_in_block = line_indent >= indent;
+    /// Insert the primary selection where the editor was middle-clicked
+    fn paste_primary_selection(&mut self, tab: usize, offset: usize) {
+        let Some(text) = primary_selection::get().filter(|text| !text.is_empty()) else {
+            return;
+        };
+        let file = &mut self.open_files[tab];
+        file.state.clear_secondary_cursors();
+        file.state
+            .set_cursor(CursorPosition::from_char_offset(&file.buffer, offset));
+        file.replace_ranges(std::slice::from_ref(&(offset..offset)), &text);
+        let end = offset + text.chars().count();
+        file.state
+            .set_cursor(CursorPosition::from_char_offset(&file.buffer, end));
+        if !self.find_replace.search_text.is_empty() {
+            self.perform_search();
+        }
+    }
 
-                    match (segment_start, is_in_block) {
-                        (None, true) => {
-                            segment_start = Some(line_idx);
-                        }
-                        (Some(start), false) => {
-                            // Draw the segment
-                            let y_start = rect.top() + (start as f32 * line_height);
-                            let y_end = rect.top() + (line_idx as f32 * line_height);
+    // === Rust Documentation ===
 
-                            let guide_color = if indent == active_indent {
-                                colors::INDENT_GUIDE_ACTIVE
-                            } else {
-                                colors::INDENT_GUIDE
-                            };
+    /// Underline a standard library path under the pointer as a link
+    fn doc_link_underline(&self, idx: usize, colors: &Palette) -> Option<Underline> {
+        let file = &self.open_files[idx];
+        if file.extension() != "rs" || self.hover.tab != idx {
+            return None;
+        }
+        let word = self.hover.word.as_ref()?;
+        let link = rust_doc::std_doc_link(&file.buffer, word.start)?;
+        let click = match self.settings.multi_cursor_modifier {
+            MultiCursorModifier::Alt => "Ctrl+Click",
+            MultiCursorModifier::Ctrl => "Alt+Click",
+        };
+        Some(Underline {
+            range: link.range,
+            color: colors.accent,
+            tooltip: format!("{click} to open the documentation of {}", link.path),
+        })
+    }
 
-                            painter.line_segment(
-                                [Pos2::new(x, y_start), Pos2::new(x, y_end)],
-                                egui::Stroke::new(1.0, guide_color),
-                            );
-                            segment_start = None;
-                        }
-                        _ => {}
-                    }
-                }
+    /// Expand a `///` just typed above a function into a doc comment with
+    /// sections for its arguments and return value
+    fn scaffold_doc_comment(&mut self, idx: usize) {
+        let file = &mut self.open_files[idx];
+        if file.extension() != "rs" || file.state.has_multiple_cursors() {
+            return;
+        }
+        let offset = file.state.cursor().offset;
+        let line = file.buffer.char_to_line(offset);
+        let Some(text) = rust_doc::scaffold(&file.buffer, line) else {
+            return;
+        };
+        file.replace_ranges(std::slice::from_ref(&(offset..offset)), &text);
+        // The caret goes to the summary line
+        file.state
+            .set_cursor(CursorPosition::from_char_offset(&file.buffer, offset + 1));
+        file.state.history.break_group();
+    }
 
-                // Draw remaining segment if exists
-                if let Some(start) = segment_start {
-                    let y_start = rect.top() + (start as f32 * line_height);
-                    let y_end = rect.top() + (total_lines as f32 * line_height);
+    /// Start the line Enter just began with the comment prefix of the line
+    /// above, as configured for the file's language
+    fn continue_comment(&mut self, idx: usize) {
+        let file = &mut self.open_files[idx];
+        let Some(prefixes) = self.settings.comment_continuation.get(file.extension()) else {
+            return;
+        };
+        if file.state.has_multiple_cursors() {
+            return;
+        }
+        let offset = file.state.cursor().offset;
+        let line = file.buffer.char_to_line(offset);
+        let Some(text) = comment::continuation(&file.buffer, line, prefixes) else {
+            return;
+        };
+        // The text moved down from after the caret loses its leading whitespace
+        let start = file.buffer.line_to_char(line);
+        let indent = file
+            .buffer
+            .line(line)
+            .chars()
+            .take_while(|c| *c == ' ' || *c == '\t')
+            .count();
+        file.replace_ranges(std::slice::from_ref(&(start..start + indent)), &text);
+        let caret = start + text.chars().count();
+        file.state
+            .set_cursor(CursorPosition::from_char_offset(&file.buffer, caret));
+        file.state.history.break_group();
+    }
 
-                    let guide_color = if indent == active_indent {
-                        colors::INDENT_GUIDE_ACTIVE
-                    } else {
-                        colors::INDENT_GUIDE
-                    };
+    /// Turn ` * /` into ` */` when `/` is typed after a block comment's ` * `
+    fn close_block_comment(&mut self, idx: usize) {
+        let file = &mut self.open_files[idx];
+        let continued = self
+            .settings
+            .comment_continuation
+            .get(file.extension())
+            .is_some_and(|prefixes| prefixes.iter().any(|p| p == "/*"));
+        if !continued || file.state.has_multiple_cursors() {
+            return;
+        }
+        let offset = file.state.cursor().offset;
+        if !comment::closes_block(&file.buffer, offset) {
+            return;
+        }
+        file.replace_ranges(std::slice::from_ref(&(offset - 2..offset - 1)), "");
+        file.state
+            .set_cursor(CursorPosition::from_char_offset(&file.buffer, offset - 1));
+        file.state.history.break_group();
+    }
 
-                    painter.line_segment(
-                        [Pos2::new(x, y_start), Pos2::new(x, y_end)],
-                        egui::Stroke::new(1.0, guide_color),
-                    );
-                }
-            }
+    // === Paired Tags ===
 
-            // Draw find/search match highlights
-            let find_matches = self.find_replace.matches.clone();
-            let current_match_idx = self.find_replace.current_match;
+    /// Insert the closing tag after a `>` just typed at the end of an opening
+    /// tag in HTML or XML
+    fn close_tag(&mut self, idx: usize) {
+        let file = &mut self.open_files[idx];
+        let Some(markup) = Markup::for_extension(file.extension()) else {
+            return;
+        };
+        if file.state.has_multiple_cursors() {
+            return;
+        }
+        let offset = file.state.cursor().offset;
+        let Some(closing) = tags::closing_tag(&file.buffer, offset, markup) else {
+            return;
+        };
+        file.replace_ranges(std::slice::from_ref(&(offset..offset)), &closing);
+        // The caret stays between the tags
+        file.state
+            .set_cursor(CursorPosition::from_char_offset(&file.buffer, offset));
+        file.state.history.break_group();
+    }
 
-            for (idx, (start, end)) in find_matches.iter().enumerate() {
-                let (start_line, start_col) = offset_to_line_col(&text, *start);
-                let (end_line, end_col) = offset_to_line_col(&text, *end);
+    /// The tag name at the caret and the name of its partner, or those of the
+    /// pending rename offer while its name is still being edited
+    fn tag_pair_at_caret(&self, tab: usize) -> Option<TagRenameOffer> {
+        let file = &self.open_files[tab];
+        let markup = Markup::for_extension(file.extension())?;
+        let pending = self
+            .tag_rename
+            .as_ref()
+            .filter(|offer| offer.tab == tab && offer.revision == file.state.revision);
+        if let Some(offer) = pending {
+            return Some(offer.clone());
+        }
+        if file.state.has_multiple_cursors() || file.state.cursor().has_selection() {
+            return None;
+        }
+        let pair = tags::matching_tag(&file.buffer, file.state.cursor().offset, markup)?;
+        Some(TagRenameOffer {
+            tab,
+            revision: file.state.revision,
+            length: file.buffer.len_chars(),
+            old_name: file.buffer.slice(pair.partner.clone()).to_string(),
+            name: pair.name,
+            partner: pair.partner,
+        })
+    }
 
-                // For simplicity, only highlight single-line matches fully
-                // Multi-line matches show just first line portion
-                if start_line == end_line {
-                    let x = rect.left() + (start_col as f32 * char_width);
-                    let y = rect.top() + (start_line as f32 * line_height);
-                    let width = (end_col - start_col) as f32 * char_width;
+    /// After an edit inside the name of a paired tag, offer to rename the
+    /// other tag of the pair to match
+    fn offer_tag_rename(&mut self, tab: usize, before: Option<TagRenameOffer>) {
+        self.tag_rename = None;
+        let Some(before) = before else {
+            return;
+        };
+        let file = &self.open_files[tab];
+        let length = file.buffer.len_chars();
+        let delta = length as isize - before.length as isize;
+        let Some(end) = before.name.end.checked_add_signed(delta) else {
+            return;
+        };
+        let caret = file.state.cursor().offset;
+        if end < before.name.start || caret < before.name.start || caret > end {
+            return;
+        }
+        let name = before.name.start..end;
+        let new_name = file.buffer.slice(name.clone()).to_string();
+        if new_name.is_empty() || !new_name.chars().all(tags::is_name_char) {
+            return;
+        }
+        let partner = if before.partner.start > before.name.start {
+            let start = before.partner.start.saturating_add_signed(delta);
+            start..start + before.partner.len()
+        } else {
+            before.partner
+        };
+        if partner.end > length || file.buffer.slice(partner.clone()) != before.old_name.as_str() {
+            return;
+        }
+        if new_name != before.old_name {
+            self.tag_rename = Some(TagRenameOffer {
+                tab,
+                revision: file.state.revision,
+                length,
+                name,
+                partner,
+                old_name: before.old_name,
+            });
+        }
+    }
 
-                    let match_rect =
-                        Rect::from_min_size(Pos2::new(x, y), Vec2::new(width, line_height));
+    fn render_tag_rename_offer(&mut self, ctx: &egui::Context) {
+        let Some(offer) = &self.tag_rename else {
+            return;
+        };
+        let file = self
+            .open_files
+            .get(offer.tab)
+            .filter(|f| offer.tab == self.active_tab && f.state.revision == offer.revision);
+        let Some(file) = file else {
+            self.tag_rename = None;
+            return;
+        };
+        let name = file.buffer.slice(offer.name.clone()).to_string();
+        let slash = if offer.partner.start > offer.name.start {
+            "/"
+        } else {
+            ""
+        };
 
-                    let (bg_color, border_color) = if idx == current_match_idx {
-                        (colors::FIND_MATCH_CURRENT_BG, colors::FIND_MATCH_BORDER)
-                    } else {
-                        (colors::FIND_MATCH_BG, colors::FIND_MATCH_BORDER)
-                    };
+        let colors = palette(&ctx.style().visuals);
+        let mut rename = false;
+        let mut dismiss = false;
+        egui::Area::new(egui::Id::new("tag_rename_offer"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, Vec2::new(-20.0, -40.0))
+            .show(ctx, |ui| {
+                Frame::none()
+                    .fill(colors.find_panel_bg)
+                    .inner_margin(Margin::same(8.0))
+                    .rounding(4.0)
+                    .shadow(egui::epaint::Shadow {
+                        extrusion: 8.0,
+                        color: Color32::from_black_alpha(100),
+                    })
+                    .show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label(format!(
+                                "Rename <{slash}{}> to <{slash}{name}>",
+                                offer.old_name
+                            ));
+                            rename = ui.button("Rename").clicked();
+                            dismiss = ui.button("✕").on_hover_text("Dismiss").clicked();
+                        });
+                    });
+            });
 
-                    painter.rect_filled(match_rect, 2.0, bg_color);
-                    if idx == current_match_idx {
-                        painter.rect_stroke(match_rect, 2.0, egui::Stroke::new(2.0, border_color));
-                    }
-                }
-            }
+        if rename {
+            self.apply_tag_rename();
+        } else if dismiss {
+            self.tag_rename = None;
+        }
+    }
 
-            // Draw bracket pair highlights
-            let cursor_offset = {
-                let file = &self.open_files[self.active_tab];
-                file.state.cursor.offset
-            };
+    /// Give the other tag of the pair the edited tag's name
+    fn apply_tag_rename(&mut self) {
+        let Some(offer) = self.tag_rename.take() else {
+            return;
+        };
+        let Some(file) = self.open_files.get_mut(offer.tab) else {
+            return;
+        };
+        let name = file.buffer.slice(offer.name.clone()).to_string();
+        let mut caret = file.state.cursor().offset;
+        if offer.partner.start < caret {
+            caret = caret + name.chars().count() - offer.partner.len();
+        }
+        file.replace_ranges(std::slice::from_ref(&offer.partner), &name);
+        file.state
+            .set_cursor(CursorPosition::from_char_offset(&file.buffer, caret));
+        file.state.history.break_group();
+        if !self.find_replace.search_text.is_empty() {
+            self.perform_search();
+        }
+    }
 
-            if let Some((open_pos, close_pos)) = find_matching_bracket(&text, cursor_offset) {
-                // Convert offsets to line/column positions
-                let (open_line, open_col) = offset_to_line_col(&text, open_pos);
-                let (close_line, close_col) = offset_to_line_col(&text, close_pos);
-
-                // Draw highlight for opening bracket
-                let open_x = rect.left() + (open_col as f32 * char_width);
-                let open_y = rect.top() + (open_line as f32 * line_height);
-                let bracket_rect = Rect::from_min_size(
-                    Pos2::new(open_x, open_y),
-                    Vec2::new(char_width, line_height),
-                );
-                painter.rect_filled(bracket_rect, 2.0, colors::BRACKET_MATCH_BG);
-                painter.rect_stroke(
-                    bracket_rect,
-                    2.0,
-                    egui::Stroke::new(1.0, colors::BRACKET_MATCH_BORDER),
-                );
+    // === Paste Formatting ===
 
-                // Draw highlight for closing bracket
-                let close_x = rect.left() + (close_col as f32 * char_width);
-                let close_y = rect.top() + (close_line as f32 * line_height);
-                let bracket_rect = Rect::from_min_size(
-                    Pos2::new(close_x, close_y),
-                    Vec2::new(char_width, line_height),
-                );
-                painter.rect_filled(bracket_rect, 2.0, colors::BRACKET_MATCH_BG);
-                painter.rect_stroke(
-                    bracket_rect,
-                    2.0,
-                    egui::Stroke::new(1.0, colors::BRACKET_MATCH_BORDER),
-                );
-            }
+    /// Offer to pretty-print pasted text when it is a single-line fragment in
+    /// the language of the file
+    fn offer_paste_format(&mut self, tab: usize, range: Range<usize>) {
+        let file = &self.open_files[tab];
+        let Some(kind) = FragmentKind::for_extension(file.extension()) else {
+            return;
+        };
 
-            let text_edit_id = ui.id().with("editor");
-            let response = ui.add(
-                TextEdit::multiline(&mut text)
-                    .id(text_edit_id)
-                    .font(TextStyle::Monospace)
-                    .code_editor()
-                    .frame(false) // Remove TextEdit's internal frame/margin
-                    .margin(Vec2::ZERO) // No margin
-                    .desired_width(f32::INFINITY)
-                    .layouter(&mut layouter),
-            );
+        // Leave whitespace around the fragment alone
+        let text = file.buffer.slice(range.clone()).to_string();
+        let leading = text.chars().take_while(|c| c.is_whitespace()).count();
+        let trailing = text.chars().rev().take_while(|c| c.is_whitespace()).count();
+        if leading == text.chars().count() {
+            return;
+        }
+        let range = range.start + leading..range.end - trailing;
 
-            // Try to get cursor position from TextEdit state
-            if let Some(state) = egui::TextEdit::load_state(ui.ctx(), text_edit_id) {
-                if let Some(cursor) = state.cursor.char_range() {
-                    let offset = cursor.primary.index;
-                    let file = &mut self.open_files[self.active_tab];
-                    file.state.cursor = CursorPosition::from_char_offset(&file.buffer, offset);
-                }
-            }
+        let indent = pretty_print::indent_unit(&file.buffer);
+        let Some(formatted) = kind.format(&text, &indent) else {
+            return;
+        };
 
-            response
+        // Continuation lines start at the indentation of the line pasted into
+        let line_idx = file.buffer.char_to_line(range.start);
+        let base: String = file
+            .buffer
+            .line(line_idx)
+            .chars()
+            .take_while(|c| *c == ' ' || *c == '\t')
+            .collect();
+        self.paste_format = Some(PasteFormatOffer {
+            tab,
+            revision: file.state.revision,
+            range,
+            kind,
+            formatted: formatted.replace('\n', &format!("\n{}", base)),
         });
+    }
 
-        // Store scroll offset for gutter sync
-        self.editor_scroll_offset = scroll_output.state.offset;
-
-        // Update file state
-        let file = &mut self.open_files[self.active_tab];
+    fn render_paste_format_offer(&mut self, ctx: &egui::Context) {
+        let Some(offer) = &self.paste_format else {
+            return;
+        };
+        let current = offer.tab == self.active_tab
+            && self
+                .open_files
+                .get(offer.tab)
+                .is_some_and(|f| f.state.revision == offer.revision);
+        if !current {
+            self.paste_format = None;
+            return;
+        }
 
-        // Update visible lines
-        let visible_start = (self.editor_scroll_offset.y / line_height).floor() as usize + 1;
-        let visible_count = (ui.available_height() / line_height).ceil() as usize;
-        file.state.visible_lines = (visible_start, visible_start + visible_count);
-
-        // Auto-closing brackets: detect if a single opening bracket was typed
-        let current_char_count = text.chars().count();
-        if current_char_count == prev_char_count + 1 {
-            // One character was added
-            let cursor_offset = file.state.cursor.offset;
-            if cursor_offset > 0 && cursor_offset <= text.len() {
-                let chars: Vec<char> = text.chars().collect();
-                let typed_char = chars.get(cursor_offset.saturating_sub(1)).copied();
-
-                if let Some(ch) = typed_char {
-                    let closing = match ch {
-                        '(' => Some(')'),
-                        '[' => Some(']'),
-                        '{' => Some('}'),
-                        '"' => Some('"'),
-                        '\'' => Some('\''),
-                        '`' => Some('`'),
-                        _ => None,
-                    };
+        let colors = palette(&ctx.style().visuals);
+        let mut format = false;
+        let mut dismiss = false;
+        egui::Area::new(egui::Id::new("paste_format_offer"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, Vec2::new(-20.0, -40.0))
+            .show(ctx, |ui| {
+                Frame::none()
+                    .fill(colors.find_panel_bg)
+                    .inner_margin(Margin::same(8.0))
+                    .rounding(4.0)
+                    .shadow(egui::epaint::Shadow {
+                        extrusion: 8.0,
+                        color: Color32::from_black_alpha(100),
+                    })
+                    .show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label(format!("Pasted {} on one line", offer.kind.name()));
+                            format = ui.button("Format").clicked();
+                            dismiss = ui.button("✕").on_hover_text("Dismiss").clicked();
+                        });
+                    });
+            });
 
-                    if let Some(close_char) = closing {
-                        // Insert closing bracket at cursor position
-                        let byte_offset = chars[..cursor_offset].iter().collect::<String>().len();
-                        text.insert(byte_offset, close_char);
-                    }
-                }
-            }
+        if format {
+            self.apply_paste_format();
+        } else if dismiss {
+            self.paste_format = None;
         }
+    }
 
-        // Update buffer if changed
-        if text != file.buffer {
-            file.buffer = Rope::from_str(&text);
-            file.state.is_modified = text != original;
+    /// Replace the pasted fragment with its pretty-printed form
+    fn apply_paste_format(&mut self) {
+        let Some(offer) = self.paste_format.take() else {
+            return;
+        };
+        let Some(file) = self.open_files.get_mut(offer.tab) else {
+            return;
+        };
+        let end = offer.range.start + offer.formatted.chars().count();
+        file.replace_ranges(&[offer.range], &offer.formatted);
+        file.state.clear_secondary_cursors();
+        file.state
+            .set_cursor(CursorPosition::from_char_offset(&file.buffer, end));
+        file.state.scroll_to_cursor = true;
+        file.state.history.break_group();
+        if !self.find_replace.search_text.is_empty() {
+            self.perform_search();
         }
     }
 
     fn render_welcome_screen(&self, ui: &mut egui::Ui) {
+        let colors = palette(ui.visuals());
         ui.centered_and_justified(|ui| {
             ui.vertical_centered(|ui| {
                 ui.add_space(ui.available_height() / 3.0);
                 ui.label(
                     RichText::new("Rust Code Editor")
                         .size(24.0)
-                        .color(colors::TEXT_SECONDARY)
+                        .color(colors.text_secondary)
                         .strong(),
                 );
                 ui.add_space(16.0);
@@ -1081,16 +9524,17 @@ impl EditorApp {
 
     // === Status Bar ===
 
-    fn render_status_bar(&self, ctx: &egui::Context) {
+    fn render_status_bar(&mut self, ctx: &egui::Context) {
         egui::TopBottomPanel::bottom("status_bar")
             .exact_height(layout::STATUS_BAR_HEIGHT)
             .frame(Frame::none())
             .show(ctx, |ui| {
                 let info = if let Some(file) = self.open_files.get(self.active_tab) {
                     StatusBarInfo {
-                        cursor: file.state.cursor.clone(),
+                        cursor: file.state.cursor().clone(),
+                        byte_offset: file.buffer.char_to_byte(file.state.cursor().offset),
                         language: detect_language(file.extension()).to_string(),
-                        encoding: "UTF-8".to_string(),
+                        encoding: file.encoding.label().to_string(),
                         line_ending: if cfg!(windows) { "CRLF" } else { "LF" }.to_string(),
                         total_lines: file.buffer.len_lines(),
                         total_chars: file.buffer.len_chars(),
@@ -1099,53 +9543,387 @@ impl EditorApp {
                     StatusBarInfo::default()
                 };
 
-                let file_name = self.open_files.get(self.active_tab).map(|f| f.name());
+                let file_name = self
+                    .open_files
+                    .get(self.active_tab)
+                    .map(|f| display_path(&f.path, self.workspace_roots()));
 
-                StatusBar::new(info).file_name(file_name).show(ui);
+                let server_url = self.server.as_ref().map(|s| s.url());
+
+                let response = StatusBar::new(info)
+                    .file_name(file_name)
+                    .server_url(server_url)
+                    .recording_macro(self.macros.recording)
+                    .pending_chord(self.pending_chord.map(|first| ctx.format_shortcut(&first)))
+                    .show(ui);
+                if response.cursor_clicked && !self.open_files.is_empty() {
+                    self.goto_line.open();
+                }
+                if response.server_clicked {
+                    self.stop_server();
+                }
+                if response.macro_clicked {
+                    self.toggle_macro_recording();
+                }
+                if response.encoding_clicked && !self.open_files.is_empty() {
+                    self.open_encoding_picker(None);
+                }
             });
     }
 
     // === File Operations ===
 
     fn open_folder(&mut self) {
-        if let Some(path) = FileDialog::new().pick_folder() {
-            self.workspace = Some(path.clone());
-            self.tree = vec![FileNode::new(path)];
+        if let Some(path) = self.blocking_dialog(|| FileDialog::new().pick_folder()) {
+            self.set_workspace(path);
         }
     }
 
+    fn set_workspace(&mut self, path: PathBuf) {
+        self.set_activity(format!("reading folder {}", path.display()));
+        self.workspace_settings = WorkspaceSettings::load(&path);
+        self.workspace_env = Environments::of(&path);
+        // Read the new folder's manifest when the Cargo panel is next shown
+        self.cargo.pending = None;
+        self.cargo.metadata = None;
+        self.cargo.features.clear();
+        self.cargo.graph_pending = None;
+        self.cargo.graph = None;
+        self.dependency_graph = None;
+        self.forge = ForgeState::default();
+        self.git_signs_commits = git::signs_commits(&path);
+        self.workspace = Some(path);
+        self.refresh_tree();
+    }
+
+    /// Entries of `root` left out of the Explorer, search and the symbol index
+    fn exclusions(&self, root: &Path) -> Exclusions {
+        let patterns = self
+            .workspace_settings
+            .exclude
+            .as_ref()
+            .unwrap_or(&self.settings.exclude);
+        Exclusions::new(root, patterns)
+    }
+
+    /// Read the workspace folder into the Explorer again
+    fn refresh_tree(&mut self) {
+        if let Some(workspace) = self.workspace.clone() {
+            let exclusions = self.exclusions(&workspace);
+            let show_hidden = self.settings.show_hidden_files;
+            self.tree = vec![FileNode::new(workspace, &exclusions, show_hidden)];
+        }
+    }
+
+    fn toggle_hidden_files(&mut self) {
+        self.toggle_setting(|s| &mut s.show_hidden_files);
+        self.refresh_tree();
+    }
+
     fn open_file_dialog(&mut self) {
-        if let Some(path) = FileDialog::new().pick_file() {
+        if let Some(path) = self.blocking_dialog(|| FileDialog::new().pick_file()) {
             self.open_file(path);
         }
     }
 
+    /// Go > Switch to Counterpart: open the test, header or source file that
+    /// goes with the active one, or create it from its template when none of
+    /// them exists
+    fn switch_to_counterpart(&mut self) {
+        let Some(file) = self.open_files.get(self.active_tab) else {
+            return;
+        };
+        let name = file.name();
+        let candidates = counterpart::counterparts(&file.path, &self.settings.counterparts);
+        let existing = candidates
+            .iter()
+            .find(|c| c.path.is_file() || self.open_files.iter().any(|f| f.path == c.path));
+        if let Some(existing) = existing {
+            self.open_file(existing.path.clone());
+            return;
+        }
+        let Some(new) = candidates.into_iter().next() else {
+            self.announce(
+                format!("No counterpart pattern matches {name}"),
+                Some(Cue::Problem),
+            );
+            return;
+        };
+
+        let created = match new.path.parent() {
+            Some(parent) => std::fs::create_dir_all(parent),
+            None => Ok(()),
+        }
+        .and_then(|()| std::fs::write(&new.path, &new.template));
+        match created {
+            Ok(()) => {
+                self.log_output(format!("Created {}", new.path.display()));
+                self.open_file(new.path);
+            }
+            Err(e) => {
+                self.log_output(format!("Could not create {}: {e}", new.path.display()));
+                self.announce(
+                    format!("Could not create the counterpart of {name}"),
+                    Some(Cue::Problem),
+                );
+            }
+        }
+    }
+
     fn open_file(&mut self, path: PathBuf) {
         // Don't open the same file twice
         if let Some(index) = self.open_files.iter().position(|f| f.path == path) {
             self.active_tab = index;
+            self.log_viewer = None;
+            self.folder_compare = None;
+            self.regex_playground = None;
+            self.dependency_graph = None;
+            return;
+        }
+
+        // Too big for a rope; view it read-only through a memory map instead
+        let size = std::fs::metadata(&path).map_or(0, |m| m.len());
+        if size >= LOG_VIEWER_THRESHOLD {
+            self.open_log_file(path);
             return;
         }
 
-        let content = std::fs::read_to_string(&path).unwrap_or_default();
-        self.open_files.push(OpenFile::new(path, content));
+        self.set_activity(format!("opening {}", path.display()));
+        self.log_viewer = None;
+        self.folder_compare = None;
+        self.regex_playground = None;
+        self.dependency_graph = None;
+        let bytes = std::fs::read(&path).unwrap_or_default();
+        let (encoding, content) = TextEncoding::read(&bytes);
+        let mut file = OpenFile::new(path, content);
+        file.disk_len = size;
+        file.encoding = encoding;
+        self.open_files.push(file);
         self.active_tab = self.open_files.len() - 1;
     }
 
+    /// Tab for a new file, which is saved wherever the user picks
+    fn new_file(&mut self) {
+        let taken: HashSet<String> = self.open_files.iter().map(|f| f.name()).collect();
+        let name = (1..)
+            .map(|n| format!("Untitled-{n}"))
+            .find(|name| !taken.contains(name))
+            .unwrap_or_default();
+        let mut file = OpenFile::new(PathBuf::from(name), String::new());
+        file.untitled = true;
+        self.log_viewer = None;
+        self.folder_compare = None;
+        self.regex_playground = None;
+        self.dependency_graph = None;
+        self.open_files.push(file);
+        self.active_tab = self.open_files.len() - 1;
+    }
+
+    /// Ask where to save the active file and point its tab there. False if
+    /// the dialog was cancelled.
+    fn choose_save_path(&mut self) -> bool {
+        let Some(file) = self.open_files.get(self.active_tab) else {
+            return false;
+        };
+        let mut dialog = FileDialog::new().set_file_name(&file.name());
+        let dir = file.path.parent().filter(|dir| dir.is_dir());
+        if let Some(dir) = dir.or(self.workspace.as_deref()) {
+            dialog = dialog.set_directory(dir);
+        }
+        let Some(path) = self.blocking_dialog(|| dialog.save_file()) else {
+            return false;
+        };
+        self.rename_open_file(self.active_tab, path);
+        self.open_files[self.active_tab].untitled = false;
+        true
+    }
+
     fn save_current_file(&mut self) {
+        // A new file, or one kept after it was deleted, has to be given a place first
+        let untitled = self
+            .open_files
+            .get(self.active_tab)
+            .is_some_and(|f| f.untitled);
+        if untitled && !self.choose_save_path() {
+            return;
+        }
+        if let Some(path) = self.open_files.get(self.active_tab).map(|f| &f.path) {
+            self.set_activity(format!("saving {}", path.display()));
+        }
+        self.format_before_save();
+        let mut reload_contributions = false;
+        let mut reload_keybindings = false;
+        let mut reload_workspace_settings = false;
         if let Some(file) = self.open_files.get_mut(self.active_tab) {
             let content = file.buffer.to_string();
-            if std::fs::write(&file.path, &content).is_ok() {
-                file.original_content = content;
-                file.state.is_modified = false;
+            let written = file.encoding.encode(&content).and_then(|bytes| {
+                std::fs::write(&file.path, &bytes)
+                    .map(|()| bytes.len())
+                    .map_err(|e| e.to_string())
+            });
+            let announcement = match written {
+                Ok(len) => {
+                    file.missing = false;
+                    file.disk_len = len as u64;
+                    file.original_content = content;
+                    file.state.is_modified = false;
+                    if let Some(server) = &self.server {
+                        server.reload();
+                    }
+                    if let Some(client) = self.language_servers.get_mut(file.extension()) {
+                        client.did_save(&file.path);
+                    }
+                    reload_contributions = contributions::is_contribution_file(&file.path);
+                    reload_keybindings = Keybindings::is_keybindings_file(&file.path);
+                    reload_workspace_settings = self.workspace.as_ref().is_some_and(|workspace| {
+                        file.path == workspace.join(WorkspaceSettings::FILE_NAME)
+                    });
+                    (format!("Saved {}", file.name()), Cue::Success)
+                }
+                Err(e) => (format!("Could not save {}: {e}", file.name()), Cue::Problem),
+            };
+            self.announce(announcement.0, Some(announcement.1));
+        }
+        if reload_contributions {
+            let errors = contributions::load();
+            if errors.is_empty() {
+                self.log_output("Reloaded file icon and language contributions");
+            }
+            for error in errors {
+                self.log_output(error);
             }
         }
+        if reload_keybindings {
+            let (keybindings, errors) = Keybindings::load();
+            self.keybindings = keybindings;
+            if errors.is_empty() {
+                self.log_output("Reloaded keybindings");
+            }
+            for error in errors {
+                self.log_output(error);
+            }
+        }
+        if reload_workspace_settings {
+            if let Some(workspace) = self.workspace.clone() {
+                self.workspace_settings = WorkspaceSettings::load(&workspace);
+                self.refresh_tree();
+                self.log_output("Reloaded workspace settings");
+            }
+        }
+    }
+
+    /// Run the formatter configured for the active file when format on save is
+    /// on. Failures are reported and the file is saved as it is.
+    fn format_before_save(&mut self) {
+        let enabled = self
+            .workspace_settings
+            .format_on_save
+            .unwrap_or(self.settings.format_on_save);
+        let Some(file) = self.open_files.get(self.active_tab).filter(|_| enabled) else {
+            return;
+        };
+        let Some(command) = self.settings.formatters.get(file.extension()) else {
+            return;
+        };
+
+        self.set_activity(format!("formatting {}", file.path.display()));
+        let text = file.buffer.to_string();
+        let dir = file.path.parent().map(Path::to_path_buf);
+        let env = &self.workspace_env;
+        let formatted = match formatter::format(command, &text, dir.as_deref(), env) {
+            Ok(formatted) => formatted,
+            Err(e) => {
+                let message = format!("Format on save failed for {}: {e}", file.path.display());
+                self.log_output(message);
+                return;
+            }
+        };
+        if formatted == text {
+            return;
+        }
+
+        // Replace the whole buffer in one undo step, keeping the caret on its line
+        let file = &mut self.open_files[self.active_tab];
+        let line_idx = file.state.cursor().line.saturating_sub(1);
+        let column = file.state.cursor().column.saturating_sub(1);
+        let whole = 0..file.buffer.len_chars();
+        file.replace_ranges(std::slice::from_ref(&whole), &formatted);
+        let line_idx = line_idx.min(file.buffer.len_lines() - 1);
+        let offset = file.buffer.line_to_char(line_idx)
+            + column.min(multi_cursor::line_len(&file.buffer, line_idx));
+        file.state.clear_secondary_cursors();
+        file.state
+            .set_cursor(CursorPosition::from_char_offset(&file.buffer, offset));
+        file.state.history.break_group();
+        if !self.find_replace.search_text.is_empty() {
+            self.perform_search();
+        }
     }
 
     fn close_tab(&mut self, index: usize) {
-        self.open_files.remove(index);
+        let file = self.open_files.remove(index);
+        if !file.untitled && !file.missing {
+            self.closed_tabs.push(ClosedTab {
+                path: file.path.clone(),
+                offset: file.state.cursor().offset,
+                scroll: (index == self.active_tab).then_some(self.editor_scroll_offset),
+            });
+            if self.closed_tabs.len() > MAX_CLOSED_TABS {
+                self.closed_tabs.remove(0);
+            }
+        }
+        self.highlighter.forget(file.id);
+        if let Some(client) = self.language_servers.get_mut(file.extension()) {
+            client.did_close(&file.path);
+        }
         if self.active_tab >= self.open_files.len() && self.active_tab > 0 {
             self.active_tab -= 1;
         }
+        self.split = self
+            .split
+            .filter(|split| split.tab != index && !self.open_files.is_empty())
+            .map(|split| EditorSplit {
+                tab: split.tab - usize::from(split.tab > index),
+                ..split
+            });
     }
 }
+
+/// Panels that `layout` shows in windows of their own
+fn floating_panels(layout: &Layout) -> HashMap<FloatingPanel, Option<Pos2>> {
+    FloatingPanel::ALL
+        .into_iter()
+        .filter(|panel| layout.floating_panels.iter().any(|t| t == panel.title()))
+        .map(|panel| (panel, None))
+        .collect()
+}
+
+/// Where `path` is after the moves in `moved`, if one of them took it along
+fn moved_path(path: &Path, moved: &[(PathBuf, PathBuf)]) -> Option<PathBuf> {
+    moved.iter().find_map(|(from, to)| {
+        let rest = path.strip_prefix(from).ok()?;
+        Some(if rest.as_os_str().is_empty() {
+            to.clone()
+        } else {
+            to.join(rest)
+        })
+    })
+}
+
+/// Lines that differ between `old` and `new`, with two lines of context
+fn changed_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    const CONTEXT: usize = 2;
+    let lines = folder_diff::diff_lines(old, new);
+    let changed: Vec<usize> = (0..lines.len())
+        .filter(|&i| lines[i].change != LineChange::Equal)
+        .collect();
+    lines
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| {
+            let nearest = changed.partition_point(|&c| c + CONTEXT < *i);
+            changed.get(nearest).is_some_and(|&c| c <= i + CONTEXT)
+        })
+        .map(|(_, line)| line)
+        .collect()
+}
@@ -0,0 +1,518 @@
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+
+/// Workspace members from `cargo metadata`
+#[derive(Debug, Clone)]
+pub struct Metadata {
+    pub workspace_root: PathBuf,
+    pub packages: Vec<Package>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Package {
+    pub name: String,
+    pub version: String,
+    pub manifest_path: PathBuf,
+    pub targets: Vec<Target>,
+    /// Features declared in the manifest, sorted, including `default`
+    pub features: Vec<String>,
+    pub dependencies: Vec<Dependency>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+    pub name: String,
+    pub kind: TargetKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TargetKind {
+    Lib,
+    Bin,
+    Example,
+    Test,
+    Bench,
+    BuildScript,
+}
+
+impl TargetKind {
+    fn from_kinds(kinds: &[Value]) -> Self {
+        match kinds.first().and_then(Value::as_str) {
+            Some("bin") => TargetKind::Bin,
+            Some("example") => TargetKind::Example,
+            Some("test") => TargetKind::Test,
+            Some("bench") => TargetKind::Bench,
+            Some("custom-build") => TargetKind::BuildScript,
+            // lib, rlib, dylib, cdylib, staticlib, proc-macro
+            _ => TargetKind::Lib,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TargetKind::Lib => "lib",
+            TargetKind::Bin => "bin",
+            TargetKind::Example => "example",
+            TargetKind::Test => "test",
+            TargetKind::Bench => "bench",
+            TargetKind::BuildScript => "build script",
+        }
+    }
+
+    /// Whether `cargo run` can start the target
+    pub fn is_runnable(&self) -> bool {
+        matches!(self, TargetKind::Bin | TargetKind::Example)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Dependency {
+    pub name: String,
+    /// Version requirement as written in the manifest
+    pub req: String,
+    /// `dev` or `build`; None for normal dependencies
+    pub kind: Option<String>,
+    pub optional: bool,
+}
+
+/// Cargo subcommand run from the Cargo panel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Build,
+    Run,
+    Test,
+    Clean,
+}
+
+impl Action {
+    fn subcommand(&self) -> &'static str {
+        match self {
+            Action::Build => "build",
+            Action::Run => "run",
+            Action::Test => "test",
+            Action::Clean => "clean",
+        }
+    }
+}
+
+/// Cargo command for a package, or one of its targets
+#[derive(Debug, Clone)]
+pub struct Invocation {
+    pub action: Action,
+    pub package: String,
+    pub target: Option<Target>,
+    /// Features ticked in the panel; leaving out `default` disables the defaults
+    pub features: Vec<String>,
+    pub default_features: bool,
+}
+
+impl Invocation {
+    /// Arguments after `cargo`
+    pub fn args(&self) -> Vec<String> {
+        let mut args = vec![
+            self.action.subcommand().to_string(),
+            "-p".to_string(),
+            self.package.clone(),
+        ];
+        // Clean doesn't take targets or features
+        if self.action == Action::Clean {
+            return args;
+        }
+        if let Some(target) = &self.target {
+            match target.kind {
+                TargetKind::Lib => args.push("--lib".to_string()),
+                TargetKind::Bin => args.extend(["--bin".to_string(), target.name.clone()]),
+                TargetKind::Example => args.extend(["--example".to_string(), target.name.clone()]),
+                TargetKind::Test => args.extend(["--test".to_string(), target.name.clone()]),
+                TargetKind::Bench => args.extend(["--bench".to_string(), target.name.clone()]),
+                TargetKind::BuildScript => {}
+            }
+        }
+        if !self.default_features {
+            args.push("--no-default-features".to_string());
+        }
+        if !self.features.is_empty() {
+            args.extend(["--features".to_string(), self.features.join(",")]);
+        }
+        args
+    }
+}
+
+/// Run `cargo metadata` for the workspace on a worker thread; the result
+/// arrives on the returned channel and `ctx` is repainted then
+pub fn load_in_background(
+    root: PathBuf,
+    env: BTreeMap<String, String>,
+    ctx: egui::Context,
+) -> Receiver<Result<Metadata, String>> {
+    let (tx, rx) = mpsc::channel();
+    let spawned = std::thread::Builder::new()
+        .name("cargo-metadata".to_string())
+        .spawn(move || {
+            let _ = tx.send(load(&root, &env));
+            ctx.request_repaint();
+        });
+    if spawned.is_err() {
+        // The channel closes without a result, which reads as a failed load
+        return mpsc::channel().1;
+    }
+    rx
+}
+
+/// Workspace members of the package or workspace at `root`
+pub fn load(root: &Path, env: &BTreeMap<String, String>) -> Result<Metadata, String> {
+    let output = Command::new("cargo")
+        .args(["metadata", "--format-version", "1", "--no-deps"])
+        .current_dir(root)
+        .envs(env)
+        .output()
+        .map_err(|e| format!("Failed to run cargo: {e}"))?;
+    if !output.status.success() {
+        let errors = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("cargo metadata failed: {}", errors.trim()));
+    }
+    let json: Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("Unreadable cargo metadata: {e}"))?;
+    parse_metadata(&json).ok_or_else(|| "Unexpected cargo metadata format".to_string())
+}
+
+fn parse_metadata(json: &Value) -> Option<Metadata> {
+    let workspace_root = PathBuf::from(json["workspace_root"].as_str()?);
+    let mut packages: Vec<Package> = json["packages"]
+        .as_array()?
+        .iter()
+        .filter_map(parse_package)
+        .collect();
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+    Some(Metadata {
+        workspace_root,
+        packages,
+    })
+}
+
+fn parse_package(json: &Value) -> Option<Package> {
+    let string = |value: &Value| value.as_str().unwrap_or_default().to_string();
+    let mut targets: Vec<Target> = json["targets"]
+        .as_array()?
+        .iter()
+        .map(|target| Target {
+            name: string(&target["name"]),
+            kind: TargetKind::from_kinds(target["kind"].as_array().map_or(&[], Vec::as_slice)),
+        })
+        .collect();
+    targets.sort_by_key(|t| (t.kind as u8, t.name.clone()));
+    let mut features: Vec<String> = json["features"]
+        .as_object()
+        .map(|features| features.keys().cloned().collect())
+        .unwrap_or_default();
+    features.sort();
+    let dependencies = json["dependencies"]
+        .as_array()
+        .map(|dependencies| {
+            dependencies
+                .iter()
+                .map(|dependency| Dependency {
+                    name: string(&dependency["name"]),
+                    req: string(&dependency["req"]),
+                    kind: dependency["kind"].as_str().map(str::to_string),
+                    optional: dependency["optional"].as_bool().unwrap_or(false),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    Some(Package {
+        name: json["name"].as_str()?.to_string(),
+        version: string(&json["version"]),
+        manifest_path: PathBuf::from(string(&json["manifest_path"])),
+        targets,
+        features,
+        dependencies,
+    })
+}
+
+/// Cargo command running in the background, with its output streamed line by line
+pub struct CargoRun {
+    /// Command line shown while it runs
+    pub label: String,
+    child: Child,
+    lines: Receiver<String>,
+}
+
+impl CargoRun {
+    pub fn spawn(
+        invocation: &Invocation,
+        root: &Path,
+        env: &BTreeMap<String, String>,
+        ctx: egui::Context,
+    ) -> Result<Self, String> {
+        let args = invocation.args();
+        let label = format!("cargo {}", args.join(" "));
+        let mut child = Command::new("cargo")
+            .args(&args)
+            // The Output panel renders the colors
+            .arg("--color=always")
+            .current_dir(root)
+            .envs(env)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to run {label}: {e}"))?;
+
+        let (tx, lines) = mpsc::channel();
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let stderr = child.stderr.take().expect("stderr is piped");
+        forward_lines(stdout, tx.clone(), ctx.clone());
+        forward_lines(stderr, tx, ctx);
+        Ok(Self {
+            label,
+            child,
+            lines,
+        })
+    }
+
+    /// Output received since the last call, and the exit status once the
+    /// command has finished and all of its output has been read
+    pub fn poll(&mut self) -> (Vec<String>, Option<Result<ExitStatus, String>>) {
+        let mut lines = Vec::new();
+        let closed = loop {
+            match self.lines.try_recv() {
+                Ok(line) => lines.push(line),
+                Err(TryRecvError::Empty) => break false,
+                Err(TryRecvError::Disconnected) => break true,
+            }
+        };
+        let status = match self.child.try_wait() {
+            Ok(Some(status)) if closed => Some(Ok(status)),
+            Ok(_) => None,
+            Err(e) => Some(Err(e.to_string())),
+        };
+        (lines, status)
+    }
+
+    pub fn stop(&mut self) {
+        let _ = self.child.kill();
+    }
+}
+
+impl Drop for CargoRun {
+    fn drop(&mut self) {
+        // Don't leave a program started with Run behind when the editor closes
+        if let Ok(None) = self.child.try_wait() {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
+
+/// Send each line of a pipe to `tx` from a reader thread
+fn forward_lines(pipe: impl Read + Send + 'static, tx: Sender<String>, ctx: egui::Context) {
+    let _ = std::thread::Builder::new()
+        .name("cargo-output".to_string())
+        .spawn(move || {
+            for line in BufReader::new(pipe).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if tx.send(line).is_err() {
+                    break;
+                }
+                ctx.request_repaint();
+            }
+        });
+}
+
+/// Resolved dependency graph of the workspace, with members first
+#[derive(Debug, Clone, Default)]
+pub struct DependencyGraph {
+    pub nodes: Vec<GraphNode>,
+    /// Dependent and dependency, as indices into `nodes`
+    pub edges: Vec<(usize, usize)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GraphNode {
+    pub name: String,
+    pub version: String,
+    pub manifest_path: PathBuf,
+    pub is_member: bool,
+    /// More than one version of the crate is in the graph
+    pub is_duplicate: bool,
+    /// Shortest distance from a workspace member
+    pub depth: usize,
+}
+
+impl DependencyGraph {
+    /// Workspace members that depend on `node` directly
+    pub fn direct_dependents(&self, node: usize) -> impl Iterator<Item = usize> + '_ {
+        self.edges
+            .iter()
+            .filter(move |&&(from, to)| to == node && self.nodes[from].is_member)
+            .map(|&(from, _)| from)
+    }
+}
+
+/// Resolve the full dependency graph on a worker thread; the result arrives
+/// on the returned channel and `ctx` is repainted then
+pub fn load_graph_in_background(
+    root: PathBuf,
+    env: BTreeMap<String, String>,
+    ctx: egui::Context,
+) -> Receiver<Result<DependencyGraph, String>> {
+    let (tx, rx) = mpsc::channel();
+    let spawned = std::thread::Builder::new()
+        .name("cargo-graph".to_string())
+        .spawn(move || {
+            let _ = tx.send(load_graph(&root, &env));
+            ctx.request_repaint();
+        });
+    if spawned.is_err() {
+        return mpsc::channel().1;
+    }
+    rx
+}
+
+/// Dependency graph of the package or workspace at `root`, for the host platform
+pub fn load_graph(root: &Path, env: &BTreeMap<String, String>) -> Result<DependencyGraph, String> {
+    let output = Command::new("cargo")
+        .args(["metadata", "--format-version", "1", "--filter-platform"])
+        .arg(host_triple(env)?)
+        .current_dir(root)
+        .envs(env)
+        .output()
+        .map_err(|e| format!("Failed to run cargo: {e}"))?;
+    if !output.status.success() {
+        let errors = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("cargo metadata failed: {}", errors.trim()));
+    }
+    let json: Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("Unreadable cargo metadata: {e}"))?;
+    parse_graph(&json).ok_or_else(|| "Unexpected cargo metadata format".to_string())
+}
+
+/// Target triple of the installed compiler, so platform-specific crates for
+/// other systems stay out of the graph
+fn host_triple(env: &BTreeMap<String, String>) -> Result<String, String> {
+    let output = Command::new("rustc")
+        .arg("-vV")
+        .envs(env)
+        .output()
+        .map_err(|e| format!("Failed to run rustc: {e}"))?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| line.strip_prefix("host: "))
+        .map(str::to_string)
+        .ok_or_else(|| "rustc didn't report the host platform".to_string())
+}
+
+fn parse_graph(json: &Value) -> Option<DependencyGraph> {
+    let members: Vec<&str> = json["workspace_members"]
+        .as_array()?
+        .iter()
+        .filter_map(Value::as_str)
+        .collect();
+    let resolve = json["resolve"]["nodes"].as_array()?;
+
+    // Only packages in the resolved graph; `packages` also lists the
+    // optional dependencies of disabled features
+    let packages: HashMap<&str, &Value> = json["packages"]
+        .as_array()?
+        .iter()
+        .filter_map(|package| Some((package["id"].as_str()?, package)))
+        .collect();
+    let mut ids: Vec<&str> = resolve.iter().filter_map(|n| n["id"].as_str()).collect();
+    ids.sort_by_key(|id| {
+        let package = packages.get(id).copied().unwrap_or(&Value::Null);
+        (
+            !members.contains(id),
+            package["name"].as_str().unwrap_or_default(),
+            package["version"].as_str().unwrap_or_default(),
+        )
+    });
+    let index: HashMap<&str, usize> = ids.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+
+    let mut nodes: Vec<GraphNode> = ids
+        .iter()
+        .map(|id| {
+            let package = packages.get(id).copied().unwrap_or(&Value::Null);
+            let string = |key: &str| package[key].as_str().unwrap_or_default().to_string();
+            GraphNode {
+                name: string("name"),
+                version: string("version"),
+                manifest_path: PathBuf::from(string("manifest_path")),
+                is_member: members.contains(id),
+                is_duplicate: false,
+                depth: 0,
+            }
+        })
+        .collect();
+
+    let mut edges = Vec::new();
+    for node in resolve {
+        let Some(&from) = node["id"].as_str().and_then(|id| index.get(id)) else {
+            continue;
+        };
+        for dependency in node["deps"].as_array().into_iter().flatten() {
+            if let Some(&to) = dependency["pkg"].as_str().and_then(|id| index.get(id)) {
+                edges.push((from, to));
+            }
+        }
+    }
+
+    let mut versions: HashMap<&str, usize> = HashMap::new();
+    for node in &nodes {
+        *versions.entry(node.name.as_str()).or_default() += 1;
+    }
+    let duplicates: Vec<bool> = nodes
+        .iter()
+        .map(|n| versions[n.name.as_str()] > 1)
+        .collect();
+    for (node, duplicate) in nodes.iter_mut().zip(duplicates) {
+        node.is_duplicate = duplicate;
+    }
+
+    // Breadth-first from the members gives each crate its column in the graph
+    let mut depths: Vec<Option<usize>> = nodes.iter().map(|n| n.is_member.then_some(0)).collect();
+    let mut queue: VecDeque<usize> = (0..nodes.len()).filter(|&i| nodes[i].is_member).collect();
+    while let Some(from) = queue.pop_front() {
+        let depth = depths[from].unwrap_or(0) + 1;
+        for &(_, to) in edges.iter().filter(|&&(f, _)| f == from) {
+            if depths[to].is_none() {
+                depths[to] = Some(depth);
+                queue.push_back(to);
+            }
+        }
+    }
+    for (node, depth) in nodes.iter_mut().zip(depths) {
+        node.depth = depth.unwrap_or(0);
+    }
+
+    Some(DependencyGraph { nodes, edges })
+}
+
+/// Line of a manifest that declares the dependency `name`, as `name = ...` in a
+/// dependencies table or as a `[dependencies.name]` table
+pub fn dependency_line(manifest: &str, name: &str) -> Option<usize> {
+    let mut in_dependencies = false;
+    manifest.lines().position(|line| {
+        let line = line.trim();
+        if let Some(header) = line.strip_prefix('[') {
+            let header = header.trim_end_matches(']').trim();
+            if header.strip_suffix(name).is_some_and(|t| t.ends_with('.'))
+                && header.contains("dependencies")
+            {
+                return true;
+            }
+            in_dependencies = header.ends_with("dependencies");
+            return false;
+        }
+        in_dependencies
+            && line
+                .strip_prefix(name)
+                .is_some_and(|rest| rest.trim_start().starts_with('='))
+    })
+}
@@ -0,0 +1,744 @@
+use crate::state::crdt::{Change, Op, Replica};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{
+    IpAddr, Ipv4Addr, Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket,
+};
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Port a shared session listens on, and guests connect to unless the
+/// address names another
+pub const DEFAULT_PORT: u16 = 7879;
+
+/// Peer id of the host, which is also its replica id
+const HOST: u32 = 1;
+
+/// Wrong codes an address may give before its connections are refused
+const MAX_CODE_ATTEMPTS: u32 = 3;
+
+/// Longest first line a guest may send, which has to be its `Join`. Nothing
+/// longer is read from a connection that hasn't given the code yet.
+const MAX_JOIN_LINE: u64 = 4 * 1024;
+
+/// How long a new connection has to send its `Join`
+const JOIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Longest message the host reads from a guest once it has joined. Edits are
+/// sent in batches of `MAX_OPS_PER_MESSAGE`, which stay well below it.
+const MAX_GUEST_MESSAGE: u64 = 1024 * 1024;
+
+/// Longest message a guest reads from the host. Each shared document comes
+/// in a message of its own, with its history of deleted characters.
+const MAX_HOST_MESSAGE: u64 = 64 * 1024 * 1024;
+
+/// Ops sent in one `Edit`
+const MAX_OPS_PER_MESSAGE: usize = 1000;
+
+/// Messages between the host and its guests, one JSON object per line.
+/// Guests only talk to the host, which passes their edits and carets on.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum Message {
+    /// First line from a guest
+    Join {
+        code: String,
+        name: String,
+    },
+    /// The host's answer: the guest's peer id. The shared documents follow
+    /// as `Open`s.
+    Welcome {
+        peer: u32,
+    },
+    Rejected {
+        reason: String,
+    },
+    /// A document the host shares, in the order they were shared
+    Open {
+        name: String,
+        replica: Replica,
+    },
+    Edit {
+        peer: u32,
+        document: usize,
+        ops: Vec<Op>,
+    },
+    Caret {
+        peer: u32,
+        name: String,
+        caret: Caret,
+    },
+    Left {
+        peer: u32,
+    },
+}
+
+/// Where someone's caret and selection are
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Caret {
+    pub document: usize,
+    pub offset: usize,
+    pub anchor: usize,
+}
+
+/// Someone else in the session
+#[derive(Debug, Clone)]
+pub struct Participant {
+    pub peer: u32,
+    pub name: String,
+    pub caret: Caret,
+}
+
+/// What happened in the session since it was last polled
+#[derive(Debug)]
+pub enum Update {
+    /// A document was shared with us
+    Opened {
+        document: usize,
+        name: String,
+        text: String,
+    },
+    /// Someone else edited a document
+    Changed {
+        document: usize,
+        changes: Vec<Change>,
+    },
+    /// Someone gave the right code and waits for the host to let them in
+    JoinRequested(String),
+    Joined(String),
+    Left(String),
+    /// The session is over, e.g. the host stopped sharing
+    Ended(String),
+}
+
+enum Event {
+    Connected(u32, Link),
+    Message(u32, Message),
+    Disconnected(u32),
+}
+
+/// Connection to the host, or to one guest
+struct Link {
+    outgoing: Sender<Message>,
+    /// Address of the other side
+    address: IpAddr,
+    /// Name the guest joined with; guests only get messages once they have
+    name: Option<String>,
+    /// Name a guest gave the right code with, until the host lets it in
+    requested: Option<String>,
+}
+
+/// Guest that gave the right code and waits for the host's answer
+#[derive(Debug, Clone)]
+pub struct JoinRequest {
+    pub peer: u32,
+    pub name: String,
+    pub address: IpAddr,
+}
+
+/// Collaborative editing over the local network: a host shares its open
+/// files and guests connect by address and code to edit them together, once
+/// the host has let them in. Each document is a CRDT replica on every side,
+/// so edits made at the same time end up the same everywhere. The session
+/// ends when dropped.
+pub struct Session {
+    /// Own peer id; 0 for a guest until the host has let it in
+    peer: u32,
+    name: String,
+    /// Code guests have to give, when hosting
+    code: Option<String>,
+    /// Address guests connect to, when hosting
+    address: Option<SocketAddr>,
+    documents: Vec<(String, Replica)>,
+    links: HashMap<u32, Link>,
+    events: Receiver<Event>,
+    /// Guests waiting for the host to let them in, oldest first
+    join_requests: Vec<JoinRequest>,
+    /// Wrong codes given per address, when hosting
+    failed_codes: HashMap<IpAddr, u32>,
+    /// Addresses whose connections the accept loop drops
+    blocked: Arc<Mutex<HashSet<IpAddr>>>,
+    participants: HashMap<u32, Participant>,
+    /// Own caret, passed on to guests that join later
+    caret: Option<Caret>,
+    updates: Vec<Update>,
+    stopped: Arc<AtomicBool>,
+}
+
+impl Session {
+    /// Share `documents` (names and text) with whoever connects with the
+    /// session's code and is let in. Only the local network interface
+    /// listens, not every interface of the machine.
+    pub fn host(
+        name: String,
+        documents: Vec<(String, String)>,
+        ctx: egui::Context,
+    ) -> std::io::Result<Self> {
+        let ip = local_ip();
+        let listener =
+            TcpListener::bind((ip, DEFAULT_PORT)).or_else(|_| TcpListener::bind((ip, 0)))?;
+        let address = listener.local_addr()?;
+        let (events_tx, events) = mpsc::channel();
+        let stopped = Arc::new(AtomicBool::new(false));
+        let blocked = Arc::new(Mutex::new(HashSet::new()));
+
+        let thread_stopped = stopped.clone();
+        let thread_blocked = blocked.clone();
+        std::thread::Builder::new()
+            .name("collab-host".to_string())
+            .spawn(move || {
+                // Guests are numbered after the host
+                let mut next_peer = HOST + 1;
+                for stream in listener.incoming() {
+                    if thread_stopped.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let Ok(stream) = stream else {
+                        continue;
+                    };
+                    let Ok(remote) = stream.peer_addr() else {
+                        continue;
+                    };
+                    // Dropping the stream closes it
+                    if thread_blocked.lock().unwrap().contains(&remote.ip()) {
+                        continue;
+                    }
+                    // A connection that never sends its code is closed
+                    if stream.set_read_timeout(Some(JOIN_TIMEOUT)).is_err() {
+                        continue;
+                    }
+                    let Ok(link) = link(&stream) else {
+                        continue;
+                    };
+                    let peer = next_peer;
+                    next_peer += 1;
+                    // The link goes first so there is somewhere to answer
+                    if events_tx.send(Event::Connected(peer, link)).is_err() {
+                        break;
+                    }
+                    let limits = (MAX_JOIN_LINE, MAX_GUEST_MESSAGE);
+                    let _ = receive(peer, stream, limits, events_tx.clone(), ctx.clone());
+                }
+            })?;
+
+        Ok(Self {
+            peer: HOST,
+            name,
+            code: Some(session_code()?),
+            address: Some(address),
+            documents: documents
+                .into_iter()
+                .map(|(name, text)| (name, Replica::new(HOST, &text)))
+                .collect(),
+            links: HashMap::new(),
+            events,
+            join_requests: Vec::new(),
+            failed_codes: HashMap::new(),
+            blocked,
+            participants: HashMap::new(),
+            caret: None,
+            updates: Vec::new(),
+            stopped,
+        })
+    }
+
+    /// Connect to the host at `address` (`host` or `host:port`). The shared
+    /// documents arrive as updates once the host has checked the code.
+    pub fn join(
+        address: &str,
+        code: &str,
+        name: String,
+        ctx: egui::Context,
+    ) -> std::io::Result<Self> {
+        let address = address.trim();
+        let addr = match address.to_socket_addrs() {
+            Ok(mut addrs) => addrs.next(),
+            Err(_) => (address, DEFAULT_PORT).to_socket_addrs()?.next(),
+        }
+        .ok_or(std::io::ErrorKind::AddrNotAvailable)?;
+        let stream = TcpStream::connect_timeout(&addr, Duration::from_secs(5))?;
+        let (events_tx, events) = mpsc::channel();
+        let mut host = link(&stream)?;
+        host.name = Some("host".to_string());
+        receive(
+            HOST,
+            stream,
+            (MAX_HOST_MESSAGE, MAX_HOST_MESSAGE),
+            events_tx,
+            ctx,
+        )?;
+        let _ = host.outgoing.send(Message::Join {
+            code: code.trim().to_string(),
+            name: name.clone(),
+        });
+        Ok(Self {
+            peer: 0,
+            name,
+            code: None,
+            address: None,
+            documents: Vec::new(),
+            links: HashMap::from([(HOST, host)]),
+            events,
+            join_requests: Vec::new(),
+            failed_codes: HashMap::new(),
+            blocked: Arc::new(Mutex::new(HashSet::new())),
+            participants: HashMap::new(),
+            caret: None,
+            updates: Vec::new(),
+            stopped: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    pub fn is_host(&self) -> bool {
+        self.peer == HOST
+    }
+
+    /// Code guests join with, when hosting
+    pub fn code(&self) -> Option<&str> {
+        self.code.as_deref()
+    }
+
+    /// Address guests connect to, when hosting
+    pub fn address(&self) -> Option<SocketAddr> {
+        self.address
+    }
+
+    /// Guests waiting to be let in, oldest first
+    pub fn join_requests(&self) -> &[JoinRequest] {
+        &self.join_requests
+    }
+
+    /// Let a waiting guest in: it gets the shared documents and everyone's
+    /// carets
+    pub fn admit(&mut self, peer: u32) {
+        self.join_requests.retain(|request| request.peer != peer);
+        let Some(link) = self.links.get_mut(&peer) else {
+            return;
+        };
+        let Some(name) = link.requested.take() else {
+            return;
+        };
+        link.name = Some(name.clone());
+        let mut welcome = vec![Message::Welcome { peer }];
+        welcome.extend(self.documents.iter().map(|(name, replica)| Message::Open {
+            name: name.clone(),
+            replica: replica.clone(),
+        }));
+        let others = self.participants.values().map(|p| Message::Caret {
+            peer: p.peer,
+            name: p.name.clone(),
+            caret: p.caret,
+        });
+        welcome.extend(others);
+        if let Some(caret) = self.caret {
+            welcome.push(Message::Caret {
+                peer: HOST,
+                name: self.name.clone(),
+                caret,
+            });
+        }
+        for message in welcome {
+            let _ = link.outgoing.send(message);
+        }
+        self.updates.push(Update::Joined(name));
+    }
+
+    /// Turn a waiting guest away and close its connection
+    pub fn refuse(&mut self, peer: u32) {
+        self.join_requests.retain(|request| request.peer != peer);
+        if let Some(link) = self.links.remove(&peer) {
+            let reason = "The host did not let you in".to_string();
+            let _ = link.outgoing.send(Message::Rejected { reason });
+        }
+    }
+
+    /// Everyone else's carets
+    pub fn participants(&self) -> impl Iterator<Item = &Participant> {
+        self.participants.values()
+    }
+
+    /// Send an edit of a document: the chars in `range` were replaced with
+    /// `text`
+    pub fn edited(&mut self, document: usize, range: Range<usize>, text: &str) {
+        let Some((_, replica)) = self.documents.get_mut(document) else {
+            return;
+        };
+        let ops = replica.replace(range, text);
+        let peer = self.peer;
+        for ops in ops.chunks(MAX_OPS_PER_MESSAGE) {
+            let ops = ops.to_vec();
+            self.broadcast(
+                Message::Edit {
+                    peer,
+                    document,
+                    ops,
+                },
+                None,
+            );
+        }
+    }
+
+    /// Show the others where our caret is
+    pub fn move_caret(&mut self, caret: Caret) {
+        if self.caret != Some(caret) {
+            self.caret = Some(caret);
+            self.send_caret();
+        }
+    }
+
+    fn send_caret(&self) {
+        if let (Some(caret), true) = (self.caret, self.peer != 0) {
+            let (peer, name) = (self.peer, self.name.clone());
+            self.broadcast(Message::Caret { peer, name, caret }, None);
+        }
+    }
+
+    /// Handle what arrived over the network
+    pub fn poll(&mut self) -> Vec<Update> {
+        while let Ok(event) = self.events.try_recv() {
+            match event {
+                Event::Connected(peer, link) => {
+                    self.links.insert(peer, link);
+                }
+                Event::Message(peer, message) if self.is_host() => {
+                    self.handle_guest_message(peer, message)
+                }
+                Event::Message(_, message) => self.handle_host_message(message),
+                Event::Disconnected(peer) => self.disconnected(peer),
+            }
+        }
+        std::mem::take(&mut self.updates)
+    }
+
+    fn handle_guest_message(&mut self, from: u32, message: Message) {
+        let Some(link) = self.links.get_mut(&from) else {
+            return;
+        };
+        let joined = link.name.is_some();
+        match message {
+            Message::Join { code, name } if !joined && link.requested.is_none() => {
+                if self.code.as_deref() != Some(code.as_str()) {
+                    let reason = "The code is not right".to_string();
+                    let _ = link.outgoing.send(Message::Rejected { reason });
+                    let failed = self.failed_codes.entry(link.address).or_insert(0);
+                    *failed += 1;
+                    if *failed >= MAX_CODE_ATTEMPTS {
+                        self.blocked.lock().unwrap().insert(link.address);
+                    }
+                    // Closes the connection once the answer is out
+                    self.links.remove(&from);
+                    return;
+                }
+                link.requested = Some(name.clone());
+                self.join_requests.push(JoinRequest {
+                    peer: from,
+                    name: name.clone(),
+                    address: link.address,
+                });
+                self.updates.push(Update::JoinRequested(name));
+            }
+            Message::Edit { document, ops, .. } if joined => {
+                self.apply(document, &ops);
+                let message = Message::Edit {
+                    peer: from,
+                    document,
+                    ops,
+                };
+                self.broadcast(message, Some(from));
+            }
+            Message::Caret { name, caret, .. } if joined => {
+                let participant = Participant {
+                    peer: from,
+                    name: name.clone(),
+                    caret,
+                };
+                self.participants.insert(from, participant);
+                let message = Message::Caret {
+                    peer: from,
+                    name,
+                    caret,
+                };
+                self.broadcast(message, Some(from));
+            }
+            Message::Left { .. } => self.disconnected(from),
+            _ => {}
+        }
+    }
+
+    fn handle_host_message(&mut self, message: Message) {
+        match message {
+            Message::Welcome { peer } => {
+                self.peer = peer;
+                self.send_caret();
+            }
+            Message::Rejected { reason } => {
+                self.links.clear();
+                self.updates.push(Update::Ended(reason));
+            }
+            Message::Open { name, replica } => {
+                let replica = replica.fork(self.peer);
+                self.opened(name, replica);
+            }
+            Message::Edit { document, ops, .. } => self.apply(document, &ops),
+            Message::Caret { peer, name, caret } if peer != self.peer => {
+                let participant = Participant { peer, name, caret };
+                self.participants.insert(peer, participant);
+            }
+            Message::Left { peer } => {
+                if let Some(participant) = self.participants.remove(&peer) {
+                    self.updates.push(Update::Left(participant.name));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn opened(&mut self, name: String, replica: Replica) {
+        self.updates.push(Update::Opened {
+            document: self.documents.len(),
+            name: name.clone(),
+            text: replica.text(),
+        });
+        self.documents.push((name, replica));
+    }
+
+    fn apply(&mut self, document: usize, ops: &[Op]) {
+        let Some((_, replica)) = self.documents.get_mut(document) else {
+            return;
+        };
+        let changes: Vec<Change> = ops.iter().filter_map(|op| replica.apply(op)).collect();
+        if !changes.is_empty() {
+            self.updates.push(Update::Changed { document, changes });
+        }
+    }
+
+    fn disconnected(&mut self, peer: u32) {
+        self.join_requests.retain(|request| request.peer != peer);
+        let Some(link) = self.links.remove(&peer) else {
+            return;
+        };
+        if !self.is_host() {
+            self.links.clear();
+            let reason = "The host ended the session".to_string();
+            self.updates.push(Update::Ended(reason));
+            return;
+        }
+        self.participants.remove(&peer);
+        if let Some(name) = link.name {
+            self.broadcast(Message::Left { peer }, None);
+            self.updates.push(Update::Left(name));
+        }
+    }
+
+    /// Send to everyone we are connected to that has joined
+    fn broadcast(&self, message: Message, except: Option<u32>) {
+        for (&peer, link) in &self.links {
+            if link.name.is_some() && Some(peer) != except {
+                let _ = link.outgoing.send(message.clone());
+            }
+        }
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        // Dropping the links closes the connections
+        self.stopped.store(true, Ordering::Relaxed);
+        if let Some(address) = self.address {
+            // Wake up the accept loop so it sees the flag
+            let _ = TcpStream::connect(address);
+        }
+    }
+}
+
+/// Name others see, from the login name
+pub fn user_name() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "Guest".to_string())
+}
+
+/// Code guests join with: 128 random bits from the operating system, as
+/// four groups of eight hex digits
+fn session_code() -> std::io::Result<String> {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+    let hex: Vec<String> = bytes
+        .chunks(4)
+        .map(|group| group.iter().map(|b| format!("{b:02x}")).collect())
+        .collect();
+    Ok(hex.join("-"))
+}
+
+/// Link over `stream`, with a thread writing what is sent through it. The
+/// connection closes once the link is dropped and everything sent is written.
+fn link(stream: &TcpStream) -> std::io::Result<Link> {
+    let address = stream.peer_addr()?.ip();
+    let mut writer = stream.try_clone()?;
+    let (outgoing, messages) = mpsc::channel::<Message>();
+    std::thread::Builder::new()
+        .name("collab-send".to_string())
+        .spawn(move || {
+            for message in messages {
+                let Some(line) = encode(&message) else {
+                    continue;
+                };
+                if writer.write_all(line.as_bytes()).is_err() {
+                    break;
+                }
+            }
+            let _ = writer.shutdown(Shutdown::Both);
+        })?;
+    Ok(Link {
+        outgoing,
+        address,
+        name: None,
+        requested: None,
+    })
+}
+
+/// Read messages from `peer` on a thread until the connection closes or
+/// sends a line longer than allowed: `limits` are the longest first line and
+/// the longest later one. A read timeout on `stream` only applies to the
+/// first line.
+fn receive(
+    peer: u32,
+    stream: TcpStream,
+    limits: (u64, u64),
+    events: Sender<Event>,
+    ctx: egui::Context,
+) -> std::io::Result<()> {
+    std::thread::Builder::new()
+        .name("collab-receive".to_string())
+        .spawn(move || {
+            let mut reader = BufReader::new(stream);
+            let (mut limit, later_limit) = limits;
+            let mut line = String::new();
+            // Anything that isn't a message ends the connection
+            while let Some(message) = read_message(&mut reader, limit, &mut line) {
+                if limit != later_limit && reader.get_ref().set_read_timeout(None).is_err() {
+                    break;
+                }
+                limit = later_limit;
+                if events.send(Event::Message(peer, message)).is_err() {
+                    return;
+                }
+                ctx.request_repaint();
+            }
+            let _ = events.send(Event::Disconnected(peer));
+            ctx.request_repaint();
+        })?;
+    Ok(())
+}
+
+/// A message as the line it is sent as
+fn encode(message: &Message) -> Option<String> {
+    let mut line = serde_json::to_string(message).ok()?;
+    line.push('\n');
+    Some(line)
+}
+
+/// Read the next message, reading at most `limit` bytes into `line`. None
+/// for a line that isn't a message, or was cut off by the limit, a read
+/// timeout or the connection closing, which leaves it without a line break.
+fn read_message(reader: &mut impl BufRead, limit: u64, line: &mut String) -> Option<Message> {
+    line.clear();
+    let read = reader.take(limit).read_line(line);
+    if !matches!(read, Ok(n) if n > 0) || !line.ends_with('\n') {
+        return None;
+    }
+    serde_json::from_str(line).ok()
+}
+
+/// Address of this machine on the local network, the one connections to
+/// the internet go out from. Connecting a UDP socket sends nothing.
+fn local_ip() -> IpAddr {
+    UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
+        .and_then(|socket| {
+            socket.connect((Ipv4Addr::new(8, 8, 8, 8), 80))?;
+            socket.local_addr()
+        })
+        .map_or(IpAddr::V4(Ipv4Addr::LOCALHOST), |addr| addr.ip())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::time::Instant;
+
+    fn read_all(bytes: &[u8], limit: u64) -> Vec<Message> {
+        let mut reader = BufReader::new(Cursor::new(bytes.to_vec()));
+        let mut line = String::new();
+        std::iter::from_fn(|| read_message(&mut reader, limit, &mut line)).collect()
+    }
+
+    #[test]
+    fn messages_are_one_json_object_per_line() {
+        let replica = Replica::new(HOST, "shared\ntext");
+        let ops = replica.fork(2).insert(0, "x");
+        let mut bytes = String::new();
+        for message in [
+            Message::Welcome { peer: 2 },
+            Message::Open {
+                name: "main.rs".to_string(),
+                replica,
+            },
+            Message::Edit {
+                peer: 2,
+                document: 0,
+                ops,
+            },
+        ] {
+            let line = encode(&message).unwrap();
+            assert_eq!(line.matches('\n').count(), 1);
+            bytes.push_str(&line);
+        }
+
+        let messages = read_all(bytes.as_bytes(), MAX_HOST_MESSAGE);
+        assert!(matches!(messages[0], Message::Welcome { peer: 2 }));
+        assert!(matches!(&messages[1], Message::Open { name, replica }
+            if name == "main.rs" && replica.text() == "shared\ntext"));
+        assert!(matches!(&messages[2], Message::Edit { ops, .. } if ops.len() == 1));
+        assert_eq!(messages.len(), 3);
+    }
+
+    #[test]
+    fn lines_over_the_limit_end_the_stream() {
+        let join = Message::Join {
+            code: "code".to_string(),
+            name: "x".repeat(100),
+        };
+        let line = encode(&join).unwrap();
+        assert_eq!(read_all(line.as_bytes(), line.len() as u64).len(), 1);
+        assert!(read_all(line.as_bytes(), line.len() as u64 - 1).is_empty());
+    }
+
+    #[test]
+    fn cut_off_and_invalid_lines_end_the_stream() {
+        let line = encode(&Message::Left { peer: 3 }).unwrap();
+        // The connection closed halfway through the line
+        assert!(read_all(line.trim_end().as_bytes(), MAX_GUEST_MESSAGE).is_empty());
+        let bytes = format!("not json\n{line}");
+        assert!(read_all(bytes.as_bytes(), MAX_GUEST_MESSAGE).is_empty());
+    }
+
+    #[test]
+    fn a_silent_connection_times_out() {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let _client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_millis(50)))
+            .unwrap();
+        let started = Instant::now();
+        let mut line = String::new();
+        let message = read_message(&mut BufReader::new(stream), MAX_JOIN_LINE, &mut line);
+        assert!(message.is_none());
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
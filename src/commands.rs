@@ -0,0 +1,713 @@
+use egui::{Event, Key, KeyboardShortcut, Modifiers};
+
+/// Top-level menus of the menu bar, in order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Menu {
+    File,
+    Edit,
+    Selection,
+    View,
+    Go,
+    Run,
+    Help,
+}
+
+/// Entry of a menu
+pub enum MenuItem {
+    Command(Command),
+    Separator,
+    Submenu(&'static str, &'static [MenuItem]),
+}
+
+impl Menu {
+    pub const ALL: [Menu; 7] = [
+        Menu::File,
+        Menu::Edit,
+        Menu::Selection,
+        Menu::View,
+        Menu::Go,
+        Menu::Run,
+        Menu::Help,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Menu::File => "File",
+            Menu::Edit => "Edit",
+            Menu::Selection => "Selection",
+            Menu::View => "View",
+            Menu::Go => "Go",
+            Menu::Run => "Run",
+            Menu::Help => "Help",
+        }
+    }
+
+    /// Layout of the menu. Every command appears in exactly one menu, so the
+    /// menus are also the list of all commands.
+    pub fn items(self) -> &'static [MenuItem] {
+        use MenuItem::{Command as Item, Separator, Submenu};
+        match self {
+            Menu::File => &[
+                Item(Command::NewFile),
+                Item(Command::NewProject),
+                Separator,
+                Item(Command::OpenFile),
+                Item(Command::OpenFolder),
+                Item(Command::OpenLogFile),
+                Item(Command::CompareFolders),
+                Item(Command::EnvironmentVariables),
+                Item(Command::EditKeybindings),
+                Separator,
+                Item(Command::SaveSession),
+                Item(Command::SwitchSession),
+                Separator,
+                Item(Command::Save),
+                Item(Command::SaveAs),
+                Item(Command::SaveWithEncoding),
+                Item(Command::ReopenWithEncoding),
+                Item(Command::FormatOnSave),
+                Item(Command::CloseDeletedTabs),
+                Item(Command::MergeFromDisk),
+                Separator,
+                Item(Command::GitCommit),
+                Item(Command::SignCommits),
+                Item(Command::ServeWorkspace),
+                Item(Command::ShareSession),
+                Item(Command::JoinSession),
+                Item(Command::ConnectDatabase),
+                Separator,
+                Item(Command::CloseEditor),
+                Item(Command::ReopenClosedTab),
+                Item(Command::Exit),
+            ],
+            Menu::Edit => &[
+                Item(Command::Undo),
+                Item(Command::Redo),
+                Separator,
+                Item(Command::Cut),
+                Item(Command::Copy),
+                Item(Command::Paste),
+                Item(Command::PasteFromHistory),
+                Separator,
+                Item(Command::Find),
+                Item(Command::Replace),
+                Separator,
+                Item(Command::SortLinesAscending),
+                Item(Command::SortLinesDescending),
+                Item(Command::RemoveDuplicateLines),
+                Separator,
+                Item(Command::TriggerSuggest),
+                Separator,
+                Item(Command::RecordMacro),
+                Item(Command::PlayMacro),
+            ],
+            Menu::Selection => &[
+                Item(Command::SelectAll),
+                Item(Command::ExpandSelection),
+                Item(Command::ShrinkSelection),
+                Separator,
+                Item(Command::CopyLineUp),
+                Item(Command::CopyLineDown),
+                Item(Command::DuplicateSelection),
+                Item(Command::DeleteLine),
+                Separator,
+                Item(Command::AddCursorAbove),
+                Item(Command::AddCursorBelow),
+                Item(Command::SelectAllOccurrences),
+                Item(Command::CursorUndo),
+                Separator,
+                Item(Command::SelectStringContents),
+                Item(Command::SelectBracketContents),
+                Item(Command::SelectToBracket),
+            ],
+            Menu::View => &[
+                Item(Command::ShowAllCommands),
+                Separator,
+                Item(Command::ShowSidebar),
+                Item(Command::ShowHiddenFiles),
+                Item(Command::ShowMinimap),
+                Item(Command::SplitEditor),
+                Item(Command::WordWrap),
+                Item(Command::RenderWhitespace),
+                Item(Command::MouseGestures),
+                Item(Command::SoundCues),
+                Item(Command::CustomTitleBar),
+                Item(Command::SaveLayout),
+                Item(Command::ResetLayout),
+                Separator,
+                Item(Command::ToggleOutput),
+                Item(Command::ShowBookmarks),
+                Item(Command::FollowFile),
+                Item(Command::MarkdownPreview),
+                Item(Command::SyncPreviewScrolling),
+                Item(Command::HtmlPreview),
+                Item(Command::RegexPlayground),
+                Separator,
+                Submenu(
+                    "Folding",
+                    &[
+                        Item(Command::Fold),
+                        Item(Command::Unfold),
+                        Item(Command::FoldAllComments),
+                        Item(Command::FoldAllRegions),
+                        Item(Command::UnfoldAll),
+                    ],
+                ),
+                Submenu(
+                    "This Tab",
+                    &[
+                        Item(Command::TabZoomIn),
+                        Item(Command::TabZoomOut),
+                        Item(Command::TabWordWrap),
+                        Item(Command::UseGlobalSettings),
+                    ],
+                ),
+                Submenu(
+                    "Text Rendering",
+                    &[
+                        Item(Command::AntiAliasing),
+                        Item(Command::RoundTextToPixels),
+                        Item(Command::SnapHighlightsToPixels),
+                    ],
+                ),
+                Submenu(
+                    "Theme",
+                    &[
+                        Item(Command::ThemeDark),
+                        Item(Command::ThemeLight),
+                        Item(Command::ThemeAuto),
+                    ],
+                ),
+                Submenu(
+                    "Keymap",
+                    &[Item(Command::KeymapDefault), Item(Command::KeymapEmacs)],
+                ),
+            ],
+            Menu::Go => &[
+                Item(Command::QuickOpen),
+                Item(Command::GoToLine),
+                Item(Command::GoToSymbol),
+                Item(Command::SwitchToCounterpart),
+                Item(Command::GoToBracket),
+                Separator,
+                Item(Command::NextFunction),
+                Item(Command::PreviousFunction),
+                Separator,
+                Item(Command::ToggleBookmark),
+                Item(Command::NextBookmark),
+                Item(Command::PreviousBookmark),
+                Separator,
+                Item(Command::GoBack),
+                Item(Command::GoForward),
+                Item(Command::ToggleLastEditor),
+                Separator,
+                Item(Command::GoToDefinition),
+                Item(Command::FindReferences),
+            ],
+            Menu::Run => &[
+                Item(Command::RunTask),
+                Item(Command::StopTask),
+                Separator,
+                Item(Command::RunQuery),
+                Item(Command::SendRequest),
+            ],
+            Menu::Help => &[
+                Item(Command::KeyboardShortcuts),
+                Item(Command::ReportIssue),
+                Separator,
+                Item(Command::About),
+            ],
+        }
+    }
+}
+
+/// Every command with the menu it is in, in menu order
+pub fn all() -> Vec<(Menu, Command)> {
+    fn collect(menu: Menu, items: &[MenuItem], commands: &mut Vec<(Menu, Command)>) {
+        for item in items {
+            match item {
+                MenuItem::Command(command) => commands.push((menu, *command)),
+                MenuItem::Separator => {}
+                MenuItem::Submenu(_, items) => collect(menu, items, commands),
+            }
+        }
+    }
+    let mut commands = Vec::new();
+    for menu in Menu::ALL {
+        collect(menu, menu.items(), &mut commands);
+    }
+    commands
+}
+
+/// Something the user can do from the menus, the command palette or a shortcut
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Command {
+    // File
+    NewFile,
+    NewProject,
+    OpenFile,
+    OpenFolder,
+    OpenLogFile,
+    CompareFolders,
+    EnvironmentVariables,
+    EditKeybindings,
+    SaveSession,
+    SwitchSession,
+    Save,
+    SaveAs,
+    SaveWithEncoding,
+    ReopenWithEncoding,
+    FormatOnSave,
+    CloseDeletedTabs,
+    MergeFromDisk,
+    GitCommit,
+    SignCommits,
+    ServeWorkspace,
+    ShareSession,
+    JoinSession,
+    ConnectDatabase,
+    CloseEditor,
+    ReopenClosedTab,
+    Exit,
+    // Edit
+    Undo,
+    Redo,
+    Cut,
+    Copy,
+    Paste,
+    PasteFromHistory,
+    Find,
+    Replace,
+    SortLinesAscending,
+    SortLinesDescending,
+    RemoveDuplicateLines,
+    TriggerSuggest,
+    RecordMacro,
+    PlayMacro,
+    // Selection
+    SelectAll,
+    ExpandSelection,
+    ShrinkSelection,
+    CopyLineUp,
+    CopyLineDown,
+    DuplicateSelection,
+    DeleteLine,
+    AddCursorAbove,
+    AddCursorBelow,
+    SelectAllOccurrences,
+    CursorUndo,
+    SelectStringContents,
+    SelectBracketContents,
+    SelectToBracket,
+    // View
+    ShowAllCommands,
+    ShowSidebar,
+    ShowMinimap,
+    WordWrap,
+    SplitEditor,
+    TabZoomIn,
+    TabZoomOut,
+    TabWordWrap,
+    UseGlobalSettings,
+    RenderWhitespace,
+    ShowHiddenFiles,
+    MouseGestures,
+    SoundCues,
+    CustomTitleBar,
+    SaveLayout,
+    ResetLayout,
+    ToggleOutput,
+    ShowBookmarks,
+    FollowFile,
+    MarkdownPreview,
+    SyncPreviewScrolling,
+    HtmlPreview,
+    RegexPlayground,
+    Fold,
+    Unfold,
+    FoldAllComments,
+    FoldAllRegions,
+    UnfoldAll,
+    AntiAliasing,
+    RoundTextToPixels,
+    SnapHighlightsToPixels,
+    ThemeDark,
+    ThemeLight,
+    ThemeAuto,
+    KeymapDefault,
+    KeymapEmacs,
+    // Go
+    GoToLine,
+    QuickOpen,
+    GoToSymbol,
+    SwitchToCounterpart,
+    GoToBracket,
+    NextFunction,
+    PreviousFunction,
+    ToggleBookmark,
+    NextBookmark,
+    PreviousBookmark,
+    GoBack,
+    GoForward,
+    ToggleLastEditor,
+    GoToDefinition,
+    FindReferences,
+    // Run
+    RunTask,
+    StopTask,
+    RunQuery,
+    SendRequest,
+    // Help
+    KeyboardShortcuts,
+    ReportIssue,
+    About,
+}
+
+impl Command {
+    /// Name of the command in `keybindings.toml`
+    pub fn id(self) -> String {
+        format!("{self:?}")
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Command::NewFile => "New File",
+            Command::NewProject => "New Project...",
+            Command::OpenFile => "Open File...",
+            Command::OpenFolder => "Open Folder...",
+            Command::OpenLogFile => "Open Log File...",
+            Command::CompareFolders => "Compare Folders...",
+            Command::EnvironmentVariables => "Environment Variables...",
+            Command::EditKeybindings => "Keybindings...",
+            Command::SaveSession => "Save Session As...",
+            Command::SwitchSession => "Switch Session...",
+            Command::Save => "Save",
+            Command::SaveAs => "Save As...",
+            Command::SaveWithEncoding => "Save with Encoding...",
+            Command::ReopenWithEncoding => "Reopen with Encoding...",
+            Command::FormatOnSave => "Format on Save",
+            Command::CloseDeletedTabs => "Close Tabs of Deleted Files",
+            Command::MergeFromDisk => "Merge Changes from Disk",
+            Command::GitCommit => "Commit Staged Changes...",
+            Command::SignCommits => "Sign Commits",
+            Command::ServeWorkspace => "Serve Workspace Folder",
+            Command::ShareSession => "Share Session",
+            Command::JoinSession => "Join Shared Session...",
+            Command::ConnectDatabase => "Connect SQLite Database...",
+            Command::CloseEditor => "Close Editor",
+            Command::ReopenClosedTab => "Reopen Closed Tab",
+            Command::Exit => "Exit",
+            Command::Undo => "Undo",
+            Command::Redo => "Redo",
+            Command::Cut => "Cut",
+            Command::Copy => "Copy",
+            Command::Paste => "Paste",
+            Command::PasteFromHistory => "Paste from History...",
+            Command::Find => "Find",
+            Command::Replace => "Find and Replace",
+            Command::SortLinesAscending => "Sort Lines Ascending",
+            Command::SortLinesDescending => "Sort Lines Descending",
+            Command::RemoveDuplicateLines => "Remove Duplicate Lines",
+            Command::TriggerSuggest => "Trigger Suggest",
+            Command::RecordMacro => "Record Macro",
+            Command::PlayMacro => "Play Macro...",
+            Command::SelectAll => "Select All",
+            Command::ExpandSelection => "Expand Selection",
+            Command::ShrinkSelection => "Shrink Selection",
+            Command::CopyLineUp => "Copy Line Up",
+            Command::CopyLineDown => "Copy Line Down",
+            Command::DuplicateSelection => "Duplicate Selection",
+            Command::DeleteLine => "Delete Line",
+            Command::AddCursorAbove => "Add Cursor Above",
+            Command::AddCursorBelow => "Add Cursor Below",
+            Command::SelectAllOccurrences => "Select All Occurrences",
+            Command::CursorUndo => "Cursor Undo",
+            Command::SelectStringContents => "Select String Contents",
+            Command::SelectBracketContents => "Select Bracket Contents",
+            Command::SelectToBracket => "Select to Bracket",
+            Command::ShowAllCommands => "Command Palette...",
+            Command::ShowSidebar => "Show Sidebar",
+            Command::ShowMinimap => "Show Minimap",
+            Command::WordWrap => "Word Wrap",
+            Command::SplitEditor => "Split Editor",
+            Command::TabZoomIn => "Zoom In",
+            Command::TabZoomOut => "Zoom Out",
+            Command::TabWordWrap => "Word Wrap in This Tab",
+            Command::UseGlobalSettings => "Use Global Settings",
+            Command::RenderWhitespace => "Render Whitespace",
+            Command::ShowHiddenFiles => "Show Hidden Files",
+            Command::MouseGestures => "Mouse Gestures",
+            Command::SoundCues => "Sound Cues",
+            Command::CustomTitleBar => "Custom Title Bar",
+            Command::SaveLayout => "Save Layout",
+            Command::ResetLayout => "Reset Layout",
+            Command::ToggleOutput => "Output",
+            Command::ShowBookmarks => "Bookmarks",
+            Command::FollowFile => "Follow File",
+            Command::MarkdownPreview => "Markdown Preview",
+            Command::SyncPreviewScrolling => "Sync Preview Scrolling",
+            Command::HtmlPreview => "Open HTML Preview in Browser",
+            Command::RegexPlayground => "Regex Playground",
+            Command::Fold => "Fold",
+            Command::Unfold => "Unfold",
+            Command::FoldAllComments => "Fold All Block Comments",
+            Command::FoldAllRegions => "Fold All Regions",
+            Command::UnfoldAll => "Unfold All",
+            Command::AntiAliasing => "Anti-aliasing",
+            Command::RoundTextToPixels => "Round Text to Pixels",
+            Command::SnapHighlightsToPixels => "Snap Highlights to Pixels",
+            Command::ThemeDark => "Dark",
+            Command::ThemeLight => "Light",
+            Command::ThemeAuto => "Auto (follow system)",
+            Command::KeymapDefault => "Default",
+            Command::KeymapEmacs => "Emacs",
+            Command::GoToLine => "Go to Line/Column...",
+            Command::QuickOpen => "Go to File...",
+            Command::GoToBracket => "Go to Bracket",
+            Command::NextFunction => "Go to Next Function",
+            Command::PreviousFunction => "Go to Previous Function",
+            Command::ToggleBookmark => "Toggle Bookmark",
+            Command::NextBookmark => "Next Bookmark",
+            Command::PreviousBookmark => "Previous Bookmark",
+            Command::GoBack => "Go Back",
+            Command::GoForward => "Go Forward",
+            Command::ToggleLastEditor => "Toggle Last Editor",
+            Command::GoToSymbol => "Go to Symbol in Workspace...",
+            Command::SwitchToCounterpart => "Switch to Test/Counterpart",
+            Command::GoToDefinition => "Go to Definition",
+            Command::FindReferences => "Find References",
+            Command::RunTask => "Run Task...",
+            Command::StopTask => "Stop Task",
+            Command::RunQuery => "Run Query",
+            Command::SendRequest => "Send Request",
+            Command::KeyboardShortcuts => "Keyboard Shortcuts",
+            Command::ReportIssue => "Report Issue",
+            Command::About => "About",
+        }
+    }
+
+    /// Shown before the label in menus and the command palette
+    pub fn icon(self) -> &'static str {
+        match self {
+            Command::NewFile => "📝",
+            Command::NewProject => "✨",
+            Command::OpenFile => "📄",
+            Command::OpenFolder => "📁",
+            Command::OpenLogFile => "📜",
+            Command::CompareFolders => "⇔",
+            Command::EnvironmentVariables => "🔑",
+            Command::EditKeybindings => "⌨",
+            Command::Save => "💾",
+            Command::ServeWorkspace => "📡",
+            Command::ConnectDatabase => "🗄",
+            Command::Find => "🔍",
+            Command::Replace => "🔄",
+            Command::RunTask | Command::RunQuery => "▶",
+            Command::StopTask => "⏹",
+            _ => "",
+        }
+    }
+
+    pub fn tooltip(self) -> Option<&'static str> {
+        match self {
+            Command::EnvironmentVariables => {
+                Some("Variables for processes started in this workspace")
+            }
+            Command::FormatOnSave => {
+                Some("Run the formatter configured for the file type before saving")
+            }
+            Command::NextFunction | Command::PreviousFunction => {
+                Some("Jump between the functions and top-level items of the outline")
+            }
+            Command::CloseDeletedTabs => {
+                Some("Close tabs without unsaved changes when their file is deleted")
+            }
+            Command::ShareSession => {
+                Some("Let others on the local network edit the tabs you choose with you")
+            }
+            Command::MergeFromDisk => {
+                Some("Apply changes made to the file outside the editor, keeping unsaved edits")
+            }
+            Command::KeymapEmacs => Some(
+                "Ctrl+A/E line start/end, Ctrl+K kill line, Ctrl+Y yank, Alt+F/B word motion, Ctrl+Space set mark",
+            ),
+            Command::ShowSidebar | Command::ShowMinimap => {
+                Some("In a narrow window, shows it over the editor instead")
+            }
+            Command::SplitEditor => {
+                Some("Show a second file beside the active one; drag a tab down onto the editor to pick it")
+            }
+            Command::TabZoomIn | Command::TabZoomOut => {
+                Some("Change the font size of the active tab only")
+            }
+            Command::TabWordWrap => Some("Wrap lines in the active tab regardless of Word Wrap"),
+            Command::UseGlobalSettings => {
+                Some("Drop the font size and word wrap set for the active tab")
+            }
+            Command::EditKeybindings => {
+                Some("Change the shortcut of any command; saved to keybindings.toml")
+            }
+            Command::SwitchToCounterpart => Some(
+                "Between a file and its test, header or source; creates it when missing",
+            ),
+            Command::ShowHiddenFiles => Some("Show dotfiles and dot folders in the Explorer"),
+            Command::MouseGestures => {
+                Some("Flick a tab up to close it or down to split, shake it to close the split")
+            }
+            Command::SoundCues => Some("Play a sound when a save finishes or diagnostics change"),
+            Command::SaveLayout => Some("Remember panel sizes and visible views between sessions"),
+            Command::FollowFile => Some("Reload content appended on disk and stay at the end"),
+            Command::SortLinesAscending
+            | Command::SortLinesDescending
+            | Command::RemoveDuplicateLines => {
+                Some("Applies to the selected lines, or the whole file without a selection")
+            }
+            Command::SaveSession => Some("Remember the open files and layout under a name"),
+            Command::SwitchSession => {
+                Some("Open the files and layout of a saved session; unsaved tabs stay open")
+            }
+            Command::SignCommits => {
+                Some("Sign commits in this folder with the key in git's user.signingkey, overriding commit.gpgsign")
+            }
+            Command::RunTask => Some("Run a cargo command for a package of the workspace"),
+            Command::PasteFromHistory => Some("Paste an earlier cut or copy of this session"),
+            Command::CursorUndo => Some("Go back to the previous cursor position or selection"),
+            Command::SelectAllOccurrences => {
+                Some("Put a cursor on every match of the find panel, the selection or the word")
+            }
+            Command::RecordMacro => Some("Start or stop recording keystrokes and commands"),
+            Command::PlayMacro => Some("Replay the recorded macro a number of times"),
+            Command::SaveWithEncoding => {
+                Some("Write the file as UTF-8, UTF-16, Latin-1 or Shift JIS, with or without a BOM")
+            }
+            Command::ReopenWithEncoding => {
+                Some("Read the file again as another encoding, e.g. when its text looks garbled")
+            }
+            Command::Fold => Some("Collapse the comment or region around the cursor"),
+            Command::FoldAllComments => Some("Collapse /* */ comments and runs of doc comments"),
+            Command::FoldAllRegions => Some("Collapse everything between region and endregion markers"),
+            _ => None,
+        }
+    }
+
+    pub fn shortcut(self) -> Option<KeyboardShortcut> {
+        const CTRL_SHIFT: Modifiers = Modifiers::CTRL.plus(Modifiers::SHIFT);
+        const CTRL_ALT: Modifiers = Modifiers::CTRL.plus(Modifiers::ALT);
+        const SHIFT_ALT: Modifiers = Modifiers::SHIFT.plus(Modifiers::ALT);
+        let (modifiers, key) = match self {
+            Command::NewFile => (Modifiers::CTRL, Key::N),
+            Command::OpenFolder => (Modifiers::CTRL, Key::O),
+            Command::Save => (Modifiers::CTRL, Key::S),
+            Command::SaveAs => (CTRL_SHIFT, Key::S),
+            Command::CloseEditor => (Modifiers::CTRL, Key::W),
+            Command::ReopenClosedTab => (CTRL_SHIFT, Key::T),
+            Command::Undo => (Modifiers::CTRL, Key::Z),
+            Command::Redo => (Modifiers::CTRL, Key::Y),
+            Command::Cut => (Modifiers::CTRL, Key::X),
+            Command::Copy => (Modifiers::CTRL, Key::C),
+            Command::Paste => (Modifiers::CTRL, Key::V),
+            Command::PasteFromHistory => (CTRL_ALT, Key::V),
+            Command::Find => (Modifiers::CTRL, Key::F),
+            Command::Replace => (Modifiers::CTRL, Key::H),
+            Command::TriggerSuggest => (Modifiers::CTRL, Key::Space),
+            Command::RecordMacro => (CTRL_ALT, Key::R),
+            Command::PlayMacro => (CTRL_ALT, Key::P),
+            Command::SelectAll => (Modifiers::CTRL, Key::A),
+            Command::ExpandSelection => (SHIFT_ALT, Key::ArrowRight),
+            Command::ShrinkSelection => (SHIFT_ALT, Key::ArrowLeft),
+            Command::CopyLineUp => (SHIFT_ALT, Key::ArrowUp),
+            Command::CopyLineDown => (SHIFT_ALT, Key::ArrowDown),
+            Command::DuplicateSelection => (CTRL_SHIFT, Key::D),
+            Command::DeleteLine => (CTRL_SHIFT, Key::K),
+            Command::AddCursorAbove => (CTRL_ALT, Key::ArrowUp),
+            Command::AddCursorBelow => (CTRL_ALT, Key::ArrowDown),
+            Command::SelectAllOccurrences => (CTRL_SHIFT, Key::L),
+            Command::CursorUndo => (Modifiers::CTRL, Key::U),
+            Command::SelectStringContents => (CTRL_ALT, Key::Backtick),
+            Command::SelectBracketContents => (CTRL_ALT, Key::OpenBracket),
+            // Shift+\ arrives as | on US layouts
+            Command::SelectToBracket => (CTRL_SHIFT.plus(Modifiers::ALT), Key::Pipe),
+            Command::GoToBracket => (CTRL_SHIFT, Key::Pipe),
+            Command::ShowAllCommands => (CTRL_SHIFT, Key::P),
+            Command::ShowSidebar => (Modifiers::CTRL, Key::B),
+            Command::SplitEditor => (Modifiers::CTRL, Key::Backslash),
+            Command::WordWrap => (Modifiers::ALT, Key::Z),
+            Command::TabZoomIn => (CTRL_ALT, Key::Equals),
+            Command::TabZoomOut => (CTRL_ALT, Key::Minus),
+            Command::ToggleOutput => (CTRL_SHIFT, Key::U),
+            Command::MarkdownPreview => (CTRL_SHIFT, Key::V),
+            // Shift+[ and Shift+] give { and }, which egui has no key for
+            Command::Fold => (CTRL_ALT, Key::Comma),
+            Command::Unfold => (CTRL_ALT, Key::Period),
+            Command::GoToLine => (Modifiers::CTRL, Key::G),
+            Command::QuickOpen => (Modifiers::CTRL, Key::P),
+            Command::GoToSymbol => (Modifiers::CTRL, Key::T),
+            Command::SwitchToCounterpart => (Modifiers::ALT, Key::O),
+            Command::NextFunction => (Modifiers::CTRL, Key::ArrowDown),
+            Command::PreviousFunction => (Modifiers::CTRL, Key::ArrowUp),
+            Command::ToggleBookmark => (Modifiers::CTRL, Key::F2),
+            Command::NextBookmark => (Modifiers::NONE, Key::F2),
+            Command::PreviousBookmark => (Modifiers::SHIFT, Key::F2),
+            Command::GoBack => (Modifiers::ALT, Key::ArrowLeft),
+            Command::GoForward => (Modifiers::ALT, Key::ArrowRight),
+            Command::ToggleLastEditor => (CTRL_ALT, Key::ArrowLeft),
+            Command::GoToDefinition => (Modifiers::NONE, Key::F12),
+            Command::FindReferences => (Modifiers::SHIFT, Key::F12),
+            Command::RunQuery => (Modifiers::CTRL, Key::Enter),
+            Command::SendRequest => (CTRL_ALT, Key::Enter),
+            _ => return None,
+        };
+        Some(KeyboardShortcut::new(modifiers, key))
+    }
+
+    /// Whether the code editor handles the command's shortcut itself. Running
+    /// such a command from a menu replays its input to the editor.
+    pub fn is_editor_command(self) -> bool {
+        matches!(
+            self,
+            Command::Undo
+                | Command::Redo
+                | Command::Cut
+                | Command::Copy
+                | Command::Paste
+                | Command::SelectAll
+                | Command::CopyLineUp
+                | Command::CopyLineDown
+                | Command::DuplicateSelection
+                | Command::DeleteLine
+        )
+    }
+
+    /// Input that makes the code editor run an editor command. Paste needs the
+    /// clipboard text, so it isn't here.
+    pub fn editor_event(self) -> Option<Event> {
+        match self {
+            Command::Cut => Some(Event::Cut),
+            Command::Copy => Some(Event::Copy),
+            Command::Paste => None,
+            _ if self.is_editor_command() => {
+                let shortcut = self.shortcut()?;
+                Some(Event::Key {
+                    key: shortcut.logical_key,
+                    physical_key: None,
+                    pressed: true,
+                    repeat: false,
+                    modifiers: shortcut.modifiers,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// One of a group of commands picking a setting, shown as radio buttons
+    pub fn is_choice(self) -> bool {
+        matches!(
+            self,
+            Command::ThemeDark
+                | Command::ThemeLight
+                | Command::ThemeAuto
+                | Command::KeymapDefault
+                | Command::KeymapEmacs
+        )
+    }
+}
@@ -0,0 +1,158 @@
+use crate::settings;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{OnceLock, RwLock};
+
+/// File icons and languages added to the built-in ones, from a contribution
+/// file such as `contributions/protobuf.toml` in the config directory:
+///
+/// ```toml
+/// [icons]
+/// proto = "📡"      # by extension
+/// Justfile = "🤖"   # by file name
+///
+/// [languages.proto]
+/// name = "Protocol Buffers"
+/// highlight = "c"   # highlight as this extension (optional)
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Contribution {
+    /// Icon by file extension or whole file name, ignoring case
+    pub icons: HashMap<String, String>,
+    /// Language by file extension
+    pub languages: HashMap<String, Language>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Language {
+    /// Shown in the status bar
+    pub name: String,
+    /// Extension whose syntax highlighting files of this language use
+    pub highlight: Option<String>,
+}
+
+/// Everything registered so far. Names are leaked into `&'static str` once
+/// each, so they can be handed out like the built-in ones.
+#[derive(Default)]
+struct Registry {
+    icons: HashMap<String, &'static str>,
+    languages: HashMap<String, &'static str>,
+    highlights: HashMap<String, &'static str>,
+    interned: HashSet<&'static str>,
+}
+
+impl Registry {
+    fn intern(&mut self, text: &str) -> &'static str {
+        if let Some(interned) = self.interned.get(text) {
+            return interned;
+        }
+        let leaked: &'static str = Box::leak(text.to_string().into_boxed_str());
+        self.interned.insert(leaked);
+        leaked
+    }
+}
+
+fn registry() -> &'static RwLock<Registry> {
+    static REGISTRY: OnceLock<RwLock<Registry>> = OnceLock::new();
+    REGISTRY.get_or_init(Default::default)
+}
+
+/// Folder of contribution files
+pub fn contributions_dir() -> Option<PathBuf> {
+    settings::config_dir().map(|dir| dir.join("contributions"))
+}
+
+/// Whether saving `path` should reload the contributions
+pub fn is_contribution_file(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "toml")
+        && contributions_dir().is_some_and(|dir| path.parent() == Some(dir.as_path()))
+}
+
+/// Add mappings at runtime, overriding built-in and earlier ones for the same
+/// extensions and file names
+pub fn register(contribution: &Contribution) {
+    let Ok(mut registry) = registry().write() else {
+        return;
+    };
+    for (key, icon) in &contribution.icons {
+        let icon = registry.intern(icon);
+        registry.icons.insert(key.to_lowercase(), icon);
+    }
+    for (ext, language) in &contribution.languages {
+        let ext = ext.to_lowercase();
+        let name = registry.intern(&language.name);
+        registry.languages.insert(ext.clone(), name);
+        match &language.highlight {
+            Some(highlight) => {
+                let highlight = registry.intern(highlight);
+                registry.highlights.insert(ext, highlight);
+            }
+            None => {
+                registry.highlights.remove(&ext);
+            }
+        }
+    }
+}
+
+/// Replace the registered mappings with those of the contribution files, in
+/// file name order. Returns a message for each file that couldn't be read.
+pub fn load() -> Vec<String> {
+    let mut paths: Vec<PathBuf> = contributions_dir()
+        .and_then(|dir| fs::read_dir(dir).ok())
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+        .collect();
+    paths.sort();
+
+    if let Ok(mut registry) = registry().write() {
+        registry.icons.clear();
+        registry.languages.clear();
+        registry.highlights.clear();
+    }
+    let mut errors = Vec::new();
+    for path in paths {
+        let contribution = fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| {
+                toml::from_str::<Contribution>(&content).map_err(|e| e.to_string())
+            });
+        match contribution {
+            Ok(contribution) => register(&contribution),
+            Err(e) => errors.push(format!("Could not load {}: {e}", path.display())),
+        }
+    }
+    errors
+}
+
+/// Contributed icon for a file name, by whole name and then by extension
+pub fn icon(filename: &str) -> Option<&'static str> {
+    let registry = registry().read().ok()?;
+    if registry.icons.is_empty() {
+        return None;
+    }
+    let lower = filename.to_lowercase();
+    registry.icons.get(&lower).copied().or_else(|| {
+        // `d.ts` before `ts`
+        lower
+            .match_indices('.')
+            .find_map(|(i, _)| registry.icons.get(&lower[i + 1..]).copied())
+    })
+}
+
+/// Contributed language name for an extension
+pub fn language(ext: &str) -> Option<&'static str> {
+    let registry = registry().read().ok()?;
+    registry.languages.get(&ext.to_lowercase()).copied()
+}
+
+/// Extension to pick the syntax highlighting of files with `ext` by
+pub fn highlight_extension(ext: &str) -> Option<&'static str> {
+    let registry = registry().read().ok()?;
+    registry.highlights.get(&ext.to_lowercase()).copied()
+}
@@ -0,0 +1,66 @@
+use crate::settings::CounterpartPair;
+use std::path::{Path, PathBuf};
+
+/// Placeholder for the name two related files share
+const NAME: &str = "{}";
+
+/// A file related to another one, and the text to create it with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Counterpart {
+    pub path: PathBuf,
+    pub template: String,
+}
+
+/// Files `path` is paired with, most specific pattern first: `foo_test.rs`
+/// pairs with `foo.rs` through `{}_test.rs` before it pairs with
+/// `foo_test_test.rs` through `{}.rs`
+pub fn counterparts(path: &Path, pairs: &[CounterpartPair]) -> Vec<Counterpart> {
+    let text = path.to_string_lossy().replace('\\', "/");
+    let mut found: Vec<(usize, Counterpart)> = Vec::new();
+    for pair in pairs {
+        let directions = [
+            (&pair.first, &pair.second, pair.template.as_str()),
+            (&pair.second, &pair.first, ""),
+        ];
+        for (from, to, template) in directions {
+            let Some((prefix, name)) = match_pattern(&text, from) else {
+                continue;
+            };
+            let path = PathBuf::from(format!("{prefix}{}", to.replace(NAME, name)));
+            if found.iter().all(|(_, c)| c.path != path) {
+                found.push((
+                    from.len() - NAME.len(),
+                    Counterpart {
+                        path,
+                        template: template.replace(NAME, name),
+                    },
+                ));
+            }
+        }
+    }
+    found.sort_by_key(|(specificity, _)| std::cmp::Reverse(*specificity));
+    found
+        .into_iter()
+        .map(|(_, counterpart)| counterpart)
+        .collect()
+}
+
+/// The part of `path` before `pattern` and the name in place of its `{}`,
+/// when the end of `path` matches it. The match starts at a folder boundary
+/// and the name has no slashes.
+fn match_pattern<'a>(path: &'a str, pattern: &str) -> Option<(&'a str, &'a str)> {
+    let (before, after) = pattern.split_once(NAME)?;
+    let rest = path.strip_suffix(after)?;
+    let slash = rest.rfind('/').map_or(0, |i| i + 1);
+    let (folder, file_start) = rest.split_at(slash);
+
+    // Folders of the pattern come before the file name
+    let (pattern_folders, name_prefix) = match before.rfind('/') {
+        Some(i) => before.split_at(i + 1),
+        None => ("", before),
+    };
+    let name = file_start.strip_prefix(name_prefix)?;
+    let prefix = folder.strip_suffix(pattern_folders)?;
+    let at_boundary = prefix.is_empty() || prefix.ends_with('/');
+    (at_boundary && !name.is_empty()).then_some((prefix, name))
+}
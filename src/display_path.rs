@@ -0,0 +1,28 @@
+use std::path::{Path, PathBuf};
+
+/// How to show `path` to the user: relative to the workspace root it's in,
+/// led by the root's folder name when more than one folder is open, and
+/// otherwise with the home directory shortened to `~`
+pub fn display_path(path: &Path, roots: &[PathBuf]) -> String {
+    // The innermost root, for folders opened inside one another
+    let root = roots
+        .iter()
+        .filter(|root| path.starts_with(root))
+        .max_by_key(|root| root.components().count());
+    if let Some(root) = root {
+        let relative = path.strip_prefix(root).unwrap_or(path);
+        return match root.file_name() {
+            Some(name) if roots.len() > 1 => Path::new(name).join(relative),
+            _ => relative.to_path_buf(),
+        }
+        .display()
+        .to_string();
+    }
+    match dirs::home_dir() {
+        Some(home) if path.starts_with(&home) => {
+            let relative = path.strip_prefix(&home).unwrap_or(path);
+            Path::new("~").join(relative).display().to_string()
+        }
+        _ => path.display().to_string(),
+    }
+}
@@ -0,0 +1,49 @@
+/// Variables in a `.env` file: `KEY=value` lines, optionally prefixed with
+/// `export`, with `#` comments and single- or double-quoted values
+pub fn parse(content: &str) -> Vec<(String, String)> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                return None;
+            }
+            let line = line.strip_prefix("export ").unwrap_or(line);
+            let (key, value) = line.split_once('=')?;
+            let key = key.trim();
+            if key.is_empty() || key.contains(char::is_whitespace) {
+                return None;
+            }
+            Some((key.to_string(), parse_value(value.trim())))
+        })
+        .collect()
+}
+
+fn parse_value(value: &str) -> String {
+    if let Some(quoted) = value.strip_prefix('\'') {
+        // Single quotes are taken literally
+        return quoted.split('\'').next().unwrap_or_default().to_string();
+    }
+    if let Some(quoted) = value.strip_prefix('"') {
+        let mut parsed = String::new();
+        let mut chars = quoted.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => break,
+                '\\' => match chars.next() {
+                    Some('n') => parsed.push('\n'),
+                    Some('t') => parsed.push('\t'),
+                    Some(c) => parsed.push(c),
+                    None => {}
+                },
+                c => parsed.push(c),
+            }
+        }
+        return parsed;
+    }
+    // Unquoted values end at a comment
+    match value.find(" #") {
+        Some(comment) => value[..comment].trim_end().to_string(),
+        None => value.to_string(),
+    }
+}
@@ -0,0 +1,199 @@
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16_LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16_BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+/// How a file's text is stored on disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextEncoding {
+    #[default]
+    Utf8,
+    /// UTF-8 starting with a byte order mark
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+    /// ISO 8859-1: one byte per character, up to U+00FF
+    Latin1,
+    ShiftJis,
+}
+
+impl TextEncoding {
+    pub const ALL: [Self; 6] = [
+        Self::Utf8,
+        Self::Utf8Bom,
+        Self::Utf16Le,
+        Self::Utf16Be,
+        Self::Latin1,
+        Self::ShiftJis,
+    ];
+
+    /// Name in the status bar and the encoding pickers
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Utf8 => "UTF-8",
+            Self::Utf8Bom => "UTF-8 with BOM",
+            Self::Utf16Le => "UTF-16 LE",
+            Self::Utf16Be => "UTF-16 BE",
+            Self::Latin1 => "ISO 8859-1",
+            Self::ShiftJis => "Shift JIS",
+        }
+    }
+
+    /// The encoding of a file from its byte order mark; files without one
+    /// are UTF-8 when they are valid UTF-8 and Latin-1 otherwise, which
+    /// reads any bytes
+    pub fn detect(bytes: &[u8]) -> Self {
+        if bytes.starts_with(UTF8_BOM) {
+            Self::Utf8Bom
+        } else if bytes.starts_with(UTF16_LE_BOM) {
+            Self::Utf16Le
+        } else if bytes.starts_with(UTF16_BE_BOM) {
+            Self::Utf16Be
+        } else if std::str::from_utf8(bytes).is_ok() {
+            Self::Utf8
+        } else {
+            Self::Latin1
+        }
+    }
+
+    /// The detected encoding of a file's bytes and its text
+    pub fn read(bytes: &[u8]) -> (Self, String) {
+        let encoding = Self::detect(bytes);
+        match encoding.decode(bytes) {
+            Ok(text) => (encoding, text),
+            // UTF-16 with a stray byte at the end
+            Err(_) => (Self::Latin1, Self::Latin1.decode(bytes).unwrap_or_default()),
+        }
+    }
+
+    /// The text of `bytes`, without a byte order mark of this encoding.
+    /// Bytes that aren't valid in it are an error rather than replaced, so
+    /// saving can't corrupt the file.
+    pub fn decode(self, bytes: &[u8]) -> Result<String, String> {
+        let invalid = || format!("The file is not valid {}", self.label());
+        match self {
+            Self::Utf8 | Self::Utf8Bom => {
+                let bytes = bytes.strip_prefix(UTF8_BOM).unwrap_or(bytes);
+                String::from_utf8(bytes.to_vec()).map_err(|_| invalid())
+            }
+            Self::Utf16Le | Self::Utf16Be => {
+                let (encoding, bom) = match self {
+                    Self::Utf16Le => (encoding_rs::UTF_16LE, UTF16_LE_BOM),
+                    _ => (encoding_rs::UTF_16BE, UTF16_BE_BOM),
+                };
+                let bytes = bytes.strip_prefix(bom).unwrap_or(bytes);
+                encoding
+                    .decode_without_bom_handling_and_without_replacement(bytes)
+                    .map(String::from)
+                    .ok_or_else(invalid)
+            }
+            Self::Latin1 => Ok(bytes.iter().map(|&b| char::from(b)).collect()),
+            Self::ShiftJis => encoding_rs::SHIFT_JIS
+                .decode_without_bom_handling_and_without_replacement(bytes)
+                .map(String::from)
+                .ok_or_else(invalid),
+        }
+    }
+
+    /// `text` as bytes in this encoding, with the byte order mark for UTF-8
+    /// with BOM and UTF-16. Characters the encoding has no bytes for are an
+    /// error naming the first of them.
+    pub fn encode(self, text: &str) -> Result<Vec<u8>, String> {
+        let unsupported =
+            |c: char| format!("{} can't store '{c}' (U+{:04X})", self.label(), c as u32);
+        match self {
+            Self::Utf8 => Ok(text.as_bytes().to_vec()),
+            Self::Utf8Bom => Ok([UTF8_BOM, text.as_bytes()].concat()),
+            Self::Utf16Le => Ok(UTF16_LE_BOM
+                .iter()
+                .copied()
+                .chain(text.encode_utf16().flat_map(u16::to_le_bytes))
+                .collect()),
+            Self::Utf16Be => Ok(UTF16_BE_BOM
+                .iter()
+                .copied()
+                .chain(text.encode_utf16().flat_map(u16::to_be_bytes))
+                .collect()),
+            Self::Latin1 => text
+                .chars()
+                .map(|c| u8::try_from(c).map_err(|_| unsupported(c)))
+                .collect(),
+            Self::ShiftJis => {
+                let (bytes, _, had_errors) = encoding_rs::SHIFT_JIS.encode(text);
+                if !had_errors {
+                    return Ok(bytes.into_owned());
+                }
+                // Find the character the encoder replaced
+                let c = text
+                    .chars()
+                    .find(|c| encoding_rs::SHIFT_JIS.encode(&c.to_string()).2)
+                    .unwrap_or(char::REPLACEMENT_CHARACTER);
+                Err(unsupported(c))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_byte_order_marks() {
+        assert_eq!(
+            TextEncoding::detect(b"\xEF\xBB\xBFhi"),
+            TextEncoding::Utf8Bom
+        );
+        assert_eq!(TextEncoding::detect(b"\xFF\xFEh\0"), TextEncoding::Utf16Le);
+        assert_eq!(TextEncoding::detect(b"\xFE\xFF\0h"), TextEncoding::Utf16Be);
+    }
+
+    #[test]
+    fn files_without_a_mark_are_utf8_or_latin1() {
+        assert_eq!(TextEncoding::detect(b""), TextEncoding::Utf8);
+        assert_eq!(TextEncoding::detect("café".as_bytes()), TextEncoding::Utf8);
+        assert_eq!(TextEncoding::detect(b"caf\xE9"), TextEncoding::Latin1);
+        assert_eq!(
+            TextEncoding::read(b"caf\xE9"),
+            (TextEncoding::Latin1, "café".to_string())
+        );
+    }
+
+    #[test]
+    fn utf16_with_a_stray_byte_reads_as_latin1() {
+        let (encoding, text) = TextEncoding::read(b"\xFF\xFEh\0i");
+        assert_eq!(encoding, TextEncoding::Latin1);
+        assert_eq!(text, "ÿþh\0i");
+    }
+
+    #[test]
+    fn every_encoding_reads_back_what_it_writes() {
+        for encoding in TextEncoding::ALL {
+            let text = if encoding == TextEncoding::ShiftJis {
+                "日本語 text\n"
+            } else {
+                "café text\n"
+            };
+            let bytes = encoding.encode(text).unwrap();
+            assert_eq!(encoding.decode(&bytes).as_deref(), Ok(text), "{encoding:?}");
+            if encoding != TextEncoding::ShiftJis {
+                assert_eq!(TextEncoding::read(&bytes), (encoding, text.to_string()));
+            }
+        }
+    }
+
+    #[test]
+    fn reports_what_an_encoding_cannot_hold() {
+        assert_eq!(
+            TextEncoding::Latin1.encode("a€"),
+            Err("ISO 8859-1 can't store '€' (U+20AC)".to_string())
+        );
+        assert_eq!(
+            TextEncoding::ShiftJis.encode("aé"),
+            Err("Shift JIS can't store 'é' (U+00E9)".to_string())
+        );
+        assert_eq!(
+            TextEncoding::Utf8.decode(b"caf\xE9"),
+            Err("The file is not valid UTF-8".to_string())
+        );
+    }
+}
@@ -0,0 +1,84 @@
+use std::path::{Path, PathBuf};
+
+/// Files and folders of a workspace left out of the Explorer, search and the
+/// symbol index. A pattern ending in `/` only matches folders; one with a `/`
+/// elsewhere matches the path from the workspace folder, and any other the
+/// file name. `*` and `?` stay within a name, `**` spans folders.
+#[derive(Debug, Clone, Default)]
+pub struct Exclusions {
+    root: PathBuf,
+    patterns: Vec<String>,
+}
+
+impl Exclusions {
+    pub fn new(root: &Path, patterns: &[String]) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            patterns: patterns
+                .iter()
+                .map(|pattern| pattern.trim().replace('\\', "/"))
+                .filter(|pattern| !pattern.is_empty())
+                .collect(),
+        }
+    }
+
+    pub fn is_excluded(&self, path: &Path, is_dir: bool) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return false;
+        };
+        let relative = relative.to_string_lossy().replace('\\', "/");
+        let name = relative.rsplit('/').next().unwrap_or_default();
+        if name.is_empty() {
+            return false;
+        }
+        self.patterns.iter().any(|pattern| {
+            let (pattern, dirs_only) = match pattern.strip_suffix('/') {
+                Some(pattern) => (pattern, true),
+                None => (pattern.as_str(), false),
+            };
+            if dirs_only && !is_dir {
+                return false;
+            }
+            match pattern.strip_prefix('/') {
+                Some(pattern) => glob_match(pattern, &relative),
+                None if pattern.contains('/') => glob_match(pattern, &relative),
+                None => glob_match(pattern, name),
+            }
+        })
+    }
+}
+
+/// Whether all of `text` matches `pattern`
+fn glob_match(pattern: &str, text: &str) -> bool {
+    if let Some(rest) = pattern.strip_prefix("**") {
+        let rest = rest.strip_prefix('/').unwrap_or(rest);
+        // Any number of folders, including none
+        return text
+            .char_indices()
+            .filter(|&(i, c)| i == 0 || c == '/')
+            .any(|(i, _)| glob_match(rest, text[i..].trim_start_matches('/')))
+            || glob_match(rest, "");
+    }
+    let mut pattern_chars = pattern.chars();
+    let Some(p) = pattern_chars.next() else {
+        return text.is_empty();
+    };
+    let rest = pattern_chars.as_str();
+    match p {
+        '*' => text
+            .char_indices()
+            .take_while(|&(_, c)| c != '/')
+            .map(|(i, _)| i)
+            .chain([text.find('/').unwrap_or(text.len())])
+            .any(|i| glob_match(rest, &text[i..])),
+        _ => {
+            let mut text_chars = text.chars();
+            match text_chars.next() {
+                Some(c) if c == p || (p == '?' && c != '/') => {
+                    glob_match(rest, text_chars.as_str())
+                }
+                _ => false,
+            }
+        }
+    }
+}
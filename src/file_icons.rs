@@ -1,4 +1,9 @@
+use crate::contributions;
+
 pub fn get_icon(filename: &str) -> &'static str {
+    if let Some(icon) = contributions::icon(filename) {
+        return icon;
+    }
     let lower = filename.to_lowercase();
     match () {
         // Rust
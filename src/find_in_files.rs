@@ -0,0 +1,91 @@
+use crate::exclude::Exclusions;
+use crate::symbol_index::MAX_FILE_BYTES;
+use std::path::{Path, PathBuf};
+
+/// Char ranges of the non-overlapping occurrences of `search` in `text`
+pub fn find_matches(text: &str, search: &str, case_sensitive: bool) -> Vec<(usize, usize)> {
+    let mut matches = Vec::new();
+    if search.is_empty() {
+        return matches;
+    }
+    let (text, search) = if case_sensitive {
+        (text.to_string(), search.to_string())
+    } else {
+        (text.to_lowercase(), search.to_lowercase())
+    };
+
+    let search_len = search.chars().count();
+    let mut start = 0;
+    let mut char_start = 0;
+    while let Some(pos) = text[start..].find(&search) {
+        let abs_pos = start + pos;
+        // Convert byte position to char position, counting only since the last match
+        char_start += text[start..abs_pos].chars().count();
+        let char_end = char_start + search_len;
+        matches.push((char_start, char_end));
+        start = abs_pos + search.len();
+        char_start = char_end;
+    }
+    matches
+}
+
+/// `text` with every occurrence of `search` replaced, and how many there were.
+/// `None` if there were none.
+pub fn replace_all(
+    text: &str,
+    search: &str,
+    case_sensitive: bool,
+    replacement: &str,
+) -> Option<(String, usize)> {
+    let matches = find_matches(text, search, case_sensitive);
+    if matches.is_empty() {
+        return None;
+    }
+    // Byte index of each char, and of the end
+    let bytes: Vec<usize> = text
+        .char_indices()
+        .map(|(i, _)| i)
+        .chain([text.len()])
+        .collect();
+    // Lowercasing can change the number of chars, so stay in bounds
+    let byte = |i: usize| bytes[i.min(bytes.len() - 1)];
+    let mut result = String::with_capacity(text.len());
+    let mut last = 0;
+    for &(start, end) in &matches {
+        let start = byte(start).max(last);
+        result.push_str(&text[last..start]);
+        result.push_str(replacement);
+        last = byte(end).max(start);
+    }
+    result.push_str(&text[last..]);
+    Some((result, matches.len()))
+}
+
+/// Text files under `root` that a workspace-wide replace looks at. Hidden
+/// folders, excluded files and large files are left out.
+pub fn workspace_files(root: &Path, exclusions: &Exclusions) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let Ok(entries) = std::fs::read_dir(root) else {
+        return files;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if exclusions.is_excluded(&path, file_type.is_dir()) {
+            continue;
+        }
+        if file_type.is_dir() {
+            if !name.starts_with('.') {
+                files.extend(workspace_files(&path, exclusions));
+            }
+        } else if entry.metadata().is_ok_and(|m| m.len() <= MAX_FILE_BYTES) {
+            files.push(path);
+        }
+    }
+    files.sort();
+    files
+}
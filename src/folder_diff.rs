@@ -0,0 +1,240 @@
+use similar::{ChangeTag, TextDiff};
+use std::collections::BTreeSet;
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+
+/// Longest a line diff may take before it settles for a coarser result
+const DIFF_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How an entry differs between the left and the right folder
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffStatus {
+    Same,
+    Changed,
+    /// Only in the right folder
+    Added,
+    /// Only in the left folder
+    Removed,
+}
+
+/// Entry of the merged tree of two compared folders
+#[derive(Debug, Clone)]
+pub struct DiffNode {
+    pub name: String,
+    /// Path relative to both folders
+    pub path: PathBuf,
+    pub is_dir: bool,
+    pub status: DiffStatus,
+    pub children: Vec<DiffNode>,
+}
+
+/// Number of files per status in a compared tree
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DiffSummary {
+    pub added: usize,
+    pub removed: usize,
+    pub changed: usize,
+}
+
+impl DiffSummary {
+    pub fn of(nodes: &[DiffNode]) -> Self {
+        let mut summary = Self::default();
+        summary.count(nodes);
+        summary
+    }
+
+    fn count(&mut self, nodes: &[DiffNode]) {
+        for node in nodes {
+            if node.is_dir {
+                self.count(&node.children);
+                continue;
+            }
+            match node.status {
+                DiffStatus::Same => {}
+                DiffStatus::Changed => self.changed += 1,
+                DiffStatus::Added => self.added += 1,
+                DiffStatus::Removed => self.removed += 1,
+            }
+        }
+    }
+}
+
+/// Compare two folders on a worker thread; the merged tree arrives on the
+/// returned channel and `ctx` is repainted then
+pub fn compare_in_background(
+    left: PathBuf,
+    right: PathBuf,
+    ctx: egui::Context,
+) -> Receiver<Vec<DiffNode>> {
+    let (tx, rx) = mpsc::channel();
+    let spawned = std::thread::Builder::new()
+        .name("folder-diff".to_string())
+        .spawn(move || {
+            let _ = tx.send(compare(&left, &right));
+            ctx.request_repaint();
+        });
+    if spawned.is_err() {
+        // The channel closes without a result, which reads as an empty comparison
+        return mpsc::channel().1;
+    }
+    rx
+}
+
+/// Merge two folders into one tree, sorted like the explorer
+pub fn compare(left: &Path, right: &Path) -> Vec<DiffNode> {
+    compare_dirs(Some(left), Some(right), Path::new(""))
+}
+
+/// Compare the entries of two directories, either of which may not exist
+fn compare_dirs(left: Option<&Path>, right: Option<&Path>, relative: &Path) -> Vec<DiffNode> {
+    let mut names = BTreeSet::new();
+    for dir in [left, right].into_iter().flatten() {
+        names.extend(entry_names(dir));
+    }
+
+    let mut nodes: Vec<DiffNode> = names
+        .into_iter()
+        .filter_map(|name| {
+            let left_path = left.map(|dir| dir.join(&name));
+            let right_path = right.map(|dir| dir.join(&name));
+            let left_meta = left_path.as_ref().and_then(|p| fs::metadata(p).ok());
+            let right_meta = right_path.as_ref().and_then(|p| fs::metadata(p).ok());
+            let path = relative.join(&name);
+
+            let (is_dir, status, children) = match (&left_meta, &right_meta) {
+                (Some(l), Some(r)) if l.is_dir() && r.is_dir() => {
+                    let children = compare_dirs(left_path.as_deref(), right_path.as_deref(), &path);
+                    let status = if children.iter().all(|c| c.status == DiffStatus::Same) {
+                        DiffStatus::Same
+                    } else {
+                        DiffStatus::Changed
+                    };
+                    (true, status, children)
+                }
+                (Some(l), None) if l.is_dir() => (
+                    true,
+                    DiffStatus::Removed,
+                    compare_dirs(left_path.as_deref(), None, &path),
+                ),
+                (None, Some(r)) if r.is_dir() => (
+                    true,
+                    DiffStatus::Added,
+                    compare_dirs(None, right_path.as_deref(), &path),
+                ),
+                (Some(_), None) => (false, DiffStatus::Removed, Vec::new()),
+                (None, Some(_)) => (false, DiffStatus::Added, Vec::new()),
+                (Some(l), Some(r)) => {
+                    let same = !l.is_dir()
+                        && !r.is_dir()
+                        && l.len() == r.len()
+                        && files_equal(left_path.as_deref()?, right_path.as_deref()?);
+                    let status = if same {
+                        DiffStatus::Same
+                    } else {
+                        DiffStatus::Changed
+                    };
+                    (false, status, Vec::new())
+                }
+                // Broken symlinks
+                (None, None) => return None,
+            };
+
+            Some(DiffNode {
+                name: name.to_string_lossy().into_owned(),
+                path,
+                is_dir,
+                status,
+                children,
+            })
+        })
+        .collect();
+
+    // Directories first, then alphabetically by name
+    nodes.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+    nodes
+}
+
+fn entry_names(dir: &Path) -> Vec<OsString> {
+    fs::read_dir(dir)
+        .map(|entries| entries.flatten().map(|entry| entry.file_name()).collect())
+        .unwrap_or_default()
+}
+
+/// Whether two files of the same size have the same content
+fn files_equal(left: &Path, right: &Path) -> bool {
+    let (Ok(mut left), Ok(mut right)) = (File::open(left), File::open(right)) else {
+        return false;
+    };
+    let mut left_buf = vec![0; 64 * 1024];
+    let mut right_buf = vec![0; 64 * 1024];
+    loop {
+        let Ok(n) = left.read(&mut left_buf) else {
+            return false;
+        };
+        if n == 0 {
+            return true;
+        }
+        if right.read_exact(&mut right_buf[..n]).is_err() || left_buf[..n] != right_buf[..n] {
+            return false;
+        }
+    }
+}
+
+/// Kind of a line in a file diff
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineChange {
+    Equal,
+    Insert,
+    Delete,
+}
+
+/// Line of a unified file diff
+#[derive(Debug, Clone)]
+pub struct DiffLine {
+    pub change: LineChange,
+    /// Zero-based line numbers in the old and the new file
+    pub old_line: Option<usize>,
+    pub new_line: Option<usize>,
+    /// Text without the line break
+    pub text: String,
+}
+
+/// Unified line diff from `old` to `new`
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let diff = TextDiff::configure()
+        .timeout(DIFF_TIMEOUT)
+        .diff_lines(old, new);
+    diff.iter_all_changes()
+        .map(|change| DiffLine {
+            change: match change.tag() {
+                ChangeTag::Equal => LineChange::Equal,
+                ChangeTag::Insert => LineChange::Insert,
+                ChangeTag::Delete => LineChange::Delete,
+            },
+            old_line: change.old_index(),
+            new_line: change.new_index(),
+            text: change.value().trim_end_matches(['\n', '\r']).to_string(),
+        })
+        .collect()
+}
+
+/// Diff a file between the two folders. A missing file diffs as empty;
+/// binary files can't be diffed line by line.
+pub fn diff_files(left: &Path, right: &Path) -> Result<Vec<DiffLine>, String> {
+    let read = |path: &Path| -> Result<String, String> {
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(format!("Failed to read {}: {e}", path.display())),
+        };
+        if bytes.contains(&0) {
+            return Err("Binary files differ".to_string());
+        }
+        String::from_utf8(bytes).map_err(|_| "Binary files differ".to_string())
+    };
+    Ok(diff_lines(&read(left)?, &read(right)?))
+}
@@ -0,0 +1,325 @@
+use crate::settings::ForgeSettings;
+use serde_json::Value;
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+
+/// Hosting service of a repository's remote
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Forge {
+    GitHub,
+    GitLab,
+}
+
+/// Repository on GitHub or GitLab, from a remote URL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Repository {
+    pub forge: Forge,
+    pub host: String,
+    /// Scheme, host and port the API is at, e.g. `https://git.example.com:8443`
+    origin: String,
+    /// e.g. `owner/name`, or `group/subgroup/name` on GitLab
+    pub path: String,
+}
+
+impl Repository {
+    /// Repository a remote URL points to: `https://host/owner/name.git`,
+    /// `git@host:owner/name.git` or `ssh://git@host:22/owner/name`. `None`
+    /// for hosts other than github.com, gitlab.com and the self-hosted servers
+    /// in `settings`, since the access token is sent to the host. The API is
+    /// asked at the port of an http(s) URL; other URLs' ports are for git.
+    pub fn from_remote(url: &str, settings: &ForgeSettings) -> Option<Self> {
+        let url = url.trim();
+        let (host, origin, path) = match url.split_once("://") {
+            Some((scheme, rest)) => {
+                let (authority, path) = rest.split_once('/')?;
+                let authority = authority.rsplit('@').next()?;
+                let host = authority.split(':').next()?;
+                let origin = match scheme.to_ascii_lowercase().as_str() {
+                    scheme @ ("http" | "https") => format!("{scheme}://{authority}"),
+                    _ => format!("https://{host}"),
+                };
+                (host, origin, path)
+            }
+            None => {
+                let (authority, path) = url.split_once(':')?;
+                let host = authority.rsplit('@').next()?;
+                (host, format!("https://{host}"), path)
+            }
+        };
+        let path = path.trim_matches('/');
+        let path = path.strip_suffix(".git").unwrap_or(path);
+        if host.is_empty() || !path.contains('/') {
+            return None;
+        }
+        let listed = |hosts: &[String]| hosts.iter().any(|h| h.eq_ignore_ascii_case(host));
+        let forge = if host.eq_ignore_ascii_case("github.com") || listed(&settings.github_hosts) {
+            Forge::GitHub
+        } else if host.eq_ignore_ascii_case("gitlab.com") || listed(&settings.gitlab_hosts) {
+            Forge::GitLab
+        } else {
+            return None;
+        };
+        Some(Self {
+            forge,
+            host: host.to_string(),
+            origin,
+            path: path.to_string(),
+        })
+    }
+
+    /// "Pull Requests" or "Merge Requests"
+    pub fn requests_label(&self) -> &'static str {
+        match self.forge {
+            Forge::GitHub => "Pull Requests",
+            Forge::GitLab => "Merge Requests",
+        }
+    }
+
+    /// Access token for the repository's host: the one `settings` has for
+    /// it, or for github.com and gitlab.com `GITHUB_TOKEN` and
+    /// `GITLAB_TOKEN`. Self-hosted servers only get a token set for them.
+    pub fn token(&self, settings: &ForgeSettings) -> Option<String> {
+        let configured = settings
+            .tokens
+            .iter()
+            .find(|(host, token)| host.eq_ignore_ascii_case(&self.host) && !token.is_empty());
+        if let Some((_, token)) = configured {
+            return Some(token.clone());
+        }
+        let variable = match self.forge {
+            Forge::GitHub if self.host.eq_ignore_ascii_case("github.com") => "GITHUB_TOKEN",
+            Forge::GitLab if self.host.eq_ignore_ascii_case("gitlab.com") => "GITLAB_TOKEN",
+            _ => return None,
+        };
+        std::env::var(variable)
+            .ok()
+            .filter(|token| !token.is_empty())
+    }
+
+    /// Ref the remote keeps a pull request's commits under, and the local
+    /// branch to check it out as
+    pub fn checkout_refs(&self, number: u64) -> (String, String) {
+        match self.forge {
+            Forge::GitHub => (format!("pull/{number}/head"), format!("pr-{number}")),
+            Forge::GitLab => (
+                format!("merge-requests/{number}/head"),
+                format!("mr-{number}"),
+            ),
+        }
+    }
+
+    fn api_base(&self) -> String {
+        match self.forge {
+            Forge::GitHub if self.host.eq_ignore_ascii_case("github.com") => {
+                "https://api.github.com".to_string()
+            }
+            Forge::GitHub => format!("{}/api/v3", self.origin),
+            Forge::GitLab => format!("{}/api/v4", self.origin),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ItemKind {
+    /// Pull request on GitHub, merge request on GitLab
+    Request,
+    Issue,
+}
+
+/// Open pull request or issue
+#[derive(Debug, Clone)]
+pub struct Item {
+    pub kind: ItemKind,
+    /// Number shown as `#12` (`!12` for GitLab merge requests)
+    pub number: u64,
+    pub title: String,
+    pub author: String,
+    pub url: String,
+    /// Branch the pull request comes from
+    pub branch: Option<String>,
+    /// Description, in Markdown
+    pub body: String,
+}
+
+impl Item {
+    /// e.g. "#12"
+    pub fn reference(&self, forge: Forge) -> String {
+        match (forge, self.kind) {
+            (Forge::GitLab, ItemKind::Request) => format!("!{}", self.number),
+            _ => format!("#{}", self.number),
+        }
+    }
+}
+
+/// Fetch the open pull requests and issues on a worker thread; the result
+/// arrives on the returned channel and `ctx` is repainted then
+pub fn load_in_background(
+    repository: Repository,
+    token: Option<String>,
+    ctx: egui::Context,
+) -> Receiver<Result<Vec<Item>, String>> {
+    let (tx, rx) = mpsc::channel();
+    let spawned = std::thread::Builder::new()
+        .name("forge".to_string())
+        .spawn(move || {
+            let _ = tx.send(load(&repository, token.as_deref()));
+            ctx.request_repaint();
+        });
+    if spawned.is_err() {
+        // The channel closes without a result, which reads as a failed load
+        return mpsc::channel().1;
+    }
+    rx
+}
+
+fn load(repository: &Repository, token: Option<&str>) -> Result<Vec<Item>, String> {
+    let client = reqwest::blocking::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .user_agent(concat!("rust_code_editor/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .map_err(|e| e.to_string())?;
+    let get = |endpoint: &str| -> Result<Vec<Value>, String> {
+        let mut request = client.get(format!("{}/{endpoint}", repository.api_base()));
+        if let Some(token) = token {
+            request = match repository.forge {
+                Forge::GitHub => request.bearer_auth(token),
+                Forge::GitLab => request.header("PRIVATE-TOKEN", token),
+            };
+        }
+        let response = request.send().map_err(|e| e.to_string())?;
+        let status = response.status();
+        let body = response.text().map_err(|e| e.to_string())?;
+        let body: Value = serde_json::from_str(&body).map_err(|e| e.to_string())?;
+        if !status.is_success() {
+            let message = body["message"].as_str().map_or_else(
+                || status.canonical_reason().unwrap_or_default().to_string(),
+                str::to_string,
+            );
+            return Err(format!("{} {message}", status.as_u16()));
+        }
+        match body {
+            Value::Array(values) => Ok(values),
+            _ => Err("Unexpected response".to_string()),
+        }
+    };
+    let text = |value: &Value| value.as_str().unwrap_or_default().to_string();
+
+    let mut items = Vec::new();
+    match repository.forge {
+        Forge::GitHub => {
+            let repo = format!("repos/{}", repository.path);
+            for pull in get(&format!("{repo}/pulls?state=open&per_page=50"))? {
+                items.push(Item {
+                    kind: ItemKind::Request,
+                    number: pull["number"].as_u64().unwrap_or_default(),
+                    title: text(&pull["title"]),
+                    author: text(&pull["user"]["login"]),
+                    url: text(&pull["html_url"]),
+                    branch: pull["head"]["ref"].as_str().map(str::to_string),
+                    body: text(&pull["body"]),
+                });
+            }
+            // The issues endpoint lists pull requests too
+            for issue in get(&format!("{repo}/issues?state=open&per_page=50"))? {
+                if issue.get("pull_request").is_some() {
+                    continue;
+                }
+                items.push(Item {
+                    kind: ItemKind::Issue,
+                    number: issue["number"].as_u64().unwrap_or_default(),
+                    title: text(&issue["title"]),
+                    author: text(&issue["user"]["login"]),
+                    url: text(&issue["html_url"]),
+                    branch: None,
+                    body: text(&issue["body"]),
+                });
+            }
+        }
+        Forge::GitLab => {
+            let project = format!("projects/{}", repository.path.replace('/', "%2F"));
+            for request in get(&format!(
+                "{project}/merge_requests?state=opened&per_page=50"
+            ))? {
+                items.push(Item {
+                    kind: ItemKind::Request,
+                    number: request["iid"].as_u64().unwrap_or_default(),
+                    title: text(&request["title"]),
+                    author: text(&request["author"]["username"]),
+                    url: text(&request["web_url"]),
+                    branch: request["source_branch"].as_str().map(str::to_string),
+                    body: text(&request["description"]),
+                });
+            }
+            for issue in get(&format!("{project}/issues?state=opened&per_page=50"))? {
+                items.push(Item {
+                    kind: ItemKind::Issue,
+                    number: issue["iid"].as_u64().unwrap_or_default(),
+                    title: text(&issue["title"]),
+                    author: text(&issue["author"]["username"]),
+                    url: text(&issue["web_url"]),
+                    branch: None,
+                    body: text(&issue["description"]),
+                });
+            }
+        }
+    }
+    Ok(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> ForgeSettings {
+        ForgeSettings {
+            gitlab_hosts: vec!["git.example.com".to_string()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn reads_https_and_ssh_remotes() {
+        for url in [
+            "https://github.com/owner/name.git",
+            "git@github.com:owner/name.git",
+            "ssh://git@github.com:22/owner/name",
+        ] {
+            let repository = Repository::from_remote(url, &settings()).unwrap();
+            assert_eq!(repository.forge, Forge::GitHub);
+            assert_eq!(repository.path, "owner/name");
+            assert_eq!(repository.api_base(), "https://api.github.com");
+        }
+    }
+
+    #[test]
+    fn keeps_the_port_of_an_https_remote() {
+        let url = "https://git.example.com:8443/group/sub/name.git";
+        let repository = Repository::from_remote(url, &settings()).unwrap();
+        assert_eq!(repository.forge, Forge::GitLab);
+        assert_eq!(repository.path, "group/sub/name");
+        assert_eq!(repository.api_base(), "https://git.example.com:8443/api/v4");
+
+        // The port of an ssh URL is the ssh server's
+        let url = "ssh://git@git.example.com:2222/group/name.git";
+        let repository = Repository::from_remote(url, &settings()).unwrap();
+        assert_eq!(repository.api_base(), "https://git.example.com/api/v4");
+    }
+
+    #[test]
+    fn refuses_hosts_not_listed() {
+        let url = "https://example.org/owner/name.git";
+        assert_eq!(Repository::from_remote(url, &settings()), None);
+    }
+
+    #[test]
+    fn tokens_only_go_to_their_host() {
+        let mut settings = settings();
+        settings
+            .tokens
+            .insert("GitHub.com".to_string(), "github-token".to_string());
+        let github = Repository::from_remote("git@github.com:o/n.git", &settings).unwrap();
+        assert_eq!(github.token(&settings).as_deref(), Some("github-token"));
+        let gitlab = Repository::from_remote("git@git.example.com:o/n.git", &settings).unwrap();
+        assert_eq!(gitlab.token(&settings), None);
+    }
+}
@@ -0,0 +1,87 @@
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// How long a formatter may run before the save goes ahead without it
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Pipe `text` through a formatter command (program and arguments) that reads
+/// the source on stdin and writes the formatted source to stdout
+pub fn format(
+    command: &[String],
+    text: &str,
+    dir: Option<&Path>,
+    env: &BTreeMap<String, String>,
+) -> Result<String, String> {
+    let Some((program, args)) = command.split_first() else {
+        return Err("Empty formatter command".to_string());
+    };
+    let mut process = Command::new(program);
+    process
+        .args(args)
+        .envs(env)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if let Some(dir) = dir {
+        process.current_dir(dir);
+    }
+    let mut child = process
+        .spawn()
+        .map_err(|e| format!("Failed to run {program}: {e}"))?;
+
+    // Feed stdin and drain the output on their own threads so a formatter
+    // that fills a pipe can't block the other side
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let input = text.to_string();
+    let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let mut stdout = child.stdout.take().expect("stdout is piped");
+    let reader = std::thread::spawn(move || {
+        let mut output = String::new();
+        stdout.read_to_string(&mut output).map(|_| output)
+    });
+    let mut stderr = child.stderr.take().expect("stderr is piped");
+    let error_reader = std::thread::spawn(move || {
+        let mut output = String::new();
+        let _ = stderr.read_to_string(&mut output);
+        output
+    });
+
+    let started = Instant::now();
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if started.elapsed() < TIMEOUT => {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            Ok(None) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("{program} timed out after {}s", TIMEOUT.as_secs()));
+            }
+            Err(e) => return Err(format!("Failed to wait for {program}: {e}")),
+        }
+    };
+
+    let _ = writer.join();
+    let output = reader
+        .join()
+        .map_err(|_| format!("Failed to read the output of {program}"))?
+        .map_err(|e| format!("Failed to read the output of {program}: {e}"))?;
+    let errors = error_reader.join().unwrap_or_default();
+    if !status.success() {
+        let errors = errors.trim();
+        return Err(if errors.is_empty() {
+            format!("{program} exited with {status}")
+        } else {
+            format!("{program} failed: {errors}")
+        });
+    }
+    // Some formatters only rewrite files in place
+    if output.is_empty() && !text.trim().is_empty() {
+        return Err(format!("{program} printed no formatted source"));
+    }
+    Ok(output)
+}
@@ -1,5 +1,6 @@
+use crate::exclude::Exclusions;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone)]
 pub struct FileNode {
@@ -9,10 +10,12 @@ pub struct FileNode {
 }
 
 impl FileNode {
-    pub fn new(path: PathBuf) -> Self {
+    /// The tree under `path`, without excluded entries, and without dotfiles
+    /// unless `show_hidden`
+    pub fn new(path: PathBuf, exclusions: &Exclusions, show_hidden: bool) -> Self {
         let is_dir = path.is_dir();
         let children = if is_dir {
-            Self::load_children(&path)
+            Self::load_children(&path, exclusions, show_hidden)
         } else {
             vec![]
         };
@@ -23,14 +26,19 @@ impl FileNode {
         }
     }
 
-    fn load_children(path: &PathBuf) -> Vec<FileNode> {
+    fn load_children(path: &PathBuf, exclusions: &Exclusions, show_hidden: bool) -> Vec<FileNode> {
         let Ok(entries) = fs::read_dir(path) else {
             return vec![];
         };
 
         let mut children: Vec<FileNode> = entries
             .flatten()
-            .map(|entry| FileNode::new(entry.path()))
+            .filter(|entry| show_hidden || !entry.file_name().to_string_lossy().starts_with('.'))
+            .filter(|entry| {
+                let is_dir = entry.file_type().is_ok_and(|t| t.is_dir());
+                !exclusions.is_excluded(&entry.path(), is_dir)
+            })
+            .map(|entry| FileNode::new(entry.path(), exclusions, show_hidden))
             .collect();
 
         // Sort: directories first, then alphabetically by name
@@ -43,6 +51,14 @@ impl FileNode {
         children
     }
 
+    /// Files in the tree, folder by folder
+    pub fn files(&self) -> Vec<&Path> {
+        if !self.is_dir {
+            return vec![&self.path];
+        }
+        self.children.iter().flat_map(FileNode::files).collect()
+    }
+
     pub fn name(&self) -> String {
         self.path
             .file_name()
@@ -0,0 +1,343 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+
+/// Longest commit subject that reads well in one-line logs
+pub const SUBJECT_LIMIT: usize = 50;
+/// Longest commit message line before terminals and mail clients wrap it
+pub const LINE_LIMIT: usize = 72;
+
+/// Conventional Commits types and what they are for
+pub const CONVENTIONAL_TYPES: [(&str, &str); 11] = [
+    ("feat", "A new feature"),
+    ("fix", "A bug fix"),
+    ("docs", "Documentation only"),
+    ("style", "Formatting, no change in behavior"),
+    ("refactor", "Neither fixes a bug nor adds a feature"),
+    ("perf", "Improves performance"),
+    ("test", "Adds or corrects tests"),
+    ("build", "Build system or dependencies"),
+    ("ci", "CI configuration"),
+    ("chore", "Other changes that don't touch the source"),
+    ("revert", "Reverts an earlier commit"),
+];
+
+/// Where git saw a deleted file move to: a rename staged with `git mv`, or one
+/// made by the last commit. Untracked files and renames git can't pair with
+/// the old path give `None`.
+pub fn renamed_to(path: &Path) -> Option<PathBuf> {
+    // The file's folder may be gone too if a parent was renamed
+    let existing = path.ancestors().skip(1).find(|dir| dir.is_dir())?;
+    let root = PathBuf::from(git(existing, &["rev-parse", "--show-toplevel"])?.trim());
+    let root = root.canonicalize().ok()?;
+    let inside = path.strip_prefix(existing).ok()?;
+    let relative = existing.canonicalize().ok()?.join(inside);
+    let relative = relative
+        .strip_prefix(&root)
+        .ok()?
+        .to_string_lossy()
+        .replace('\\', "/");
+
+    let staged = git(existing, &["status", "--porcelain", "-z"])
+        .and_then(|status| staged_rename(&status, &relative));
+    let renamed = staged.or_else(|| {
+        let show = git(
+            existing,
+            &["show", "-M", "-z", "--name-status", "--format=", "HEAD"],
+        )?;
+        committed_rename(&show, &relative)
+    })?;
+    Some(root.join(renamed)).filter(|new| new.is_file())
+}
+
+/// New path of `old` among the renames of `git status --porcelain -z`, which
+/// lists them as `R  new\0old\0`
+fn staged_rename(status: &str, old: &str) -> Option<String> {
+    let mut fields = status.split('\0');
+    while let Some(entry) = fields.next() {
+        let (Some(code), Some(new)) = (entry.get(..3), entry.get(3..)) else {
+            continue;
+        };
+        if code.contains(['R', 'C']) {
+            let from = fields.next()?;
+            if code.contains('R') && from == old {
+                return Some(new.to_string());
+            }
+        }
+    }
+    None
+}
+
+/// New path of `old` among the renames of `git show --name-status -z`, which
+/// lists them as `R100\0old\0new\0`
+fn committed_rename(show: &str, old: &str) -> Option<String> {
+    let mut fields = show.split('\0').map(|f| f.trim_start_matches('\n'));
+    while let Some(code) = fields.next() {
+        if code.starts_with(['R', 'C']) {
+            let from = fields.next()?;
+            let to = fields.next()?;
+            if code.starts_with('R') && from == old {
+                return Some(to.to_string());
+            }
+        } else {
+            fields.next()?;
+        }
+    }
+    None
+}
+
+/// Paths of the changes staged in the repository around `dir`, relative to
+/// its root. `None` outside a repository.
+pub fn staged_files(dir: &Path) -> Option<Vec<String>> {
+    let names = git(dir, &["diff", "--cached", "--name-only", "-z"])?;
+    Some(
+        names
+            .split('\0')
+            .filter(|name| !name.is_empty())
+            .map(str::to_string)
+            .collect(),
+    )
+}
+
+/// Untracked paths under `dir` that `.gitignore` files and git's other
+/// excludes leave out, relative to `dir`; ignored folders end in `/`. `None`
+/// outside a repository.
+pub fn ignored_paths(dir: &Path) -> Option<Vec<String>> {
+    let names = git(
+        dir,
+        &[
+            "ls-files",
+            "--others",
+            "--ignored",
+            "--exclude-standard",
+            "--directory",
+            "-z",
+        ],
+    )?;
+    Some(
+        names
+            .split('\0')
+            .filter(|name| !name.is_empty())
+            .map(str::to_string)
+            .collect(),
+    )
+}
+
+/// Conventional Commits types that complete the start of `subject`, while it
+/// has no type yet
+pub fn conventional_completions(subject: &str) -> Vec<(&'static str, &'static str)> {
+    let typed = subject.trim_start();
+    if typed.contains([':', ' ', '(']) {
+        return Vec::new();
+    }
+    CONVENTIONAL_TYPES
+        .into_iter()
+        .filter(|(kind, _)| kind.starts_with(typed) && *kind != typed)
+        .collect()
+}
+
+/// Key that commits in a repository are signed with, from git's config
+#[derive(Debug, Clone)]
+pub struct SigningKey {
+    /// `gpg.format`: `openpgp`, `x509` or `ssh`
+    pub format: String,
+    /// `user.signingkey`: a GPG key id, or an SSH key or its path
+    pub key: String,
+}
+
+impl SigningKey {
+    /// e.g. "SSH key ~/.ssh/id_ed25519.pub"
+    pub fn describe(&self) -> String {
+        let kind = match self.format.as_str() {
+            "ssh" => "SSH key",
+            "x509" => "X.509 certificate",
+            _ => "GPG key",
+        };
+        // Literal SSH keys are long; the comment at the end names them
+        let key = if self.key.starts_with("key::") || self.key.starts_with("ssh-") {
+            self.key.rsplit(' ').next().unwrap_or(&self.key)
+        } else {
+            &self.key
+        };
+        format!("{kind} {key}")
+    }
+}
+
+/// Signing key configured for the repository around `dir`, if there is one
+pub fn signing_key(dir: &Path) -> Option<SigningKey> {
+    let key = git(dir, &["config", "--get", "user.signingkey"])?;
+    let key = key.trim();
+    if key.is_empty() {
+        return None;
+    }
+    let format = git(dir, &["config", "--get", "gpg.format"])
+        .map(|format| format.trim().to_string())
+        .unwrap_or_else(|| "openpgp".to_string());
+    Some(SigningKey {
+        format,
+        key: key.to_string(),
+    })
+}
+
+/// Whether git signs commits in the repository around `dir` by itself,
+/// i.e. `commit.gpgsign` is on
+pub fn signs_commits(dir: &Path) -> bool {
+    git(dir, &["config", "--bool", "--get", "commit.gpgsign"])
+        .is_some_and(|value| value.trim() == "true")
+}
+
+/// Full commit message: the subject, a blank line and the body
+pub fn commit_message(subject: &str, body: &str) -> String {
+    let body = body.trim_end();
+    if body.trim().is_empty() {
+        format!("{}\n", subject.trim())
+    } else {
+        format!("{}\n\n{body}\n", subject.trim())
+    }
+}
+
+/// Commit the staged changes in `dir` on a worker thread, running the commit
+/// hooks unless `verify` is off. `sign` signs the commit with the configured
+/// key or never signs it; `None` leaves it to `commit.gpgsign`. The result
+/// carries git's output, which holds the hooks' output when one of them
+/// rejects the commit, and `ctx` is repainted when it arrives.
+pub fn commit_in_background(
+    dir: PathBuf,
+    message: String,
+    verify: bool,
+    sign: Option<bool>,
+    ctx: egui::Context,
+) -> Receiver<Result<String, String>> {
+    let (tx, rx) = mpsc::channel();
+    let spawned = std::thread::Builder::new()
+        .name("git-commit".to_string())
+        .spawn(move || {
+            let _ = tx.send(commit(&dir, &message, verify, sign));
+            ctx.request_repaint();
+        });
+    if spawned.is_err() {
+        // The channel closes without a result, which reads as a failed commit
+        return mpsc::channel().1;
+    }
+    rx
+}
+
+fn commit(dir: &Path, message: &str, verify: bool, sign: Option<bool>) -> Result<String, String> {
+    let mut command = Command::new("git");
+    command.arg("-C").arg(dir).args(["commit", "--file=-"]);
+    if !verify {
+        command.arg("--no-verify");
+    }
+    match sign {
+        Some(true) => command.arg("--gpg-sign"),
+        Some(false) => command.arg("--no-gpg-sign"),
+        None => &mut command,
+    };
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run git: {e}"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(message.as_bytes());
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("Failed to run git: {e}"))?;
+    let text = combined_output(&output);
+    if output.status.success() {
+        Ok(text)
+    } else if sign != Some(false) && signing_failed(&text) {
+        Err(format!(
+            "Signing the commit failed. Check that the key in user.signingkey is \
+             available to gpg or ssh-agent, or turn off File > Sign Commits.\n\n{text}"
+        ))
+    } else {
+        Err(text)
+    }
+}
+
+/// Whether git's output says the commit couldn't be signed. GPG failures are
+/// reported as such; SSH ones only through the commit object not being
+/// written, since ssh-keygen's own messages vary.
+fn signing_failed(output: &str) -> bool {
+    output.contains("failed to sign") || output.contains("failed to write commit object")
+}
+
+/// URL of a remote of the repository around `dir`
+pub fn remote_url(dir: &Path, remote: &str) -> Option<String> {
+    git(dir, &["remote", "get-url", remote]).map(|url| url.trim().to_string())
+}
+
+/// Fetch `source` (e.g. `pull/12/head`) from `remote`, a remote's name or
+/// URL, into the local branch `branch` and check it out, on a worker thread.
+/// The result carries git's output and `ctx` is repainted when it arrives.
+pub fn checkout_in_background(
+    dir: PathBuf,
+    remote: String,
+    source: String,
+    branch: String,
+    ctx: egui::Context,
+) -> Receiver<Result<String, String>> {
+    let (tx, rx) = mpsc::channel();
+    let spawned = std::thread::Builder::new()
+        .name("git-checkout".to_string())
+        .spawn(move || {
+            let fetch = format!("+{source}:{branch}");
+            let result = run(&dir, &["fetch", &remote, &fetch]).and_then(|fetched| {
+                Ok(format!("{fetched}\n{}", run(&dir, &["checkout", &branch])?))
+            });
+            let _ = tx.send(result.map(|output| output.trim().to_string()));
+            ctx.request_repaint();
+        });
+    if spawned.is_err() {
+        // The channel closes without a result, which reads as a failed checkout
+        return mpsc::channel().1;
+    }
+    rx
+}
+
+/// Output of a git command run in `dir`, or its errors
+fn run(dir: &Path, args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("Failed to run git: {e}"))?;
+    let text = combined_output(&output);
+    if output.status.success() {
+        Ok(text)
+    } else {
+        Err(text)
+    }
+}
+
+/// What a git command printed, errors and progress included
+fn combined_output(output: &std::process::Output) -> String {
+    let text = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    text.trim_end().to_string()
+}
+
+/// Output of a git command run in `dir`, if it succeeded
+fn git(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
@@ -0,0 +1,383 @@
+use crate::contributions;
+use crate::state::outline::{OutlineSymbol, SymbolKind};
+use crate::theme::layout;
+use crate::widgets::LineStyle;
+use egui::Color32;
+use ropey::Rope;
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use syntect::easy::ScopeRegionIterator;
+use syntect::highlighting::{HighlightIterator, HighlightState, Highlighter, Theme, ThemeSet};
+use syntect::parsing::{ParseState, Scope, ScopeStack, SyntaxReference, SyntaxSet};
+
+/// Syntax theme used when the requested one isn't available
+const FALLBACK_THEME: &str = "base16-ocean.dark";
+
+/// Parser and highlighter state at the end of a line
+type LineState = (ParseState, HighlightState);
+
+/// Scopes the grammars give to the names of declarations, and what they declare
+const SYMBOL_SCOPES: &[(&str, SymbolKind)] = &[
+    ("entity.name.function", SymbolKind::Function),
+    ("entity.name.struct", SymbolKind::Struct),
+    ("entity.name.enum", SymbolKind::Enum),
+    ("entity.name.union", SymbolKind::Struct),
+    ("entity.name.trait", SymbolKind::Trait),
+    ("entity.name.impl", SymbolKind::Impl),
+    ("entity.name.class", SymbolKind::Class),
+    ("entity.name.interface", SymbolKind::Interface),
+    ("entity.name.type", SymbolKind::Type),
+    ("entity.name.module", SymbolKind::Module),
+    ("entity.name.namespace", SymbolKind::Module),
+    ("entity.name.constant", SymbolKind::Constant),
+    ("entity.name.section", SymbolKind::Heading),
+];
+
+/// Declarations found on one line: kind, name and char column
+type LineSymbols = Vec<(SymbolKind, String, usize)>;
+
+/// Per-line syntax highlighting results for one buffer.
+///
+/// After an edit only the changed lines are highlighted again, starting from the
+/// state of the previous line and stopping as soon as the state at the end of a
+/// line matches what it was before the edit.
+#[derive(Default)]
+struct HighlightCache {
+    /// Syntax and theme the cached styles were computed with
+    key: Option<(String, String)>,
+    /// Buffer revision the cache is up to date with
+    revision: u64,
+    texts: Vec<String>,
+    styles: Arc<Vec<LineStyle>>,
+    states: Vec<LineState>,
+    symbols: Vec<LineSymbols>,
+}
+
+impl HighlightCache {
+    /// Styles for every line, as of the last `update`
+    fn styles(&self) -> Arc<Vec<LineStyle>> {
+        self.styles.clone()
+    }
+
+    /// Declarations in the buffer, as of the last `update`
+    fn outline(&self) -> Vec<OutlineSymbol> {
+        let mut outline = Vec::new();
+        for (line, (symbols, text)) in self.symbols.iter().zip(&self.texts).enumerate() {
+            for (kind, name, column) in symbols {
+                // Headings nest by level, code by indentation
+                let depth = if *kind == SymbolKind::Heading {
+                    text.chars()
+                        .take_while(|c| *c == '#')
+                        .count()
+                        .saturating_sub(1)
+                } else {
+                    let indent: usize = text
+                        .chars()
+                        .take_while(|c| c.is_whitespace())
+                        .map(|c| if c == '\t' { layout::TAB_SIZE } else { 1 })
+                        .sum();
+                    indent / layout::TAB_SIZE
+                };
+                outline.push(OutlineSymbol {
+                    name: name.clone(),
+                    kind: *kind,
+                    line,
+                    column: *column,
+                    depth,
+                });
+            }
+        }
+        outline
+    }
+
+    fn update(
+        &mut self,
+        buffer: &Rope,
+        revision: u64,
+        syntax: &SyntaxReference,
+        syntax_set: &SyntaxSet,
+        theme: &Theme,
+        theme_name: &str,
+    ) {
+        let key = (syntax.name.clone(), theme_name.to_string());
+        if self.key.as_ref() != Some(&key) {
+            *self = Self {
+                key: Some(key),
+                ..Default::default()
+            };
+        } else if self.revision == revision && !self.texts.is_empty() {
+            return;
+        }
+        self.revision = revision;
+
+        let texts: Vec<String> = buffer.lines().map(String::from).collect();
+
+        // Lines shared with the cached text at the start and at the end
+        let max_common = texts.len().min(self.texts.len());
+        let prefix = texts
+            .iter()
+            .zip(&self.texts)
+            .take_while(|(a, b)| a == b)
+            .count();
+        let suffix = texts
+            .iter()
+            .rev()
+            .zip(self.texts.iter().rev())
+            .take(max_common - prefix)
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        let changed_end = texts.len() - suffix;
+        let old_changed_end = self.texts.len() - suffix;
+        let highlighter = Highlighter::new(theme);
+        let placeholder = || {
+            (
+                ParseState::new(syntax),
+                HighlightState::new(&highlighter, ScopeStack::new()),
+            )
+        };
+
+        // Copies the styles only if the UI still holds the previous result
+        let line_styles = Arc::make_mut(&mut self.styles);
+        line_styles.splice(
+            prefix..old_changed_end,
+            (prefix..changed_end).map(|_| LineStyle::new()),
+        );
+        self.states.splice(
+            prefix..old_changed_end,
+            (prefix..changed_end).map(|_| placeholder()),
+        );
+        self.symbols.splice(
+            prefix..old_changed_end,
+            (prefix..changed_end).map(|_| Vec::new()),
+        );
+        self.texts = texts;
+        let symbol_scopes: Vec<(Scope, SymbolKind)> = SYMBOL_SCOPES
+            .iter()
+            .filter_map(|(name, kind)| Some((Scope::new(name).ok()?, *kind)))
+            .collect();
+
+        let (mut parse_state, mut highlight_state) = match prefix {
+            0 => placeholder(),
+            _ => self.states[prefix - 1].clone(),
+        };
+
+        let lines = self
+            .texts
+            .iter()
+            .zip(line_styles.iter_mut())
+            .zip(self.states.iter_mut())
+            .zip(self.symbols.iter_mut())
+            .enumerate()
+            .skip(prefix);
+        for (line_idx, (((line, line_style), line_state), line_symbols)) in lines {
+            let mut styles = LineStyle::new();
+            if let Ok(ops) = parse_state.parse_line(line, syntax_set) {
+                *line_symbols =
+                    declarations(line, &ops, highlight_state.path.clone(), &symbol_scopes);
+                let mut start = 0;
+                for (style, segment) in
+                    HighlightIterator::new(&mut highlight_state, &ops, line, &highlighter)
+                {
+                    let end = start + segment.len();
+                    let fg = style.foreground;
+                    styles.push((start..end, Color32::from_rgb(fg.r, fg.g, fg.b)));
+                    start = end;
+                }
+            }
+            *line_style = styles;
+
+            // Past the edited lines, the rest of the cache is still valid once the
+            // state carried into the next line is unchanged
+            let state = (parse_state.clone(), highlight_state.clone());
+            let converged = line_idx >= changed_end && *line_state == state;
+            *line_state = state;
+            if converged {
+                break;
+            }
+        }
+    }
+}
+
+/// Names of the declarations on a line, found by the scopes of their tokens.
+/// Tokens next to each other with the same kind form one name; a
+/// heading is named by the whole line.
+fn declarations(
+    line: &str,
+    ops: &[(usize, syntect::parsing::ScopeStackOp)],
+    mut stack: ScopeStack,
+    symbol_scopes: &[(Scope, SymbolKind)],
+) -> LineSymbols {
+    let mut symbols: LineSymbols = Vec::new();
+    let mut start = 0;
+    // Byte range of the last symbol, to extend it with the next token
+    let mut last_end = None;
+    for (text, op) in ScopeRegionIterator::new(ops, line) {
+        if stack.apply(op).is_err() {
+            break;
+        }
+        let region = start..start + text.len();
+        start = region.end;
+        if text.trim().is_empty() {
+            continue;
+        }
+        let kind = stack.as_slice().iter().rev().find_map(|scope| {
+            symbol_scopes
+                .iter()
+                .find(|(prefix, _)| prefix.is_prefix_of(*scope))
+                .map(|(_, kind)| *kind)
+        });
+        let Some(kind) = kind else {
+            last_end = None;
+            continue;
+        };
+        if kind == SymbolKind::Heading {
+            let name = line.trim().trim_start_matches('#').trim();
+            let column = line[..line.find(name).unwrap_or(0)].chars().count();
+            return vec![(kind, name.to_string(), column)];
+        }
+        match symbols.last_mut() {
+            Some((last_kind, name, _)) if *last_kind == kind && last_end == Some(region.start) => {
+                name.push_str(text);
+            }
+            _ => symbols.push((kind, text.to_string(), line[..region.start].chars().count())),
+        }
+        last_end = Some(region.end);
+    }
+    for (_, name, _) in &mut symbols {
+        *name = name.trim().to_string();
+    }
+    symbols
+}
+
+/// A request to highlight one document
+pub struct HighlightRequest {
+    pub document: u64,
+    pub revision: u64,
+    pub buffer: Rope,
+    /// File extension used to pick the syntax
+    pub extension: String,
+    pub theme: String,
+    /// Repainted once the result is ready
+    pub ctx: egui::Context,
+}
+
+pub struct HighlightResult {
+    pub document: u64,
+    pub styles: Arc<Vec<LineStyle>>,
+    /// Declarations found while highlighting, for the outline
+    pub symbols: Vec<OutlineSymbol>,
+}
+
+enum Message {
+    Highlight(HighlightRequest),
+    Forget(u64),
+}
+
+/// Runs syntax highlighting on a worker thread so large files don't block the UI.
+/// Results arrive asynchronously; until then the editor keeps showing the
+/// previous (possibly stale) colors.
+pub struct BackgroundHighlighter {
+    requests: Sender<Message>,
+    results: Receiver<HighlightResult>,
+}
+
+impl Default for BackgroundHighlighter {
+    fn default() -> Self {
+        Self::spawn(
+            SyntaxSet::load_defaults_newlines(),
+            ThemeSet::load_defaults(),
+        )
+    }
+}
+
+impl BackgroundHighlighter {
+    pub fn spawn(syntax_set: SyntaxSet, theme_set: ThemeSet) -> Self {
+        let (request_tx, request_rx) = mpsc::channel();
+        let (result_tx, result_rx) = mpsc::channel();
+
+        std::thread::Builder::new()
+            .name("syntax-highlighter".to_string())
+            .spawn(move || run_worker(&syntax_set, &theme_set, request_rx, result_tx))
+            .expect("failed to spawn the syntax highlighting thread");
+
+        Self {
+            requests: request_tx,
+            results: result_rx,
+        }
+    }
+
+    pub fn request(&self, request: HighlightRequest) {
+        let _ = self.requests.send(Message::Highlight(request));
+    }
+
+    /// Drop the cached state of a closed document
+    pub fn forget(&self, document: u64) {
+        let _ = self.requests.send(Message::Forget(document));
+    }
+
+    /// Results finished since the last call
+    pub fn poll(&self) -> impl Iterator<Item = HighlightResult> + '_ {
+        self.results.try_iter()
+    }
+}
+
+fn run_worker(
+    syntax_set: &SyntaxSet,
+    theme_set: &ThemeSet,
+    requests: Receiver<Message>,
+    results: Sender<HighlightResult>,
+) {
+    let mut caches: HashMap<u64, HighlightCache> = HashMap::new();
+
+    while let Ok(message) = requests.recv() {
+        // Only the latest request per document matters; skip the ones typing has
+        // already made obsolete
+        let mut pending: Vec<HighlightRequest> = Vec::new();
+        for message in std::iter::once(message).chain(requests.try_iter()) {
+            match message {
+                Message::Highlight(request) => {
+                    pending.retain(|r| r.document != request.document);
+                    pending.push(request);
+                }
+                Message::Forget(document) => {
+                    pending.retain(|r| r.document != document);
+                    caches.remove(&document);
+                }
+            }
+        }
+
+        for request in pending {
+            let extension = contributions::highlight_extension(&request.extension)
+                .unwrap_or(&request.extension);
+            let syntax = syntax_set
+                .find_syntax_by_extension(extension)
+                .unwrap_or_else(|| syntax_set.find_syntax_plain_text());
+            let (theme_name, theme) = match theme_set.themes.get(&request.theme) {
+                Some(theme) => (request.theme.as_str(), theme),
+                None => (FALLBACK_THEME, &theme_set.themes[FALLBACK_THEME]),
+            };
+
+            let cache = caches.entry(request.document).or_default();
+            cache.update(
+                &request.buffer,
+                request.revision,
+                syntax,
+                syntax_set,
+                theme,
+                theme_name,
+            );
+
+            let result = HighlightResult {
+                document: request.document,
+                styles: cache.styles(),
+                symbols: cache.outline(),
+            };
+            if results.send(result).is_err() {
+                return;
+            }
+            request.ctx.request_repaint();
+        }
+    }
+}
@@ -0,0 +1,440 @@
+use crate::commands::{self, Command};
+use crate::settings;
+use egui::{Event, InputState, Key, KeyboardShortcut, Modifiers};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A shortcut, or a chord of two shortcuts pressed one after the other such
+/// as Ctrl+K Ctrl+S
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyBinding {
+    pub first: KeyboardShortcut,
+    pub second: Option<KeyboardShortcut>,
+}
+
+impl KeyBinding {
+    pub fn new(shortcut: KeyboardShortcut) -> Self {
+        Self {
+            first: shortcut,
+            second: None,
+        }
+    }
+
+    /// Parse `Ctrl+Shift+K`, `F12` or a chord like `Ctrl+K Ctrl+S`
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut strokes = text.split_whitespace().map(parse_shortcut);
+        let first = strokes.next().ok_or("no key")??;
+        let second = strokes.next().transpose()?;
+        if strokes.next().is_some() {
+            return Err("chords have two keys at most".to_string());
+        }
+        Ok(Self { first, second })
+    }
+
+    /// Whether pressing one runs the other: the same shortcut, or a shortcut
+    /// that starts the other's chord
+    pub fn conflicts_with(&self, other: &KeyBinding) -> bool {
+        self.first == other.first
+            && (self.second.is_none() || other.second.is_none() || self.second == other.second)
+    }
+
+    /// The binding as written in `keybindings.toml`
+    pub fn text(&self) -> String {
+        let mut text = shortcut_text(&self.first);
+        if let Some(second) = &self.second {
+            text.push(' ');
+            text.push_str(&shortcut_text(second));
+        }
+        text
+    }
+
+    /// The binding for menus and the command palette, as the platform
+    /// writes shortcuts
+    pub fn format(&self, ctx: &egui::Context) -> String {
+        let mut text = ctx.format_shortcut(&self.first);
+        if let Some(second) = &self.second {
+            text.push(' ');
+            text.push_str(&ctx.format_shortcut(second));
+        }
+        text
+    }
+}
+
+/// e.g. `Ctrl+Shift+K`. Modifiers are `Ctrl` / `Control`, `Shift`, `Alt` /
+/// `Option` and `Cmd` / `Command` / `Meta` / `Super`, in any case
+fn parse_shortcut(text: &str) -> Result<KeyboardShortcut, String> {
+    let mut parts: Vec<&str> = text.split('+').collect();
+    // `Ctrl++` binds the plus key
+    if text.ends_with("++") {
+        parts.truncate(parts.len() - 2);
+        parts.push("+");
+    }
+    let key_name = parts.pop().unwrap_or_default();
+    let key = Key::from_name(key_name).ok_or_else(|| format!("unknown key `{key_name}`"))?;
+    let mut modifiers = Modifiers::NONE;
+    for part in parts {
+        modifiers = modifiers
+            | match part.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => Modifiers::CTRL,
+                "shift" => Modifiers::SHIFT,
+                "alt" | "option" => Modifiers::ALT,
+                "cmd" | "command" | "meta" | "super" => Modifiers::MAC_CMD,
+                _ => return Err(format!("unknown modifier `{part}`")),
+            };
+    }
+    Ok(KeyboardShortcut::new(modifiers, key))
+}
+
+fn shortcut_text(shortcut: &KeyboardShortcut) -> String {
+    let m = shortcut.modifiers;
+    let mut text = String::new();
+    for (held, name) in [
+        (m.ctrl, "Ctrl+"),
+        (m.mac_cmd, "Cmd+"),
+        (m.alt, "Alt+"),
+        (m.shift, "Shift+"),
+    ] {
+        if held {
+            text.push_str(name);
+        }
+    }
+    text.push_str(shortcut.logical_key.name());
+    text
+}
+
+/// A key pressed with `modifiers` as a shortcut, with the modifiers written
+/// the way the built-in shortcuts are
+pub fn recorded_shortcut(modifiers: Modifiers, key: Key) -> KeyboardShortcut {
+    let modifiers = Modifiers {
+        alt: modifiers.alt,
+        ctrl: modifiers.ctrl,
+        shift: modifiers.shift,
+        mac_cmd: modifiers.mac_cmd,
+        command: false,
+    };
+    KeyboardShortcut::new(modifiers, key)
+}
+
+/// Consume `shortcut` if it was pressed this frame. egui-winit sends
+/// Ctrl+X, C and V as Cut, Copy and Paste events instead of keys, whatever
+/// else is held, so with Shift or Alt held too those events stand for the key,
+/// e.g. Ctrl+Shift+V. Plain Ctrl+X, C and V stay with the editor.
+pub fn consume_shortcut(input: &mut InputState, shortcut: &KeyboardShortcut) -> bool {
+    if input.consume_shortcut(shortcut) {
+        return true;
+    }
+    let held = input.modifiers;
+    if !(held.shift || held.alt) || !held.matches_exact(shortcut.modifiers) {
+        return false;
+    }
+    let is_stroke = |event: &Event| match shortcut.logical_key {
+        Key::X => matches!(event, Event::Cut),
+        Key::C => matches!(event, Event::Copy),
+        Key::V => matches!(event, Event::Paste(_)),
+        _ => false,
+    };
+    let count = input.events.len();
+    input.events.retain(|event| !is_stroke(event));
+    input.events.len() < count
+}
+
+/// Shortcuts of the commands: the built-in ones, with the overrides from
+/// `keybindings.toml` in the config directory. The file maps command names to
+/// a shortcut, a chord, or nothing to remove the shortcut:
+///
+/// ```toml
+/// GoToLine = "Ctrl+L"
+/// SaveAs = "Ctrl+K Ctrl+S"
+/// ToggleOutput = ""
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Keybindings {
+    /// Bindings that replace a command's own shortcut; `None` removes it
+    overrides: HashMap<Command, Option<KeyBinding>>,
+}
+
+impl Keybindings {
+    pub fn path() -> Option<PathBuf> {
+        settings::config_dir().map(|dir| dir.join("keybindings.toml"))
+    }
+
+    /// Whether saving `path` should reload the keybindings
+    pub fn is_keybindings_file(path: &Path) -> bool {
+        Self::path().is_some_and(|own| own == path)
+    }
+
+    /// The overrides in the keybindings file, and a message for each one that
+    /// couldn't be read. Without a file every command keeps its shortcut.
+    pub fn load() -> (Self, Vec<String>) {
+        let mut keybindings = Self::default();
+        let Some(content) = Self::path().and_then(|path| fs::read_to_string(path).ok()) else {
+            return (keybindings, Vec::new());
+        };
+        let entries: BTreeMap<String, String> = match toml::from_str(&content) {
+            Ok(entries) => entries,
+            Err(e) => {
+                return (
+                    keybindings,
+                    vec![format!("Could not load keybindings.toml: {e}")],
+                )
+            }
+        };
+
+        let commands: HashMap<String, Command> = commands::all()
+            .into_iter()
+            .map(|(_, command)| (command.id(), command))
+            .collect();
+        let mut errors = Vec::new();
+        for (name, text) in entries {
+            let Some(&command) = commands.get(&name) else {
+                errors.push(format!("keybindings.toml: unknown command `{name}`"));
+                continue;
+            };
+            if text.trim().is_empty() {
+                keybindings.overrides.insert(command, None);
+                continue;
+            }
+            match KeyBinding::parse(&text) {
+                Ok(binding) => {
+                    keybindings.overrides.insert(command, Some(binding));
+                }
+                Err(e) => errors.push(format!("keybindings.toml: `{text}` for {name}: {e}")),
+            }
+        }
+        (keybindings, errors)
+    }
+
+    /// Write the overrides to the keybindings file
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = Self::path() else {
+            return Ok(());
+        };
+        let entries: BTreeMap<String, String> = self
+            .overrides
+            .iter()
+            .map(|(command, binding)| (command.id(), binding.map_or(String::new(), |b| b.text())))
+            .collect();
+        let content = toml::to_string_pretty(&entries)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        settings::write_atomic(&path, &content)
+    }
+
+    /// Bind `command` to `binding`, or to nothing
+    pub fn set(&mut self, command: Command, binding: Option<KeyBinding>) {
+        if binding == command.shortcut().map(KeyBinding::new) {
+            self.overrides.remove(&command);
+        } else {
+            self.overrides.insert(command, binding);
+        }
+    }
+
+    /// Give `command` its own shortcut back
+    pub fn reset(&mut self, command: Command) {
+        self.overrides.remove(&command);
+    }
+
+    pub fn is_overridden(&self, command: Command) -> bool {
+        self.overrides.contains_key(&command)
+    }
+
+    /// The shortcut or chord that runs `command`
+    pub fn get(&self, command: Command) -> Option<KeyBinding> {
+        match self.overrides.get(&command) {
+            Some(binding) => *binding,
+            None => command.shortcut().map(KeyBinding::new),
+        }
+    }
+
+    /// Commands with a binding the app handles, most specific first:
+    /// `consume_shortcut` ignores extra Shift and Alt, so Ctrl+Shift+S has to
+    /// be checked before Ctrl+S. Editor commands are only here when rebound,
+    /// since the editor handles their own shortcuts.
+    pub fn bindings(&self) -> Vec<(Command, KeyBinding)> {
+        let mut bindings: Vec<_> = commands::all()
+            .into_iter()
+            .filter(|(_, command)| {
+                !command.is_editor_command() || self.overrides.contains_key(command)
+            })
+            .filter_map(|(_, command)| Some((command, self.get(command)?)))
+            .collect();
+        bindings.sort_by_key(|(_, binding)| {
+            let m = binding.first.modifiers;
+            std::cmp::Reverse(
+                [m.alt, m.ctrl, m.shift]
+                    .into_iter()
+                    .filter(|&held| held)
+                    .count(),
+            )
+        });
+        bindings
+    }
+
+    /// Editor commands that were rebound or had their shortcut removed, whose
+    /// own shortcut must no longer reach the editor
+    pub fn rebound_editor_commands(&self) -> Vec<Command> {
+        self.overrides
+            .keys()
+            .copied()
+            .filter(|command| command.is_editor_command())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CTRL_SHIFT: Modifiers = Modifiers::CTRL.plus(Modifiers::SHIFT);
+
+    /// Run one frame with `events` arriving while `modifiers` are held, the
+    /// way egui-winit hands them over, and consume `shortcut`
+    fn pressed(modifiers: Modifiers, events: Vec<Event>, shortcut: KeyboardShortcut) -> bool {
+        let ctx = egui::Context::default();
+        // The first frame gains focus, which clears the modifiers
+        let _ = ctx.run(egui::RawInput::default(), |_| {});
+        let input = egui::RawInput {
+            modifiers,
+            events,
+            ..Default::default()
+        };
+        let mut pressed = false;
+        let _ = ctx.run(input, |ctx| {
+            pressed = ctx.input_mut(|i| consume_shortcut(i, &shortcut));
+        });
+        pressed
+    }
+
+    fn key(key: Key, modifiers: Modifiers) -> Event {
+        Event::Key {
+            key,
+            physical_key: None,
+            pressed: true,
+            repeat: false,
+            modifiers,
+        }
+    }
+
+    #[test]
+    fn paste_event_with_shift_is_the_v_key() {
+        let shortcut = KeyboardShortcut::new(CTRL_SHIFT, Key::V);
+        let paste = Event::Paste("text".to_string());
+        assert!(pressed(CTRL_SHIFT, vec![paste.clone()], shortcut));
+        // Plain Ctrl+V pastes
+        let ctrl_v = KeyboardShortcut::new(Modifiers::CTRL, Key::V);
+        assert!(!pressed(Modifiers::CTRL, vec![paste.clone()], ctrl_v));
+        // Ctrl+Alt+V is not Ctrl+Shift+V
+        let ctrl_alt = Modifiers::CTRL.plus(Modifiers::ALT);
+        assert!(!pressed(ctrl_alt, vec![paste], shortcut));
+    }
+
+    #[test]
+    fn paste_from_history_shortcut_arrives_as_paste() {
+        let shortcut = Command::PasteFromHistory.shortcut().unwrap();
+        let held = shortcut.modifiers;
+        let paste = Event::Paste("text".to_string());
+        assert!(pressed(held, vec![paste], shortcut));
+    }
+
+    #[test]
+    fn go_to_bracket_matches_shift_backslash() {
+        // winit reports Shift+\ as | on US layouts
+        let go_to_bracket = Command::GoToBracket.shortcut().unwrap();
+        let split = Command::SplitEditor.shortcut().unwrap();
+        let stroke = || vec![key(Key::Pipe, CTRL_SHIFT)];
+        assert!(pressed(CTRL_SHIFT, stroke(), go_to_bracket));
+        assert!(!pressed(CTRL_SHIFT, stroke(), split));
+        let with_alt = CTRL_SHIFT.plus(Modifiers::ALT);
+        let select = Command::SelectToBracket.shortcut().unwrap();
+        assert!(pressed(with_alt, vec![key(Key::Pipe, with_alt)], select));
+    }
+
+    #[test]
+    fn fold_shortcuts_have_keys_egui_sends() {
+        let ctrl_alt = Modifiers::CTRL.plus(Modifiers::ALT);
+        for (command, key_sent) in [(Command::Fold, Key::Comma), (Command::Unfold, Key::Period)] {
+            let shortcut = command.shortcut().unwrap();
+            assert!(pressed(ctrl_alt, vec![key(key_sent, ctrl_alt)], shortcut));
+        }
+    }
+
+    #[test]
+    fn key_events_still_match() {
+        let shortcut = KeyboardShortcut::new(CTRL_SHIFT, Key::P);
+        assert!(pressed(CTRL_SHIFT, vec![key(Key::P, CTRL_SHIFT)], shortcut));
+        assert!(!pressed(
+            CTRL_SHIFT,
+            vec![key(Key::O, CTRL_SHIFT)],
+            shortcut
+        ));
+    }
+
+    fn binding(text: &str) -> KeyBinding {
+        KeyBinding::parse(text).unwrap()
+    }
+
+    #[test]
+    fn parses_shortcuts_and_modifier_names() {
+        let shortcut = |modifiers, key| KeyBinding::new(KeyboardShortcut::new(modifiers, key));
+        assert_eq!(binding("F12"), shortcut(Modifiers::NONE, Key::F12));
+        assert_eq!(binding("Ctrl+Shift+K"), shortcut(CTRL_SHIFT, Key::K));
+        assert_eq!(binding("control+SHIFT+k"), binding("Ctrl+Shift+K"));
+        assert_eq!(
+            binding("Option+Left"),
+            shortcut(Modifiers::ALT, Key::ArrowLeft)
+        );
+        for cmd in ["Cmd", "Command", "Meta", "Super"] {
+            assert_eq!(
+                binding(&format!("{cmd}+P")),
+                shortcut(Modifiers::MAC_CMD, Key::P)
+            );
+        }
+        assert_eq!(binding("Ctrl++"), shortcut(Modifiers::CTRL, Key::Plus));
+    }
+
+    #[test]
+    fn parses_chords() {
+        let chord = binding("Ctrl+K Ctrl+S");
+        assert_eq!(chord.first, KeyboardShortcut::new(Modifiers::CTRL, Key::K));
+        assert_eq!(
+            chord.second,
+            Some(KeyboardShortcut::new(Modifiers::CTRL, Key::S))
+        );
+    }
+
+    #[test]
+    fn rejects_what_it_cannot_read() {
+        assert_eq!(KeyBinding::parse(""), Err("no key".to_string()));
+        assert_eq!(
+            KeyBinding::parse("Ctrl+Nope"),
+            Err("unknown key `Nope`".to_string())
+        );
+        assert_eq!(
+            KeyBinding::parse("Hyper+K"),
+            Err("unknown modifier `Hyper`".to_string())
+        );
+        assert_eq!(
+            KeyBinding::parse("Ctrl+K Ctrl+S Ctrl+D"),
+            Err("chords have two keys at most".to_string())
+        );
+    }
+
+    #[test]
+    fn text_reads_back_as_the_same_binding() {
+        for text in ["Ctrl+Alt+Shift+Enter", "Cmd+K Cmd+S", "Ctrl++", "F5"] {
+            let parsed = binding(text);
+            assert_eq!(binding(&parsed.text()), parsed, "{text}");
+        }
+        assert_eq!(binding("shift+ctrl+k").text(), "Ctrl+Shift+K");
+    }
+
+    #[test]
+    fn chords_conflict_with_their_first_key() {
+        let save_as = binding("Ctrl+K Ctrl+S");
+        assert!(save_as.conflicts_with(&binding("Ctrl+K")));
+        assert!(binding("Ctrl+K").conflicts_with(&save_as));
+        assert!(!save_as.conflicts_with(&binding("Ctrl+K Ctrl+D")));
+        assert!(!save_as.conflicts_with(&binding("Ctrl+S")));
+    }
+}
@@ -0,0 +1,221 @@
+use memmap2::Mmap;
+use regex::bytes::Regex;
+use std::fs::File;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// Lines between two entries of the sparse line index
+const INDEX_INTERVAL: u64 = 1024;
+
+/// Bytes indexed or filtered per `scan` call, so huge files don't stall a frame
+const SCAN_BUDGET: usize = 64 << 20;
+
+/// Longest part of a line that is returned for display
+const MAX_LINE_BYTES: usize = 4096;
+
+/// Read-only view of a (possibly huge) log file through a memory map.
+///
+/// Nothing is copied into memory: lines are found through a sparse index
+/// holding the offset of every `INDEX_INTERVAL`th line, which is built a chunk
+/// at a time by `scan` along with the matches of the filter. `refresh` picks up
+/// data appended to the file.
+pub struct LogFile {
+    path: PathBuf,
+    file: File,
+    /// None while the file is empty, which can't be mapped
+    map: Option<Mmap>,
+    /// Byte offset of lines 0, INDEX_INTERVAL, 2 * INDEX_INTERVAL, ...
+    checkpoints: Vec<usize>,
+    /// Bytes scanned for line breaks so far
+    indexed: usize,
+    newlines: u64,
+    /// Start of the line after the last line break
+    last_line_start: usize,
+    filter: Option<Filter>,
+}
+
+struct Filter {
+    regex: Regex,
+    /// Matching line numbers, ascending
+    lines: Vec<u64>,
+    /// Next line to test and its byte offset
+    next_line: u64,
+    next_byte: usize,
+}
+
+impl LogFile {
+    pub fn open(path: &Path) -> std::io::Result<Self> {
+        let file = File::open(path)?;
+        let mut log = Self {
+            path: path.to_path_buf(),
+            file,
+            map: None,
+            checkpoints: vec![0],
+            indexed: 0,
+            newlines: 0,
+            last_line_start: 0,
+            filter: None,
+        };
+        log.remap()?;
+        Ok(log)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Size of the mapped part of the file in bytes
+    pub fn len(&self) -> usize {
+        self.map.as_ref().map_or(0, |map| map.len())
+    }
+
+    fn bytes(&self) -> &[u8] {
+        self.map.as_deref().unwrap_or_default()
+    }
+
+    fn remap(&mut self) -> std::io::Result<()> {
+        self.map = if self.file.metadata()?.len() == 0 {
+            None
+        } else {
+            // SAFETY: the map is only read. Other processes may append to the
+            // file, which doesn't affect the mapped range; `refresh` remaps
+            // before the file is read past it.
+            Some(unsafe { Mmap::map(&self.file)? })
+        };
+        Ok(())
+    }
+
+    /// Map data appended since the last call. A file that got shorter (rotated
+    /// or truncated) is read again from the start. Returns whether it changed.
+    pub fn refresh(&mut self) -> std::io::Result<bool> {
+        let len = self.file.metadata()?.len() as usize;
+        if len == self.len() {
+            return Ok(false);
+        }
+        if len < self.len() {
+            // Start over, keeping the filter
+            let regex = self.filter.take().map(|f| f.regex);
+            self.checkpoints = vec![0];
+            self.indexed = 0;
+            self.newlines = 0;
+            self.last_line_start = 0;
+            self.set_filter(regex);
+        }
+        self.remap()?;
+        Ok(true)
+    }
+
+    /// Whether the whole file has been indexed and filtered
+    pub fn is_scanned(&self) -> bool {
+        self.indexed == self.len()
+            && self
+                .filter
+                .as_ref()
+                .map_or(true, |f| f.next_byte >= self.last_line_start)
+    }
+
+    /// Index and filter the next chunk of the file. Returns whether work is left.
+    pub fn scan(&mut self) -> bool {
+        let end = (self.indexed + SCAN_BUDGET).min(self.len());
+        let bytes = self.map.as_deref().unwrap_or_default();
+        for (i, byte) in bytes[self.indexed..end].iter().enumerate() {
+            if *byte == b'\n' {
+                self.newlines += 1;
+                self.last_line_start = self.indexed + i + 1;
+                if self.newlines % INDEX_INTERVAL == 0 {
+                    self.checkpoints.push(self.last_line_start);
+                }
+            }
+        }
+        self.indexed = end;
+
+        // Only complete lines are filtered; the last one may still be growing
+        if let Some(filter) = &mut self.filter {
+            let budget_end = (filter.next_byte + SCAN_BUDGET).min(self.last_line_start);
+            let bytes = self.map.as_deref().unwrap_or_default();
+            while filter.next_byte < budget_end {
+                let start = filter.next_byte;
+                let end = start
+                    + bytes[start..self.last_line_start]
+                        .iter()
+                        .position(|b| *b == b'\n')
+                        .unwrap_or(self.last_line_start - start - 1);
+                if filter.regex.is_match(&bytes[start..end]) {
+                    filter.lines.push(filter.next_line);
+                }
+                filter.next_line += 1;
+                filter.next_byte = end + 1;
+            }
+        }
+
+        !self.is_scanned()
+    }
+
+    /// Lines found so far, including a last line without a line break
+    pub fn line_count(&self) -> u64 {
+        self.newlines + u64::from(self.indexed > self.last_line_start)
+    }
+
+    /// Show only lines matching `regex`, or all lines for None
+    pub fn set_filter(&mut self, regex: Option<Regex>) {
+        self.filter = regex.map(|regex| Filter {
+            regex,
+            lines: Vec::new(),
+            next_line: 0,
+            next_byte: 0,
+        });
+    }
+
+    pub fn filter(&self) -> Option<&Regex> {
+        self.filter.as_ref().map(|f| &f.regex)
+    }
+
+    /// Number of lines shown: matches of the filter, or all lines
+    pub fn visible_count(&self) -> u64 {
+        match &self.filter {
+            Some(filter) => filter.lines.len() as u64,
+            None => self.line_count(),
+        }
+    }
+
+    /// Line number of the `index`th shown line
+    pub fn visible_line(&self, index: u64) -> u64 {
+        match &self.filter {
+            Some(filter) => filter.lines[index as usize],
+            None => index,
+        }
+    }
+
+    /// Text of a line without its line break, cut off after `MAX_LINE_BYTES`
+    pub fn line(&self, line: u64) -> String {
+        let range = self.line_range(line);
+        let end = range.end.min(range.start + MAX_LINE_BYTES);
+        String::from_utf8_lossy(&self.bytes()[range.start..end]).into_owned()
+    }
+
+    fn line_range(&self, line: u64) -> Range<usize> {
+        let bytes = &self.bytes()[..self.indexed];
+        let checkpoint = (line / INDEX_INTERVAL) as usize;
+        let mut start = self
+            .checkpoints
+            .get(checkpoint)
+            .copied()
+            .unwrap_or(self.indexed);
+        for _ in 0..line % INDEX_INTERVAL {
+            start = match bytes[start..].iter().position(|b| *b == b'\n') {
+                Some(i) => start + i + 1,
+                None => bytes.len(),
+            };
+        }
+        let end = bytes[start..]
+            .iter()
+            .position(|b| *b == b'\n')
+            .map_or(bytes.len(), |i| start + i);
+        let end = if end > start && bytes[end - 1] == b'\r' {
+            end - 1
+        } else {
+            end
+        };
+        start..end
+    }
+}
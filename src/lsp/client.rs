@@ -0,0 +1,504 @@
+use super::transport::{read_message, write_message};
+use super::{
+    completion_items, document_highlights, document_symbols, hover_markdown, locations,
+    path_to_uri, uri_to_path, workspace_symbols, Diagnostic, DocumentHighlight, Position,
+    PositionEncoding,
+};
+use crate::state::completion::CompletionItem;
+use crate::state::outline::OutlineSymbol;
+use crate::symbol_index::WorkspaceSymbol;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver, Sender};
+
+/// Id of the `initialize` request
+const INITIALIZE_ID: u64 = 0;
+
+/// Something the server sent that the editor should act on
+pub enum LspEvent {
+    Diagnostics {
+        path: PathBuf,
+        diagnostics: Vec<Diagnostic>,
+    },
+    /// Answer to `completion`
+    Completion { id: u64, items: Vec<CompletionItem> },
+    /// Answer to `hover`, as markdown
+    Hover { id: u64, contents: Option<String> },
+    /// Answer to `definition`
+    Definition {
+        id: u64,
+        locations: Vec<(PathBuf, Position)>,
+    },
+    /// Answer to `references`
+    References {
+        id: u64,
+        locations: Vec<(PathBuf, Position)>,
+    },
+    /// Answer to `document_highlights`
+    DocumentHighlights {
+        id: u64,
+        highlights: Vec<DocumentHighlight>,
+    },
+    /// Answer to `document_symbols`
+    DocumentSymbols {
+        id: u64,
+        symbols: Vec<OutlineSymbol>,
+    },
+    /// Answer to `workspace_symbols`
+    WorkspaceSymbols {
+        id: u64,
+        symbols: Vec<WorkspaceSymbol>,
+    },
+    /// `window/showMessage`, or the reason the server stopped
+    Message(String),
+}
+
+/// Requests whose responses are turned into events
+enum RequestKind {
+    Completion,
+    Hover,
+    Definition,
+    References,
+    DocumentHighlights,
+    DocumentSymbols,
+    WorkspaceSymbols,
+}
+
+/// Messages for the thread writing to the server's stdin
+enum Outgoing {
+    Message(Value),
+    /// The server answered `initialize`; queued messages can go out now
+    Initialized,
+}
+
+/// Internal events from the reader thread
+enum Incoming {
+    Initialized(PositionEncoding),
+    Response { id: u64, result: Value },
+    Event(LspEvent),
+}
+
+/// Connection to one language server process over stdio.
+///
+/// Reading and writing happen on background threads; the UI thread queues
+/// notifications and drains events with `poll` once per frame.
+pub struct LspClient {
+    outgoing: Sender<Outgoing>,
+    incoming: Receiver<Incoming>,
+    next_id: u64,
+    encoding: PositionEncoding,
+    /// Version of each open document
+    versions: HashMap<PathBuf, i32>,
+    /// Requests awaiting a response
+    requests: HashMap<u64, RequestKind>,
+}
+
+impl LspClient {
+    /// Start `command` (program and arguments) for a workspace with extra
+    /// environment variables. `ctx` is repainted whenever the server sends something.
+    pub fn spawn(
+        command: &[String],
+        root: &Path,
+        env: &BTreeMap<String, String>,
+        ctx: egui::Context,
+    ) -> std::io::Result<Self> {
+        let (program, args) = command.split_first().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::InvalidInput, "empty server command")
+        })?;
+        let mut child = Command::new(program)
+            .args(args)
+            .current_dir(root)
+            .envs(env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+
+        let (outgoing_tx, outgoing_rx) = mpsc::channel::<Outgoing>();
+        let (incoming_tx, incoming_rx) = mpsc::channel();
+
+        // The protocol forbids sending anything but `initialize` until the server
+        // has answered it, so hold everything else back until then
+        outgoing_tx
+            .send(Outgoing::Message(initialize_request(root)))
+            .expect("receiver is alive");
+        std::thread::Builder::new()
+            .name("lsp-writer".to_string())
+            .spawn(move || {
+                let mut queued = Vec::new();
+                let mut initialized = false;
+                for message in outgoing_rx {
+                    match message {
+                        Outgoing::Message(message)
+                            if initialized
+                                || message.get("id").and_then(Value::as_u64)
+                                    == Some(INITIALIZE_ID) =>
+                        {
+                            if write_message(&mut stdin, &message).is_err() {
+                                break;
+                            }
+                        }
+                        Outgoing::Message(message) => queued.push(message),
+                        Outgoing::Initialized => {
+                            initialized = true;
+                            let initialized_notification = notification("initialized", json!({}));
+                            for message in
+                                std::iter::once(initialized_notification).chain(queued.drain(..))
+                            {
+                                let _ = write_message(&mut stdin, &message);
+                            }
+                        }
+                    }
+                }
+                let _ = child.wait();
+            })?;
+
+        let reply_tx = outgoing_tx.clone();
+        std::thread::Builder::new()
+            .name("lsp-reader".to_string())
+            .spawn(move || {
+                let mut stdout = BufReader::new(stdout);
+                loop {
+                    let message = match read_message(&mut stdout) {
+                        Ok(message) => message,
+                        Err(e) => {
+                            let event = LspEvent::Message(format!("Language server stopped: {e}"));
+                            let _ = incoming_tx.send(Incoming::Event(event));
+                            ctx.request_repaint();
+                            return;
+                        }
+                    };
+                    if let Some(incoming) = handle_message(message, &reply_tx) {
+                        if incoming_tx.send(incoming).is_err() {
+                            return;
+                        }
+                        ctx.request_repaint();
+                    }
+                }
+            })?;
+
+        Ok(Self {
+            outgoing: outgoing_tx,
+            incoming: incoming_rx,
+            next_id: INITIALIZE_ID + 1,
+            encoding: PositionEncoding::default(),
+            versions: HashMap::new(),
+            requests: HashMap::new(),
+        })
+    }
+
+    /// Position encoding agreed on with the server
+    pub fn encoding(&self) -> PositionEncoding {
+        self.encoding
+    }
+
+    /// Events received since the last call
+    pub fn poll(&mut self) -> Vec<LspEvent> {
+        let mut events = Vec::new();
+        for incoming in self.incoming.try_iter() {
+            match incoming {
+                Incoming::Initialized(encoding) => self.encoding = encoding,
+                Incoming::Response { id, result } => match self.requests.remove(&id) {
+                    Some(RequestKind::Completion) => events.push(LspEvent::Completion {
+                        id,
+                        items: completion_items(&result),
+                    }),
+                    Some(RequestKind::Hover) => events.push(LspEvent::Hover {
+                        id,
+                        contents: hover_markdown(&result),
+                    }),
+                    Some(RequestKind::Definition) => events.push(LspEvent::Definition {
+                        id,
+                        locations: locations(&result),
+                    }),
+                    Some(RequestKind::References) => events.push(LspEvent::References {
+                        id,
+                        locations: locations(&result),
+                    }),
+                    Some(RequestKind::DocumentHighlights) => {
+                        events.push(LspEvent::DocumentHighlights {
+                            id,
+                            highlights: document_highlights(&result),
+                        })
+                    }
+                    Some(RequestKind::DocumentSymbols) => events.push(LspEvent::DocumentSymbols {
+                        id,
+                        symbols: document_symbols(&result),
+                    }),
+                    Some(RequestKind::WorkspaceSymbols) => {
+                        events.push(LspEvent::WorkspaceSymbols {
+                            id,
+                            symbols: workspace_symbols(&result, self.encoding),
+                        })
+                    }
+                    None => {}
+                },
+                Incoming::Event(event) => events.push(event),
+            }
+        }
+        events
+    }
+
+    pub fn is_open(&self, path: &Path) -> bool {
+        self.versions.contains_key(path)
+    }
+
+    pub fn did_open(&mut self, path: &Path, language_id: &str, text: String) {
+        self.versions.insert(path.to_path_buf(), 1);
+        self.notify(
+            "textDocument/didOpen",
+            json!({
+                "textDocument": {
+                    "uri": path_to_uri(path),
+                    "languageId": language_id,
+                    "version": 1,
+                    "text": text,
+                }
+            }),
+        );
+    }
+
+    /// Send the full new text of a document
+    pub fn did_change(&mut self, path: &Path, text: String) {
+        let Some(version) = self.versions.get_mut(path) else {
+            return;
+        };
+        *version += 1;
+        let version = *version;
+        self.notify(
+            "textDocument/didChange",
+            json!({
+                "textDocument": { "uri": path_to_uri(path), "version": version },
+                "contentChanges": [{ "text": text }],
+            }),
+        );
+    }
+
+    pub fn did_save(&mut self, path: &Path) {
+        if self.is_open(path) {
+            self.notify(
+                "textDocument/didSave",
+                json!({ "textDocument": { "uri": path_to_uri(path) } }),
+            );
+        }
+    }
+
+    pub fn did_close(&mut self, path: &Path) {
+        if self.versions.remove(path).is_some() {
+            self.notify(
+                "textDocument/didClose",
+                json!({ "textDocument": { "uri": path_to_uri(path) } }),
+            );
+        }
+    }
+
+    /// Ask for completions at a position; the answer arrives as
+    /// `LspEvent::Completion` with the returned id
+    pub fn completion(&mut self, path: &Path, position: Position) -> u64 {
+        let id = self.request(
+            "textDocument/completion",
+            json!({
+                "textDocument": { "uri": path_to_uri(path) },
+                "position": position.to_json(),
+            }),
+        );
+        self.requests.insert(id, RequestKind::Completion);
+        id
+    }
+
+    /// Ask for documentation of the symbol at a position; the answer arrives as
+    /// `LspEvent::Hover` with the returned id
+    pub fn hover(&mut self, path: &Path, position: Position) -> u64 {
+        let id = self.request(
+            "textDocument/hover",
+            json!({
+                "textDocument": { "uri": path_to_uri(path) },
+                "position": position.to_json(),
+            }),
+        );
+        self.requests.insert(id, RequestKind::Hover);
+        id
+    }
+
+    /// Ask where the symbol at a position is defined; the answer arrives as
+    /// `LspEvent::Definition` with the returned id
+    pub fn definition(&mut self, path: &Path, position: Position) -> u64 {
+        let id = self.request(
+            "textDocument/definition",
+            json!({
+                "textDocument": { "uri": path_to_uri(path) },
+                "position": position.to_json(),
+            }),
+        );
+        self.requests.insert(id, RequestKind::Definition);
+        id
+    }
+
+    /// Ask for every use of the symbol at a position, including its
+    /// declaration; the answer arrives as `LspEvent::References`
+    pub fn references(&mut self, path: &Path, position: Position) -> u64 {
+        let id = self.request(
+            "textDocument/references",
+            json!({
+                "textDocument": { "uri": path_to_uri(path) },
+                "position": position.to_json(),
+                "context": { "includeDeclaration": true },
+            }),
+        );
+        self.requests.insert(id, RequestKind::References);
+        id
+    }
+
+    /// Ask for the uses of the symbol at a position in its document, marked as
+    /// reads or writes; the answer arrives as `LspEvent::DocumentHighlights`
+    pub fn document_highlights(&mut self, path: &Path, position: Position) -> u64 {
+        let id = self.request(
+            "textDocument/documentHighlight",
+            json!({
+                "textDocument": { "uri": path_to_uri(path) },
+                "position": position.to_json(),
+            }),
+        );
+        self.requests.insert(id, RequestKind::DocumentHighlights);
+        id
+    }
+
+    /// Ask for the declarations in a document, for the outline; the answer
+    /// arrives as `LspEvent::DocumentSymbols`
+    pub fn document_symbols(&mut self, path: &Path) -> u64 {
+        let id = self.request(
+            "textDocument/documentSymbol",
+            json!({ "textDocument": { "uri": path_to_uri(path) } }),
+        );
+        self.requests.insert(id, RequestKind::DocumentSymbols);
+        id
+    }
+
+    /// Search the declarations in the whole workspace; the answer arrives as
+    /// `LspEvent::WorkspaceSymbols`
+    pub fn workspace_symbols(&mut self, query: &str) -> u64 {
+        let id = self.request("workspace/symbol", json!({ "query": query }));
+        self.requests.insert(id, RequestKind::WorkspaceSymbols);
+        id
+    }
+
+    fn notify(&self, method: &str, params: Value) {
+        let _ = self
+            .outgoing
+            .send(Outgoing::Message(notification(method, params)));
+    }
+
+    fn request(&mut self, method: &str, params: Value) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        let message = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        let _ = self.outgoing.send(Outgoing::Message(message));
+        id
+    }
+}
+
+impl Drop for LspClient {
+    fn drop(&mut self) {
+        // The writer thread exits, closing stdin, once the sender is dropped
+        self.request("shutdown", Value::Null);
+        self.notify("exit", Value::Null);
+    }
+}
+
+fn notification(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
+
+fn initialize_request(root: &Path) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": INITIALIZE_ID,
+        "method": "initialize",
+        "params": {
+            "processId": std::process::id(),
+            "rootUri": path_to_uri(root),
+            "workspaceFolders": [{
+                "uri": path_to_uri(root),
+                "name": root.file_name().map(|n| n.to_string_lossy()).unwrap_or_default(),
+            }],
+            "capabilities": {
+                "general": { "positionEncodings": ["utf-8", "utf-16"] },
+                "textDocument": {
+                    "synchronization": { "didSave": true },
+                    "publishDiagnostics": { "relatedInformation": false },
+                    "completion": { "completionItem": { "snippetSupport": false } },
+                    "hover": { "contentFormat": ["markdown", "plaintext"] },
+                    "definition": { "linkSupport": true },
+                    "references": {},
+                    "documentHighlight": {},
+                    "documentSymbol": { "hierarchicalDocumentSymbolSupport": true },
+                },
+                "workspace": {
+                    "workspaceFolders": true,
+                    "configuration": true,
+                    "symbol": {},
+                },
+            },
+        },
+    })
+}
+
+fn handle_message(message: Value, reply: &Sender<Outgoing>) -> Option<Incoming> {
+    let method = message.get("method").and_then(Value::as_str);
+    let id = message.get("id").cloned();
+
+    match (method, id) {
+        // Response to one of our requests
+        (None, Some(id)) => {
+            let id = id.as_u64()?;
+            if id != INITIALIZE_ID {
+                let result = message.get("result").cloned().unwrap_or(Value::Null);
+                return Some(Incoming::Response { id, result });
+            }
+            let encoding = message
+                .pointer("/result/capabilities/positionEncoding")
+                .and_then(Value::as_str)
+                .map(PositionEncoding::from_name)
+                .unwrap_or_default();
+            let _ = reply.send(Outgoing::Initialized);
+            Some(Incoming::Initialized(encoding))
+        }
+        // Request from the server; nothing is supported, but it has to get an answer
+        (Some(method), Some(id)) => {
+            let result = match method {
+                "workspace/configuration" => {
+                    let items = message
+                        .pointer("/params/items")
+                        .and_then(Value::as_array)
+                        .map_or(0, Vec::len);
+                    Value::Array(vec![Value::Null; items])
+                }
+                _ => Value::Null,
+            };
+            let response = json!({ "jsonrpc": "2.0", "id": id, "result": result });
+            let _ = reply.send(Outgoing::Message(response));
+            None
+        }
+        (Some("textDocument/publishDiagnostics"), None) => {
+            let params = message.get("params")?;
+            let path = uri_to_path(params.get("uri")?.as_str()?)?;
+            let diagnostics = params
+                .get("diagnostics")?
+                .as_array()?
+                .iter()
+                .filter_map(Diagnostic::from_json)
+                .collect();
+            Some(Incoming::Event(LspEvent::Diagnostics { path, diagnostics }))
+        }
+        (Some("window/showMessage"), None) => {
+            let text = message.pointer("/params/message")?.as_str()?;
+            Some(Incoming::Event(LspEvent::Message(text.to_string())))
+        }
+        _ => None,
+    }
+}
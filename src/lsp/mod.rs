@@ -0,0 +1,386 @@
+mod client;
+mod transport;
+
+pub use client::{LspClient, LspEvent};
+
+use crate::state::completion::CompletionItem;
+use crate::state::outline::{OutlineSymbol, SymbolKind};
+use crate::symbol_index::WorkspaceSymbol;
+use ropey::Rope;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+/// Line and column as sent over the protocol. The unit of the column depends on
+/// the position encoding negotiated with the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub character: usize,
+}
+
+impl Position {
+    fn from_json(value: &Value) -> Option<Self> {
+        Some(Self {
+            line: value.get("line")?.as_u64()? as usize,
+            character: value.get("character")?.as_u64()? as usize,
+        })
+    }
+
+    fn to_json(self) -> Value {
+        serde_json::json!({ "line": self.line, "character": self.character })
+    }
+}
+
+/// How the server counts columns
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PositionEncoding {
+    Utf8,
+    /// The protocol's default
+    #[default]
+    Utf16,
+    Utf32,
+}
+
+impl PositionEncoding {
+    fn from_name(name: &str) -> Self {
+        match name {
+            "utf-8" => Self::Utf8,
+            "utf-32" => Self::Utf32,
+            _ => Self::Utf16,
+        }
+    }
+
+    /// Char offset in the rope for a protocol position, clamped to the buffer
+    pub fn to_char(self, rope: &Rope, position: Position) -> usize {
+        if position.line >= rope.len_lines() {
+            return rope.len_chars();
+        }
+        let line_start = rope.line_to_char(position.line);
+        let line = rope.line(position.line);
+        let mut units = 0;
+        for (column, ch) in line.chars().enumerate() {
+            if units >= position.character || ch == '\n' || ch == '\r' {
+                return line_start + column;
+            }
+            units += self.len(ch);
+        }
+        line_start + line.len_chars()
+    }
+
+    /// Protocol position of a char offset in the rope
+    pub fn to_position(self, rope: &Rope, offset: usize) -> Position {
+        let offset = offset.min(rope.len_chars());
+        let line = rope.char_to_line(offset);
+        let line_start = rope.line_to_char(line);
+        let character = rope
+            .slice(line_start..offset)
+            .chars()
+            .map(|ch| self.len(ch))
+            .sum();
+        Position { line, character }
+    }
+
+    fn len(self, ch: char) -> usize {
+        match self {
+            Self::Utf8 => ch.len_utf8(),
+            Self::Utf16 => ch.len_utf16(),
+            Self::Utf32 => 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+    Information,
+    Hint,
+}
+
+/// A `textDocument/publishDiagnostics` entry
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub start: Position,
+    pub end: Position,
+    pub severity: Severity,
+    pub message: String,
+    /// Tool that produced it, e.g. "rustc" or "clippy"
+    pub source: Option<String>,
+}
+
+impl Diagnostic {
+    fn from_json(value: &Value) -> Option<Self> {
+        let range = value.get("range")?;
+        let severity = match value.get("severity").and_then(Value::as_u64) {
+            Some(1) | None => Severity::Error,
+            Some(2) => Severity::Warning,
+            Some(3) => Severity::Information,
+            _ => Severity::Hint,
+        };
+        Some(Self {
+            start: Position::from_json(range.get("start")?)?,
+            end: Position::from_json(range.get("end")?)?,
+            severity,
+            message: value.get("message")?.as_str()?.to_string(),
+            source: value
+                .get("source")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+        })
+    }
+}
+
+/// Items of a `textDocument/completion` result, which is either a list or an
+/// object holding one
+fn completion_items(result: &Value) -> Vec<CompletionItem> {
+    let items = result
+        .as_array()
+        .or_else(|| result.get("items").and_then(Value::as_array));
+    items
+        .into_iter()
+        .flatten()
+        .filter_map(|item| {
+            let label = item.get("label")?.as_str()?.to_string();
+            let insert_text = item
+                .pointer("/textEdit/newText")
+                .or_else(|| item.get("insertText"))
+                .and_then(Value::as_str)
+                .unwrap_or(&label)
+                .to_string();
+            Some(CompletionItem {
+                insert_text,
+                detail: item
+                    .get("detail")
+                    .and_then(Value::as_str)
+                    .map(str::to_string),
+                label,
+            })
+        })
+        .collect()
+}
+
+/// Targets of a `textDocument/definition` or `textDocument/references` result:
+/// a location, a list of locations, or a list of location links
+fn locations(result: &Value) -> Vec<(PathBuf, Position)> {
+    let location = |value: &Value| {
+        let (uri, range) = match value.get("targetUri") {
+            Some(uri) => (uri, value.get("targetSelectionRange")?),
+            None => (value.get("uri")?, value.get("range")?),
+        };
+        Some((
+            uri_to_path(uri.as_str()?)?,
+            Position::from_json(range.get("start")?)?,
+        ))
+    };
+    match result {
+        Value::Array(items) => items.iter().filter_map(location).collect(),
+        other => location(other).into_iter().collect(),
+    }
+}
+
+/// A `textDocument/documentHighlight` entry
+#[derive(Debug, Clone)]
+pub struct DocumentHighlight {
+    pub start: Position,
+    pub end: Position,
+    /// Kind 3 (write access); text and read highlights are reads
+    pub write: bool,
+}
+
+fn document_highlights(result: &Value) -> Vec<DocumentHighlight> {
+    let Some(items) = result.as_array() else {
+        return Vec::new();
+    };
+    items
+        .iter()
+        .filter_map(|item| {
+            let range = item.get("range")?;
+            Some(DocumentHighlight {
+                start: Position::from_json(range.get("start")?)?,
+                end: Position::from_json(range.get("end")?)?,
+                write: item.get("kind").and_then(Value::as_u64) == Some(3),
+            })
+        })
+        .collect()
+}
+
+/// Symbols of a `textDocument/documentSymbol` result in document order, with
+/// nested symbols after their parent. The result is either a tree of document
+/// symbols or a flat list of symbol information. Columns are in the server's
+/// position encoding.
+fn document_symbols(result: &Value) -> Vec<OutlineSymbol> {
+    fn flatten(items: &[Value], depth: usize, symbols: &mut Vec<OutlineSymbol>) {
+        for item in items {
+            let start = item
+                .pointer("/selectionRange/start")
+                .or_else(|| item.pointer("/location/range/start"))
+                .and_then(Position::from_json);
+            let (Some(name), Some(start)) = (item.get("name").and_then(Value::as_str), start)
+            else {
+                continue;
+            };
+            symbols.push(OutlineSymbol {
+                name: name.to_string(),
+                kind: symbol_kind(item.get("kind").and_then(Value::as_u64).unwrap_or(0)),
+                line: start.line,
+                column: start.character,
+                // A flat list only says which symbol contains another
+                depth: depth + usize::from(item.get("containerName").is_some_and(|c| c != "")),
+            });
+            if let Some(children) = item.get("children").and_then(Value::as_array) {
+                flatten(children, depth + 1, symbols);
+            }
+        }
+    }
+
+    let mut symbols = Vec::new();
+    flatten(
+        result.as_array().map_or(&[], Vec::as_slice),
+        0,
+        &mut symbols,
+    );
+    if result.pointer("/0/location").is_some() {
+        symbols.sort_by_key(|symbol| (symbol.line, symbol.column));
+    }
+    symbols
+}
+
+/// Symbols of a `workspace/symbol` result. Workspace symbols may only name
+/// their file, in which case they point at its start.
+fn workspace_symbols(result: &Value, encoding: PositionEncoding) -> Vec<WorkspaceSymbol> {
+    let items = result.as_array().map_or(&[][..], Vec::as_slice);
+    items
+        .iter()
+        .filter_map(|item| {
+            let location = item.get("location")?;
+            let position = location
+                .pointer("/range/start")
+                .and_then(Position::from_json)
+                .unwrap_or(Position {
+                    line: 0,
+                    character: 0,
+                });
+            Some(WorkspaceSymbol {
+                name: item.get("name")?.as_str()?.to_string(),
+                kind: symbol_kind(item.get("kind").and_then(Value::as_u64).unwrap_or(0)),
+                path: uri_to_path(location.get("uri")?.as_str()?)?,
+                position,
+                encoding,
+            })
+        })
+        .collect()
+}
+
+/// Outline kind for a protocol `SymbolKind` number
+fn symbol_kind(kind: u64) -> SymbolKind {
+    match kind {
+        2..=4 => SymbolKind::Module,
+        5 => SymbolKind::Class,
+        6 | 9 => SymbolKind::Method,
+        7 | 8 | 22 => SymbolKind::Field,
+        10 => SymbolKind::Enum,
+        11 => SymbolKind::Interface,
+        12 => SymbolKind::Function,
+        13 => SymbolKind::Variable,
+        14 => SymbolKind::Constant,
+        23 => SymbolKind::Struct,
+        26 => SymbolKind::Type,
+        _ => SymbolKind::Other,
+    }
+}
+
+/// Markdown for the contents of a `textDocument/hover` result: markup content,
+/// a marked string, or a list of marked strings
+fn hover_markdown(result: &Value) -> Option<String> {
+    fn marked_string(value: &Value) -> Option<String> {
+        match value {
+            Value::String(text) => Some(text.clone()),
+            Value::Object(object) => {
+                let text = object.get("value")?.as_str()?;
+                Some(match object.get("language").and_then(Value::as_str) {
+                    Some(language) => format!("```{language}\n{text}\n```"),
+                    None => text.to_string(),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    let contents = result.get("contents")?;
+    let markdown = match contents {
+        Value::Array(items) => items
+            .iter()
+            .filter_map(marked_string)
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+        other => marked_string(other)?,
+    };
+    (!markdown.trim().is_empty()).then_some(markdown)
+}
+
+/// `languageId` the protocol expects for a file extension
+pub fn language_id(extension: &str) -> &str {
+    match extension {
+        "rs" => "rust",
+        "py" => "python",
+        "js" => "javascript",
+        "ts" => "typescript",
+        "jsx" => "javascriptreact",
+        "tsx" => "typescriptreact",
+        "cc" | "cxx" | "hpp" => "cpp",
+        "h" => "c",
+        "md" => "markdown",
+        "sh" => "shellscript",
+        "yml" => "yaml",
+        other => other,
+    }
+}
+
+pub fn path_to_uri(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let encoded: String = path
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' | b':' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect();
+    if encoded.starts_with('/') {
+        format!("file://{encoded}")
+    } else {
+        // Windows drive paths
+        format!("file:///{encoded}")
+    }
+}
+
+pub fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    let path = String::from_utf8(decoded).ok()?;
+    // "/C:/dir" on Windows
+    let path = match path.as_bytes() {
+        [b'/', _, b':', ..] => &path[1..],
+        _ => &path,
+    };
+    Some(PathBuf::from(path))
+}
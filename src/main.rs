@@ -1,25 +1,62 @@
+mod announcer;
+mod ansi;
 mod app;
+mod cargo;
+mod collab;
+mod commands;
+mod contributions;
+mod counterpart;
+mod display_path;
+mod dotenv;
+mod encoding;
+mod exclude;
 mod file_icons;
+mod find_in_files;
+mod folder_diff;
+mod forge;
+mod formatter;
 mod fs_tree;
+mod git;
+mod highlight;
+mod keybindings;
+mod log_file;
+mod lsp;
+mod pretty_print;
+mod primary_selection;
+mod project_template;
+mod rest_client;
+mod server;
+mod settings;
+mod sql;
 mod state;
+mod symbol_index;
+#[cfg(feature = "tree-sitter")]
+mod syntax_tree;
 mod theme;
+mod watchdog;
 mod widgets;
 
 use app::EditorApp;
 use eframe::egui;
+use settings::Settings;
 
 fn main() -> eframe::Result<()> {
+    let (settings, settings_error) = Settings::load();
+
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_inner_size([1280.0, 720.0])
-            .with_min_inner_size([800.0, 600.0]),
+            .with_min_inner_size([800.0, 600.0])
+            .with_decorations(!settings.custom_title_bar),
         vsync: true,
+        // Report OS dark/light changes so the "auto" theme can follow them
+        follow_system_theme: true,
         ..Default::default()
     };
 
     eframe::run_native(
         "Rust Code Editor",
         options,
-        Box::new(|_cc| Box::new(EditorApp::default())),
+        Box::new(|_cc| Box::new(EditorApp::new(settings, settings_error))),
     )
 }
@@ -0,0 +1,229 @@
+use ropey::Rope;
+
+/// Languages a pasted single-line fragment can be pretty-printed as
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FragmentKind {
+    Json,
+    Xml,
+}
+
+impl FragmentKind {
+    /// Kind of fragment worth reformatting in a file with this extension
+    pub fn for_extension(ext: &str) -> Option<Self> {
+        match ext {
+            "json" => Some(Self::Json),
+            "xml" | "svg" | "xaml" | "csproj" | "plist" => Some(Self::Xml),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Json => "JSON",
+            Self::Xml => "XML",
+        }
+    }
+
+    /// Spread a single-line fragment over several lines, one level of `indent`
+    /// per nesting level. Returns `None` for invalid fragments and ones that
+    /// already span several lines or wouldn't change.
+    pub fn format(self, text: &str, indent: &str) -> Option<String> {
+        let text = text.trim();
+        if text.contains('\n') {
+            return None;
+        }
+        let formatted = match self {
+            Self::Json => json(text, indent)?,
+            Self::Xml => xml(text, indent)?,
+        };
+        formatted.contains('\n').then_some(formatted)
+    }
+}
+
+/// Indentation used by a document: a tab, or the leading spaces of the least
+/// indented line (four spaces when nothing is indented)
+pub fn indent_unit(rope: &Rope) -> String {
+    let mut spaces = usize::MAX;
+    for line in rope.lines() {
+        match line.chars().next() {
+            Some('\t') => return "\t".to_string(),
+            Some(' ') => {
+                let count = line.chars().take_while(|&c| c == ' ').count();
+                if line.chars().nth(count).is_some_and(|c| !c.is_whitespace()) {
+                    spaces = spaces.min(count);
+                }
+            }
+            _ => {}
+        }
+    }
+    " ".repeat(if spaces == usize::MAX { 4 } else { spaces })
+}
+
+/// Reformat JSON without parsing it into values, so keys keep their order and
+/// numbers their spelling
+fn json(text: &str, indent: &str) -> Option<String> {
+    serde_json::from_str::<serde::de::IgnoredAny>(text).ok()?;
+
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len() * 2);
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    let newline = |out: &mut String, depth: usize| {
+        out.push('\n');
+        out.push_str(&indent.repeat(depth));
+    };
+
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        i += 1;
+        if in_string {
+            out.push(c);
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+        match c {
+            '"' => {
+                in_string = true;
+                out.push(c);
+            }
+            '{' | '[' => {
+                out.push(c);
+                // Keep empty objects and arrays on one line
+                let next = chars[i..]
+                    .iter()
+                    .position(|c| !c.is_whitespace())
+                    .map(|n| i + n);
+                match next.filter(|&n| matches!(chars[n], '}' | ']')) {
+                    Some(n) => {
+                        out.push(chars[n]);
+                        i = n + 1;
+                    }
+                    None => {
+                        depth += 1;
+                        newline(&mut out, depth);
+                    }
+                }
+            }
+            '}' | ']' => {
+                depth = depth.saturating_sub(1);
+                newline(&mut out, depth);
+                out.push(c);
+            }
+            ',' => {
+                out.push(c);
+                newline(&mut out, depth);
+            }
+            ':' => out.push_str(": "),
+            c if c.is_whitespace() => {}
+            c => out.push(c),
+        }
+    }
+    Some(out)
+}
+
+enum XmlToken<'a> {
+    Open(&'a str),
+    Close(&'a str),
+    /// Self-closing tags, comments, CDATA, declarations, and processing instructions
+    Other(&'a str),
+    Text(&'a str),
+}
+
+/// Split XML into tags and trimmed text; `None` when a tag is left open
+fn xml_tokens(text: &str) -> Option<Vec<XmlToken<'_>>> {
+    let mut tokens = Vec::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        if !rest.starts_with('<') {
+            let end = rest.find('<').unwrap_or(rest.len());
+            let content = rest[..end].trim();
+            if !content.is_empty() {
+                tokens.push(XmlToken::Text(content));
+            }
+            rest = &rest[end..];
+            continue;
+        }
+
+        let end = if rest.starts_with("<!--") {
+            rest.find("-->")? + 3
+        } else if rest.starts_with("<![CDATA[") {
+            rest.find("]]>")? + 3
+        } else {
+            // Attribute values may contain '>'
+            let mut quote = None;
+            let mut end = None;
+            for (i, c) in rest.char_indices() {
+                match (quote, c) {
+                    (Some(q), c) if c == q => quote = None,
+                    (Some(_), _) => {}
+                    (None, '"' | '\'') => quote = Some(c),
+                    (None, '>') => {
+                        end = Some(i + 1);
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+            end?
+        };
+
+        let tag = &rest[..end];
+        tokens.push(if tag.starts_with("</") {
+            XmlToken::Close(tag)
+        } else if tag.starts_with("<!") || tag.starts_with("<?") || tag.ends_with("/>") {
+            XmlToken::Other(tag)
+        } else {
+            XmlToken::Open(tag)
+        });
+        rest = &rest[end..];
+    }
+    Some(tokens)
+}
+
+/// Put every element on its own line, keeping elements that only hold text on one
+fn xml(text: &str, indent: &str) -> Option<String> {
+    if !text.starts_with('<') {
+        return None;
+    }
+    let tokens = xml_tokens(text)?;
+
+    let mut lines: Vec<String> = Vec::new();
+    let mut depth = 0usize;
+    let mut i = 0;
+    while i < tokens.len() {
+        let prefix = indent.repeat(depth);
+        match (&tokens[i], tokens.get(i + 1), tokens.get(i + 2)) {
+            (XmlToken::Open(open), Some(XmlToken::Text(text)), Some(XmlToken::Close(close))) => {
+                lines.push(format!("{}{}{}{}", prefix, open, text, close));
+                i += 3;
+            }
+            (XmlToken::Open(open), Some(XmlToken::Close(close)), _) => {
+                lines.push(format!("{}{}{}", prefix, open, close));
+                i += 2;
+            }
+            (XmlToken::Open(open), _, _) => {
+                lines.push(format!("{}{}", prefix, open));
+                depth += 1;
+                i += 1;
+            }
+            (XmlToken::Close(close), _, _) => {
+                depth = depth.checked_sub(1)?;
+                lines.push(format!("{}{}", indent.repeat(depth), close));
+                i += 1;
+            }
+            (XmlToken::Other(content) | XmlToken::Text(content), _, _) => {
+                lines.push(format!("{}{}", prefix, content));
+                i += 1;
+            }
+        }
+    }
+    (depth == 0).then(|| lines.join("\n"))
+}
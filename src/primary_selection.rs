@@ -0,0 +1,77 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Whether the platform has a primary selection (X11 and Wayland desktops)
+pub const SUPPORTED: bool = cfg!(all(unix, not(target_os = "macos")));
+
+/// Make `text` the primary selection without waiting for it. Missing tools
+/// are ignored.
+pub fn set(text: String) {
+    if !SUPPORTED || text.is_empty() {
+        return;
+    }
+    let _ = std::thread::Builder::new()
+        .name("primary selection".to_string())
+        .spawn(move || {
+            for mut command in commands(false) {
+                let Ok(mut child) = command
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .spawn()
+                else {
+                    continue;
+                };
+                // The tools fork to serve the selection once their input closes
+                if let Some(mut stdin) = child.stdin.take() {
+                    let _ = stdin.write_all(text.as_bytes());
+                }
+                if child.wait().is_ok_and(|status| status.success()) {
+                    break;
+                }
+            }
+        });
+}
+
+/// Current primary selection, from whichever application owns it
+pub fn get() -> Option<String> {
+    if !SUPPORTED {
+        return None;
+    }
+    commands(true).into_iter().find_map(|mut command| {
+        let output = command
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .ok()?;
+        output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+    })
+}
+
+/// Commands to try in order for reading or writing the primary selection:
+/// wl-clipboard's tools on Wayland, then xclip and xsel
+fn commands(reading: bool) -> Vec<Command> {
+    let mut commands = Vec::new();
+    if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        let mut wayland = Command::new(if reading { "wl-paste" } else { "wl-copy" });
+        wayland.arg("--primary");
+        if reading {
+            wayland.arg("--no-newline");
+        }
+        commands.push(wayland);
+    }
+    let mut xclip = Command::new("xclip");
+    xclip.args([
+        "-selection",
+        "primary",
+        if reading { "-out" } else { "-in" },
+    ]);
+    commands.push(xclip);
+    let mut xsel = Command::new("xsel");
+    xsel.args(["--primary", if reading { "--output" } else { "--input" }]);
+    commands.push(xsel);
+    commands
+}
@@ -0,0 +1,147 @@
+use crate::settings;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Written into every file of a user-defined template in place of this marker
+const NAME_PLACEHOLDER: &str = "{{project_name}}";
+
+const GITIGNORE: &str = "/target\n/node_modules\n/dist\n.DS_Store\n*.log\n";
+
+/// What File > New Project scaffolds from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Template {
+    CargoBinary,
+    CargoLibrary,
+    /// Empty folder with a `.gitignore` and a README
+    Plain,
+    /// Copy of a folder in the templates directory
+    Custom(PathBuf),
+}
+
+impl Template {
+    pub fn name(&self) -> String {
+        match self {
+            Template::CargoBinary => "Rust binary (cargo new --bin)".to_string(),
+            Template::CargoLibrary => "Rust library (cargo new --lib)".to_string(),
+            Template::Plain => "Empty folder with .gitignore and README".to_string(),
+            Template::Custom(dir) => dir
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned(),
+        }
+    }
+}
+
+/// Folder holding user-defined templates, one subfolder each
+pub fn templates_dir() -> Option<PathBuf> {
+    settings::config_dir().map(|dir| dir.join("templates"))
+}
+
+/// Built-in templates followed by the user-defined ones
+pub fn templates() -> Vec<Template> {
+    let mut custom: Vec<PathBuf> = templates_dir()
+        .and_then(|dir| fs::read_dir(dir).ok())
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect();
+    custom.sort();
+
+    [
+        Template::CargoBinary,
+        Template::CargoLibrary,
+        Template::Plain,
+    ]
+    .into_iter()
+    .chain(custom.into_iter().map(Template::Custom))
+    .collect()
+}
+
+/// Create `location/name` from a template and return its path
+pub fn create_project(
+    template: &Template,
+    location: &Path,
+    name: &str,
+    init_git: bool,
+) -> Result<PathBuf, String> {
+    let name = name.trim();
+    if name.is_empty() || name.contains(['/', '\\']) || name == "." || name == ".." {
+        return Err("Enter a folder name for the project".to_string());
+    }
+    if !location.is_dir() {
+        return Err(format!("{} is not a folder", location.display()));
+    }
+    let root = location.join(name);
+    if root.exists() {
+        return Err(format!("{} already exists", root.display()));
+    }
+
+    match template {
+        Template::CargoBinary | Template::CargoLibrary => {
+            let kind = if *template == Template::CargoBinary {
+                "--bin"
+            } else {
+                "--lib"
+            };
+            let vcs = if init_git { "git" } else { "none" };
+            // cargo initializes the repository itself
+            return run(Command::new("cargo")
+                .args(["new", kind, "--vcs", vcs])
+                .arg(name)
+                .current_dir(location))
+            .map(|()| root);
+        }
+        Template::Plain => {
+            fs::create_dir(&root).map_err(|e| e.to_string())?;
+            fs::write(root.join(".gitignore"), GITIGNORE).map_err(|e| e.to_string())?;
+            fs::write(root.join("README.md"), format!("# {name}\n")).map_err(|e| e.to_string())?;
+        }
+        Template::Custom(dir) => {
+            copy_template(dir, &root, name).map_err(|e| e.to_string())?;
+        }
+    }
+
+    if init_git {
+        run(Command::new("git").arg("init").current_dir(&root))?;
+    }
+    Ok(root)
+}
+
+/// Copy a template folder, filling in the project name in text files
+fn copy_template(from: &Path, to: &Path, name: &str) -> std::io::Result<()> {
+    fs::create_dir(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            // The template's own history doesn't belong in the project
+            if entry.file_name() != ".git" {
+                copy_template(&entry.path(), &target, name)?;
+            }
+            continue;
+        }
+        let bytes = fs::read(entry.path())?;
+        match String::from_utf8(bytes) {
+            Ok(text) => fs::write(target, text.replace(NAME_PLACEHOLDER, name))?,
+            Err(e) => fs::write(target, e.into_bytes())?,
+        }
+    }
+    Ok(())
+}
+
+/// Run a command, turning a failure into its error output
+fn run(command: &mut Command) -> Result<(), String> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let output = command
+        .output()
+        .map_err(|e| format!("Failed to run {program}: {e}"))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
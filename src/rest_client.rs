@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant};
+
+const METHODS: &[&str] = &[
+    "GET", "POST", "PUT", "PATCH", "DELETE", "HEAD", "OPTIONS", "TRACE", "CONNECT",
+];
+
+/// One request block of a `.http` / `.rest` file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpRequest {
+    /// Zero-based line of the request line
+    pub line: usize,
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl HttpRequest {
+    /// "GET https://example.com"
+    pub fn title(&self) -> String {
+        format!("{} {}", self.method, self.url)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HttpResponse {
+    /// e.g. "200 OK"
+    pub status: String,
+    pub status_code: u16,
+    pub headers: Vec<(String, String)>,
+    /// Pretty-printed when it is JSON
+    pub body: String,
+    pub elapsed: Duration,
+}
+
+/// Parse the request blocks of a file in the REST Client format: blocks are
+/// separated by `###` lines, each has a request line (`METHOD URL`, or just a
+/// URL for GET), header lines, and a body after the first blank line. `#` and
+/// `//` lines are comments, and `@name = value` lines define variables used
+/// as `{{name}}`.
+pub fn parse_requests(text: &str) -> Vec<HttpRequest> {
+    let mut variables = HashMap::new();
+    for line in text.lines() {
+        if let Some((name, value)) = line
+            .trim()
+            .strip_prefix('@')
+            .and_then(|v| v.split_once('='))
+        {
+            variables.insert(name.trim().to_string(), value.trim().to_string());
+        }
+    }
+    let substitute = |text: &str| {
+        let mut text = text.to_string();
+        for (name, value) in &variables {
+            text = text.replace(&format!("{{{{{name}}}}}"), value);
+        }
+        text
+    };
+
+    let mut requests = Vec::new();
+    let mut current: Option<HttpRequest> = None;
+    let mut in_body = false;
+    for (line_idx, line) in text.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("###") {
+            requests.extend(current.take());
+            in_body = false;
+            continue;
+        }
+
+        let Some(request) = &mut current else {
+            let is_comment = trimmed.starts_with('#') || trimmed.starts_with("//");
+            if trimmed.is_empty() || is_comment || trimmed.starts_with('@') {
+                continue;
+            }
+            let mut parts = trimmed.split_whitespace();
+            let first = parts.next().unwrap_or_default();
+            let (method, url) = if METHODS.contains(&first.to_uppercase().as_str()) {
+                (first.to_uppercase(), parts.next().unwrap_or_default())
+            } else {
+                ("GET".to_string(), first)
+            };
+            current = Some(HttpRequest {
+                line: line_idx,
+                method,
+                url: substitute(url),
+                headers: Vec::new(),
+                body: String::new(),
+            });
+            continue;
+        };
+
+        if in_body {
+            request.body.push_str(line);
+            request.body.push('\n');
+        } else if trimmed.is_empty() {
+            in_body = true;
+        } else if trimmed.starts_with('#') || trimmed.starts_with("//") {
+            // Comment between headers
+        } else if let Some((name, value)) = trimmed.split_once(':') {
+            request
+                .headers
+                .push((name.trim().to_string(), substitute(value.trim())));
+        }
+    }
+    requests.extend(current);
+
+    for request in &mut requests {
+        request.body = substitute(request.body.trim_end());
+    }
+    requests
+}
+
+/// Run a request on a worker thread; the result arrives on the returned
+/// channel and `ctx` is repainted then
+pub fn send_in_background(
+    request: HttpRequest,
+    ctx: egui::Context,
+) -> Receiver<Result<HttpResponse, String>> {
+    let (tx, rx) = mpsc::channel();
+    let spawned = std::thread::Builder::new()
+        .name("rest-client".to_string())
+        .spawn(move || {
+            let _ = tx.send(send(&request));
+            ctx.request_repaint();
+        });
+    if let Err(e) = spawned {
+        let (tx, failed) = mpsc::channel();
+        let _ = tx.send(Err(e.to_string()));
+        return failed;
+    }
+    rx
+}
+
+fn send(request: &HttpRequest) -> Result<HttpResponse, String> {
+    let method =
+        reqwest::Method::from_bytes(request.method.as_bytes()).map_err(|e| e.to_string())?;
+    let client = reqwest::blocking::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .build()
+        .map_err(|e| e.to_string())?;
+    let mut builder = client.request(method, &request.url);
+    for (name, value) in &request.headers {
+        builder = builder.header(name, value);
+    }
+    if !request.body.is_empty() {
+        builder = builder.body(request.body.clone());
+    }
+
+    let started = Instant::now();
+    let response = builder.send().map_err(|e| e.to_string())?;
+    let status = response.status();
+    let headers = response
+        .headers()
+        .iter()
+        .map(|(name, value)| {
+            let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
+            (name.to_string(), value)
+        })
+        .collect();
+    let body = response.text().map_err(|e| e.to_string())?;
+    let elapsed = started.elapsed();
+
+    Ok(HttpResponse {
+        status: format!(
+            "{} {}",
+            status.as_u16(),
+            status.canonical_reason().unwrap_or_default()
+        ),
+        status_code: status.as_u16(),
+        headers,
+        body: pretty_print(body),
+        elapsed,
+    })
+}
+
+/// Indent JSON bodies; anything else is returned unchanged
+fn pretty_print(body: String) -> String {
+    serde_json::from_str::<serde_json::Value>(&body)
+        .ok()
+        .and_then(|value| serde_json::to_string_pretty(&value).ok())
+        .unwrap_or(body)
+}
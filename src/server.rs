@@ -0,0 +1,250 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Path polled by the live-reload script injected into HTML pages
+const LIVE_RELOAD_PATH: &str = "/__live_reload";
+
+/// Reloads the page once the generation reported by the server changes
+const LIVE_RELOAD_SCRIPT: &str = r#"<script>
+(() => {
+  let generation = null;
+  setInterval(async () => {
+    try {
+      const current = await (await fetch("/__live_reload", { cache: "no-store" })).text();
+      if (generation !== null && current !== generation) location.reload();
+      generation = current;
+    } catch (_) {}
+  }, 1000);
+})();
+</script>"#;
+
+/// Minimal HTTP server for the files under a folder, bound to localhost.
+///
+/// HTML pages get a small script injected that reloads them after `reload` is
+/// called (e.g. when a file is saved). The server stops when dropped.
+pub struct StaticServer {
+    root: PathBuf,
+    addr: SocketAddr,
+    generation: Arc<AtomicU64>,
+    stopped: Arc<AtomicBool>,
+}
+
+impl StaticServer {
+    /// Port 0 picks any free port
+    pub fn start(root: PathBuf, port: u16) -> std::io::Result<Self> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+        let addr = listener.local_addr()?;
+        let generation = Arc::new(AtomicU64::new(0));
+        let stopped = Arc::new(AtomicBool::new(false));
+
+        let (thread_root, thread_generation, thread_stopped) =
+            (root.clone(), generation.clone(), stopped.clone());
+        std::thread::Builder::new()
+            .name("static-server".to_string())
+            .spawn(move || {
+                for stream in listener.incoming() {
+                    if thread_stopped.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let Ok(stream) = stream else {
+                        continue;
+                    };
+                    let (root, generation) = (thread_root.clone(), thread_generation.clone());
+                    std::thread::spawn(move || {
+                        let _ = handle_connection(stream, &root, &generation);
+                    });
+                }
+            })?;
+
+        Ok(Self {
+            root,
+            addr,
+            generation,
+            stopped,
+        })
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://{}/", self.addr)
+    }
+
+    /// URL of a file under the served folder
+    pub fn url_for(&self, path: &Path) -> Option<String> {
+        let relative = path.strip_prefix(&self.root).ok()?;
+        let segments: Vec<String> = relative
+            .components()
+            .map(|c| percent_encode(&c.as_os_str().to_string_lossy()))
+            .collect();
+        Some(format!("{}{}", self.url(), segments.join("/")))
+    }
+
+    /// Make open pages reload themselves
+    pub fn reload(&self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl Drop for StaticServer {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        // Wake up the accept loop so it sees the flag
+        let _ = TcpStream::connect(self.addr);
+    }
+}
+
+/// Open a URL in the default browser
+pub fn open_in_browser(url: &str) -> std::io::Result<()> {
+    let mut command = if cfg!(target_os = "windows") {
+        let mut command = std::process::Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else if cfg!(target_os = "macos") {
+        std::process::Command::new("open")
+    } else {
+        std::process::Command::new("xdg-open")
+    };
+    command.arg(url).spawn().map(|_| ())
+}
+
+fn handle_connection(
+    mut stream: TcpStream,
+    root: &Path,
+    generation: &AtomicU64,
+) -> std::io::Result<()> {
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return respond(&mut stream, "400 Bad Request", "text/plain", b"Bad Request");
+    };
+    if method != "GET" && method != "HEAD" {
+        return respond(
+            &mut stream,
+            "405 Method Not Allowed",
+            "text/plain",
+            b"Method Not Allowed",
+        );
+    }
+
+    let path = percent_decode(target.split(['?', '#']).next().unwrap_or("/"));
+    if path == LIVE_RELOAD_PATH {
+        let body = generation.load(Ordering::Relaxed).to_string();
+        return respond(&mut stream, "200 OK", "text/plain", body.as_bytes());
+    }
+
+    let Some(mut file) = resolve(root, &path) else {
+        return respond(&mut stream, "404 Not Found", "text/plain", b"Not Found");
+    };
+    if file.is_dir() {
+        file.push("index.html");
+    }
+    let Ok(mut body) = std::fs::read(&file) else {
+        return respond(&mut stream, "404 Not Found", "text/plain", b"Not Found");
+    };
+
+    let content_type = content_type(&file);
+    if content_type.starts_with("text/html") {
+        body = inject_live_reload(body);
+    }
+    if method == "HEAD" {
+        body.clear();
+    }
+    respond(&mut stream, "200 OK", content_type, &body)
+}
+
+fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+) -> std::io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\n\
+         Cache-Control: no-store\r\nConnection: close\r\n\r\n",
+        body.len()
+    )?;
+    stream.write_all(body)
+}
+
+/// Map a URL path to a file under `root`, refusing anything that escapes it
+fn resolve(root: &Path, url_path: &str) -> Option<PathBuf> {
+    let relative = Path::new(url_path.trim_start_matches('/'));
+    if relative
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_)))
+    {
+        return None;
+    }
+    Some(root.join(relative))
+}
+
+fn inject_live_reload(mut html: Vec<u8>) -> Vec<u8> {
+    let lowercase = html.to_ascii_lowercase();
+    let position = lowercase
+        .windows(b"</body>".len())
+        .rposition(|w| w == b"</body>")
+        .unwrap_or(html.len());
+    html.splice(position..position, LIVE_RELOAD_SCRIPT.bytes());
+    html
+}
+
+fn content_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    match extension.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "json" => "application/json",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "ico" => "image/x-icon",
+        "wasm" => "application/wasm",
+        "txt" | "md" => "text/plain; charset=utf-8",
+        _ => "application/octet-stream",
+    }
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn percent_encode(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
@@ -0,0 +1,518 @@
+use crate::theme::layout;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Directory holding the editor's configuration files
+pub fn config_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("rust_code_editor"))
+}
+
+/// Write `content` to a file next to `path` and move it over `path`, so a
+/// crash or kill mid-write leaves the old file rather than a truncated one
+pub fn write_atomic(path: &Path, content: &str) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    let temp = path.with_file_name(name);
+    let mut file = std::fs::File::create(&temp)?;
+    file.write_all(content.as_bytes())?;
+    file.sync_all()?;
+    std::fs::rename(&temp, path)
+}
+
+/// Which color theme the editor uses
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemeMode {
+    #[default]
+    Dark,
+    Light,
+    /// Follow the operating system's dark/light preference
+    Auto,
+}
+
+/// Key bindings of the code editor
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Keymap {
+    #[default]
+    Default,
+    /// Ctrl+A/E, Ctrl+K, Ctrl+Y, Alt+F/B and Ctrl+Space for the mark, over
+    /// the commands that have those shortcuts otherwise
+    Emacs,
+}
+
+/// Modifier that adds a caret where you click. Ctrl+Click adding carets was
+/// asked for first, but Go to Definition wants the same gesture, so the
+/// default follows VSCode: Alt+Click adds carets and Ctrl+Click goes to the
+/// definition, as in most editors and browsers that treat Ctrl+Click as
+/// following a link. `ctrl` swaps the two.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MultiCursorModifier {
+    #[default]
+    Alt,
+    Ctrl,
+}
+
+/// Text and overlay rendering tweaks, mostly relevant on fractional scale factors
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TextRendering {
+    /// Anti-alias shape edges (feathering)
+    pub anti_aliasing: bool,
+    /// Place glyphs on whole physical pixels
+    pub round_text_to_pixels: bool,
+    /// Align line highlights, selections, and carets to physical pixels
+    pub pixel_snapping: bool,
+}
+
+impl Default for TextRendering {
+    fn default() -> Self {
+        Self {
+            anti_aliasing: true,
+            round_text_to_pixels: true,
+            pixel_snapping: true,
+        }
+    }
+}
+
+/// Detection of UI freezes, see `watchdog.rs`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WatchdogSettings {
+    pub enabled: bool,
+    /// Seconds a frame may take before the UI thread counts as hung
+    pub timeout_secs: u64,
+    /// Write unsaved buffers to the recovery directory when a hang is detected
+    pub save_recovery: bool,
+}
+
+impl Default for WatchdogSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            timeout_secs: 10,
+            save_recovery: true,
+        }
+    }
+}
+
+/// Pull requests and issues in the Source Control view, see `forge.rs`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ForgeSettings {
+    /// Whether the view asks GitHub or GitLab for them
+    pub enabled: bool,
+    /// Name or URL of the remote the repository is at
+    pub remote: String,
+    /// Access tokens by host name, each sent only to its host. Without one,
+    /// `GITHUB_TOKEN` is used for github.com and `GITLAB_TOKEN` for
+    /// gitlab.com. Public repositories can be listed without one.
+    pub tokens: BTreeMap<String, String>,
+    /// Self-hosted GitHub Enterprise servers, by host name. Besides these
+    /// only github.com is asked.
+    pub github_hosts: Vec<String>,
+    /// Self-hosted GitLab servers, by host name, besides gitlab.com
+    pub gitlab_hosts: Vec<String>,
+}
+
+impl Default for ForgeSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            remote: "origin".to_string(),
+            tokens: BTreeMap::new(),
+            github_hosts: Vec::new(),
+            gitlab_hosts: Vec::new(),
+        }
+    }
+}
+
+/// Two kinds of files that belong together for Go > Switch to Counterpart,
+/// such as a source file and its test. Each pattern has `{}` where the files
+/// share a name and is matched against the end of a path, so it can include
+/// folders: `src/{}.rs` pairs with `tests/{}.rs` in the same crate.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CounterpartPair {
+    pub first: String,
+    pub second: String,
+    /// Text of a new `second` file, with `{}` replaced by the name
+    #[serde(default)]
+    pub template: String,
+}
+
+impl CounterpartPair {
+    fn new(first: &str, second: &str, template: &str) -> Self {
+        Self {
+            first: first.to_string(),
+            second: second.to_string(),
+            template: template.to_string(),
+        }
+    }
+}
+
+/// User settings, persisted as `settings.toml` in the config directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Draw the title bar (menus, title, window buttons) with egui instead of
+    /// using the native window decorations
+    pub custom_title_bar: bool,
+    pub theme: ThemeMode,
+    /// syntect theme used for syntax highlighting in dark mode
+    pub dark_syntax_theme: String,
+    /// syntect theme used for syntax highlighting in light mode
+    pub light_syntax_theme: String,
+    pub text_rendering: TextRendering,
+    pub keymap: Keymap,
+    pub multi_cursor_modifier: MultiCursorModifier,
+    /// Mark spaces, tabs and trailing whitespace in the editor
+    pub render_whitespace: bool,
+    /// Soft-wrap long lines at the width of the editor
+    pub word_wrap: bool,
+    /// Play a sound along with screen reader announcements of saves and
+    /// diagnostics
+    pub sound_cues: bool,
+    pub watchdog: WatchdogSettings,
+    /// Port for Serve Workspace Folder; 0 picks a free port
+    pub server_port: u16,
+    /// Language server command (program and arguments) per file extension
+    pub language_servers: BTreeMap<String, Vec<String>>,
+    /// Run the file's formatter before writing it
+    pub format_on_save: bool,
+    /// Formatter command per file extension; it reads the source on stdin and
+    /// prints the formatted source
+    pub formatters: BTreeMap<String, Vec<String>>,
+    /// Comment starts that Enter continues on the next line, per file
+    /// extension; `/*` continues block comments with ` * `
+    pub comment_continuation: BTreeMap<String, Vec<String>>,
+    /// Close tabs without unsaved changes when their file is deleted on disk,
+    /// instead of marking them
+    pub close_deleted_tabs: bool,
+    /// Keep panel sizes and visible views in `layout.toml` as they change
+    pub save_layout: bool,
+    /// Sign Commits, by workspace folder: sign every commit made there, or
+    /// never sign. Folders not listed leave it to git's `commit.gpgsign`.
+    pub sign_commits_in: BTreeMap<PathBuf, bool>,
+    /// Ask before a Replace All that makes more replacements than this; 0
+    /// never asks
+    pub confirm_replace_all_above: usize,
+    /// Cuts and copies kept for Paste from History
+    pub clipboard_history_size: usize,
+    /// Window width in points below which the sidebar no longer takes room
+    /// from the editor and opens over it instead; 0 always docks it
+    pub sidebar_overlay_below: f32,
+    /// Window width in points below which the minimap is hidden, and View >
+    /// Show Minimap shows it over the editor; 0 always docks it
+    pub minimap_overlay_below: f32,
+    /// Flick a tab up to close it or down to open it in the other editor
+    /// group, and shake it to close the other group
+    pub mouse_gestures: bool,
+    /// Files Switch to Counterpart goes between, most specific first
+    pub counterparts: Vec<CounterpartPair>,
+    /// Folders and globs left out of the Explorer, search and the symbol
+    /// index; `target/` only matches folders, `*.log` any file name and
+    /// `docs/gen` a path from the workspace folder
+    pub exclude: Vec<String>,
+    /// Show dotfiles and dot folders in the Explorer
+    pub show_hidden_files: bool,
+    pub forge: ForgeSettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            custom_title_bar: false,
+            theme: ThemeMode::default(),
+            dark_syntax_theme: "base16-ocean.dark".to_string(),
+            light_syntax_theme: "InspiredGitHub".to_string(),
+            text_rendering: TextRendering::default(),
+            keymap: Keymap::default(),
+            multi_cursor_modifier: MultiCursorModifier::default(),
+            render_whitespace: false,
+            word_wrap: false,
+            sound_cues: false,
+            watchdog: WatchdogSettings::default(),
+            server_port: 5500,
+            language_servers: BTreeMap::from([(
+                "rs".to_string(),
+                vec!["rust-analyzer".to_string()],
+            )]),
+            format_on_save: false,
+            formatters: BTreeMap::from([(
+                "rs".to_string(),
+                vec![
+                    "rustfmt".to_string(),
+                    "--edition".to_string(),
+                    "2021".to_string(),
+                ],
+            )]),
+            comment_continuation: default_comment_continuation(),
+            close_deleted_tabs: false,
+            save_layout: true,
+            sign_commits_in: BTreeMap::new(),
+            confirm_replace_all_above: 100,
+            clipboard_history_size: 20,
+            sidebar_overlay_below: 900.0,
+            minimap_overlay_below: 1100.0,
+            mouse_gestures: false,
+            counterparts: vec![
+                CounterpartPair::new("{}.rs", "{}_test.rs", ""),
+                CounterpartPair::new("src/{}.rs", "tests/{}.rs", "#[test]\nfn {}() {\n}\n"),
+                CounterpartPair::new("{}.h", "{}.cpp", "#include \"{}.h\"\n"),
+                CounterpartPair::new("{}.h", "{}.cc", "#include \"{}.h\"\n"),
+                CounterpartPair::new("{}.h", "{}.c", "#include \"{}.h\"\n"),
+                CounterpartPair::new("{}.hpp", "{}.cpp", "#include \"{}.hpp\"\n"),
+                CounterpartPair::new("{}.ts", "{}.test.ts", ""),
+                CounterpartPair::new("{}.js", "{}.test.js", ""),
+                CounterpartPair::new("{}.py", "test_{}.py", ""),
+            ],
+            exclude: [
+                "target/",
+                ".git/",
+                "node_modules/",
+                "dist/",
+                "build/",
+                "__pycache__/",
+            ]
+            .map(String::from)
+            .to_vec(),
+            show_hidden_files: true,
+            forge: ForgeSettings::default(),
+        }
+    }
+}
+
+/// Line and block comments of the languages the editor knows
+fn default_comment_continuation() -> BTreeMap<String, Vec<String>> {
+    let c_like: &[&str] = &["//", "/*"];
+    let hash: &[&str] = &["#"];
+    let languages: [(&[&str], &[&str]); 4] = [
+        (&["rs"], &["///", "//!", "//", "/*"]),
+        (
+            &[
+                "c", "h", "cc", "cpp", "hpp", "js", "jsx", "ts", "tsx", "go", "java", "cs",
+            ],
+            c_like,
+        ),
+        (&["css", "scss"], &["/*"]),
+        (&["py", "sh", "rb", "toml", "yaml", "yml"], hash),
+    ];
+    languages
+        .into_iter()
+        .flat_map(|(extensions, prefixes)| {
+            extensions.iter().map(move |ext| {
+                let prefixes = prefixes.iter().map(|p| p.to_string()).collect();
+                (ext.to_string(), prefixes)
+            })
+        })
+        .collect()
+}
+
+impl Settings {
+    fn path() -> Option<PathBuf> {
+        config_dir().map(|dir| dir.join("settings.toml"))
+    }
+
+    /// Load settings from disk, falling back to defaults for a missing file.
+    /// For a file that doesn't parse, the defaults come with the error.
+    pub fn load() -> (Self, Option<String>) {
+        let Some(content) = Self::path().and_then(|path| std::fs::read_to_string(path).ok()) else {
+            return (Self::default(), None);
+        };
+        match toml::from_str(&content) {
+            Ok(settings) => (settings, None),
+            Err(e) => (
+                Self::default(),
+                Some(format!("Could not load settings.toml: {e}")),
+            ),
+        }
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let Some(path) = Self::path() else {
+            return Ok(());
+        };
+        let content = toml::to_string_pretty(self)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        write_atomic(&path, &content)
+    }
+}
+
+/// Window layout, persisted as `layout.toml` in the config directory
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Layout {
+    /// Format of the file; files written in another format are ignored
+    pub version: u32,
+    pub sidebar_width: f32,
+    pub outline_height: f32,
+    pub output_height: f32,
+    pub references_height: f32,
+    /// Sidebar visibility, toggled with Ctrl+B
+    pub show_sidebar: bool,
+    pub show_minimap: bool,
+    pub show_output: bool,
+    /// Titles of the panels shown in windows of their own
+    pub floating_panels: Vec<String>,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self {
+            version: Self::VERSION,
+            sidebar_width: layout::SIDEBAR_DEFAULT_WIDTH,
+            outline_height: layout::OUTLINE_DEFAULT_HEIGHT,
+            output_height: layout::OUTPUT_DEFAULT_HEIGHT,
+            references_height: layout::REFERENCES_DEFAULT_HEIGHT,
+            show_sidebar: true,
+            show_minimap: true,
+            show_output: false,
+            floating_panels: Vec::new(),
+        }
+    }
+}
+
+impl Layout {
+    /// Bump when a field changes meaning, so older files are dropped rather
+    /// than misread
+    pub const VERSION: u32 = 1;
+
+    fn path() -> Option<PathBuf> {
+        config_dir().map(|dir| dir.join("layout.toml"))
+    }
+
+    /// Load the saved layout, falling back to the default for missing, invalid
+    /// or differently versioned files
+    pub fn load() -> Self {
+        Self::path()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|content| content.parse::<toml::Table>().ok())
+            .filter(|table| {
+                table.get("version").and_then(toml::Value::as_integer) == Some(Self::VERSION.into())
+            })
+            .and_then(|table| table.try_into().ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let Some(path) = Self::path() else {
+            return Ok(());
+        };
+        let content = toml::to_string_pretty(self)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        write_atomic(&path, &content)
+    }
+}
+
+/// A named set of open files and the layout around them
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workspace: Option<PathBuf>,
+    /// File of the active tab
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active: Option<PathBuf>,
+    /// Files of the open tabs, in tab order
+    pub files: Vec<PathBuf>,
+    pub layout: Layout,
+}
+
+/// Named sessions, persisted as `sessions.toml` in the config directory
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Sessions {
+    pub sessions: BTreeMap<String, Session>,
+}
+
+impl Sessions {
+    fn path() -> Option<PathBuf> {
+        config_dir().map(|dir| dir.join("sessions.toml"))
+    }
+
+    /// Load the saved sessions, with none for missing or invalid files
+    pub fn load() -> Self {
+        Self::path()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|content| toml::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let Some(path) = Self::path() else {
+            return Ok(());
+        };
+        let content = toml::to_string_pretty(self)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        write_atomic(&path, &content)
+    }
+}
+
+/// Environment variables for the processes the editor starts, per workspace
+/// folder, persisted as `environments.toml` in the config directory. They
+/// aren't read from the workspace, where a cloned repository could set
+/// `LD_PRELOAD` or `RUSTC_WRAPPER` and secrets could get committed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Environments {
+    pub workspaces: BTreeMap<PathBuf, BTreeMap<String, String>>,
+}
+
+impl Environments {
+    fn path() -> Option<PathBuf> {
+        config_dir().map(|dir| dir.join("environments.toml"))
+    }
+
+    /// Load the saved variables, with none for missing or invalid files
+    pub fn load() -> Self {
+        Self::path()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|content| toml::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let Some(path) = Self::path() else {
+            return Ok(());
+        };
+        let content = toml::to_string_pretty(self)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        write_atomic(&path, &content)
+    }
+
+    /// Variables of `workspace`
+    pub fn of(workspace: &Path) -> BTreeMap<String, String> {
+        Self::load()
+            .workspaces
+            .remove(workspace)
+            .unwrap_or_default()
+    }
+}
+
+/// Per-project overrides, read from `.rust_code_editor.toml` in the workspace folder
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct WorkspaceSettings {
+    /// Overrides `Settings::format_on_save`, e.g. to opt a project out
+    pub format_on_save: Option<bool>,
+    /// Replaces `Settings::exclude` for this project
+    pub exclude: Option<Vec<String>>,
+}
+
+impl WorkspaceSettings {
+    pub const FILE_NAME: &'static str = ".rust_code_editor.toml";
+
+    /// Load the workspace's overrides, with none for missing or invalid files
+    pub fn load(workspace: &Path) -> Self {
+        std::fs::read_to_string(workspace.join(Self::FILE_NAME))
+            .ok()
+            .and_then(|content| toml::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+}
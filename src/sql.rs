@@ -0,0 +1,150 @@
+use rusqlite::types::ValueRef;
+use rusqlite::{Connection, OpenFlags};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant};
+
+/// Rows kept from a result; the rest are counted but not shown
+const MAX_ROWS: usize = 1000;
+
+/// Outcome of the last statement of a query
+#[derive(Debug, Clone, Default)]
+pub struct QueryResult {
+    /// Empty for statements that return no rows
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+    /// Rows returned beyond `MAX_ROWS`
+    pub truncated_rows: usize,
+    /// Rows changed by an INSERT, UPDATE or DELETE
+    pub rows_affected: usize,
+    pub statements: usize,
+    pub elapsed: Duration,
+}
+
+/// Byte ranges of the statements in a script, split at semicolons outside of
+/// quotes and comments. Ranges include the semicolon and skip leading whitespace.
+pub fn statement_ranges(sql: &str) -> Vec<Range<usize>> {
+    let bytes = sql.as_bytes();
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            quote @ (b'\'' | b'"' | b'`') => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != quote {
+                    i += 1;
+                }
+            }
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i += 2;
+                while i < bytes.len() && !(bytes[i] == b'*' && bytes.get(i + 1) == Some(&b'/')) {
+                    i += 1;
+                }
+                i += 1;
+            }
+            b';' => {
+                ranges.push(start..i + 1);
+                start = i + 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    ranges.push(start..sql.len());
+
+    ranges
+        .into_iter()
+        .map(|range| {
+            let text = &sql[range.clone()];
+            range.start + (text.len() - text.trim_start().len())..range.end
+        })
+        .filter(|range| !is_blank(&sql[range.clone()]))
+        .collect()
+}
+
+/// Only whitespace, comments and semicolons
+fn is_blank(statement: &str) -> bool {
+    statement
+        .lines()
+        .map(|line| line.split("--").next().unwrap_or_default().trim())
+        .all(|line| line.is_empty() || line == ";")
+}
+
+/// Run a query against a database file on a worker thread; the result arrives on
+/// the returned channel and `ctx` is repainted then
+pub fn run_in_background(
+    database: PathBuf,
+    sql: String,
+    ctx: egui::Context,
+) -> Receiver<Result<QueryResult, String>> {
+    let (tx, rx) = mpsc::channel();
+    let spawned = std::thread::Builder::new()
+        .name("sql-query".to_string())
+        .spawn(move || {
+            let _ = tx.send(run(&database, &sql).map_err(|e| e.to_string()));
+            ctx.request_repaint();
+        });
+    if let Err(e) = spawned {
+        let (tx, failed) = mpsc::channel();
+        let _ = tx.send(Err(e.to_string()));
+        return failed;
+    }
+    rx
+}
+
+/// Run every statement in `sql` and return the result of the last one
+fn run(database: &Path, sql: &str) -> rusqlite::Result<QueryResult> {
+    // Don't create a new database for a mistyped path
+    let connection = Connection::open_with_flags(
+        database,
+        OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_URI,
+    )?;
+    let started = Instant::now();
+    let mut result = QueryResult::default();
+    for range in statement_ranges(sql) {
+        let mut statement = connection.prepare(&sql[range])?;
+        result = QueryResult {
+            columns: statement
+                .column_names()
+                .into_iter()
+                .map(str::to_string)
+                .collect(),
+            statements: result.statements + 1,
+            ..Default::default()
+        };
+        if result.columns.is_empty() {
+            result.rows_affected = statement.execute([])?;
+            continue;
+        }
+        let mut rows = statement.query([])?;
+        while let Some(row) = rows.next()? {
+            if result.rows.len() == MAX_ROWS {
+                result.truncated_rows += 1;
+                continue;
+            }
+            let values = (0..result.columns.len())
+                .map(|i| row.get_ref(i).map(format_value))
+                .collect::<rusqlite::Result<_>>()?;
+            result.rows.push(values);
+        }
+    }
+    result.elapsed = started.elapsed();
+    Ok(result)
+}
+
+fn format_value(value: ValueRef) -> String {
+    match value {
+        ValueRef::Null => "NULL".to_string(),
+        ValueRef::Integer(i) => i.to_string(),
+        ValueRef::Real(f) => f.to_string(),
+        ValueRef::Text(text) => String::from_utf8_lossy(text).into_owned(),
+        ValueRef::Blob(blob) => format!("<{} bytes>", blob.len()),
+    }
+}
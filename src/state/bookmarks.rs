@@ -0,0 +1,89 @@
+use ropey::Rope;
+
+/// Bookmarked lines of a file. Each bookmark is the offset of its line's
+/// start in the text it was last moved in, and follows edits around it.
+#[derive(Debug, Clone, Default)]
+pub struct Bookmarks {
+    /// Sorted char offsets of line starts in `text`
+    offsets: Vec<usize>,
+    /// Text the offsets point into, and its revision
+    text: Rope,
+    revision: Option<u64>,
+}
+
+impl Bookmarks {
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    /// Bookmark line `line_idx` (zero-based) of `rope` at `revision`, or
+    /// remove its bookmark
+    pub fn toggle(&mut self, rope: &Rope, revision: u64, line_idx: usize) {
+        self.update(rope, revision);
+        let offset = rope.line_to_char(line_idx.min(rope.len_lines() - 1));
+        match self.offsets.binary_search(&offset) {
+            Ok(i) => {
+                self.offsets.remove(i);
+            }
+            Err(i) => self.offsets.insert(i, offset),
+        }
+    }
+
+    /// Zero-based lines of the bookmarks, in order, for the text they were
+    /// last moved in
+    pub fn lines(&self) -> Vec<usize> {
+        self.offsets
+            .iter()
+            .map(|&offset| self.text.char_to_line(offset))
+            .collect()
+    }
+
+    /// Move the bookmarks from the text they were set in to `rope`, which
+    /// `revision` identifies. Text between the first and last difference
+    /// counts as replaced: bookmarks before it stay, those after it move by
+    /// the change in length, and those inside go to the line it starts on.
+    /// Bookmarks that end up on the same line are merged.
+    pub fn update(&mut self, rope: &Rope, revision: u64) {
+        if self.revision == Some(revision) {
+            return;
+        }
+        if !self.offsets.is_empty() {
+            let (old, new) = (&self.text, rope);
+            let prefix = old
+                .chars()
+                .zip(new.chars())
+                .take_while(|(a, b)| a == b)
+                .count();
+            let max_suffix = old.len_chars().min(new.len_chars()) - prefix;
+            let suffix = old
+                .chars_at(old.len_chars())
+                .reversed()
+                .zip(new.chars_at(new.len_chars()).reversed())
+                .take(max_suffix)
+                .take_while(|(a, b)| a == b)
+                .count();
+            let old_end = old.len_chars() - suffix;
+            let new_end = new.len_chars() - suffix;
+
+            let mut offsets: Vec<usize> = self
+                .offsets
+                .iter()
+                .map(|&offset| {
+                    // Text inserted at the start of a line pushes it down
+                    let moved = if offset >= old_end {
+                        offset - old_end + new_end
+                    } else if offset <= prefix {
+                        offset
+                    } else {
+                        prefix
+                    };
+                    new.line_to_char(new.char_to_line(moved))
+                })
+                .collect();
+            offsets.dedup();
+            self.offsets = offsets;
+        }
+        self.text = rope.clone();
+        self.revision = Some(revision);
+    }
+}
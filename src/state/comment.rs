@@ -0,0 +1,78 @@
+use ropey::Rope;
+
+/// Lines looked back over for the `/*` of the block comment a line is in
+const MAX_BLOCK_LINES: usize = 1000;
+
+/// Text to start line `line_idx` with, in place of its leading whitespace,
+/// after Enter split a comment line above it. Line comments continue with
+/// the first of `prefixes` the line above starts with, which has to be
+/// followed by whitespace, as in `/// ` or `# `. With `/*` among `prefixes`,
+/// lines in a block comment continue with ` * `.
+pub fn continuation(rope: &Rope, line_idx: usize, prefixes: &[String]) -> Option<String> {
+    let above = rope.line(line_idx.checked_sub(1)?).to_string();
+    let above = above.trim_end_matches(['\n', '\r']);
+    let trimmed = above.trim_start();
+    let indent = &above[..above.len() - trimmed.len()];
+
+    let block = prefixes.iter().any(|prefix| prefix == "/*");
+    if block {
+        if let Some(rest) = trimmed.strip_prefix("/*") {
+            if rest.contains("*/") {
+                return None;
+            }
+            return Some(format!("{indent}{}", block_line(rope, line_idx, " * ")));
+        }
+        let continues = trimmed.starts_with('*') && !trimmed.starts_with("*/");
+        if continues && !trimmed.contains("*/") && in_block_comment(rope, line_idx - 1) {
+            return Some(format!("{indent}{}", block_line(rope, line_idx, "* ")));
+        }
+    }
+
+    let mut prefixes: Vec<&str> = prefixes
+        .iter()
+        .map(String::as_str)
+        .filter(|prefix| *prefix != "/*")
+        .collect();
+    // `///` before `//`
+    prefixes.sort_by_key(|prefix| std::cmp::Reverse(prefix.len()));
+    prefixes.into_iter().find_map(|prefix| {
+        let rest = trimmed.strip_prefix(prefix)?;
+        let space = &rest[..rest.len() - rest.trim_start().len()];
+        (rest.is_empty() || !space.is_empty()).then(|| format!("{indent}{prefix}{space}"))
+    })
+}
+
+/// Whether `/` typed right before `offset` follows the ` * ` that continues a
+/// block comment, so the space in between should go to close it as ` */`
+pub fn closes_block(rope: &Rope, offset: usize) -> bool {
+    let line_idx = rope.char_to_line(offset);
+    let before = rope.slice(rope.line_to_char(line_idx)..offset).to_string();
+    before.trim_start() == "* /" && in_block_comment(rope, line_idx)
+}
+
+/// The continuation for a line of a block comment; ` */` on it already only
+/// needs the indentation of the `*` lines
+fn block_line<'a>(rope: &Rope, line_idx: usize, star: &'a str) -> &'a str {
+    let rest = rope.line(line_idx).to_string();
+    if rest.trim_start().starts_with("*/") {
+        &star[..star.len() - "* ".len()]
+    } else {
+        star
+    }
+}
+
+/// Whether a `/*` before line `line_idx` is still open at its start
+fn in_block_comment(rope: &Rope, line_idx: usize) -> bool {
+    for idx in (line_idx.saturating_sub(MAX_BLOCK_LINES)..line_idx).rev() {
+        let line = rope.line(idx).to_string();
+        let opened = line.rfind("/*");
+        let closed = line.rfind("*/");
+        match (opened, closed) {
+            (Some(open), Some(close)) => return open > close,
+            (Some(_), None) => return true,
+            (None, Some(_)) => return false,
+            (None, None) => {}
+        }
+    }
+    false
+}
@@ -0,0 +1,150 @@
+use ropey::Rope;
+use std::collections::BTreeSet;
+use std::path::Path;
+
+/// Most candidates shown in the completion popup
+const MAX_ITEMS: usize = 50;
+
+/// A completion candidate
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompletionItem {
+    /// Shown in the popup and matched against the typed prefix
+    pub label: String,
+    /// Replaces the typed prefix when accepted
+    pub insert_text: String,
+    /// Type or signature, shown dimmed next to the label
+    pub detail: Option<String>,
+}
+
+impl CompletionItem {
+    pub fn word(word: &str) -> Self {
+        Self {
+            label: word.to_string(),
+            insert_text: word.to_string(),
+            detail: None,
+        }
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Start of the identifier that ends at `offset`
+pub fn word_start(rope: &Rope, offset: usize) -> usize {
+    let mut start = offset.min(rope.len_chars());
+    while start > 0 && is_word_char(rope.char(start - 1)) {
+        start -= 1;
+    }
+    start
+}
+
+/// Whether typing the character before `offset` should open completions:
+/// an identifier character, `.`, or `::`
+pub fn is_trigger(rope: &Rope, offset: usize) -> bool {
+    match offset.checked_sub(1).map(|i| rope.char(i)) {
+        Some(c) if is_word_char(c) || c == '.' => true,
+        Some(':') => offset >= 2 && rope.char(offset - 2) == ':',
+        _ => false,
+    }
+}
+
+/// Every distinct identifier in the buffers, for completion without a language
+/// server
+pub fn buffer_words<'a>(buffers: impl IntoIterator<Item = &'a Rope>) -> Vec<CompletionItem> {
+    let mut words = BTreeSet::new();
+    for buffer in buffers {
+        let mut word = String::new();
+        for c in buffer.chars().chain(std::iter::once(' ')) {
+            if is_word_char(c) {
+                word.push(c);
+            } else if !word.is_empty() {
+                // Skip numbers and one-letter names
+                if word.chars().count() > 1 && !word.starts_with(|c: char| c.is_ascii_digit()) {
+                    words.insert(std::mem::take(&mut word));
+                } else {
+                    word.clear();
+                }
+            }
+        }
+    }
+    words.iter().map(|w| CompletionItem::word(w)).collect()
+}
+
+/// Relative path being typed in a string literal
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathPrefix {
+    /// Directory part up to and including the last `/`, e.g. `./src/`
+    pub dir: String,
+    /// Start of the file name after it, which completions replace
+    pub segment_start: usize,
+}
+
+/// The path in the string literal the caret at `offset` is in, when the string
+/// starts like a relative path (`./` or `../`)
+pub fn path_prefix(rope: &Rope, offset: usize) -> Option<PathPrefix> {
+    let line_start = rope.line_to_char(rope.char_to_line(offset));
+    let before: Vec<char> = rope.slice(line_start..offset).chars().collect();
+
+    // The nearest quote opens the string if an even number of the same quote
+    // (not escaped) comes before it
+    let quote = before.iter().rposition(|c| matches!(c, '"' | '\'' | '`'))?;
+    let quotes_before = before[..quote]
+        .iter()
+        .enumerate()
+        .filter(|(i, c)| **c == before[quote] && (*i == 0 || before[i - 1] != '\\'))
+        .count();
+    if quotes_before % 2 != 0 {
+        return None;
+    }
+    let text: String = before[quote + 1..].iter().collect();
+    if !text.starts_with("./") && !text.starts_with("../") {
+        return None;
+    }
+    let dir = &text[..=text.rfind('/')?];
+    Some(PathPrefix {
+        dir: dir.to_string(),
+        segment_start: line_start + quote + 1 + dir.chars().count(),
+    })
+}
+
+/// Entries of `base/dir`, folders first and ending in `/` so completing one
+/// continues into it
+pub fn path_items(base: &Path, dir: &str) -> Vec<CompletionItem> {
+    let Ok(entries) = std::fs::read_dir(base.join(dir)) else {
+        return Vec::new();
+    };
+    let mut entries: Vec<(bool, String)> = entries
+        .flatten()
+        .map(|entry| {
+            let is_dir = entry.path().is_dir();
+            (is_dir, entry.file_name().to_string_lossy().into_owned())
+        })
+        .collect();
+    entries.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+    entries
+        .into_iter()
+        .map(|(is_dir, name)| {
+            let label = if is_dir { format!("{name}/") } else { name };
+            CompletionItem {
+                insert_text: label.clone(),
+                detail: Some(if is_dir { "folder" } else { "file" }.to_string()),
+                label,
+            }
+        })
+        .collect()
+}
+
+/// Candidates starting with the typed prefix (case-insensitively), keeping their
+/// order. A candidate that is exactly the prefix has nothing left to complete.
+pub fn filter(candidates: &[CompletionItem], prefix: &str) -> Vec<CompletionItem> {
+    let lowercase_prefix = prefix.to_lowercase();
+    candidates
+        .iter()
+        .filter(|item| {
+            item.label != prefix && item.label.to_lowercase().starts_with(&lowercase_prefix)
+        })
+        .take(MAX_ITEMS)
+        .cloned()
+        .collect()
+}
@@ -0,0 +1,53 @@
+/// Characters that can't be seen, or that change how the text around them is
+/// shown, so that code reads differently than it runs ("trojan source")
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlChar {
+    /// C0 and C1 control codes other than tab and line breaks, such as NUL,
+    /// vertical tab and form feed
+    Control,
+    /// Zero-width spaces and joiners, and the byte order mark mid-text
+    ZeroWidth,
+    /// Bidirectional embeddings, overrides, isolates and marks
+    Bidi,
+    /// U+FFFD, which stands for bytes that weren't valid text
+    Replacement,
+}
+
+impl ControlChar {
+    pub fn of(c: char) -> Option<Self> {
+        match c {
+            '\t' | '\n' | '\r' => None,
+            '\u{0}'..='\u{1F}' | '\u{7F}'..='\u{9F}' => Some(Self::Control),
+            '\u{200B}'..='\u{200D}' | '\u{2060}' | '\u{FEFF}' => Some(Self::ZeroWidth),
+            '\u{061C}' | '\u{200E}' | '\u{200F}' | '\u{202A}'..='\u{202E}' => Some(Self::Bidi),
+            '\u{2066}'..='\u{2069}' => Some(Self::Bidi),
+            '\u{FFFD}' => Some(Self::Replacement),
+            _ => None,
+        }
+    }
+
+    /// Glyph drawn in place of the character, taking one column
+    pub fn placeholder(self) -> char {
+        match self {
+            Self::Control => '¤',
+            Self::ZeroWidth => '¦',
+            Self::Bidi => '↔',
+            Self::Replacement => '▯',
+        }
+    }
+
+    /// Warning shown for a character of this kind
+    pub fn warning(self, c: char) -> String {
+        let what = match self {
+            Self::Control => "Control character",
+            Self::ZeroWidth => "Invisible zero-width character",
+            Self::Bidi => {
+                "Bidirectional text control, which can reorder how the text after it shows"
+            }
+            Self::Replacement => {
+                "Replacement character, where the file had bytes that weren't text"
+            }
+        };
+        format!("{what} (U+{:04X})", c as u32)
+    }
+}
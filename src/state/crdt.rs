@@ -0,0 +1,289 @@
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// Replica id of the characters every replica starts with
+const BASE_REPLICA: u32 = 0;
+
+/// Identity of a character: a Lamport timestamp and the replica that
+/// inserted it. Ids only ever grow, so later inserts compare greater.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct CharId {
+    pub counter: u64,
+    pub replica: u32,
+}
+
+/// Change made by one replica, applied as-is by the others
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Op {
+    /// `ch` goes right after the character `after` (None for the start)
+    Insert {
+        id: CharId,
+        after: Option<CharId>,
+        ch: char,
+    },
+    Delete {
+        id: CharId,
+    },
+}
+
+/// What applying an op did to the visible text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    Inserted { offset: usize, ch: char },
+    Deleted { offset: usize },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Element {
+    id: CharId,
+    ch: char,
+    /// Deleted characters stay as tombstones so later inserts can refer to them
+    deleted: bool,
+}
+
+/// Copy of a text that can be edited independently of the other copies and
+/// still end up identical to them once every replica has applied every
+/// other's ops, in any order that keeps each replica's own ops in sequence.
+/// A replicated growable array: characters are kept in document order with
+/// tombstones, and each operation is a linear scan, which is fine for the
+/// edits of a session but not for a file's whole history.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Replica {
+    id: u32,
+    counter: u64,
+    elements: Vec<Element>,
+}
+
+impl Replica {
+    /// Replica `id` of `text`. Replicas of the same text share its
+    /// characters, so their ops apply to each other; `id` must be unique
+    /// among them and not 0.
+    pub fn new(id: u32, text: &str) -> Self {
+        let elements: Vec<Element> = text
+            .chars()
+            .enumerate()
+            .map(|(i, ch)| Element {
+                id: CharId {
+                    counter: i as u64 + 1,
+                    replica: BASE_REPLICA,
+                },
+                ch,
+                deleted: false,
+            })
+            .collect();
+        Self {
+            id,
+            counter: elements.len() as u64,
+            elements,
+        }
+    }
+
+    /// Copy of this replica, characters and tombstones included, that edits
+    /// as replica `id`. Replicas joining later start from a fork.
+    pub fn fork(&self, id: u32) -> Self {
+        Self { id, ..self.clone() }
+    }
+
+    pub fn text(&self) -> String {
+        self.visible().map(|element| element.ch).collect()
+    }
+
+    /// Insert `text` at char offset `offset`
+    pub fn insert(&mut self, offset: usize, text: &str) -> Vec<Op> {
+        let mut after = offset
+            .checked_sub(1)
+            .and_then(|i| self.visible().nth(i))
+            .map(|element| element.id);
+        let mut ops = Vec::new();
+        for ch in text.chars() {
+            self.counter += 1;
+            let id = CharId {
+                counter: self.counter,
+                replica: self.id,
+            };
+            let op = Op::Insert { id, after, ch };
+            self.apply(&op);
+            ops.push(op);
+            after = Some(id);
+        }
+        ops
+    }
+
+    /// Delete the chars in `range`
+    pub fn delete(&mut self, range: Range<usize>) -> Vec<Op> {
+        let ops: Vec<Op> = self
+            .visible()
+            .skip(range.start)
+            .take(range.len())
+            .map(|element| Op::Delete { id: element.id })
+            .collect();
+        for op in &ops {
+            self.apply(op);
+        }
+        ops
+    }
+
+    /// Replace the chars in `range` with `text`
+    pub fn replace(&mut self, range: Range<usize>, text: &str) -> Vec<Op> {
+        let start = range.start;
+        let mut ops = self.delete(range);
+        ops.extend(self.insert(start, text));
+        ops
+    }
+
+    /// Apply an op from this or another replica, returning the change to the
+    /// text. Ops that were already applied, or refer to characters this
+    /// replica hasn't seen, are ignored.
+    pub fn apply(&mut self, op: &Op) -> Option<Change> {
+        match *op {
+            Op::Insert { id, after, ch } => {
+                if self.position(id).is_some() {
+                    return None;
+                }
+                let mut index = match after {
+                    Some(after) => self.position(after)? + 1,
+                    None => 0,
+                };
+                // Concurrent inserts at the same place go in descending id
+                // order, and so do the characters typed after those
+                while self.elements.get(index).is_some_and(|e| e.id > id) {
+                    index += 1;
+                }
+                self.elements.insert(
+                    index,
+                    Element {
+                        id,
+                        ch,
+                        deleted: false,
+                    },
+                );
+                self.counter = self.counter.max(id.counter);
+                Some(Change::Inserted {
+                    offset: self.offset(index),
+                    ch,
+                })
+            }
+            Op::Delete { id } => {
+                let i = self.position(id).filter(|&i| !self.elements[i].deleted)?;
+                self.elements[i].deleted = true;
+                Some(Change::Deleted {
+                    offset: self.offset(i),
+                })
+            }
+        }
+    }
+
+    /// Char offset in the visible text of the element at `index`
+    fn offset(&self, index: usize) -> usize {
+        self.elements[..index].iter().filter(|e| !e.deleted).count()
+    }
+
+    fn position(&self, id: CharId) -> Option<usize> {
+        self.elements.iter().position(|element| element.id == id)
+    }
+
+    fn visible(&self) -> impl Iterator<Item = &Element> {
+        self.elements.iter().filter(|element| !element.deleted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Apply `ops` from another replica
+    fn receive(replica: &mut Replica, ops: &[Op]) {
+        for op in ops {
+            replica.apply(op);
+        }
+    }
+
+    #[test]
+    fn replicas_converge_whatever_order_ops_arrive_in() {
+        let base = Replica::new(1, "hello world");
+        let (mut a, mut b, mut c) = (base.fork(1), base.fork(2), base.fork(3));
+        let from_a = a.replace(0..5, "goodbye");
+        let mut from_b = b.insert(11, "!");
+        from_b.extend(b.delete(5..6));
+        let from_c = c.insert(6, "big ");
+
+        receive(&mut a, &from_c);
+        receive(&mut a, &from_b);
+        receive(&mut b, &from_a);
+        receive(&mut b, &from_c);
+        receive(&mut c, &from_b);
+        receive(&mut c, &from_a);
+        assert_eq!(a.text(), "goodbyebig world!");
+        assert_eq!(b.text(), a.text());
+        assert_eq!(c.text(), a.text());
+    }
+
+    #[test]
+    fn concurrent_inserts_at_the_same_place_stay_whole() {
+        let base = Replica::new(1, "ab");
+        let (mut a, mut b) = (base.fork(1), base.fork(2));
+        let from_a = a.insert(1, "xyz");
+        let from_b = b.insert(1, "123");
+        receive(&mut a, &from_b);
+        receive(&mut b, &from_a);
+        assert_eq!(a.text(), b.text());
+        // Neither run of typing is interleaved with the other
+        assert!(
+            a.text() == "a123xyzb" || a.text() == "axyz123b",
+            "{}",
+            a.text()
+        );
+    }
+
+    #[test]
+    fn insert_after_a_concurrently_deleted_char_is_kept() {
+        let base = Replica::new(1, "abc");
+        let (mut a, mut b) = (base.fork(1), base.fork(2));
+        let from_a = a.delete(1..2);
+        let from_b = b.insert(2, "X");
+        receive(&mut a, &from_b);
+        receive(&mut b, &from_a);
+        assert_eq!(a.text(), "aXc");
+        assert_eq!(b.text(), "aXc");
+    }
+
+    #[test]
+    fn deleting_the_same_char_twice_changes_nothing_more() {
+        let base = Replica::new(1, "abc");
+        let (mut a, mut b) = (base.fork(1), base.fork(2));
+        let from_a = a.delete(0..1);
+        let from_b = b.delete(0..2);
+        let changes: Vec<_> = from_b.iter().filter_map(|op| a.apply(op)).collect();
+        assert_eq!(changes, [Change::Deleted { offset: 0 }]);
+        receive(&mut b, &from_a);
+        assert_eq!(a.text(), "c");
+        assert_eq!(b.text(), "c");
+    }
+
+    #[test]
+    fn reported_changes_turn_the_text_into_the_replicas() {
+        let base = Replica::new(1, "one two");
+        let (mut a, mut b) = (base.fork(1), base.fork(2));
+        let from_a = a.replace(4..7, "three");
+        let mut text: Vec<char> = b.text().chars().collect();
+        for op in &from_a {
+            match b.apply(op) {
+                Some(Change::Inserted { offset, ch }) => text.insert(offset, ch),
+                Some(Change::Deleted { offset }) => {
+                    text.remove(offset);
+                }
+                None => {}
+            }
+        }
+        assert_eq!(text.into_iter().collect::<String>(), "one three");
+        assert_eq!(b.text(), "one three");
+    }
+
+    #[test]
+    fn ops_applied_again_are_ignored() {
+        let mut a = Replica::new(1, "ab");
+        let ops = a.insert(1, "x");
+        assert_eq!(a.apply(&ops[0]), None);
+        assert_eq!(a.text(), "axb");
+    }
+}
@@ -1,23 +1,32 @@
+use super::wrap::WrapLayout;
 use ropey::Rope;
+use std::ops::Range;
 
 /// Tracks cursor position within the editor (1-indexed for display)
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct CursorPosition {
     pub line: usize,
     pub column: usize,
     pub offset: usize,
+    /// Character offset where the selection started (equal to `offset` when nothing is selected)
+    pub anchor: usize,
+    /// Column (0-indexed) that Up/Down aim for, kept while passing through shorter lines
+    pub goal_column: Option<usize>,
 }
 
 impl CursorPosition {
     /// Convert character offset to line/column using ropey::Rope
     pub fn from_char_offset(rope: &Rope, offset: usize) -> Self {
-        let safe_offset = offset.min(rope.len_chars().saturating_sub(1).max(0));
+        // The caret may sit just past the last character
+        let safe_offset = offset.min(rope.len_chars());
 
         if rope.len_chars() == 0 {
             return Self {
                 line: 1,
                 column: 1,
                 offset: 0,
+                anchor: 0,
+                goal_column: None,
             };
         }
 
@@ -29,9 +38,40 @@ impl CursorPosition {
             line: line_idx + 1,
             column,
             offset: safe_offset,
+            anchor: safe_offset,
+            goal_column: None,
         }
     }
 
+    /// Visual row of the caret when lines are soft-wrapped, and its column
+    /// (0-indexed) within that row
+    pub fn visual_position(&self, wrap: &WrapLayout) -> (usize, usize) {
+        let column = self.column.saturating_sub(1);
+        let row = wrap.row_at(self.line.saturating_sub(1), column);
+        (row, column - wrap.row_columns(row).start)
+    }
+
+    /// Keep a selection anchor while the caret moves
+    pub fn with_anchor(mut self, anchor: usize) -> Self {
+        self.anchor = anchor;
+        self
+    }
+
+    /// Remember the column vertical moves should return to
+    pub fn with_goal_column(mut self, goal_column: Option<usize>) -> Self {
+        self.goal_column = goal_column;
+        self
+    }
+
+    pub fn has_selection(&self) -> bool {
+        self.anchor != self.offset
+    }
+
+    /// Selected character range, ordered from start to end
+    pub fn selection(&self) -> Range<usize> {
+        self.anchor.min(self.offset)..self.anchor.max(self.offset)
+    }
+
     /// Format as "Ln X, Col Y" for status bar display
     pub fn display(&self) -> String {
         format!("Ln {}, Col {}", self.line, self.column)
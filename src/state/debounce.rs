@@ -0,0 +1,68 @@
+use std::time::{Duration, Instant};
+
+/// How long a buffer has to stay unchanged before work put off by a
+/// `Debouncer` runs
+pub const DEBOUNCE_DELAY: Duration = Duration::from_millis(150);
+
+/// What a `Debouncer` says about the work for a revision
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Debounce {
+    /// Do the work now
+    Ready,
+    /// Typing is still going on; ask again after this long
+    Wait(Duration),
+    /// The work was already done for the revision
+    Done,
+}
+
+/// Puts off work on a buffer while it is being edited: each new revision
+/// restarts the wait, so a burst of typing redoes the work once, after the
+/// last keystroke. Revisions are keyed by document, since switching to
+/// another one doesn't need to wait.
+#[derive(Debug, Clone, Default)]
+pub struct Debouncer {
+    /// Document and revision waiting for the work, and when it was reached
+    pending: Option<((u64, u64), Instant)>,
+    /// Document and revision the work was last done for
+    done: Option<(u64, u64)>,
+}
+
+impl Debouncer {
+    /// Whether to do the work for `revision` of `document` now
+    pub fn poll(&mut self, document: u64, revision: u64) -> Debounce {
+        let key = (document, revision);
+        if self.done == Some(key) {
+            return Debounce::Done;
+        }
+        let since = match self.pending {
+            Some((pending, since)) if pending == key => since,
+            // An edit of the document last worked on
+            _ if self.done.is_some_and(|(done, _)| done == document) => {
+                self.pending = Some((key, Instant::now()));
+                return Debounce::Wait(DEBOUNCE_DELAY);
+            }
+            _ => {
+                self.mark_done(document, revision);
+                return Debounce::Ready;
+            }
+        };
+        match DEBOUNCE_DELAY.checked_sub(since.elapsed()) {
+            Some(left) if !left.is_zero() => Debounce::Wait(left),
+            _ => {
+                self.mark_done(document, revision);
+                Debounce::Ready
+            }
+        }
+    }
+
+    /// Note that the work was done for `revision` of `document` without
+    /// waiting, such as after a change to what it depends on
+    pub fn mark_done(&mut self, document: u64, revision: u64) {
+        self.done = Some((document, revision));
+        self.pending = None;
+    }
+
+    pub fn is_done(&self, document: u64, revision: u64) -> bool {
+        self.done == Some((document, revision))
+    }
+}
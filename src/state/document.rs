@@ -0,0 +1,63 @@
+use super::crdt::{Change, Replica};
+use ropey::Rope;
+use std::ops::Range;
+
+/// Text that edits are made to by char offset. Tabs keep their text in a
+/// `Rope`; a `Replica` holds it as a CRDT that several peers edit at once,
+/// and code written against this trait works on either.
+pub trait Document {
+    /// Replace the chars in `range` with `text`
+    fn replace(&mut self, range: Range<usize>, text: &str);
+
+    /// Make a change some replica reported applying
+    fn apply_change(&mut self, change: Change) {
+        match change {
+            Change::Inserted { offset, ch } => {
+                self.replace(offset..offset, ch.encode_utf8(&mut [0; 4]));
+            }
+            Change::Deleted { offset } => self.replace(offset..offset + 1, ""),
+        }
+    }
+}
+
+impl Document for Rope {
+    fn replace(&mut self, range: Range<usize>, text: &str) {
+        let start = range.start;
+        self.remove(range);
+        self.insert(start, text);
+    }
+}
+
+impl Document for Replica {
+    /// The ops are dropped; use `Replica::replace` to send them to others
+    fn replace(&mut self, range: Range<usize>, text: &str) {
+        Replica::replace(self, range, text);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The same edits through the trait
+    fn edit(document: &mut impl Document) {
+        document.replace(0..0, "fn main() {}\n");
+        document.replace(11..11, " println!(\"hi\"); ");
+        document.replace(0..2, "pub fn");
+        document.apply_change(Change::Deleted { offset: 0 });
+        document.apply_change(Change::Inserted { offset: 0, ch: 'P' });
+    }
+
+    #[test]
+    fn rope_and_replica_agree() {
+        let mut rope = Rope::from_str("// é\n");
+        let mut replica = Replica::new(1, "// é\n");
+        edit(&mut rope);
+        edit(&mut replica);
+        assert_eq!(
+            rope.to_string(),
+            "Pub fn main() { println!(\"hi\"); }\n// é\n"
+        );
+        assert_eq!(replica.text(), rope.to_string());
+    }
+}
@@ -1,22 +1,91 @@
+use super::bookmarks::Bookmarks;
 use super::cursor::CursorPosition;
+use super::folding::FoldRange;
+use super::history::{CursorHistory, EditHistory};
+use super::wrap::WrapLayout;
+use std::sync::Arc;
 
 /// State for a single editor tab
 #[derive(Debug, Clone)]
 pub struct EditorTabState {
-    /// Current cursor position
-    pub cursor: CursorPosition,
+    /// All carets in the tab; the first entry is the primary cursor
+    pub cursors: Vec<CursorPosition>,
     /// Whether the file has unsaved changes
     pub is_modified: bool,
     /// Range of visible lines (for minimap viewport indicator)
     pub visible_lines: (usize, usize),
+    /// Scroll the primary cursor into view on the next frame
+    pub scroll_to_cursor: bool,
+    /// Scroll the primary cursor to the middle of the view on the next frame
+    pub center_on_cursor: bool,
+    /// Incremented on every change to the buffer
+    pub revision: u64,
+    /// Undo/redo snapshots of the buffer
+    pub history: EditHistory,
+    /// Earlier cursors, for Cursor Undo
+    pub cursor_history: CursorHistory,
+    /// Longest line length in characters and the revision it was measured at
+    pub widest_line: Option<(u64, usize)>,
+    /// Visual rows while word wrap is on or lines are folded, rebuilt when
+    /// the revision, the folds or the editor width changes
+    pub wrap: Option<Arc<WrapLayout>>,
+    /// Collapsed folds; a caret moving into one opens it
+    pub folded: Vec<FoldRange>,
+    /// Bookmarked lines, kept on their text as it is edited
+    pub bookmarks: Bookmarks,
+    /// Char offsets of closing brackets and quotes that auto-close inserted
+    /// and the caret hasn't left yet; typing the same character steps over them
+    pub auto_closed: Vec<usize>,
+    /// Font size of this tab instead of the default one
+    pub font_size: Option<f32>,
+    /// Word wrap of this tab instead of the `word_wrap` setting
+    pub word_wrap: Option<bool>,
 }
 
 impl Default for EditorTabState {
     fn default() -> Self {
         Self {
-            cursor: CursorPosition::default(),
+            cursors: vec![CursorPosition::default()],
             is_modified: false,
             visible_lines: (1, 50),
+            scroll_to_cursor: false,
+            center_on_cursor: false,
+            revision: 0,
+            history: EditHistory::default(),
+            cursor_history: CursorHistory::default(),
+            widest_line: None,
+            wrap: None,
+            folded: Vec::new(),
+            bookmarks: Bookmarks::default(),
+            auto_closed: Vec::new(),
+            font_size: None,
+            word_wrap: None,
         }
     }
 }
+
+impl EditorTabState {
+    /// The primary cursor (shown in the status bar and driven by the mouse)
+    pub fn cursor(&self) -> &CursorPosition {
+        &self.cursors[0]
+    }
+
+    pub fn set_cursor(&mut self, cursor: CursorPosition) {
+        self.cursors[0] = cursor;
+    }
+
+    pub fn has_multiple_cursors(&self) -> bool {
+        self.cursors.len() > 1
+    }
+
+    /// Add a secondary cursor unless a caret already sits at that offset
+    pub fn add_cursor(&mut self, cursor: CursorPosition) {
+        if !self.cursors.iter().any(|c| c.offset == cursor.offset) {
+            self.cursors.push(cursor);
+        }
+    }
+
+    pub fn clear_secondary_cursors(&mut self) {
+        self.cursors.truncate(1);
+    }
+}
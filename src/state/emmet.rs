@@ -0,0 +1,859 @@
+use super::tags::VOID_ELEMENTS;
+use ropey::Rope;
+use std::ops::Range;
+
+/// Kinds of files Emmet abbreviations expand in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Syntax {
+    Html,
+    /// HTML in JavaScript, with `className` and `htmlFor` and self-closing
+    /// void elements
+    Jsx,
+    Css,
+}
+
+impl Syntax {
+    pub fn for_extension(ext: &str) -> Option<Self> {
+        match ext {
+            "html" | "htm" => Some(Self::Html),
+            "jsx" | "tsx" => Some(Self::Jsx),
+            "css" | "scss" | "sass" | "less" => Some(Self::Css),
+            _ => None,
+        }
+    }
+}
+
+/// Text that replaces an abbreviation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expansion {
+    /// Char range of the abbreviation
+    pub range: Range<usize>,
+    pub text: String,
+    /// Where the caret goes, in chars into `text`: the first empty attribute
+    /// value or element
+    pub caret: usize,
+}
+
+/// Expansion of the abbreviation right before `offset`, e.g. `ul>li*3>a` or
+/// `m10-20`. `indent` is one level of indentation; lines after the first
+/// also get the indentation of the line the abbreviation is on. `None` when
+/// there is no abbreviation there, so Tab can indent as usual.
+pub fn expand(rope: &Rope, offset: usize, syntax: Syntax, indent: &str) -> Option<Expansion> {
+    let line_start = rope.line_to_char(rope.char_to_line(offset));
+    let before = rope.slice(line_start..offset).to_string();
+    let base: String = before
+        .chars()
+        .take_while(|c| *c == ' ' || *c == '\t')
+        .collect();
+    let (abbreviation, text, caret) = match syntax {
+        Syntax::Css => {
+            let abbreviation = css_abbreviation(&before)?;
+            // Property abbreviations only mean something inside a rule
+            let (opened, closed) = rope
+                .slice(..offset - abbreviation.chars().count())
+                .chars()
+                .fold((0, 0), |(o, c), ch| match ch {
+                    '{' => (o + 1, c),
+                    '}' => (o, c + 1),
+                    _ => (o, c),
+                });
+            if opened <= closed {
+                return None;
+            }
+            let (text, caret) = expand_css(abbreviation)?;
+            (abbreviation, text, caret)
+        }
+        Syntax::Html | Syntax::Jsx => {
+            let abbreviation = html_abbreviation(&before)?;
+            let (text, caret) = expand_html(abbreviation, syntax == Syntax::Jsx, indent)?;
+            (abbreviation, text, caret)
+        }
+    };
+
+    // Continuation lines start at the indentation of the abbreviation's line
+    let caret_line = text.chars().take(caret).filter(|&c| c == '\n').count();
+    let caret = caret + caret_line * base.chars().count();
+    let text = text.replace('\n', &format!("\n{base}"));
+    let length = abbreviation.chars().count();
+    Some(Expansion {
+        range: offset - length..offset,
+        text,
+        caret,
+    })
+}
+
+// === HTML ===
+
+/// Elements that stay on the line of their parent
+const INLINE_ELEMENTS: &[&str] = &[
+    "a", "abbr", "b", "br", "button", "code", "em", "i", "img", "input", "kbd", "label", "q", "s",
+    "select", "small", "span", "strong", "sub", "sup", "textarea", "time", "u",
+];
+
+/// Elements a lone word expands to. Anything else needs an operator, so Tab
+/// after an ordinary word still indents.
+const KNOWN_ELEMENTS: &[&str] = &[
+    "article",
+    "aside",
+    "audio",
+    "blockquote",
+    "body",
+    "canvas",
+    "caption",
+    "dd",
+    "details",
+    "dialog",
+    "div",
+    "dl",
+    "dt",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "head",
+    "header",
+    "html",
+    "iframe",
+    "legend",
+    "li",
+    "main",
+    "nav",
+    "ol",
+    "optgroup",
+    "option",
+    "p",
+    "picture",
+    "pre",
+    "script",
+    "section",
+    "style",
+    "summary",
+    "table",
+    "tbody",
+    "td",
+    "template",
+    "tfoot",
+    "th",
+    "thead",
+    "title",
+    "tr",
+    "ul",
+    "video",
+];
+
+/// Attributes elements get unless the abbreviation sets them
+const DEFAULT_ATTRIBUTES: &[(&str, &[&str])] = &[
+    ("a", &["href"]),
+    ("abbr", &["title"]),
+    ("form", &["action"]),
+    ("iframe", &["src"]),
+    ("img", &["src", "alt"]),
+    ("label", &["for"]),
+    ("link", &["rel=stylesheet", "href"]),
+    ("script", &[]),
+];
+
+/// The abbreviation at the end of `before`: the text after the last space
+/// outside `[...]` and `{...}`, and after any tag it follows
+fn html_abbreviation(before: &str) -> Option<&str> {
+    let mut depth = 0i32;
+    let mut start = before.len();
+    for (i, c) in before.char_indices().rev() {
+        match c {
+            '}' | ']' => depth += 1,
+            '{' | '[' => depth -= 1,
+            c if depth == 0 && (c.is_whitespace() || c == '<' || c == ';') => {
+                // Right after a tag, e.g. `<div>ul>li`
+                if c == '<' {
+                    let after_tag = before[start..].find('>')?;
+                    start += after_tag + 1;
+                }
+                break;
+            }
+            _ => {}
+        }
+        start = i;
+    }
+    let abbreviation = &before[start..];
+    let has_operator = abbreviation.contains(['>', '+', '^', '*', '.', '#', '[', '{', '(', '!']);
+    let is_known = KNOWN_ELEMENTS.contains(&abbreviation)
+        || VOID_ELEMENTS.contains(&abbreviation)
+        || INLINE_ELEMENTS.contains(&abbreviation);
+    (depth == 0 && (has_operator || is_known)).then_some(abbreviation)
+}
+
+/// Largest `*N`; anything bigger is not taken for an abbreviation
+const MAX_REPEAT: usize = 1000;
+/// Most elements one abbreviation expands to, e.g. `(div*1000)*1000` is
+/// refused
+const MAX_ELEMENTS: usize = 10_000;
+
+/// Element of a parsed abbreviation; groups have no name
+#[derive(Debug, Default)]
+struct Node {
+    name: Option<String>,
+    group: bool,
+    id: Option<String>,
+    classes: Vec<String>,
+    attributes: Vec<(String, Option<String>)>,
+    text: Option<String>,
+    count: Option<usize>,
+    children: Vec<usize>,
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    nodes: Vec<Node>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    /// Siblings and children up to the end, or the `)` of a group
+    fn parse_list(&mut self, root: usize, in_group: bool) -> Option<()> {
+        let mut parent = root;
+        let mut ancestors = Vec::new();
+        loop {
+            let node = if self.peek() == Some('(') {
+                self.pos += 1;
+                self.nodes.push(Node {
+                    group: true,
+                    ..Node::default()
+                });
+                let group = self.nodes.len() - 1;
+                self.parse_list(group, true)?;
+                (self.peek() == Some(')')).then_some(())?;
+                self.pos += 1;
+                group
+            } else {
+                self.parse_element()?
+            };
+            if self.peek() == Some('*') {
+                self.pos += 1;
+                let digits = self.take_while(|c| c.is_ascii_digit());
+                self.nodes[node].count =
+                    Some(digits.parse().ok().filter(|&n| n > 0 && n <= MAX_REPEAT)?);
+            }
+            self.nodes[parent].children.push(node);
+
+            match self.peek() {
+                None => return (!in_group).then_some(()),
+                Some(')') if in_group => return Some(()),
+                Some('>') => {
+                    ancestors.push(parent);
+                    parent = node;
+                }
+                Some('+') => {}
+                Some('^') => {
+                    // Each `^` climbs one level
+                    parent = ancestors.pop().unwrap_or(root);
+                    while self.chars.get(self.pos + 1) == Some(&'^') {
+                        self.pos += 1;
+                        parent = ancestors.pop().unwrap_or(root);
+                    }
+                }
+                Some(_) => return None,
+            }
+            self.pos += 1;
+        }
+    }
+
+    fn parse_element(&mut self) -> Option<usize> {
+        let is_name_char = |c: char| c.is_alphanumeric() || matches!(c, '-' | '_' | ':' | '$');
+        let mut node = Node::default();
+        let name = self.take_while(is_name_char);
+        if !name.is_empty() {
+            node.name = Some(name);
+        }
+        loop {
+            match self.peek() {
+                Some('#') => {
+                    self.pos += 1;
+                    node.id = Some(self.take_while(is_name_char));
+                }
+                Some('.') => {
+                    self.pos += 1;
+                    node.classes.push(self.take_while(is_name_char));
+                }
+                Some('[') => {
+                    self.pos += 1;
+                    let inside = self.take_until(']')?;
+                    node.attributes.extend(parse_attributes(&inside));
+                }
+                Some('{') => {
+                    self.pos += 1;
+                    node.text = Some(self.take_until('}')?);
+                }
+                _ => break,
+            }
+        }
+        let empty = node.name.is_none()
+            && node.id.is_none()
+            && node.classes.is_empty()
+            && node.attributes.is_empty()
+            && node.text.is_none();
+        if empty {
+            return None;
+        }
+        self.nodes.push(node);
+        Some(self.nodes.len() - 1)
+    }
+
+    fn take_while(&mut self, f: impl Fn(char) -> bool) -> String {
+        let start = self.pos;
+        while self.peek().is_some_and(&f) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    /// Text up to `end`, which is skipped
+    fn take_until(&mut self, end: char) -> Option<String> {
+        let text = self.take_while(|c| c != end);
+        (self.peek() == Some(end)).then_some(())?;
+        self.pos += 1;
+        Some(text)
+    }
+}
+
+/// `name="value" other=value flag` inside `[...]`
+fn parse_attributes(text: &str) -> Vec<(String, Option<String>)> {
+    let mut attributes = Vec::new();
+    let mut rest = text.trim_start();
+    while !rest.is_empty() {
+        let name_end = rest
+            .find(|c: char| c == '=' || c.is_whitespace())
+            .unwrap_or(rest.len());
+        let name = rest[..name_end].to_string();
+        rest = &rest[name_end..];
+        let value = match rest.strip_prefix('=') {
+            Some(value) => {
+                let (value, after) = match value.chars().next() {
+                    Some(quote @ ('"' | '\'')) => {
+                        let value = &value[1..];
+                        let end = value.find(quote).unwrap_or(value.len());
+                        (&value[..end], value.get(end + 1..).unwrap_or(""))
+                    }
+                    _ => {
+                        let end = value.find(char::is_whitespace).unwrap_or(value.len());
+                        (&value[..end], &value[end..])
+                    }
+                };
+                rest = after;
+                Some(value.to_string())
+            }
+            None => None,
+        };
+        if !name.is_empty() {
+            attributes.push((name, value));
+        }
+        rest = rest.trim_start();
+    }
+    attributes
+}
+
+/// Element with repetition and numbering resolved
+struct Element {
+    name: String,
+    attributes: Vec<(String, Option<String>)>,
+    text: Option<String>,
+    children: Vec<Element>,
+}
+
+/// Number of elements `node` expands to, repetitions included
+fn element_count(nodes: &[Node], node: usize) -> usize {
+    let n = &nodes[node];
+    let children = n.children.iter().fold(0, |sum: usize, &child| {
+        sum.saturating_add(element_count(nodes, child))
+    });
+    let own = usize::from(!n.group);
+    n.count
+        .unwrap_or(1)
+        .saturating_mul(own.saturating_add(children))
+}
+
+/// Elements for `node`, once per repetition. `$` is replaced by the number of
+/// the nearest repeated element, `$$` zero-padded to two digits and so on.
+fn resolve(nodes: &[Node], node: usize, parent: &str, number: usize) -> Vec<Element> {
+    let n = &nodes[node];
+    let mut elements = Vec::new();
+    for i in 1..=n.count.unwrap_or(1) {
+        let number = if n.count.is_some() { i } else { number };
+        let children = |parent: &str| -> Vec<Element> {
+            n.children
+                .iter()
+                .flat_map(|&child| resolve(nodes, child, parent, number))
+                .collect()
+        };
+        if n.group {
+            elements.extend(children(parent));
+            continue;
+        }
+        let name = match &n.name {
+            Some(name) => numbered(name, number),
+            None => implicit_name(parent).to_string(),
+        };
+        let mut attributes = Vec::new();
+        if let Some(id) = &n.id {
+            attributes.push(("id".to_string(), Some(numbered(id, number))));
+        }
+        if !n.classes.is_empty() {
+            let classes: Vec<String> = n.classes.iter().map(|c| numbered(c, number)).collect();
+            attributes.push(("class".to_string(), Some(classes.join(" "))));
+        }
+        let defaults = DEFAULT_ATTRIBUTES
+            .iter()
+            .find(|(element, _)| *element == name)
+            .map_or(&[][..], |(_, attributes)| *attributes);
+        for default in defaults {
+            let (key, value) = match default.split_once('=') {
+                Some((key, value)) => (key, Some(value.to_string())),
+                None => (*default, None),
+            };
+            if !n.attributes.iter().any(|(name, _)| name == key) {
+                attributes.push((key.to_string(), value.or(Some(String::new()))));
+            }
+        }
+        if name == "input" && !n.attributes.iter().any(|(name, _)| name == "type") {
+            attributes.push(("type".to_string(), Some("text".to_string())));
+        }
+        for (key, value) in &n.attributes {
+            let value = value.as_ref().map(|v| numbered(v, number));
+            attributes.push((numbered(key, number), value));
+        }
+        elements.push(Element {
+            children: children(&name),
+            name,
+            attributes,
+            text: n.text.as_ref().map(|text| numbered(text, number)),
+        });
+    }
+    elements
+}
+
+/// Name of an element given only by its id, class or attributes
+fn implicit_name(parent: &str) -> &'static str {
+    match parent {
+        "ul" | "ol" => "li",
+        "table" | "tbody" | "thead" | "tfoot" => "tr",
+        "tr" => "td",
+        "select" | "optgroup" => "option",
+        _ if INLINE_ELEMENTS.contains(&parent) => "span",
+        _ => "div",
+    }
+}
+
+fn numbered(text: &str, number: usize) -> String {
+    let mut result = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '$' {
+            result.push(c);
+            continue;
+        }
+        let mut width = 1;
+        while chars.next_if_eq(&'$').is_some() {
+            width += 1;
+        }
+        result.push_str(&format!("{number:0width$}"));
+    }
+    result
+}
+
+fn expand_html(abbreviation: &str, jsx: bool, indent: &str) -> Option<(String, usize)> {
+    if abbreviation == "!" {
+        return Some(html_document(indent));
+    }
+    let mut parser = Parser {
+        chars: abbreviation.chars().collect(),
+        pos: 0,
+        nodes: vec![Node {
+            group: true,
+            ..Node::default()
+        }],
+    };
+    parser.parse_list(0, false)?;
+    if element_count(&parser.nodes, 0) > MAX_ELEMENTS {
+        return None;
+    }
+    let elements = resolve(&parser.nodes, 0, "", 1);
+    let mut writer = Writer {
+        text: String::new(),
+        caret: None,
+        jsx,
+        indent,
+    };
+    for (i, element) in elements.iter().enumerate() {
+        if i > 0 {
+            writer.text.push('\n');
+        }
+        writer.element(element, 0);
+    }
+    let caret = writer.caret.unwrap_or(writer.text.chars().count());
+    Some((writer.text, caret))
+}
+
+struct Writer<'a> {
+    text: String,
+    /// First place worth filling in
+    caret: Option<usize>,
+    jsx: bool,
+    indent: &'a str,
+}
+
+impl Writer<'_> {
+    fn mark_caret(&mut self) {
+        if self.caret.is_none() {
+            self.caret = Some(self.text.chars().count());
+        }
+    }
+
+    fn element(&mut self, element: &Element, depth: usize) {
+        self.text.push('<');
+        self.text.push_str(&element.name);
+        for (name, value) in &element.attributes {
+            let name = match (self.jsx, name.as_str()) {
+                (true, "class") => "className",
+                (true, "for") => "htmlFor",
+                _ => name,
+            };
+            self.text.push(' ');
+            self.text.push_str(name);
+            if let Some(value) = value {
+                self.text.push_str("=\"");
+                if value.is_empty() {
+                    self.mark_caret();
+                }
+                self.text.push_str(value);
+                self.text.push('"');
+            }
+        }
+        if VOID_ELEMENTS.contains(&element.name.as_str()) {
+            self.text.push_str(if self.jsx { " />" } else { ">" });
+            return;
+        }
+        self.text.push('>');
+        if let Some(text) = &element.text {
+            self.text.push_str(text);
+        }
+        if element.children.iter().all(is_inline) {
+            if element.children.is_empty() && element.text.is_none() {
+                self.mark_caret();
+            }
+            for child in &element.children {
+                self.element(child, depth);
+            }
+        } else {
+            for child in &element.children {
+                self.text.push('\n');
+                self.text.push_str(&self.indent.repeat(depth + 1));
+                self.element(child, depth + 1);
+            }
+            self.text.push('\n');
+            self.text.push_str(&self.indent.repeat(depth));
+        }
+        self.text.push_str("</");
+        self.text.push_str(&element.name);
+        self.text.push('>');
+    }
+}
+
+/// Whether an element stays on its parent's line
+fn is_inline(element: &Element) -> bool {
+    INLINE_ELEMENTS.contains(&element.name.as_str()) && element.children.iter().all(is_inline)
+}
+
+/// `!`: an HTML5 document with the caret in the body
+fn html_document(indent: &str) -> (String, usize) {
+    let before = format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n{indent}<meta charset=\"UTF-8\">\n\
+         {indent}<meta name=\"viewport\" content=\"width=device-width, initial-scale=1.0\">\n\
+         {indent}<title>Document</title>\n</head>\n<body>\n{indent}"
+    );
+    let caret = before.chars().count();
+    (before + "\n</body>\n</html>", caret)
+}
+
+// === CSS ===
+
+/// Property abbreviations
+const CSS_PROPERTIES: &[(&str, &str)] = &[
+    ("ai", "align-items"),
+    ("b", "bottom"),
+    ("bd", "border"),
+    ("bdrs", "border-radius"),
+    ("bg", "background"),
+    ("bgc", "background-color"),
+    ("bxz", "box-sizing"),
+    ("c", "color"),
+    ("cur", "cursor"),
+    ("d", "display"),
+    ("ff", "font-family"),
+    ("fw", "font-weight"),
+    ("fx", "flex"),
+    ("fxd", "flex-direction"),
+    ("fz", "font-size"),
+    ("g", "gap"),
+    ("h", "height"),
+    ("jc", "justify-content"),
+    ("l", "left"),
+    ("lh", "line-height"),
+    ("m", "margin"),
+    ("mah", "max-height"),
+    ("maw", "max-width"),
+    ("mb", "margin-bottom"),
+    ("mih", "min-height"),
+    ("miw", "min-width"),
+    ("ml", "margin-left"),
+    ("mr", "margin-right"),
+    ("mt", "margin-top"),
+    ("op", "opacity"),
+    ("ov", "overflow"),
+    ("p", "padding"),
+    ("pb", "padding-bottom"),
+    ("pl", "padding-left"),
+    ("pos", "position"),
+    ("pr", "padding-right"),
+    ("pt", "padding-top"),
+    ("r", "right"),
+    ("t", "top"),
+    ("ta", "text-align"),
+    ("td", "text-decoration"),
+    ("trs", "transition"),
+    ("tt", "text-transform"),
+    ("w", "width"),
+    ("z", "z-index"),
+];
+
+/// Whole declarations
+const CSS_DECLARATIONS: &[(&str, &str)] = &[
+    ("aic", "align-items: center"),
+    ("aife", "align-items: flex-end"),
+    ("aifs", "align-items: flex-start"),
+    ("bdn", "border: none"),
+    ("bxzbb", "box-sizing: border-box"),
+    ("curp", "cursor: pointer"),
+    ("db", "display: block"),
+    ("df", "display: flex"),
+    ("dg", "display: grid"),
+    ("di", "display: inline"),
+    ("dib", "display: inline-block"),
+    ("dif", "display: inline-flex"),
+    ("dn", "display: none"),
+    ("fwb", "font-weight: bold"),
+    ("fwn", "font-weight: normal"),
+    ("fxdc", "flex-direction: column"),
+    ("fxdr", "flex-direction: row"),
+    ("jcc", "justify-content: center"),
+    ("jcfe", "justify-content: flex-end"),
+    ("jcfs", "justify-content: flex-start"),
+    ("jcsa", "justify-content: space-around"),
+    ("jcsb", "justify-content: space-between"),
+    ("ova", "overflow: auto"),
+    ("ovh", "overflow: hidden"),
+    ("ovs", "overflow: scroll"),
+    ("posa", "position: absolute"),
+    ("posf", "position: fixed"),
+    ("posr", "position: relative"),
+    ("poss", "position: sticky"),
+    ("tac", "text-align: center"),
+    ("taj", "text-align: justify"),
+    ("tal", "text-align: left"),
+    ("tar", "text-align: right"),
+    ("tdn", "text-decoration: none"),
+    ("tdu", "text-decoration: underline"),
+    ("ttl", "text-transform: lowercase"),
+    ("ttu", "text-transform: uppercase"),
+];
+
+/// Properties whose numbers have no unit
+const UNITLESS_PROPERTIES: &[&str] = &["flex", "font-weight", "line-height", "opacity", "z-index"];
+
+/// The abbreviation at the end of `before`, when it is the only thing on the
+/// line after `{` or `;`
+fn css_abbreviation(before: &str) -> Option<&str> {
+    let start = before
+        .rfind(|c: char| c.is_whitespace() || c == '{' || c == ';')
+        .map_or(0, |i| i + 1);
+    let abbreviation = &before[start..];
+    let is_abbreviation_char =
+        |c: char| c.is_ascii_alphanumeric() || matches!(c, '#' | '.' | '-' | ':' | '%');
+    (!abbreviation.is_empty()
+        && abbreviation.starts_with(|c: char| c.is_ascii_lowercase())
+        && abbreviation.chars().all(is_abbreviation_char))
+    .then_some(abbreviation)
+}
+
+/// `m10-20` to `margin: 10px 20px;`, `c#fff` to `color: #fff;`, `dn` to
+/// `display: none;`. The caret goes after the declaration, or into the value
+/// when there is none.
+fn expand_css(abbreviation: &str) -> Option<(String, usize)> {
+    if let Some((_, declaration)) = CSS_DECLARATIONS.iter().find(|(a, _)| *a == abbreviation) {
+        let text = format!("{declaration};");
+        let caret = text.chars().count();
+        return Some((text, caret));
+    }
+    let name_end = abbreviation
+        .find(|c: char| !c.is_ascii_lowercase())
+        .unwrap_or(abbreviation.len());
+    let (name, value) = abbreviation.split_at(name_end);
+    let (_, property) = CSS_PROPERTIES.iter().find(|(a, _)| *a == name)?;
+    let value = match value.strip_prefix(':') {
+        Some(keyword) => keyword.to_string(),
+        None if value.starts_with('#') => value.to_string(),
+        None if value.is_empty() => String::new(),
+        None => css_numbers(value, UNITLESS_PROPERTIES.contains(property))?,
+    };
+    let text = format!("{property}: {value};");
+    let caret = if value.is_empty() {
+        text.chars().count() - 1
+    } else {
+        text.chars().count()
+    };
+    Some((text, caret))
+}
+
+/// `10-20` to `10px 20px`, `-5` to `-5px`, `50p` to `50%`, `1.5e` to `1.5em`
+fn css_numbers(value: &str, unitless: bool) -> Option<String> {
+    let mut numbers = Vec::new();
+    let mut rest = value;
+    while !rest.is_empty() {
+        let negative = rest.starts_with('-');
+        let digits = &rest[usize::from(negative)..];
+        let number_end = digits
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(digits.len());
+        if number_end == 0 {
+            return None;
+        }
+        let number = &digits[..number_end];
+        let unit_end = digits[number_end..]
+            .find(|c: char| !c.is_ascii_alphabetic() && c != '%')
+            .map_or(digits.len(), |i| number_end + i);
+        let unit = match &digits[number_end..unit_end] {
+            "" if unitless || number.parse::<f64>() == Ok(0.0) => "",
+            "" => "px",
+            "p" => "%",
+            "e" => "em",
+            "r" => "rem",
+            "x" => "ex",
+            unit => unit,
+        };
+        numbers.push(format!("{}{number}{unit}", if negative { "-" } else { "" }));
+        rest = &digits[unit_end..];
+        // A dash between numbers only separates them
+        if let Some(after) = rest.strip_prefix('-') {
+            if !after.starts_with('-') {
+                rest = after;
+            }
+        }
+    }
+    Some(numbers.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Expansion of the whole of `text`, with the caret marked by `|`
+    fn expanded(text: &str, syntax: Syntax) -> Option<String> {
+        let rope = Rope::from_str(text);
+        let expansion = expand(&rope, rope.len_chars(), syntax, "  ")?;
+        let mut result: String = expansion.text.chars().take(expansion.caret).collect();
+        result.push('|');
+        result.extend(expansion.text.chars().skip(expansion.caret));
+        Some(result)
+    }
+
+    fn html(text: &str) -> Option<String> {
+        expanded(text, Syntax::Html)
+    }
+
+    fn css(declaration: &str) -> Option<String> {
+        expanded(&format!("a {{\n  {declaration}"), Syntax::Css)
+    }
+
+    #[test]
+    fn nests_and_repeats_elements() {
+        assert_eq!(
+            html("ul>li*2>a").as_deref(),
+            Some("<ul>\n  <li><a href=\"|\"></a></li>\n  <li><a href=\"\"></a></li>\n</ul>")
+        );
+        assert_eq!(
+            html("div>p+p^span").as_deref(),
+            Some("<div>\n  <p>|</p>\n  <p></p>\n</div>\n<span></span>")
+        );
+    }
+
+    #[test]
+    fn numbers_repeated_elements() {
+        assert_eq!(
+            html("li.item$${Item $}*2").as_deref(),
+            Some("<li class=\"item01\">Item 1</li>\n<li class=\"item02\">Item 2</li>|")
+        );
+    }
+
+    #[test]
+    fn names_elements_from_their_parent() {
+        assert_eq!(
+            html("ul>.a").as_deref(),
+            Some("<ul>\n  <li class=\"a\">|</li>\n</ul>")
+        );
+    }
+
+    #[test]
+    fn writes_jsx_attributes() {
+        assert_eq!(
+            expanded("label.x+br", Syntax::Jsx).as_deref(),
+            Some("<label className=\"x\" htmlFor=\"|\"></label>\n<br />")
+        );
+    }
+
+    #[test]
+    fn indents_to_the_abbreviation_line() {
+        let rope = Rope::from_str("    div>p");
+        let expansion = expand(&rope, rope.len_chars(), Syntax::Html, "  ").unwrap();
+        assert_eq!(expansion.range, 4..9);
+        assert_eq!(expansion.text, "<div>\n      <p></p>\n    </div>");
+        assert_eq!(expansion.caret, "<div>\n      <p>".len());
+    }
+
+    #[test]
+    fn leaves_plain_words_alone() {
+        assert_eq!(html("hello"), None);
+        assert_eq!(html("some text"), None);
+        assert_eq!(html("div").as_deref(), Some("<div>|</div>"));
+    }
+
+    #[test]
+    fn refuses_abbreviations_that_expand_too_far() {
+        assert!(html("b*1000").is_some());
+        assert_eq!(html("b*1001"), None);
+        assert_eq!(html("(b*1000)*11"), None);
+        assert_eq!(html("ul>li*1000>a*11"), None);
+    }
+
+    #[test]
+    fn expands_css_inside_rules_only() {
+        assert_eq!(css("m10-20").as_deref(), Some("margin: 10px 20px;|"));
+        assert_eq!(css("m-5").as_deref(), Some("margin: -5px;|"));
+        assert_eq!(css("w50p").as_deref(), Some("width: 50%;|"));
+        assert_eq!(css("lh1.5").as_deref(), Some("line-height: 1.5;|"));
+        assert_eq!(css("c#fff").as_deref(), Some("color: #fff;|"));
+        assert_eq!(css("dn").as_deref(), Some("display: none;|"));
+        assert_eq!(css("d:grid").as_deref(), Some("display: grid;|"));
+        assert_eq!(css("m").as_deref(), Some("margin: |;"));
+        assert_eq!(css("nope"), None);
+        assert_eq!(expanded("m10", Syntax::Css), None);
+    }
+}
@@ -0,0 +1,288 @@
+use super::rope_diff;
+use ropey::Rope;
+use std::borrow::Cow;
+use std::ops::Range;
+
+/// What makes a range of lines foldable
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FoldKind {
+    /// `/* ... */` over several lines
+    BlockComment,
+    /// Consecutive `///` or `//!` lines
+    DocComment,
+    /// `// region: name` down to `// endregion`
+    Region,
+}
+
+/// Lines that can collapse into their first one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FoldRange {
+    /// Zero-based lines, the first of which stays visible when folded
+    pub lines: Range<usize>,
+    pub kind: FoldKind,
+    /// Shown after the first line while folded
+    pub label: String,
+}
+
+impl FoldRange {
+    /// Lines hidden while folded
+    pub fn hidden(&self) -> Range<usize> {
+        self.lines.start + 1..self.lines.end
+    }
+}
+
+/// Foldable ranges of a buffer. After an edit only the lines it touched are
+/// read again; the ranges are then rebuilt from what each line holds.
+#[derive(Debug, Default)]
+pub struct FoldIndex {
+    /// Text the lines were read from
+    text: Option<Rope>,
+    lines: Vec<LineKind>,
+    ranges: Vec<FoldRange>,
+}
+
+impl FoldIndex {
+    /// Bring the ranges up to date with `rope`
+    pub fn update(&mut self, rope: &Rope) {
+        match &self.text {
+            Some(old) => {
+                let Some(edit) = rope_diff::changed_range(old, rope) else {
+                    return;
+                };
+                let start = old.char_to_line(edit.old.start);
+                let old_end = old.char_to_line(edit.old.end);
+                let new_end = rope.char_to_line(edit.new.end);
+                let changed = (start..=new_end).map(|line_idx| line_kind(rope, line_idx));
+                self.lines.splice(start..=old_end, changed);
+            }
+            None => {
+                self.lines = (0..rope.len_lines())
+                    .map(|line_idx| line_kind(rope, line_idx))
+                    .collect();
+            }
+        }
+        self.text = Some(rope.clone());
+        self.ranges = fold_ranges(rope, &self.lines);
+    }
+
+    /// Block comments, runs of doc comments and region markers, by first line
+    pub fn ranges(&self) -> &[FoldRange] {
+        &self.ranges
+    }
+}
+
+/// What a line can start or end
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct LineKind {
+    /// A `///` or `//!` comment
+    doc: bool,
+    region: bool,
+    endregion: bool,
+    /// Starts with a `/*` that doesn't end on the same line
+    opens_comment: bool,
+    /// Has a `*/`
+    closes_comment: bool,
+}
+
+fn line_kind(rope: &Rope, line_idx: usize) -> LineKind {
+    // Borrowed unless the line spans two chunks of the rope
+    let line: Cow<str> = rope.line(line_idx).into();
+    let trimmed = line.trim();
+    LineKind {
+        doc: (trimmed.starts_with("///") && !trimmed.starts_with("////"))
+            || trimmed.starts_with("//!"),
+        region: is_marker(trimmed, "region"),
+        endregion: is_marker(trimmed, "endregion"),
+        opens_comment: trimmed
+            .strip_prefix("/*")
+            .is_some_and(|rest| !rest.contains("*/")),
+        closes_comment: line.contains("*/"),
+    }
+}
+
+/// The ranges the lines make up. Regions nest; an `endregion` closes the
+/// innermost open one.
+fn fold_ranges(rope: &Rope, lines: &[LineKind]) -> Vec<FoldRange> {
+    let line_count = lines.len();
+    let mut ranges = Vec::new();
+    let mut regions: Vec<usize> = Vec::new();
+    let mut doc_start: Option<usize> = None;
+    // Once no line ends a comment, none further down does either
+    let mut unclosed = false;
+
+    let mut line_idx = 0;
+    while line_idx < line_count {
+        let kind = lines[line_idx];
+        if kind.doc {
+            doc_start.get_or_insert(line_idx);
+            line_idx += 1;
+            continue;
+        }
+        if let Some(start) = doc_start.take() {
+            push_doc_run(&mut ranges, start..line_idx);
+        }
+
+        if kind.region {
+            regions.push(line_idx);
+        } else if kind.endregion {
+            if let Some(start) = regions.pop() {
+                let lines = start..line_idx + 1;
+                ranges.push(FoldRange {
+                    label: format!("… {} lines", lines.len() - 1),
+                    lines,
+                    kind: FoldKind::Region,
+                });
+            }
+        } else if kind.opens_comment && !unclosed {
+            // Down to the line that ends it; an unclosed comment doesn't fold
+            let end = (line_idx + 1..line_count).find(|&i| lines[i].closes_comment);
+            unclosed = end.is_none();
+            if let Some(end) = end {
+                ranges.push(FoldRange {
+                    lines: line_idx..end + 1,
+                    kind: FoldKind::BlockComment,
+                    label: block_comment_label(rope, line_idx, end),
+                });
+                line_idx = end + 1;
+                continue;
+            }
+        }
+        line_idx += 1;
+    }
+    if let Some(start) = doc_start {
+        push_doc_run(&mut ranges, start..line_count);
+    }
+
+    ranges.sort_by_key(|range| range.lines.start);
+    ranges
+}
+
+/// The first line of text of the comment from line `start` to `end`
+fn block_comment_label(rope: &Rope, start: usize, end: usize) -> String {
+    let text = |line_idx: usize| -> Cow<str> { rope.line(line_idx).into() };
+    let first = text(start);
+    let rest = first.trim_start().strip_prefix("/*").unwrap_or_default();
+    let summary = comment_text(rest)
+        .is_empty()
+        .then(|| {
+            (start + 1..end)
+                .map(|i| comment_text(&text(i)).to_string())
+                .find(|text| !text.is_empty())
+        })
+        .flatten();
+    match summary {
+        Some(summary) => format!("{summary} … */"),
+        None => "… */".to_string(),
+    }
+}
+
+/// The fold starting at `line_idx`, the innermost one when several do
+pub fn fold_at(ranges: &[FoldRange], line_idx: usize) -> Option<&FoldRange> {
+    ranges
+        .iter()
+        .filter(|range| range.lines.start == line_idx)
+        .min_by_key(|range| range.lines.len())
+}
+
+/// Whether a line is a region marker with `keyword` after the comment start,
+/// as in `// region: name`, `//#region name` or `# endregion`
+fn is_marker(line: &str, keyword: &str) -> bool {
+    marker(line)
+        .and_then(|rest| rest.strip_prefix(keyword))
+        .is_some_and(|rest| !rest.starts_with(|c: char| c.is_alphanumeric() || c == '_'))
+}
+
+/// What follows the `//` or `#` a region marker line starts with
+fn marker(line: &str) -> Option<&str> {
+    let rest = line
+        .strip_prefix("//")
+        .or_else(|| line.strip_prefix('#'))?
+        .trim_start();
+    Some(rest.strip_prefix('#').unwrap_or(rest))
+}
+
+fn push_doc_run(ranges: &mut Vec<FoldRange>, lines: Range<usize>) {
+    if lines.len() > 1 {
+        ranges.push(FoldRange {
+            label: format!("… {} lines", lines.len() - 1),
+            lines,
+            kind: FoldKind::DocComment,
+        });
+    }
+}
+
+/// Text of a block comment line without the `*` decorations around it
+fn comment_text(line: &str) -> &str {
+    line.trim()
+        .trim_start_matches(['/', '*', '!'])
+        .trim_end_matches(['*', '/'])
+        .trim()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranges(text: &str) -> Vec<(Range<usize>, FoldKind, String)> {
+        let mut index = FoldIndex::default();
+        index.update(&Rope::from_str(text));
+        index
+            .ranges()
+            .iter()
+            .map(|range| (range.lines.clone(), range.kind, range.label.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn finds_comments_doc_runs_and_nested_regions() {
+        let text = "/* Summary\n * more\n */\n/// one\n/// two\nfn f() {}\n\
+                    // region: outer\n//#region inner\n// endregion\n// endregion\n";
+        assert_eq!(
+            ranges(text),
+            vec![
+                // The first line stays visible, so it isn't repeated
+                (0..3, FoldKind::BlockComment, "… */".to_string()),
+                (3..5, FoldKind::DocComment, "… 1 lines".to_string()),
+                (6..10, FoldKind::Region, "… 3 lines".to_string()),
+                (7..9, FoldKind::Region, "… 1 lines".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn unclosed_comments_and_single_doc_lines_do_not_fold() {
+        assert_eq!(ranges("/// one\nfn f() {}\n/* open\n/* open\n"), vec![]);
+        // `regional` is not a region marker
+        assert_eq!(ranges("// regional\n// endregion\n"), vec![]);
+    }
+
+    #[test]
+    fn label_skips_an_empty_first_line() {
+        let labels = ranges("/*\n *\n * Text\n */\n");
+        assert_eq!(labels[0].2, "Text … */");
+    }
+
+    #[test]
+    fn edits_give_the_same_ranges_as_reading_everything() {
+        let mut rope = Rope::from_str("fn a() {}\n/// doc\nfn b() {}\n/*\n x\n*/\n// region\n");
+        let mut index = FoldIndex::default();
+        index.update(&rope);
+        let edits: [(usize, &str, usize); 4] = [
+            (0, "", 0),                // nothing changed
+            (10, "/// more\n", 0),     // doc run grows
+            (40, "", 3),               // comment loses its end
+            (0, "// endregion\n", 10), // region closed, lines removed
+        ];
+        for (at, insert, remove) in edits {
+            let at = at.min(rope.len_chars());
+            let end = (at + remove).min(rope.len_chars());
+            rope.remove(at..end);
+            rope.insert(at, insert);
+            index.update(&rope);
+            let mut fresh = FoldIndex::default();
+            fresh.update(&rope);
+            assert_eq!(index.ranges(), fresh.ranges(), "after editing at {at}");
+            assert_eq!(index.lines, fresh.lines);
+        }
+    }
+}
@@ -0,0 +1,39 @@
+/// How well `candidate` matches a fuzzy `query`, or None if it doesn't: every
+/// query character has to appear in order, ignoring case. Matches at the start
+/// of words and runs of consecutive characters score higher; shorter
+/// candidates win ties.
+pub fn score(query: &str, candidate: &str) -> Option<i32> {
+    let chars: Vec<char> = candidate.chars().collect();
+    let mut score = 0;
+    let mut next = 0;
+    let mut previous_match: Option<usize> = None;
+
+    for q in query.chars().filter(|c| !c.is_whitespace()) {
+        let q = q.to_lowercase().next().unwrap_or(q);
+        let offset = chars[next..]
+            .iter()
+            .position(|c| c.to_lowercase().next() == Some(q))?;
+        let i = next + offset;
+
+        score += 1;
+        if previous_match.is_some_and(|p| p + 1 == i) {
+            score += 5;
+        } else if is_word_start(&chars, i) {
+            score += 8;
+        } else {
+            score -= offset.min(3) as i32;
+        }
+        previous_match = Some(i);
+        next = i + 1;
+    }
+
+    Some(score * 10 - chars.len() as i32)
+}
+
+/// First character, or one after a separator or at a lower-to-upper case change
+fn is_word_start(chars: &[char], i: usize) -> bool {
+    let Some(previous) = i.checked_sub(1).map(|p| chars[p]) else {
+        return true;
+    };
+    !previous.is_alphanumeric() || (previous.is_lowercase() && chars[i].is_uppercase())
+}
@@ -0,0 +1,92 @@
+/// Seconds of pointer movement a shake has to fit in
+const SHAKE_TIME: f64 = 0.8;
+/// Points the pointer has to travel back before a turn counts
+const SHAKE_DISTANCE: f32 = 30.0;
+/// Turns that make a shake
+const SHAKE_TURNS: usize = 3;
+/// Seconds before the release that the speed of a flick is measured over
+const FLICK_TIME: f64 = 0.1;
+/// Points per second a flick has to move at
+const FLICK_SPEED: f32 = 1200.0;
+
+/// What a drag turned out to be
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gesture {
+    /// Released while moving up fast
+    FlickUp,
+    /// Released while moving down fast
+    FlickDown,
+    /// Moved left and right a few times in quick succession
+    Shake,
+}
+
+/// Recognizes flicks and shakes in the pointer positions of one drag
+#[derive(Debug, Default)]
+pub struct DragGesture {
+    /// Time in seconds and position of the pointer, oldest first
+    samples: Vec<(f64, (f32, f32))>,
+    /// A shake was reported, so the rest of the drag is ignored
+    shaken: bool,
+}
+
+impl DragGesture {
+    /// Add the pointer position of a frame; returns `Shake` once when the
+    /// pointer has been shaken
+    pub fn push(&mut self, time: f64, pos: (f32, f32)) -> Option<Gesture> {
+        if self.shaken {
+            return None;
+        }
+        self.samples.retain(|&(t, _)| time - t <= SHAKE_TIME);
+        self.samples.push((time, pos));
+        self.shaken = self.turns() >= SHAKE_TURNS;
+        self.shaken.then_some(Gesture::Shake)
+    }
+
+    /// End the drag; a flick if the pointer was moving fast and mostly
+    /// vertically when let go, and `Shake` again for a shaken drag
+    pub fn release(&mut self, time: f64) -> Option<Gesture> {
+        let samples = std::mem::take(&mut self.samples);
+        if std::mem::take(&mut self.shaken) {
+            return Some(Gesture::Shake);
+        }
+        let &(last_time, (x, y)) = samples.last()?;
+        let &(first_time, (first_x, first_y)) = samples
+            .iter()
+            .find(|&&(t, _)| time - t <= FLICK_TIME)
+            .filter(|&&(t, _)| t < last_time)?;
+        let seconds = (last_time - first_time) as f32;
+        let (dx, dy) = ((x - first_x) / seconds, (y - first_y) / seconds);
+        if dy.abs() < FLICK_SPEED || dy.abs() < dx.abs() * 2.0 {
+            return None;
+        }
+        Some(if dy < 0.0 {
+            Gesture::FlickUp
+        } else {
+            Gesture::FlickDown
+        })
+    }
+
+    /// Times the horizontal movement changed direction by more than
+    /// `SHAKE_DISTANCE`
+    fn turns(&self) -> usize {
+        let mut turns = 0;
+        let mut direction = 0.0f32;
+        let Some(&(_, (mut extreme, _))) = self.samples.first() else {
+            return 0;
+        };
+        for &(_, (x, _)) in &self.samples[1..] {
+            let moved = x - extreme;
+            if moved * direction > 0.0 {
+                // Further the same way
+                extreme = x;
+            } else if moved.abs() >= SHAKE_DISTANCE {
+                if direction != 0.0 {
+                    turns += 1;
+                }
+                direction = moved.signum();
+                extreme = x;
+            }
+        }
+        turns
+    }
+}
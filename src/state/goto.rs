@@ -0,0 +1,49 @@
+use ropey::Rope;
+
+/// Where a "Go to Line" query points
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GotoTarget {
+    /// 1-based line and optional 1-based column (`42`, `42:7`)
+    Line { line: usize, column: Option<usize> },
+    /// Absolute character offset (`:o 10234`)
+    CharOffset(usize),
+    /// Absolute byte offset in the UTF-8 text (`:b 10234`)
+    ByteOffset(usize),
+}
+
+impl GotoTarget {
+    pub fn parse(input: &str) -> Option<Self> {
+        let input = input.trim();
+
+        if let Some(rest) = input.strip_prefix(":o") {
+            return rest.trim().parse().ok().map(Self::CharOffset);
+        }
+        if let Some(rest) = input.strip_prefix(":b") {
+            return rest.trim().parse().ok().map(Self::ByteOffset);
+        }
+
+        let (line, column) = match input.split_once(':') {
+            Some((line, column)) => (line, Some(column.trim().parse().ok()?)),
+            None => (input, None),
+        };
+        Some(Self::Line {
+            line: line.trim().parse().ok()?,
+            column,
+        })
+    }
+
+    /// Character offset of the target, clamped to the buffer
+    pub fn resolve(self, rope: &Rope) -> usize {
+        match self {
+            Self::Line { line, column } => {
+                let line_idx = line.saturating_sub(1).min(rope.len_lines() - 1);
+                let len = super::multi_cursor::line_len(rope, line_idx);
+                let column = column.unwrap_or(1).saturating_sub(1).min(len);
+                rope.line_to_char(line_idx) + column
+            }
+            Self::CharOffset(offset) => offset.min(rope.len_chars()),
+            // Offsets inside a multi-byte character land on that character
+            Self::ByteOffset(offset) => rope.byte_to_char(offset.min(rope.len_bytes())),
+        }
+    }
+}
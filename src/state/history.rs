@@ -0,0 +1,283 @@
+use super::crdt::Change;
+use super::cursor::CursorPosition;
+use super::rope_diff;
+use ropey::Rope;
+
+const MAX_UNDO_STEPS: usize = 1000;
+const MAX_CURSOR_UNDO_STEPS: usize = 100;
+
+/// Kind of edit, used to merge consecutive keystrokes into one undo step
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EditKind {
+    Typing,
+    Deleting,
+    Other,
+}
+
+#[derive(Debug, Clone)]
+struct Snapshot {
+    buffer: Rope,
+    cursors: Vec<CursorPosition>,
+}
+
+/// Undo/redo history for a tab.
+/// Snapshots are whole ropes, which share their unchanged chunks, so keeping many is cheap.
+#[derive(Debug, Clone, Default)]
+pub struct EditHistory {
+    undo: Vec<Snapshot>,
+    redo: Vec<Snapshot>,
+    last_kind: Option<EditKind>,
+}
+
+impl EditHistory {
+    /// Record the state right before an edit
+    pub fn record(&mut self, buffer: &Rope, cursors: &[CursorPosition], kind: EditKind) {
+        self.redo.clear();
+        if kind != EditKind::Other && self.last_kind == Some(kind) {
+            return;
+        }
+
+        self.undo.push(Snapshot {
+            buffer: buffer.clone(),
+            cursors: cursors.to_vec(),
+        });
+        if self.undo.len() > MAX_UNDO_STEPS {
+            self.undo.remove(0);
+        }
+        self.last_kind = Some(kind);
+    }
+
+    /// Make the next edit start a new undo step (e.g. after the caret moved)
+    pub fn break_group(&mut self) {
+        self.last_kind = None;
+    }
+
+    pub fn undo(&mut self, buffer: &mut Rope, cursors: &mut Vec<CursorPosition>) -> bool {
+        self.last_kind = None;
+        Self::restore(&mut self.undo, &mut self.redo, buffer, cursors)
+    }
+
+    pub fn redo(&mut self, buffer: &mut Rope, cursors: &mut Vec<CursorPosition>) -> bool {
+        self.last_kind = None;
+        Self::restore(&mut self.redo, &mut self.undo, buffer, cursors)
+    }
+
+    /// Carry edits made by someone else into the history: `changes` turned
+    /// `before`, the current text, into `after`. Each snapshot gets them
+    /// too, except inside the text it differs from `before` in, which undo
+    /// puts back as it was. Without this, undo would revert the others'
+    /// edits along with ours.
+    pub fn rebase(&mut self, before: &Rope, after: &Rope, changes: &[Change]) {
+        for snapshot in self.undo.iter_mut().chain(&mut self.redo) {
+            snapshot.rebase(before, after, changes);
+        }
+    }
+
+    /// Pop a snapshot from `from`, pushing the current state onto `to`
+    fn restore(
+        from: &mut Vec<Snapshot>,
+        to: &mut Vec<Snapshot>,
+        buffer: &mut Rope,
+        cursors: &mut Vec<CursorPosition>,
+    ) -> bool {
+        let Some(snapshot) = from.pop() else {
+            return false;
+        };
+        to.push(Snapshot {
+            buffer: buffer.clone(),
+            cursors: cursors.clone(),
+        });
+        *buffer = snapshot.buffer;
+        *cursors = snapshot.cursors;
+        true
+    }
+}
+
+impl Snapshot {
+    fn rebase(&mut self, before: &Rope, after: &Rope, changes: &[Change]) {
+        let Some(edit) = rope_diff::changed_range(&self.buffer, before) else {
+            self.buffer = after.clone();
+            for cursor in &mut self.cursors {
+                let offset = shift(cursor.offset, changes);
+                let anchor = shift(cursor.anchor, changes);
+                *cursor = CursorPosition::from_char_offset(after, offset).with_anchor(anchor);
+            }
+            return;
+        };
+        // Where the text this snapshot differs in ends up in `after`. Text
+        // inserted at its start is kept in front of it.
+        let (mut start, mut end) = (edit.new.start, edit.new.end);
+        for change in changes {
+            match *change {
+                Change::Inserted { offset, .. } if offset <= start => {
+                    start += 1;
+                    end += 1;
+                }
+                Change::Inserted { offset, .. } if offset < end => end += 1,
+                Change::Deleted { offset } if offset < start => {
+                    start -= 1;
+                    end -= 1;
+                }
+                Change::Deleted { offset } if offset < end => end -= 1,
+                _ => {}
+            }
+        }
+
+        let mut buffer = after.clone();
+        buffer.remove(start..end);
+        let mut at = start;
+        for chunk in self.buffer.slice(edit.old.clone()).chunks() {
+            buffer.insert(at, chunk);
+            at += chunk.chars().count();
+        }
+        let map = |offset: usize| {
+            if offset < edit.old.start {
+                shift(offset, changes)
+            } else if offset < edit.old.end {
+                offset - edit.old.start + start
+            } else {
+                shift(offset - edit.old.end + edit.new.end, changes) - end + at
+            }
+        };
+        for cursor in &mut self.cursors {
+            let (offset, anchor) = (map(cursor.offset), map(cursor.anchor));
+            *cursor = CursorPosition::from_char_offset(&buffer, offset).with_anchor(anchor);
+        }
+        self.buffer = buffer;
+    }
+}
+
+/// Where `offset` ends up after `changes`. Text inserted right at it goes
+/// after it.
+fn shift(mut offset: usize, changes: &[Change]) -> usize {
+    for change in changes {
+        match *change {
+            Change::Inserted { offset: at, .. } if at < offset => offset += 1,
+            Change::Deleted { offset: at } if at < offset => offset -= 1,
+            _ => {}
+        }
+    }
+    offset
+}
+
+/// Earlier carets and selections of a tab, for Cursor Undo. Kept beside the
+/// edit history: only moves that leave the text alone are recorded, and an
+/// edit clears them since their offsets may no longer fit the text.
+#[derive(Debug, Clone, Default)]
+pub struct CursorHistory {
+    undo: Vec<Vec<CursorPosition>>,
+    /// Cursors and buffer revision when last observed
+    last: Option<(u64, Vec<CursorPosition>)>,
+}
+
+impl CursorHistory {
+    /// Note the cursors once per frame, recording the previous ones if they
+    /// moved without the text changing
+    pub fn observe(&mut self, cursors: &[CursorPosition], revision: u64) {
+        if let Some((last_revision, previous)) = self.last.take() {
+            if last_revision != revision {
+                self.undo.clear();
+            } else if previous != cursors {
+                self.undo.push(previous);
+                if self.undo.len() > MAX_CURSOR_UNDO_STEPS {
+                    self.undo.remove(0);
+                }
+            }
+        }
+        self.last = Some((revision, cursors.to_vec()));
+    }
+
+    /// Put back the cursors before the last move
+    pub fn undo(&mut self, cursors: &mut Vec<CursorPosition>) -> bool {
+        let Some(previous) = self.undo.pop() else {
+            return false;
+        };
+        if let Some((_, last)) = &mut self.last {
+            last.clone_from(&previous);
+        }
+        *cursors = previous;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Apply `changes` to `text`
+    fn apply(text: &Rope, changes: &[Change]) -> Rope {
+        let mut text = text.clone();
+        for change in changes {
+            match *change {
+                Change::Inserted { offset, ch } => text.insert_char(offset, ch),
+                Change::Deleted { offset } => text.remove(offset..offset + 1),
+            }
+        }
+        text
+    }
+
+    fn inserted(offset: usize, text: &str) -> Vec<Change> {
+        text.chars()
+            .enumerate()
+            .map(|(i, ch)| Change::Inserted {
+                offset: offset + i,
+                ch,
+            })
+            .collect()
+    }
+
+    /// Type `typed` at `offset` of `text` with undo, then have someone else
+    /// make `changes`. Returns the text after undoing.
+    fn undo_after_remote(text: &str, offset: usize, typed: &str, changes: &[Change]) -> String {
+        let mut history = EditHistory::default();
+        let mut buffer = Rope::from_str(text);
+        let mut cursors = vec![CursorPosition::from_char_offset(&buffer, offset)];
+        history.record(&buffer, &cursors, EditKind::Other);
+        buffer.insert(offset, typed);
+        let after = apply(&buffer, changes);
+        history.rebase(&buffer, &after, changes);
+        buffer = after;
+        assert!(history.undo(&mut buffer, &mut cursors));
+        buffer.to_string()
+    }
+
+    #[test]
+    fn undo_keeps_remote_edits_before_and_after() {
+        // "hello world" with "big " typed at 6, then "A" inserted at the
+        // start and "!" at the end by someone else
+        let mut changes = inserted(0, "A");
+        changes.extend(inserted(16, "!"));
+        assert_eq!(
+            undo_after_remote("hello world", 6, "big ", &changes),
+            "Ahello world!"
+        );
+    }
+
+    #[test]
+    fn undo_keeps_remote_deletions() {
+        let changes = [Change::Deleted { offset: 0 }, Change::Deleted { offset: 0 }];
+        assert_eq!(undo_after_remote("abcdef", 4, "XY", &changes), "cdef");
+    }
+
+    #[test]
+    fn undo_keeps_text_inserted_where_ours_starts() {
+        assert_eq!(undo_after_remote("ab", 1, "XY", &inserted(1, "r")), "arb");
+    }
+
+    #[test]
+    fn redo_keeps_remote_edits() {
+        let mut history = EditHistory::default();
+        let mut buffer = Rope::from_str("one two");
+        let mut cursors = vec![CursorPosition::from_char_offset(&buffer, 3)];
+        history.record(&buffer, &cursors, EditKind::Other);
+        buffer.insert(3, ",");
+        assert!(history.undo(&mut buffer, &mut cursors));
+        let changes = inserted(7, " three");
+        let after = apply(&buffer, &changes);
+        history.rebase(&buffer, &after, &changes);
+        buffer = after;
+        assert!(history.redo(&mut buffer, &mut cursors));
+        assert_eq!(buffer.to_string(), "one, two three");
+        assert_eq!(cursors[0].offset, 3);
+    }
+}
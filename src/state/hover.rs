@@ -0,0 +1,30 @@
+use super::multi_cursor;
+use ropey::Rope;
+use std::ops::Range;
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Identifier under an offset, if there is one
+pub fn word_at(rope: &Rope, offset: usize) -> Option<Range<usize>> {
+    let range = multi_cursor::word_range_at(rope, offset);
+    (!range.is_empty() && is_word_char(rope.char(range.start))).then_some(range)
+}
+
+/// Char offsets where `word` appears in the rope as a whole word
+pub fn word_positions(rope: &Rope, word: &str) -> Vec<usize> {
+    let mut positions = Vec::new();
+    let mut current = String::new();
+    for (i, c) in rope.chars().chain(std::iter::once(' ')).enumerate() {
+        if is_word_char(c) {
+            current.push(c);
+        } else if !current.is_empty() {
+            if current == word {
+                positions.push(i - current.chars().count());
+            }
+            current.clear();
+        }
+    }
+    positions
+}
@@ -0,0 +1,191 @@
+use similar::{DiffOp, TextDiff};
+use std::ops::Range;
+
+/// Outcome of a three-way merge
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Merged {
+    pub text: String,
+    /// Places both sides changed differently, left between conflict markers
+    pub conflicts: usize,
+}
+
+/// Lines of `base` one side replaced with `lines` of its own text
+#[derive(Debug, Clone)]
+struct Hunk {
+    base: Range<usize>,
+    lines: Range<usize>,
+}
+
+/// Combine two independent edits of `base` line by line, like diff3: lines
+/// only one side changed take that side's version, and lines both changed
+/// the same way are taken once. Where the two changed the same or adjacent
+/// lines differently, both versions are kept between `<<<<<<<`, `=======`
+/// and `>>>>>>>` markers for the user to pick from.
+pub fn merge(base: &str, ours: &str, theirs: &str) -> Merged {
+    let ours_diff = TextDiff::from_lines(base, ours);
+    let theirs_diff = TextDiff::from_lines(base, theirs);
+    let base_lines = ours_diff.old_slices();
+    let (ours_lines, theirs_lines) = (ours_diff.new_slices(), theirs_diff.new_slices());
+    let ours_hunks = hunks(ours_diff.ops());
+    let theirs_hunks = hunks(theirs_diff.ops());
+
+    let mut merged = Merged {
+        text: String::new(),
+        conflicts: 0,
+    };
+    let (mut i, mut j, mut position) = (0, 0, 0);
+    loop {
+        let next_ours = ours_hunks.get(i).map(|h| h.base.start);
+        let next_theirs = theirs_hunks.get(j).map(|h| h.base.start);
+        let start = match (next_ours, next_theirs) {
+            (Some(a), Some(b)) => a.min(b),
+            (Some(a), None) => a,
+            (None, Some(b)) => b,
+            (None, None) => break,
+        };
+        merged
+            .text
+            .extend(base_lines[position..start].iter().copied());
+
+        // Both sides' hunks that overlap or touch, as one region of base
+        let (first_ours, first_theirs) = (i, j);
+        let mut end = start;
+        loop {
+            if ours_hunks.get(i).is_some_and(|h| h.base.start <= end) {
+                end = end.max(ours_hunks[i].base.end);
+                i += 1;
+            } else if theirs_hunks.get(j).is_some_and(|h| h.base.start <= end) {
+                end = end.max(theirs_hunks[j].base.end);
+                j += 1;
+            } else {
+                break;
+            }
+        }
+        let region = start..end;
+        let ours_text = side(base_lines, ours_lines, &ours_hunks[first_ours..i], &region);
+        let theirs_text = side(
+            base_lines,
+            theirs_lines,
+            &theirs_hunks[first_theirs..j],
+            &region,
+        );
+        if first_theirs == j || ours_text == theirs_text {
+            merged.text.push_str(&ours_text);
+        } else if first_ours == i {
+            merged.text.push_str(&theirs_text);
+        } else {
+            merged.conflicts += 1;
+            merged.text.push_str("<<<<<<< buffer\n");
+            push_line_block(&mut merged.text, &ours_text);
+            merged.text.push_str("=======\n");
+            push_line_block(&mut merged.text, &theirs_text);
+            merged.text.push_str(">>>>>>> disk\n");
+        }
+        position = end;
+    }
+    merged.text.extend(base_lines[position..].iter().copied());
+    merged
+}
+
+/// The changed parts of a diff, with a deletion right before an insertion
+/// counted as one replacement
+fn hunks(ops: &[DiffOp]) -> Vec<Hunk> {
+    let mut hunks: Vec<Hunk> = Vec::new();
+    for op in ops {
+        if matches!(op, DiffOp::Equal { .. }) {
+            continue;
+        }
+        let (base, lines) = (op.old_range(), op.new_range());
+        match hunks.last_mut() {
+            Some(last) if last.base.end == base.start && last.lines.end == lines.start => {
+                last.base.end = base.end;
+                last.lines.end = lines.end;
+            }
+            _ => hunks.push(Hunk { base, lines }),
+        }
+    }
+    hunks
+}
+
+/// One side's text for the lines `region` of base, given its hunks there
+fn side(base: &[&str], lines: &[&str], hunks: &[Hunk], region: &Range<usize>) -> String {
+    let mut text = String::new();
+    let mut position = region.start;
+    for hunk in hunks {
+        text.extend(base[position..hunk.base.start].iter().copied());
+        text.extend(lines[hunk.lines.clone()].iter().copied());
+        position = hunk.base.end;
+    }
+    text.extend(base[position..region.end].iter().copied());
+    text
+}
+
+/// Add `text` before a conflict marker, which has to start a line
+fn push_line_block(out: &mut String, text: &str) {
+    out.push_str(text);
+    if !text.is_empty() && !text.ends_with('\n') {
+        out.push('\n');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changes_to_different_lines_are_combined() {
+        let base = "one\ntwo\nthree\nfour\n";
+        let ours = "ONE\ntwo\nthree\nfour\n";
+        let theirs = "one\ntwo\nthree\nFOUR\nfive\n";
+        assert_eq!(
+            merge(base, ours, theirs),
+            Merged {
+                text: "ONE\ntwo\nthree\nFOUR\nfive\n".to_string(),
+                conflicts: 0
+            }
+        );
+    }
+
+    #[test]
+    fn identical_changes_are_taken_once() {
+        let merged = merge("a\nb\nc\n", "a\nB\nc\n", "a\nB\nc\n");
+        assert_eq!(merged.text, "a\nB\nc\n");
+        assert_eq!(merged.conflicts, 0);
+    }
+
+    #[test]
+    fn different_changes_to_a_line_conflict() {
+        let merged = merge("a\nb\nc\n", "a\nours\nc\n", "a\ntheirs\nc\n");
+        assert_eq!(
+            merged.text,
+            "a\n<<<<<<< buffer\nours\n=======\ntheirs\n>>>>>>> disk\nc\n"
+        );
+        assert_eq!(merged.conflicts, 1);
+    }
+
+    #[test]
+    fn deleted_line_edited_on_the_other_side_conflicts() {
+        let merged = merge("a\nb\nc\n", "a\nc\n", "a\nbee\nc\n");
+        assert_eq!(
+            merged.text,
+            "a\n<<<<<<< buffer\n=======\nbee\n>>>>>>> disk\nc\n"
+        );
+        assert_eq!(merged.conflicts, 1);
+    }
+
+    #[test]
+    fn markers_start_their_own_lines_without_a_final_newline() {
+        let merged = merge("a\nb", "a\nours", "a\ntheirs");
+        assert_eq!(
+            merged.text,
+            "a\n<<<<<<< buffer\nours\n=======\ntheirs\n>>>>>>> disk\n"
+        );
+    }
+
+    #[test]
+    fn unchanged_sides_give_the_other() {
+        let base = "x\ny\n";
+        assert_eq!(merge(base, base, "x\nz\n").text, "x\nz\n");
+        assert_eq!(merge(base, "w\ny\n", base).text, "w\ny\n");
+    }
+}
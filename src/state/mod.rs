@@ -1,5 +1,31 @@
+pub mod bookmarks;
+pub mod comment;
+pub mod completion;
+pub mod control_chars;
+pub mod crdt;
 mod cursor;
+pub mod debounce;
+pub mod document;
 mod editor_state;
+pub mod emmet;
+pub mod folding;
+pub mod fuzzy;
+pub mod gestures;
+pub mod goto;
+mod history;
+pub mod hover;
+pub mod merge;
+pub mod multi_cursor;
+pub mod navigation;
+pub mod occurrences;
+pub mod outline;
+pub mod rope_diff;
+pub mod rust_doc;
+pub mod rust_mod;
+pub mod tags;
+mod wrap;
 
 pub use cursor::CursorPosition;
 pub use editor_state::EditorTabState;
+pub use history::EditKind;
+pub use wrap::WrapLayout;
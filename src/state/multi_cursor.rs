@@ -0,0 +1,860 @@
+use super::cursor::CursorPosition;
+use super::wrap::WrapLayout;
+use ropey::Rope;
+use std::collections::HashSet;
+use std::ops::Range;
+
+/// Caret motions applied to every cursor at once
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CursorMotion {
+    Left,
+    Right,
+    WordLeft,
+    WordRight,
+    Up,
+    Down,
+    /// Toggles between the first non-whitespace character and the start of the line
+    LineStart,
+    LineEnd,
+    DocumentStart,
+    DocumentEnd,
+    PageUp(usize),
+    PageDown(usize),
+}
+
+/// Replacement of `range` by `text`, leaving the caret `caret` characters into the new text
+struct Edit {
+    range: Range<usize>,
+    text: String,
+    caret: usize,
+    /// Where the selection starts in the new text, if the caret should select
+    anchor: Option<usize>,
+}
+
+#[derive(PartialEq, Eq)]
+enum CharClass {
+    Whitespace,
+    Word,
+    Punctuation,
+}
+
+fn char_class(c: char) -> CharClass {
+    if c.is_whitespace() {
+        CharClass::Whitespace
+    } else if c.is_alphanumeric() || c == '_' {
+        CharClass::Word
+    } else {
+        CharClass::Punctuation
+    }
+}
+
+/// Indices of `cursors` ordered by where their selection starts
+fn sorted_indices(cursors: &[CursorPosition]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..cursors.len()).collect();
+    order.sort_by_key(|&i| cursors[i].selection().start);
+    order
+}
+
+/// Number of characters in a line, excluding its line break
+pub fn line_len(rope: &Rope, line_idx: usize) -> usize {
+    let line = rope.line(line_idx);
+    let mut len = line.len_chars();
+    if len > 0 && line.char(len - 1) == '\n' {
+        len -= 1;
+        if len > 0 && line.char(len - 1) == '\r' {
+            len -= 1;
+        }
+    }
+    len
+}
+
+/// Drop carets that coincide with or overlap an earlier one, keeping the primary
+pub fn dedup(cursors: &mut Vec<CursorPosition>) {
+    let mut kept: Vec<Range<usize>> = Vec::with_capacity(cursors.len());
+    cursors.retain(|c| {
+        let range = c.selection();
+        let overlaps = kept
+            .iter()
+            .any(|k| *k == range || (k.start < range.end && range.start < k.end));
+        if !overlaps {
+            kept.push(range);
+        }
+        !overlaps
+    });
+}
+
+/// Apply one edit per caret. Edits are applied back to front so earlier offsets
+/// stay valid, then every caret is placed relative to its own edit.
+fn apply_edits(
+    rope: &mut Rope,
+    cursors: &mut Vec<CursorPosition>,
+    mut edit_for: impl FnMut(&Rope, &CursorPosition) -> Edit,
+) {
+    let order = sorted_indices(cursors);
+    let mut edits: Vec<Edit> = order.iter().map(|&i| edit_for(rope, &cursors[i])).collect();
+
+    // Clip overlapping ranges (e.g. backspace at two adjacent carets)
+    let mut prev_end = 0;
+    for edit in edits.iter_mut() {
+        let start = edit.range.start.max(prev_end);
+        let end = edit.range.end.max(start);
+        edit.range = start..end;
+        prev_end = end;
+    }
+
+    for edit in edits.iter().rev() {
+        if !edit.range.is_empty() {
+            rope.remove(edit.range.clone());
+        }
+        if !edit.text.is_empty() {
+            rope.insert(edit.range.start, &edit.text);
+        }
+    }
+
+    let mut delta: isize = 0;
+    for (edit, &i) in edits.iter().zip(&order) {
+        let start = (edit.range.start as isize + delta) as usize;
+        let anchor = start + edit.anchor.unwrap_or(edit.caret);
+        cursors[i] = CursorPosition::from_char_offset(rope, start + edit.caret).with_anchor(anchor);
+        delta += edit.text.chars().count() as isize - edit.range.len() as isize;
+    }
+    dedup(cursors);
+}
+
+/// Insert `text` at every caret, replacing any selection
+pub fn insert_text(rope: &mut Rope, cursors: &mut Vec<CursorPosition>, text: &str) {
+    let caret = text.chars().count();
+    apply_edits(rope, cursors, |_, cursor| Edit {
+        range: cursor.selection(),
+        text: text.to_string(),
+        caret,
+        anchor: None,
+    });
+}
+
+/// Insert one piece of text per caret, in document order (multi-cursor paste)
+pub fn insert_per_cursor(rope: &mut Rope, cursors: &mut Vec<CursorPosition>, texts: &[&str]) {
+    let mut pieces = texts.iter();
+    apply_edits(rope, cursors, |_, cursor| {
+        let text = pieces.next().copied().unwrap_or_default().to_string();
+        Edit {
+            range: cursor.selection(),
+            caret: text.chars().count(),
+            text,
+            anchor: None,
+        }
+    });
+}
+
+/// Insert whole lines at the start of the line of every caret, so they land
+/// above it with the caret staying where it was in its line
+pub fn insert_above(rope: &mut Rope, cursors: &mut Vec<CursorPosition>, text: &str) {
+    let length = text.chars().count();
+    apply_edits(rope, cursors, |rope, cursor| {
+        let line_start = rope.line_to_char(rope.char_to_line(cursor.offset));
+        Edit {
+            range: line_start..line_start,
+            text: text.to_string(),
+            caret: length + cursor.offset - line_start,
+            anchor: None,
+        }
+    });
+}
+
+/// Insert an opening/closing pair at every caret, leaving the caret between them
+pub fn insert_pair(rope: &mut Rope, cursors: &mut Vec<CursorPosition>, open: char, close: char) {
+    apply_edits(rope, cursors, |_, cursor| Edit {
+        range: cursor.selection(),
+        text: format!("{}{}", open, close),
+        caret: 1,
+        anchor: None,
+    });
+}
+
+/// Step every caret over the `close` character after it instead of typing
+/// another one. Only done, returning true, when each caret is right before
+/// one of `auto_closed`, the closing characters auto-close inserted.
+pub fn type_over(
+    rope: &Rope,
+    cursors: &mut [CursorPosition],
+    close: char,
+    auto_closed: &[usize],
+) -> bool {
+    let applies = cursors.iter().all(|cursor| {
+        !cursor.has_selection()
+            && auto_closed.contains(&cursor.offset)
+            && rope.get_char(cursor.offset) == Some(close)
+    });
+    if applies {
+        for cursor in cursors.iter_mut() {
+            *cursor = CursorPosition::from_char_offset(rope, cursor.offset + 1);
+        }
+    }
+    applies
+}
+
+/// Whether typing `quote` at `offset` should insert the closing quote too:
+/// not next to a word, like the apostrophe in "don't", and not inside a
+/// string that is already open on the line
+pub fn quote_closes(rope: &Rope, offset: usize, quote: char) -> bool {
+    let next_to_word = |c: Option<char>| c.is_some_and(|c| char_class(c) == CharClass::Word);
+    if next_to_word(offset.checked_sub(1).and_then(|i| rope.get_char(i)))
+        || next_to_word(rope.get_char(offset))
+    {
+        return false;
+    }
+    let line_start = rope.line_to_char(rope.char_to_line(offset));
+    let mut in_string = false;
+    let mut escaped = false;
+    for c in rope.slice(line_start..offset).chars() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == quote {
+            in_string = !in_string;
+        }
+    }
+    !in_string
+}
+
+/// Delete the empty pair around every caret, e.g. `(|)` (Backspace). Does
+/// nothing and returns false unless every caret is inside one of `pairs`.
+pub fn delete_pair(
+    rope: &mut Rope,
+    cursors: &mut Vec<CursorPosition>,
+    pairs: &[(char, char)],
+) -> bool {
+    let applies = cursors.iter().all(|cursor| {
+        !cursor.has_selection()
+            && cursor.offset > 0
+            && rope
+                .get_char(cursor.offset)
+                .is_some_and(|close| pairs.contains(&(rope.char(cursor.offset - 1), close)))
+    });
+    if applies {
+        apply_edits(rope, cursors, |_, cursor| Edit {
+            range: cursor.offset - 1..cursor.offset + 1,
+            text: String::new(),
+            caret: 0,
+            anchor: None,
+        });
+    }
+    applies
+}
+
+/// Delete the selection or the character (or word) before every caret (Backspace)
+pub fn delete_backward(rope: &mut Rope, cursors: &mut Vec<CursorPosition>, word: bool) {
+    apply_edits(rope, cursors, |rope, cursor| {
+        let range = if cursor.has_selection() {
+            cursor.selection()
+        } else if word {
+            word_left(rope, cursor.offset)..cursor.offset
+        } else {
+            cursor.offset.saturating_sub(1)..cursor.offset
+        };
+        Edit {
+            range,
+            text: String::new(),
+            caret: 0,
+            anchor: None,
+        }
+    });
+}
+
+/// Delete the selection or the character (or word) after every caret (Delete)
+pub fn delete_forward(rope: &mut Rope, cursors: &mut Vec<CursorPosition>, word: bool) {
+    apply_edits(rope, cursors, |rope, cursor| {
+        let range = if cursor.has_selection() {
+            cursor.selection()
+        } else if word {
+            cursor.offset..word_right(rope, cursor.offset)
+        } else {
+            cursor.offset..(cursor.offset + 1).min(rope.len_chars())
+        };
+        Edit {
+            range,
+            text: String::new(),
+            caret: 0,
+            anchor: None,
+        }
+    });
+}
+
+/// Insert a copy of every selection after it and select the copy; carets
+/// without a selection duplicate their line below, keeping their column
+pub fn duplicate(rope: &mut Rope, cursors: &mut Vec<CursorPosition>) {
+    apply_edits(rope, cursors, |rope, cursor| {
+        if cursor.has_selection() {
+            let range = cursor.selection();
+            let text = rope.slice(range.clone()).to_string();
+            return Edit {
+                range: range.end..range.end,
+                caret: text.chars().count(),
+                text,
+                anchor: Some(0),
+            };
+        }
+
+        let line_idx = rope.char_to_line(cursor.offset);
+        let line_start = rope.line_to_char(line_idx);
+        let line_end = line_start + line_len(rope, line_idx);
+        // The last line has no break of its own
+        let mut line_break = rope
+            .slice(line_end..line_start + rope.line(line_idx).len_chars())
+            .to_string();
+        if line_break.is_empty() {
+            line_break.push('\n');
+        }
+        let text = format!("{}{}", line_break, rope.slice(line_start..line_end));
+        Edit {
+            range: line_end..line_end,
+            caret: line_break.chars().count() + cursor.offset - line_start,
+            text,
+            anchor: None,
+        }
+    });
+}
+
+/// Lines touched by a selection, from the start of its first line to the end
+/// of its last without the line break. A selection ending at the start of a
+/// line doesn't touch that line.
+fn selected_lines(rope: &Rope, selection: Range<usize>) -> Range<usize> {
+    let first = rope.char_to_line(selection.start);
+    let mut last = rope.char_to_line(selection.end);
+    if last > first && rope.line_to_char(last) == selection.end {
+        last -= 1;
+    }
+    rope.line_to_char(first)..rope.line_to_char(last) + line_len(rope, last)
+}
+
+/// Copy the lines touched by every selection (or caret) above or below them.
+/// The selection moves to the lower copy when copying down and stays on the
+/// upper one when copying up, so repeated presses keep adding copies.
+pub fn copy_lines(rope: &mut Rope, cursors: &mut Vec<CursorPosition>, up: bool) {
+    // Carets on lines an earlier caret already copied leave them alone
+    let mut copied_to = 0;
+    apply_edits(rope, cursors, |rope, cursor| {
+        let lines = selected_lines(rope, cursor.selection());
+        if lines.start < copied_to {
+            return Edit {
+                range: copied_to..copied_to,
+                text: String::new(),
+                caret: 0,
+                anchor: None,
+            };
+        }
+        copied_to = lines.end;
+
+        let last_line = rope.char_to_line(lines.end);
+        // The last line has no break of its own
+        let mut line_break = rope
+            .slice(lines.end..rope.line_to_char(last_line) + rope.line(last_line).len_chars())
+            .to_string();
+        if line_break.is_empty() {
+            line_break.push('\n');
+        }
+        let block = rope.slice(lines.clone()).to_string();
+        let shift = if up {
+            0
+        } else {
+            lines.len() + line_break.chars().count()
+        };
+        Edit {
+            text: format!("{block}{line_break}{block}"),
+            caret: shift + cursor.offset - lines.start,
+            anchor: Some(shift + cursor.anchor - lines.start),
+            range: lines,
+        }
+    });
+}
+
+/// Delete the lines touched by every selection (or caret), leaving each caret
+/// at its column on the line that followed, or on the line before when the
+/// last lines go
+pub fn delete_lines(rope: &mut Rope, cursors: &mut Vec<CursorPosition>) {
+    // Line indices to delete with the column of the caret that asked, merged
+    // where carets share or touch lines
+    let mut blocks: Vec<(Range<usize>, usize)> = Vec::new();
+    for i in sorted_indices(cursors) {
+        let cursor = &cursors[i];
+        let lines = selected_lines(rope, cursor.selection());
+        let first = rope.char_to_line(lines.start);
+        let last = rope.char_to_line(lines.end);
+        let column = cursor.offset - rope.line_to_char(rope.char_to_line(cursor.offset));
+        match blocks.last_mut() {
+            Some((block, _)) if first <= block.end => block.end = block.end.max(last + 1),
+            _ => blocks.push((first..last + 1, column)),
+        }
+    }
+
+    for (lines, _) in blocks.iter().rev() {
+        let range = if lines.end < rope.len_lines() {
+            rope.line_to_char(lines.start)..rope.line_to_char(lines.end)
+        } else if lines.start > 0 {
+            // The last line has no break of its own, so the one before it goes
+            let previous = lines.start - 1;
+            rope.line_to_char(previous) + line_len(rope, previous)..rope.len_chars()
+        } else {
+            0..rope.len_chars()
+        };
+        rope.remove(range);
+    }
+
+    let mut removed = 0;
+    *cursors = blocks
+        .iter()
+        .map(|(lines, column)| {
+            let line_idx = (lines.start - removed).min(rope.len_lines() - 1);
+            removed += lines.len();
+            let offset = rope.line_to_char(line_idx) + (*column).min(line_len(rope, line_idx));
+            CursorPosition::from_char_offset(rope, offset)
+        })
+        .collect();
+    dedup(cursors);
+}
+
+/// Sort the lines touched by the selections, or every line of the file when
+/// nothing is selected
+pub fn sort_lines(rope: &mut Rope, cursors: &mut Vec<CursorPosition>, descending: bool) {
+    rewrite_lines(rope, cursors, |lines| {
+        lines.sort();
+        if descending {
+            lines.reverse();
+        }
+    });
+}
+
+/// Remove repeated lines among the lines touched by the selections, or the
+/// whole file when nothing is selected, keeping the first of each
+pub fn remove_duplicate_lines(rope: &mut Rope, cursors: &mut Vec<CursorPosition>) {
+    rewrite_lines(rope, cursors, |lines| {
+        let mut seen = HashSet::new();
+        lines.retain(|line| seen.insert(line.clone()));
+    });
+}
+
+/// Replace blocks of whole lines by `rewrite` of them: the lines of every
+/// selection, merged where they overlap, or all lines but a trailing empty
+/// one when nothing is selected. Rewritten blocks end up selected; without a
+/// selection the caret stays where it was.
+fn rewrite_lines(
+    rope: &mut Rope,
+    cursors: &mut Vec<CursorPosition>,
+    rewrite: impl Fn(&mut Vec<String>),
+) {
+    let selections: Vec<Range<usize>> = sorted_indices(cursors)
+        .into_iter()
+        .map(|i| cursors[i].selection())
+        .filter(|selection| !selection.is_empty())
+        .collect();
+    let whole_file = selections.is_empty();
+    let selections = if whole_file {
+        std::iter::once(0..rope.len_chars()).collect()
+    } else {
+        selections
+    };
+
+    let mut blocks: Vec<Range<usize>> = Vec::new();
+    for selection in selections {
+        let lines = selected_lines(rope, selection);
+        let first = rope.char_to_line(lines.start);
+        let last = rope.char_to_line(lines.end);
+        match blocks.last_mut() {
+            Some(block) if first < block.end => block.end = block.end.max(last + 1),
+            _ => blocks.push(first..last + 1),
+        }
+    }
+
+    // (start, old length, new length) of each block, applied back to front
+    let mut replaced = Vec::with_capacity(blocks.len());
+    for lines in blocks.iter().rev() {
+        let start = rope.line_to_char(lines.start);
+        let end = rope.line_to_char(lines.end - 1) + line_len(rope, lines.end - 1);
+        let text = rope.slice(start..end).to_string();
+        let line_break = if text.contains("\r\n") { "\r\n" } else { "\n" };
+        let mut block: Vec<String> = text
+            .split('\n')
+            .map(|line| line.strip_suffix('\r').unwrap_or(line).to_string())
+            .collect();
+        rewrite(&mut block);
+        let new_text = block.join(line_break);
+        rope.remove(start..end);
+        rope.insert(start, &new_text);
+        replaced.push((start, end - start, new_text.chars().count()));
+    }
+
+    if whole_file {
+        for cursor in cursors.iter_mut() {
+            *cursor = CursorPosition::from_char_offset(rope, cursor.offset.min(rope.len_chars()));
+        }
+        return;
+    }
+    let mut delta: isize = 0;
+    *cursors = replaced
+        .iter()
+        .rev()
+        .map(|&(start, old_len, new_len)| {
+            let start = (start as isize + delta) as usize;
+            delta += new_len as isize - old_len as isize;
+            CursorPosition::from_char_offset(rope, start + new_len).with_anchor(start)
+        })
+        .collect();
+    dedup(cursors);
+}
+
+/// Text of every non-empty selection in document order, one per line
+pub fn selected_text(rope: &Rope, cursors: &[CursorPosition]) -> String {
+    sorted_indices(cursors)
+        .into_iter()
+        .map(|i| cursors[i].selection())
+        .filter(|range| !range.is_empty())
+        .map(|range| rope.slice(range).to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The lines with a caret, each once and with its line break, for copying
+/// when nothing is selected
+pub fn caret_lines(rope: &Rope, cursors: &[CursorPosition]) -> String {
+    let mut lines: Vec<usize> = cursors
+        .iter()
+        .map(|cursor| rope.char_to_line(cursor.offset))
+        .collect();
+    lines.sort_unstable();
+    lines.dedup();
+    lines
+        .into_iter()
+        .map(|line_idx| {
+            let mut line = rope.line(line_idx).to_string();
+            if !line.ends_with('\n') {
+                line.push('\n');
+            }
+            line
+        })
+        .collect()
+}
+
+pub fn select_all(rope: &Rope, cursors: &mut Vec<CursorPosition>) {
+    *cursors = vec![CursorPosition::from_char_offset(rope, rope.len_chars()).with_anchor(0)];
+}
+
+/// Range of the word (or run of punctuation/whitespace) around an offset
+pub fn word_range_at(rope: &Rope, offset: usize) -> Range<usize> {
+    let len = rope.len_chars();
+    if len == 0 {
+        return 0..0;
+    }
+    let class = char_class(rope.char(offset.min(len - 1)));
+    let mut start = offset.min(len);
+    while start > 0 && char_class(rope.char(start - 1)) == class {
+        start -= 1;
+    }
+    let mut end = offset.min(len);
+    while end < len && char_class(rope.char(end)) == class {
+        end += 1;
+    }
+    start..end
+}
+
+/// Range of the whole line containing an offset, including its line break
+pub fn line_range_at(rope: &Rope, offset: usize) -> Range<usize> {
+    let line_idx = rope.char_to_line(offset.min(rope.len_chars()));
+    let start = rope.line_to_char(line_idx);
+    start..start + rope.line(line_idx).len_chars()
+}
+
+/// Contents of the nearest quoted string around a selection, on the line
+/// where the selection starts; the whole string with its quotes when the
+/// contents are already selected
+pub fn string_range_around(rope: &Rope, selection: Range<usize>) -> Option<Range<usize>> {
+    let line_idx = rope.char_to_line(selection.start.min(rope.len_chars()));
+    let line_start = rope.line_to_char(line_idx);
+    let line: Vec<char> = rope.line(line_idx).chars().collect();
+
+    // Pair up unescaped quotes from the start of the line; quotes of another
+    // kind inside a string are part of it
+    let mut open: Option<(usize, char)> = None;
+    let mut escaped = false;
+    for (i, &c) in line.iter().enumerate() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        if c == '\\' {
+            escaped = true;
+            continue;
+        }
+        if !matches!(c, '"' | '\'' | '`') {
+            continue;
+        }
+        match open {
+            Some((start, quote)) if quote == c => {
+                open = None;
+                let contents = line_start + start + 1..line_start + i;
+                if contents == selection {
+                    return Some(contents.start - 1..contents.end + 1);
+                }
+                if contents.start <= selection.start && selection.end <= contents.end {
+                    return Some(contents);
+                }
+            }
+            Some(_) => {}
+            None => open = Some((i, c)),
+        }
+    }
+    None
+}
+
+/// Contents of the nearest bracket pair around a selection; the pair with
+/// its brackets when the contents are already selected
+pub fn bracket_range_around(rope: &Rope, selection: Range<usize>) -> Option<Range<usize>> {
+    const PAIRS: [(char, char); 3] = [('(', ')'), ('[', ']'), ('{', '}')];
+
+    // Walk back to the nearest opening bracket that isn't closed before the selection
+    let mut closers = Vec::new();
+    let mut chars = rope.chars_at(selection.start);
+    let mut offset = selection.start;
+    let (open_offset, close) = loop {
+        let c = chars.prev()?;
+        offset -= 1;
+        if let Some(&(_, close)) = PAIRS.iter().find(|(open, _)| *open == c) {
+            match closers.pop() {
+                None => break (offset, close),
+                Some(expected) if expected != close => return None,
+                Some(_) => {}
+            }
+        } else if PAIRS.iter().any(|(_, close)| *close == c) {
+            closers.push(c);
+        }
+    };
+    let open = rope.char(open_offset);
+
+    // ...and forward to the bracket closing it
+    let mut depth = 0usize;
+    for (i, c) in rope.chars_at(selection.end).enumerate() {
+        if c == open {
+            depth += 1;
+        } else if c == close {
+            if depth == 0 {
+                let contents = open_offset + 1..selection.end + i;
+                return Some(if contents == selection {
+                    open_offset..contents.end + 1
+                } else {
+                    contents
+                });
+            }
+            depth -= 1;
+        }
+    }
+    None
+}
+
+/// Next larger syntactic range around a selection, found by scanning: the
+/// word, the string's contents and then the string, the brackets' contents
+/// and then the brackets, the line without and then with its indentation and
+/// break, the whole lines of the bracket block, and finally the whole text
+pub fn expand_range(rope: &Rope, selection: Range<usize>) -> Option<Range<usize>> {
+    let len = rope.len_chars();
+    let contains = |r: &Range<usize>| {
+        r.start <= selection.start && selection.end <= r.end && r.len() > selection.len()
+    };
+    let whole_lines = |r: Range<usize>| {
+        let first = rope.char_to_line(r.start);
+        let last = rope.char_to_line(r.end.saturating_sub(1).max(r.start));
+        rope.line_to_char(first)..rope.line_to_char(last) + rope.line(last).len_chars()
+    };
+
+    let mut candidates = Vec::new();
+    if selection.start < len && char_class(rope.char(selection.start)) == CharClass::Word {
+        candidates.push(word_range_at(rope, selection.start));
+    }
+    candidates.extend(string_range_around(rope, selection.clone()));
+    if let Some(brackets) = bracket_range_around(rope, selection.clone()) {
+        let outer = if (brackets.start + 1..brackets.end - 1) == selection {
+            brackets.clone()
+        } else {
+            brackets.start - 1..brackets.end + 1
+        };
+        candidates.push(brackets);
+        candidates.push(whole_lines(outer));
+    }
+    let line = line_range_at(rope, selection.start);
+    let text: String = rope.slice(line.clone()).chars().collect();
+    let indent = text.len() - text.trim_start().len();
+    let content = text.trim_start().trim_end_matches(['\r', '\n']);
+    let line_content = line.start + text[..indent].chars().count();
+    candidates.push(line_content..line_content + content.chars().count());
+    candidates.push(whole_lines(selection.clone()));
+    candidates.push(0..len);
+
+    candidates
+        .into_iter()
+        .filter(contains)
+        .min_by_key(|r| r.len())
+}
+
+fn word_left(rope: &Rope, offset: usize) -> usize {
+    let mut i = offset;
+    while i > 0 && char_class(rope.char(i - 1)) == CharClass::Whitespace {
+        i -= 1;
+    }
+    if i > 0 {
+        let class = char_class(rope.char(i - 1));
+        while i > 0 && char_class(rope.char(i - 1)) == class {
+            i -= 1;
+        }
+    }
+    i
+}
+
+fn word_right(rope: &Rope, offset: usize) -> usize {
+    let len = rope.len_chars();
+    let mut i = offset;
+    while i < len && char_class(rope.char(i)) == CharClass::Whitespace {
+        i += 1;
+    }
+    if i < len {
+        let class = char_class(rope.char(i));
+        while i < len && char_class(rope.char(i)) == class {
+            i += 1;
+        }
+    }
+    i
+}
+
+/// Offset on another line, keeping the column where possible
+fn offset_on_line(rope: &Rope, line_idx: usize, column: usize) -> usize {
+    rope.line_to_char(line_idx) + column.min(line_len(rope, line_idx))
+}
+
+fn is_vertical(motion: CursorMotion) -> bool {
+    matches!(
+        motion,
+        CursorMotion::Up | CursorMotion::Down | CursorMotion::PageUp(_) | CursorMotion::PageDown(_)
+    )
+}
+
+/// Where a motion takes the caret; vertical motions aim for `goal_column`
+fn motion_target(rope: &Rope, offset: usize, motion: CursorMotion, goal_column: usize) -> usize {
+    let len = rope.len_chars();
+    let line_idx = rope.char_to_line(offset.min(len));
+    let line_start = rope.line_to_char(line_idx);
+    let column = offset - line_start;
+    let last_line = rope.len_lines() - 1;
+
+    match motion {
+        CursorMotion::Left => offset.saturating_sub(1),
+        CursorMotion::Right => (offset + 1).min(len),
+        CursorMotion::WordLeft => word_left(rope, offset),
+        CursorMotion::WordRight => word_right(rope, offset),
+        CursorMotion::Up if line_idx > 0 => offset_on_line(rope, line_idx - 1, goal_column),
+        CursorMotion::Up => 0,
+        CursorMotion::Down if line_idx < last_line => {
+            offset_on_line(rope, line_idx + 1, goal_column)
+        }
+        CursorMotion::Down => len,
+        CursorMotion::LineStart => {
+            let indent = rope
+                .line(line_idx)
+                .chars()
+                .take(line_len(rope, line_idx))
+                .take_while(|c| c.is_whitespace())
+                .count();
+            if column == indent {
+                line_start
+            } else {
+                line_start + indent
+            }
+        }
+        CursorMotion::LineEnd => line_start + line_len(rope, line_idx),
+        CursorMotion::DocumentStart => 0,
+        CursorMotion::DocumentEnd => len,
+        CursorMotion::PageUp(lines) => {
+            offset_on_line(rope, line_idx.saturating_sub(lines), goal_column)
+        }
+        CursorMotion::PageDown(lines) => {
+            offset_on_line(rope, (line_idx + lines).min(last_line), goal_column)
+        }
+    }
+}
+
+/// Where a vertical motion takes the caret across the visual rows of
+/// soft-wrapped lines; None for other motions
+fn wrapped_motion_target(
+    rope: &Rope,
+    wrap: &WrapLayout,
+    cursor: &CursorPosition,
+    motion: CursorMotion,
+    goal_column: usize,
+) -> Option<usize> {
+    let (row, _) = cursor.visual_position(wrap);
+    let last_row = wrap.row_count() - 1;
+    let target_row = match motion {
+        CursorMotion::Up if row > 0 => row - 1,
+        CursorMotion::Up => return Some(0),
+        CursorMotion::Down if row < last_row => row + 1,
+        CursorMotion::Down => return Some(rope.len_chars()),
+        CursorMotion::PageUp(rows) => row.saturating_sub(rows),
+        CursorMotion::PageDown(rows) => (row + rows).min(last_row),
+        _ => return None,
+    };
+    Some(wrap.offset_in_row(rope, target_row, goal_column))
+}
+
+/// Move every caret by the given motion, extending selections when `extend` is
+/// set. With `wrap`, vertical motions move by visual rows.
+pub fn move_cursors(
+    rope: &Rope,
+    cursors: &mut Vec<CursorPosition>,
+    motion: CursorMotion,
+    extend: bool,
+    wrap: Option<&WrapLayout>,
+) {
+    for cursor in cursors.iter_mut() {
+        // Vertical moves keep aiming for the column they started from
+        let goal_column = cursor.goal_column.unwrap_or_else(|| match wrap {
+            Some(wrap) => cursor.visual_position(wrap).1,
+            None => cursor.column.saturating_sub(1),
+        });
+        let offset = match motion {
+            // Without Shift, Left/Right first collapse an existing selection
+            CursorMotion::Left if !extend && cursor.has_selection() => cursor.selection().start,
+            CursorMotion::Right if !extend && cursor.has_selection() => cursor.selection().end,
+            _ => wrap
+                .and_then(|wrap| wrapped_motion_target(rope, wrap, cursor, motion, goal_column))
+                .unwrap_or_else(|| motion_target(rope, cursor.offset, motion, goal_column)),
+        };
+        let anchor = if extend { cursor.anchor } else { offset };
+        *cursor = CursorPosition::from_char_offset(rope, offset)
+            .with_anchor(anchor)
+            .with_goal_column(is_vertical(motion).then_some(goal_column));
+    }
+    dedup(cursors);
+}
+
+/// Add a caret on the line above the topmost caret, or below the bottommost one
+pub fn add_cursor_vertical(rope: &Rope, cursors: &mut Vec<CursorPosition>, above: bool) {
+    let edge = if above {
+        cursors.iter().min_by_key(|c| c.offset)
+    } else {
+        cursors.iter().max_by_key(|c| c.offset)
+    };
+    let Some(edge) = edge else {
+        return;
+    };
+
+    let line_idx = edge.line.saturating_sub(1);
+    let target_line = if above {
+        line_idx.checked_sub(1)
+    } else {
+        Some(line_idx + 1).filter(|&l| l < rope.len_lines())
+    };
+
+    if let Some(target_line) = target_line {
+        let column = edge.goal_column.unwrap_or(edge.column.saturating_sub(1));
+        let offset = offset_on_line(rope, target_line, column);
+        cursors.push(CursorPosition::from_char_offset(rope, offset));
+        dedup(cursors);
+    }
+}
@@ -0,0 +1,59 @@
+use std::path::PathBuf;
+
+/// Jumps kept to go back to
+const MAX_LOCATIONS: usize = 50;
+
+/// A place in a file the cursor jumped from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub path: PathBuf,
+    /// Char offset of the cursor; clamped to the text when going back to it
+    pub offset: usize,
+}
+
+/// Where the cursor was before each jump, across tabs, for Go Back and Go
+/// Forward
+#[derive(Debug, Default)]
+pub struct NavigationHistory {
+    back: Vec<Location>,
+    forward: Vec<Location>,
+}
+
+impl NavigationHistory {
+    /// Note a jump away from `from`. A new jump drops the locations gone
+    /// back from.
+    pub fn push(&mut self, from: Location) {
+        self.forward.clear();
+        if self.back.last() == Some(&from) {
+            return;
+        }
+        self.back.push(from);
+        if self.back.len() > MAX_LOCATIONS {
+            self.back.remove(0);
+        }
+    }
+
+    /// The location before the last jump; `current` becomes the one to go
+    /// forward to
+    pub fn back(&mut self, current: Location) -> Option<Location> {
+        let location = self.back.pop()?;
+        self.forward.push(current);
+        Some(location)
+    }
+
+    /// The location last gone back from; `current` becomes the one to go
+    /// back to
+    pub fn forward(&mut self, current: Location) -> Option<Location> {
+        let location = self.forward.pop()?;
+        self.back.push(current);
+        Some(location)
+    }
+
+    pub fn can_go_back(&self) -> bool {
+        !self.back.is_empty()
+    }
+
+    pub fn can_go_forward(&self) -> bool {
+        !self.forward.is_empty()
+    }
+}
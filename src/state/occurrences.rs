@@ -0,0 +1,55 @@
+use super::hover;
+use ropey::Rope;
+use std::ops::Range;
+
+/// Compound assignment operators, longest first
+const COMPOUND_ASSIGNMENTS: [&str; 10] =
+    ["<<=", ">>=", "+=", "-=", "*=", "/=", "%=", "&=", "|=", "^="];
+/// Keywords that make the identifier after them a new binding
+const BINDING_KEYWORDS: [&str; 4] = ["let", "mut", "for", "&mut"];
+
+/// A use of the identifier at the caret
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Occurrence {
+    pub range: Range<usize>,
+    /// Assigned or declared here rather than read
+    pub write: bool,
+}
+
+/// Whole-word occurrences of the identifier in `word`, each marked as a read or
+/// a write by the text around it
+pub fn occurrences(rope: &Rope, word: Range<usize>) -> Vec<Occurrence> {
+    let text = rope.slice(word.clone()).to_string();
+    let len = word.len();
+    hover::word_positions(rope, &text)
+        .into_iter()
+        .map(|start| Occurrence {
+            range: start..start + len,
+            write: is_write(rope, start..start + len),
+        })
+        .collect()
+}
+
+/// Whether the identifier at `range` is an assignment target or a new binding:
+/// `x = ..`, `x += ..`, `let x`, `let mut x`, `for x in`, `&mut x`
+fn is_write(rope: &Rope, range: Range<usize>) -> bool {
+    let line = rope.char_to_line(range.start);
+    let line_start = rope.line_to_char(line);
+    let line_end = line_start + rope.line(line).len_chars();
+    let before = rope.slice(line_start..range.start).to_string();
+    let after = rope.slice(range.end..line_end).to_string();
+
+    let after = after.trim_start();
+    let assigned = COMPOUND_ASSIGNMENTS.iter().any(|op| after.starts_with(op))
+        || (after.starts_with('=') && !after.starts_with("==") && !after.starts_with("=>"));
+    if assigned {
+        return true;
+    }
+    let before = before.trim_end();
+    BINDING_KEYWORDS.iter().any(|keyword| {
+        before.strip_suffix(keyword).is_some_and(|rest| {
+            // A whole keyword, not the end of a longer identifier
+            !rest.ends_with(|c: char| c.is_alphanumeric() || c == '_')
+        })
+    })
+}
@@ -0,0 +1,92 @@
+/// What a symbol in the outline declares; picks its icon
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Module,
+    Function,
+    Method,
+    Struct,
+    Enum,
+    Trait,
+    Impl,
+    Class,
+    Interface,
+    Type,
+    Constant,
+    Field,
+    Variable,
+    Heading,
+    Other,
+}
+
+impl SymbolKind {
+    pub fn icon(self) -> &'static str {
+        match self {
+            SymbolKind::Module => "▣",
+            SymbolKind::Function | SymbolKind::Method => "ƒ",
+            SymbolKind::Struct | SymbolKind::Class => "◆",
+            SymbolKind::Enum => "☰",
+            SymbolKind::Trait | SymbolKind::Interface => "◇",
+            SymbolKind::Impl => "⚙",
+            SymbolKind::Type => "T",
+            SymbolKind::Constant => "π",
+            SymbolKind::Field | SymbolKind::Variable => "▪",
+            SymbolKind::Heading => "#",
+            SymbolKind::Other => "•",
+        }
+    }
+}
+
+/// Entry of the document outline
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutlineSymbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// Zero-based line and character column of the symbol's name
+    pub line: usize,
+    pub column: usize,
+    /// Nesting level, 0 at the top
+    pub depth: usize,
+}
+
+/// Index of the last symbol starting at or before `line`, which is the one the
+/// cursor is in for a list sorted by line
+pub fn symbol_at_line(symbols: &[OutlineSymbol], line: usize) -> Option<usize> {
+    symbols.iter().rposition(|symbol| symbol.line <= line)
+}
+
+/// Index of the nearest function or top-level symbol starting after `line`
+/// (zero-based), or before it when not `forward`
+pub fn adjacent_item(symbols: &[OutlineSymbol], line: usize, forward: bool) -> Option<usize> {
+    let items = symbols.iter().enumerate().filter(|(_, symbol)| {
+        symbol.depth == 0 || matches!(symbol.kind, SymbolKind::Function | SymbolKind::Method)
+    });
+    if forward {
+        items
+            .filter(|(_, symbol)| symbol.line > line)
+            .min_by_key(|(_, symbol)| symbol.line)
+            .map(|(i, _)| i)
+    } else {
+        items
+            .filter(|(_, symbol)| symbol.line < line)
+            .max_by_key(|(_, symbol)| symbol.line)
+            .map(|(i, _)| i)
+    }
+}
+
+/// Indices of the symbol containing `line` (zero-based) and its ancestors,
+/// outermost first
+pub fn symbol_chain(symbols: &[OutlineSymbol], line: usize) -> Vec<usize> {
+    let Some(innermost) = symbol_at_line(symbols, line) else {
+        return Vec::new();
+    };
+    let mut chain = vec![innermost];
+    let mut depth = symbols[innermost].depth;
+    for i in (0..innermost).rev() {
+        if symbols[i].depth < depth {
+            chain.push(i);
+            depth = symbols[i].depth;
+        }
+    }
+    chain.reverse();
+    chain
+}
@@ -0,0 +1,160 @@
+use ropey::Rope;
+use std::ops::Range;
+
+/// Where two versions of a text differ: `old` chars of the old text were
+/// replaced by `new` chars of the new one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit {
+    pub old: Range<usize>,
+    pub new: Range<usize>,
+}
+
+/// The part of `new` that differs from `old`, found by comparing chunks from
+/// both ends without copying either text. `None` when they are the same.
+/// Clones of a rope share their chunks, so keeping the previous version to
+/// compare against is cheap.
+pub fn changed_range(old: &Rope, new: &Rope) -> Option<Edit> {
+    let prefix = common_prefix(old, new);
+    if prefix == old.len_bytes() && prefix == new.len_bytes() {
+        return None;
+    }
+    let limit = old.len_bytes().min(new.len_bytes()) - prefix;
+    let suffix = common_suffix(old, new, limit);
+    // A char only partly in the common bytes counts as changed
+    let start = old.byte_to_char(prefix);
+    let end = |rope: &Rope| {
+        let byte = rope.len_bytes() - suffix;
+        let char_idx = rope.byte_to_char(byte);
+        if rope.char_to_byte(char_idx) < byte {
+            char_idx + 1
+        } else {
+            char_idx
+        }
+    };
+    Some(Edit {
+        old: start..end(old),
+        new: start..end(new),
+    })
+}
+
+/// Bytes the two texts start with in common
+fn common_prefix(a: &Rope, b: &Rope) -> usize {
+    let mut a_chunks = a.chunks().map(str::as_bytes);
+    let mut b_chunks = b.chunks().map(str::as_bytes);
+    let (mut x, mut y): (&[u8], &[u8]) = (&[], &[]);
+    let mut len = 0;
+    loop {
+        while x.is_empty() {
+            match a_chunks.next() {
+                Some(chunk) => x = chunk,
+                None => return len,
+            }
+        }
+        while y.is_empty() {
+            match b_chunks.next() {
+                Some(chunk) => y = chunk,
+                None => return len,
+            }
+        }
+        let n = x.len().min(y.len());
+        // Chunks a clone still shares are the same memory
+        if x.as_ptr() != y.as_ptr() && x[..n] != y[..n] {
+            let same = x.iter().zip(y).take_while(|(p, q)| p == q).count();
+            return len + same;
+        }
+        len += n;
+        x = &x[n..];
+        y = &y[n..];
+    }
+}
+
+/// Bytes the two texts end with in common, at most `limit`
+fn common_suffix(a: &Rope, b: &Rope, limit: usize) -> usize {
+    let mut a_chunks = a.chunks_at_byte(a.len_bytes()).0;
+    let mut b_chunks = b.chunks_at_byte(b.len_bytes()).0;
+    let (mut x, mut y): (&[u8], &[u8]) = (&[], &[]);
+    let mut len = 0;
+    while len < limit {
+        while x.is_empty() {
+            match a_chunks.prev() {
+                Some(chunk) => x = chunk.as_bytes(),
+                None => return len,
+            }
+        }
+        while y.is_empty() {
+            match b_chunks.prev() {
+                Some(chunk) => y = chunk.as_bytes(),
+                None => return len,
+            }
+        }
+        let n = x.len().min(y.len()).min(limit - len);
+        let (x_tail, y_tail) = (&x[x.len() - n..], &y[y.len() - n..]);
+        if x_tail.as_ptr() != y_tail.as_ptr() && x_tail != y_tail {
+            let same = x_tail
+                .iter()
+                .rev()
+                .zip(y_tail.iter().rev())
+                .take_while(|(p, q)| p == q)
+                .count();
+            return len + same;
+        }
+        len += n;
+        x = &x[..x.len() - n];
+        y = &y[..y.len() - n];
+    }
+    len
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit(old: &str, new: &str) -> Option<Edit> {
+        changed_range(&Rope::from_str(old), &Rope::from_str(new))
+    }
+
+    #[test]
+    fn same_text_has_no_change() {
+        assert_eq!(edit("abc", "abc"), None);
+        assert_eq!(edit("", ""), None);
+    }
+
+    #[test]
+    fn finds_insertions_deletions_and_replacements() {
+        let change = |old: Range<usize>, new: Range<usize>| Some(Edit { old, new });
+        assert_eq!(edit("abc", "abXc"), change(2..2, 2..3));
+        assert_eq!(edit("abc", "ac"), change(1..2, 1..1));
+        assert_eq!(edit("abc", "aXYc"), change(1..2, 1..3));
+        assert_eq!(edit("", "new"), change(0..0, 0..3));
+        // Repeated text: the change can't overlap the common prefix
+        assert_eq!(edit("aaa", "aaaa"), change(3..3, 3..4));
+    }
+
+    #[test]
+    fn counts_in_chars() {
+        // é and ê share their first byte
+        assert_eq!(
+            edit("café!", "cafê!"),
+            Some(Edit {
+                old: 3..4,
+                new: 3..4
+            })
+        );
+    }
+
+    #[test]
+    fn works_across_chunks() {
+        let old: String = (0..2000).map(|i| format!("line {i}\n")).collect();
+        let mut rope = Rope::from_str(&old);
+        let before = rope.clone();
+        let offset = rope.line_to_char(1500);
+        rope.insert(offset, "inserted ");
+        assert_eq!(
+            changed_range(&before, &rope),
+            Some(Edit {
+                old: offset..offset,
+                new: offset..offset + 9
+            })
+        );
+    }
+}
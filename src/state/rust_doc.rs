@@ -0,0 +1,257 @@
+use ropey::Rope;
+use std::ops::Range;
+
+/// Crates documented at doc.rust-lang.org rather than docs.rs
+const STD_CRATES: [&str; 3] = ["std", "core", "alloc"];
+/// How far below a new `///` the scaffold looks for the end of a signature
+const MAX_SIGNATURE_LINES: usize = 30;
+
+/// Text to insert after a `///` just typed on `line` when the line starts a doc
+/// comment above a function: a summary line, then `# Arguments` for its
+/// parameters and `# Returns` for a return type. The caret belongs one
+/// character into the text, at the summary.
+pub fn scaffold(rope: &Rope, line: usize) -> Option<String> {
+    let text = line_text(rope, line);
+    if text.trim() != "///" {
+        return None;
+    }
+    let indent = &text[..text.len() - text.trim_start().len()];
+    // Only a new comment gets a scaffold, not another line of an existing one
+    let is_doc = |line: usize| line_text(rope, line).trim_start().starts_with("///");
+    if (line > 0 && is_doc(line - 1)) || (line + 1 < rope.len_lines() && is_doc(line + 1)) {
+        return None;
+    }
+
+    let (params, returns) = parse_signature(&signature_after(rope, line)?)?;
+    if params.is_empty() && returns.is_none() {
+        return None;
+    }
+
+    let mut lines = vec![" ".to_string(), String::new()];
+    if !params.is_empty() {
+        lines.push(" # Arguments".to_string());
+        lines.push(String::new());
+        lines.extend(params.iter().map(|p| format!(" * `{p}` - ")));
+        lines.push(String::new());
+    }
+    if returns.is_some() {
+        lines.push(" # Returns".to_string());
+        lines.push(String::new());
+        lines.push(" ".to_string());
+    } else {
+        lines.pop();
+    }
+    Some(lines.join(&format!("\n{indent}///")))
+}
+
+fn line_text(rope: &Rope, line: usize) -> String {
+    rope.line(line)
+        .to_string()
+        .trim_end_matches(['\n', '\r'])
+        .to_string()
+}
+
+/// Signature of the function declared below `line`, past any attributes, up
+/// to its body or `;`
+fn signature_after(rope: &Rope, line: usize) -> Option<String> {
+    let mut lines = (line + 1..rope.len_lines())
+        .map(|l| line_text(rope, l))
+        .skip_while(|l| l.trim_start().starts_with("#["))
+        .take(MAX_SIGNATURE_LINES);
+    let first = lines.next()?;
+    if !declares_fn(&first) {
+        return None;
+    }
+    let mut signature = String::new();
+    for text in std::iter::once(first).chain(lines) {
+        if let Some(end) = text.find(['{', ';']) {
+            signature.push_str(&text[..end]);
+            return Some(signature);
+        }
+        signature.push_str(&text);
+        signature.push(' ');
+    }
+    None
+}
+
+/// Whether a line starts with `fn`, after visibility and qualifiers
+fn declares_fn(line: &str) -> bool {
+    let mut rest = line.trim_start();
+    if let Some(after) = rest.strip_prefix("pub") {
+        rest = after.trim_start();
+        if rest.starts_with('(') {
+            let Some(close) = rest.find(')') else {
+                return false;
+            };
+            rest = rest[close + 1..].trim_start();
+        }
+    }
+    loop {
+        let Some(word) = rest.split_whitespace().next() else {
+            return false;
+        };
+        match word {
+            "fn" => return true,
+            "const" | "async" | "unsafe" | "default" | "extern" => {
+                rest = rest[word.len()..].trim_start();
+                // ABI string of `extern "C" fn`
+                if rest.starts_with('"') {
+                    match rest[1..].find('"') {
+                        Some(close) => rest = rest[close + 2..].trim_start(),
+                        None => return false,
+                    }
+                }
+            }
+            _ => return false,
+        }
+    }
+}
+
+/// Parameter names (without `self`) and the return type of a signature
+fn parse_signature(signature: &str) -> Option<(Vec<String>, Option<String>)> {
+    let after_fn = &signature[signature.find("fn ")? + 3..];
+    let name_end = after_fn
+        .find(|c: char| !(c.is_alphanumeric() || c == '_' || c.is_whitespace()))
+        .unwrap_or(after_fn.len());
+    let mut rest = &after_fn[name_end..];
+    if rest.starts_with('<') {
+        rest = &rest[closing(rest, '<', '>')? + 1..];
+    }
+    let rest = rest.trim_start();
+    if !rest.starts_with('(') {
+        return None;
+    }
+    let close = closing(rest, '(', ')')?;
+
+    let params = split_top_level(&rest[1..close], ',')
+        .into_iter()
+        .filter_map(|param| {
+            let param = param.trim();
+            let pattern = split_top_level(param, ':').into_iter().next()?.trim();
+            let pattern = pattern.strip_prefix("mut ").unwrap_or(pattern).trim();
+            (!pattern.is_empty() && !is_self(pattern)).then(|| pattern.to_string())
+        })
+        .collect();
+
+    let returns = rest[close + 1..]
+        .trim_start()
+        .strip_prefix("->")
+        .map(|ty| ty.split(" where ").next().unwrap_or(ty).trim().to_string())
+        .filter(|ty| !ty.is_empty() && ty != "()");
+    Some((params, returns))
+}
+
+fn is_self(pattern: &str) -> bool {
+    let pattern = pattern.trim_start_matches('&').trim_start();
+    // Lifetime of `&'a self`
+    let pattern = match pattern.strip_prefix('\'') {
+        Some(lifetime) => lifetime
+            .split_once(char::is_whitespace)
+            .map_or("", |(_, rest)| rest),
+        None => pattern,
+    };
+    let pattern = pattern.trim_start();
+    pattern.strip_prefix("mut ").unwrap_or(pattern).trim() == "self"
+}
+
+/// Byte index of the bracket closing the one `text` starts with. The `>` of
+/// `->` doesn't close an angle bracket.
+fn closing(text: &str, open: char, close: char) -> Option<usize> {
+    let mut depth = 0;
+    let mut previous = ' ';
+    for (i, c) in text.char_indices() {
+        if c == open {
+            depth += 1;
+        } else if c == close && !(close == '>' && previous == '-') {
+            depth -= 1;
+            if depth == 0 {
+                return Some(i);
+            }
+        }
+        previous = c;
+    }
+    None
+}
+
+/// Split on `separator` outside of brackets
+fn split_top_level(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0i32;
+    let mut start = 0;
+    let mut previous = ' ';
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '(' | '[' | '{' | '<' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            '>' if previous != '-' => depth -= 1,
+            // `::` in a path is not a type annotation
+            ':' if separator == ':'
+                && (previous == ':' || chars.peek().map(|p| p.1) == Some(':')) => {}
+            c if c == separator && depth == 0 => {
+                parts.push(&text[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+        previous = c;
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+/// `std`, `core` or `alloc` path under the pointer, up to the hovered segment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocLink {
+    pub range: Range<usize>,
+    pub path: String,
+    pub url: String,
+}
+
+/// Documentation link for a standard library path like
+/// `std::collections::HashMap` at `offset`
+pub fn std_doc_link(rope: &Rope, offset: usize) -> Option<DocLink> {
+    if offset >= rope.len_chars() {
+        return None;
+    }
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    if !is_ident(rope.char(offset)) {
+        return None;
+    }
+    let line = rope.char_to_line(offset);
+    let line_start = rope.line_to_char(line);
+    let chars: Vec<char> = rope.line(line).chars().collect();
+    let column = offset - line_start;
+
+    let mut end = column;
+    while end < chars.len() && is_ident(chars[end]) {
+        end += 1;
+    }
+    let mut start = column;
+    while start > 0 && (is_ident(chars[start - 1]) || chars[start - 1] == ':') {
+        start -= 1;
+    }
+    while start < column && chars[start] == ':' {
+        start += 1;
+    }
+
+    let path: String = chars[start..end].iter().collect();
+    let segments: Vec<&str> = path.split("::").collect();
+    if !STD_CRATES.contains(&segments[0])
+        || segments.iter().any(|s| s.is_empty() || s.contains(':'))
+    {
+        return None;
+    }
+    let krate = segments[0];
+    let url = if segments.len() == 1 {
+        format!("https://doc.rust-lang.org/{krate}/index.html")
+    } else {
+        // rustdoc's search opens the first result for an exact path
+        format!("https://doc.rust-lang.org/{krate}/index.html?search={path}&go_to_first=true")
+    };
+    Some(DocLink {
+        range: line_start + start..line_start + end,
+        path,
+        url,
+    })
+}
@@ -0,0 +1,230 @@
+use std::path::{Path, PathBuf};
+
+/// `src` folder of the crate a file belongs to, found from the nearest
+/// Cargo.toml above it
+pub fn crate_src(file: &Path) -> Option<PathBuf> {
+    let root = file
+        .ancestors()
+        .skip(1)
+        .find(|dir| dir.join("Cargo.toml").is_file())?;
+    Some(root.join("src")).filter(|src| file.starts_with(src))
+}
+
+/// Module path of a source file in its crate, e.g. `["widgets", "tab_bar"]`
+/// for `src/widgets/tab_bar.rs`. Crate roots have an empty path; binaries in
+/// `src/bin` are crates of their own and have none.
+pub fn module_path(src: &Path, file: &Path) -> Option<Vec<String>> {
+    let relative = file.strip_prefix(src).ok()?;
+    let mut segments: Vec<String> = relative
+        .iter()
+        .map(|s| s.to_string_lossy().into_owned())
+        .collect();
+    if segments.len() > 1 && segments[0] == "bin" {
+        return None;
+    }
+    let file_name = segments.pop()?;
+    let stem = file_name.strip_suffix(".rs")?;
+    match stem {
+        "mod" => {}
+        "main" | "lib" if segments.is_empty() => {}
+        _ => segments.push(stem.to_string()),
+    }
+    Some(segments)
+}
+
+/// Rust source files in a folder and its subfolders
+pub fn source_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return files;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if entry.file_type().is_ok_and(|t| t.is_dir()) {
+            files.extend(source_files(&path));
+        } else if path.extension().is_some_and(|ext| ext == "rs") {
+            files.push(path);
+        }
+    }
+    files.sort();
+    files
+}
+
+/// Whether `name` can be a module name
+pub fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(is_ident_char)
+        && name != "_"
+}
+
+/// Text of a source file in `file_module` after the module at `module` is
+/// renamed to `new_name`: its `mod` declaration if this file is the parent
+/// module, and paths through it in `use` declarations and expressions.
+/// `None` if the file doesn't refer to the module.
+pub fn rename_references(
+    text: &str,
+    file_module: &[String],
+    module: &[String],
+    new_name: &str,
+) -> Option<String> {
+    let (old, parent) = module.split_last()?;
+
+    // Occurrences that refer to the module, and bare `old::` paths that would
+    // if the file imports it
+    let mut renamed = Vec::new();
+    let mut bare = Vec::new();
+    let mut imported = file_module == parent;
+    for start in word_positions(text, old) {
+        let end = start + old.len();
+        let line_start = text[..start].rfind('\n').map_or(0, |i| i + 1);
+        if text[line_start..start].contains("//") {
+            continue;
+        }
+        let declared = text[..start]
+            .trim_end()
+            .strip_suffix("mod")
+            .is_some_and(|rest| !rest.ends_with(is_ident_char));
+        if declared {
+            if file_module == parent {
+                renamed.push(start);
+            }
+            continue;
+        }
+        let Some(prefix) = path_before(text, start) else {
+            continue;
+        };
+        let followed_by_path = text[end..].starts_with("::");
+        let in_use = in_use_declaration(text, start);
+        if !followed_by_path && !in_use {
+            continue;
+        }
+        if prefix.is_empty() && !in_use {
+            bare.push(start);
+        } else if resolve(file_module, &prefix).as_deref() == Some(parent) {
+            renamed.push(start);
+            if in_use && !followed_by_path && !text[end..].trim_start().starts_with("as ") {
+                imported = true;
+            }
+        }
+    }
+    if imported {
+        renamed.extend(bare);
+        renamed.sort_unstable();
+    }
+    if renamed.is_empty() {
+        return None;
+    }
+
+    let mut result = String::with_capacity(text.len());
+    let mut last = 0;
+    for start in renamed {
+        result.push_str(&text[last..start]);
+        result.push_str(new_name);
+        last = start + old.len();
+    }
+    result.push_str(&text[last..]);
+    Some(result)
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Byte offsets of whole-word occurrences of `word`
+fn word_positions(text: &str, word: &str) -> Vec<usize> {
+    text.match_indices(word)
+        .map(|(start, _)| start)
+        .filter(|&start| {
+            !text[..start].ends_with(is_ident_char)
+                && !text[start + word.len()..].starts_with(is_ident_char)
+                // Raw identifiers and lifetimes are something else
+                && !text[..start].ends_with(['#', '\''])
+        })
+        .collect()
+}
+
+/// Path segments written before the identifier at `start`, e.g. `crate`,
+/// `widgets` for `crate::widgets::tab_bar`, continuing past the `{` of a
+/// `use` group the identifier is in. `None` after a leading `::`.
+fn path_before(text: &str, start: usize) -> Option<Vec<String>> {
+    let mut segments = Vec::new();
+    let mut end = start;
+    while text[..end].ends_with("::") {
+        end -= 2;
+        let segment_start = text[..end].trim_end_matches(is_ident_char).len();
+        if segment_start == end {
+            return None;
+        }
+        segments.push(text[segment_start..end].to_string());
+        end = segment_start;
+    }
+    segments.reverse();
+
+    let before = text[..end].trim_end();
+    if before.ends_with(['{', ',']) {
+        if let Some(open) = group_open(text, before.len()) {
+            if text[..open].ends_with("::") {
+                let mut outer = path_before(text, open)?;
+                outer.extend(segments);
+                return Some(outer);
+            }
+        }
+    }
+    Some(segments)
+}
+
+/// Byte index of the unclosed `{` before `end`, within the same statement
+fn group_open(text: &str, end: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in text[..end].char_indices().rev() {
+        match c {
+            '}' => depth += 1,
+            '{' if depth == 0 => return Some(i),
+            '{' => depth -= 1,
+            ';' | '(' | '[' if depth == 0 => return None,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Whether the statement around byte index `start` is a `use` declaration
+fn in_use_declaration(text: &str, start: usize) -> bool {
+    let statement_start = text[..start].rfind(';').map_or(0, |i| i + 1);
+    text[statement_start..start]
+        .lines()
+        .map(str::trim_start)
+        // Skip comments, attributes and the ends of blocks since the last `;`
+        .find(|line| {
+            !line.trim_start_matches('}').trim().is_empty()
+                && !line.starts_with("//")
+                && !line.starts_with("#[")
+        })
+        .is_some_and(|line| {
+            let line = line.strip_prefix("pub").map_or(line, |rest| {
+                match rest.trim_start().strip_prefix('(') {
+                    Some(scoped) => scoped.split_once(')').map_or(rest, |(_, after)| after),
+                    None => rest,
+                }
+            });
+            line.trim_start().starts_with("use ")
+        })
+}
+
+/// Absolute module path that `prefix` names from inside `file_module`
+fn resolve(file_module: &[String], prefix: &[String]) -> Option<Vec<String>> {
+    let (mut module, rest) = match prefix.first().map(String::as_str) {
+        Some("crate") => (Vec::new(), &prefix[1..]),
+        Some("self") => (file_module.to_vec(), &prefix[1..]),
+        _ => (file_module.to_vec(), prefix),
+    };
+    for segment in rest {
+        if segment == "super" {
+            module.pop()?;
+        } else {
+            module.push(segment.clone());
+        }
+    }
+    Some(module)
+}
@@ -0,0 +1,226 @@
+use ropey::Rope;
+use std::ops::Range;
+
+/// HTML elements without content or a closing tag
+pub const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// Kinds of files with paired tags
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Markup {
+    /// Void elements have no closing tag, and names ignore case
+    Html,
+    Xml,
+}
+
+impl Markup {
+    pub fn for_extension(ext: &str) -> Option<Self> {
+        match ext {
+            "html" | "htm" | "vue" => Some(Self::Html),
+            "xml" | "xhtml" | "svg" | "xsl" | "xslt" | "xaml" | "plist" => Some(Self::Xml),
+            _ => None,
+        }
+    }
+
+    fn same_name(self, a: &str, b: &str) -> bool {
+        match self {
+            Self::Html => a.eq_ignore_ascii_case(b),
+            Self::Xml => a == b,
+        }
+    }
+
+    fn is_void(self, name: &str) -> bool {
+        self == Self::Html && VOID_ELEMENTS.contains(&name.to_ascii_lowercase().as_str())
+    }
+}
+
+/// Name of a tag and the name of the tag it pairs with, as char ranges
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagPair {
+    pub name: Range<usize>,
+    pub partner: Range<usize>,
+}
+
+/// Whether `c` can be part of a tag name
+pub fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '-' | '_' | ':' | '.')
+}
+
+/// Closing tag to insert after a `>` just typed at `offset - 1`, e.g.
+/// `</div>` after `<div class="a">`. `None` after closing, self-closing and
+/// void tags, comments and declarations, or when the closing tag already
+/// follows.
+pub fn closing_tag(rope: &Rope, offset: usize, markup: Markup) -> Option<String> {
+    if offset == 0 || rope.char(offset - 1) != '>' {
+        return None;
+    }
+    // Back to the `<` of the tag this `>` ends, which may come after other
+    // `>` in attribute values
+    let mut chars = rope.chars_at(offset - 1);
+    let mut start = offset - 1;
+    let tag = loop {
+        let c = chars.prev()?;
+        start -= 1;
+        if offset - start > 1000 {
+            return None;
+        }
+        if c != '<' {
+            continue;
+        }
+        let tag = parse_tag(rope, start)?;
+        if tag.end == offset {
+            break tag;
+        }
+    };
+    if tag.closing || tag.self_closing {
+        return None;
+    }
+    let name = rope.slice(tag.name).to_string();
+    if markup.is_void(&name) {
+        return None;
+    }
+    let closing = format!("</{name}>");
+    let length = closing.chars().count();
+    let follows = offset + length <= rope.len_chars()
+        && rope.slice(offset..offset + length) == closing.as_str();
+    (!follows).then_some(closing)
+}
+
+/// The name of the tag the caret at `offset` is in or right after, and the
+/// name of its opening or closing partner
+pub fn matching_tag(rope: &Rope, offset: usize, markup: Markup) -> Option<TagPair> {
+    let mut start = offset;
+    let mut chars = rope.chars_at(offset);
+    while chars.prev().is_some_and(is_name_char) {
+        start -= 1;
+    }
+    let lt = match (start.checked_sub(1).map(|i| rope.char(i)), start) {
+        (Some('<'), _) => start - 1,
+        (Some('/'), 2..) if rope.char(start - 2) == '<' => start - 2,
+        _ => return None,
+    };
+    let tag = parse_tag(rope, lt)?;
+    let name = rope.slice(tag.name.clone()).to_string();
+    if tag.self_closing || markup.is_void(&name) {
+        return None;
+    }
+
+    let mut depth = 0usize;
+    if tag.closing {
+        // Back through the tags before it for the opening one
+        let mut chars = rope.chars_at(lt);
+        let mut position = lt;
+        while let Some(c) = chars.prev() {
+            position -= 1;
+            if c != '<' {
+                continue;
+            }
+            let Some(other) = parse_tag(rope, position) else {
+                continue;
+            };
+            if other.self_closing
+                || !markup.same_name(&rope.slice(other.name.clone()).to_string(), &name)
+            {
+                continue;
+            }
+            if other.closing {
+                depth += 1;
+            } else if depth == 0 {
+                return Some(TagPair {
+                    name: tag.name,
+                    partner: other.name,
+                });
+            } else {
+                depth -= 1;
+            }
+        }
+    } else {
+        // On through the tags after it for the closing one
+        let chars = rope.chars_at(tag.end);
+        for (i, c) in chars.enumerate() {
+            if c != '<' {
+                continue;
+            }
+            let Some(other) = parse_tag(rope, tag.end + i) else {
+                continue;
+            };
+            if other.self_closing
+                || !markup.same_name(&rope.slice(other.name.clone()).to_string(), &name)
+            {
+                continue;
+            }
+            if !other.closing {
+                depth += 1;
+            } else if depth == 0 {
+                return Some(TagPair {
+                    name: tag.name,
+                    partner: other.name,
+                });
+            } else {
+                depth -= 1;
+            }
+        }
+    }
+    None
+}
+
+struct Tag {
+    name: Range<usize>,
+    closing: bool,
+    self_closing: bool,
+    /// Offset after the `>`, or where the tag breaks off
+    end: usize,
+}
+
+/// The tag starting with the `<` at `lt`; `None` for comments, declarations
+/// and a `<` that doesn't start a tag
+fn parse_tag(rope: &Rope, lt: usize) -> Option<Tag> {
+    let mut chars = rope.chars_at(lt + 1).peekable();
+    let mut position = lt + 1;
+    let closing = chars.next_if_eq(&'/').is_some();
+    if closing {
+        position += 1;
+    }
+    if !chars.peek().is_some_and(|c| c.is_alphabetic()) {
+        return None;
+    }
+    let name_start = position;
+    while chars.next_if(|&c| is_name_char(c)).is_some() {
+        position += 1;
+    }
+    let name = name_start..position;
+
+    // To the `>`, skipping quoted attribute values
+    let mut quote = None;
+    let mut previous = ' ';
+    for c in chars {
+        position += 1;
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '>') => {
+                return Some(Tag {
+                    name,
+                    closing,
+                    self_closing: previous == '/',
+                    end: position,
+                })
+            }
+            (None, '<') => {
+                position -= 1;
+                break;
+            }
+            _ => {}
+        }
+        previous = c;
+    }
+    Some(Tag {
+        name,
+        closing,
+        self_closing: false,
+        end: position,
+    })
+}
@@ -0,0 +1,174 @@
+use super::multi_cursor;
+use ropey::Rope;
+use std::ops::Range;
+
+/// Columns a tab takes; egui draws it as four spaces wherever it is
+const TAB_WIDTH: usize = 4;
+
+/// Visual rows of a buffer whose lines are soft-wrapped at a number of columns.
+/// Rows break after whitespace where possible and mid-word when a word is
+/// longer than a row; whitespace at a break hangs past the edge. Lines inside
+/// a fold have no rows.
+#[derive(Debug, Clone, Default)]
+pub struct WrapLayout {
+    /// Buffer revision the layout was made for
+    pub revision: u64,
+    /// Width of a row in monospace columns
+    pub columns: usize,
+    /// First visual row of each line, then the total row count
+    line_rows: Vec<usize>,
+    /// Column within its line where each visual row starts
+    row_starts: Vec<usize>,
+    /// Length of each line without the line break
+    line_lens: Vec<usize>,
+    /// Line ranges of the folds the layout was made with
+    hidden: Vec<Range<usize>>,
+}
+
+impl WrapLayout {
+    /// Rows of `rope` at `columns`, leaving out the `hidden` lines. Pass
+    /// `usize::MAX` columns to only fold.
+    pub fn new(rope: &Rope, revision: u64, columns: usize, hidden: Vec<Range<usize>>) -> Self {
+        let columns = columns.max(1);
+        let mut layout = Self {
+            revision,
+            columns,
+            line_rows: Vec::with_capacity(rope.len_lines() + 1),
+            row_starts: Vec::with_capacity(rope.len_lines()),
+            line_lens: Vec::with_capacity(rope.len_lines()),
+            hidden: Vec::new(),
+        };
+        let mut sorted = hidden.clone();
+        sorted.sort_by_key(|range| range.start);
+        let mut folds = sorted.iter().peekable();
+        for line_idx in 0..rope.len_lines() {
+            let len = multi_cursor::line_len(rope, line_idx);
+            layout.line_rows.push(layout.row_starts.len());
+            layout.line_lens.push(len);
+            while folds.next_if(|range| range.end <= line_idx).is_some() {}
+            // Folds are in order of their first line, so the first one not
+            // over yet covers the line if any does
+            if folds.peek().is_some_and(|range| range.contains(&line_idx)) {
+                continue;
+            }
+            layout.row_starts.push(0);
+            // Lines that fit even if every character were a tab need no scan
+            if len * TAB_WIDTH > columns {
+                let chars = rope.line(line_idx).chars().take(len);
+                wrap_line(chars, columns, &mut layout.row_starts);
+            }
+        }
+        layout.line_rows.push(layout.row_starts.len());
+        layout.hidden = hidden;
+        layout
+    }
+
+    /// Line ranges of the folds the layout was made with
+    pub fn hidden(&self) -> &[Range<usize>] {
+        &self.hidden
+    }
+
+    /// Whether a line is inside a fold
+    pub fn is_hidden(&self, line_idx: usize) -> bool {
+        line_idx < self.line_count() && self.line_rows(line_idx).is_empty()
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_lens.len()
+    }
+
+    pub fn row_count(&self) -> usize {
+        self.row_starts.len()
+    }
+
+    /// Visual rows of a line. `line_idx` may be one past the last line, which
+    /// gives the empty range at the end.
+    pub fn line_rows(&self, line_idx: usize) -> Range<usize> {
+        let last = self.line_lens.len();
+        let line_idx = line_idx.min(last);
+        self.line_rows[line_idx]..self.line_rows[(line_idx + 1).min(last)]
+    }
+
+    /// Line a visual row belongs to; rows past the end belong to the last line
+    pub fn row_line(&self, row: usize) -> usize {
+        let row = row.min(self.row_count().saturating_sub(1));
+        self.line_rows.partition_point(|&first| first <= row) - 1
+    }
+
+    /// Columns of a row's line that the row shows, without the whitespace it
+    /// breaks after
+    pub fn row_columns(&self, row: usize) -> Range<usize> {
+        let line_idx = self.row_line(row);
+        let start = self.row_starts[row];
+        let end = if row + 1 < self.line_rows[line_idx + 1] {
+            self.row_starts[row + 1]
+        } else {
+            self.line_lens[line_idx]
+        };
+        start..end
+    }
+
+    /// Whether a row is the last of its line
+    pub fn is_last_row(&self, row: usize) -> bool {
+        row + 1 == self.line_rows[self.row_line(row) + 1]
+    }
+
+    /// Visual row showing a column of a line. A column where a row breaks
+    /// belongs to the row it starts, and lines inside a fold to the last row
+    /// of the line it folds into.
+    pub fn row_at(&self, line_idx: usize, column: usize) -> usize {
+        if self.is_hidden(line_idx) {
+            return self.line_rows[line_idx].saturating_sub(1);
+        }
+        let rows = self.line_rows(line_idx);
+        let within = self.row_starts[rows.clone()].partition_point(|&start| start <= column);
+        rows.start + within.saturating_sub(1)
+    }
+
+    /// Char offset of a column within a visual row, clamped to the row so the
+    /// caret stays on it
+    pub fn offset_in_row(&self, rope: &Rope, row: usize, column: usize) -> usize {
+        let line_idx = self.row_line(row);
+        let columns = self.row_columns(row);
+        let last_column = if self.is_last_row(row) {
+            columns.end
+        } else {
+            // The row's end is where the next one starts
+            columns.end - 1
+        };
+        rope.line_to_char(line_idx) + (columns.start + column).min(last_column)
+    }
+}
+
+/// Push the columns where the rows of a line after its first one start
+fn wrap_line(chars: impl Iterator<Item = char>, columns: usize, row_starts: &mut Vec<usize>) {
+    let mut row_start = 0;
+    let mut width = 0;
+    // Column after the last whitespace in the row, and the width since then
+    let mut break_at: Option<usize> = None;
+    let mut since_break = 0;
+    for (i, c) in chars.enumerate() {
+        let char_width = if c == '\t' { TAB_WIDTH } else { 1 };
+        if !c.is_whitespace() && width + char_width > columns && i > row_start {
+            match break_at.filter(|&b| b > row_start) {
+                Some(b) => {
+                    row_start = b;
+                    width = since_break;
+                }
+                None => {
+                    row_start = i;
+                    width = 0;
+                }
+            }
+            row_starts.push(row_start);
+            break_at = None;
+        }
+        width += char_width;
+        if c.is_whitespace() {
+            break_at = Some(i + 1);
+            since_break = 0;
+        } else {
+            since_break += char_width;
+        }
+    }
+}
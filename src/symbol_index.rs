@@ -0,0 +1,158 @@
+use crate::exclude::Exclusions;
+use crate::lsp::{Position, PositionEncoding};
+use crate::state::outline::SymbolKind;
+use regex::Regex;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+
+/// Files larger than this are skipped; they are rarely hand-written source
+pub const MAX_FILE_BYTES: u64 = 1 << 20;
+
+/// Source files the declaration patterns are written for
+const EXTENSIONS: &[&str] = &["rs", "py", "js", "jsx", "mjs", "cjs", "ts", "tsx", "go"];
+
+/// Declaration patterns; the `name` group is the symbol
+const PATTERNS: &[(&str, SymbolKind)] = &[
+    (
+        r#"^\s*(?:pub(?:\([^)]*\))?\s+)?(?:(?:const|async|unsafe|extern(?:\s+"[^"]*")?)\s+)*fn\s+(?P<name>\w+)"#,
+        SymbolKind::Function,
+    ),
+    (
+        r"^\s*(?:pub(?:\([^)]*\))?\s+)?(?:struct|union)\s+(?P<name>\w+)",
+        SymbolKind::Struct,
+    ),
+    (
+        r"^\s*(?:pub(?:\([^)]*\))?\s+)?(?:export\s+)?(?:const\s+)?enum\s+(?P<name>\w+)",
+        SymbolKind::Enum,
+    ),
+    (
+        r"^\s*(?:pub(?:\([^)]*\))?\s+)?(?:unsafe\s+)?trait\s+(?P<name>\w+)",
+        SymbolKind::Trait,
+    ),
+    (
+        r"^\s*(?:pub(?:\([^)]*\))?\s+)?mod\s+(?P<name>\w+)",
+        SymbolKind::Module,
+    ),
+    (
+        r"^\s*(?:pub(?:\([^)]*\))?\s+)?(?:export\s+)?type\s+(?P<name>\w+)",
+        SymbolKind::Type,
+    ),
+    (
+        r"^\s*(?:pub(?:\([^)]*\))?\s+)?(?:const|static)\s+(?:mut\s+)?(?P<name>[A-Z][A-Z0-9_]*)\s*:",
+        SymbolKind::Constant,
+    ),
+    (
+        r"^\s*(?:export\s+)?(?:default\s+)?(?:abstract\s+)?class\s+(?P<name>\w+)",
+        SymbolKind::Class,
+    ),
+    (
+        r"^\s*(?:export\s+)?interface\s+(?P<name>\w+)",
+        SymbolKind::Interface,
+    ),
+    (
+        r"^\s*(?:async\s+)?def\s+(?P<name>\w+)",
+        SymbolKind::Function,
+    ),
+    (
+        r"^\s*(?:export\s+)?(?:default\s+)?(?:async\s+)?function\*?\s+(?P<name>\w+)",
+        SymbolKind::Function,
+    ),
+    (
+        r"^func\s+(?:\([^)]*\)\s*)?(?P<name>\w+)",
+        SymbolKind::Function,
+    ),
+];
+
+/// A declaration somewhere in the workspace
+#[derive(Debug, Clone)]
+pub struct WorkspaceSymbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub path: PathBuf,
+    pub position: Position,
+    /// Unit of `position.character`
+    pub encoding: PositionEncoding,
+}
+
+/// Find the declarations in the source files under `root` on a worker thread;
+/// they arrive on the returned channel and `ctx` is repainted then
+pub fn index_in_background(
+    root: PathBuf,
+    exclusions: Exclusions,
+    ctx: egui::Context,
+) -> Receiver<Vec<WorkspaceSymbol>> {
+    let (tx, rx) = mpsc::channel();
+    let spawned = std::thread::Builder::new()
+        .name("symbol-index".to_string())
+        .spawn(move || {
+            let patterns: Vec<(Regex, SymbolKind)> = PATTERNS
+                .iter()
+                .filter_map(|(pattern, kind)| Some((Regex::new(pattern).ok()?, *kind)))
+                .collect();
+            let mut symbols = Vec::new();
+            index_dir(&root, &exclusions, &patterns, &mut symbols);
+            let _ = tx.send(symbols);
+            ctx.request_repaint();
+        });
+    if spawned.is_err() {
+        // Without a result the search falls back to the language servers
+        return mpsc::channel().1;
+    }
+    rx
+}
+
+fn index_dir(
+    dir: &Path,
+    exclusions: &Exclusions,
+    patterns: &[(Regex, SymbolKind)],
+    symbols: &mut Vec<WorkspaceSymbol>,
+) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if exclusions.is_excluded(&path, file_type.is_dir()) {
+            continue;
+        }
+        if file_type.is_dir() {
+            if !name.starts_with('.') {
+                index_dir(&path, exclusions, patterns, symbols);
+            }
+            continue;
+        }
+        let is_source = path
+            .extension()
+            .is_some_and(|ext| EXTENSIONS.contains(&ext.to_string_lossy().as_ref()));
+        let small = entry.metadata().is_ok_and(|m| m.len() <= MAX_FILE_BYTES);
+        if !is_source || !small {
+            continue;
+        }
+        let Ok(text) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        for (line, text) in text.lines().enumerate() {
+            let found = patterns.iter().find_map(|(regex, kind)| {
+                let name = regex.captures(text)?.name("name")?;
+                Some((name, *kind))
+            });
+            if let Some((name, kind)) = found {
+                symbols.push(WorkspaceSymbol {
+                    name: name.as_str().to_string(),
+                    kind,
+                    path: path.clone(),
+                    position: Position {
+                        line,
+                        character: name.start(),
+                    },
+                    encoding: PositionEncoding::Utf8,
+                });
+            }
+        }
+    }
+}
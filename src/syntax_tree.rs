@@ -0,0 +1,167 @@
+use ropey::Rope;
+use std::ops::Range;
+use tree_sitter::{InputEdit, Language, Node, Parser, Point, Tree};
+
+/// Bracket tokens that form a pair within the same syntax node
+const BRACKET_PAIRS: &[(&str, &str)] = &[("(", ")"), ("[", "]"), ("{", "}"), ("<", ">")];
+
+fn language(extension: &str) -> Option<Language> {
+    match extension {
+        "rs" => Some(tree_sitter_rust::LANGUAGE.into()),
+        "json" => Some(tree_sitter_json::LANGUAGE.into()),
+        "toml" => Some(tree_sitter_toml_ng::LANGUAGE.into()),
+        _ => None,
+    }
+}
+
+/// Tree-sitter syntax tree of one buffer, kept up to date incrementally.
+///
+/// Edits aren't reported to the tree as they happen; instead the buffer is
+/// compared with the copy the tree was parsed from, and the changed byte range
+/// is applied to the old tree before reparsing.
+pub struct SyntaxTree {
+    parser: Parser,
+    tree: Option<Tree>,
+    /// The buffer as of the last parse (a cheap clone sharing the rope's nodes)
+    source: Rope,
+    revision: u64,
+}
+
+impl SyntaxTree {
+    /// Returns None if there is no grammar for the extension
+    pub fn new(extension: &str) -> Option<Self> {
+        let mut parser = Parser::new();
+        parser.set_language(&language(extension)?).ok()?;
+        Some(Self {
+            parser,
+            tree: None,
+            source: Rope::new(),
+            revision: 0,
+        })
+    }
+
+    pub fn update(&mut self, buffer: &Rope, revision: u64) {
+        if self.tree.is_some() && self.revision == revision {
+            return;
+        }
+        if let Some(tree) = &mut self.tree {
+            tree.edit(&input_edit(&self.source, buffer));
+        }
+        self.tree = self.parser.parse_with(
+            &mut |byte, _| chunk_at_byte(buffer, byte),
+            self.tree.as_ref(),
+        );
+        self.source = buffer.clone();
+        self.revision = revision;
+    }
+
+    /// Bracket pair around a bracket at or just before `offset` (in chars).
+    /// Brackets inside strings and comments aren't matched.
+    pub fn matching_bracket(&self, offset: usize) -> Option<(usize, usize)> {
+        let root = self.tree.as_ref()?.root_node();
+        let len = self.source.len_chars();
+
+        for pos in [Some(offset), offset.checked_sub(1)].into_iter().flatten() {
+            if pos >= len {
+                continue;
+            }
+            let byte = self.source.char_to_byte(pos);
+            let Some(node) = root.descendant_for_byte_range(byte, byte + 1) else {
+                continue;
+            };
+            if node.is_named() || node.start_byte() != byte {
+                continue;
+            }
+            if let Some(other) = matching_sibling(node) {
+                let (a, b) = (
+                    self.source.byte_to_char(node.start_byte()),
+                    self.source.byte_to_char(other.start_byte()),
+                );
+                return Some((a.min(b), a.max(b)));
+            }
+        }
+        None
+    }
+
+    /// Range of the smallest syntax node that is larger than `range` (in chars)
+    pub fn expand_selection(&self, range: Range<usize>) -> Option<Range<usize>> {
+        let root = self.tree.as_ref()?.root_node();
+        let start = self
+            .source
+            .char_to_byte(range.start.min(self.source.len_chars()));
+        let end = self
+            .source
+            .char_to_byte(range.end.min(self.source.len_chars()));
+
+        let mut node = root.descendant_for_byte_range(start, end)?;
+        while node.start_byte() == start && node.end_byte() == end {
+            node = node.parent()?;
+        }
+        let to_char = |byte| self.source.byte_to_char(byte);
+        Some(to_char(node.start_byte())..to_char(node.end_byte()))
+    }
+}
+
+/// The closing token for an opening bracket token, or the other way round
+fn matching_sibling(node: Node) -> Option<Node> {
+    let kind = node.kind();
+    let parent = node.parent()?;
+    let mut walker = parent.walk();
+    let siblings: Vec<Node> = parent.children(&mut walker).collect();
+    let index = siblings.iter().position(|n| n.id() == node.id())?;
+
+    if let Some(&(_, close)) = BRACKET_PAIRS.iter().find(|(open, _)| *open == kind) {
+        siblings[index + 1..]
+            .iter()
+            .find(|n| n.kind() == close)
+            .copied()
+    } else if let Some(&(open, _)) = BRACKET_PAIRS.iter().find(|(_, close)| *close == kind) {
+        siblings[..index]
+            .iter()
+            .rev()
+            .find(|n| n.kind() == open)
+            .copied()
+    } else {
+        None
+    }
+}
+
+fn chunk_at_byte(rope: &Rope, byte: usize) -> &[u8] {
+    if byte >= rope.len_bytes() {
+        return &[];
+    }
+    let (chunk, chunk_start, _, _) = rope.chunk_at_byte(byte);
+    &chunk.as_bytes()[byte - chunk_start..]
+}
+
+fn point_at_byte(rope: &Rope, byte: usize) -> Point {
+    let row = rope.byte_to_line(byte);
+    Point::new(row, byte - rope.line_to_byte(row))
+}
+
+/// The single edit that turns `old` into `new`: everything between their common
+/// prefix and common suffix
+fn input_edit(old: &Rope, new: &Rope) -> InputEdit {
+    let (old_len, new_len) = (old.len_bytes(), new.len_bytes());
+    let prefix = old
+        .bytes()
+        .zip(new.bytes())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = old
+        .bytes_at(old_len)
+        .reversed()
+        .zip(new.bytes_at(new_len).reversed())
+        .take(old_len.min(new_len) - prefix)
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    InputEdit {
+        start_byte: prefix,
+        old_end_byte: old_len - suffix,
+        new_end_byte: new_len - suffix,
+        start_position: point_at_byte(old, prefix),
+        old_end_position: point_at_byte(old, old_len - suffix),
+        new_end_position: point_at_byte(new, new_len - suffix),
+    }
+}
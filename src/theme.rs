@@ -1,71 +1,270 @@
 use egui::{Color32, FontFamily, FontId, Stroke, Style, TextStyle, Visuals};
 
-// VSCode Dark+ color palette
-pub mod colors {
-    use egui::Color32;
-
+/// Editor color palette (VSCode Dark+ / Light+)
+#[derive(Debug, Clone, Copy)]
+pub struct Palette {
     // Background colors
-    pub const WINDOW_BG: Color32 = Color32::from_rgb(30, 30, 30);
-    pub const PANEL_BG: Color32 = Color32::from_rgb(37, 37, 38);
-    pub const EDITOR_BG: Color32 = Color32::from_rgb(30, 30, 30);
-    pub const MENU_BAR_BG: Color32 = Color32::from_rgb(51, 51, 51);
-    pub const STATUS_BAR_BG: Color32 = Color32::from_rgb(0, 122, 204);
+    pub window_bg: Color32,
+    pub panel_bg: Color32,
+    pub editor_bg: Color32,
+    pub menu_bar_bg: Color32,
+    pub title_bar_close_hover: Color32,
+    pub status_bar_bg: Color32,
 
     // Widget backgrounds
-    pub const WIDGET_BG: Color32 = Color32::from_rgb(37, 37, 38);
-    pub const WIDGET_INACTIVE: Color32 = Color32::from_rgb(45, 45, 45);
-    pub const WIDGET_HOVERED: Color32 = Color32::from_rgb(60, 60, 60);
-    pub const ACCENT: Color32 = Color32::from_rgb(0, 122, 204);
+    pub widget_bg: Color32,
+    pub widget_inactive: Color32,
+    pub widget_hovered: Color32,
+    pub accent: Color32,
 
     // Tab colors
-    pub const TAB_ACTIVE_BG: Color32 = Color32::from_rgb(30, 30, 30);
-    pub const TAB_INACTIVE_BG: Color32 = Color32::from_rgb(45, 45, 45);
-    pub const TAB_MODIFIED_DOT: Color32 = Color32::WHITE;
+    pub tab_active_bg: Color32,
+    pub tab_inactive_bg: Color32,
+    pub tab_modified_dot: Color32,
 
     // Text colors
-    pub const TEXT_PRIMARY: Color32 = Color32::WHITE;
-    pub const TEXT_SECONDARY: Color32 = Color32::from_rgb(170, 170, 170);
-    pub const TEXT_MUTED: Color32 = Color32::from_rgb(204, 204, 204);
-    pub const TEXT_FALLBACK: Color32 = Color32::from_rgb(212, 212, 212);
+    pub text_primary: Color32,
+    pub text_secondary: Color32,
+    pub text_muted: Color32,
+    pub text_fallback: Color32,
+    /// Text on the status bar and badges
+    pub status_bar_text: Color32,
 
     // Selection
-    pub const SELECTION_BG: Color32 = Color32::from_rgb(38, 79, 120);
+    pub selection_bg: Color32,
 
     // Editor gutter colors
-    pub const LINE_NUMBER: Color32 = Color32::from_rgb(133, 133, 133);
-    pub const LINE_NUMBER_ACTIVE: Color32 = Color32::from_rgb(199, 199, 199);
-    pub const CURRENT_LINE_BG: Color32 = Color32::from_rgb(40, 40, 40);
-    pub const GUTTER_BG: Color32 = Color32::from_rgb(30, 30, 30);
-    pub const GUTTER_BORDER: Color32 = Color32::from_rgb(50, 50, 50);
+    pub line_number: Color32,
+    pub line_number_active: Color32,
+    pub current_line_bg: Color32,
+    pub gutter_bg: Color32,
+    pub gutter_border: Color32,
 
     // Indent guide colors (VSCode style)
-    pub const INDENT_GUIDE: Color32 = Color32::from_rgb(64, 64, 64);
-    pub const INDENT_GUIDE_ACTIVE: Color32 = Color32::from_rgb(115, 115, 115);
+    pub indent_guide: Color32,
+    pub indent_guide_active: Color32,
 
     // Bracket matching colors (VSCode style)
-    pub const BRACKET_MATCH_BG: Color32 = Color32::from_rgba_premultiplied(0, 100, 150, 60);
-    pub const BRACKET_MATCH_BORDER: Color32 = Color32::from_rgb(100, 150, 180);
+    pub bracket_match_bg: Color32,
+    pub bracket_match_border: Color32,
 
     // Find/Replace colors (VSCode style)
-    pub const FIND_MATCH_BG: Color32 = Color32::from_rgba_premultiplied(234, 92, 0, 70);
-    pub const FIND_MATCH_CURRENT_BG: Color32 = Color32::from_rgba_premultiplied(81, 92, 106, 150);
-    pub const FIND_MATCH_BORDER: Color32 = Color32::from_rgb(234, 128, 64);
-    pub const FIND_PANEL_BG: Color32 = Color32::from_rgb(37, 37, 38);
+    pub find_match_bg: Color32,
+    pub find_match_current_bg: Color32,
+    pub find_match_border: Color32,
+    pub find_panel_bg: Color32,
+
+    // Occurrences of the identifier at the caret (VSCode word highlight)
+    pub occurrence_read_bg: Color32,
+    pub occurrence_write_bg: Color32,
+
+    // Diagnostics (squiggles and gutter markers)
+    pub diagnostic_error: Color32,
+    pub diagnostic_warning: Color32,
+    pub diagnostic_info: Color32,
+
+    // Diff colors (compared folders and files)
+    pub diff_added: Color32,
+    pub diff_removed: Color32,
+    pub diff_changed: Color32,
+    pub diff_added_bg: Color32,
+    pub diff_removed_bg: Color32,
 
     // Activity bar colors
-    pub const ACTIVITY_BAR_BG: Color32 = Color32::from_rgb(51, 51, 51);
-    pub const ACTIVITY_BAR_ACTIVE: Color32 = Color32::WHITE;
-    pub const ACTIVITY_BAR_INACTIVE: Color32 = Color32::from_rgb(133, 133, 133);
-    pub const ACTIVITY_BAR_BADGE_BG: Color32 = Color32::from_rgb(0, 122, 204);
+    pub activity_bar_bg: Color32,
+    pub activity_bar_active: Color32,
+    pub activity_bar_inactive: Color32,
+    pub activity_bar_badge_bg: Color32,
 
     // Minimap colors
-    pub const MINIMAP_BG: Color32 = Color32::from_rgb(30, 30, 30);
-    pub const MINIMAP_VIEWPORT: Color32 = Color32::from_rgb(60, 60, 60);
-    pub const MINIMAP_CODE: Color32 = Color32::from_rgb(150, 150, 150);
+    pub minimap_bg: Color32,
+    pub minimap_viewport: Color32,
+    pub minimap_code: Color32,
+    /// Find match ticks on the minimap
+    pub minimap_find_match: Color32,
 
     // File tree colors
-    pub const FILE_TREE_HOVER: Color32 = Color32::from_rgb(45, 45, 45);
-    pub const FILE_TREE_SELECTED: Color32 = Color32::from_rgb(55, 55, 55);
+    pub file_tree_hover: Color32,
+    pub file_tree_selected: Color32,
+}
+
+impl Palette {
+    pub const DARK: Palette = Palette {
+        // Background colors
+        window_bg: Color32::from_rgb(30, 30, 30),
+        panel_bg: Color32::from_rgb(37, 37, 38),
+        editor_bg: Color32::from_rgb(30, 30, 30),
+        menu_bar_bg: Color32::from_rgb(51, 51, 51),
+        title_bar_close_hover: Color32::from_rgb(232, 17, 35),
+        status_bar_bg: Color32::from_rgb(0, 122, 204),
+
+        // Widget backgrounds
+        widget_bg: Color32::from_rgb(37, 37, 38),
+        widget_inactive: Color32::from_rgb(45, 45, 45),
+        widget_hovered: Color32::from_rgb(60, 60, 60),
+        accent: Color32::from_rgb(0, 122, 204),
+
+        // Tab colors
+        tab_active_bg: Color32::from_rgb(30, 30, 30),
+        tab_inactive_bg: Color32::from_rgb(45, 45, 45),
+        tab_modified_dot: Color32::WHITE,
+
+        // Text colors
+        text_primary: Color32::WHITE,
+        text_secondary: Color32::from_rgb(170, 170, 170),
+        text_muted: Color32::from_rgb(204, 204, 204),
+        text_fallback: Color32::from_rgb(212, 212, 212),
+        status_bar_text: Color32::WHITE,
+
+        // Selection
+        selection_bg: Color32::from_rgb(38, 79, 120),
+
+        // Editor gutter colors
+        line_number: Color32::from_rgb(133, 133, 133),
+        line_number_active: Color32::from_rgb(199, 199, 199),
+        current_line_bg: Color32::from_rgb(40, 40, 40),
+        gutter_bg: Color32::from_rgb(30, 30, 30),
+        gutter_border: Color32::from_rgb(50, 50, 50),
+
+        // Indent guide colors (VSCode style)
+        indent_guide: Color32::from_rgb(64, 64, 64),
+        indent_guide_active: Color32::from_rgb(115, 115, 115),
+
+        // Bracket matching colors (VSCode style)
+        bracket_match_bg: Color32::from_rgba_premultiplied(0, 100, 150, 60),
+        bracket_match_border: Color32::from_rgb(100, 150, 180),
+
+        // Find/Replace colors (VSCode style)
+        find_match_bg: Color32::from_rgba_premultiplied(234, 92, 0, 70),
+        find_match_current_bg: Color32::from_rgba_premultiplied(81, 92, 106, 150),
+        find_match_border: Color32::from_rgb(234, 128, 64),
+        find_panel_bg: Color32::from_rgb(37, 37, 38),
+
+        // Occurrences of the identifier at the caret (VSCode word highlight)
+        occurrence_read_bg: Color32::from_rgba_premultiplied(63, 63, 63, 184),
+        occurrence_write_bg: Color32::from_rgba_premultiplied(0, 53, 82, 184),
+
+        // Diagnostics (squiggles and gutter markers)
+        diagnostic_error: Color32::from_rgb(241, 76, 76),
+        diagnostic_warning: Color32::from_rgb(204, 167, 0),
+        diagnostic_info: Color32::from_rgb(55, 148, 255),
+
+        // Diff colors (compared folders and files)
+        diff_added: Color32::from_rgb(115, 201, 145),
+        diff_removed: Color32::from_rgb(241, 76, 76),
+        diff_changed: Color32::from_rgb(226, 192, 141),
+        diff_added_bg: Color32::from_rgba_premultiplied(20, 70, 30, 90),
+        diff_removed_bg: Color32::from_rgba_premultiplied(90, 20, 20, 90),
+
+        // Activity bar colors
+        activity_bar_bg: Color32::from_rgb(51, 51, 51),
+        activity_bar_active: Color32::WHITE,
+        activity_bar_inactive: Color32::from_rgb(133, 133, 133),
+        activity_bar_badge_bg: Color32::from_rgb(0, 122, 204),
+
+        // Minimap colors
+        minimap_bg: Color32::from_rgb(30, 30, 30),
+        minimap_viewport: Color32::from_rgb(60, 60, 60),
+        minimap_code: Color32::from_rgb(150, 150, 150),
+        minimap_find_match: Color32::from_rgb(209, 134, 22),
+
+        // File tree colors
+        file_tree_hover: Color32::from_rgb(45, 45, 45),
+        file_tree_selected: Color32::from_rgb(55, 55, 55),
+    };
+
+    pub const LIGHT: Palette = Palette {
+        // Background colors
+        window_bg: Color32::from_rgb(243, 243, 243),
+        panel_bg: Color32::from_rgb(243, 243, 243),
+        editor_bg: Color32::WHITE,
+        menu_bar_bg: Color32::from_rgb(221, 221, 221),
+        title_bar_close_hover: Color32::from_rgb(232, 17, 35),
+        status_bar_bg: Color32::from_rgb(0, 122, 204),
+
+        // Widget backgrounds
+        widget_bg: Color32::from_rgb(243, 243, 243),
+        widget_inactive: Color32::from_rgb(232, 232, 232),
+        widget_hovered: Color32::from_rgb(218, 218, 218),
+        accent: Color32::from_rgb(0, 122, 204),
+
+        // Tab colors
+        tab_active_bg: Color32::WHITE,
+        tab_inactive_bg: Color32::from_rgb(236, 236, 236),
+        tab_modified_dot: Color32::from_rgb(51, 51, 51),
+
+        // Text colors
+        text_primary: Color32::from_rgb(30, 30, 30),
+        text_secondary: Color32::from_rgb(110, 110, 110),
+        text_muted: Color32::from_rgb(68, 68, 68),
+        text_fallback: Color32::from_rgb(0, 0, 0),
+        status_bar_text: Color32::WHITE,
+
+        // Selection
+        selection_bg: Color32::from_rgb(173, 214, 255),
+
+        // Editor gutter colors
+        line_number: Color32::from_rgb(35, 120, 147),
+        line_number_active: Color32::from_rgb(11, 33, 111),
+        current_line_bg: Color32::from_rgb(238, 238, 238),
+        gutter_bg: Color32::WHITE,
+        gutter_border: Color32::from_rgb(229, 229, 229),
+
+        // Indent guide colors (VSCode style)
+        indent_guide: Color32::from_rgb(211, 211, 211),
+        indent_guide_active: Color32::from_rgb(147, 147, 147),
+
+        // Bracket matching colors (VSCode style)
+        bracket_match_bg: Color32::from_rgba_premultiplied(0, 100, 0, 26),
+        bracket_match_border: Color32::from_rgb(185, 185, 185),
+
+        // Find/Replace colors (VSCode style)
+        find_match_bg: Color32::from_rgba_premultiplied(234, 92, 0, 70),
+        find_match_current_bg: Color32::from_rgba_premultiplied(168, 172, 148, 120),
+        find_match_border: Color32::from_rgb(234, 128, 64),
+        find_panel_bg: Color32::from_rgb(243, 243, 243),
+
+        // Occurrences of the identifier at the caret (VSCode word highlight)
+        occurrence_read_bg: Color32::from_rgba_premultiplied(22, 22, 22, 64),
+        occurrence_write_bg: Color32::from_rgba_premultiplied(3, 25, 39, 64),
+
+        // Diagnostics (squiggles and gutter markers)
+        diagnostic_error: Color32::from_rgb(229, 20, 0),
+        diagnostic_warning: Color32::from_rgb(191, 136, 3),
+        diagnostic_info: Color32::from_rgb(26, 133, 255),
+
+        // Diff colors (compared folders and files)
+        diff_added: Color32::from_rgb(88, 115, 43),
+        diff_removed: Color32::from_rgb(173, 11, 11),
+        diff_changed: Color32::from_rgb(137, 92, 0),
+        diff_added_bg: Color32::from_rgba_premultiplied(155, 185, 85, 51),
+        diff_removed_bg: Color32::from_rgba_premultiplied(255, 0, 0, 41),
+
+        // Activity bar colors
+        activity_bar_bg: Color32::from_rgb(44, 44, 44),
+        activity_bar_active: Color32::WHITE,
+        activity_bar_inactive: Color32::from_rgb(133, 133, 133),
+        activity_bar_badge_bg: Color32::from_rgb(0, 122, 204),
+
+        // Minimap colors
+        minimap_bg: Color32::WHITE,
+        minimap_viewport: Color32::from_rgb(225, 225, 225),
+        minimap_code: Color32::from_rgb(130, 130, 130),
+        minimap_find_match: Color32::from_rgb(209, 134, 22),
+
+        // File tree colors
+        file_tree_hover: Color32::from_rgb(232, 232, 232),
+        file_tree_selected: Color32::from_rgb(228, 230, 241),
+    };
+}
+
+/// Palette matching the current egui visuals
+pub fn palette(visuals: &Visuals) -> &'static Palette {
+    if visuals.dark_mode {
+        &Palette::DARK
+    } else {
+        &Palette::LIGHT
+    }
 }
 
 // Font sizes
@@ -82,14 +281,23 @@ pub mod fonts {
 
 // Layout constants
 pub mod layout {
+    // Title bar (custom, frameless window)
+    pub const TITLE_BAR_HEIGHT: f32 = 32.0;
+    pub const TITLE_BAR_BUTTON_WIDTH: f32 = 46.0;
+
     // Sidebar
     pub const SIDEBAR_DEFAULT_WIDTH: f32 = 250.0;
     pub const SIDEBAR_MIN_WIDTH: f32 = 150.0;
+    pub const OUTLINE_DEFAULT_HEIGHT: f32 = 220.0;
+    pub const OUTPUT_DEFAULT_HEIGHT: f32 = 160.0;
+    pub const REFERENCES_DEFAULT_HEIGHT: f32 = 200.0;
     pub const INDENT_SIZE: f32 = 16.0;
 
     // Gutter
     pub const GUTTER_PADDING_LEFT: f32 = 8.0;
     pub const GUTTER_PADDING_RIGHT: f32 = 12.0;
+    pub const GUTTER_DECORATION_WIDTH: f32 = 14.0;
+    pub const GUTTER_BAR_WIDTH: f32 = 4.0;
 
     // Activity bar
     pub const ACTIVITY_BAR_WIDTH: f32 = 50.0;
@@ -104,6 +312,9 @@ pub mod layout {
     pub const TAB_PADDING_H: f32 = 12.0;
     pub const TAB_PADDING_V: f32 = 8.0;
     pub const TAB_MODIFIED_DOT_SIZE: f32 = 8.0;
+    /// How far below the tab bar a dragged tab has to be dropped to move it
+    /// to an editor group
+    pub const TAB_DROP_DISTANCE: f32 = 30.0;
 
     // Status bar
     pub const STATUS_BAR_HEIGHT: f32 = 22.0;
@@ -112,27 +323,37 @@ pub mod layout {
     // Editor
     pub const LINE_HEIGHT: f32 = 18.0;
     pub const TAB_SIZE: usize = 4; // Number of spaces per indent level
+    pub const EDITOR_PADDING_LEFT: f32 = 4.0;
 }
 
-pub fn create_vscode_style() -> Style {
+pub fn create_vscode_style(dark_mode: bool) -> Style {
+    let (visuals, colors) = if dark_mode {
+        (Visuals::dark(), &Palette::DARK)
+    } else {
+        (Visuals::light(), &Palette::LIGHT)
+    };
     let mut style = Style {
-        visuals: Visuals::dark(),
+        visuals,
         ..Default::default()
     };
 
-    style.visuals.window_fill = colors::WINDOW_BG;
-    style.visuals.panel_fill = colors::PANEL_BG;
-    style.visuals.faint_bg_color = colors::WIDGET_INACTIVE;
-    style.visuals.extreme_bg_color = Color32::from_rgb(25, 25, 25);
-    style.visuals.code_bg_color = colors::WINDOW_BG;
+    style.visuals.window_fill = colors.window_bg;
+    style.visuals.panel_fill = colors.panel_bg;
+    style.visuals.faint_bg_color = colors.widget_inactive;
+    style.visuals.extreme_bg_color = if dark_mode {
+        Color32::from_rgb(25, 25, 25)
+    } else {
+        Color32::WHITE
+    };
+    style.visuals.code_bg_color = colors.window_bg;
 
-    style.visuals.widgets.noninteractive.bg_fill = colors::WIDGET_BG;
-    style.visuals.widgets.inactive.bg_fill = colors::WIDGET_INACTIVE;
-    style.visuals.widgets.hovered.bg_fill = colors::WIDGET_HOVERED;
-    style.visuals.widgets.active.bg_fill = colors::ACCENT;
+    style.visuals.widgets.noninteractive.bg_fill = colors.widget_bg;
+    style.visuals.widgets.inactive.bg_fill = colors.widget_inactive;
+    style.visuals.widgets.hovered.bg_fill = colors.widget_hovered;
+    style.visuals.widgets.active.bg_fill = colors.accent;
 
-    style.visuals.selection.bg_fill = colors::SELECTION_BG;
-    style.visuals.selection.stroke = Stroke::new(1.0, colors::ACCENT);
+    style.visuals.selection.bg_fill = colors.selection_bg;
+    style.visuals.selection.stroke = Stroke::new(1.0, colors.accent);
 
     // Remove widget rounding and strokes for flat VSCode look
     style.visuals.widgets.noninteractive.bg_stroke = Stroke::NONE;
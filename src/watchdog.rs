@@ -0,0 +1,183 @@
+use ropey::Rope;
+use std::borrow::Cow;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// State shared between the UI thread and the watchdog thread
+struct Shared {
+    /// When the current or last frame started
+    frame_started: Mutex<Instant>,
+    /// Set from the start of a frame to its end; the UI thread is only
+    /// watched then, so an idle window doesn't need frames to stay quiet
+    in_frame: AtomicBool,
+    frames: AtomicU64,
+    /// What the UI thread was last doing, reported when it hangs
+    activity: Mutex<Cow<'static, str>>,
+    /// Set while the UI thread is expected to block (native file dialogs)
+    suspended: AtomicBool,
+    /// Unsaved buffers to write out if the UI thread hangs
+    recovery: Mutex<Vec<(PathBuf, Rope)>>,
+    /// The app is shutting down and will draw no more frames
+    stopped: AtomicBool,
+}
+
+/// Detects when the UI thread takes too long over a frame (e.g. hung on
+/// synchronous IO), logs what it was doing, and optionally writes unsaved
+/// buffers to a recovery directory. Time between frames, when egui waits for
+/// input, doesn't count.
+///
+/// Rust can't capture another thread's stack, so the report is built from the
+/// activity breadcrumbs the UI thread leaves via `set_activity`.
+pub struct Watchdog {
+    shared: Arc<Shared>,
+}
+
+impl Watchdog {
+    pub fn spawn(timeout: Duration, save_recovery: bool) -> Self {
+        let shared = Arc::new(Shared {
+            frame_started: Mutex::new(Instant::now()),
+            in_frame: AtomicBool::new(true),
+            frames: AtomicU64::new(0),
+            activity: Mutex::new(Cow::Borrowed("starting up")),
+            suspended: AtomicBool::new(false),
+            recovery: Mutex::new(Vec::new()),
+            stopped: AtomicBool::new(false),
+        });
+
+        let thread_shared = shared.clone();
+        std::thread::Builder::new()
+            .name("watchdog".to_string())
+            .spawn(move || run_watchdog(&thread_shared, timeout, save_recovery))
+            .expect("failed to spawn the watchdog thread");
+
+        Self { shared }
+    }
+
+    /// Called at the start of every frame
+    pub fn frame_started(&self) {
+        *self.shared.frame_started.lock().unwrap() = Instant::now();
+        self.shared.frames.fetch_add(1, Ordering::Relaxed);
+        self.set_activity("rendering a frame");
+        self.shared.in_frame.store(true, Ordering::Relaxed);
+    }
+
+    /// Called at the end of every frame
+    pub fn frame_finished(&self) {
+        self.shared.in_frame.store(false, Ordering::Relaxed);
+    }
+
+    pub fn set_activity(&self, activity: impl Into<Cow<'static, str>>) {
+        *self.shared.activity.lock().unwrap() = activity.into();
+    }
+
+    pub fn set_recovery_data(&self, files: Vec<(PathBuf, Rope)>) {
+        *self.shared.recovery.lock().unwrap() = files;
+    }
+
+    /// Run something that is allowed to block the UI thread, like a native dialog
+    pub fn suspended<R>(&self, f: impl FnOnce() -> R) -> R {
+        self.shared.suspended.store(true, Ordering::Relaxed);
+        let result = f();
+        *self.shared.frame_started.lock().unwrap() = Instant::now();
+        self.shared.suspended.store(false, Ordering::Relaxed);
+        result
+    }
+
+    /// End the watchdog thread, so the last frame before exit isn't taken for
+    /// a hang while the app shuts down
+    pub fn stop(&self) {
+        self.shared.stopped.store(true, Ordering::Relaxed);
+    }
+}
+
+fn run_watchdog(shared: &Shared, timeout: Duration, save_recovery: bool) {
+    let mut reported_at_frame: Option<u64> = None;
+
+    loop {
+        std::thread::sleep(timeout / 4);
+        if shared.stopped.load(Ordering::Relaxed) {
+            return;
+        }
+
+        let stalled_for = shared.frame_started.lock().unwrap().elapsed();
+        let frames = shared.frames.load(Ordering::Relaxed);
+        let in_frame = shared.in_frame.load(Ordering::Relaxed);
+
+        if let Some(frame) = reported_at_frame {
+            if frames != frame || !in_frame {
+                log(&format!("UI thread recovered after frame {frame}"));
+                reported_at_frame = None;
+            }
+            continue;
+        }
+
+        if !in_frame || stalled_for < timeout || shared.suspended.load(Ordering::Relaxed) {
+            continue;
+        }
+
+        let activity = shared.activity.lock().unwrap().clone();
+        log(&format!(
+            "UI thread has been in frame {frames} for {:.1}s; last activity: {activity}",
+            stalled_for.as_secs_f32()
+        ));
+        if save_recovery {
+            save_recovery_files(&shared.recovery.lock().unwrap());
+        }
+        reported_at_frame = Some(frames);
+    }
+}
+
+fn log(message: &str) {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let line = format!("[{timestamp}] watchdog: {message}");
+    eprintln!("{line}");
+
+    let Some(dir) = crate::settings::config_dir() else {
+        return;
+    };
+    let _ = std::fs::create_dir_all(&dir);
+    if let Ok(mut file) = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join("watchdog.log"))
+    {
+        let _ = writeln!(file, "{line}");
+    }
+}
+
+/// Write unsaved buffers to `<config dir>/recovery`
+fn save_recovery_files(files: &[(PathBuf, Rope)]) {
+    let Some(dir) = crate::settings::config_dir().map(|dir| dir.join("recovery")) else {
+        return;
+    };
+    if files.is_empty() || std::fs::create_dir_all(&dir).is_err() {
+        return;
+    }
+
+    for (index, (path, buffer)) in files.iter().enumerate() {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "untitled".to_string());
+        let target = dir.join(format!("{index}-{name}"));
+        let saved = std::fs::File::create(&target)
+            .and_then(|file| buffer.write_to(std::io::BufWriter::new(file)));
+        match saved {
+            Ok(()) => log(&format!(
+                "saved recovery copy of {} to {}",
+                path.display(),
+                target.display()
+            )),
+            Err(e) => log(&format!(
+                "failed to save recovery copy of {}: {e}",
+                path.display()
+            )),
+        }
+    }
+}
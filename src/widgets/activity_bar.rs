@@ -1,4 +1,4 @@
-use crate::theme::{colors, fonts, layout};
+use crate::theme::{fonts, layout, palette};
 use egui::{FontId, Pos2, Rect, Sense, Ui, Vec2};
 
 /// Activity items for the activity bar
@@ -7,6 +7,7 @@ pub enum ActivityItem {
     Explorer,
     Search,
     Git,
+    Cargo,
     Extensions,
 }
 
@@ -16,6 +17,7 @@ impl ActivityItem {
             ActivityItem::Explorer => "E",
             ActivityItem::Search => "S",
             ActivityItem::Git => "G",
+            ActivityItem::Cargo => "C",
             ActivityItem::Extensions => "X",
         }
     }
@@ -25,6 +27,7 @@ impl ActivityItem {
             ActivityItem::Explorer => "Explorer (Ctrl+Shift+E)",
             ActivityItem::Search => "Search (Ctrl+Shift+F)",
             ActivityItem::Git => "Source Control (Ctrl+Shift+G)",
+            ActivityItem::Cargo => "Cargo",
             ActivityItem::Extensions => "Extensions (Ctrl+Shift+X)",
         }
     }
@@ -50,6 +53,7 @@ impl ActivityBar {
     }
 
     pub fn show(self, ui: &mut Ui) -> ActivityBarResponse {
+        let colors = palette(ui.visuals());
         let available_height = ui.available_height();
         let desired_size = Vec2::new(layout::ACTIVITY_BAR_WIDTH, available_height);
         let (rect, _response) = ui.allocate_exact_size(desired_size, Sense::hover());
@@ -60,12 +64,13 @@ impl ActivityBar {
             let painter = ui.painter_at(rect);
 
             // Draw background
-            painter.rect_filled(rect, 0.0, colors::ACTIVITY_BAR_BG);
+            painter.rect_filled(rect, 0.0, colors.activity_bar_bg);
 
             let items = [
                 ActivityItem::Explorer,
                 ActivityItem::Search,
                 ActivityItem::Git,
+                ActivityItem::Cargo,
                 ActivityItem::Extensions,
             ];
 
@@ -87,7 +92,7 @@ impl ActivityBar {
 
                 // Draw hover background
                 if is_hovered && !is_active {
-                    painter.rect_filled(item_rect, 0.0, colors::WIDGET_HOVERED);
+                    painter.rect_filled(item_rect, 0.0, colors.widget_hovered);
                 }
 
                 // Draw active indicator (left border)
@@ -96,14 +101,14 @@ impl ActivityBar {
                         Pos2::new(rect.left(), item_y),
                         Vec2::new(3.0, layout::ACTIVITY_ITEM_HEIGHT),
                     );
-                    painter.rect_filled(indicator_rect, 0.0, colors::ACCENT);
+                    painter.rect_filled(indicator_rect, 0.0, colors.accent);
                 }
 
                 // Determine icon color
                 let icon_color = if is_active {
-                    colors::ACTIVITY_BAR_ACTIVE
+                    colors.activity_bar_active
                 } else {
-                    colors::ACTIVITY_BAR_INACTIVE
+                    colors.activity_bar_inactive
                 };
 
                 // Draw icon
@@ -118,13 +123,13 @@ impl ActivityBar {
                 // Draw git badge
                 if *item == ActivityItem::Git && self.git_changes > 0 {
                     let badge_center = Pos2::new(item_center.x + 10.0, item_center.y - 10.0);
-                    painter.circle_filled(badge_center, 8.0, colors::ACTIVITY_BAR_BADGE_BG);
+                    painter.circle_filled(badge_center, 8.0, colors.activity_bar_badge_bg);
                     painter.text(
                         badge_center,
                         egui::Align2::CENTER_CENTER,
                         self.git_changes.to_string(),
                         FontId::proportional(9.0),
-                        colors::TEXT_PRIMARY,
+                        colors.status_bar_text,
                     );
                 }
 
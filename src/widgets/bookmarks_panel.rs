@@ -0,0 +1,92 @@
+use crate::theme::{fonts, palette};
+use egui::{FontId, RichText, ScrollArea, Ui};
+use std::path::PathBuf;
+
+/// A bookmarked line of an open file
+#[derive(Clone)]
+pub struct Bookmark {
+    pub path: PathBuf,
+    /// Zero-based line
+    pub line: usize,
+    /// Text of the line, trimmed
+    pub preview: String,
+}
+
+/// Bookmarks of all open files, in tab order
+pub struct BookmarksPanel<'a> {
+    bookmarks: &'a [Bookmark],
+}
+
+/// Response from BookmarksPanel widget
+#[derive(Default)]
+pub struct BookmarksPanelResponse {
+    /// Index of the bookmark that was clicked
+    pub clicked: Option<usize>,
+    /// Index of the bookmark whose ✕ was clicked
+    pub removed: Option<usize>,
+    pub close_clicked: bool,
+}
+
+impl<'a> BookmarksPanel<'a> {
+    pub fn new(bookmarks: &'a [Bookmark]) -> Self {
+        Self { bookmarks }
+    }
+
+    pub fn show(self, ui: &mut Ui) -> BookmarksPanelResponse {
+        let colors = palette(ui.visuals());
+        let mut response = BookmarksPanelResponse::default();
+
+        ui.horizontal(|ui| {
+            ui.label(
+                RichText::new("BOOKMARKS")
+                    .size(fonts::EXPLORER_HEADER)
+                    .color(colors.text_secondary)
+                    .strong(),
+            );
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                response.close_clicked = ui.small_button("✕").on_hover_text("Close").clicked();
+            });
+        });
+
+        ScrollArea::vertical()
+            .id_source("bookmarks_panel")
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                if self.bookmarks.is_empty() {
+                    ui.label(
+                        RichText::new("No bookmarks. Ctrl+F2 bookmarks the cursor's line.")
+                            .color(colors.text_muted),
+                    );
+                }
+                for (i, bookmark) in self.bookmarks.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        if ui
+                            .small_button("✕")
+                            .on_hover_text("Remove Bookmark")
+                            .clicked()
+                        {
+                            response.removed = Some(i);
+                        }
+                        let name = bookmark.path.file_name().unwrap_or_default();
+                        let text = RichText::new(format!(
+                            "{}:{}  {}",
+                            name.to_string_lossy(),
+                            bookmark.line + 1,
+                            bookmark.preview
+                        ))
+                        .font(FontId::monospace(fonts::BODY))
+                        .color(colors.text_primary);
+                        if ui
+                            .selectable_label(false, text)
+                            .on_hover_text(bookmark.path.display().to_string())
+                            .clicked()
+                        {
+                            response.clicked = Some(i);
+                        }
+                    });
+                }
+            });
+
+        response
+    }
+}
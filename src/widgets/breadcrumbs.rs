@@ -0,0 +1,161 @@
+use crate::file_icons;
+use crate::fs_tree::FileNode;
+use crate::state::outline::OutlineSymbol;
+use crate::theme::{fonts, palette};
+use egui::{Frame, Margin, RichText, ScrollArea, Ui};
+use std::path::{Path, PathBuf};
+
+/// Longest a sibling dropdown gets before it scrolls
+const DROPDOWN_MAX_HEIGHT: f32 = 320.0;
+
+/// Path segment of the active file
+pub struct PathSegment<'a> {
+    pub name: String,
+    pub path: PathBuf,
+    /// Entries of the folder containing the segment, when it is in the workspace tree
+    pub siblings: Option<&'a [FileNode]>,
+}
+
+/// Strip above the editor with the path of the active file and the symbols the
+/// cursor is in; each segment opens a dropdown of its siblings
+pub struct Breadcrumbs<'a> {
+    path: Vec<PathSegment<'a>>,
+    symbols: &'a [OutlineSymbol],
+    /// Indices into `symbols`, outermost first
+    symbol_chain: Vec<usize>,
+}
+
+/// Response from Breadcrumbs widget
+#[derive(Default)]
+pub struct BreadcrumbsResponse {
+    pub open_file: Option<PathBuf>,
+    /// Index of the symbol picked from a dropdown
+    pub goto_symbol: Option<usize>,
+}
+
+impl<'a> Breadcrumbs<'a> {
+    pub fn new(path: Vec<PathSegment<'a>>) -> Self {
+        Self {
+            path,
+            symbols: &[],
+            symbol_chain: Vec::new(),
+        }
+    }
+
+    /// Outline of the file and the chain of symbols around the cursor
+    pub fn symbols(mut self, symbols: &'a [OutlineSymbol], chain: Vec<usize>) -> Self {
+        self.symbols = symbols;
+        self.symbol_chain = chain;
+        self
+    }
+
+    pub fn show(self, ui: &mut Ui) -> BreadcrumbsResponse {
+        let colors = palette(ui.visuals());
+        let mut response = BreadcrumbsResponse::default();
+
+        Frame::none()
+            .fill(colors.editor_bg)
+            .inner_margin(Margin::symmetric(8.0, 2.0))
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.spacing_mut().item_spacing.x = 2.0;
+                    ui.spacing_mut().button_padding.y = 0.0;
+                    let mut first = true;
+                    let mut separator = |ui: &mut Ui| {
+                        if !std::mem::take(&mut first) {
+                            ui.label(
+                                RichText::new("›")
+                                    .size(fonts::STATUS_BAR)
+                                    .color(colors.text_muted),
+                            );
+                        }
+                    };
+
+                    for segment in &self.path {
+                        separator(ui);
+                        let label = RichText::new(&segment.name)
+                            .size(fonts::STATUS_BAR)
+                            .color(colors.text_secondary);
+                        let Some(siblings) = segment.siblings else {
+                            ui.label(label);
+                            continue;
+                        };
+                        ui.menu_button(label, |ui| {
+                            ScrollArea::vertical()
+                                .max_height(DROPDOWN_MAX_HEIGHT)
+                                .show(ui, |ui| {
+                                    if let Some(path) = file_menu(ui, siblings, &segment.path) {
+                                        response.open_file = Some(path);
+                                    }
+                                });
+                        });
+                    }
+
+                    for (i, &index) in self.symbol_chain.iter().enumerate() {
+                        separator(ui);
+                        let symbol = &self.symbols[index];
+                        let label =
+                            RichText::new(format!("{} {}", symbol.kind.icon(), symbol.name))
+                                .size(fonts::STATUS_BAR)
+                                .color(colors.text_secondary);
+                        let parent = i.checked_sub(1).map(|p| self.symbol_chain[p]);
+                        ui.menu_button(label, |ui| {
+                            ScrollArea::vertical()
+                                .max_height(DROPDOWN_MAX_HEIGHT)
+                                .show(ui, |ui| {
+                                    for sibling in symbol_siblings(self.symbols, parent, index) {
+                                        let symbol = &self.symbols[sibling];
+                                        let text =
+                                            format!("{} {}", symbol.kind.icon(), symbol.name);
+                                        if ui.selectable_label(sibling == index, text).clicked() {
+                                            response.goto_symbol = Some(sibling);
+                                            ui.close_menu();
+                                        }
+                                    }
+                                });
+                        });
+                    }
+                });
+            });
+
+        response
+    }
+}
+
+/// Folder entries as a menu, with folders as submenus. Returns the file picked.
+fn file_menu(ui: &mut Ui, nodes: &[FileNode], current: &Path) -> Option<PathBuf> {
+    let mut picked = None;
+    for node in nodes {
+        let text = format!("{} {}", file_icons::get_icon(&node.name()), node.name());
+        if node.is_dir {
+            ui.menu_button(text, |ui| {
+                if let Some(path) = file_menu(ui, &node.children, current) {
+                    picked = Some(path);
+                }
+            });
+        } else if ui
+            .selectable_label(current.starts_with(&node.path), text)
+            .clicked()
+        {
+            picked = Some(node.path.clone());
+            ui.close_menu();
+        }
+    }
+    picked
+}
+
+/// Symbols at the same level as `index` within `parent` (or the whole file)
+fn symbol_siblings(symbols: &[OutlineSymbol], parent: Option<usize>, index: usize) -> Vec<usize> {
+    let depth = symbols[index].depth;
+    let (start, parent_depth) = match parent {
+        Some(parent) => (parent + 1, Some(symbols[parent].depth)),
+        None => (0, None),
+    };
+    symbols[start..]
+        .iter()
+        .enumerate()
+        .take_while(|(_, s)| parent_depth.map_or(true, |d| s.depth > d))
+        .filter(|(_, s)| s.depth == depth)
+        .map(|(i, _)| start + i)
+        .collect()
+}
@@ -0,0 +1,267 @@
+use crate::cargo::{Action, Invocation, Metadata, Package, Target, TargetKind};
+use crate::theme::{fonts, layout, palette};
+use egui::{CollapsingHeader, Frame, Margin, RichText, ScrollArea, Ui};
+use std::collections::{BTreeSet, HashMap};
+use std::path::PathBuf;
+
+/// Sidebar view of the Cargo workspace: members with their targets, features
+/// and dependencies, and buttons to build, run, test or clean them
+pub struct CargoPanel<'a> {
+    /// None while `cargo metadata` runs
+    metadata: Option<&'a Result<Metadata, String>>,
+    /// Features ticked per package
+    features: &'a mut HashMap<String, BTreeSet<String>>,
+    /// Command line of the command that is running
+    running: Option<&'a str>,
+}
+
+/// Response from CargoPanel widget
+#[derive(Default)]
+pub struct CargoPanelResponse {
+    pub run: Option<Invocation>,
+    pub refresh_clicked: bool,
+    pub stop_clicked: bool,
+    pub graph_clicked: bool,
+    /// Manifest to open
+    pub open_file: Option<PathBuf>,
+}
+
+impl<'a> CargoPanel<'a> {
+    pub fn new(
+        metadata: Option<&'a Result<Metadata, String>>,
+        features: &'a mut HashMap<String, BTreeSet<String>>,
+    ) -> Self {
+        Self {
+            metadata,
+            features,
+            running: None,
+        }
+    }
+
+    pub fn running(mut self, running: Option<&'a str>) -> Self {
+        self.running = running;
+        self
+    }
+
+    pub fn show(self, ui: &mut Ui) -> CargoPanelResponse {
+        let colors = palette(ui.visuals());
+        let mut response = CargoPanelResponse::default();
+
+        Frame::none()
+            .inner_margin(Margin::symmetric(12.0, 8.0))
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(
+                        RichText::new("CARGO")
+                            .size(fonts::EXPLORER_HEADER)
+                            .color(colors.text_muted)
+                            .strong(),
+                    );
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        response.graph_clicked = ui
+                            .add_enabled(self.metadata.is_some(), egui::Button::new("🕸").small())
+                            .on_hover_text("Dependency Graph")
+                            .clicked();
+                        response.refresh_clicked = ui
+                            .add_enabled(self.metadata.is_some(), egui::Button::new("⟳").small())
+                            .on_hover_text("Reload cargo metadata")
+                            .clicked();
+                    });
+                });
+                if let Some(running) = self.running {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label(RichText::new(running).color(colors.text_secondary))
+                            .on_hover_text(running);
+                        response.stop_clicked =
+                            ui.small_button("■").on_hover_text("Stop").clicked();
+                    });
+                }
+            });
+
+        let metadata = match self.metadata {
+            None => {
+                ui.horizontal(|ui| {
+                    ui.add_space(12.0);
+                    ui.spinner();
+                    ui.label(RichText::new("Reading Cargo.toml...").color(colors.text_muted));
+                });
+                return response;
+            }
+            Some(Err(e)) => {
+                ui.horizontal_wrapped(|ui| {
+                    ui.add_space(12.0);
+                    ui.label(RichText::new(e).color(colors.text_muted));
+                });
+                return response;
+            }
+            Some(Ok(metadata)) => metadata,
+        };
+
+        let idle = self.running.is_none();
+        let single = metadata.packages.len() == 1;
+        ScrollArea::vertical()
+            .id_source("cargo_panel")
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                for package in &metadata.packages {
+                    // Default features start ticked, like a plain cargo command
+                    let ticked = self
+                        .features
+                        .entry(package.name.clone())
+                        .or_insert_with(|| {
+                            package
+                                .features
+                                .iter()
+                                .filter(|f| *f == "default")
+                                .cloned()
+                                .collect()
+                        });
+                    ui.horizontal(|ui| {
+                        ui.add_space(8.0);
+                        ui.vertical(|ui| {
+                            show_package(ui, package, ticked, idle, single, &mut response);
+                        });
+                    });
+                }
+            });
+
+        response
+    }
+}
+
+fn show_package(
+    ui: &mut Ui,
+    package: &Package,
+    ticked: &mut BTreeSet<String>,
+    idle: bool,
+    open: bool,
+    response: &mut CargoPanelResponse,
+) {
+    let colors = palette(ui.visuals());
+    let invocation =
+        |action: Action, target: Option<&Target>, ticked: &BTreeSet<String>| Invocation {
+            action,
+            package: package.name.clone(),
+            target: target.cloned(),
+            features: ticked.iter().filter(|f| *f != "default").cloned().collect(),
+            default_features: ticked.contains("default")
+                || !package.features.iter().any(|f| f == "default"),
+        };
+
+    let header = RichText::new(format!("📦 {} {}", package.name, package.version))
+        .size(fonts::BODY)
+        .color(colors.text_primary);
+    CollapsingHeader::new(header)
+        .id_source(("cargo_package", &package.name))
+        .default_open(open)
+        .show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.add_enabled_ui(idle, |ui| {
+                    if ui.small_button("Build").clicked() {
+                        response.run = Some(invocation(Action::Build, None, ticked));
+                    }
+                    if ui.small_button("Test").clicked() {
+                        response.run = Some(invocation(Action::Test, None, ticked));
+                    }
+                    if ui.small_button("Clean").clicked() {
+                        response.run = Some(invocation(Action::Clean, None, ticked));
+                    }
+                });
+                if ui
+                    .small_button("📄")
+                    .on_hover_text("Open Cargo.toml")
+                    .clicked()
+                {
+                    response.open_file = Some(package.manifest_path.clone());
+                }
+            });
+
+            CollapsingHeader::new("Targets")
+                .id_source(("cargo_targets", &package.name))
+                .default_open(true)
+                .show(ui, |ui| {
+                    for target in &package.targets {
+                        if target.kind == TargetKind::BuildScript {
+                            continue;
+                        }
+                        ui.horizontal(|ui| {
+                            ui.label(
+                                RichText::new(&target.name)
+                                    .size(fonts::BODY)
+                                    .color(colors.text_primary),
+                            );
+                            ui.label(
+                                RichText::new(target.kind.name())
+                                    .size(fonts::STATUS_BAR)
+                                    .color(colors.text_muted),
+                            );
+                            ui.with_layout(
+                                egui::Layout::right_to_left(egui::Align::Center),
+                                |ui| {
+                                    ui.add_enabled_ui(idle, |ui| {
+                                        let mut button = |ui: &mut Ui, icon, tip, action| {
+                                            if ui.small_button(icon).on_hover_text(tip).clicked() {
+                                                response.run =
+                                                    Some(invocation(action, Some(target), ticked));
+                                            }
+                                        };
+                                        button(ui, "✔", "Test", Action::Test);
+                                        button(ui, "🔨", "Build", Action::Build);
+                                        if target.kind.is_runnable() {
+                                            button(ui, "▶", "Run", Action::Run);
+                                        }
+                                    });
+                                },
+                            );
+                        });
+                    }
+                });
+
+            if !package.features.is_empty() {
+                CollapsingHeader::new("Features")
+                    .id_source(("cargo_features", &package.name))
+                    .show(ui, |ui| {
+                        for feature in &package.features {
+                            let mut on = ticked.contains(feature);
+                            if ui.checkbox(&mut on, feature).changed() {
+                                if on {
+                                    ticked.insert(feature.clone());
+                                } else {
+                                    ticked.remove(feature);
+                                }
+                            }
+                        }
+                    });
+            }
+
+            if !package.dependencies.is_empty() {
+                CollapsingHeader::new(format!("Dependencies ({})", package.dependencies.len()))
+                    .id_source(("cargo_dependencies", &package.name))
+                    .show(ui, |ui| {
+                        for dependency in &package.dependencies {
+                            ui.horizontal(|ui| {
+                                ui.add_space(layout::INDENT_SIZE / 2.0);
+                                ui.label(
+                                    RichText::new(&dependency.name)
+                                        .size(fonts::BODY)
+                                        .color(colors.text_primary),
+                                );
+                                let mut details = dependency.req.clone();
+                                if let Some(kind) = &dependency.kind {
+                                    details.push_str(&format!(" ({kind})"));
+                                }
+                                if dependency.optional {
+                                    details.push_str(" optional");
+                                }
+                                ui.label(
+                                    RichText::new(details)
+                                        .size(fonts::STATUS_BAR)
+                                        .color(colors.text_muted),
+                                );
+                            });
+                        }
+                    });
+            }
+        });
+}
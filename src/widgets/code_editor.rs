@@ -0,0 +1,1483 @@
+use super::{snap_line_x, snap_rect, MarkdownPreview};
+use crate::state::control_chars::ControlChar;
+use crate::state::folding::FoldRange;
+use crate::state::multi_cursor::{self, CursorMotion};
+use crate::state::{CursorPosition, EditKind, EditorTabState, WrapLayout};
+use crate::theme::{fonts, layout, palette, Palette};
+use egui::text::{CCursor, LayoutJob, TextFormat};
+use egui::{
+    Color32, Event, EventFilter, FontId, Galley, Id, Key, Modifiers, Pos2, Rect, Response, Sense,
+    Stroke, Ui, Vec2,
+};
+use ropey::Rope;
+use std::borrow::Cow;
+use std::ops::Range;
+use std::sync::Arc;
+
+/// Syntax colors for one line: byte ranges within the line and their color
+pub type LineStyle = Vec<(Range<usize>, Color32)>;
+
+/// Background decoration over a range of characters (find matches, bracket pairs)
+#[derive(Clone)]
+pub struct TextHighlight {
+    pub range: Range<usize>,
+    pub fill: Color32,
+    pub stroke: Stroke,
+}
+
+/// Wavy underline under a range of characters (diagnostics), with text shown
+/// when the pointer rests on it
+#[derive(Clone)]
+pub struct Underline {
+    pub range: Range<usize>,
+    pub color: Color32,
+    pub tooltip: String,
+}
+
+/// Caret of someone else editing the same file, with their name above it
+#[derive(Clone)]
+pub struct RemoteCaret {
+    pub offset: usize,
+    pub color: Color32,
+    pub label: String,
+}
+
+/// Clickable action drawn after the text of a line (e.g. "Send Request")
+#[derive(Clone)]
+pub struct CodeLens {
+    /// Zero-based line
+    pub line: usize,
+    pub label: String,
+}
+
+/// How long the pointer has to rest on text before it counts as hovering
+const HOVER_DELAY: f32 = 0.5;
+
+/// Memory key of the text last copied without a selection, which pastes as
+/// whole lines
+const WHOLE_LINE_CLIPBOARD: &str = "code_editor_whole_line_clipboard";
+
+/// Characters that get their closing counterpart inserted automatically
+const AUTO_CLOSE_PAIRS: &[(char, char)] = &[
+    ('(', ')'),
+    ('[', ']'),
+    ('{', '}'),
+    ('"', '"'),
+    ('\'', '\''),
+    ('`', '`'),
+];
+
+/// Galleys for the lines in view. Lines outside of it are laid out on demand,
+/// e.g. when the caret or a drag selection is off screen.
+struct VisibleLines {
+    ctx: egui::Context,
+    font_id: FontId,
+    first: usize,
+    galleys: Vec<Arc<Galley>>,
+}
+
+impl VisibleLines {
+    fn range(&self) -> Range<usize> {
+        self.first..self.first + self.galleys.len()
+    }
+}
+
+/// Code editing widget that handles input, carets, and selections directly
+/// against the `Rope` of a tab
+pub struct CodeEditor<'a> {
+    id: Id,
+    buffer: &'a mut Rope,
+    state: &'a mut EditorTabState,
+    line_styles: &'a [LineStyle],
+    highlights: Vec<TextHighlight>,
+    underlines: Vec<Underline>,
+    remote_carets: Vec<RemoteCaret>,
+    code_lenses: Vec<CodeLens>,
+    hover_markdown: Option<String>,
+    line_height: f32,
+    font_size: f32,
+    pixel_snapping: bool,
+    render_whitespace: bool,
+    word_wrap: bool,
+    /// Ctrl+Click adds a caret and Alt+Click goes to the definition, rather
+    /// than the other way round
+    add_caret_with_ctrl: bool,
+    /// Width of a wrapped row in characters, while word wrap is on
+    wrap_columns: Option<usize>,
+    pasted: Option<Range<usize>>,
+    typed: Option<char>,
+}
+
+/// Response from CodeEditor widget
+pub struct CodeEditorResponse {
+    /// Whether the buffer was modified this frame
+    pub changed: bool,
+    /// Screen rect of the primary caret
+    pub caret: Rect,
+    /// Index of the code lens that was clicked
+    pub code_lens_clicked: Option<usize>,
+    /// Char offset the pointer has been resting on
+    pub hover_offset: Option<usize>,
+    /// Offset Ctrl+Clicked (or Alt+Clicked, when Ctrl+Click adds carets) to
+    /// go to its definition
+    pub definition_clicked: Option<usize>,
+    /// Char range of text pasted at a single caret this frame
+    pub pasted: Option<Range<usize>>,
+    /// Last character typed this frame; `\n` for Enter
+    pub typed: Option<char>,
+    /// Offset middle-clicked to paste the primary selection
+    pub middle_clicked: Option<usize>,
+}
+
+impl<'a> CodeEditor<'a> {
+    pub fn new(id: Id, buffer: &'a mut Rope, state: &'a mut EditorTabState) -> Self {
+        Self {
+            id,
+            buffer,
+            state,
+            line_styles: &[],
+            highlights: Vec::new(),
+            underlines: Vec::new(),
+            remote_carets: Vec::new(),
+            code_lenses: Vec::new(),
+            hover_markdown: None,
+            line_height: layout::LINE_HEIGHT,
+            font_size: fonts::BODY,
+            pixel_snapping: true,
+            render_whitespace: false,
+            word_wrap: false,
+            add_caret_with_ctrl: false,
+            wrap_columns: None,
+            pasted: None,
+            typed: None,
+        }
+    }
+
+    pub fn line_styles(mut self, styles: &'a [LineStyle]) -> Self {
+        self.line_styles = styles;
+        self
+    }
+
+    pub fn highlights(mut self, highlights: Vec<TextHighlight>) -> Self {
+        self.highlights = highlights;
+        self
+    }
+
+    pub fn underlines(mut self, underlines: Vec<Underline>) -> Self {
+        self.underlines = underlines;
+        self
+    }
+
+    pub fn remote_carets(mut self, carets: Vec<RemoteCaret>) -> Self {
+        self.remote_carets = carets;
+        self
+    }
+
+    pub fn code_lenses(mut self, lenses: Vec<CodeLens>) -> Self {
+        self.code_lenses = lenses;
+        self
+    }
+
+    /// Documentation for the symbol at the last `hover_offset`, shown in the
+    /// hover tooltip
+    pub fn hover_markdown(mut self, markdown: Option<String>) -> Self {
+        self.hover_markdown = markdown;
+        self
+    }
+
+    pub fn line_height(mut self, height: f32) -> Self {
+        self.line_height = height;
+        self
+    }
+
+    pub fn font_size(mut self, size: f32) -> Self {
+        self.font_size = size;
+        self
+    }
+
+    /// Align highlight rects, carets, and text to physical pixels
+    pub fn pixel_snapping(mut self, enabled: bool) -> Self {
+        self.pixel_snapping = enabled;
+        self
+    }
+
+    /// Draw spaces as dots, tabs as arrows, and trailing whitespace in the
+    /// warning color
+    pub fn render_whitespace(mut self, enabled: bool) -> Self {
+        self.render_whitespace = enabled;
+        self
+    }
+
+    /// Soft-wrap long lines at the width of the editor instead of scrolling
+    /// sideways
+    pub fn word_wrap(mut self, enabled: bool) -> Self {
+        self.word_wrap = enabled;
+        self
+    }
+
+    pub fn add_caret_with_ctrl(mut self, enabled: bool) -> Self {
+        self.add_caret_with_ctrl = enabled;
+        self
+    }
+
+    pub fn show(mut self, ui: &mut Ui) -> CodeEditorResponse {
+        let font_id = FontId::monospace(self.font_size);
+        let char_width = ui.fonts(|f| f.glyph_width(&font_id, ' '));
+
+        // Rows leave the same margin on the right as the scroll width without wrap
+        self.wrap_columns = self.word_wrap.then(|| {
+            let width = ui.available_width() - layout::EDITOR_PADDING_LEFT - char_width * 2.0;
+            (width / char_width).floor().max(1.0) as usize
+        });
+
+        // Handle keyboard input before layout so this frame paints the edited text
+        let has_focus = ui.memory(|m| m.has_focus(self.id));
+        let mut changed = false;
+        let mut caret_moved = false;
+        if has_focus {
+            ui.memory_mut(|m| {
+                m.set_focus_lock_filter(
+                    self.id,
+                    EventFilter {
+                        tab: true,
+                        horizontal_arrows: true,
+                        vertical_arrows: true,
+                        escape: true,
+                    },
+                )
+            });
+            (changed, caret_moved) = self.handle_keyboard(ui);
+        }
+        self.unfold_at_carets();
+        if self.layout_columns().is_none() {
+            self.state.wrap = None;
+        }
+        self.ensure_wrap();
+
+        // Only the lines inside the scroll area's viewport are laid out
+        let total_rows = self.row_count();
+        let clip = ui.clip_rect();
+        let top = ui.cursor().top();
+        let first_row =
+            (((clip.top() - top) / self.line_height).floor().max(0.0) as usize).min(total_rows - 1);
+        let last_row = (((clip.bottom() - top) / self.line_height).ceil().max(0.0) as usize)
+            .clamp(first_row + 1, total_rows);
+        let first = self.row_line(first_row);
+        let last = self.row_line(last_row - 1) + 1;
+        // Folded lines in between are never painted
+        let folded_galley =
+            ui.fonts(|f| f.layout_no_wrap(String::new(), font_id.clone(), Color32::PLACEHOLDER));
+        let lines = VisibleLines {
+            ctx: ui.ctx().clone(),
+            galleys: (first..last)
+                .map(|line_idx| {
+                    if self.is_hidden(line_idx) {
+                        folded_galley.clone()
+                    } else {
+                        self.layout_line(ui.ctx(), line_idx, &font_id)
+                    }
+                })
+                .collect(),
+            font_id,
+            first,
+        };
+
+        let content_width = if self.wrap_columns.is_some() {
+            // Wrapped rows never scroll sideways
+            0.0
+        } else {
+            let text_width = (self.widest_line() as f32 * char_width)
+                .max(lines.galleys.iter().map(|g| g.size().x).fold(0.0, f32::max));
+            text_width + layout::EDITOR_PADDING_LEFT + char_width * 2.0
+        };
+        let desired_size = Vec2::new(
+            content_width.max(ui.available_width()),
+            (total_rows as f32 * self.line_height).max(ui.available_height()),
+        );
+        let (_, rect) = ui.allocate_space(desired_size);
+        let response = ui
+            .interact(rect, self.id, Sense::click_and_drag())
+            .on_hover_cursor(egui::CursorIcon::Text);
+        let text_origin = rect.left_top() + Vec2::new(layout::EDITOR_PADDING_LEFT, 0.0);
+
+        if self.handle_pointer(ui, &response, &lines, text_origin) {
+            caret_moved = true;
+        }
+        if caret_moved {
+            self.forget_auto_closed();
+        }
+        let modifiers = ui.input(|i| i.modifiers);
+        let definition_modifier = if self.add_caret_with_ctrl {
+            modifiers.alt
+        } else {
+            modifiers.ctrl
+        };
+        let definition_clicked =
+            (response.clicked() && definition_modifier).then(|| self.state.cursor().offset);
+        let middle_clicked = response
+            .middle_clicked()
+            .then(|| response.interact_pointer_pos())
+            .flatten()
+            .map(|pos| self.offset_at(pos, &lines, text_origin));
+
+        let caret = self.caret_rect(self.state.cursor().offset, &lines, text_origin);
+        let center = std::mem::take(&mut self.state.center_on_cursor);
+        if caret_moved || changed || center || std::mem::take(&mut self.state.scroll_to_cursor) {
+            let align = center.then_some(egui::Align::Center);
+            ui.scroll_to_rect(caret.expand2(Vec2::new(char_width * 2.0, 0.0)), align);
+        }
+
+        let has_focus = ui.memory(|m| m.has_focus(self.id));
+        self.paint(ui, rect, text_origin, &lines, char_width, has_focus);
+        let hover_offset = self.hover_offset(ui, &response, text_origin, &lines);
+        self.show_hover_tooltip(ui, &response, text_origin, &lines, char_width);
+        let code_lens_clicked = self.show_code_lenses(ui, text_origin, &lines, char_width);
+        self.show_fold_labels(ui, text_origin, &lines, char_width);
+
+        CodeEditorResponse {
+            changed,
+            caret,
+            code_lens_clicked,
+            hover_offset,
+            definition_clicked,
+            pasted: self.pasted,
+            typed: self.typed,
+            middle_clicked,
+        }
+    }
+
+    // === Layout ===
+
+    fn line_text(&self, line_idx: usize) -> Cow<'_, str> {
+        let line = self.buffer.line(line_idx);
+        let len = multi_cursor::line_len(self.buffer, line_idx);
+        line.slice(..len).into()
+    }
+
+    /// Length in characters of the longest line, recomputed once per revision.
+    /// Tabs count as one character; the visible galleys correct for them.
+    fn widest_line(&mut self) -> usize {
+        match self.state.widest_line {
+            Some((revision, width)) if revision == self.state.revision => width,
+            _ => {
+                let width = (0..self.buffer.len_lines())
+                    .map(|line_idx| multi_cursor::line_len(self.buffer, line_idx))
+                    .max()
+                    .unwrap_or(0);
+                self.state.widest_line = Some((self.state.revision, width));
+                width
+            }
+        }
+    }
+
+    /// Columns of the visual rows: the wrap width, or unlimited when lines
+    /// are only folded
+    fn layout_columns(&self) -> Option<usize> {
+        self.wrap_columns
+            .or_else(|| (!self.state.folded.is_empty()).then_some(usize::MAX))
+    }
+
+    /// Rebuild the visual rows when word wrap is on or lines are folded, and
+    /// the buffer, the folds or the width changed
+    fn ensure_wrap(&mut self) {
+        let Some(columns) = self.layout_columns() else {
+            return;
+        };
+        let hidden: Vec<Range<usize>> = self.state.folded.iter().map(FoldRange::hidden).collect();
+        let current = self.state.wrap.as_ref().is_some_and(|wrap| {
+            wrap.revision == self.state.revision
+                && wrap.columns == columns
+                && wrap.line_count() == self.buffer.len_lines()
+                && wrap.hidden() == hidden
+        });
+        if !current {
+            self.state.wrap = Some(Arc::new(WrapLayout::new(
+                self.buffer,
+                self.state.revision,
+                columns,
+                hidden,
+            )));
+        }
+    }
+
+    fn wrap(&self) -> Option<&WrapLayout> {
+        self.layout_columns().and(self.state.wrap.as_deref())
+    }
+
+    /// Whether a line is inside a collapsed fold
+    fn is_hidden(&self, line_idx: usize) -> bool {
+        self.wrap().is_some_and(|wrap| wrap.is_hidden(line_idx))
+    }
+
+    /// Open the folds carets have moved into, and drop those past the end
+    fn unfold_at_carets(&mut self) {
+        let buffer = &*self.buffer;
+        let state = &mut *self.state;
+        let caret_lines: Vec<usize> = state
+            .cursors
+            .iter()
+            .map(|c| buffer.char_to_line(c.offset.min(buffer.len_chars())))
+            .collect();
+        state.folded.retain(|fold| {
+            let hidden = fold.hidden();
+            hidden.end <= buffer.len_lines() && !caret_lines.iter().any(|l| hidden.contains(l))
+        });
+    }
+
+    /// Number of visual rows, which is the number of lines without word wrap
+    fn row_count(&self) -> usize {
+        self.wrap()
+            .map_or(self.buffer.len_lines(), WrapLayout::row_count)
+    }
+
+    fn row_line(&self, row: usize) -> usize {
+        self.wrap().map_or(row, |wrap| wrap.row_line(row))
+    }
+
+    /// First visual row of a line; one past the last line gives the row count
+    fn line_row(&self, line_idx: usize) -> usize {
+        self.wrap()
+            .map_or(line_idx, |wrap| wrap.line_rows(line_idx).start)
+    }
+
+    /// Line of a visual row, the columns of the line it shows, and whether it
+    /// is the line's last row
+    fn row_span(&self, row: usize) -> (usize, Range<usize>, bool) {
+        match self.wrap() {
+            Some(wrap) => (
+                wrap.row_line(row),
+                wrap.row_columns(row),
+                wrap.is_last_row(row),
+            ),
+            None => (row, 0..multi_cursor::line_len(self.buffer, row), true),
+        }
+    }
+
+    /// Visual rows of a line with the columns each shows
+    fn line_segments(&self, line_idx: usize) -> Vec<(usize, Range<usize>)> {
+        match self.wrap() {
+            Some(wrap) => wrap
+                .line_rows(line_idx)
+                .map(|row| (row, wrap.row_columns(row)))
+                .collect(),
+            None => vec![(line_idx, 0..multi_cursor::line_len(self.buffer, line_idx))],
+        }
+    }
+
+    /// Position of a column of a laid out line relative to the text origin:
+    /// x within its visual row, and the top of the row
+    fn column_pos(&self, line_idx: usize, galley: &Galley, column: usize) -> Vec2 {
+        let (row, row_start) = match self.wrap() {
+            Some(wrap) => {
+                let row = wrap.row_at(line_idx, column);
+                (row, wrap.row_columns(row).start)
+            }
+            None => (line_idx, 0),
+        };
+        Vec2::new(
+            Self::column_x(galley, column) - Self::column_x(galley, row_start),
+            row as f32 * self.line_height,
+        )
+    }
+
+    fn layout_line(&self, ctx: &egui::Context, line_idx: usize, font_id: &FontId) -> Arc<Galley> {
+        let colors = palette(&ctx.style().visuals);
+        let text = self.line_text(line_idx);
+        let format = |color: Color32| TextFormat {
+            font_id: font_id.clone(),
+            color,
+            line_height: Some(self.line_height),
+            ..Default::default()
+        };
+
+        let mut job = LayoutJob::default();
+        let mut pos = 0;
+
+        // Styles may be stale for a frame after an edit, so stop at the first range
+        // that no longer fits the line
+        if let Some(styles) = self.line_styles.get(line_idx) {
+            for (range, color) in styles {
+                let end = range.end.min(text.len());
+                if range.start != pos || !text.is_char_boundary(end) {
+                    break;
+                }
+                if end > pos {
+                    append_text(&mut job, &text[pos..end], format(*color), colors);
+                    pos = end;
+                }
+            }
+        }
+        if pos < text.len() {
+            append_text(&mut job, &text[pos..], format(colors.text_fallback), colors);
+        }
+
+        ctx.fonts(|f| f.layout_job(job))
+    }
+
+    fn galley(&self, lines: &VisibleLines, line_idx: usize) -> Arc<Galley> {
+        match line_idx.checked_sub(lines.first) {
+            Some(i) if i < lines.galleys.len() => lines.galleys[i].clone(),
+            _ => self.layout_line(&lines.ctx, line_idx, &lines.font_id),
+        }
+    }
+
+    /// Horizontal position of a column within a laid out line
+    fn column_x(galley: &Galley, column: usize) -> f32 {
+        galley.pos_from_ccursor(CCursor::new(column)).min.x
+    }
+
+    fn caret_rect(&self, offset: usize, lines: &VisibleLines, origin: Pos2) -> Rect {
+        let line_idx = self.buffer.char_to_line(offset);
+        let column = offset - self.buffer.line_to_char(line_idx);
+        let pos = origin + self.column_pos(line_idx, &self.galley(lines, line_idx), column);
+        Rect::from_min_size(pos, Vec2::new(1.0, self.line_height))
+    }
+
+    /// Rectangles covering a character range, one per visible row it spans
+    fn range_rects(
+        &self,
+        range: &Range<usize>,
+        lines: &VisibleLines,
+        origin: Pos2,
+        char_width: f32,
+    ) -> Vec<Rect> {
+        let len = self.buffer.len_chars();
+        let (start, end) = (range.start.min(len), range.end.min(len));
+        let first_line = self.buffer.char_to_line(start);
+        let last_line = self.buffer.char_to_line(end);
+        let visible = lines.range();
+
+        let mut rects = Vec::new();
+        for line_idx in first_line.max(visible.start)..=last_line.min(visible.end.saturating_sub(1))
+        {
+            let line_start = self.buffer.line_to_char(line_idx);
+            let galley = &lines.galleys[line_idx - lines.first];
+            let from = if line_idx == first_line {
+                start - line_start
+            } else {
+                0
+            };
+            // None runs through the line break
+            let to = (line_idx == last_line).then(|| end - line_start);
+            let segments = self.line_segments(line_idx);
+            let Some(last_segment) = segments.len().checked_sub(1) else {
+                continue;
+            };
+            for (i, (row, columns)) in segments.into_iter().enumerate() {
+                let row_from = from.max(columns.start);
+                let row_to = to.map_or(columns.end, |to| to.min(columns.end));
+                if row_from > row_to {
+                    continue;
+                }
+                let row_x = Self::column_x(galley, columns.start);
+                let start_x = Self::column_x(galley, row_from) - row_x;
+                let mut end_x = Self::column_x(galley, row_to) - row_x;
+                if to.is_none() && i == last_segment {
+                    end_x += char_width;
+                }
+                let y = origin.y + row as f32 * self.line_height;
+                rects.push(Rect::from_min_max(
+                    Pos2::new(origin.x + start_x, y),
+                    Pos2::new(origin.x + end_x, y + self.line_height),
+                ));
+            }
+        }
+        rects.retain(|r| r.width() > 0.0);
+        rects
+    }
+
+    fn offset_at(&self, pos: Pos2, lines: &VisibleLines, origin: Pos2) -> usize {
+        let last_row = self.row_count() - 1;
+        let row = (((pos.y - origin.y) / self.line_height).max(0.0) as usize).min(last_row);
+        let (line_idx, columns, is_last_row) = self.row_span(row);
+        let galley = self.galley(lines, line_idx);
+        let x = pos.x - origin.x + Self::column_x(&galley, columns.start);
+        let column = galley.cursor_from_pos(Vec2::new(x, 0.0)).ccursor.index;
+        // A wrapped row ends before the column the next row starts at
+        let last_column = if is_last_row {
+            columns.end
+        } else {
+            columns.end - 1
+        };
+        self.buffer.line_to_char(line_idx) + column.clamp(columns.start, last_column)
+    }
+
+    // === Input ===
+
+    /// Returns (text changed, caret moved)
+    fn handle_keyboard(&mut self, ui: &Ui) -> (bool, bool) {
+        let events = ui.input(|i| i.events.clone());
+        let page_lines = (ui.clip_rect().height() / self.line_height).max(1.0) as usize;
+        let mut changed = false;
+        let mut moved = false;
+
+        for event in &events {
+            let (event_changed, event_moved) = match event {
+                // Newlines are handled by Key::Enter
+                Event::Text(text) if !text.is_empty() && text != "\n" && text != "\r" => {
+                    self.type_text(text);
+                    (true, false)
+                }
+                Event::CompositionEnd(text) if !text.is_empty() => {
+                    self.type_text(text);
+                    (true, false)
+                }
+                Event::Paste(text) if !text.is_empty() => {
+                    self.paste(ui, text);
+                    (true, false)
+                }
+                Event::Copy => {
+                    self.copy(ui);
+                    (false, false)
+                }
+                Event::Cut => {
+                    self.copy(ui);
+                    self.record(EditKind::Other);
+                    // Without a selection the whole line goes
+                    if self.state.cursors.iter().any(|c| c.has_selection()) {
+                        multi_cursor::delete_backward(self.buffer, &mut self.state.cursors, false);
+                    } else {
+                        multi_cursor::delete_lines(self.buffer, &mut self.state.cursors);
+                    }
+                    (true, false)
+                }
+                Event::Key {
+                    key,
+                    pressed: true,
+                    modifiers,
+                    ..
+                } => self.on_key(*key, *modifiers, page_lines),
+                _ => (false, false),
+            };
+            // Counted per event so an Up or Down later in the frame sees fresh wrapped rows
+            if event_changed {
+                self.state.revision += 1;
+            }
+            changed |= event_changed;
+            moved |= event_moved;
+        }
+
+        (changed, moved)
+    }
+
+    /// Returns (text changed, caret moved)
+    fn on_key(&mut self, key: Key, modifiers: Modifiers, page_lines: usize) -> (bool, bool) {
+        let shift = modifiers.shift;
+        let ctrl = modifiers.ctrl;
+
+        // Shift+Alt+Up/Down copy lines rather than extending the selection
+        if modifiers.alt && shift && !ctrl && matches!(key, Key::ArrowUp | Key::ArrowDown) {
+            self.record(EditKind::Other);
+            multi_cursor::copy_lines(self.buffer, &mut self.state.cursors, key == Key::ArrowUp);
+            return (true, true);
+        }
+
+        let motion = match key {
+            Key::ArrowLeft if ctrl => Some(CursorMotion::WordLeft),
+            Key::ArrowRight if ctrl => Some(CursorMotion::WordRight),
+            Key::ArrowLeft => Some(CursorMotion::Left),
+            Key::ArrowRight => Some(CursorMotion::Right),
+            Key::ArrowUp if !ctrl => Some(CursorMotion::Up),
+            Key::ArrowDown if !ctrl => Some(CursorMotion::Down),
+            Key::Home if ctrl => Some(CursorMotion::DocumentStart),
+            Key::End if ctrl => Some(CursorMotion::DocumentEnd),
+            Key::Home => Some(CursorMotion::LineStart),
+            Key::End => Some(CursorMotion::LineEnd),
+            Key::PageUp => Some(CursorMotion::PageUp(page_lines)),
+            Key::PageDown => Some(CursorMotion::PageDown(page_lines)),
+            _ => None,
+        };
+        if let Some(motion) = motion {
+            self.ensure_wrap();
+            let wrap = self.layout_columns().and(self.state.wrap.as_deref());
+            multi_cursor::move_cursors(self.buffer, &mut self.state.cursors, motion, shift, wrap);
+            self.state.history.break_group();
+            return (false, true);
+        }
+
+        // Undo and redo move the auto-inserted closing characters around
+        if ctrl && matches!(key, Key::Z | Key::Y) {
+            self.state.auto_closed.clear();
+        }
+
+        match key {
+            Key::Enter => {
+                self.record(EditKind::Other);
+                multi_cursor::insert_text(self.buffer, &mut self.state.cursors, "\n");
+                self.typed = Some('\n');
+                (true, true)
+            }
+            Key::Tab if !shift => {
+                self.record(EditKind::Other);
+                multi_cursor::insert_text(self.buffer, &mut self.state.cursors, "\t");
+                (true, true)
+            }
+            Key::Backspace => {
+                self.backspace(ctrl);
+                (true, true)
+            }
+            Key::Delete => {
+                self.record(EditKind::Deleting);
+                multi_cursor::delete_forward(self.buffer, &mut self.state.cursors, ctrl);
+                (true, true)
+            }
+            Key::D if ctrl && shift => {
+                self.record(EditKind::Other);
+                multi_cursor::duplicate(self.buffer, &mut self.state.cursors);
+                (true, true)
+            }
+            Key::K if ctrl && shift => {
+                self.record(EditKind::Other);
+                multi_cursor::delete_lines(self.buffer, &mut self.state.cursors);
+                (true, true)
+            }
+            Key::A if ctrl => {
+                multi_cursor::select_all(self.buffer, &mut self.state.cursors);
+                (false, false)
+            }
+            Key::Z if ctrl && shift => {
+                let redone = self
+                    .state
+                    .history
+                    .redo(self.buffer, &mut self.state.cursors);
+                (redone, redone)
+            }
+            Key::Z if ctrl => {
+                let undone = self
+                    .state
+                    .history
+                    .undo(self.buffer, &mut self.state.cursors);
+                (undone, undone)
+            }
+            Key::Y if ctrl => {
+                let redone = self
+                    .state
+                    .history
+                    .redo(self.buffer, &mut self.state.cursors);
+                (redone, redone)
+            }
+            _ => (false, false),
+        }
+    }
+
+    fn record(&mut self, kind: EditKind) {
+        // Typing and Backspace keep track of where the closing characters went
+        if kind != EditKind::Typing {
+            self.state.auto_closed.clear();
+        }
+        self.state
+            .history
+            .record(self.buffer, &self.state.cursors, kind);
+    }
+
+    fn type_text(&mut self, text: &str) {
+        self.record(EditKind::Typing);
+
+        let mut chars = text.chars();
+        let single_char = chars.next().filter(|_| chars.next().is_none());
+        self.typed = single_char;
+        let closing = single_char.and_then(|ch| {
+            AUTO_CLOSE_PAIRS
+                .iter()
+                .find(|(open, _)| *open == ch)
+                .map(|&(open, close)| (open, close))
+        });
+        let has_selection = self.state.cursors.iter().any(|c| c.has_selection());
+
+        if let Some(ch) = single_char {
+            if multi_cursor::type_over(
+                self.buffer,
+                &mut self.state.cursors,
+                ch,
+                &self.state.auto_closed,
+            ) {
+                self.forget_auto_closed();
+                return;
+            }
+        }
+        let closing = closing.filter(|&(open, close)| {
+            open != close
+                || self
+                    .state
+                    .cursors
+                    .iter()
+                    .all(|c| multi_cursor::quote_closes(self.buffer, c.offset, open))
+        });
+        if has_selection {
+            self.state.auto_closed.clear();
+        }
+        let carets: Vec<usize> = self.state.cursors.iter().map(|c| c.offset).collect();
+
+        match closing {
+            Some((open, close)) if !has_selection => {
+                multi_cursor::insert_pair(self.buffer, &mut self.state.cursors, open, close);
+                self.shift_auto_closed(&carets, 2);
+                let closes = self.state.cursors.iter().map(|c| c.offset);
+                self.state.auto_closed.extend(closes);
+            }
+            _ => {
+                multi_cursor::insert_text(self.buffer, &mut self.state.cursors, text);
+                self.shift_auto_closed(&carets, text.chars().count() as isize);
+            }
+        }
+    }
+
+    fn backspace(&mut self, word: bool) {
+        let auto_closed = std::mem::take(&mut self.state.auto_closed);
+        self.record(EditKind::Deleting);
+        let carets: Vec<usize> = self.state.cursors.iter().map(|c| c.offset).collect();
+        if !word
+            && multi_cursor::delete_pair(self.buffer, &mut self.state.cursors, AUTO_CLOSE_PAIRS)
+        {
+            // The deleted closing characters were at the carets
+            self.state.auto_closed = auto_closed;
+            self.state
+                .auto_closed
+                .retain(|offset| !carets.contains(offset));
+            self.shift_auto_closed(&carets, -2);
+        } else if !word && !self.state.cursors.iter().any(|c| c.has_selection()) {
+            multi_cursor::delete_backward(self.buffer, &mut self.state.cursors, false);
+            // Nothing is deleted at the start of the file
+            let carets: Vec<usize> = carets.into_iter().filter(|&caret| caret > 0).collect();
+            self.state.auto_closed = auto_closed;
+            self.state
+                .auto_closed
+                .retain(|offset| !carets.contains(&(offset + 1)));
+            self.shift_auto_closed(&carets, -1);
+        } else {
+            multi_cursor::delete_backward(self.buffer, &mut self.state.cursors, word);
+        }
+    }
+
+    /// Move the auto-inserted closing characters along with an edit of `delta`
+    /// characters at each of `carets`, and forget the ones no caret is before
+    /// on the same line anymore
+    fn shift_auto_closed(&mut self, carets: &[usize], delta: isize) {
+        for offset in &mut self.state.auto_closed {
+            let edits_before = carets.iter().filter(|&&caret| caret <= *offset).count() as isize;
+            *offset = offset.saturating_add_signed(delta * edits_before);
+        }
+        self.forget_auto_closed();
+    }
+
+    fn forget_auto_closed(&mut self) {
+        let buffer = &*self.buffer;
+        let cursors = &self.state.cursors;
+        self.state.auto_closed.retain(|&offset| {
+            offset < buffer.len_chars()
+                && cursors.iter().any(|c| {
+                    c.offset <= offset
+                        && buffer.char_to_line(c.offset) == buffer.char_to_line(offset)
+                })
+        });
+    }
+
+    fn paste(&mut self, ui: &Ui, text: &str) {
+        self.record(EditKind::Other);
+
+        // Lines copied without a selection go above the caret's line
+        let whole_lines = !self.state.cursors.iter().any(|c| c.has_selection())
+            && ui
+                .data(|d| d.get_temp::<String>(Id::new(WHOLE_LINE_CLIPBOARD)))
+                .is_some_and(|copied| copied == text);
+
+        // One clipboard line per caret, like VSCode
+        let lines: Vec<&str> = text.lines().collect();
+        if self.state.has_multiple_cursors() && lines.len() == self.state.cursors.len() {
+            multi_cursor::insert_per_cursor(self.buffer, &mut self.state.cursors, &lines);
+        } else if whole_lines {
+            multi_cursor::insert_above(self.buffer, &mut self.state.cursors, text);
+        } else {
+            let start = self.state.cursor().selection().start;
+            multi_cursor::insert_text(self.buffer, &mut self.state.cursors, text);
+            self.pasted =
+                (!self.state.has_multiple_cursors()).then(|| start..self.state.cursor().offset);
+        }
+    }
+
+    /// Copy the selections, or the lines with a caret when nothing is selected
+    fn copy(&self, ui: &Ui) {
+        let id = Id::new(WHOLE_LINE_CLIPBOARD);
+        if self.state.cursors.iter().any(|c| c.has_selection()) {
+            let text = multi_cursor::selected_text(self.buffer, &self.state.cursors);
+            ui.ctx().output_mut(|o| o.copied_text = text);
+            ui.data_mut(|d| d.remove::<String>(id));
+        } else {
+            let text = multi_cursor::caret_lines(self.buffer, &self.state.cursors);
+            ui.ctx().output_mut(|o| o.copied_text = text.clone());
+            ui.data_mut(|d| d.insert_temp(id, text));
+        }
+    }
+
+    /// Returns true if the caret moved
+    fn handle_pointer(
+        &mut self,
+        ui: &Ui,
+        response: &Response,
+        lines: &VisibleLines,
+        origin: Pos2,
+    ) -> bool {
+        if !response.is_pointer_button_down_on() && !response.clicked() {
+            return false;
+        }
+        let Some(pos) = response.interact_pointer_pos() else {
+            return false;
+        };
+
+        let offset = self.offset_at(pos, lines, origin);
+        let (pressed, down, modifiers) = ui.input(|i| {
+            (
+                i.pointer.primary_pressed(),
+                i.pointer.primary_down(),
+                i.modifiers,
+            )
+        });
+        let primary = self.state.cursor().clone();
+        let add_caret = if self.add_caret_with_ctrl {
+            modifiers.ctrl
+        } else {
+            modifiers.alt
+        };
+
+        if response.triple_clicked() {
+            let range = multi_cursor::line_range_at(self.buffer, offset);
+            self.select_range(range);
+        } else if response.double_clicked() {
+            let range = multi_cursor::word_range_at(self.buffer, offset);
+            self.select_range(range);
+        } else if pressed {
+            response.request_focus();
+            self.state.history.break_group();
+
+            if add_caret {
+                self.state
+                    .add_cursor(CursorPosition::from_char_offset(self.buffer, offset));
+            } else if modifiers.shift {
+                self.state.clear_secondary_cursors();
+                self.state.set_cursor(
+                    CursorPosition::from_char_offset(self.buffer, offset)
+                        .with_anchor(primary.anchor),
+                );
+            } else {
+                self.state.clear_secondary_cursors();
+                self.state
+                    .set_cursor(CursorPosition::from_char_offset(self.buffer, offset));
+            }
+        } else if response.is_pointer_button_down_on() && down && !add_caret {
+            // Drag to select
+            self.state.set_cursor(
+                CursorPosition::from_char_offset(self.buffer, offset).with_anchor(primary.anchor),
+            );
+        }
+
+        self.state.cursor().offset != primary.offset || self.state.cursor().anchor != primary.anchor
+    }
+
+    fn select_range(&mut self, range: Range<usize>) {
+        self.state.clear_secondary_cursors();
+        self.state.set_cursor(
+            CursorPosition::from_char_offset(self.buffer, range.end).with_anchor(range.start),
+        );
+    }
+
+    // === Painting ===
+
+    fn paint(
+        &self,
+        ui: &Ui,
+        rect: Rect,
+        origin: Pos2,
+        lines: &VisibleLines,
+        char_width: f32,
+        has_focus: bool,
+    ) {
+        let colors = palette(ui.visuals());
+        let painter = ui.painter_at(rect);
+        let primary = self.state.cursor();
+        let current_line = self.buffer.char_to_line(primary.offset);
+        let snap = |r: Rect| {
+            if self.pixel_snapping {
+                snap_rect(&painter, r)
+            } else {
+                r
+            }
+        };
+
+        // Current line highlight, over all of its rows when wrapped
+        let line_rows = self.line_row(current_line)..self.line_row(current_line + 1);
+        let line_y = origin.y + line_rows.start as f32 * self.line_height;
+        painter.rect_filled(
+            snap(Rect::from_min_size(
+                Pos2::new(rect.left(), line_y),
+                Vec2::new(rect.width(), line_rows.len() as f32 * self.line_height),
+            )),
+            0.0,
+            colors.current_line_bg,
+        );
+
+        // Selections
+        for cursor in &self.state.cursors {
+            if cursor.has_selection() {
+                for r in self.range_rects(&cursor.selection(), lines, origin, char_width) {
+                    painter.rect_filled(snap(r), 0.0, colors.selection_bg);
+                }
+            }
+        }
+
+        self.paint_indent_guides(&painter, origin, lines.range(), char_width, current_line);
+
+        // Find matches, bracket pairs, ...
+        let visible = lines.range();
+        let visible_chars =
+            self.buffer.line_to_char(visible.start)..self.buffer.line_to_char(visible.end);
+        let in_view = |range: &Range<usize>| {
+            range.start <= visible_chars.end && range.end >= visible_chars.start
+        };
+        for highlight in self.highlights.iter().filter(|h| in_view(&h.range)) {
+            for r in self.range_rects(&highlight.range, lines, origin, char_width) {
+                let r = snap(if r.width() < 1.0 {
+                    r.expand2(Vec2::new(char_width * 0.5, 0.0))
+                } else {
+                    r
+                });
+                painter.rect_filled(r, 2.0, highlight.fill);
+                painter.rect_stroke(r, 2.0, highlight.stroke);
+            }
+        }
+
+        // Squiggles
+        for underline in self.underlines.iter().filter(|u| in_view(&u.range)) {
+            let stroke = Stroke::new(1.0, underline.color);
+            for r in self.range_rects(&underline.range, lines, origin, char_width) {
+                let r = if r.width() < 1.0 {
+                    r.expand2(Vec2::new(char_width * 0.5, 0.0))
+                } else {
+                    r
+                };
+                painter.add(egui::Shape::line(squiggle(r), stroke));
+            }
+        }
+
+        if self.render_whitespace {
+            self.paint_whitespace(&painter, origin, lines, char_width);
+        }
+
+        // Text. A wrapped line is painted once per row, shifted and clipped to
+        // the columns of the row.
+        for (line_idx, galley) in visible.clone().zip(&lines.galleys) {
+            let segments = self.line_segments(line_idx);
+            let Some(last_segment) = segments.len().checked_sub(1) else {
+                continue;
+            };
+            for (i, (row, columns)) in segments.into_iter().enumerate() {
+                let row_x = Self::column_x(galley, columns.start);
+                let y = origin.y + row as f32 * self.line_height;
+                let left = if i == 0 { rect.left() } else { origin.x };
+                let right = if i == last_segment {
+                    rect.right()
+                } else {
+                    origin.x + Self::column_x(galley, columns.end) - row_x
+                };
+                let clip = painter.clip_rect().intersect(Rect::from_min_max(
+                    Pos2::new(left, y),
+                    Pos2::new(right, y + self.line_height),
+                ));
+                if !clip.is_positive() {
+                    continue;
+                }
+                let mut pos = Pos2::new(origin.x - row_x, y);
+                if self.pixel_snapping {
+                    pos = painter.round_pos_to_pixels(pos);
+                }
+                painter
+                    .with_clip_rect(clip)
+                    .galley(pos, galley.clone(), colors.text_fallback);
+            }
+        }
+
+        for remote in &self.remote_carets {
+            if remote.offset > self.buffer.len_chars()
+                || !visible.contains(&self.buffer.char_to_line(remote.offset))
+                || self.is_hidden(self.buffer.char_to_line(remote.offset))
+            {
+                continue;
+            }
+            let caret = snap(self.caret_rect(remote.offset, lines, origin));
+            let x = self.line_x(&painter, caret.left());
+            painter.line_segment(
+                [Pos2::new(x, caret.top()), Pos2::new(x, caret.bottom())],
+                Stroke::new(2.0, remote.color),
+            );
+            let label = painter.layout_no_wrap(
+                remote.label.clone(),
+                FontId::proportional(fonts::SMALL),
+                Color32::WHITE,
+            );
+            let label_rect = Rect::from_min_size(
+                Pos2::new(x, caret.top() - label.size().y - 2.0),
+                label.size() + Vec2::new(6.0, 2.0),
+            );
+            painter.rect_filled(label_rect, 2.0, remote.color);
+            painter.galley(label_rect.min + Vec2::new(3.0, 1.0), label, Color32::WHITE);
+        }
+
+        // Carets
+        if has_focus {
+            let stroke = ui.visuals().text_cursor;
+            for cursor in &self.state.cursors {
+                if !visible.contains(&self.buffer.char_to_line(cursor.offset)) {
+                    continue;
+                }
+                let caret = snap(self.caret_rect(cursor.offset, lines, origin));
+                let x = self.line_x(&painter, caret.left());
+                painter.line_segment(
+                    [Pos2::new(x, caret.top()), Pos2::new(x, caret.bottom())],
+                    stroke,
+                );
+            }
+        }
+    }
+
+    /// Offset of the character under a pointer that has rested on the text for
+    /// `HOVER_DELAY`
+    fn hover_offset(
+        &self,
+        ui: &Ui,
+        response: &Response,
+        origin: Pos2,
+        lines: &VisibleLines,
+    ) -> Option<usize> {
+        let pointer = response.hover_pos()?;
+        let (still_for, any_down) =
+            ui.input(|i| (i.pointer.time_since_last_movement(), i.pointer.any_down()));
+        if any_down {
+            return None;
+        }
+        if still_for < HOVER_DELAY {
+            ui.ctx()
+                .request_repaint_after(std::time::Duration::from_secs_f32(HOVER_DELAY - still_for));
+            return None;
+        }
+        let row = ((pointer.y - origin.y) / self.line_height).floor();
+        if row < 0.0 || row as usize >= self.row_count() {
+            return None;
+        }
+        // Past the end of the row is not on any character
+        let (line_idx, columns, _) = self.row_span(row as usize);
+        let galley = self.galley(lines, line_idx);
+        let row_width =
+            Self::column_x(&galley, columns.end) - Self::column_x(&galley, columns.start);
+        if pointer.x - origin.x > row_width {
+            return None;
+        }
+        let offset = self.offset_at(pointer, lines, origin);
+        // offset_at rounds to the nearest caret position; hover wants the
+        // character the pointer is over
+        let caret_x = self.caret_rect(offset, lines, origin).left();
+        Some(if pointer.x < caret_x {
+            offset.saturating_sub(1)
+        } else {
+            offset
+        })
+    }
+
+    /// Diagnostics under the pointer, then `hover_markdown`
+    fn show_hover_tooltip(
+        &self,
+        ui: &Ui,
+        response: &Response,
+        origin: Pos2,
+        lines: &VisibleLines,
+        char_width: f32,
+    ) {
+        let Some(pointer) = response.hover_pos() else {
+            return;
+        };
+        let messages: Vec<&str> = self
+            .underlines
+            .iter()
+            .filter(|u| {
+                self.range_rects(&u.range, lines, origin, char_width)
+                    .iter()
+                    .any(|r| {
+                        r.expand2(Vec2::new(char_width * 0.5, 0.0))
+                            .contains(pointer)
+                    })
+            })
+            .map(|u| u.tooltip.as_str())
+            .collect();
+        if messages.is_empty() && self.hover_markdown.is_none() {
+            return;
+        }
+        egui::show_tooltip_at_pointer(ui.ctx(), self.id.with("hover"), |ui| {
+            ui.set_max_width(500.0);
+            for message in &messages {
+                ui.label(*message);
+            }
+            if let Some(markdown) = &self.hover_markdown {
+                if !messages.is_empty() {
+                    ui.separator();
+                }
+                MarkdownPreview::new(self.id.with("hover_markdown"), markdown).show_inline(ui);
+            }
+        });
+    }
+
+    /// Paint the code lenses of visible lines; returns the clicked one
+    fn show_code_lenses(
+        &self,
+        ui: &Ui,
+        origin: Pos2,
+        lines: &VisibleLines,
+        char_width: f32,
+    ) -> Option<usize> {
+        let colors = palette(ui.visuals());
+        let font_id = FontId::proportional(fonts::STATUS_BAR);
+        let mut clicked = None;
+        for (i, lens) in self.code_lenses.iter().enumerate() {
+            if !lines.range().contains(&lens.line) || self.is_hidden(lens.line) {
+                continue;
+            }
+            let galley = &lines.galleys[lens.line - lines.first];
+            let line_end = multi_cursor::line_len(self.buffer, lens.line);
+            let pos = origin
+                + self.column_pos(lens.line, galley, line_end)
+                + Vec2::new(char_width * 2.0, 0.0);
+            let text = ui.painter().layout_no_wrap(
+                format!("▶ {}", lens.label),
+                font_id.clone(),
+                Color32::PLACEHOLDER,
+            );
+            let rect = Rect::from_min_size(
+                Pos2::new(pos.x, pos.y + (self.line_height - text.size().y) / 2.0),
+                text.size(),
+            );
+            let response = ui
+                .interact(rect, self.id.with(("code_lens", i)), Sense::click())
+                .on_hover_cursor(egui::CursorIcon::PointingHand);
+            let color = if response.hovered() {
+                colors.text_primary
+            } else {
+                colors.text_secondary
+            };
+            ui.painter().galley(rect.min, text, color);
+            if response.clicked() {
+                clicked = Some(i);
+            }
+        }
+        clicked
+    }
+
+    /// Paint the labels of collapsed folds after their first line; clicking
+    /// one opens the fold
+    fn show_fold_labels(&mut self, ui: &Ui, origin: Pos2, lines: &VisibleLines, char_width: f32) {
+        let colors = palette(ui.visuals());
+        let font_id = FontId::monospace(self.font_size);
+        let mut unfold = None;
+        for (i, fold) in self.state.folded.iter().enumerate() {
+            let line_idx = fold.lines.start;
+            if !lines.range().contains(&line_idx) || self.is_hidden(line_idx) {
+                continue;
+            }
+            let galley = &lines.galleys[line_idx - lines.first];
+            let line_end = multi_cursor::line_len(self.buffer, line_idx);
+            let pos =
+                origin + self.column_pos(line_idx, galley, line_end) + Vec2::new(char_width, 0.0);
+            let text = ui.painter().layout_no_wrap(
+                fold.label.clone(),
+                font_id.clone(),
+                Color32::PLACEHOLDER,
+            );
+            let rect = Rect::from_min_size(
+                Pos2::new(pos.x, pos.y + (self.line_height - text.size().y) / 2.0),
+                text.size(),
+            )
+            .expand2(Vec2::new(3.0, 0.0));
+            let response = ui
+                .interact(rect, self.id.with(("fold_label", line_idx)), Sense::click())
+                .on_hover_cursor(egui::CursorIcon::PointingHand);
+            let color = if response.hovered() {
+                colors.text_primary
+            } else {
+                colors.text_secondary
+            };
+            ui.painter().rect_filled(rect, 3.0, colors.widget_bg);
+            ui.painter()
+                .galley(rect.min + Vec2::new(3.0, 0.0), text, color);
+            if response.clicked() {
+                unfold = Some(i);
+            }
+        }
+        if let Some(i) = unfold {
+            self.state.folded.remove(i);
+        }
+    }
+
+    fn line_x(&self, painter: &egui::Painter, x: f32) -> f32 {
+        if self.pixel_snapping {
+            snap_line_x(painter, x)
+        } else {
+            x
+        }
+    }
+
+    /// Whitespace markers drawn under the text, so the glyphs around them keep
+    /// their syntax colors
+    fn paint_whitespace(
+        &self,
+        painter: &egui::Painter,
+        origin: Pos2,
+        lines: &VisibleLines,
+        char_width: f32,
+    ) {
+        let colors = palette(&lines.ctx.style().visuals);
+        let trailing_bg = colors.diagnostic_warning.gamma_multiply(0.2);
+        for (line_idx, galley) in lines.range().zip(&lines.galleys) {
+            if self.is_hidden(line_idx) {
+                continue;
+            }
+            let text = self.line_text(line_idx);
+            let chars: Vec<char> = text.chars().collect();
+            let trailing_start = chars
+                .iter()
+                .rposition(|c| !c.is_whitespace())
+                .map_or(0, |i| i + 1);
+            let cell = |column: usize| {
+                let width = Self::column_x(galley, column + 1) - Self::column_x(galley, column);
+                let pos = origin + self.column_pos(line_idx, galley, column);
+                Rect::from_min_size(pos, Vec2::new(width, self.line_height))
+            };
+
+            // One run of trailing whitespace per row it wraps onto
+            let mut run: Option<Rect> = None;
+            for column in trailing_start..chars.len() {
+                let cell = cell(column);
+                run = match run {
+                    Some(run) if run.top() == cell.top() => Some(run.union(cell)),
+                    Some(run) => {
+                        painter.rect_filled(run, 0.0, trailing_bg);
+                        Some(cell)
+                    }
+                    None => Some(cell),
+                };
+            }
+            if let Some(run) = run {
+                painter.rect_filled(run, 0.0, trailing_bg);
+            }
+            for (column, &c) in chars.iter().enumerate() {
+                if c != ' ' && c != '\t' {
+                    continue;
+                }
+                let color = if column >= trailing_start {
+                    colors.diagnostic_warning
+                } else {
+                    colors.indent_guide_active
+                };
+                let cell = cell(column);
+                if c == ' ' {
+                    painter.circle_filled(cell.center(), (char_width * 0.08).max(1.0), color);
+                } else {
+                    // Arrow across the tab stop
+                    let stroke = Stroke::new(1.0, color);
+                    let head = char_width * 0.3;
+                    let tip = Pos2::new(cell.right() - char_width * 0.2, cell.center().y);
+                    let tail = Pos2::new(cell.left() + char_width * 0.2, cell.center().y);
+                    painter.line_segment([tail, tip], stroke);
+                    painter.line_segment([tip, tip + Vec2::new(-head, -head)], stroke);
+                    painter.line_segment([tip, tip + Vec2::new(-head, head)], stroke);
+                }
+            }
+        }
+    }
+
+    fn paint_indent_guides(
+        &self,
+        painter: &egui::Painter,
+        origin: Pos2,
+        visible: Range<usize>,
+        char_width: f32,
+        current_line: usize,
+    ) {
+        let colors = palette(&painter.ctx().style().visuals);
+        let indent_level = |line_idx: usize| {
+            let line = self.buffer.line(line_idx);
+            let spaces = line.chars().take_while(|c| *c == ' ').count();
+            let tabs = line.chars().take_while(|c| *c == '\t').count();
+            (spaces + tabs * layout::TAB_SIZE) / layout::TAB_SIZE
+        };
+        let first_line = visible.start;
+        let indent_levels: Vec<usize> = visible.map(indent_level).collect();
+
+        // The active indent level is the indent level of the current line
+        let active_indent = indent_level(current_line);
+        let indent_width = char_width * layout::TAB_SIZE as f32;
+        let max_indent = indent_levels.iter().copied().max().unwrap_or(0);
+
+        for indent in 1..=max_indent {
+            let x = self.line_x(
+                painter,
+                origin.x + (indent as f32 * indent_width) - (indent_width - char_width * 0.5),
+            );
+            let guide_color = if indent == active_indent {
+                colors.indent_guide_active
+            } else {
+                colors.indent_guide
+            };
+            let stroke = Stroke::new(1.0, guide_color);
+
+            // Draw vertical line segments where this indent level is active
+            let mut segment_start: Option<usize> = None;
+            for (line_idx, &line_indent) in
+                indent_levels.iter().chain(std::iter::once(&0)).enumerate()
+            {
+                match (segment_start, line_indent >= indent) {
+                    (None, true) => segment_start = Some(first_line + line_idx),
+                    (Some(start), false) => {
+                        let y_start = origin.y + self.line_row(start) as f32 * self.line_height;
+                        let y_end = origin.y
+                            + self.line_row(first_line + line_idx) as f32 * self.line_height;
+                        painter.line_segment([Pos2::new(x, y_start), Pos2::new(x, y_end)], stroke);
+                        segment_start = None;
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+/// Append `text` to `job` with control and invisible characters drawn as
+/// their placeholders, so each still takes one column and can be seen
+fn append_text(job: &mut LayoutJob, text: &str, format: TextFormat, colors: &Palette) {
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        let Some(control) = ControlChar::of(c) else {
+            continue;
+        };
+        if start < i {
+            job.append(&text[start..i], 0.0, format.clone());
+        }
+        let placeholder = TextFormat {
+            color: colors.diagnostic_warning,
+            ..format.clone()
+        };
+        job.append(&control.placeholder().to_string(), 0.0, placeholder);
+        start = i + c.len_utf8();
+    }
+    if start < text.len() {
+        job.append(&text[start..], 0.0, format);
+    }
+}
+
+/// Zigzag along the bottom edge of a rect
+fn squiggle(rect: Rect) -> Vec<Pos2> {
+    const STEP: f32 = 2.0;
+    let bottom = rect.bottom() - 1.0;
+    let steps = (rect.width() / STEP).ceil().max(1.0) as usize;
+    (0..=steps)
+        .map(|i| {
+            let y = if i % 2 == 0 { bottom } else { bottom - STEP };
+            Pos2::new((rect.left() + i as f32 * STEP).min(rect.right()), y)
+        })
+        .collect()
+}
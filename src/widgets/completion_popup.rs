@@ -0,0 +1,83 @@
+use crate::state::completion::CompletionItem;
+use crate::theme::{fonts, palette};
+use egui::{Area, Context, FontId, Frame, Id, Order, Pos2, RichText, ScrollArea};
+
+/// Rows visible before the list scrolls
+const VISIBLE_ROWS: f32 = 10.0;
+
+/// Autocomplete list shown below the caret
+pub struct CompletionPopup<'a> {
+    items: &'a [CompletionItem],
+    selected: usize,
+    position: Pos2,
+}
+
+/// Response from CompletionPopup widget
+#[derive(Default)]
+pub struct CompletionPopupResponse {
+    /// Index of the item that was clicked
+    pub clicked: Option<usize>,
+}
+
+impl<'a> CompletionPopup<'a> {
+    pub fn new(items: &'a [CompletionItem], selected: usize) -> Self {
+        Self {
+            items,
+            selected,
+            position: Pos2::ZERO,
+        }
+    }
+
+    /// Top-left corner of the popup, usually the bottom of the caret
+    pub fn position(mut self, position: Pos2) -> Self {
+        self.position = position;
+        self
+    }
+
+    pub fn show(self, ctx: &Context) -> CompletionPopupResponse {
+        let mut response = CompletionPopupResponse::default();
+        let font_id = FontId::monospace(fonts::BODY);
+        let row_height = ctx.fonts(|f| f.row_height(&font_id)) + 4.0;
+
+        Area::new(Id::new("completion_popup"))
+            .order(Order::Foreground)
+            .fixed_pos(self.position)
+            .show(ctx, |ui| {
+                let colors = palette(ui.visuals());
+                Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.set_min_width(240.0);
+                    ScrollArea::vertical()
+                        .max_height(row_height * VISIBLE_ROWS)
+                        .show(ui, |ui| {
+                            for (i, item) in self.items.iter().enumerate() {
+                                let is_selected = i == self.selected;
+                                let row = ui.horizontal(|ui| {
+                                    let label = ui.selectable_label(
+                                        is_selected,
+                                        RichText::new(&item.label)
+                                            .font(font_id.clone())
+                                            .color(colors.text_primary),
+                                    );
+                                    if let Some(detail) = &item.detail {
+                                        ui.label(
+                                            RichText::new(detail)
+                                                .font(font_id.clone())
+                                                .color(colors.text_secondary),
+                                        );
+                                    }
+                                    label
+                                });
+                                if row.inner.clicked() {
+                                    response.clicked = Some(i);
+                                }
+                                if is_selected {
+                                    row.response.scroll_to_me(None);
+                                }
+                            }
+                        });
+                });
+            });
+
+        response
+    }
+}
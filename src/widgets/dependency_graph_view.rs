@@ -0,0 +1,256 @@
+use crate::cargo::DependencyGraph;
+use crate::theme::{fonts, palette};
+use egui::{Align2, FontId, Pos2, Rect, RichText, Sense, Stroke, Ui, Vec2};
+
+/// Horizontal distance between dependency levels, in graph units
+const COLUMN_WIDTH: f32 = 240.0;
+const ROW_HEIGHT: f32 = 32.0;
+const NODE_SIZE: Vec2 = Vec2::new(200.0, 24.0);
+/// Space around the graph at the default view
+const MARGIN: f32 = 24.0;
+const MIN_ZOOM: f32 = 0.1;
+const MAX_ZOOM: f32 = 4.0;
+/// Below this zoom the labels are too small to read and are left out
+const MIN_LABEL_ZOOM: f32 = 0.35;
+
+/// Pan and zoom of the graph, kept between frames
+#[derive(Clone, Copy, Debug)]
+pub struct GraphViewport {
+    pub offset: Vec2,
+    pub zoom: f32,
+    /// Node whose edges stay highlighted
+    pub selected: Option<usize>,
+}
+
+impl Default for GraphViewport {
+    fn default() -> Self {
+        Self {
+            offset: Vec2::splat(MARGIN),
+            zoom: 1.0,
+            selected: None,
+        }
+    }
+}
+
+/// Crates of the workspace and their dependencies in columns by distance from
+/// the workspace members, with crates in several versions marked
+pub struct DependencyGraphView<'a> {
+    /// None while cargo resolves the graph
+    graph: Option<&'a Result<DependencyGraph, String>>,
+    viewport: &'a mut GraphViewport,
+}
+
+/// Response from DependencyGraphView widget
+#[derive(Default)]
+pub struct DependencyGraphViewResponse {
+    /// Node that was clicked
+    pub clicked: Option<usize>,
+    pub refresh_clicked: bool,
+    pub close_clicked: bool,
+}
+
+impl<'a> DependencyGraphView<'a> {
+    pub fn new(
+        graph: Option<&'a Result<DependencyGraph, String>>,
+        viewport: &'a mut GraphViewport,
+    ) -> Self {
+        Self { graph, viewport }
+    }
+
+    pub fn show(self, ui: &mut Ui) -> DependencyGraphViewResponse {
+        let colors = palette(ui.visuals());
+        let mut response = DependencyGraphViewResponse::default();
+        let graph = match self.graph {
+            Some(Ok(graph)) => Some(graph),
+            _ => None,
+        };
+
+        ui.horizontal(|ui| {
+            ui.label(
+                RichText::new("Dependency Graph")
+                    .color(colors.text_primary)
+                    .strong(),
+            );
+            if let Some(graph) = graph {
+                ui.label(
+                    RichText::new(format!("{} crates", graph.nodes.len()))
+                        .color(colors.text_secondary),
+                );
+                let duplicates = duplicate_names(graph);
+                if duplicates > 0 {
+                    ui.label(
+                        RichText::new(format!("{duplicates} in several versions"))
+                            .color(colors.diagnostic_warning),
+                    );
+                }
+                ui.label(
+                    RichText::new("Drag to pan, Ctrl+scroll to zoom, click to open Cargo.toml")
+                        .color(colors.text_muted),
+                );
+            }
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                response.close_clicked = ui.small_button("✕").on_hover_text("Close").clicked();
+                response.refresh_clicked = ui
+                    .add_enabled(self.graph.is_some(), egui::Button::new("⟳").small())
+                    .on_hover_text("Reload cargo metadata")
+                    .clicked();
+                if ui.small_button("Reset View").clicked() {
+                    *self.viewport = GraphViewport::default();
+                }
+            });
+        });
+        ui.separator();
+
+        let graph = match self.graph {
+            None => {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label(RichText::new("Resolving dependencies...").color(colors.text_muted));
+                });
+                return response;
+            }
+            Some(Err(e)) => {
+                ui.label(RichText::new(e).color(colors.diagnostic_error));
+                return response;
+            }
+            Some(Ok(graph)) => graph,
+        };
+
+        let (rect, canvas) = ui.allocate_exact_size(ui.available_size(), Sense::click_and_drag());
+        let viewport = self.viewport;
+        if canvas.dragged() {
+            viewport.offset += canvas.drag_delta();
+        }
+        if canvas.hovered() {
+            let (zoom_delta, scroll) = ui.input(|i| (i.zoom_delta(), i.smooth_scroll_delta));
+            viewport.offset += scroll;
+            if zoom_delta != 1.0 {
+                // Keep the point under the mouse in place
+                let anchor = canvas.hover_pos().unwrap_or(rect.center()) - rect.min;
+                let zoom = (viewport.zoom * zoom_delta).clamp(MIN_ZOOM, MAX_ZOOM);
+                viewport.offset = anchor - (anchor - viewport.offset) * (zoom / viewport.zoom);
+                viewport.zoom = zoom;
+            }
+        }
+
+        let positions = layout(graph);
+        let node_rect = |i: usize| {
+            let min = rect.min + viewport.offset + positions[i].to_vec2() * viewport.zoom;
+            Rect::from_min_size(min, NODE_SIZE * viewport.zoom)
+        };
+        let hovered = canvas
+            .hover_pos()
+            .and_then(|pos| (0..graph.nodes.len()).find(|&i| node_rect(i).contains(pos)));
+        if canvas.clicked() {
+            viewport.selected = hovered;
+            response.clicked = hovered;
+        }
+        let focus = hovered.or(viewport.selected);
+
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, colors.editor_bg);
+
+        // Edges leave a node on its right and enter the dependency on its left
+        let edge_points = |from: usize, to: usize| {
+            let from = node_rect(from);
+            let to = node_rect(to);
+            [from.right_center(), to.left_center()]
+        };
+        let faint = Stroke::new(1.0, colors.text_muted.gamma_multiply(0.35));
+        for &(from, to) in &graph.edges {
+            if Some(from) != focus && Some(to) != focus {
+                painter.line_segment(edge_points(from, to), faint);
+            }
+        }
+        if let Some(focus) = focus {
+            for &(from, to) in &graph.edges {
+                if from == focus || to == focus {
+                    painter.line_segment(edge_points(from, to), Stroke::new(2.0, colors.accent));
+                }
+            }
+        }
+
+        let font = FontId::proportional(fonts::BODY * viewport.zoom);
+        for (i, node) in graph.nodes.iter().enumerate() {
+            let node_rect = node_rect(i);
+            if !rect.intersects(node_rect) {
+                continue;
+            }
+            let rounding = 3.0 * viewport.zoom;
+            painter.rect_filled(node_rect, rounding, colors.widget_bg);
+            let (width, color) = if Some(i) == focus {
+                (2.0, colors.accent)
+            } else if node.is_duplicate {
+                (1.5, colors.diagnostic_warning)
+            } else if node.is_member {
+                (1.5, colors.text_secondary)
+            } else {
+                (1.0, colors.gutter_border)
+            };
+            painter.rect_stroke(node_rect, rounding, Stroke::new(width, color));
+            if viewport.zoom >= MIN_LABEL_ZOOM {
+                let text_color = if node.is_duplicate {
+                    colors.diagnostic_warning
+                } else {
+                    colors.text_primary
+                };
+                painter.with_clip_rect(node_rect.intersect(rect)).text(
+                    node_rect.left_center() + Vec2::new(6.0 * viewport.zoom, 0.0),
+                    Align2::LEFT_CENTER,
+                    format!("{} {}", node.name, node.version),
+                    font.clone(),
+                    text_color,
+                );
+            }
+        }
+
+        if let Some(index) = hovered {
+            let node = &graph.nodes[index];
+            let dependencies = graph.edges.iter().filter(|e| e.0 == index).count();
+            let dependents = graph.edges.iter().filter(|e| e.1 == index).count();
+            canvas.on_hover_ui_at_pointer(|ui| {
+                ui.label(RichText::new(format!("{} {}", node.name, node.version)).strong());
+                ui.label(format!("{dependencies} dependencies, used by {dependents}"));
+                if node.is_duplicate {
+                    ui.label(
+                        RichText::new("Several versions in the graph")
+                            .color(colors.diagnostic_warning),
+                    );
+                }
+            });
+        }
+
+        response
+    }
+}
+
+/// Top-left corner of each node in graph units: one column per depth, nodes
+/// in graph order down each column
+fn layout(graph: &DependencyGraph) -> Vec<Pos2> {
+    let mut rows = Vec::new();
+    graph
+        .nodes
+        .iter()
+        .map(|node| {
+            if rows.len() <= node.depth {
+                rows.resize(node.depth + 1, 0);
+            }
+            let row = rows[node.depth];
+            rows[node.depth] += 1;
+            Pos2::new(node.depth as f32 * COLUMN_WIDTH, row as f32 * ROW_HEIGHT)
+        })
+        .collect()
+}
+
+/// Number of crates that appear in more than one version
+fn duplicate_names(graph: &DependencyGraph) -> usize {
+    let mut names: Vec<&str> = graph
+        .nodes
+        .iter()
+        .filter(|n| n.is_duplicate)
+        .map(|n| n.name.as_str())
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+    names.len()
+}
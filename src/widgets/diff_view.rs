@@ -0,0 +1,85 @@
+use crate::folder_diff::{DiffLine, LineChange};
+use crate::theme::{fonts, palette};
+use egui::{Align2, FontId, Pos2, ScrollArea, Sense, Ui, Vec2};
+
+/// Unified diff of one file: old and new line numbers, then the line marked
+/// `-` or `+` on a tinted background
+pub struct DiffView<'a> {
+    id_source: &'a str,
+    lines: &'a [DiffLine],
+}
+
+impl<'a> DiffView<'a> {
+    pub fn new(id_source: &'a str, lines: &'a [DiffLine]) -> Self {
+        Self { id_source, lines }
+    }
+
+    pub fn show(self, ui: &mut Ui) {
+        let colors = palette(ui.visuals());
+        let font_id = FontId::monospace(fonts::BODY);
+        let char_width = ui.fonts(|f| f.glyph_width(&font_id, ' '));
+        let row_height = ui.fonts(|f| f.row_height(&font_id));
+
+        let last_line = self
+            .lines
+            .iter()
+            .flat_map(|line| line.old_line.max(line.new_line))
+            .max()
+            .unwrap_or(0);
+        let number_width = (last_line + 1).ilog10() as usize + 1;
+        // Two line numbers, the marker and the gaps between them
+        let prefix_chars = number_width * 2 + 5;
+        let longest = self
+            .lines
+            .iter()
+            .map(|line| line.text.chars().count())
+            .max()
+            .unwrap_or(0);
+        let row_width = ((prefix_chars + longest) as f32 * char_width).max(ui.available_width());
+
+        let number = |line: Option<usize>| match line {
+            Some(line) => format!("{:>number_width$}", line + 1),
+            None => " ".repeat(number_width),
+        };
+
+        ui.scope(|ui| {
+            // Rows touch, so runs of changed lines read as one block
+            ui.spacing_mut().item_spacing = Vec2::ZERO;
+            ScrollArea::both()
+                .id_source(self.id_source)
+                .auto_shrink([false, false])
+                .show_rows(ui, row_height, self.lines.len(), |ui, range| {
+                    for line in &self.lines[range] {
+                        let (rect, _) = ui
+                            .allocate_exact_size(Vec2::new(row_width, row_height), Sense::hover());
+                        let (marker, background) = match line.change {
+                            LineChange::Equal => (' ', None),
+                            LineChange::Insert => ('+', Some(colors.diff_added_bg)),
+                            LineChange::Delete => ('-', Some(colors.diff_removed_bg)),
+                        };
+                        let painter = ui.painter();
+                        if let Some(background) = background {
+                            painter.rect_filled(rect, 0.0, background);
+                        }
+                        painter.text(
+                            Pos2::new(rect.left(), rect.center().y),
+                            Align2::LEFT_CENTER,
+                            format!("{} {} ", number(line.old_line), number(line.new_line)),
+                            font_id.clone(),
+                            colors.line_number,
+                        );
+                        painter.text(
+                            Pos2::new(
+                                rect.left() + (number_width * 2 + 2) as f32 * char_width,
+                                rect.center().y,
+                            ),
+                            Align2::LEFT_CENTER,
+                            format!("{marker}  {}", line.text),
+                            font_id.clone(),
+                            colors.text_primary,
+                        );
+                    }
+                });
+        });
+    }
+}
@@ -0,0 +1,198 @@
+use super::DiffView;
+use crate::file_icons;
+use crate::folder_diff::{DiffLine, DiffNode, DiffStatus, DiffSummary};
+use crate::theme::{fonts, palette, Palette};
+use egui::{CollapsingHeader, Color32, RichText, ScrollArea, Ui};
+use std::path::{Path, PathBuf};
+
+/// Width of the merged tree beside the file diff
+const TREE_WIDTH: f32 = 300.0;
+
+/// Two compared folders: the merged tree with entries colored by status, and
+/// the diff of the selected file beside it
+pub struct FolderDiffView<'a> {
+    left: &'a Path,
+    right: &'a Path,
+    /// None while the folders are compared
+    nodes: Option<&'a [DiffNode]>,
+    changed_only: &'a mut bool,
+    selected: Option<&'a Path>,
+    file_diff: Option<&'a Result<Vec<DiffLine>, String>>,
+}
+
+/// Response from FolderDiffView widget
+#[derive(Default)]
+pub struct FolderDiffViewResponse {
+    /// Relative path of the file that was clicked
+    pub clicked: Option<PathBuf>,
+    pub close_clicked: bool,
+}
+
+impl<'a> FolderDiffView<'a> {
+    pub fn new(
+        left: &'a Path,
+        right: &'a Path,
+        nodes: Option<&'a [DiffNode]>,
+        changed_only: &'a mut bool,
+    ) -> Self {
+        Self {
+            left,
+            right,
+            nodes,
+            changed_only,
+            selected: None,
+            file_diff: None,
+        }
+    }
+
+    /// The selected file (relative path) and its diff
+    pub fn selected(mut self, path: &'a Path, diff: &'a Result<Vec<DiffLine>, String>) -> Self {
+        self.selected = Some(path);
+        self.file_diff = Some(diff);
+        self
+    }
+
+    pub fn show(self, ui: &mut Ui) -> FolderDiffViewResponse {
+        let colors = palette(ui.visuals());
+        let mut response = FolderDiffViewResponse::default();
+
+        ui.horizontal(|ui| {
+            ui.label(
+                RichText::new(format!(
+                    "{}  ↔  {}",
+                    self.left.display(),
+                    self.right.display()
+                ))
+                .color(colors.text_primary)
+                .strong(),
+            );
+            if let Some(nodes) = self.nodes {
+                let summary = DiffSummary::of(nodes);
+                for (count, label, color) in [
+                    (summary.added, "added", colors.diff_added),
+                    (summary.removed, "removed", colors.diff_removed),
+                    (summary.changed, "changed", colors.diff_changed),
+                ] {
+                    ui.label(RichText::new(format!("{count} {label}")).color(color));
+                }
+            }
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                response.close_clicked = ui.small_button("✕").on_hover_text("Close").clicked();
+                ui.checkbox(self.changed_only, "Differences only");
+            });
+        });
+        ui.separator();
+
+        let Some(nodes) = self.nodes else {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Comparing folders...");
+            });
+            return response;
+        };
+
+        ui.horizontal_top(|ui| {
+            ui.vertical(|ui| {
+                ui.set_width(TREE_WIDTH);
+                ScrollArea::vertical()
+                    .id_source("folder_diff_tree")
+                    .auto_shrink([false, false])
+                    .show(ui, |ui| {
+                        if nodes.is_empty() {
+                            ui.label(
+                                RichText::new("Both folders are empty").color(colors.text_muted),
+                            );
+                        }
+                        for node in nodes {
+                            self.show_node(ui, node, colors, &mut response.clicked);
+                        }
+                    });
+            });
+            ui.separator();
+            ui.vertical(|ui| match (self.selected, self.file_diff) {
+                (Some(path), Some(diff)) => {
+                    ui.label(RichText::new(path.display().to_string()).color(colors.text_primary));
+                    match diff {
+                        Ok(lines) if lines.is_empty() => {
+                            ui.label(
+                                RichText::new("Both files are empty").color(colors.text_muted),
+                            );
+                        }
+                        Ok(lines) => DiffView::new("folder_diff_file", lines).show(ui),
+                        Err(error) => {
+                            ui.label(RichText::new(error).color(colors.text_muted));
+                        }
+                    }
+                }
+                _ => {
+                    ui.label(
+                        RichText::new("Select a file to see its changes").color(colors.text_muted),
+                    );
+                }
+            });
+        });
+
+        response
+    }
+
+    fn show_node(
+        &self,
+        ui: &mut Ui,
+        node: &DiffNode,
+        colors: &Palette,
+        clicked: &mut Option<PathBuf>,
+    ) {
+        if *self.changed_only && node.status == DiffStatus::Same {
+            return;
+        }
+        let color = status_color(node.status, colors);
+
+        if node.is_dir {
+            let header = format!("{} {}", file_icons::FOLDER_ICON, node.name);
+            CollapsingHeader::new(RichText::new(header).size(fonts::BODY).color(color))
+                .id_source(&node.path)
+                .default_open(node.status != DiffStatus::Same)
+                .show(ui, |ui| {
+                    for child in &node.children {
+                        self.show_node(ui, child, colors, clicked);
+                    }
+                });
+        } else {
+            let text = format!(
+                "{} {}  {}",
+                file_icons::get_icon(&node.name),
+                node.name,
+                status_letter(node.status)
+            );
+            let is_selected = self.selected == Some(node.path.as_path());
+            if ui
+                .selectable_label(
+                    is_selected,
+                    RichText::new(text).size(fonts::BODY).color(color),
+                )
+                .clicked()
+            {
+                *clicked = Some(node.path.clone());
+            }
+        }
+    }
+}
+
+fn status_color(status: DiffStatus, colors: &Palette) -> Color32 {
+    match status {
+        DiffStatus::Same => colors.text_secondary,
+        DiffStatus::Changed => colors.diff_changed,
+        DiffStatus::Added => colors.diff_added,
+        DiffStatus::Removed => colors.diff_removed,
+    }
+}
+
+/// Marker after a file name, as in source control views
+fn status_letter(status: DiffStatus) -> &'static str {
+    match status {
+        DiffStatus::Same => "",
+        DiffStatus::Changed => "M",
+        DiffStatus::Added => "A",
+        DiffStatus::Removed => "D",
+    }
+}
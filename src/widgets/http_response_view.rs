@@ -0,0 +1,87 @@
+use crate::rest_client::HttpResponse;
+use crate::theme::{fonts, palette};
+use egui::{CollapsingHeader, FontId, Frame, Margin, RichText, ScrollArea, Ui};
+
+/// Result of a request sent from a `.http` file
+pub struct HttpResponseView<'a> {
+    title: &'a str,
+    /// None while the request is in flight
+    result: Option<&'a Result<HttpResponse, String>>,
+}
+
+/// Response from HttpResponseView widget
+#[derive(Default)]
+pub struct HttpResponseViewResponse {
+    pub close_clicked: bool,
+}
+
+impl<'a> HttpResponseView<'a> {
+    pub fn new(title: &'a str, result: Option<&'a Result<HttpResponse, String>>) -> Self {
+        Self { title, result }
+    }
+
+    pub fn show(self, ui: &mut Ui) -> HttpResponseViewResponse {
+        let colors = palette(ui.visuals());
+        let mut response = HttpResponseViewResponse::default();
+        let mono = |text: &str| RichText::new(text).font(FontId::monospace(fonts::BODY));
+
+        Frame::none()
+            .inner_margin(Margin::symmetric(12.0, 8.0))
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(
+                        RichText::new(self.title)
+                            .color(colors.text_secondary)
+                            .strong(),
+                    );
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        response.close_clicked =
+                            ui.small_button("✕").on_hover_text("Close").clicked();
+                    });
+                });
+                ui.separator();
+
+                match self.result {
+                    None => {
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.label("Sending request...");
+                        });
+                    }
+                    Some(Err(error)) => {
+                        ui.label(RichText::new(error).color(colors.diagnostic_error));
+                    }
+                    Some(Ok(http)) => {
+                        let status_color = match http.status_code {
+                            200..=299 => colors.accent,
+                            400..=599 => colors.diagnostic_error,
+                            _ => colors.diagnostic_warning,
+                        };
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new(&http.status).color(status_color).strong());
+                            ui.label(
+                                RichText::new(format!("{} ms", http.elapsed.as_millis()))
+                                    .color(colors.text_secondary),
+                            );
+                        });
+                        CollapsingHeader::new(format!("Headers ({})", http.headers.len()))
+                            .id_source("http_response_headers")
+                            .show(ui, |ui| {
+                                for (name, value) in &http.headers {
+                                    ui.label(mono(&format!("{name}: {value}")));
+                                }
+                            });
+                        ui.separator();
+                        ScrollArea::both()
+                            .id_source("http_response_body")
+                            .auto_shrink([false, false])
+                            .show(ui, |ui| {
+                                ui.label(mono(&http.body).color(colors.text_primary));
+                            });
+                    }
+                }
+            });
+
+        response
+    }
+}
@@ -1,26 +1,125 @@
-use crate::theme::{colors, fonts, layout};
-use egui::{FontId, Pos2, Rect, Response, Sense, Ui, Vec2};
+use super::snap_rect;
+use crate::state::WrapLayout;
+use crate::theme::{fonts, layout, palette, Palette};
+use egui::{Color32, FontId, Pos2, Rect, Sense, Stroke, Ui, Vec2};
 
-/// Custom line numbers gutter widget that renders VSCode-style line numbers
-pub struct LineNumbersGutter {
+/// Column of the gutter a decoration is drawn in. Bookmarks, breakpoints and
+/// diagnostics sit left of the numbers, fold chevrons and change bars right
+/// of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GutterColumn {
+    Bookmark,
+    Breakpoint,
+    Diagnostic,
+    Fold,
+    Change,
+}
+
+impl GutterColumn {
+    /// Columns in the order they are laid out
+    const ALL: [GutterColumn; 5] = [
+        GutterColumn::Bookmark,
+        GutterColumn::Breakpoint,
+        GutterColumn::Diagnostic,
+        GutterColumn::Fold,
+        GutterColumn::Change,
+    ];
+
+    fn width(self) -> f32 {
+        match self {
+            GutterColumn::Change => layout::GUTTER_BAR_WIDTH,
+            _ => layout::GUTTER_DECORATION_WIDTH,
+        }
+    }
+
+    fn before_numbers(self) -> bool {
+        matches!(
+            self,
+            GutterColumn::Bookmark | GutterColumn::Breakpoint | GutterColumn::Diagnostic
+        )
+    }
+}
+
+/// What a decoration draws in its cell
+#[derive(Debug, Clone, PartialEq)]
+#[allow(dead_code)] // Only diagnostics, bookmarks and folds register marks so far
+pub enum GutterMark {
+    /// Thin bar down the line, e.g. for added or modified lines
+    Bar(Color32),
+    /// Filled circle, e.g. a breakpoint
+    Dot(Color32),
+    /// A glyph such as "✖" or "⚠"
+    Icon { glyph: &'static str, color: Color32 },
+    /// Chevron pointing right when folded, down when open
+    Fold { folded: bool },
+}
+
+/// A mark registered by a feature for one line of the gutter
+#[derive(Debug, Clone, PartialEq)]
+pub struct GutterDecoration {
+    /// 1-based line
+    pub line: usize,
+    pub column: GutterColumn,
+    pub mark: GutterMark,
+    /// Shown when the pointer rests on the mark
+    pub tooltip: Option<String>,
+}
+
+impl GutterDecoration {
+    pub fn new(line: usize, column: GutterColumn, mark: GutterMark) -> Self {
+        Self {
+            line,
+            column,
+            mark,
+            tooltip: None,
+        }
+    }
+
+    pub fn tooltip(mut self, text: impl Into<String>) -> Self {
+        self.tooltip = Some(text.into());
+        self
+    }
+}
+
+/// Response from LineNumbersGutter widget
+#[derive(Default)]
+pub struct LineNumbersGutterResponse {
+    /// Line (1-based) and column of the decoration that was clicked
+    pub clicked: Option<(usize, GutterColumn)>,
+}
+
+/// Custom line numbers gutter widget that renders VSCode-style line numbers,
+/// with columns of decorations that features register per line
+pub struct LineNumbersGutter<'a> {
     total_lines: usize,
     current_line: usize,
     scroll_offset_y: f32,
     line_height: f32,
+    font_size: f32,
     visible_height: f32,
+    pixel_snapping: bool,
+    decorations: Vec<GutterDecoration>,
+    /// Columns kept even without decorations, so the numbers don't shift as
+    /// marks come and go
+    reserved: Vec<GutterColumn>,
+    wrap: Option<&'a WrapLayout>,
 }
 
-impl LineNumbersGutter {
+impl<'a> LineNumbersGutter<'a> {
     pub fn new(total_lines: usize) -> Self {
         Self {
             total_lines: total_lines.max(1),
             current_line: 1,
             scroll_offset_y: 0.0,
             line_height: layout::LINE_HEIGHT,
+            font_size: fonts::LINE_NUMBER,
             visible_height: 500.0,
+            pixel_snapping: true,
+            decorations: Vec::new(),
+            reserved: Vec::new(),
+            wrap: None,
         }
     }
-
     pub fn current_line(mut self, line: usize) -> Self {
         self.current_line = line.max(1);
         self
@@ -36,15 +135,58 @@ impl LineNumbersGutter {
         self
     }
 
+    pub fn font_size(mut self, size: f32) -> Self {
+        self.font_size = size;
+        self
+    }
+
     pub fn visible_height(mut self, height: f32) -> Self {
         self.visible_height = height;
         self
     }
 
-    /// Calculate the width needed for line numbers based on digit count
-    fn calculate_width(&self, ui: &Ui) -> f32 {
+    /// Align the current line highlight and numbers to physical pixels
+    pub fn pixel_snapping(mut self, enabled: bool) -> Self {
+        self.pixel_snapping = enabled;
+        self
+    }
+
+    /// Add decorations. A line shows one mark per column: the first one
+    /// registered for it.
+    pub fn decorations(mut self, decorations: impl IntoIterator<Item = GutterDecoration>) -> Self {
+        self.decorations.extend(decorations);
+        self
+    }
+
+    /// Keep a column's space whether or not any line has a mark in it
+    pub fn reserve(mut self, column: GutterColumn) -> Self {
+        if !self.reserved.contains(&column) {
+            self.reserved.push(column);
+        }
+        self
+    }
+
+    /// Visual rows of soft-wrapped lines; rows that continue a line stay blank
+    pub fn wrap(mut self, wrap: Option<&'a WrapLayout>) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    /// Columns shown: the reserved ones and any with a decoration, in layout order
+    fn columns(&self) -> Vec<GutterColumn> {
+        GutterColumn::ALL
+            .into_iter()
+            .filter(|column| {
+                self.reserved.contains(column)
+                    || self.decorations.iter().any(|d| d.column == *column)
+            })
+            .collect()
+    }
+
+    /// Width of the line numbers themselves, based on digit count
+    fn numbers_width(&self, ui: &Ui) -> f32 {
         let max_digits = self.total_lines.to_string().len().max(3);
-        let font_id = FontId::monospace(fonts::LINE_NUMBER);
+        let font_id = FontId::monospace(self.font_size);
         let digit_width = ui.fonts(|f| f.glyph_width(&font_id, '0'));
 
         (max_digits as f32 * digit_width)
@@ -52,31 +194,111 @@ impl LineNumbersGutter {
             + layout::GUTTER_PADDING_RIGHT
     }
 
-    pub fn show(self, ui: &mut Ui) -> Response {
-        let gutter_width = self.calculate_width(ui);
+    /// Horizontal span of each shown column, given the gutter's left edge
+    fn column_spans(
+        columns: &[GutterColumn],
+        left: f32,
+        numbers_width: f32,
+    ) -> Vec<(GutterColumn, f32, f32)> {
+        let mut x = left;
+        let mut spans = Vec::with_capacity(columns.len());
+        for before_numbers in [true, false] {
+            if !before_numbers {
+                x += numbers_width;
+            }
+            for &column in columns
+                .iter()
+                .filter(|c| c.before_numbers() == before_numbers)
+            {
+                spans.push((column, x, x + column.width()));
+                x += column.width();
+            }
+        }
+        spans
+    }
+
+    pub fn show(self, ui: &mut Ui) -> LineNumbersGutterResponse {
+        let colors = palette(ui.visuals());
+        let columns = self.columns();
+        let numbers_width = self.numbers_width(ui);
+        let columns_before: f32 = columns
+            .iter()
+            .filter(|c| c.before_numbers())
+            .map(|c| c.width())
+            .sum();
+        let columns_after: f32 = columns
+            .iter()
+            .filter(|c| !c.before_numbers())
+            .map(|c| c.width())
+            .sum();
+        let gutter_width = columns_before + numbers_width + columns_after;
         let desired_size = Vec2::new(gutter_width, self.visible_height);
-        let (rect, response) = ui.allocate_exact_size(desired_size, Sense::hover());
+        let (rect, response) = ui.allocate_exact_size(desired_size, Sense::click());
+        let spans = Self::column_spans(&columns, rect.left(), numbers_width);
+        let numbers_right = rect.left() + columns_before + numbers_width;
+
+        // The first decoration registered for a line and column wins
+        let decoration_at = |line: usize, column: GutterColumn| {
+            self.decorations
+                .iter()
+                .find(|d| d.line == line && d.column == column)
+        };
+
+        // Line and column of the decoration under the pointer
+        let row_line = |row: usize| match self.wrap {
+            Some(wrap) => {
+                let line_idx = wrap.row_line(row);
+                (wrap.line_rows(line_idx).start == row).then_some(line_idx + 1)
+            }
+            None => Some(row + 1),
+        };
+        let hovered = response.hover_pos().and_then(|pos| {
+            let row = ((pos.y - rect.top() + self.scroll_offset_y) / self.line_height) as usize;
+            let line = row_line(row)?;
+            let &(column, _, _) = spans
+                .iter()
+                .find(|(_, left, right)| (*left..*right).contains(&pos.x))?;
+            decoration_at(line, column)
+        });
+
+        let mut gutter_response = LineNumbersGutterResponse::default();
+        if let Some(decoration) = hovered {
+            if response.clicked() {
+                gutter_response.clicked = Some((decoration.line, decoration.column));
+            }
+            if let Some(tooltip) = &decoration.tooltip {
+                egui::show_tooltip_at_pointer(ui.ctx(), response.id.with("decoration"), |ui| {
+                    ui.label(tooltip);
+                });
+            }
+            ui.ctx().set_cursor_icon(egui::CursorIcon::PointingHand);
+        }
 
         if ui.is_rect_visible(rect) {
             let painter = ui.painter_at(rect);
-            let font_id = FontId::monospace(fonts::LINE_NUMBER);
+            let font_id = FontId::monospace(self.font_size);
 
             // Draw gutter background
-            painter.rect_filled(rect, 0.0, colors::GUTTER_BG);
+            painter.rect_filled(rect, 0.0, colors.gutter_bg);
 
-            // Calculate visible line range
+            // Calculate visible row range; rows are lines unless they wrap
+            let total_rows = self.wrap.map_or(self.total_lines, WrapLayout::row_count);
             let first_visible = (self.scroll_offset_y / self.line_height).floor() as usize;
             let visible_count = (self.visible_height / self.line_height).ceil() as usize + 2;
-            let last_visible = (first_visible + visible_count).min(self.total_lines);
+            let last_visible = (first_visible + visible_count).min(total_rows);
 
             // Draw each visible line number
-            for line_num in (first_visible + 1)..=(last_visible) {
-                if line_num > self.total_lines {
-                    break;
-                }
+            for row in first_visible..last_visible {
+                let (line_num, continuation) = match self.wrap {
+                    Some(wrap) => {
+                        let line_idx = wrap.row_line(row);
+                        (line_idx + 1, wrap.line_rows(line_idx).start != row)
+                    }
+                    None => (row + 1, false),
+                };
 
-                // Calculate Y position for this line
-                let line_top = ((line_num - 1) as f32 * self.line_height) - self.scroll_offset_y;
+                // Calculate Y position for this row
+                let line_top = (row as f32 * self.line_height) - self.scroll_offset_y;
 
                 // Skip if outside visible area
                 if line_top < -self.line_height || line_top > self.visible_height {
@@ -85,27 +307,47 @@ impl LineNumbersGutter {
 
                 let is_current = line_num == self.current_line;
 
-                // Draw current line highlight background
+                // Draw current line highlight background, on every row of a wrapped line
                 if is_current {
-                    let highlight_rect = Rect::from_min_size(
+                    let mut highlight_rect = Rect::from_min_size(
                         Pos2::new(rect.left(), rect.top() + line_top),
                         Vec2::new(gutter_width, self.line_height),
                     );
-                    painter.rect_filled(highlight_rect, 0.0, colors::CURRENT_LINE_BG);
+                    if self.pixel_snapping {
+                        highlight_rect = snap_rect(&painter, highlight_rect);
+                    }
+                    painter.rect_filled(highlight_rect, 0.0, colors.current_line_bg);
+                }
+                if continuation {
+                    continue;
+                }
+
+                for &(column, left, right) in &spans {
+                    if let Some(decoration) = decoration_at(line_num, column) {
+                        let cell = Rect::from_min_max(
+                            Pos2::new(left, rect.top() + line_top),
+                            Pos2::new(right, rect.top() + line_top + self.line_height),
+                        );
+                        let hovered = hovered.is_some_and(|h| std::ptr::eq(h, decoration));
+                        self.paint_mark(&painter, cell, &decoration.mark, hovered, colors);
+                    }
                 }
 
                 // Determine text color
                 let text_color = if is_current {
-                    colors::LINE_NUMBER_ACTIVE
+                    colors.line_number_active
                 } else {
-                    colors::LINE_NUMBER
+                    colors.line_number
                 };
 
                 // Draw line number (right-aligned)
-                let text_pos = Pos2::new(
-                    rect.right() - layout::GUTTER_PADDING_RIGHT,
+                let mut text_pos = Pos2::new(
+                    numbers_right - layout::GUTTER_PADDING_RIGHT,
                     rect.top() + line_top + (self.line_height / 2.0),
                 );
+                if self.pixel_snapping {
+                    text_pos = painter.round_pos_to_pixels(text_pos);
+                }
 
                 painter.text(
                     text_pos,
@@ -122,10 +364,67 @@ impl LineNumbersGutter {
                     Pos2::new(rect.right() - 0.5, rect.top()),
                     Pos2::new(rect.right() - 0.5, rect.bottom()),
                 ],
-                egui::Stroke::new(1.0, colors::GUTTER_BORDER),
+                Stroke::new(1.0, colors.gutter_border),
             );
         }
 
-        response
+        gutter_response
+    }
+
+    fn paint_mark(
+        &self,
+        painter: &egui::Painter,
+        cell: Rect,
+        mark: &GutterMark,
+        hovered: bool,
+        colors: &Palette,
+    ) {
+        match mark {
+            GutterMark::Bar(color) => {
+                let mut bar =
+                    Rect::from_center_size(cell.center(), Vec2::new(3.0, self.line_height - 4.0));
+                if self.pixel_snapping {
+                    bar = snap_rect(painter, bar);
+                }
+                painter.rect_filled(bar, 1.0, *color);
+            }
+            GutterMark::Dot(color) => {
+                let radius = cell.width().min(cell.height()) * 0.3;
+                painter.circle_filled(cell.center(), radius, *color);
+            }
+            GutterMark::Icon { glyph, color } => {
+                painter.text(
+                    cell.center(),
+                    egui::Align2::CENTER_CENTER,
+                    *glyph,
+                    FontId::proportional(fonts::LINE_NUMBER),
+                    *color,
+                );
+            }
+            GutterMark::Fold { folded } => {
+                let color = if hovered {
+                    colors.line_number_active
+                } else {
+                    colors.line_number
+                };
+                let c = cell.center();
+                let s = 3.0;
+                let points = if *folded {
+                    [
+                        c + Vec2::new(-s / 2.0, -s),
+                        c + Vec2::new(s / 2.0, 0.0),
+                        c + Vec2::new(-s / 2.0, s),
+                    ]
+                } else {
+                    [
+                        c + Vec2::new(-s, -s / 2.0),
+                        c + Vec2::new(0.0, s / 2.0),
+                        c + Vec2::new(s, -s / 2.0),
+                    ]
+                };
+                painter.line_segment([points[0], points[1]], Stroke::new(1.5, color));
+                painter.line_segment([points[1], points[2]], Stroke::new(1.5, color));
+            }
+        }
     }
 }
@@ -0,0 +1,144 @@
+use crate::log_file::LogFile;
+use crate::theme::{fonts, palette};
+use egui::text::{LayoutJob, TextFormat};
+use egui::{Color32, FontId, RichText, ScrollArea, TextEdit, Ui};
+
+/// Read-only viewer for a large log file: a toolbar with the filter and the
+/// follow toggle above the lines in view
+pub struct LogView<'a> {
+    log: &'a LogFile,
+    filter_text: &'a mut String,
+    filter_error: Option<&'a str>,
+    follow: &'a mut bool,
+}
+
+/// Response from LogView widget
+#[derive(Default)]
+pub struct LogViewResponse {
+    pub filter_changed: bool,
+    pub close_clicked: bool,
+}
+
+impl<'a> LogView<'a> {
+    pub fn new(log: &'a LogFile, filter_text: &'a mut String, follow: &'a mut bool) -> Self {
+        Self {
+            log,
+            filter_text,
+            filter_error: None,
+            follow,
+        }
+    }
+
+    /// Why the filter text couldn't be used
+    pub fn filter_error(mut self, error: Option<&'a str>) -> Self {
+        self.filter_error = error;
+        self
+    }
+
+    pub fn show(self, ui: &mut Ui) -> LogViewResponse {
+        let colors = palette(ui.visuals());
+        let mut response = LogViewResponse::default();
+        let font_id = FontId::monospace(fonts::BODY);
+
+        ui.horizontal(|ui| {
+            let name = self
+                .log
+                .path()
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy();
+            ui.label(RichText::new(name).color(colors.text_primary).strong());
+            let mut details = format!(
+                "{:.1} MB, {} lines",
+                self.log.len() as f64 / (1 << 20) as f64,
+                self.log.line_count()
+            );
+            if self.log.filter().is_some() {
+                details.push_str(&format!(", {} matching", self.log.visible_count()));
+            }
+            if !self.log.is_scanned() {
+                details.push_str(", scanning...");
+            }
+            ui.label(RichText::new(details).color(colors.text_secondary));
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                response.close_clicked = ui.small_button("✕").on_hover_text("Close").clicked();
+                ui.checkbox(self.follow, "Follow")
+                    .on_hover_text("Scroll to new lines as the file grows");
+                let mut filter = TextEdit::singleline(self.filter_text)
+                    .hint_text("Filter (regex)")
+                    .desired_width(240.0);
+                if self.filter_error.is_some() {
+                    filter = filter.text_color(colors.diagnostic_error);
+                }
+                let filter = ui.add(filter);
+                if let Some(error) = self.filter_error {
+                    filter.clone().on_hover_text(error);
+                }
+                response.filter_changed = filter.changed();
+            });
+        });
+        ui.separator();
+
+        let row_height = ui.fonts(|f| f.row_height(&font_id));
+        let rows = self.log.visible_count() as usize;
+        let number_width = self.log.line_count().max(1).ilog10() as usize + 1;
+        ScrollArea::both()
+            .id_source("log_view")
+            .auto_shrink([false, false])
+            .stick_to_bottom(*self.follow)
+            .show_rows(ui, row_height, rows, |ui, range| {
+                for index in range {
+                    let line = self.log.visible_line(index as u64);
+                    let text = self.log.line(line);
+                    let mut job = LayoutJob::default();
+                    job.append(
+                        &format!("{:>number_width$}  ", line + 1),
+                        0.0,
+                        TextFormat::simple(font_id.clone(), colors.line_number),
+                    );
+                    append_highlighted(
+                        &mut job,
+                        &text,
+                        self.log.filter(),
+                        &font_id,
+                        colors.text_primary,
+                        colors.find_match_bg,
+                    );
+                    ui.label(job);
+                }
+            });
+
+        response
+    }
+}
+
+/// Append a line with the matches of the filter highlighted
+fn append_highlighted(
+    job: &mut LayoutJob,
+    text: &str,
+    filter: Option<&regex::bytes::Regex>,
+    font_id: &FontId,
+    color: Color32,
+    match_background: Color32,
+) {
+    let plain = TextFormat::simple(font_id.clone(), color);
+    let highlighted = TextFormat {
+        background: match_background,
+        ..plain.clone()
+    };
+    let mut last = 0;
+    for found in filter
+        .into_iter()
+        .flat_map(|r| r.find_iter(text.as_bytes()))
+    {
+        // Matches splitting a character can't be highlighted on their own
+        if !text.is_char_boundary(found.start()) || !text.is_char_boundary(found.end()) {
+            continue;
+        }
+        job.append(&text[last..found.start()], 0.0, plain.clone());
+        job.append(&text[found.range()], 0.0, highlighted.clone());
+        last = found.end();
+    }
+    job.append(&text[last..], 0.0, plain);
+}
@@ -0,0 +1,163 @@
+use crate::theme::{fonts, palette};
+use egui::{FontId, Frame, Id, Margin, RichText, ScrollArea, Sense, Ui, Vec2};
+
+/// Read-only rendering of a markdown document.
+///
+/// Only block structure is rendered (headings, lists, quotes, code blocks, rules);
+/// inline markup is shown as written.
+pub struct MarkdownPreview<'a> {
+    id: Id,
+    source: &'a str,
+    scroll_to: Option<f32>,
+}
+
+/// Response from MarkdownPreview widget
+pub struct MarkdownPreviewResponse {
+    /// Relative scroll position, 0.0 at the top and 1.0 at the bottom
+    pub scroll_fraction: f32,
+}
+
+impl<'a> MarkdownPreview<'a> {
+    pub fn new(id: Id, source: &'a str) -> Self {
+        Self {
+            id,
+            source,
+            scroll_to: None,
+        }
+    }
+
+    /// Scroll to a relative position (0.0..=1.0) this frame
+    pub fn scroll_to(mut self, fraction: Option<f32>) -> Self {
+        self.scroll_to = fraction;
+        self
+    }
+
+    pub fn show(self, ui: &mut Ui) -> MarkdownPreviewResponse {
+        // The scrollable height is only known after a frame has been laid out
+        let max_scroll_id = self.id.with("max_scroll");
+        let max_scroll = ui.data(|d| d.get_temp::<f32>(max_scroll_id).unwrap_or(0.0));
+
+        let mut scroll_area = ScrollArea::vertical()
+            .id_source(self.id)
+            .auto_shrink([false, false]);
+        if let Some(fraction) = self.scroll_to {
+            scroll_area = scroll_area.vertical_scroll_offset(fraction * max_scroll);
+        }
+
+        let output = scroll_area.show(ui, |ui| {
+            Frame::none()
+                .inner_margin(Margin::symmetric(16.0, 12.0))
+                .show(ui, |ui| self.render_blocks(ui));
+        });
+
+        let max_scroll = (output.content_size.y - output.inner_rect.height()).max(0.0);
+        ui.data_mut(|d| d.insert_temp(max_scroll_id, max_scroll));
+
+        MarkdownPreviewResponse {
+            scroll_fraction: scroll_fraction(output.state.offset.y, max_scroll),
+        }
+    }
+
+    /// Render without a scroll area, e.g. inside a tooltip
+    pub fn show_inline(self, ui: &mut Ui) {
+        self.render_blocks(ui);
+    }
+
+    fn render_blocks(&self, ui: &mut Ui) {
+        let colors = palette(ui.visuals());
+        let mut lines = self.source.lines();
+
+        while let Some(line) = lines.next() {
+            let trimmed = line.trim_start();
+
+            if let Some(fence) = ["```", "~~~"].into_iter().find(|f| trimmed.starts_with(f)) {
+                let code: Vec<&str> = lines
+                    .by_ref()
+                    .take_while(|l| !l.trim_start().starts_with(fence))
+                    .collect();
+                Frame::none()
+                    .fill(colors.gutter_bg)
+                    .rounding(3.0)
+                    .inner_margin(Margin::same(8.0))
+                    .show(ui, |ui| {
+                        ui.set_width(ui.available_width());
+                        ui.label(
+                            RichText::new(code.join("\n"))
+                                .font(FontId::monospace(fonts::BODY))
+                                .color(colors.text_primary),
+                        );
+                    });
+                ui.add_space(6.0);
+            } else if let Some((level, text)) = heading(trimmed) {
+                let size = fonts::HEADING + 2.0 * (3usize.saturating_sub(level)) as f32;
+                ui.add_space(8.0);
+                ui.label(
+                    RichText::new(text)
+                        .size(size)
+                        .strong()
+                        .color(colors.text_primary),
+                );
+                if level <= 2 {
+                    ui.separator();
+                }
+            } else if is_rule(trimmed) {
+                ui.separator();
+            } else if let Some(text) = trimmed.strip_prefix('>') {
+                ui.horizontal_wrapped(|ui| {
+                    let (bar, _) = ui.allocate_exact_size(
+                        Vec2::new(3.0, ui.text_style_height(&egui::TextStyle::Body)),
+                        Sense::hover(),
+                    );
+                    ui.painter().rect_filled(bar, 0.0, colors.accent);
+                    ui.add_space(8.0);
+                    ui.label(RichText::new(text.trim()).color(colors.text_secondary));
+                });
+            } else if let Some(text) = list_item(trimmed) {
+                let indent = (line.len() - trimmed.len()) as f32 * 4.0;
+                ui.horizontal_wrapped(|ui| {
+                    ui.add_space(8.0 + indent);
+                    ui.label(RichText::new("•").color(colors.text_secondary));
+                    ui.label(RichText::new(text).color(colors.text_primary));
+                });
+            } else if trimmed.is_empty() {
+                ui.add_space(fonts::BODY * 0.5);
+            } else {
+                ui.label(RichText::new(trimmed).color(colors.text_primary));
+            }
+        }
+    }
+}
+
+/// Scroll offset relative to the scrollable range
+pub fn scroll_fraction(offset: f32, max_scroll: f32) -> f32 {
+    if max_scroll > 0.0 {
+        (offset / max_scroll).clamp(0.0, 1.0)
+    } else {
+        0.0
+    }
+}
+
+/// `# Title` -> (1, "Title")
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    let rest = &line[level..];
+    ((1..=6).contains(&level) && (rest.is_empty() || rest.starts_with(' ')))
+        .then(|| (level, rest.trim()))
+}
+
+/// `---`, `***` or `___`, optionally with spaces in between
+fn is_rule(line: &str) -> bool {
+    let marks: Vec<char> = line.chars().filter(|c| !c.is_whitespace()).collect();
+    marks.len() >= 3 && matches!(marks[0], '-' | '*' | '_') && marks.iter().all(|c| *c == marks[0])
+}
+
+/// Text of a `-`, `*`, `+` or `1.` list item
+fn list_item(line: &str) -> Option<&str> {
+    if let Some(text) = ["- ", "* ", "+ "].iter().find_map(|m| line.strip_prefix(m)) {
+        return Some(text);
+    }
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    (digits > 0)
+        .then(|| line[digits..].strip_prefix(". "))
+        .flatten()
+}
@@ -1,24 +1,109 @@
-use crate::theme::{colors, layout};
-use egui::{Pos2, Rect, Sense, Stroke, Ui, Vec2};
+use super::LineStyle;
+use crate::theme::{layout, palette};
+use egui::{Color32, Pos2, Rect, Sense, Stroke, Ui, Vec2};
+use ropey::Rope;
+use std::sync::Arc;
+
+/// Longest run of code drawn for a line, in characters
+const MAX_LINE_CHARS: usize = 80;
+
+/// Condensed shape of one line
+#[derive(Clone, Copy)]
+struct LineSummary {
+    /// Leading whitespace in characters
+    indent: usize,
+    /// Characters between the leading and trailing whitespace, capped at
+    /// `MAX_LINE_CHARS`
+    len: usize,
+    /// Syntax color covering most of the line
+    color: Option<Color32>,
+}
+
+/// Line summaries kept between frames until the text or its syntax colors
+/// change, so a frame only draws instead of walking the whole buffer
+#[derive(Default)]
+pub struct MinimapCache {
+    /// Buffer revision and syntax colors the summaries were made from
+    key: Option<(u64, Option<Arc<Vec<LineStyle>>>)>,
+    lines: Vec<LineSummary>,
+}
+
+impl MinimapCache {
+    fn update(&mut self, buffer: &Rope, revision: u64, styles: Option<&Arc<Vec<LineStyle>>>) {
+        let current = self.key.as_ref().is_some_and(|(r, s)| {
+            *r == revision
+                && match (s, styles) {
+                    (Some(s), Some(styles)) => Arc::ptr_eq(s, styles),
+                    (s, styles) => s.is_none() && styles.is_none(),
+                }
+        });
+        if current {
+            return;
+        }
+        self.lines.clear();
+        self.lines
+            .extend(buffer.lines().enumerate().map(|(line_idx, line)| {
+                let indent = line.chars().take_while(|c| c.is_whitespace()).count();
+                let trailing = line
+                    .chars_at(line.len_chars())
+                    .reversed()
+                    .take_while(|c| c.is_whitespace())
+                    .count();
+                let color = styles.and_then(|s| s.get(line_idx)).and_then(|style| {
+                    style
+                        .iter()
+                        .max_by_key(|(range, _)| range.len())
+                        .map(|(_, color)| *color)
+                });
+                LineSummary {
+                    indent,
+                    len: line
+                        .len_chars()
+                        .saturating_sub(indent + trailing)
+                        .min(MAX_LINE_CHARS),
+                    color,
+                }
+            }));
+        self.key = Some((revision, styles.cloned()));
+    }
+}
 
 /// Minimap widget showing a condensed code overview
 pub struct Minimap<'a> {
-    text: &'a str,
+    buffer: &'a Rope,
+    revision: u64,
+    cache: &'a mut MinimapCache,
+    line_styles: Option<&'a Arc<Vec<LineStyle>>>,
     total_lines: usize,
     visible_lines: (usize, usize),
     current_line: usize,
+    /// Zero-based lines of find matches, and of the current one
+    match_lines: &'a [usize],
+    current_match_line: Option<usize>,
 }
 
 impl<'a> Minimap<'a> {
-    pub fn new(text: &'a str, total_lines: usize) -> Self {
+    /// `revision` identifies the buffer's content for `cache`
+    pub fn new(buffer: &'a Rope, revision: u64, cache: &'a mut MinimapCache) -> Self {
         Self {
-            text,
-            total_lines: total_lines.max(1),
+            buffer,
+            revision,
+            cache,
+            line_styles: None,
+            total_lines: buffer.len_lines().max(1),
             visible_lines: (1, 50),
             current_line: 1,
+            match_lines: &[],
+            current_match_line: None,
         }
     }
 
+    /// Syntax colors to tint the lines with
+    pub fn line_styles(mut self, styles: &'a Arc<Vec<LineStyle>>) -> Self {
+        self.line_styles = Some(styles);
+        self
+    }
+
     pub fn visible_lines(mut self, range: (usize, usize)) -> Self {
         self.visible_lines = range;
         self
@@ -29,7 +114,16 @@ impl<'a> Minimap<'a> {
         self
     }
 
+    /// Mark the lines of find matches along the right edge, the current one
+    /// across the whole width
+    pub fn find_matches(mut self, lines: &'a [usize], current: Option<usize>) -> Self {
+        self.match_lines = lines;
+        self.current_match_line = current;
+        self
+    }
+
     pub fn show(self, ui: &mut Ui) -> MinimapResponse {
+        let colors = palette(ui.visuals());
         let available_height = ui.available_height();
         let desired_size = Vec2::new(layout::MINIMAP_WIDTH, available_height);
         let (rect, response) = ui.allocate_exact_size(desired_size, Sense::click());
@@ -40,7 +134,7 @@ impl<'a> Minimap<'a> {
             let painter = ui.painter_at(rect);
 
             // Draw background
-            painter.rect_filled(rect, 0.0, colors::MINIMAP_BG);
+            painter.rect_filled(rect, 0.0, colors.minimap_bg);
 
             // Calculate scale to fit all lines
             let scale = if self.total_lines > 0 {
@@ -60,7 +154,7 @@ impl<'a> Minimap<'a> {
                 Pos2::new(rect.left(), viewport_top),
                 Vec2::new(layout::MINIMAP_WIDTH, viewport_height),
             );
-            painter.rect_filled(viewport_rect, 2.0, colors::MINIMAP_VIEWPORT);
+            painter.rect_filled(viewport_rect, 2.0, colors.minimap_viewport);
 
             // Draw current line indicator
             let current_y =
@@ -69,34 +163,65 @@ impl<'a> Minimap<'a> {
                 Pos2::new(rect.left(), current_y),
                 Vec2::new(layout::MINIMAP_WIDTH, line_height.max(2.0)),
             );
-            painter.rect_filled(current_line_rect, 0.0, colors::CURRENT_LINE_BG);
+            painter.rect_filled(current_line_rect, 0.0, colors.current_line_bg);
 
             // Draw condensed code representation
-            for (line_idx, line) in self.text.lines().enumerate() {
-                let y = rect.top() + (line_idx as f32 * line_height);
-
-                if y > rect.bottom() {
-                    break;
+            self.cache
+                .update(self.buffer, self.revision, self.line_styles);
+            let summaries = &self.cache.lines;
+            // Only the lines that fit are drawn, and one per pixel row when
+            // the whole document is squeezed in: the longest of the lines sharing it
+            let visible = ((rect.height() / line_height).ceil() as usize).min(summaries.len());
+            let lines_per_row = (1.0 / line_height).ceil().max(1.0) as usize;
+            for first in (0..visible).step_by(lines_per_row) {
+                let group = &summaries[first..(first + lines_per_row).min(visible)];
+                let Some(line) = group.iter().max_by_key(|line| line.len) else {
+                    continue;
+                };
+                if line.len == 0 {
+                    continue;
                 }
+                let y = rect.top() + (first as f32 * line_height);
+                let height = line_height * group.len() as f32;
+                let x_start =
+                    rect.left() + 4.0 + (line.indent as f32 * layout::MINIMAP_CHAR_WIDTH * 0.5);
+                let x_end = x_start + (line.len as f32 * layout::MINIMAP_CHAR_WIDTH);
+                let color = line
+                    .color
+                    .map_or(colors.minimap_code, |c| c.gamma_multiply(0.8));
+
+                painter.line_segment(
+                    [
+                        Pos2::new(x_start, y + height * 0.5),
+                        Pos2::new(x_end.min(rect.right() - 4.0), y + height * 0.5),
+                    ],
+                    Stroke::new(height * 0.6, color),
+                );
+            }
 
-                // Calculate indent and content length
-                let indent = line.chars().take_while(|c| c.is_whitespace()).count();
-                let trimmed = line.trim();
-                let content_len = trimmed.len().min(80);
-
-                if content_len > 0 {
-                    let x_start =
-                        rect.left() + 4.0 + (indent as f32 * layout::MINIMAP_CHAR_WIDTH * 0.5);
-                    let x_end = x_start + (content_len as f32 * layout::MINIMAP_CHAR_WIDTH);
-
-                    painter.line_segment(
-                        [
-                            Pos2::new(x_start, y + line_height * 0.5),
-                            Pos2::new(x_end.min(rect.right() - 4.0), y + line_height * 0.5),
-                        ],
-                        Stroke::new(line_height * 0.6, colors::MINIMAP_CODE),
-                    );
+            // Find match ticks, one per pixel row
+            let tick_height = line_height.max(2.0);
+            let mut last_row = None;
+            for &line_idx in self.match_lines {
+                let y = rect.top() + line_idx as f32 * line_height;
+                let row = y as i32;
+                if last_row == Some(row) {
+                    continue;
                 }
+                last_row = Some(row);
+                let tick = Rect::from_min_size(
+                    Pos2::new(rect.right() - 6.0, y),
+                    Vec2::new(6.0, tick_height),
+                );
+                painter.rect_filled(tick, 0.0, colors.minimap_find_match);
+            }
+            if let Some(line_idx) = self.current_match_line {
+                let y = rect.top() + line_idx as f32 * line_height;
+                let current = Rect::from_min_size(
+                    Pos2::new(rect.left(), y),
+                    Vec2::new(layout::MINIMAP_WIDTH, tick_height),
+                );
+                painter.rect_filled(current, 0.0, colors.minimap_find_match.gamma_multiply(0.6));
             }
 
             // Handle click to navigate
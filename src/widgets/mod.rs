@@ -1,11 +1,90 @@
 mod activity_bar;
+mod bookmarks_panel;
+mod breadcrumbs;
+mod cargo_panel;
+mod code_editor;
+mod completion_popup;
+mod dependency_graph_view;
+mod diff_view;
+mod folder_diff_view;
+mod http_response_view;
 mod line_numbers;
+mod log_view;
+mod markdown_preview;
 mod minimap;
+mod outline_panel;
+mod output_panel;
+mod query_results;
+mod quick_pick;
+mod references_panel;
+mod regex_playground;
+mod source_control_panel;
 pub mod status_bar;
 mod tab_bar;
+mod table_view;
 
 pub use activity_bar::{ActivityBar, ActivityItem};
-pub use line_numbers::LineNumbersGutter;
-pub use minimap::Minimap;
+pub use bookmarks_panel::{Bookmark, BookmarksPanel};
+pub use breadcrumbs::{Breadcrumbs, PathSegment};
+pub use cargo_panel::CargoPanel;
+pub use code_editor::{CodeEditor, CodeLens, LineStyle, RemoteCaret, TextHighlight, Underline};
+pub use completion_popup::CompletionPopup;
+pub use dependency_graph_view::{DependencyGraphView, GraphViewport};
+pub use diff_view::DiffView;
+pub use folder_diff_view::FolderDiffView;
+pub use http_response_view::HttpResponseView;
+pub use line_numbers::{GutterColumn, GutterDecoration, GutterMark, LineNumbersGutter};
+pub use log_view::LogView;
+pub use markdown_preview::{scroll_fraction, MarkdownPreview};
+pub use minimap::{Minimap, MinimapCache};
+pub use outline_panel::OutlinePanel;
+pub use output_panel::{OutputLink, OutputPanel};
+pub use query_results::QueryResults;
+pub use quick_pick::{QuickPick, QuickPickItem};
+pub use references_panel::{Reference, ReferencesPanel};
+pub use regex_playground::{RegexFlags, RegexPlayground};
+pub use source_control_panel::SourceControlPanel;
 pub use status_bar::{StatusBar, StatusBarInfo};
 pub use tab_bar::{Tab, TabBar};
+pub use table_view::TableView;
+
+use egui::{Painter, Rect, RichText, Sense, Ui};
+
+/// Round a rect to physical pixels so fills don't blur across pixel edges
+pub(crate) fn snap_rect(painter: &Painter, rect: Rect) -> Rect {
+    Rect::from_min_max(
+        painter.round_pos_to_pixels(rect.min),
+        painter.round_pos_to_pixels(rect.max),
+    )
+}
+
+/// Center a thin vertical line on a physical pixel column
+pub(crate) fn snap_line_x(painter: &Painter, x: f32) -> f32 {
+    let half_pixel = 0.5 / painter.ctx().pixels_per_point();
+    painter.round_to_pixel(x - half_pixel) + half_pixel
+}
+
+/// Title of a bottom panel that can move to a window of its own. Returns true
+/// when the title is dragged and dropped outside of `panel`.
+pub(crate) fn floating_panel_title(ui: &mut Ui, title: RichText, panel: Rect) -> bool {
+    let response = ui.add(egui::Label::new(title).sense(Sense::drag()));
+    if response.dragged() {
+        ui.ctx().set_cursor_icon(egui::CursorIcon::Grabbing);
+    } else if response.hovered() {
+        ui.ctx().set_cursor_icon(egui::CursorIcon::Grab);
+    }
+    response.drag_released()
+        && ui
+            .input(|i| i.pointer.interact_pos())
+            .is_some_and(|pos| !panel.contains(pos))
+}
+
+/// Button in a panel header that moves the panel to a window or docks it back
+pub(crate) fn floating_panel_button(ui: &mut Ui, floating: bool) -> bool {
+    let (icon, tip) = if floating {
+        ("⇲", "Dock Panel")
+    } else {
+        ("⧉", "Move Panel to Window")
+    };
+    ui.small_button(icon).on_hover_text(tip).clicked()
+}
@@ -0,0 +1,72 @@
+use crate::state::outline::OutlineSymbol;
+use crate::theme::{fonts, layout, palette};
+use egui::{Frame, Margin, RichText, ScrollArea, Ui};
+
+/// Declarations of the active file, indented by nesting, in the sidebar
+pub struct OutlinePanel<'a> {
+    symbols: &'a [OutlineSymbol],
+    /// Symbol the cursor is in
+    current: Option<usize>,
+}
+
+/// Response from OutlinePanel widget
+#[derive(Default)]
+pub struct OutlinePanelResponse {
+    /// Index of the symbol that was clicked
+    pub clicked: Option<usize>,
+}
+
+impl<'a> OutlinePanel<'a> {
+    pub fn new(symbols: &'a [OutlineSymbol]) -> Self {
+        Self {
+            symbols,
+            current: None,
+        }
+    }
+
+    pub fn current(mut self, current: Option<usize>) -> Self {
+        self.current = current;
+        self
+    }
+
+    pub fn show(self, ui: &mut Ui) -> OutlinePanelResponse {
+        let colors = palette(ui.visuals());
+        let mut response = OutlinePanelResponse::default();
+
+        Frame::none()
+            .inner_margin(Margin::symmetric(12.0, 8.0))
+            .show(ui, |ui| {
+                ui.label(
+                    RichText::new("OUTLINE")
+                        .size(fonts::EXPLORER_HEADER)
+                        .color(colors.text_muted)
+                        .strong(),
+                );
+            });
+
+        ScrollArea::vertical()
+            .id_source("outline_panel")
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                if self.symbols.is_empty() {
+                    ui.horizontal(|ui| {
+                        ui.add_space(12.0);
+                        ui.label(RichText::new("No symbols found").color(colors.text_muted));
+                    });
+                }
+                for (i, symbol) in self.symbols.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.add_space(12.0 + symbol.depth as f32 * layout::INDENT_SIZE);
+                        let text = RichText::new(format!("{} {}", symbol.kind.icon(), symbol.name))
+                            .size(fonts::BODY)
+                            .color(colors.text_primary);
+                        if ui.selectable_label(self.current == Some(i), text).clicked() {
+                            response.clicked = Some(i);
+                        }
+                    });
+                }
+            });
+
+        response
+    }
+}
@@ -0,0 +1,171 @@
+use crate::ansi::{self, AnsiStyle};
+use crate::theme::{fonts, palette, Palette};
+use egui::{FontId, Label, RichText, ScrollArea, Sense, Ui};
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// Read-only log of messages from editor features (servers, tools), with ANSI
+/// colors and clickable URLs and `file:line:col` locations
+pub struct OutputPanel<'a> {
+    lines: &'a [String],
+    /// In a window of its own rather than docked at the bottom
+    floating: bool,
+}
+
+/// Target of a link in the output
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OutputLink {
+    Url(String),
+    /// File path as written in the output, with a one-based line and column
+    Location {
+        path: String,
+        line: usize,
+        column: usize,
+    },
+}
+
+/// Response from OutputPanel widget
+#[derive(Default)]
+pub struct OutputPanelResponse {
+    pub clear_clicked: bool,
+    pub close_clicked: bool,
+    /// Move to a window or dock back
+    pub float_toggled: bool,
+    pub link_clicked: Option<OutputLink>,
+}
+
+impl<'a> OutputPanel<'a> {
+    pub fn new(lines: &'a [String]) -> Self {
+        Self {
+            lines,
+            floating: false,
+        }
+    }
+
+    pub fn floating(mut self, floating: bool) -> Self {
+        self.floating = floating;
+        self
+    }
+
+    pub fn show(self, ui: &mut Ui) -> OutputPanelResponse {
+        let colors = palette(ui.visuals());
+        let mut response = OutputPanelResponse::default();
+        let panel = ui.max_rect();
+
+        ui.horizontal(|ui| {
+            let title = RichText::new("OUTPUT")
+                .size(fonts::EXPLORER_HEADER)
+                .color(colors.text_secondary)
+                .strong();
+            let dragged_out = super::floating_panel_title(ui, title, panel);
+            response.float_toggled = dragged_out && !self.floating;
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                response.close_clicked = ui.small_button("✕").on_hover_text("Close").clicked();
+                response.float_toggled |= super::floating_panel_button(ui, self.floating);
+                response.clear_clicked =
+                    ui.small_button("🗑").on_hover_text("Clear Output").clicked();
+            });
+        });
+
+        ScrollArea::both()
+            .auto_shrink([false, false])
+            .stick_to_bottom(true)
+            .show(ui, |ui| {
+                for line in self.lines {
+                    ui.horizontal(|ui| {
+                        ui.spacing_mut().item_spacing.x = 0.0;
+                        for run in ansi::parse(line) {
+                            if let Some(link) = show_run(ui, &run.text, &run.style, colors) {
+                                response.link_clicked = Some(link);
+                            }
+                        }
+                    });
+                }
+            });
+
+        response
+    }
+}
+
+/// URLs, and paths with a file extension followed by `:line` or `:line:col`
+fn link_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(
+            r#"(?P<url>https?://[^\s<>"'`)\]]+)|(?P<path>(?:[A-Za-z]:)?[\w./\\-]*\w\.\w+):(?P<line>\d+)(?::(?P<column>\d+))?"#,
+        )
+        .expect("link pattern is valid")
+    })
+}
+
+/// Show one styled run, with the links in it clickable. Returns the clicked link.
+fn show_run(ui: &mut Ui, text: &str, style: &AnsiStyle, colors: &Palette) -> Option<OutputLink> {
+    let mut clicked = None;
+
+    // The whole run is one link when the program marked it as a hyperlink
+    if let Some(url) = &style.link {
+        if link_label(ui, text, style, colors).clicked() {
+            clicked = Some(OutputLink::Url(url.clone()));
+        }
+        return clicked;
+    }
+
+    let mut last = 0;
+    for captures in link_pattern().captures_iter(text) {
+        let found = captures.get(0).expect("group 0 is the whole match");
+        plain_label(ui, &text[last..found.start()], style, colors);
+        last = found.end();
+
+        let link = match captures.name("url") {
+            Some(url) => OutputLink::Url(url.as_str().to_string()),
+            None => OutputLink::Location {
+                path: captures["path"].to_string(),
+                line: captures["line"].parse().unwrap_or(1),
+                column: captures
+                    .name("column")
+                    .and_then(|c| c.as_str().parse().ok())
+                    .unwrap_or(1),
+            },
+        };
+        if link_label(ui, found.as_str(), style, colors).clicked() {
+            clicked = Some(link);
+        }
+    }
+    plain_label(ui, &text[last..], style, colors);
+    clicked
+}
+
+fn rich_text(ui: &Ui, text: &str, style: &AnsiStyle, colors: &Palette) -> RichText {
+    let default_color = if style.bold {
+        ui.visuals().strong_text_color()
+    } else {
+        colors.text_primary
+    };
+    let mut rich = RichText::new(text)
+        .font(FontId::monospace(fonts::BODY))
+        .color(style.foreground.unwrap_or(default_color));
+    if let Some(background) = style.background {
+        rich = rich.background_color(background);
+    }
+    if style.italic {
+        rich = rich.italics();
+    }
+    if style.underline {
+        rich = rich.underline();
+    }
+    rich
+}
+
+fn plain_label(ui: &mut Ui, text: &str, style: &AnsiStyle, colors: &Palette) {
+    if !text.is_empty() {
+        ui.label(rich_text(ui, text, style, colors));
+    }
+}
+
+fn link_label(ui: &mut Ui, text: &str, style: &AnsiStyle, colors: &Palette) -> egui::Response {
+    let rich = rich_text(ui, text, style, colors)
+        .color(colors.accent)
+        .underline();
+    ui.add(Label::new(rich).sense(Sense::click()))
+        .on_hover_cursor(egui::CursorIcon::PointingHand)
+}
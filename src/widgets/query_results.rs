@@ -0,0 +1,80 @@
+use super::TableView;
+use crate::sql::QueryResult;
+use crate::theme::{fonts, palette};
+use egui::{RichText, Ui};
+
+/// Result of a query run from a `.sql` file
+pub struct QueryResults<'a> {
+    database: &'a str,
+    /// None while the query runs
+    result: Option<&'a Result<QueryResult, String>>,
+}
+
+/// Response from QueryResults widget
+#[derive(Default)]
+pub struct QueryResultsResponse {
+    pub close_clicked: bool,
+}
+
+impl<'a> QueryResults<'a> {
+    pub fn new(database: &'a str, result: Option<&'a Result<QueryResult, String>>) -> Self {
+        Self { database, result }
+    }
+
+    pub fn show(self, ui: &mut Ui) -> QueryResultsResponse {
+        let colors = palette(ui.visuals());
+        let mut response = QueryResultsResponse::default();
+
+        ui.horizontal(|ui| {
+            ui.label(
+                RichText::new("QUERY RESULTS")
+                    .size(fonts::EXPLORER_HEADER)
+                    .color(colors.text_secondary)
+                    .strong(),
+            );
+            ui.label(
+                RichText::new(self.database)
+                    .size(fonts::EXPLORER_HEADER)
+                    .color(colors.text_secondary),
+            );
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                response.close_clicked = ui.small_button("✕").on_hover_text("Close").clicked();
+            });
+        });
+
+        match self.result {
+            None => {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("Running query...");
+                });
+            }
+            Some(Err(error)) => {
+                ui.label(RichText::new(error).color(colors.diagnostic_error));
+            }
+            Some(Ok(result)) => {
+                let ms = result.elapsed.as_millis();
+                let summary = if result.columns.is_empty() {
+                    format!(
+                        "{} statement(s) run, {} row(s) affected in {ms} ms",
+                        result.statements, result.rows_affected
+                    )
+                } else if result.truncated_rows > 0 {
+                    format!(
+                        "First {} of {} rows in {ms} ms",
+                        result.rows.len(),
+                        result.rows.len() + result.truncated_rows
+                    )
+                } else {
+                    format!("{} row(s) in {ms} ms", result.rows.len())
+                };
+                ui.label(RichText::new(summary).color(colors.text_secondary));
+                if !result.columns.is_empty() {
+                    TableView::new(&result.columns, &result.rows).show(ui);
+                }
+            }
+        }
+
+        response
+    }
+}
@@ -0,0 +1,153 @@
+use crate::theme::{fonts, palette};
+use egui::{
+    Area, Color32, Context, Frame, Id, Key, Margin, Modifiers, RichText, ScrollArea, TextEdit,
+};
+
+/// Rows visible before the list scrolls
+const VISIBLE_ROWS: f32 = 12.0;
+
+/// Entry of a quick pick list
+pub struct QuickPickItem {
+    pub icon: &'static str,
+    pub label: String,
+    /// Shown dimmed after the label, e.g. where the item is
+    pub detail: String,
+}
+
+/// Query box with a list of matching items below it, at the top of the window.
+/// Up/Down move the selection, Enter accepts it and Escape dismisses the picker.
+pub struct QuickPick<'a> {
+    id: Id,
+    query: &'a mut String,
+    items: &'a [QuickPickItem],
+    selected: &'a mut usize,
+    hint_text: &'a str,
+    /// Shown instead of the list when there are no items
+    empty_text: &'a str,
+}
+
+/// Response from QuickPick widget
+#[derive(Default)]
+pub struct QuickPickResponse {
+    pub query_changed: bool,
+    /// Index of the item picked with Enter or a click
+    pub accepted: Option<usize>,
+    pub dismissed: bool,
+}
+
+impl<'a> QuickPick<'a> {
+    pub fn new(
+        id: impl Into<Id>,
+        query: &'a mut String,
+        items: &'a [QuickPickItem],
+        selected: &'a mut usize,
+    ) -> Self {
+        Self {
+            id: id.into(),
+            query,
+            items,
+            selected,
+            hint_text: "",
+            empty_text: "No results",
+        }
+    }
+
+    pub fn hint_text(mut self, hint_text: &'a str) -> Self {
+        self.hint_text = hint_text;
+        self
+    }
+
+    pub fn empty_text(mut self, empty_text: &'a str) -> Self {
+        self.empty_text = empty_text;
+        self
+    }
+
+    pub fn show(self, ctx: &Context) -> QuickPickResponse {
+        let colors = palette(&ctx.style().visuals);
+        let mut response = QuickPickResponse::default();
+
+        // Consumed so the query box doesn't also handle them
+        let (up, down, enter, escape) = ctx.input_mut(|i| {
+            (
+                i.consume_key(Modifiers::NONE, Key::ArrowUp),
+                i.consume_key(Modifiers::NONE, Key::ArrowDown),
+                i.consume_key(Modifiers::NONE, Key::Enter),
+                i.consume_key(Modifiers::NONE, Key::Escape),
+            )
+        });
+        let count = self.items.len();
+        if count > 0 {
+            *self.selected = (*self.selected).min(count - 1);
+            if up {
+                *self.selected = (*self.selected + count - 1) % count;
+            } else if down {
+                *self.selected = (*self.selected + 1) % count;
+            } else if enter {
+                response.accepted = Some(*self.selected);
+            }
+        }
+        response.dismissed = escape;
+
+        Area::new(self.id)
+            .anchor(egui::Align2::CENTER_TOP, egui::Vec2::new(0.0, 50.0))
+            .show(ctx, |ui| {
+                Frame::none()
+                    .fill(colors.find_panel_bg)
+                    .inner_margin(Margin::same(8.0))
+                    .rounding(4.0)
+                    .shadow(egui::epaint::Shadow {
+                        extrusion: 8.0,
+                        color: Color32::from_black_alpha(100),
+                    })
+                    .show(ui, |ui| {
+                        ui.set_width(520.0);
+                        let query = ui.add(
+                            TextEdit::singleline(self.query)
+                                .desired_width(f32::INFINITY)
+                                .hint_text(self.hint_text),
+                        );
+                        query.request_focus();
+                        if query.changed() {
+                            response.query_changed = true;
+                            *self.selected = 0;
+                        }
+
+                        if self.items.is_empty() {
+                            ui.label(RichText::new(self.empty_text).color(colors.text_muted));
+                            return;
+                        }
+                        let row_height = ui.spacing().interact_size.y;
+                        ScrollArea::vertical()
+                            .id_source(self.id.with("items"))
+                            .max_height(row_height * VISIBLE_ROWS)
+                            .show(ui, |ui| {
+                                for (i, item) in self.items.iter().enumerate() {
+                                    let is_selected = i == *self.selected;
+                                    let row = ui.horizontal(|ui| {
+                                        let label = ui.selectable_label(
+                                            is_selected,
+                                            RichText::new(format!("{} {}", item.icon, item.label))
+                                                .size(fonts::BODY)
+                                                .color(colors.text_primary),
+                                        );
+                                        ui.label(
+                                            RichText::new(&item.detail)
+                                                .size(fonts::SMALL)
+                                                .color(colors.text_muted),
+                                        );
+                                        label
+                                    });
+                                    if is_selected && (up || down) {
+                                        row.inner.scroll_to_me(None);
+                                    }
+                                    if row.inner.clicked() {
+                                        response.accepted = Some(i);
+                                    }
+                                }
+                            });
+                    });
+            });
+
+        response
+    }
+}
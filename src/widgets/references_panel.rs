@@ -0,0 +1,139 @@
+use crate::theme::{fonts, palette};
+use egui::{CollapsingHeader, FontId, RichText, ScrollArea, Ui};
+use std::path::PathBuf;
+
+/// One place a symbol is used
+#[derive(Clone)]
+pub struct Reference {
+    pub path: PathBuf,
+    /// Zero-based line and character column
+    pub line: usize,
+    pub column: usize,
+    /// Text of the line, trimmed
+    pub preview: String,
+}
+
+/// List of references grouped by file
+pub struct ReferencesPanel<'a> {
+    symbol: &'a str,
+    /// Sorted by path
+    references: &'a [Reference],
+    searching: bool,
+    /// In a window of its own rather than docked at the bottom
+    floating: bool,
+}
+
+/// Response from ReferencesPanel widget
+#[derive(Default)]
+pub struct ReferencesPanelResponse {
+    /// Index of the reference that was clicked
+    pub clicked: Option<usize>,
+    pub close_clicked: bool,
+    /// Move to a window or dock back
+    pub float_toggled: bool,
+}
+
+impl<'a> ReferencesPanel<'a> {
+    pub fn new(symbol: &'a str, references: &'a [Reference]) -> Self {
+        Self {
+            symbol,
+            references,
+            searching: false,
+            floating: false,
+        }
+    }
+
+    /// Show a spinner instead of the results
+    pub fn searching(mut self, searching: bool) -> Self {
+        self.searching = searching;
+        self
+    }
+
+    pub fn floating(mut self, floating: bool) -> Self {
+        self.floating = floating;
+        self
+    }
+
+    pub fn show(self, ui: &mut Ui) -> ReferencesPanelResponse {
+        let colors = palette(ui.visuals());
+        let mut response = ReferencesPanelResponse::default();
+        let panel = ui.max_rect();
+
+        let mut groups: Vec<(usize, usize)> = Vec::new();
+        for (i, reference) in self.references.iter().enumerate() {
+            match groups.last_mut() {
+                Some((start, end)) if self.references[*start].path == reference.path => {
+                    *end = i + 1
+                }
+                _ => groups.push((i, i + 1)),
+            }
+        }
+
+        ui.horizontal(|ui| {
+            let title = RichText::new("REFERENCES")
+                .size(fonts::EXPLORER_HEADER)
+                .color(colors.text_secondary)
+                .strong();
+            let dragged_out = super::floating_panel_title(ui, title, panel);
+            response.float_toggled = dragged_out && !self.floating;
+            if !self.searching {
+                ui.label(
+                    RichText::new(format!(
+                        "{}: {} results in {} files",
+                        self.symbol,
+                        self.references.len(),
+                        groups.len()
+                    ))
+                    .size(fonts::EXPLORER_HEADER)
+                    .color(colors.text_secondary),
+                );
+            }
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                response.close_clicked = ui.small_button("✕").on_hover_text("Close").clicked();
+                response.float_toggled |= super::floating_panel_button(ui, self.floating);
+            });
+        });
+
+        if self.searching {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label(format!("Finding references to {}...", self.symbol));
+            });
+            return response;
+        }
+
+        ScrollArea::vertical()
+            .id_source("references_panel")
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                for (start, end) in groups {
+                    let path = &self.references[start].path;
+                    let name = path.file_name().unwrap_or_default().to_string_lossy();
+                    CollapsingHeader::new(format!("{name} ({})", end - start))
+                        .id_source(path)
+                        .default_open(true)
+                        .show(ui, |ui| {
+                            for (i, reference) in self.references[start..end].iter().enumerate() {
+                                let text = RichText::new(format!(
+                                    "{}:{}  {}",
+                                    reference.line + 1,
+                                    reference.column + 1,
+                                    reference.preview
+                                ))
+                                .font(FontId::monospace(fonts::BODY))
+                                .color(colors.text_primary);
+                                if ui
+                                    .selectable_label(false, text)
+                                    .on_hover_text(path.display().to_string())
+                                    .clicked()
+                                {
+                                    response.clicked = Some(start + i);
+                                }
+                            }
+                        });
+                }
+            });
+
+        response
+    }
+}
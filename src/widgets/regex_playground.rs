@@ -0,0 +1,243 @@
+use crate::theme::{fonts, palette};
+use egui::text::{LayoutJob, TextFormat};
+use egui::{FontId, Grid, RichText, ScrollArea, TextEdit, Ui};
+use regex::{Regex, RegexBuilder};
+
+/// Most matches listed in the capture table; all of them are still highlighted
+const MAX_TABLE_MATCHES: usize = 200;
+
+/// Options for compiling the playground's pattern
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RegexFlags {
+    /// `i`: letters match both cases
+    pub case_insensitive: bool,
+    /// `m`: `^` and `$` match at line breaks
+    pub multi_line: bool,
+    /// `s`: `.` matches line breaks
+    pub dot_matches_new_line: bool,
+    /// `x`: whitespace and `#` comments in the pattern are ignored
+    pub ignore_whitespace: bool,
+}
+
+impl RegexFlags {
+    pub fn compile(&self, pattern: &str) -> Result<Regex, regex::Error> {
+        RegexBuilder::new(pattern)
+            .case_insensitive(self.case_insensitive)
+            .multi_line(self.multi_line)
+            .dot_matches_new_line(self.dot_matches_new_line)
+            .ignore_whitespace(self.ignore_whitespace)
+            .build()
+    }
+}
+
+/// Pattern, flags, and sample text, with matches highlighted in the sample and
+/// their capture groups listed below it
+pub struct RegexPlayground<'a> {
+    pattern: &'a mut String,
+    flags: &'a mut RegexFlags,
+    sample: &'a mut String,
+    regex: Option<&'a Regex>,
+    error: Option<&'a str>,
+}
+
+/// Response from RegexPlayground widget
+#[derive(Default)]
+pub struct RegexPlaygroundResponse {
+    /// The pattern or the flags changed and the regex has to be recompiled
+    pub pattern_changed: bool,
+    pub close_clicked: bool,
+}
+
+impl<'a> RegexPlayground<'a> {
+    pub fn new(pattern: &'a mut String, flags: &'a mut RegexFlags, sample: &'a mut String) -> Self {
+        Self {
+            pattern,
+            flags,
+            sample,
+            regex: None,
+            error: None,
+        }
+    }
+
+    /// The compiled pattern, if it is valid and not empty
+    pub fn regex(mut self, regex: Option<&'a Regex>) -> Self {
+        self.regex = regex;
+        self
+    }
+
+    /// Why the pattern couldn't be compiled
+    pub fn error(mut self, error: Option<&'a str>) -> Self {
+        self.error = error;
+        self
+    }
+
+    pub fn show(self, ui: &mut Ui) -> RegexPlaygroundResponse {
+        let colors = palette(ui.visuals());
+        let mut response = RegexPlaygroundResponse::default();
+        let font_id = FontId::monospace(fonts::BODY);
+        let regex = self.regex;
+        let flags = self.flags;
+
+        ui.horizontal(|ui| {
+            ui.label(
+                RichText::new("Regex Playground")
+                    .color(colors.text_primary)
+                    .strong(),
+            );
+            if let Some(regex) = regex {
+                let count = regex.find_iter(self.sample).count();
+                let details = match count {
+                    1 => "1 match".to_string(),
+                    count => format!("{count} matches"),
+                };
+                ui.label(RichText::new(details).color(colors.text_secondary));
+            }
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                response.close_clicked = ui.small_button("✕").on_hover_text("Close").clicked();
+            });
+        });
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            let mut pattern = TextEdit::singleline(self.pattern)
+                .font(font_id.clone())
+                .hint_text("Pattern")
+                .desired_width(ui.available_width() - 180.0);
+            if self.error.is_some() {
+                pattern = pattern.text_color(colors.diagnostic_error);
+            }
+            response.pattern_changed = ui.add(pattern).changed();
+            for (flag, label, hover) in [
+                (&mut flags.case_insensitive, "i", "Case insensitive"),
+                (&mut flags.multi_line, "m", "^ and $ match at line breaks"),
+                (
+                    &mut flags.dot_matches_new_line,
+                    "s",
+                    ". matches line breaks",
+                ),
+                (
+                    &mut flags.ignore_whitespace,
+                    "x",
+                    "Ignore whitespace and # comments",
+                ),
+            ] {
+                response.pattern_changed |= ui.checkbox(flag, label).on_hover_text(hover).changed();
+            }
+        });
+        if let Some(error) = self.error {
+            ui.label(
+                RichText::new(error)
+                    .font(font_id.clone())
+                    .color(colors.diagnostic_error),
+            );
+        }
+        ui.add_space(4.0);
+
+        // Sample text with the matches highlighted, alternating colors so
+        // adjacent matches stay apart
+        let mut layouter = |ui: &Ui, text: &str, wrap_width: f32| {
+            let plain = TextFormat::simple(font_id.clone(), colors.text_primary);
+            let mut job = LayoutJob::default();
+            let mut last = 0;
+            for (i, found) in regex
+                .into_iter()
+                .flat_map(|r| r.find_iter(text))
+                .enumerate()
+            {
+                job.append(&text[last..found.start()], 0.0, plain.clone());
+                let background = if i % 2 == 0 {
+                    colors.find_match_bg
+                } else {
+                    colors.find_match_current_bg
+                };
+                job.append(
+                    found.as_str(),
+                    0.0,
+                    TextFormat {
+                        background,
+                        ..plain.clone()
+                    },
+                );
+                last = found.end();
+            }
+            job.append(&text[last..], 0.0, plain);
+            job.wrap.max_width = wrap_width;
+            ui.fonts(|f| f.layout_job(job))
+        };
+        let sample_height = (ui.available_height() * 0.5).max(120.0);
+        ScrollArea::vertical()
+            .id_source("regex_playground_sample")
+            .max_height(sample_height)
+            .show(ui, |ui| {
+                ui.add(
+                    TextEdit::multiline(self.sample)
+                        .hint_text("Sample text")
+                        .desired_width(f32::INFINITY)
+                        .desired_rows(8)
+                        .layouter(&mut layouter),
+                );
+            });
+        ui.separator();
+
+        let Some(regex) = regex else {
+            return response;
+        };
+        let names: Vec<Option<&str>> = regex.capture_names().collect();
+        ScrollArea::both()
+            .id_source("regex_playground_groups")
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                Grid::new("regex_playground_groups")
+                    .striped(true)
+                    .num_columns(4)
+                    .show(ui, |ui| {
+                        for header in ["Match", "Group", "Range", "Text"] {
+                            ui.label(RichText::new(header).color(colors.text_secondary).strong());
+                        }
+                        ui.end_row();
+
+                        for (index, captures) in regex
+                            .captures_iter(self.sample)
+                            .take(MAX_TABLE_MATCHES)
+                            .enumerate()
+                        {
+                            for (group, name) in names.iter().enumerate() {
+                                ui.label(if group == 0 {
+                                    (index + 1).to_string()
+                                } else {
+                                    String::new()
+                                });
+                                ui.label(match name {
+                                    Some(name) => format!("{group} <{name}>"),
+                                    None => group.to_string(),
+                                });
+                                match captures.get(group) {
+                                    Some(found) => {
+                                        // Character offsets, like the rest of the editor
+                                        let start = self.sample[..found.start()].chars().count();
+                                        let end = start + found.as_str().chars().count();
+                                        ui.label(format!("{start}..{end}"));
+                                        ui.label(
+                                            RichText::new(found.as_str())
+                                                .font(font_id.clone())
+                                                .color(colors.text_primary),
+                                        );
+                                    }
+                                    None => {
+                                        ui.label("");
+                                        ui.label(
+                                            RichText::new("not matched")
+                                                .color(colors.text_muted)
+                                                .italics(),
+                                        );
+                                    }
+                                }
+                                ui.end_row();
+                            }
+                        }
+                    });
+            });
+
+        response
+    }
+}
@@ -0,0 +1,162 @@
+use crate::forge::{Item, ItemKind, Repository};
+use crate::theme::{fonts, palette};
+use egui::{CollapsingHeader, Frame, Margin, RichText, ScrollArea, Ui};
+
+/// Sidebar view of the workspace's repository: committing the staged changes,
+/// and the open pull requests and issues on GitHub or GitLab
+pub struct SourceControlPanel<'a> {
+    /// Whether pull requests and issues are listed at all
+    enabled: bool,
+    repository: Option<&'a Repository>,
+    /// None while they load
+    items: Option<&'a Result<Vec<Item>, String>>,
+}
+
+/// Response from SourceControlPanel widget
+#[derive(Default)]
+pub struct SourceControlPanelResponse {
+    pub commit_clicked: bool,
+    pub refresh_clicked: bool,
+    pub enable_clicked: bool,
+    /// Index of the item whose details to show
+    pub opened: Option<usize>,
+}
+
+impl<'a> SourceControlPanel<'a> {
+    pub fn new(enabled: bool, items: Option<&'a Result<Vec<Item>, String>>) -> Self {
+        Self {
+            enabled,
+            repository: None,
+            items,
+        }
+    }
+
+    pub fn repository(mut self, repository: Option<&'a Repository>) -> Self {
+        self.repository = repository;
+        self
+    }
+
+    pub fn show(self, ui: &mut Ui) -> SourceControlPanelResponse {
+        let colors = palette(ui.visuals());
+        let mut response = SourceControlPanelResponse::default();
+
+        Frame::none()
+            .inner_margin(Margin::symmetric(12.0, 8.0))
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(
+                        RichText::new("SOURCE CONTROL")
+                            .size(fonts::EXPLORER_HEADER)
+                            .color(colors.text_muted)
+                            .strong(),
+                    );
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if self.enabled {
+                            response.refresh_clicked = ui
+                                .add_enabled(self.items.is_some(), egui::Button::new("⟳").small())
+                                .on_hover_text("Reload pull requests and issues")
+                                .clicked();
+                        }
+                    });
+                });
+                ui.add_space(4.0);
+                response.commit_clicked = ui.button("Commit Staged Changes...").clicked();
+            });
+
+        if !self.enabled {
+            ui.horizontal_wrapped(|ui| {
+                ui.add_space(12.0);
+                ui.label(
+                    RichText::new(
+                        "Open pull requests and issues can be listed from GitHub or GitLab",
+                    )
+                    .color(colors.text_muted),
+                );
+            });
+            ui.horizontal(|ui| {
+                ui.add_space(12.0);
+                response.enable_clicked = ui.button("List Pull Requests and Issues").clicked();
+            });
+            return response;
+        }
+
+        let items = match self.items {
+            None => {
+                ui.horizontal(|ui| {
+                    ui.add_space(12.0);
+                    ui.spinner();
+                    ui.label(
+                        RichText::new("Loading pull requests and issues...")
+                            .color(colors.text_muted),
+                    );
+                });
+                return response;
+            }
+            Some(Err(e)) => {
+                ui.horizontal_wrapped(|ui| {
+                    ui.add_space(12.0);
+                    ui.label(RichText::new(e).color(colors.text_muted));
+                });
+                return response;
+            }
+            Some(Ok(items)) => items,
+        };
+
+        let Some(repository) = self.repository else {
+            return response;
+        };
+        let requests_label = repository.requests_label().to_uppercase();
+        ScrollArea::vertical()
+            .id_source("source_control_panel")
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                for (kind, label) in [
+                    (ItemKind::Request, requests_label.as_str()),
+                    (ItemKind::Issue, "ISSUES"),
+                ] {
+                    let count = items.iter().filter(|item| item.kind == kind).count();
+                    ui.horizontal(|ui| {
+                        ui.add_space(8.0);
+                        ui.vertical(|ui| {
+                            CollapsingHeader::new(
+                                RichText::new(format!("{label} ({count})"))
+                                    .size(fonts::SMALL)
+                                    .strong(),
+                            )
+                            .id_source(label)
+                            .default_open(true)
+                            .show(ui, |ui| {
+                                if count == 0 {
+                                    ui.label(RichText::new("None open").color(colors.text_muted));
+                                }
+                                for (index, item) in items.iter().enumerate() {
+                                    if item.kind != kind {
+                                        continue;
+                                    }
+                                    let title = format!(
+                                        "{} {}",
+                                        item.reference(repository.forge),
+                                        item.title
+                                    );
+                                    if ui.selectable_label(false, title).clicked() {
+                                        response.opened = Some(index);
+                                    }
+                                    let detail = match &item.branch {
+                                        Some(branch) => format!("{} · {branch}", item.author),
+                                        None => item.author.clone(),
+                                    };
+                                    ui.label(
+                                        RichText::new(detail)
+                                            .size(fonts::SMALL)
+                                            .color(colors.text_muted),
+                                    );
+                                }
+                            });
+                        });
+                    });
+                }
+            });
+
+        response
+    }
+}
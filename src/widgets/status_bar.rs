@@ -1,11 +1,14 @@
+use crate::contributions;
 use crate::state::CursorPosition;
-use crate::theme::{colors, fonts, layout};
+use crate::theme::{fonts, layout, palette};
 use egui::{Frame, Margin, RichText, Ui};
 
 /// Information displayed in the status bar
 #[derive(Clone)]
 pub struct StatusBarInfo {
     pub cursor: CursorPosition,
+    /// UTF-8 byte offset of the cursor
+    pub byte_offset: usize,
     pub language: String,
     pub encoding: String,
     pub line_ending: String,
@@ -17,6 +20,7 @@ impl Default for StatusBarInfo {
     fn default() -> Self {
         Self {
             cursor: CursorPosition::default(),
+            byte_offset: 0,
             language: "Plain Text".to_string(),
             encoding: "UTF-8".to_string(),
             line_ending: if cfg!(windows) { "CRLF" } else { "LF" }.to_string(),
@@ -30,6 +34,9 @@ impl Default for StatusBarInfo {
 pub struct StatusBar {
     info: StatusBarInfo,
     file_name: Option<String>,
+    server_url: Option<String>,
+    recording_macro: bool,
+    pending_chord: Option<String>,
 }
 
 impl StatusBar {
@@ -37,6 +44,9 @@ impl StatusBar {
         Self {
             info,
             file_name: None,
+            server_url: None,
+            recording_macro: false,
+            pending_chord: None,
         }
     }
 
@@ -45,9 +55,29 @@ impl StatusBar {
         self
     }
 
-    pub fn show(self, ui: &mut Ui) {
+    /// URL of the running static file server
+    pub fn server_url(mut self, url: Option<String>) -> Self {
+        self.server_url = url;
+        self
+    }
+
+    /// Show that a keyboard macro is being recorded
+    pub fn recording_macro(mut self, recording: bool) -> Self {
+        self.recording_macro = recording;
+        self
+    }
+
+    /// First key of a chord that was pressed, while waiting for the second
+    pub fn pending_chord(mut self, first: Option<String>) -> Self {
+        self.pending_chord = first;
+        self
+    }
+
+    pub fn show(self, ui: &mut Ui) -> StatusBarResponse {
+        let colors = palette(ui.visuals());
+        let mut response = StatusBarResponse::default();
         Frame::none()
-            .fill(colors::STATUS_BAR_BG)
+            .fill(colors.status_bar_bg)
             .inner_margin(Margin::symmetric(layout::STATUS_BAR_ITEM_PADDING, 4.0))
             .show(ui, |ui| {
                 ui.horizontal(|ui| {
@@ -56,7 +86,7 @@ impl StatusBar {
                     let label_style = |text: &str| {
                         RichText::new(text)
                             .size(fonts::STATUS_BAR)
-                            .color(colors::TEXT_PRIMARY)
+                            .color(colors.status_bar_text)
                     };
 
                     // === Left side items ===
@@ -67,13 +97,39 @@ impl StatusBar {
                         ui.separator();
                     }
 
+                    // Static file server
+                    if let Some(url) = &self.server_url {
+                        let address = url.trim_start_matches("http://").trim_end_matches('/');
+                        response.server_clicked = ui
+                            .selectable_label(false, label_style(&format!("📡 {address}")))
+                            .on_hover_text(format!("Serving at {url}\nClick to stop"))
+                            .clicked();
+                        ui.separator();
+                    }
+
+                    // Keyboard macro being recorded
+                    if self.recording_macro {
+                        response.macro_clicked = ui
+                            .selectable_label(false, label_style("⏺ Recording Macro"))
+                            .on_hover_text("Click to stop recording (Ctrl+Alt+R)")
+                            .clicked();
+                        ui.separator();
+                    }
+
+                    if let Some(first) = &self.pending_chord {
+                        ui.label(label_style(&format!(
+                            "⌨ {first} was pressed, waiting for the second key of the chord"
+                        )));
+                        ui.separator();
+                    }
+
                     // === Right side items ===
                     ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                         // App name
                         ui.label(
                             RichText::new("Rust Code Editor")
                                 .size(fonts::STATUS_BAR)
-                                .color(colors::TEXT_PRIMARY)
+                                .color(colors.status_bar_text)
                                 .strong(),
                         );
 
@@ -81,12 +137,13 @@ impl StatusBar {
 
                         // Cursor position: "Ln X, Col Y"
                         let cursor_text = self.info.cursor.display();
-                        if ui
+                        response.cursor_clicked = ui
                             .selectable_label(false, label_style(&cursor_text))
-                            .clicked()
-                        {
-                            // Could open "Go to Line" dialog
-                        }
+                            .on_hover_text(format!(
+                                "Offset {} (byte {})\nClick to go to line (Ctrl+G)",
+                                self.info.cursor.offset, self.info.byte_offset
+                            ))
+                            .clicked();
 
                         ui.separator();
 
@@ -101,12 +158,10 @@ impl StatusBar {
                         ui.separator();
 
                         // Encoding
-                        if ui
+                        response.encoding_clicked = ui
                             .selectable_label(false, label_style(&self.info.encoding))
-                            .clicked()
-                        {
-                            // Could show encoding selector
-                        }
+                            .on_hover_text("Reopen or save with another encoding")
+                            .clicked();
 
                         ui.separator();
 
@@ -128,11 +183,27 @@ impl StatusBar {
                     });
                 });
             });
+        response
     }
 }
 
+#[derive(Default)]
+pub struct StatusBarResponse {
+    /// The cursor position was clicked (opens Go to Line)
+    pub cursor_clicked: bool,
+    /// The static file server item was clicked (stops the server)
+    pub server_clicked: bool,
+    /// The macro recording item was clicked (stops recording)
+    pub macro_clicked: bool,
+    /// The encoding was clicked (asks to reopen or save with another one)
+    pub encoding_clicked: bool,
+}
+
 /// Helper to detect language from file extension
 pub fn detect_language(ext: &str) -> &'static str {
+    if let Some(language) = contributions::language(ext) {
+        return language;
+    }
     match ext.to_lowercase().as_str() {
         "rs" => "Rust",
         "js" => "JavaScript",
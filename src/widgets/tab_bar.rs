@@ -1,4 +1,4 @@
-use crate::theme::{colors, fonts, layout};
+use crate::theme::{fonts, layout, palette};
 use egui::{Frame, Margin, Pos2, RichText, Sense, Stroke, Ui, Vec2};
 
 /// Represents a single tab
@@ -7,6 +7,14 @@ pub struct Tab {
     pub name: String,
     pub icon: String,
     pub is_modified: bool,
+    /// Following the end of the file as it grows
+    pub is_following: bool,
+    /// The file was deleted or moved on disk
+    pub is_missing: bool,
+    /// Kept in the editor without a file on disk
+    pub is_untitled: bool,
+    /// Shown in the other editor group
+    pub is_split: bool,
 }
 
 impl Tab {
@@ -15,6 +23,10 @@ impl Tab {
             name: name.into(),
             icon: icon.into(),
             is_modified: false,
+            is_following: false,
+            is_missing: false,
+            is_untitled: false,
+            is_split: false,
         }
     }
 
@@ -22,12 +34,38 @@ impl Tab {
         self.is_modified = is_modified;
         self
     }
+
+    pub fn following(mut self, is_following: bool) -> Self {
+        self.is_following = is_following;
+        self
+    }
+
+    pub fn missing(mut self, is_missing: bool) -> Self {
+        self.is_missing = is_missing;
+        self
+    }
+
+    pub fn untitled(mut self, is_untitled: bool) -> Self {
+        self.is_untitled = is_untitled;
+        self
+    }
+
+    pub fn split(mut self, is_split: bool) -> Self {
+        self.is_split = is_split;
+        self
+    }
 }
 
 /// Response from TabBar widget
 pub struct TabBarResponse {
     pub activated: Option<usize>,
     pub closed: Option<usize>,
+    /// Tab being dragged this frame
+    pub dragged: Option<usize>,
+    /// Tab whose drag ended this frame
+    pub released: Option<usize>,
+    /// Screen rect of the bar
+    pub rect: egui::Rect,
 }
 
 /// Enhanced tab bar with modified indicators
@@ -42,13 +80,17 @@ impl TabBar {
     }
 
     pub fn show(self, ui: &mut Ui) -> TabBarResponse {
+        let colors = palette(ui.visuals());
         let mut response = TabBarResponse {
             activated: None,
             closed: None,
+            dragged: None,
+            released: None,
+            rect: egui::Rect::NOTHING,
         };
 
-        Frame::none()
-            .fill(colors::PANEL_BG)
+        response.rect = Frame::none()
+            .fill(colors.panel_bg)
             .inner_margin(Margin::symmetric(0.0, 0.0))
             .show(ui, |ui| {
                 ui.horizontal(|ui| {
@@ -64,9 +106,25 @@ impl TabBar {
                         if tab_response.closed {
                             response.closed = Some(i);
                         }
+                        if tab_response.dragged {
+                            response.dragged = Some(i);
+                        }
+                        if tab_response.released {
+                            response.released = Some(i);
+                        }
                     }
                 });
+            })
+            .response
+            .rect;
+
+        // The dragged tab follows the pointer
+        if let Some(tab) = response.dragged.and_then(|i| self.tabs.get(i)) {
+            ui.ctx().set_cursor_icon(egui::CursorIcon::Grabbing);
+            egui::show_tooltip_at_pointer(ui.ctx(), egui::Id::new("dragged_tab"), |ui| {
+                ui.label(format!("{} {}", tab.icon, tab.name));
             });
+        }
 
         response
     }
@@ -78,13 +136,16 @@ impl TabBar {
         is_active: bool,
         _index: usize,
     ) -> SingleTabResponse {
+        let colors = palette(ui.visuals());
         let mut activated = false;
         let mut closed = false;
+        let mut dragged = false;
+        let mut released = false;
 
         let bg_color = if is_active {
-            colors::TAB_ACTIVE_BG
+            colors.tab_active_bg
         } else {
-            colors::TAB_INACTIVE_BG
+            colors.tab_inactive_bg
         };
 
         Frame::none()
@@ -94,15 +155,21 @@ impl TabBar {
                 layout::TAB_PADDING_V,
             ))
             .show(ui, |ui| {
-                // Draw top border for active tab
-                if is_active {
+                // Draw top border for active tab, and a fainter one for the
+                // tab of the other editor group
+                if is_active || tab.is_split {
                     let rect = ui.min_rect();
+                    let color = if is_active {
+                        colors.accent
+                    } else {
+                        colors.text_secondary
+                    };
                     ui.painter().line_segment(
                         [
                             Pos2::new(rect.left(), rect.top() - layout::TAB_PADDING_V),
                             Pos2::new(rect.right(), rect.top() - layout::TAB_PADDING_V),
                         ],
-                        Stroke::new(2.0, colors::ACCENT),
+                        Stroke::new(2.0, color),
                     );
                 }
 
@@ -118,26 +185,44 @@ impl TabBar {
                         ui.painter().circle_filled(
                             dot_rect.center(),
                             layout::TAB_MODIFIED_DOT_SIZE / 2.0 - 1.0,
-                            colors::TAB_MODIFIED_DOT,
+                            colors.tab_modified_dot,
                         );
                     }
 
                     // Icon and file name
-                    let text_color = if is_active {
-                        colors::TEXT_PRIMARY
+                    let text_color = if tab.is_missing {
+                        colors.diagnostic_warning
+                    } else if is_active {
+                        colors.text_primary
                     } else {
-                        colors::TEXT_SECONDARY
+                        colors.text_secondary
                     };
 
-                    let label_text = format!("{} {}", tab.icon, tab.name);
-                    let label = RichText::new(&label_text)
+                    let mut label_text = format!("{} {}", tab.icon, tab.name);
+                    if tab.is_following {
+                        label_text.push_str(" ⏬");
+                    }
+                    if tab.is_missing {
+                        label_text.push_str(" ⚠");
+                    }
+                    if tab.is_untitled {
+                        label_text.push_str(" (untitled)");
+                    }
+                    let mut label = RichText::new(&label_text)
                         .size(fonts::BODY)
                         .color(text_color);
+                    if tab.is_missing {
+                        label = label.strikethrough();
+                    }
 
-                    let label_response = ui.selectable_label(false, label);
+                    let label_response = ui
+                        .selectable_label(false, label)
+                        .interact(Sense::click_and_drag());
                     if label_response.clicked() {
                         activated = true;
                     }
+                    dragged = label_response.dragged();
+                    released = label_response.drag_released();
 
                     // Close button
                     let (close_rect, close_response) =
@@ -146,14 +231,14 @@ impl TabBar {
                     // Draw close button
                     let close_hovered = close_response.hovered();
                     let close_color = if close_hovered {
-                        colors::TEXT_PRIMARY
+                        colors.text_primary
                     } else {
-                        colors::TEXT_SECONDARY
+                        colors.text_secondary
                     };
 
                     if close_hovered {
                         ui.painter()
-                            .rect_filled(close_rect, 2.0, colors::WIDGET_HOVERED);
+                            .rect_filled(close_rect, 2.0, colors.widget_hovered);
                     }
 
                     ui.painter().text(
@@ -170,11 +255,18 @@ impl TabBar {
                 });
             });
 
-        SingleTabResponse { activated, closed }
+        SingleTabResponse {
+            activated,
+            closed,
+            dragged,
+            released,
+        }
     }
 }
 
 struct SingleTabResponse {
     activated: bool,
     closed: bool,
+    dragged: bool,
+    released: bool,
 }
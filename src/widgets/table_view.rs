@@ -0,0 +1,95 @@
+use crate::theme::{fonts, palette};
+use egui::{Align2, FontId, Pos2, Rect, ScrollArea, Sense, Ui, Vec2};
+
+/// Widest a column gets, in characters; longer values are cut off
+const MAX_COLUMN_CHARS: usize = 48;
+const CELL_PADDING: f32 = 8.0;
+
+/// Read-only grid of text cells with a header row. Only the rows in view are
+/// painted, so large results stay cheap.
+pub struct TableView<'a> {
+    columns: &'a [String],
+    rows: &'a [Vec<String>],
+}
+
+impl<'a> TableView<'a> {
+    pub fn new(columns: &'a [String], rows: &'a [Vec<String>]) -> Self {
+        Self { columns, rows }
+    }
+
+    pub fn show(self, ui: &mut Ui) {
+        let colors = palette(ui.visuals());
+        let font_id = FontId::monospace(fonts::BODY);
+        let char_width = ui.fonts(|f| f.glyph_width(&font_id, ' '));
+        let row_height = ui.fonts(|f| f.row_height(&font_id)) + 4.0;
+
+        let widths: Vec<f32> = self
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let chars = self
+                    .rows
+                    .iter()
+                    .filter_map(|row| row.get(i))
+                    .map(|cell| cell.chars().count())
+                    .chain(std::iter::once(name.chars().count()))
+                    .max()
+                    .unwrap_or(0)
+                    .min(MAX_COLUMN_CHARS);
+                chars as f32 * char_width + CELL_PADDING * 2.0
+            })
+            .collect();
+        let total_width: f32 = widths.iter().sum();
+
+        let paint_row = |ui: &mut Ui, cells: &[String], header: bool, striped: bool| {
+            let (rect, _) =
+                ui.allocate_exact_size(Vec2::new(total_width, row_height), Sense::hover());
+            let painter = ui.painter_at(rect);
+            if header {
+                painter.rect_filled(rect, 0.0, colors.gutter_bg);
+            } else if striped {
+                painter.rect_filled(rect, 0.0, colors.current_line_bg);
+            }
+            let mut x = rect.left();
+            for (cell, width) in cells.iter().zip(&widths) {
+                let cell_rect =
+                    Rect::from_min_size(Pos2::new(x, rect.top()), Vec2::new(*width, row_height));
+                let text: String = cell.chars().take(MAX_COLUMN_CHARS).collect();
+                painter
+                    .with_clip_rect(cell_rect.shrink2(Vec2::new(CELL_PADDING / 2.0, 0.0)))
+                    .text(
+                        Pos2::new(x + CELL_PADDING, rect.center().y),
+                        Align2::LEFT_CENTER,
+                        text,
+                        font_id.clone(),
+                        if header {
+                            colors.text_primary
+                        } else {
+                            colors.text_secondary
+                        },
+                    );
+                x += width;
+                painter.vline(x, rect.y_range(), (1.0, colors.gutter_border));
+            }
+        };
+
+        ScrollArea::horizontal()
+            .id_source("table_view")
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                ui.spacing_mut().item_spacing = Vec2::ZERO;
+                ui.vertical(|ui| {
+                    paint_row(ui, self.columns, true, false);
+                    ScrollArea::vertical()
+                        .id_source("table_view_rows")
+                        .auto_shrink([true, false])
+                        .show_rows(ui, row_height, self.rows.len(), |ui, range| {
+                            for i in range {
+                                paint_row(ui, &self.rows[i], false, i % 2 == 1);
+                            }
+                        });
+                });
+            });
+    }
+}